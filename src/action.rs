@@ -0,0 +1,72 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The action bus. Everything that changes the state arrives as an
+//! [`Action`] on one queue, which the UI loop drains in order: keys from the
+//! terminal, the commands they turn into, and what background tasks report
+//! back. Anything that can send on the [`Bus`] can drive dbvi the same way
+//! the keyboard does.
+
+use crossterm::event::{Event, KeyEvent};
+use tokio::sync::mpsc::error::SendError;
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel};
+
+use crate::state::{Command, Message};
+
+#[derive(Debug)]
+pub enum Action {
+    /// A key, mouse or resize event from the terminal, which input handling
+    /// turns into edits and a command.
+    Event(Event),
+    /// Keys typed by a mapping or a script, which mappings don't apply to.
+    Keys(Vec<KeyEvent>),
+    Command(Command),
+    /// A background task reporting back.
+    Message(Message),
+}
+
+impl From<Event> for Action {
+    fn from(event: Event) -> Self {
+        Self::Event(event)
+    }
+}
+
+impl From<Command> for Action {
+    fn from(command: Command) -> Self {
+        Self::Command(command)
+    }
+}
+
+impl From<Message> for Action {
+    fn from(message: Message) -> Self {
+        Self::Message(message)
+    }
+}
+
+/// The sending end of the action queue, cheap to clone into tasks.
+#[derive(Debug, Clone)]
+pub struct Bus(UnboundedSender<Action>);
+
+impl Bus {
+    /// Queues `action`. Fails only once the UI loop is gone.
+    pub fn send(&self, action: impl Into<Action>) -> Result<(), SendError<Action>> {
+        self.0.send(action.into())
+    }
+}
+
+/// A bus and the queue it sends to.
+pub fn channel() -> (Bus, UnboundedReceiver<Action>) {
+    let (sender, receiver) = unbounded_channel();
+    (Bus(sender), receiver)
+}
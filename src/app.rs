@@ -0,0 +1,5569 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The terminal, the event loop, and running [`Command`]s against the
+//! database.
+
+use std::time::{Duration, Instant};
+use std::{
+    env, fs,
+    io::{self, Write},
+    pin::Pin,
+};
+
+use crossterm::{
+    cursor::{SetCursorStyle, Show},
+    event::{
+        self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+        Event as CEvent, EventStream, KeyCode, KeyModifiers,
+    },
+    execute,
+    terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
+};
+use futures_util::StreamExt;
+use ratatui::{
+    Terminal,
+    backend::CrosstermBackend,
+    layout::{Constraint, Flex, Layout},
+    style::{Color, Modifier, Style},
+    text::Line,
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+};
+use sqlx::{Executor, PgPool, postgres::PgConnectOptions};
+use tokio::sync::mpsc::UnboundedReceiver;
+
+use crate::action::{self, Action, Bus};
+use crate::browse::{Browser, Filter, PageTo, Total};
+use crate::clipboard::Clipboard;
+use crate::compare::Compare;
+use crate::dashboard::{Dashboard, ServerStats};
+use crate::db::asof::{self, AsOf};
+use crate::db::capabilities::{Capabilities, Need};
+use crate::db::monitor::Report;
+use crate::db::server::{Flavor, Server};
+use crate::db::session::{STATEMENT_SAVEPOINT, Session, Transaction};
+use crate::editor::{Buffer, Cursor, Register};
+use crate::edits::{Edits, Target};
+use crate::erd::Erd;
+use crate::input::{flush_keys, handle_input, press, selected_results};
+use crate::jobs::{Done, Kind, Output, Reporter};
+use crate::keymap;
+use crate::library::{self, Library};
+use crate::options::{self, Request};
+use crate::popup::{Popup, Toast};
+use crate::quickfix::{self, Entry};
+use crate::redact::Redactions;
+use crate::results::ResultSet;
+use crate::rowdiff::RowDiff;
+use crate::snapshot::{self, Snapshot};
+use crate::state::{Command, Message, Mode, Pane, State};
+use crate::statusline::Segment;
+use crate::tutor::Tutor;
+use crate::ui::{self, draw_ui};
+use crate::wizard::{self, Step, Wizard};
+use crate::workspace::{self, Workspace};
+use crate::{
+    audit, bench, chart, completion, config, db, dialect, dump, export, generate, highlight, hover,
+    import, join, lint, logging, lsp, migrate, params, pivot, plan, record, results, rowdiff,
+    shell, statements, stats, substitute, suggest, swap, template, transfer, vars,
+};
+
+/// What stands for `NULL` when changing a cell, as in `COPY`.
+const NULL_INPUT: &str = "\\N";
+
+/// How long past `statement_timeout` to wait for the server to cancel a
+/// query itself before giving up on it client side.
+const CLIENT_TIMEOUT_GRACE: Duration = Duration::from_secs(2);
+
+/// How often the connection health is checked.
+const PING_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How often to check whether the schema changed underneath the cache.
+const SCHEMA_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How long toasts stay up.
+const TOAST_DURATION: Duration = Duration::from_secs(15);
+
+/// How often the screen is redrawn while something on it moves by itself.
+const TICK: Duration = Duration::from_millis(200);
+
+/// Checks the connection every [`PING_INTERVAL`] in the background.
+fn spawn_health_check(session: Session, messages: Bus) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(PING_INTERVAL);
+        loop {
+            interval.tick().await;
+            // `:db` closed the pool for a new one, with its own check.
+            if session.pool.is_closed() {
+                return;
+            }
+            // A busy connection is healthy enough; skip rather than queue
+            // behind a long running query.
+            let Some(result) = session.ping().await else {
+                continue;
+            };
+            // Only a lost connection is unhealthy, not a SELECT refused in
+            // an aborted transaction.
+            if let Err(err) = &result
+                && !db::session::is_connection_error(err)
+            {
+                continue;
+            }
+            if messages
+                .send(Message::Ping(result.map_err(|err| err.to_string())))
+                .is_err()
+            {
+                return;
+            }
+        }
+    });
+}
+
+async fn run_app(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    mut state: State,
+    mut actions: UnboundedReceiver<Action>,
+) -> io::Result<()> {
+    // Drawn only when something changed: an action came in, or the loop
+    // itself changed the state, or time moved something on screen.
+    let mut dirty = true;
+    let mut drawn_at = Instant::now();
+    let mut drawn_minute = minute();
+    // The cursor shape set last, `None` for the terminal's own.
+    let mut drawn_cursor = None;
+    let mut events = EventStream::new();
+    while state.is_running {
+        while state.is_running
+            && let Ok(action) = actions.try_recv()
+        {
+            dispatch(action, &mut state, terminal).await?;
+            dirty = true;
+        }
+        if let Some(mut tutor) = state.tutor.take() {
+            tutor.check(&mut state);
+            state.tutor = Some(tutor);
+        }
+        if let Some(lsp) = &mut state.lsp {
+            lsp.sync(&state.buffers);
+        }
+        if let Some(swap) = &mut state.swap
+            && let Err(err) = swap.autosave(&state.buffers)
+        {
+            state.status = format!("Failed to write swap file: {err}");
+            dirty = true;
+        }
+        if state.resolver.timed_out(state.timeout_len) {
+            for command in flush_keys(&mut state) {
+                let _ = state.messages.send(command);
+            }
+            dirty = true;
+        }
+        if state.toast.as_ref().is_some_and(Toast::expired) {
+            state.toast = None;
+            dirty = true;
+        }
+        if state.schema_checked_at.elapsed() >= SCHEMA_CHECK_INTERVAL && state.connected {
+            check_schema(&mut state);
+            dirty = true;
+        }
+        if let Some(interval) = state.report.as_ref().and_then(Report::interval)
+            && state.report_at.elapsed() >= interval
+            && state.connected
+        {
+            refresh_report(&mut state);
+            dirty = true;
+        }
+        // Running jobs count up their time in the jobs list.
+        if state.jobs.shown && state.jobs.running() > 0 && drawn_at.elapsed() >= TICK {
+            dirty = true;
+        }
+        // The dashboard's uptime counts up, and the server's side is asked
+        // for again every so often.
+        if state.dashboard.is_some() && drawn_at.elapsed() >= TICK {
+            dirty = true;
+        }
+        if state.dashboard.as_ref().is_some_and(Dashboard::due) && state.connected {
+            refresh_server_stats(&mut state);
+        }
+        if state.statusline.shows(Segment::Clock) && minute() != drawn_minute {
+            dirty = true;
+        }
+        if dirty {
+            terminal.draw(|f| draw_ui(f, &mut state))?;
+            let cursor = state.cursor_shape.then_some(state.mode);
+            if cursor != drawn_cursor {
+                let style = cursor.map_or(SetCursorStyle::DefaultUserShape, ui::cursor_style);
+                execute!(terminal.backend_mut(), style)?;
+                drawn_cursor = cursor;
+            }
+            dirty = false;
+            drawn_at = Instant::now();
+            drawn_minute = minute();
+        }
+
+        // Nothing happens until input, an action or something due: the
+        // loop sleeps rather than polls.
+        let wake = wake_at(&state, drawn_at);
+        tokio::select! {
+            event = events.next() => match event {
+                Some(event) => {
+                    let event = event?;
+                    if let Some(recorder) = &mut state.recorder
+                        && let Err(err) = recorder.record(&event)
+                    {
+                        state.status = format!("Stopped recording: {err}");
+                        state.recorder = None;
+                    }
+                    let _ = state.messages.send(event);
+                }
+                None => break,
+            },
+            action = actions.recv() => match action {
+                Some(action) => {
+                    dispatch(action, &mut state, terminal).await?;
+                    dirty = true;
+                }
+                None => break,
+            },
+            () = tokio::time::sleep_until(wake.unwrap_or_else(Instant::now).into()),
+                if wake.is_some() => {}
+        }
+    }
+    if let Some(swap) = &mut state.swap {
+        swap.remove_all();
+    }
+    Ok(())
+}
+
+/// The minute of the clock in the status line.
+fn minute() -> i64 {
+    chrono::Local::now().timestamp() / 60
+}
+
+/// When the loop next has something to do by itself: redraw what moves,
+/// expire, refresh or save. `None` while only input or an action can change
+/// anything.
+fn wake_at(state: &State, drawn_at: Instant) -> Option<Instant> {
+    let tick = drawn_at + TICK;
+    let next_minute = || {
+        let into = chrono::Local::now().timestamp_millis().rem_euclid(60_000);
+        Instant::now() + Duration::from_millis((60_000 - into) as u64)
+    };
+    let report = state
+        .report
+        .as_ref()
+        .and_then(Report::interval)
+        .filter(|_| state.connected)
+        .map(|interval| state.report_at + interval);
+    [
+        (state.jobs.shown && state.jobs.running() > 0).then_some(tick),
+        state.dashboard.is_some().then_some(tick),
+        state.statusline.shows(Segment::Clock).then(next_minute),
+        state.toast.as_ref().map(Toast::until),
+        state
+            .connected
+            .then(|| state.schema_checked_at + SCHEMA_CHECK_INTERVAL),
+        report,
+        state.resolver.deadline(state.timeout_len),
+        state
+            .swap
+            .as_ref()
+            .and_then(|swap| swap.due(&state.buffers)),
+    ]
+    .into_iter()
+    .flatten()
+    .min()
+}
+
+/// Applies one action to the state. Commands that keys turn into go back on
+/// the bus rather than running right away, so they take their turn behind
+/// whatever was already queued.
+async fn dispatch(
+    action: Action,
+    state: &mut State,
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+) -> io::Result<()> {
+    let input = |state: &mut State, event| match handle_input(state, event) {
+        Command::None => {}
+        command => {
+            let _ = state.messages.send(command);
+        }
+    };
+    match action {
+        Action::Event(CEvent::Key(key)) => {
+            for command in press(state, key) {
+                let _ = state.messages.send(command);
+            }
+        }
+        Action::Event(event) => input(state, event),
+        Action::Keys(keys) => {
+            for key in keys {
+                input(state, CEvent::Key(key));
+            }
+        }
+        Action::Command(command) => handle_command(command, state, terminal).await?,
+        Action::Message(message) => handle_message(state, message),
+    }
+    Ok(())
+}
+
+fn handle_message(state: &mut State, message: Message) {
+    match message {
+        Message::Reconnecting {
+            attempt,
+            delay,
+            error,
+        } => {
+            state.status = format!(
+                "Reconnecting… attempt {attempt} failed ({error}), retrying in {:.1}s",
+                delay.as_secs_f32()
+            );
+        }
+        Message::Reconnected => {
+            state.connected = true;
+            state.activity.connected_at = Instant::now();
+            state.status = "Reconnected".into();
+            refresh_schema(state);
+            check_search_path(state);
+            check_capabilities(state);
+            run_queued(state);
+        }
+        Message::SourceProgress { done, total } => {
+            state.status = format!("Sourcing… {done}/{total} statements");
+        }
+        Message::SourceDone {
+            path,
+            report,
+            failures,
+            status,
+            connection_lost,
+        } => {
+            state.quickfix.set(format!("source {path}"), failures);
+            let mut buffer = Buffer::from_text(format!("[source] {path}"), &report);
+            buffer.read_only = true;
+            state.open_buffer(buffer);
+            if connection_lost {
+                start_reconnect(state);
+            } else {
+                state.status = status;
+                // Scripts are often migrations.
+                refresh_schema(state);
+                check_search_path(state);
+            }
+        }
+        Message::QueuedDone {
+            id,
+            sql,
+            outcome,
+            connection_lost,
+        } => {
+            let ok = outcome.is_ok();
+            state.transaction = state.transaction.after(&sql, ok);
+            match outcome {
+                Ok((results, status)) => {
+                    state.session.record(&sql);
+                    if let Some(names) = db::schema::ddl_targets(&sql) {
+                        state.schema.invalidate(&names);
+                        state.schema_stale = true;
+                    }
+                    if state.schema_stale && state.transaction == Transaction::Idle {
+                        refresh_schema(state);
+                    }
+                    if !results.is_empty() {
+                        // Behind the queue while it is being looked at.
+                        let shown = state.queue.shown;
+                        state.last_query = Some(sql);
+                        state.show_batch(results);
+                        state.queue.shown = shown;
+                    }
+                    state.status = format!("Queued #{id}: {status}");
+                }
+                Err(_) if connection_lost => start_reconnect(state),
+                Err(err) => state.status = format!("Queued #{id} failed: {err}"),
+            }
+            if ok && !state.queue.is_empty() {
+                state.status += &format!(", {} still queued", state.queue.len());
+            }
+        }
+        Message::PipeDone { command, outcome } => match outcome {
+            Ok((output, status)) => {
+                if !output.is_empty() {
+                    let mut buffer = Buffer::from_text(format!("[pipe] {command}"), &output);
+                    buffer.read_only = true;
+                    state.open_buffer(buffer);
+                }
+                state.status = if status.success() {
+                    format!("{} lines from {command}", output.lines().count())
+                } else {
+                    format!("{command} exited with {status}")
+                };
+            }
+            Err(err) => state.status = err,
+        },
+        Message::ImportProgress { done, total } => {
+            const WIDTH: u64 = 20;
+            let filled = (done * WIDTH).checked_div(total).unwrap_or(WIDTH);
+            state.status = format!(
+                "Importing… [{}{}] {}%",
+                "█".repeat(filled as usize),
+                "░".repeat((WIDTH - filled) as usize),
+                (done * 100).checked_div(total).unwrap_or(100)
+            );
+        }
+        Message::CopyProgress { bytes } => {
+            state.status = format!("Copying… {} written", format_bytes(bytes));
+        }
+        Message::Status(status) => state.status = status,
+        Message::Popup(popup) => state.popup = Some(popup),
+        Message::ServerStats(outcome) => {
+            if let Some(dashboard) = &mut state.dashboard {
+                dashboard.server = Some(outcome);
+            }
+        }
+        Message::ReportDone { report, outcome } => match outcome {
+            // Refreshes may come back after the grid has moved on.
+            Ok(mut results) if state.report.as_ref() == Some(&report) => {
+                if let Report::Grep(_) = report {
+                    state.quickfix.set(report.title(), grep_entries(&results));
+                }
+                if let Some((col, descending)) = state.grid.sort
+                    && col < results.columns.len()
+                {
+                    results.sort(col, descending);
+                }
+                state.grid = state.grid.refreshed(&results);
+                state.results = Some(results);
+            }
+            Ok(_) => {}
+            Err(err) => state.status = format!("Failed to run {}: {err}", report.title()),
+        },
+        Message::Log { buffer, line } => {
+            // Gone if it was closed while the job ran.
+            if let Some(buffer) = state.buffers.iter_mut().find(|b| b.id == buffer) {
+                let following = buffer.cursor.row + 1 == buffer.lines.len();
+                buffer.lines.push(line);
+                if following {
+                    buffer.cursor.row = buffer.lines.len() - 1;
+                }
+            }
+        }
+        Message::SchemaChecked(signatures) => {
+            // A refresh may have finished since the check started, or be on
+            // its way.
+            let changes = state.schema.changes(&signatures);
+            if changes.is_empty() || state.jobs.is_running(Kind::Schema) {
+                state.schema_notice = None;
+            } else if state.schema_notice != Some(changes) {
+                tracing::info!(%changes, "schema changed");
+                state.schema_notice = Some(changes);
+                state.toast = Some(Toast::new(
+                    format!("The schema changed ({changes}), :schema refresh to update it"),
+                    TOAST_DURATION,
+                ));
+            }
+        }
+        Message::SearchPath(path) => {
+            // Other functions may be visible now.
+            if path != state.search_path {
+                state.signatures.clear();
+            }
+            state.search_path = path;
+        }
+        Message::Capabilities(capabilities) => {
+            if let Some(notice) = capabilities.notice() {
+                state.status = notice;
+            }
+            state.capabilities = capabilities;
+        }
+        Message::Signatures(name, functions) => state.signatures.found(name, functions),
+        Message::LspDiagnostics { uri, diagnostics } => {
+            if let Some(lsp) = &mut state.lsp {
+                lsp.published(uri, diagnostics);
+            }
+        }
+        Message::LspExited => {
+            if let Some(lsp) = state.lsp.take() {
+                state.status = format!("The language server {} exited", lsp.name);
+            }
+        }
+        Message::SchemaRefreshed(schema) => {
+            state.schema = schema;
+            if state.schema_notice.take().is_some() {
+                state.toast = None;
+            }
+            if state.schema_stale {
+                refresh_schema(state);
+            }
+        }
+        Message::JobProgress { id, progress } => state.jobs.progress(id, progress),
+        Message::JobDone {
+            id,
+            elapsed,
+            outcome,
+        } => {
+            state.jobs.finish(id, elapsed, outcome);
+            if state
+                .queue
+                .running
+                .as_ref()
+                .is_some_and(|(job, _)| *job == id)
+            {
+                state.queue.running = None;
+            }
+            // Whatever it was, the connection may be free for the queue now.
+            run_queued(state);
+        }
+        Message::Ping(Ok(latency)) => state.latency = Some(latency),
+        Message::Ping(Err(err)) => {
+            tracing::warn!(error = %err, "health check failed");
+            state.latency = None;
+            if state.connected {
+                start_reconnect(state);
+            }
+        }
+    }
+}
+
+/// Whether `what` may go ahead: anywhere but `prod`, and there once it is
+/// answered with `y`.
+fn confirm_write(
+    state: &State,
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    what: &str,
+) -> io::Result<bool> {
+    confirm_write_to(state.environment, terminal, what)
+}
+
+/// [`confirm_write`] for a server of `environment` other than the session's.
+fn confirm_write_to(
+    environment: Option<config::Environment>,
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    what: &str,
+) -> io::Result<bool> {
+    if !environment.is_some_and(|env| env.is_prod()) {
+        return Ok(true);
+    }
+    Ok(prompt(
+        terminal,
+        "Write to prod?",
+        Line::from(format!("{what}. y to go ahead, anything else cancels")),
+        false,
+        String::new(),
+    )?
+    .is_some_and(|answer| answer.trim().eq_ignore_ascii_case("y")))
+}
+
+/// Whether the session has no connection to run anything on, saying why in
+/// the status line if so.
+fn offline(state: &mut State) -> bool {
+    if state.connected {
+        return false;
+    }
+    state.status = if state.session.pool.is_closed() {
+        "Disconnected by the kill switch, :reconnect to connect again".into()
+    } else {
+        "Not connected yet, still reconnecting…".into()
+    };
+    true
+}
+
+/// Marks the connection as lost and retries in the background.
+fn start_reconnect(state: &mut State) {
+    state.connected = false;
+    // The server rolls back whatever the lost connection had open.
+    state.transaction = Transaction::Idle;
+    state.status = "Connection lost, reconnecting…".into();
+    let session = state.session.clone();
+    let messages = state.messages.clone();
+    tokio::spawn(async move {
+        session
+            .reconnect(|attempt, delay, err| {
+                tracing::warn!(attempt, ?delay, error = %err, "reconnect failed");
+                let _ = messages.send(Message::Reconnecting {
+                    attempt,
+                    delay,
+                    error: err.to_string(),
+                });
+            })
+            .await;
+        // The kill switch closed it in the meantime.
+        if session.pool.is_closed() {
+            return;
+        }
+        tracing::info!("reconnected");
+        let _ = messages.send(Message::Reconnected);
+    });
+}
+
+/// Walks through importing the CSV file at `path`: asks for the column
+/// types if the table doesn't exist yet, shows the `CREATE TABLE` and the
+/// `COPY` in the SQL preview, then loads the file in the background.
+async fn import_csv(
+    state: &mut State,
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    path: String,
+    table: Option<String>,
+) -> io::Result<()> {
+    let file = config::expand_home(&path);
+    let csv = match import::inspect(&file) {
+        Ok(csv) => csv,
+        Err(err) => {
+            state.status = format!("Failed to read \"{path}\": {err}");
+            return Ok(());
+        }
+    };
+    let table = table.unwrap_or_else(|| {
+        let stem = file.file_stem().unwrap_or_default().to_string_lossy();
+        import::column_name(&stem)
+    });
+    let exists = sqlx::query_scalar::<_, bool>("SELECT to_regclass($1) IS NOT NULL")
+        .bind(&table)
+        .fetch_one(&state.session.pool)
+        .await;
+    match exists {
+        Ok(true) => {}
+        Ok(false) => {
+            let total = csv.columns.len();
+            let mut types = Vec::with_capacity(total);
+            for (index, (column, guess)) in csv.columns.iter().zip(&csv.types).enumerate() {
+                let title = format!("Type of {column} ({}/{total})", index + 1);
+                let message = Line::styled(
+                    format!("Guessed from the first rows of {path}"),
+                    Style::default().fg(Color::DarkGray),
+                );
+                match prompt(terminal, &title, message, false, guess.to_string())? {
+                    Some(ty) if !ty.trim().is_empty() => types.push(ty.trim().to_string()),
+                    _ => {
+                        state.status = "Import cancelled".into();
+                        return Ok(());
+                    }
+                }
+            }
+            let sql = import::create_table(&table, &csv.columns, &types);
+            let what = format!("Create table {table}?");
+            let Some(sql) = preview_sql(terminal, state.dialect, &what, sql).await? else {
+                state.status = "Import cancelled".into();
+                return Ok(());
+            };
+            let started = Instant::now();
+            let outcome = sqlx::raw_sql(&sql).execute(&state.session.pool).await;
+            let audited = match &outcome {
+                Ok(done) => Ok(done.rows_affected()),
+                Err(err) => Err(err.to_string()),
+            };
+            state.audit(&sql, &[], started.elapsed(), audited);
+            if let Err(err) = outcome {
+                state.status = format!("Failed to create {table}: {err}");
+                return Ok(());
+            }
+            refresh_schema(state);
+        }
+        Err(err) => {
+            state.status = format!("Failed to look up {table}: {err}");
+            return Ok(());
+        }
+    }
+
+    let statement = import::copy_statement(&table, &csv.columns);
+    let what = format!("Import \"{path}\" into {table}?");
+    let Some(statement) = preview_sql(terminal, state.dialect, &what, statement).await? else {
+        state.status = "Import cancelled".into();
+        return Ok(());
+    };
+    let session = state.session.clone();
+    let messages = state.messages.clone();
+    let audit = state.audit.clone();
+    state.status = format!("Importing \"{path}\" into {table}…");
+    let what = format!("\"{path}\" into {table}");
+    state
+        .jobs
+        .spawn(&state.messages, Kind::Import, what, |reporter| async move {
+            let started = Instant::now();
+            let load = import::load(&session.pool, &statement, &file, |done, total| {
+                let _ = messages.send(Message::ImportProgress { done, total });
+            });
+            let outcome = watched(&session, &reporter, load).await;
+            let elapsed = started.elapsed();
+            if let Some(audit) = &audit {
+                let statement = format!("{statement} -- :import {}", file.display());
+                let outcome = match &outcome {
+                    Ok(rows) => Ok(*rows),
+                    Err(err) => Err(err.to_string()),
+                };
+                if let Err(err) = audit.record(&statement, &[], elapsed, outcome) {
+                    tracing::warn!(error = %err, "failed to write audit log");
+                }
+            }
+            let outcome = match outcome {
+                Ok(rows) => {
+                    tracing::info!(?elapsed, rows, table, "imported csv");
+                    Ok(format!(
+                        "Imported {rows} rows into {table} in {elapsed:.1?}"
+                    ))
+                }
+                Err(err) => {
+                    tracing::warn!(error = %err, table, "csv import failed");
+                    Err(format!("Failed to import into {table}: {err}"))
+                }
+            };
+            let _ = messages.send(Message::Status(outcome.clone().unwrap_or_else(|err| err)));
+            outcome.map(Done::from)
+        });
+    Ok(())
+}
+
+/// Runs the report in the grid again in the background.
+pub(crate) fn refresh_report(state: &mut State) {
+    let Some(report) = state.report.clone() else {
+        return;
+    };
+    state.report_at = Instant::now();
+    let pool = state.session.pool.clone();
+    let server = state.session.server;
+    let messages = state.messages.clone();
+    tokio::spawn(async move {
+        let outcome = report
+            .run(&pool, server)
+            .await
+            .map_err(|err| err.to_string());
+        let _ = messages.send(Message::ReportDone { report, outcome });
+    });
+}
+
+/// Asks the server for the statistics of `:stats`, in the background.
+fn refresh_server_stats(state: &mut State) {
+    let Some(dashboard) = &mut state.dashboard else {
+        return;
+    };
+    dashboard.fetched_at = Some(Instant::now());
+    let pool = state.session.pool.clone();
+    let messages = state.messages.clone();
+    tokio::spawn(async move {
+        let outcome = ServerStats::fetch(&pool)
+            .await
+            .map_err(|err| err.to_string());
+        let _ = messages.send(Message::ServerStats(outcome));
+    });
+}
+
+/// The quickfix entries of a `:grep-schema` report, one for each match.
+fn grep_entries(results: &ResultSet) -> Vec<Entry> {
+    let [kind, object, column, found, text] =
+        ["kind", "object", "column", "in", "text"].map(|name| results.column_index(name));
+    results
+        .rows
+        .iter()
+        .filter_map(|row| {
+            let cell = |col: Option<usize>| col.and_then(|col| row.get(col));
+            let (kind, object) = (cell(kind)?, cell(object)?);
+            let what = match cell(column) {
+                Some(column) => format!("column {column}"),
+                None => kind.to_string(),
+            };
+            Some(Entry {
+                target: quickfix::Target::Object {
+                    name: object.to_string(),
+                    definition: matches!(kind, "view" | "matview" | "function"),
+                },
+                text: format!(
+                    "{what} {}: {}",
+                    cell(found).unwrap_or_default(),
+                    cell(text).unwrap_or_default()
+                ),
+            })
+        })
+        .collect()
+}
+
+/// Loads the cached schema of the session's database and brings it up to
+/// date.
+fn load_schema(state: &mut State) {
+    state.schema_path = db::schema::cache_path(&db::profile_key(&state.session.options));
+    if let Some(path) = &state.schema_path {
+        match db::schema::Schema::load(path) {
+            Ok(schema) => state.schema = schema,
+            Err(err) => tracing::warn!(error = %err, "failed to load cached schema"),
+        }
+    }
+    refresh_schema(state);
+}
+
+/// What the Lua `on_connect` hooks are told about `session`.
+#[cfg(feature = "lua")]
+fn connect_info(session: &Session) -> Vec<(&'static str, String)> {
+    let options = &session.options;
+    vec![
+        (
+            "database",
+            options.get_database().unwrap_or_default().into(),
+        ),
+        ("user", options.get_username().into()),
+        ("host", options.get_host().into()),
+        ("server", session.server.name().into()),
+    ]
+}
+
+/// Brings the cached schema up to date in the background. With a refresh
+/// running already, another one follows it.
+fn refresh_schema(state: &mut State) {
+    let Some(path) = state.schema_path.clone() else {
+        return;
+    };
+    if state.jobs.is_running(Kind::Schema) {
+        state.schema_stale = true;
+        return;
+    }
+    state.schema_stale = false;
+    let schema = state.schema.clone();
+    let options = state.session.options.clone();
+    let server = state.session.server;
+    let messages = state.messages.clone();
+    let what = db::profile_key(&options);
+    state
+        .jobs
+        .spawn(&state.messages, Kind::Schema, what, |_| async move {
+            let (schema, changes) = schema.refresh(&options, server).await.map_err(|err| {
+                tracing::warn!(error = %err, "schema refresh failed");
+                format!("Failed to refresh the schema: {err}")
+            })?;
+            tracing::debug!(?changes, "refreshed schema");
+            if let Err(err) = schema.save(&path) {
+                tracing::warn!(error = %err, path = %path.display(), "failed to cache schema");
+            }
+            let summary = format!("{} relations, {changes}", schema.relations.len());
+            let _ = messages.send(Message::SchemaRefreshed(schema));
+            Ok(summary.into())
+        });
+}
+
+/// Asks the server in the background what the `search_path` is now.
+fn check_search_path(state: &State) {
+    let session = state.session.clone();
+    let messages = state.messages.clone();
+    tokio::spawn(async move {
+        match session.search_path().await {
+            Ok(path) => {
+                let _ = messages.send(Message::SearchPath(path));
+            }
+            Err(err) => tracing::debug!(error = %err, "failed to read the search_path"),
+        }
+    });
+}
+
+/// Asks the server in the background what the session's role may do, for
+/// the features it can't use to say so.
+fn check_capabilities(state: &State) {
+    let pool = state.session.pool.clone();
+    let messages = state.messages.clone();
+    tokio::spawn(async move {
+        let capabilities = Capabilities::probe(&pool).await;
+        let _ = messages.send(Message::Capabilities(capabilities));
+    });
+}
+
+/// Asks the server in the background whether the schema changed since it
+/// was cached, DDL from elsewhere like a migration.
+fn check_schema(state: &mut State) {
+    state.schema_checked_at = Instant::now();
+    if state.schema_path.is_none()
+        || state.schema.relations.is_empty()
+        || state.jobs.is_running(Kind::Schema)
+    {
+        return;
+    }
+    let options = state.session.options.clone();
+    let server = state.session.server;
+    let messages = state.messages.clone();
+    tokio::spawn(async move {
+        match db::schema::signatures(&options, server).await {
+            Ok(signatures) => {
+                let _ = messages.send(Message::SchemaChecked(signatures));
+            }
+            Err(err) => tracing::debug!(error = %err, "schema check failed"),
+        }
+    });
+}
+
+/// Runs `sql`, a vacuum or a refresh of `name`, in the background,
+/// reporting the progress in the status line.
+fn spawn_watched(state: &mut State, kind: Kind, name: String, sql: String) {
+    let (doing, did, verb) = match kind {
+        Kind::Refresh => ("Refreshing", "Refreshed", "refresh"),
+        _ => ("Vacuuming", "Vacuumed and analyzed", "vacuum"),
+    };
+    let session = state.session.clone();
+    let messages = state.messages.clone();
+    let audit = state.audit.clone();
+    state.status = format!("{doing} {name}…");
+    state
+        .jobs
+        .spawn(&state.messages, kind, name.clone(), |reporter| async move {
+            let started = Instant::now();
+            let report = |progress: db::monitor::Progress| {
+                let status = match progress.percent() {
+                    Some(percent) => format!("{}, {percent}%", progress.describe()),
+                    None => progress.describe(),
+                };
+                let _ = messages.send(Message::Status(format!("{doing} {name}: {status}")));
+                reporter.progress(progress);
+            };
+            let outcome = match kind {
+                Kind::Refresh => db::monitor::refresh(&session, sql.clone(), report).await,
+                _ => db::monitor::vacuum(&session, sql.clone(), report).await,
+            };
+            let elapsed = started.elapsed();
+            if let Some(audit) = &audit {
+                let audited = match &outcome {
+                    Ok(()) => Ok(0),
+                    Err(err) => Err(err.to_string()),
+                };
+                if let Err(err) = audit.record(&sql, &[], elapsed, audited) {
+                    tracing::warn!(error = %err, "failed to write audit log");
+                }
+            }
+            let outcome = match outcome {
+                Ok(()) => Ok(format!("{did} {name} in {elapsed:.1?}")),
+                Err(err) => Err(format!("Failed to {verb} {name}: {err}")),
+            };
+            let _ = messages.send(Message::Status(outcome.clone().unwrap_or_else(|err| err)));
+            outcome.map(Done::from)
+        });
+}
+
+/// Runs `pg_dump` or `pg_restore` with `args` in the background, connected
+/// as the session is, with what it prints going to a log buffer.
+fn spawn_tool(state: &mut State, kind: Kind, args: Vec<String>, what: String) {
+    let program = match kind {
+        Kind::Restore => "pg_restore",
+        _ => "pg_dump",
+    };
+    let name = format!("[{}] {what}", kind.name());
+    let mut buffer = Buffer::from_text(name.clone(), &format!("$ {program} {}", args.join(" ")));
+    buffer.read_only = true;
+    let id = buffer.id;
+    state.open_buffer(buffer);
+    let env = dump::env(&state.session.options, state.read_only);
+    let messages = state.messages.clone();
+    state.status = format!("Running {program}…");
+    state
+        .jobs
+        .spawn(&state.messages, kind, what, |_| async move {
+            let started = Instant::now();
+            let outcome = dump::run(program, &args, env, |line| {
+                let _ = messages.send(Message::Log { buffer: id, line });
+            })
+            .await;
+            let outcome = match outcome {
+                Ok(status) if status.success() => {
+                    Ok(format!("{program} is done in {:.1?}", started.elapsed()))
+                }
+                Ok(status) => Err(format!(
+                    "{program} exited with {status}, the log is in {name}"
+                )),
+                Err(err) => Err(err),
+            };
+            let _ = messages.send(Message::Status(outcome.clone().unwrap_or_else(|err| err)));
+            outcome.map(|summary| Done {
+                summary,
+                output: Some(Output::Buffer(name)),
+            })
+        });
+}
+
+/// Feeds the results as CSV to `command` in the background; its output
+/// comes back as [`Message::PipeDone`].
+fn spawn_pipe(state: &mut State, command: String) {
+    let Some(results) = &state.results else {
+        state.status = "No results".into();
+        return;
+    };
+    let results = state.redactions.apply(results);
+    let mut input = Vec::new();
+    if let Err(err) = export::write_csv(&results, &mut input) {
+        state.status = format!("Failed to write CSV: {err}");
+        return;
+    }
+    let messages = state.messages.clone();
+    state.status = format!("Piping {} rows to {command}…", results.rows.len());
+    state.jobs.spawn(
+        &state.messages,
+        Kind::Pipe,
+        command.clone(),
+        |_| async move {
+            let outcome = shell::pipe(&command, input).await;
+            let done = match &outcome {
+                Ok((output, status)) => Ok(Done {
+                    summary: format!("{} lines, {status}", output.lines().count()),
+                    output: (!output.is_empty())
+                        .then(|| Output::Buffer(format!("[pipe] {command}"))),
+                }),
+                Err(err) => Err(err.clone()),
+            };
+            let _ = messages.send(Message::PipeDone { command, outcome });
+            done
+        },
+    );
+}
+
+/// Sizes in the units people read them in.
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    match unit {
+        0 => format!("{bytes} B"),
+        _ => format!("{size:.1} {}", UNITS[unit]),
+    }
+}
+
+/// Copies the output of `query` to the CSV file at `path` in the
+/// background.
+fn spawn_copy(state: &mut State, query: String, path: String) {
+    if params::find(&query).is_some() {
+        state.status = "COPY can't take bind parameters".into();
+        return;
+    }
+    let file = config::expand_home(&path);
+    let session = state.session.clone();
+    let messages = state.messages.clone();
+    let audit = state.audit.clone();
+    let redactions = state.redactions.clone();
+    state.status = format!("Copying to \"{path}\"…");
+    let what = format!("\"{path}\"");
+    state
+        .jobs
+        .spawn(&state.messages, Kind::Copy, what, |reporter| async move {
+            let started = Instant::now();
+            let copy = async {
+                // The rows never reach the grid, so the server masks them.
+                let query = redactions.masked_query(&session.pool, &query).await?;
+                export::copy_csv(&session.pool, &query, &file, |bytes| {
+                    let _ = messages.send(Message::CopyProgress { bytes });
+                })
+                .await
+            };
+            let outcome = watched(&session, &reporter, copy).await;
+            let elapsed = started.elapsed();
+            if let Some(audit) = &audit {
+                let statement = format!("COPY ({query}) TO '{}' -- :copy", file.display());
+                let outcome = match &outcome {
+                    Ok(_) => Ok(0),
+                    Err(err) => Err(err.to_string()),
+                };
+                if let Err(err) = audit.record(&statement, &[], elapsed, outcome) {
+                    tracing::warn!(error = %err, "failed to write audit log");
+                }
+            }
+            let outcome = match outcome {
+                Ok(bytes) => {
+                    tracing::info!(?elapsed, bytes, "copied query output");
+                    Ok(format!(
+                        "Wrote {} to \"{path}\" in {elapsed:.1?}",
+                        format_bytes(bytes)
+                    ))
+                }
+                Err(err) => {
+                    tracing::warn!(error = %err, "copy failed");
+                    Err(format!("Failed to copy to \"{path}\": {err}"))
+                }
+            };
+            let _ = messages.send(Message::Status(outcome.clone().unwrap_or_else(|err| err)));
+            outcome.map(Done::from)
+        });
+}
+
+/// `:copy-to`: works out the columns and counts the rows, connects to
+/// `target`, offers to create `table` there if it is missing and, once
+/// the `COPY` with the count is confirmed, copies in the background.
+async fn copy_to(
+    state: &mut State,
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    target: String,
+    table: String,
+    source: transfer::Source,
+) -> io::Result<()> {
+    let (query, results) = match source {
+        transfer::Source::Results => match &state.results {
+            // What leaves for another server is what the grid shows.
+            Some(results) => (None, state.redactions.apply(results).into_owned()),
+            None => {
+                state.status = "No results".into();
+                return Ok(());
+            }
+        },
+        transfer::Source::Query => {
+            if offline(state) {
+                return Ok(());
+            }
+            let query = state.buffer().text();
+            if params::find(&query).is_some() {
+                state.status = "COPY can't take bind parameters".into();
+                return Ok(());
+            }
+            let pool = state.session.pool.clone();
+            let query = match state.redactions.masked_query(&pool, &query).await {
+                Ok(query) => query,
+                Err(err) => {
+                    state.status = format!("Failed to look at the query: {err}");
+                    return Ok(());
+                }
+            };
+            let trimmed = query.trim().trim_end_matches(';');
+            let counted = async {
+                let columns = results::describe(&pool, trimmed).await?;
+                let count: i64 =
+                    sqlx::query_scalar(&format!("SELECT count(*) FROM ({trimmed}\n) q"))
+                        .fetch_one(&pool)
+                        .await?;
+                Ok::<_, sqlx::Error>((columns, count))
+            };
+            state.status = "Counting the rows…".into();
+            terminal.draw(|f| draw_ui(f, state))?;
+            match counted.await {
+                Ok((columns, count)) => {
+                    state.status.clear();
+                    (Some((query, count as u64)), columns)
+                }
+                Err(err) => {
+                    state.status = format!("Failed to count the rows: {err}");
+                    return Ok(());
+                }
+            }
+        }
+    };
+    let rows = match &query {
+        Some((_, count)) => *count,
+        None => results.rows.len() as u64,
+    };
+    // A profile's say over writes to it holds whichever session it's
+    // written to from.
+    let profile = match config::Config::load() {
+        Ok(mut config) => config.profiles.remove(&target),
+        Err(err) => {
+            state.status = format!("Failed to load the config: {err}");
+            return Ok(());
+        }
+    };
+    if let Some(refusal) = profile
+        .as_ref()
+        .and_then(|profile| transfer::refusal(&target, profile))
+    {
+        state.status = refusal;
+        return Ok(());
+    }
+    let s = if rows == 1 { "" } else { "s" };
+    let environment = profile.as_ref().and_then(config::Profile::environment);
+    let what = format!("Copy {rows} row{s} into {table} on {target}");
+    if !confirm_write_to(environment, terminal, &what)? {
+        state.status = "Copy cancelled".into();
+        return Ok(());
+    }
+    state.status = format!("Connecting to {target}…");
+    terminal.draw(|f| draw_ui(f, state))?;
+    let (name, session, tunnel) = match connect_to(terminal, &target).await {
+        Ok(connected) => connected,
+        Err(err) => {
+            state.status = format!("Failed to connect to {target}: {err}");
+            return Ok(());
+        }
+    };
+    state.status.clear();
+    let exists = sqlx::query_scalar::<_, bool>("SELECT to_regclass($1) IS NOT NULL")
+        .bind(&table)
+        .fetch_one(&session.pool)
+        .await;
+    let created = match exists {
+        Ok(true) => Ok(true),
+        Ok(false) => {
+            let sql = transfer::create_table(&table, &results.columns);
+            let what = format!("Create table {table} on {name}?");
+            match preview_sql(terminal, state.dialect, &what, sql).await? {
+                Some(sql) => sqlx::raw_sql(&sql)
+                    .execute(&session.pool)
+                    .await
+                    .map(|_| true)
+                    .map_err(|err| format!("Failed to create {table} on {name}: {err}")),
+                None => Ok(false),
+            }
+        }
+        Err(err) => Err(format!("Failed to look up {table} on {name}: {err}")),
+    };
+    let statement = transfer::copy_in_statement(&table, &results.columns);
+    let what = format!("Copy {rows} row{s} into {table} on {name}?");
+    let confirmed = match created {
+        Ok(true) => preview_sql(terminal, state.dialect, &what, statement).await?,
+        Ok(false) => None,
+        Err(err) => {
+            session.pool.close().await;
+            state.status = err;
+            return Ok(());
+        }
+    };
+    let Some(statement) = confirmed else {
+        session.pool.close().await;
+        state.status = "Copy cancelled".into();
+        return Ok(());
+    };
+
+    let source_session = state.session.clone();
+    let messages = state.messages.clone();
+    let audit = state.audit.clone();
+    state.status = format!("Copying {rows} row{s} into {table} on {name}…");
+    let what = format!("{table} on {name}");
+    state
+        .jobs
+        .spawn(&state.messages, Kind::CopyTo, what, |reporter| async move {
+            // Kept open until the copy is done.
+            let _tunnel = tunnel;
+            let started = Instant::now();
+            let progress = |done: u64| {
+                let percent = (done * 100).checked_div(rows).unwrap_or(100);
+                let status =
+                    format!("Copying into {table} on {name}: {done} of {rows} rows, {percent}%");
+                let _ = messages.send(Message::Status(status));
+                reporter.progress(db::monitor::Progress {
+                    command: "COPY".into(),
+                    phase: String::new(),
+                    done: done as i64,
+                    total: Some(rows as i64),
+                    unit: "rows".into(),
+                });
+            };
+            let outcome = match &query {
+                Some((query, _)) => {
+                    let stream = transfer::stream_query(
+                        &source_session.pool,
+                        query,
+                        &session.pool,
+                        &statement,
+                        progress,
+                    );
+                    watched(&source_session, &reporter, stream).await
+                }
+                None => transfer::send_results(&session.pool, &statement, &results, progress).await,
+            };
+            session.pool.close().await;
+            let elapsed = started.elapsed();
+            if let Some(audit) = &audit {
+                let statement = format!("{statement} -- :copy-to {name}");
+                let outcome = match &outcome {
+                    Ok(rows) => Ok(*rows),
+                    Err(err) => Err(err.to_string()),
+                };
+                if let Err(err) = audit.record(&statement, &[], elapsed, outcome) {
+                    tracing::warn!(error = %err, "failed to write audit log");
+                }
+            }
+            let outcome = match outcome {
+                Ok(rows) => {
+                    let s = if rows == 1 { "" } else { "s" };
+                    Ok(format!(
+                        "Copied {rows} row{s} into {table} on {name} in {elapsed:.1?}"
+                    ))
+                }
+                Err(err) => Err(format!("Failed to copy into {table} on {name}: {err}")),
+            };
+            let _ = messages.send(Message::Status(outcome.clone().unwrap_or_else(|err| err)));
+            outcome.map(Done::from)
+        });
+    Ok(())
+}
+
+/// Runs `:s` on the current buffer, asking about each match first if the
+/// command has the `c` flag.
+fn substitute_lines(
+    state: &mut State,
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    substitute: &substitute::Substitute,
+) -> io::Result<()> {
+    let buffer = state.buffer();
+    let lines = match substitute.lines(buffer.cursor.row, buffer.lines.len()) {
+        Ok(lines) => lines,
+        Err(err) => {
+            state.status = err;
+            return Ok(());
+        }
+    };
+    state.search = Some(substitute.pattern.clone());
+    let (mut count, mut changed_lines, mut last_changed) = (0, 0, None);
+    // Every remaining match, without asking, after `a`.
+    let mut all = !substitute.confirm;
+    'lines: for row in lines {
+        if all {
+            let (line, replaced) = substitute.apply(&state.buffer().lines[row]);
+            if replaced > 0 {
+                state.buffer_mut().lines[row] = line;
+                (count, changed_lines, last_changed) =
+                    (count + replaced, changed_lines + 1, Some(row));
+            }
+            continue;
+        }
+        let mut offset = 0;
+        let mut changed = false;
+        while offset <= state.buffer().lines[row].len() {
+            let line = state.buffer().lines[row].clone();
+            let Some(captures) = substitute.pattern.captures_at(&line, offset) else {
+                break;
+            };
+            let matched = captures.get(0).map_or(0..0, |m| m.range());
+            let buffer = state.buffer_mut();
+            buffer.cursor.row = row;
+            buffer.cursor.col = line[..matched.start].chars().count();
+            state.status = "Replace this match? (y/n/a/q/l)".into();
+            terminal.draw(|f| draw_ui(f, state))?;
+            let CEvent::Key(key) = event::read()? else {
+                continue;
+            };
+            let next = |end: usize| {
+                // Step past empty matches so they don't match again.
+                if matched.is_empty() {
+                    end + line[end..].chars().next().map_or(1, char::len_utf8)
+                } else {
+                    end
+                }
+            };
+            match key.code {
+                KeyCode::Char(c @ ('y' | 'l' | 'a')) => {
+                    let (replaced, end) = substitute.replace_one(&line, &captures);
+                    state.buffer_mut().lines[row] = replaced;
+                    (count, changed, last_changed) = (count + 1, true, Some(row));
+                    if c == 'l' {
+                        changed_lines += usize::from(changed);
+                        break 'lines;
+                    }
+                    if c == 'a' {
+                        all = true;
+                        let rest = state.buffer().lines[row][end..].to_string();
+                        let (rest, replaced) = if substitute.global {
+                            substitute.apply(&rest)
+                        } else {
+                            (rest, 0)
+                        };
+                        state.buffer_mut().lines[row].replace_range(end.., &rest);
+                        count += replaced;
+                        break;
+                    }
+                    offset = next(end);
+                }
+                KeyCode::Char('n') => offset = next(matched.end),
+                KeyCode::Char('q') | KeyCode::Esc => {
+                    changed_lines += usize::from(changed);
+                    break 'lines;
+                }
+                _ => continue,
+            }
+            if !substitute.global {
+                break;
+            }
+        }
+        changed_lines += usize::from(changed);
+    }
+
+    let buffer = state.buffer_mut();
+    if let Some(row) = last_changed {
+        buffer.cursor.row = row;
+        buffer.first_non_blank();
+        buffer.modified = true;
+    }
+    state.status = match count {
+        0 if substitute.confirm => "Nothing replaced".into(),
+        0 => format!("Pattern not found: {}", substitute.pattern.as_str()),
+        1 => "1 substitution on 1 line".into(),
+        _ => format!("{count} substitutions on {changed_lines} lines"),
+    };
+    Ok(())
+}
+
+/// Benchmarks `sql` in the background, showing the timings in a popup.
+fn spawn_bench(
+    state: &mut State,
+    sql: String,
+    binds: Vec<Option<String>>,
+    runs: usize,
+    warm_up: bool,
+) {
+    let options = state.session.options.clone();
+    let messages = state.messages.clone();
+    let audit = state.audit.clone();
+    state.status = format!("Benchmarking… 0/{runs}");
+    let what = format!("{runs} runs");
+    state
+        .jobs
+        .spawn(&state.messages, Kind::Bench, what, |_| async move {
+            let started = Instant::now();
+            let outcome = bench::run(&options, &sql, &binds, runs, warm_up, |done| {
+                let _ = messages.send(Message::Status(format!("Benchmarking… {done}/{runs}")));
+            })
+            .await;
+            if let Some(audit) = &audit {
+                let statement =
+                    format!("{} -- :bench {runs}", sql.trim_end().trim_end_matches(';'));
+                let outcome = match &outcome {
+                    Ok(_) => Ok(0),
+                    Err(err) => Err(err.to_string()),
+                };
+                if let Err(err) = audit.record(&statement, &binds, started.elapsed(), outcome) {
+                    tracing::warn!(error = %err, "failed to write audit log");
+                }
+            }
+            match outcome {
+                Ok(timings) => {
+                    let summary = format!("Benchmarked {runs} runs in {:.1?}", started.elapsed());
+                    let popup = Popup::Text {
+                        title: "Benchmark".into(),
+                        lines: bench::summary(&timings),
+                    };
+                    let _ = messages.send(Message::Status(summary.clone()));
+                    let _ = messages.send(Message::Popup(popup.clone()));
+                    Ok(Done {
+                        summary,
+                        output: Some(Output::Popup(Box::new(popup))),
+                    })
+                }
+                Err(err) => {
+                    let err = format!("Benchmark failed: {err}");
+                    let _ = messages.send(Message::Status(err.clone()));
+                    Err(err)
+                }
+            }
+        });
+}
+
+/// Runs `task`, which keeps the session's connection busy, passing on what
+/// the progress views say about it to the job list.
+async fn watched<T>(session: &Session, reporter: &Reporter, task: impl Future<Output = T>) -> T {
+    db::monitor::watch(
+        &session.options,
+        session.server,
+        session.pid(),
+        task,
+        |progress| reporter.progress(progress),
+    )
+    .await
+}
+
+/// Starts the query at the front of the queue in the background, unless
+/// something else is using the session's connection.
+fn run_queued(state: &mut State) {
+    if state.queue.running.is_some() || state.jobs.on_session() || !state.connected {
+        return;
+    }
+    let Some(queued) = state.queue.pop() else {
+        return;
+    };
+    let session = state.session.clone();
+    let messages = state.messages.clone();
+    let audit = state.audit.clone();
+    let (id, sql) = (queued.id, queued.sql.clone());
+    let what = format!("#{id} {}", queued.summary());
+    let job = state
+        .jobs
+        .spawn(&state.messages, Kind::Query, what, |reporter| async move {
+            let started = Instant::now();
+            let run = run_query(&session.pool, &sql, &[]);
+            let outcome = watched(&session, &reporter, run).await;
+            let elapsed = started.elapsed();
+            tracing::debug!(?elapsed, query = %sql, "ran queued query");
+            if let Some(audit) = &audit {
+                let outcome = match &outcome {
+                    Ok((_, _, rows)) => Ok(*rows),
+                    Err(err) => Err(err.to_string()),
+                };
+                if let Err(err) = audit.record(&sql, &[], elapsed, outcome) {
+                    tracing::warn!(error = %err, "failed to write audit log");
+                }
+            }
+            let connection_lost =
+                matches!(&outcome, Err(err) if db::session::is_connection_error(err));
+            let outcome = outcome
+                .map(|(results, status, _)| (results, status))
+                .map_err(|err| err.to_string());
+            let done = match &outcome {
+                Ok((_, status)) => Ok(Done::from(status.clone())),
+                Err(err) => Err(err.clone()),
+            };
+            let _ = messages.send(Message::QueuedDone {
+                id,
+                sql,
+                outcome,
+                connection_lost,
+            });
+            done
+        });
+    state.queue.running = Some((job, queued));
+}
+
+/// Runs the statements of the script at `path` one by one in the
+/// background, reporting progress and the time each one took.
+fn spawn_source(state: &mut State, path: String, force: bool) {
+    let script = match std::fs::read_to_string(config::expand_home(&path)) {
+        Ok(script) => script,
+        Err(err) => {
+            state.status = format!("Failed to read \"{path}\": {err}");
+            return;
+        }
+    };
+    let session = state.session.clone();
+    let messages = state.messages.clone();
+    let audit = state.audit.clone();
+    state.status = format!("Sourcing \"{path}\"…");
+    let what = format!("\"{path}\"");
+    state
+        .jobs
+        .spawn(&state.messages, Kind::Source, what, |reporter| async move {
+            let statements = statements::split(&script);
+            let total = statements.len();
+            let mut report = vec![format!("-- :source {path}")];
+            let mut failures = Vec::new();
+            let (mut failed, mut connection_lost) = (0, false);
+            let started = Instant::now();
+            for (index, statement) in statements.iter().enumerate() {
+                let (skipped, summary) = statement
+                    .lines()
+                    .map(str::trim)
+                    .enumerate()
+                    .find(|(_, line)| !line.is_empty() && !line.starts_with("--"))
+                    .unwrap_or_default();
+                let start = Instant::now();
+                let run = sqlx::raw_sql(statement).execute(&session.pool);
+                let outcome = watched(&session, &reporter, run).await;
+                let elapsed = start.elapsed();
+                tracing::debug!(?elapsed, statement, "sourced statement");
+                if let Some(audit) = &audit {
+                    let outcome = match &outcome {
+                        Ok(done) => Ok(done.rows_affected()),
+                        Err(err) => Err(err.to_string()),
+                    };
+                    if let Err(err) = audit.record(statement, &[], elapsed, outcome) {
+                        tracing::warn!(error = %err, "failed to write audit log");
+                    }
+                }
+                match outcome {
+                    Ok(done) => {
+                        session.record(statement);
+                        report.push(format!(
+                            "-- [{}/{total}] ok, {} rows, {elapsed:.1?}: {summary}",
+                            index + 1,
+                            done.rows_affected()
+                        ));
+                    }
+                    Err(err) => {
+                        tracing::warn!(error = %err, statement, "sourced statement failed");
+                        failed += 1;
+                        connection_lost = db::session::is_connection_error(&err);
+                        report.push(format!(
+                            "-- [{}/{total}] failed after {elapsed:.1?}: {summary}",
+                            index + 1
+                        ));
+                        report.push(format!("--   {err}"));
+                        let offset = statement.as_ptr() as usize - script.as_ptr() as usize;
+                        failures.push(Entry {
+                            target: quickfix::Target::Line {
+                                path: path.clone(),
+                                line: script[..offset].matches('\n').count() + skipped,
+                            },
+                            text: err.to_string(),
+                        });
+                        if connection_lost || !force {
+                            report.push(format!(
+                                "-- stopped, {} statements not run",
+                                total - index - 1
+                            ));
+                            break;
+                        }
+                    }
+                }
+                let _ = messages.send(Message::SourceProgress {
+                    done: index + 1,
+                    total,
+                });
+            }
+
+            let elapsed = started.elapsed();
+            let status = match failed {
+                0 => format!("Sourced \"{path}\": {total} statements in {elapsed:.1?}"),
+                _ => format!(
+                    "Sourced \"{path}\" with {failed} failed of {total} statements, :copen lists them"
+                ),
+            };
+            let done = if connection_lost {
+                Err(format!("Connection lost sourcing \"{path}\""))
+            } else {
+                Ok(Done {
+                    summary: status.clone(),
+                    output: Some(Output::Buffer(format!("[source] {path}"))),
+                })
+            };
+            let _ = messages.send(Message::SourceDone {
+                path,
+                report: report.join("\n"),
+                failures,
+                status,
+                connection_lost,
+            });
+            done
+        });
+}
+
+/// Whether `query` produces rows we can wrap and render, as opposed to a
+/// statement like `SET` or `UPDATE` that only reports what it did.
+pub(crate) fn returns_rows(query: &str) -> bool {
+    let keyword = query
+        .trim_start()
+        .split(|c: char| !c.is_alphanumeric())
+        .next()
+        .unwrap_or_default();
+    ["select", "with", "values", "table"]
+        .iter()
+        .any(|k| keyword.eq_ignore_ascii_case(k))
+}
+
+/// What [`run_query`] returns.
+pub(crate) type QueryOutcome = Result<(Vec<(String, ResultSet)>, String, u64), sqlx::Error>;
+
+/// Runs `raw_query`, returning the rows of each statement that returned any
+/// along with the statement, a status message and the number of rows
+/// returned or affected by the last one.
+/// With `binds` it runs as a single prepared statement with those values
+/// bound to its parameters, `None` being `NULL`.
+pub(crate) async fn run_query(
+    pool: &PgPool,
+    raw_query: &str,
+    binds: &[Option<String>],
+) -> QueryOutcome {
+    if binds.is_empty() {
+        let (results, rows) = results::fetch_batch(pool, raw_query).await?;
+        let status = match results.as_slice() {
+            [] => format!("Query executed successfully, {rows} rows affected"),
+            [(_, only)] => format!("Query executed successfully, {} rows", only.rows.len()),
+            sets => format!("Query executed successfully, {} result sets", sets.len()),
+        };
+        return Ok((results, status, rows));
+    }
+
+    let prepare = |sql| {
+        binds
+            .iter()
+            .fold(sqlx::query(sql), |query, value| query.bind(value.clone()))
+    };
+    if !returns_rows(raw_query) {
+        let done = prepare(raw_query).execute(pool).await?;
+        return Ok((
+            Vec::new(),
+            format!(
+                "Query executed successfully, {} rows affected",
+                done.rows_affected()
+            ),
+            done.rows_affected(),
+        ));
+    }
+
+    let results = results::fetch(pool, raw_query, binds).await?;
+    let rows = results.rows.len() as u64;
+    Ok((
+        vec![(raw_query.to_string(), results)],
+        format!("Query executed successfully, {rows} rows"),
+        rows,
+    ))
+}
+
+/// Rolls back what a dry run of a query did with `rollback`, and tells what
+/// it would have done.
+async fn finish_dry_run(state: &mut State, outcome: Option<QueryOutcome>, rollback: &str) {
+    let outcome = match outcome {
+        Some(Err(err)) if db::session::is_connection_error(&err) => {
+            // The transaction is gone along with the connection.
+            tracing::warn!(error = %err, "connection lost on a dry run");
+            state.results = None;
+            start_reconnect(state);
+            return;
+        }
+        Some(outcome) => outcome.map_err(|err| err.to_string()),
+        None => Err(match state.session.cancel().await {
+            Ok(_) => "timed out and was cancelled".into(),
+            Err(err) => format!("timed out, failed to cancel it: {err}"),
+        }),
+    };
+    if let Err(err) = sqlx::raw_sql(rollback).execute(&state.session.pool).await {
+        tracing::warn!(error = %err, "failed to roll back a dry run");
+        state.transaction = Transaction::Open;
+        state.status = format!("Failed to roll back the dry run, ROLLBACK it yourself: {err}");
+        return;
+    }
+    match outcome {
+        Ok((results, _, rows)) => {
+            state.status = if results.is_empty() {
+                format!("Dry run: would affect {rows} rows, rolled back")
+            } else {
+                format!("Dry run: {rows} rows, rolled back")
+            };
+            state.show_batch(results);
+        }
+        Err(err) => {
+            state.results = None;
+            state.status = format!("Dry run failed: {err}");
+        }
+    }
+}
+
+/// Counts the rows of `browser` and shows its first page.
+async fn browse_from_start(state: &mut State, mut browser: Browser) {
+    let pool = &state.session.pool;
+    let fetched = match browser.count(pool).await {
+        Ok(total) => {
+            browser.total = total;
+            browser.fetch(pool, PageTo::Number(1)).await
+        }
+        Err(err) => Err(err),
+    };
+    match fetched {
+        Ok((browser, results)) => {
+            state.status = match browser.total {
+                Total { rows, exact: true } => format!("{} ({rows} rows)", browser.table),
+                Total { rows, .. } => format!("{} (about {rows} rows)", browser.table),
+            };
+            state.show_page(browser, results);
+        }
+        Err(err) if db::session::is_connection_error(&err) => start_reconnect(state),
+        Err(err) => state.status = format!("Failed to browse {}: {err}", browser.table),
+    }
+}
+
+/// With `:set preview`, shows the query of page `to` of `browser` in the SQL
+/// preview before it runs. Returns whether to go on and fetch the page: not
+/// when backed out of, nor when edited, as the edit runs as a query of its
+/// own instead.
+async fn preview_page(
+    state: &mut State,
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    browser: &Browser,
+    to: PageTo,
+) -> io::Result<bool> {
+    if !state.preview {
+        return Ok(true);
+    }
+    let (_, sql) = browser.query(to);
+    let what = format!("Browse {}?", browser.table);
+    match preview_sql(terminal, state.dialect, &what, sql.clone()).await? {
+        Some(edited) if edited == sql => Ok(true),
+        Some(edited) => {
+            handle_command(Command::RunQuery(edited), state, terminal).await?;
+            Ok(false)
+        }
+        None => Ok(false),
+    }
+}
+
+/// Handles `:set option[=value]`.
+async fn set_option(state: &mut State, option: &str, value: Option<&str>) {
+    let (opt, value) = match options::request(state, option, value) {
+        Ok(Request::All) => {
+            state.popup = Some(Popup::Text {
+                title: "Options".into(),
+                lines: options::OPTIONS.iter().map(|opt| opt.show(state)).collect(),
+            });
+            return;
+        }
+        Ok(Request::Show(opt)) => {
+            state.status = opt.show(state);
+            return;
+        }
+        Ok(Request::Set(opt, value)) => (opt, value),
+        Err(err) => {
+            state.status = err;
+            return;
+        }
+    };
+    // Other backends have no such settings, and the demo no server.
+    if state.backend.is_none()
+        && let Some(sql) = opt.sql(&value)
+    {
+        let started = Instant::now();
+        let outcome = sqlx::raw_sql(&sql).execute(&state.session.pool).await;
+        let audited = match &outcome {
+            Ok(done) => Ok(done.rows_affected()),
+            Err(err) => Err(err.to_string()),
+        };
+        state.audit(&sql, &[], started.elapsed(), audited);
+        match outcome {
+            Ok(_) => state.session.record(&sql),
+            Err(err) => {
+                state.status = format!("Failed to set {}: {err}", opt.name);
+                return;
+            }
+        }
+    }
+    state.status = match opt.set(state, value) {
+        Ok(()) => opt.show(state),
+        Err(err) => err,
+    };
+}
+
+/// Prompts for a value for each parameter of `query`, with its type if the
+/// server can infer it. Returns the query to run and the values to bind, or
+/// `None` if the user cancelled.
+async fn bind_parameters(
+    state: &mut State,
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    query: &params::Parameterized,
+) -> io::Result<Result<Option<(String, Vec<Option<String>>)>, sqlx::Error>> {
+    use sqlx::{Either, TypeInfo};
+    let positional = query.positional();
+    let types: Vec<Option<String>> = match (&state.session.pool).describe(&positional).await {
+        Ok(describe) => match describe.parameters() {
+            Some(Either::Left(types)) => types
+                .iter()
+                .map(|ty| Some(ty.name().to_lowercase()))
+                .collect(),
+            _ => Vec::new(),
+        },
+        // `SELECT $1` and the like: bind it as text and let the query sort it
+        // out.
+        Err(err) if err.as_database_error().and_then(|e| e.code()).as_deref() == Some("42P18") => {
+            Vec::new()
+        }
+        Err(err) => return Ok(Err(err)),
+    };
+    let type_of = |number: usize| types.get(number - 1).cloned().flatten();
+
+    let hint = Line::styled(
+        "Enter to bind, \\N for NULL, Esc to cancel",
+        Style::default().fg(Color::DarkGray),
+    );
+    let mut values = Vec::new();
+    for (index, label) in query.labels.iter().enumerate() {
+        let title = match type_of(index + 1) {
+            Some(ty) => format!("{label} ({ty})"),
+            None => label.clone(),
+        };
+        let previous = state.binds.get(label).cloned().unwrap_or_default();
+        let mut labels = match type_of(index + 1) {
+            Some(ty) => db::catalog::enum_labels(&state.session.pool, &ty)
+                .await
+                .unwrap_or_default(),
+            None => Vec::new(),
+        };
+        let value = if labels.is_empty() {
+            prompt(terminal, &title, hint.clone(), false, previous)?
+        } else {
+            labels.push("\\N".into());
+            pick(terminal, &title, hint.clone(), &labels, &previous)?
+        };
+        let Some(value) = value else {
+            return Ok(Ok(None));
+        };
+        state.binds.insert(label.clone(), value.clone());
+        values.push((value != "\\N").then_some(value));
+    }
+
+    // Values are sent as text, the casts turn them into what the query
+    // expects.
+    let sql = query.render(|number| match type_of(number) {
+        Some(ty) if ty != "text" => format!("(${number}::text::{ty})"),
+        _ => format!("${number}"),
+    });
+    Ok(Ok(Some((sql, values))))
+}
+
+/// What `cmd` needs of the server beyond a connection, to refuse it up
+/// front when the server doesn't have it.
+fn need(cmd: &Command, server: Server) -> Option<Need> {
+    match cmd {
+        Command::Definition(_)
+        | Command::Ddl(_)
+        | Command::Generate(..)
+        | Command::Triggers { .. }
+        | Command::SuggestIndex
+        | Command::Erd(_)
+        | Command::Report(
+            Report::Sizes(_)
+            | Report::Privileges(_)
+            | Report::Grep(_)
+            | Report::Extensions
+            | Report::Sequences
+            | Report::Partitions(_)
+            | Report::MatViews,
+        ) => Some(Need::Catalogs),
+        // CockroachDB has statement statistics of its own.
+        Command::Report(Report::Statements) if server.flavor != Flavor::Cockroach => {
+            Some(Need::Extension("pg_stat_statements"))
+        }
+        _ => None,
+    }
+}
+
+fn handle_command<'a>(
+    cmd: Command,
+    state: &'a mut State,
+    terminal: &'a mut Terminal<CrosstermBackend<io::Stdout>>,
+) -> Pin<Box<dyn Future<Output = io::Result<()>> + 'a>> {
+    Box::pin(async move {
+        if !matches!(cmd, Command::None) {
+            tracing::debug!(?cmd, "command");
+        }
+        if let Some(backend) = &state.backend
+            && matches!(
+                cmd,
+                Command::ConnInfo
+                    | Command::Definition(_)
+                    | Command::Ddl(_)
+                    | Command::Generate(..)
+                    | Command::Source { .. }
+                    | Command::CopyOut(_)
+                    | Command::CopyTo { .. }
+                    | Command::Dump { .. }
+                    | Command::Restore(_)
+                    | Command::Migrate(_)
+                    | Command::Report(_)
+                    | Command::SignalBackend { .. }
+                    | Command::SetVal { .. }
+                    | Command::Triggers { .. }
+                    | Command::ValidateConstraints(_)
+                    | Command::Vacuum(_)
+                    | Command::Refresh { .. }
+                    | Command::RenderReport { .. }
+                    | Command::Import { .. }
+                    | Command::EditCell
+                    | Command::ApplyEdits
+                    | Command::Explain { .. }
+                    | Command::PlanDiff
+                    | Command::SuggestIndex
+                    | Command::Erd(_)
+                    | Command::SwitchDatabase(_)
+                    | Command::Bench { .. }
+                    | Command::AsOf(_)
+                    | Command::Compare(_)
+                    | Command::Prepare(_)
+                    | Command::Execute { .. }
+                    | Command::Queue
+                    | Command::Capabilities
+            )
+        {
+            state.status = if state.demo {
+                "Only Postgres supports that, and the demo has no Postgres behind it".into()
+            } else {
+                format!(
+                    "Only Postgres supports that, not {}; :disconnect to go back",
+                    backend.name()
+                )
+            };
+            return Ok(());
+        }
+        if let Some(need) = need(&cmd, state.session.server)
+            && state.capabilities.lacks(need)
+        {
+            state.status = need.refusal();
+            return Ok(());
+        }
+        if state.dry_run
+            && matches!(
+                cmd,
+                Command::SignalBackend { .. }
+                    | Command::SetVal { .. }
+                    | Command::Vacuum(_)
+                    | Command::Refresh { .. }
+                    | Command::CopyTo { .. }
+                    | Command::Restore(_)
+                    | Command::Import { .. }
+            )
+        {
+            state.status = "That can't be rolled back, :set nodryrun first".into();
+            return Ok(());
+        }
+        #[cfg(feature = "lua")]
+        let cmd = match (cmd, &state.lua) {
+            (Command::RunQuery(query), Some(lua)) => match lua.pre_query(query) {
+                Ok(query) => Command::RunQuery(query),
+                Err(err) => {
+                    state.status = err;
+                    return Ok(());
+                }
+            },
+            (cmd, _) => cmd,
+        };
+        let cmd = match cmd {
+            Command::RunQuery(query) => Command::RunQuery(vars::substitute(&query, &state.vars)),
+            cmd => cmd,
+        };
+        match cmd {
+            // Behind whatever is running on the connection, rather than
+            // waiting for it with everything else.
+            Command::Run { expect }
+                if state.queue.running.is_some()
+                    || !state.queue.is_empty()
+                    || state.jobs.on_session() =>
+            {
+                if expect.is_some() {
+                    state.status =
+                        "The connection is busy, :run expect= has to wait until it is free".into();
+                    return Ok(());
+                }
+                handle_command(Command::Queue, state, terminal).await?;
+            }
+            Command::Run { expect } => {
+                state.expected_rows = expect;
+                let query = state.buffer().text();
+                handle_command(Command::RunQuery(query), state, terminal).await?;
+                // Unless the query never got to run.
+                state.expected_rows = None;
+            }
+            Command::RunQuery(_) if state.dry_run && state.backend.is_some() => {
+                state.status = "Only Postgres takes a dry run, :set nodryrun first".into();
+            }
+            Command::RunQuery(raw_query) if state.backend.is_some() => {
+                let Some(backend) = &mut state.backend else {
+                    return Ok(());
+                };
+                tracing::debug!(query = %raw_query, backend = %backend.name(), "running query");
+                let started = Instant::now();
+                let outcome = backend.query(&raw_query).await;
+                let elapsed = started.elapsed();
+                let audited = match &outcome {
+                    Ok((_, rows)) => Ok(*rows),
+                    Err(err) => Err(err.clone()),
+                };
+                state.audit(&raw_query, &[], elapsed, audited);
+                match outcome {
+                    Ok((results, rows)) => {
+                        state.status = match &results {
+                            Some(_) => format!("Query executed successfully, {rows} rows"),
+                            None => format!("Query executed successfully, {rows} rows affected"),
+                        };
+                        if results.is_some() {
+                            state.last_query = Some(raw_query);
+                        }
+                        state.show_results(results);
+                    }
+                    Err(err) => {
+                        tracing::warn!(?elapsed, error = %err, "query failed");
+                        state.results = None;
+                        state.status = format!("Failed to run query: {err}");
+                    }
+                }
+            }
+            Command::RunQuery(raw_query) => {
+                if offline(state) {
+                    return Ok(());
+                }
+                let mut sql = raw_query.clone();
+                let mut binds = Vec::new();
+                if let Some(query) = params::find(&raw_query) {
+                    match bind_parameters(state, terminal, &query).await? {
+                        Ok(Some((query, values))) => (sql, binds) = (query, values),
+                        Ok(None) => {
+                            state.status = "Query cancelled".into();
+                            return Ok(());
+                        }
+                        Err(err) => {
+                            state.results = None;
+                            state.status = format!("Failed to prepare query: {err}");
+                            return Ok(());
+                        }
+                    }
+                }
+                tracing::debug!(query = %sql, binds = binds.len(), "running query");
+                if state.dry_run && Transaction::is_control(&sql) {
+                    state.status =
+                        "A dry run doesn't begin or end transactions, :set nodryrun first".into();
+                    return Ok(());
+                }
+                if !state.dry_run
+                    && statements::is_write(&sql)
+                    && !confirm_write(state, terminal, "The query writes")?
+                {
+                    state.status = "Query cancelled".into();
+                    return Ok(());
+                }
+                let limited = match state.auto_limit {
+                    0 => None,
+                    rows => statements::limit(&sql, rows).map(|limited| (limited, rows)),
+                };
+                if let Some((limited, _)) = &limited {
+                    sql.clone_from(limited);
+                }
+                if (state.cost_guard > 0 || state.est_row_guard > 0)
+                    && statements::is_explainable(&sql)
+                {
+                    // Without an estimate the query runs as it would have.
+                    let estimate = match plan::estimate(&state.session.pool, &sql, &binds).await {
+                        Ok(estimate) => estimate,
+                        Err(err) => {
+                            tracing::debug!(error = %err, "failed to estimate the query");
+                            None
+                        }
+                    };
+                    if let Some(why) = estimate.and_then(|estimate| {
+                        estimate.exceeds(state.cost_guard, state.est_row_guard)
+                    }) && !prompt(
+                        terminal,
+                        "Run it anyway? y runs it, anything else cancels",
+                        Line::from(why.clone()),
+                        false,
+                        String::new(),
+                    )?
+                    .is_some_and(|answer| answer.trim().eq_ignore_ascii_case("y"))
+                    {
+                        state.status = format!("Query cancelled: {}", why.to_lowercase());
+                        return Ok(());
+                    }
+                }
+                let pool = &state.session.pool;
+                // A dry run runs in a transaction of its own, or under a
+                // savepoint in the one open, and rolls it back whatever it did.
+                let dry_run = match state.transaction {
+                    _ if !state.dry_run => None,
+                    Transaction::Idle => Some(("BEGIN".to_string(), "ROLLBACK".to_string())),
+                    _ => Some((
+                        format!("SAVEPOINT {STATEMENT_SAVEPOINT}"),
+                        format!(
+                            "ROLLBACK TO SAVEPOINT {STATEMENT_SAVEPOINT}; \
+                             RELEASE SAVEPOINT {STATEMENT_SAVEPOINT}"
+                        ),
+                    )),
+                };
+                if let Some((begin, _)) = &dry_run
+                    && let Err(err) = pool.execute(begin.as_str()).await
+                {
+                    state.status = format!("Failed to start the dry run: {err}");
+                    return Ok(());
+                }
+                let expect = state.expected_rows.take();
+                let control = Transaction::is_control(&sql);
+                // A SET of it, or the end of a transaction that may undo one.
+                let search_path = control || sql.to_ascii_lowercase().contains("search_path");
+                let guarded = dry_run.is_none()
+                    && !control
+                    && (expect.is_some() || state.row_guard > 0 && statements::is_bulk_write(&sql));
+                // Outside a transaction, a guarded write gets one of its own
+                // to wait in until it's let through.
+                let held = guarded
+                    && state.transaction == Transaction::Idle
+                    && pool.execute("BEGIN").await.is_ok();
+                let savepoint = dry_run.is_none()
+                    && (state.on_error_rollback || guarded)
+                    && state.transaction == Transaction::Open
+                    && !control
+                    && pool
+                        .execute(format!("SAVEPOINT {STATEMENT_SAVEPOINT}").as_str())
+                        .await
+                        .is_ok();
+                let started = Instant::now();
+                let run = run_query(pool, &sql, &binds);
+                let timeout = state.statement_timeout;
+                let run = async {
+                    match timeout {
+                        Some(timeout) => tokio::time::timeout(timeout + CLIENT_TIMEOUT_GRACE, run)
+                            .await
+                            .ok(),
+                        None => Some(run.await),
+                    }
+                };
+                // The loop reads no keys until the query is done, so the kill
+                // switch is watched for here.
+                let killed = keymap::unless_killed(
+                    run,
+                    EventStream::new(),
+                    &state.keymap,
+                    state.mode,
+                    &state.messages,
+                );
+                let Some(outcome) = killed.await else {
+                    let elapsed = started.elapsed();
+                    let killed = Err("killed by the kill switch".to_string());
+                    state.audit(&sql, &binds, elapsed, killed);
+                    return handle_command(Command::KillSwitch, state, terminal).await;
+                };
+                let elapsed = started.elapsed();
+                let audited = match &outcome {
+                    Some(Ok((_, _, rows))) => Ok(*rows),
+                    Some(Err(err)) => Err(err.to_string()),
+                    None => Err("timed out".to_string()),
+                };
+                if let Some((_, rollback)) = dry_run {
+                    state.audit(&format!("{sql} -- dry run"), &binds, elapsed, audited);
+                    finish_dry_run(state, outcome, &rollback).await;
+                    return Ok(());
+                }
+                state.audit(&sql, &binds, elapsed, audited);
+                let ok = matches!(outcome, Some(Ok(_)));
+                // Neither after a timeout, with the query still to be
+                // cancelled, nor with the connection gone.
+                let failed = matches!(&outcome, Some(Err(err))
+                    if !db::session::is_connection_error(err));
+                let kept = match &outcome {
+                    Some(Ok((_, _, rows))) if guarded => {
+                        let rows = *rows;
+                        let why = match expect {
+                            Some(expect) if rows != expect => format!("Expected {expect}"),
+                            None if rows > state.row_guard => {
+                                format!("Over rowguard={}", state.row_guard)
+                            }
+                            _ => String::new(),
+                        };
+                        let verb = if held { "commit" } else { "keep" };
+                        why.is_empty()
+                            || prompt(
+                                terminal,
+                                &format!("{rows} rows changed, {verb} them?"),
+                                Line::from(format!(
+                                    "{why}. y to {verb} them, anything else rolls back"
+                                )),
+                                false,
+                                String::new(),
+                            )?
+                            .is_some_and(|answer| answer.trim().eq_ignore_ascii_case("y"))
+                    }
+                    _ => true,
+                };
+                let pool = &state.session.pool;
+                let mut rolled_back = false;
+                let mut commit_error = None;
+                if held && (ok || failed) {
+                    let end = if ok && kept { "COMMIT" } else { "ROLLBACK" };
+                    if let Err(err) = pool.execute(end).await {
+                        commit_error = Some(err);
+                    }
+                } else if savepoint && ok && kept {
+                    let release = format!("RELEASE SAVEPOINT {STATEMENT_SAVEPOINT}");
+                    if let Err(err) = pool.execute(release.as_str()).await {
+                        tracing::warn!(error = %err, "failed to release savepoint");
+                    }
+                } else if savepoint && (ok || failed && state.on_error_rollback) {
+                    let rollback = format!(
+                        "ROLLBACK TO SAVEPOINT {STATEMENT_SAVEPOINT}; \
+                         RELEASE SAVEPOINT {STATEMENT_SAVEPOINT}"
+                    );
+                    rolled_back = sqlx::raw_sql(&rollback).execute(pool).await.is_ok() && failed;
+                }
+                state.transaction = state.transaction.after(&sql, ok || rolled_back);
+                if ok
+                    && kept
+                    && let Some(names) = db::schema::ddl_targets(&sql)
+                {
+                    state.schema.invalidate(&names);
+                    state.schema_stale = true;
+                }
+                // DDL in a transaction only shows on other connections, like
+                // the refresh's, once it commits.
+                if state.schema_stale && state.transaction == Transaction::Idle {
+                    refresh_schema(state);
+                }
+                let Some(outcome) = outcome else {
+                    tracing::warn!(?elapsed, "query timed out, cancelling");
+                    state.results = None;
+                    state.status = match state.session.cancel().await {
+                        Ok(_) => "Query timed out and was cancelled".into(),
+                        Err(err) => format!("Query timed out, failed to cancel it: {err}"),
+                    };
+                    if held && let Err(err) = state.session.pool.execute("ROLLBACK").await {
+                        tracing::warn!(error = %err, "failed to roll back a timed out query");
+                    }
+                    return Ok(());
+                };
+                let outcome = match (outcome, commit_error) {
+                    (Ok(_), Some(err)) => Err(err),
+                    (outcome, _) => outcome,
+                };
+                match outcome {
+                    Ok((results, status, _)) if !kept => {
+                        state.show_batch(results);
+                        state.status = format!("{status}, rolled back");
+                    }
+                    Ok((results, status, _)) => {
+                        tracing::info!(?elapsed, "{status}");
+                        state.session.record(&raw_query);
+                        if !results.is_empty() {
+                            state.last_query = Some(raw_query);
+                        }
+                        let cut = limited.is_some_and(|(_, rows)| {
+                            results
+                                .first()
+                                .is_some_and(|(_, set)| set.rows.len() as u64 == rows)
+                        });
+                        state.show_batch(results);
+                        state.status = match cut {
+                            true => format!("{status}, up to autolimit={}", state.auto_limit),
+                            false => status,
+                        };
+                    }
+                    Err(err) if db::session::is_connection_error(&err) => {
+                        tracing::warn!(error = %err, "connection lost running query");
+                        state.results = None;
+                        start_reconnect(state);
+                    }
+                    Err(err) if db::session::is_query_canceled(&err) => {
+                        tracing::warn!(?elapsed, "query cancelled");
+                        state.results = None;
+                        state.status = match state.statement_timeout {
+                            Some(timeout) => {
+                                format!("Query cancelled after statement_timeout of {timeout:?}")
+                            }
+                            None => "Query cancelled".into(),
+                        };
+                    }
+                    Err(err) => {
+                        tracing::warn!(?elapsed, error = %err, "query failed");
+                        state.results = None;
+                        state.status = format!("Failed to run query: {}", err);
+                    }
+                }
+                if rolled_back {
+                    state.status += "; rolled back to before it";
+                }
+                if search_path {
+                    check_search_path(state);
+                }
+            }
+            Command::Definition(name) => {
+                match db::catalog::definition(&state.session.pool, state.session.server, &name)
+                    .await
+                {
+                    Ok(Some(definition)) => {
+                        let mut buffer =
+                            Buffer::from_text(format!("[definition] {name}"), &definition);
+                        buffer.read_only = true;
+                        state.open_buffer(buffer);
+                        state.status = format!("Definition of {name}");
+                    }
+                    Ok(None) => state.status = format!("No view or function named {name}"),
+                    Err(err) if db::session::is_connection_error(&err) => start_reconnect(state),
+                    Err(err) => state.status = format!("Failed to get definition: {err}"),
+                }
+            }
+            Command::Ddl(name) => {
+                match db::catalog::table_ddl(&state.session.pool, state.session.server, &name).await
+                {
+                    Ok(Some(ddl)) => {
+                        state.open_buffer(Buffer::from_text(format!("[ddl] {name}"), &ddl));
+                        state.status = format!("DDL of {name}");
+                    }
+                    Ok(None) => state.status = format!("No table named {name}"),
+                    Err(err) if db::session::is_connection_error(&err) => start_reconnect(state),
+                    Err(err) => state.status = format!("Failed to generate DDL: {err}"),
+                }
+            }
+            Command::Generate(kind, name) => {
+                let cached = state
+                    .schema
+                    .resolve(&name, &state.search_path)
+                    .map(|relation| (relation.qualified(), relation.columns.clone()));
+                let found = match cached {
+                    Some(found) => Ok(Some(found)),
+                    None => db::catalog::columns(&state.session.pool, &name).await,
+                };
+                match found {
+                    Ok(Some((table, columns))) => {
+                        let sql = generate::generate(kind, &table, &columns);
+                        let name = format!("[{}] {table}", format!("{kind:?}").to_lowercase());
+                        state.open_buffer(Buffer::from_text(name, &sql));
+                        state.status = format!("Generated {kind:?} for {table}");
+                    }
+                    Ok(None) => state.status = format!("No table named {name}"),
+                    Err(err) if db::session::is_connection_error(&err) => start_reconnect(state),
+                    Err(err) => state.status = format!("Failed to generate statement: {err}"),
+                }
+            }
+            Command::Substitute(substitute) => {
+                if state.editable() {
+                    substitute_lines(state, terminal, &substitute)?;
+                }
+            }
+            Command::NoHighlight => state.search = None,
+            Command::YankBuffer => {
+                state.status = format!("{} lines yanked", state.buffer().lines.len());
+                state.yank(Register {
+                    text: state.buffer().text(),
+                    linewise: true,
+                });
+            }
+            Command::Edit(path) => {
+                let path = config::expand_home(&path);
+                if let Some(index) = state
+                    .buffers
+                    .iter()
+                    .position(|buffer| buffer.path.as_deref() == Some(path.as_path()))
+                {
+                    state.current = index;
+                    return Ok(());
+                }
+                match Buffer::open(&path) {
+                    Ok(buffer) => {
+                        state.status = match buffer.lines.len() {
+                            1 if buffer.lines[0].is_empty() => {
+                                format!("\"{}\" [New]", path.display())
+                            }
+                            lines => format!("\"{}\" {lines} lines", path.display()),
+                        };
+                        state.open_buffer(buffer);
+                    }
+                    Err(err) => {
+                        state.status = format!("Failed to open \"{}\": {err}", path.display())
+                    }
+                }
+            }
+            Command::Write(path) => {
+                let Some(path) = path
+                    .map(|path| config::expand_home(&path))
+                    .or_else(|| state.buffer().path.clone())
+                else {
+                    state.status = "No file name".into();
+                    return Ok(());
+                };
+                let lines = state.buffer().lines.len();
+                match state.buffer_mut().write(&path) {
+                    Ok(()) => {
+                        state.status = format!("\"{}\" {lines} lines written", path.display())
+                    }
+                    Err(err) => {
+                        state.status = format!("Failed to write \"{}\": {err}", path.display())
+                    }
+                }
+            }
+            Command::Source { path, force } => {
+                if offline(state) {
+                    return Ok(());
+                }
+                if !confirm_write(state, terminal, &format!("The script {path} may write"))? {
+                    state.status = "Source cancelled".into();
+                    return Ok(());
+                }
+                spawn_source(state, path, force);
+            }
+            Command::Recover { discard } => {
+                let leftovers = swap::swap_dir()
+                    .map(|dir| swap::leftovers(&dir))
+                    .unwrap_or_default();
+                let mut recovered = 0;
+                for path in &leftovers {
+                    if !discard {
+                        match swap::recover(path) {
+                            Ok(buffer) => state.open_buffer(buffer),
+                            Err(err) => {
+                                state.status =
+                                    format!("Failed to recover {}: {err}", path.display());
+                                continue;
+                            }
+                        }
+                    }
+                    let _ = std::fs::remove_file(path);
+                    recovered += 1;
+                }
+                state.status = match (leftovers.len(), discard) {
+                    (0, _) => "No swap files to recover".into(),
+                    (_, true) => format!("Discarded {recovered} swap files"),
+                    (_, false) => format!("Recovered {recovered} buffers"),
+                };
+            }
+            Command::Explain { .. } if state.session.server.flavor == Flavor::Cockroach => {
+                state.status =
+                    "CockroachDB has no EXPLAIN (FORMAT JSON) to draw a plan from".into();
+            }
+            Command::Explain { analyze } => {
+                if offline(state) {
+                    return Ok(());
+                }
+                let text = state.buffer().text();
+                let Some(statement) = statements::split(&text)
+                    .into_iter()
+                    .rfind(|statement| !statement.trim().is_empty())
+                else {
+                    state.status = "Nothing to explain".into();
+                    return Ok(());
+                };
+                let statement = vars::substitute(statement, &state.vars);
+                let mut sql = statement.clone();
+                let mut binds = Vec::new();
+                if let Some(query) = params::find(&statement) {
+                    match bind_parameters(state, terminal, &query).await? {
+                        Ok(Some((query, values))) => (sql, binds) = (query, values),
+                        Ok(None) => {
+                            state.status = "Explain cancelled".into();
+                            return Ok(());
+                        }
+                        Err(err) => {
+                            state.status = format!("Failed to prepare query: {err}");
+                            return Ok(());
+                        }
+                    }
+                }
+                if !state.dialect.json_plans() {
+                    if analyze {
+                        state.status = "EXPLAIN ANALYZE is not available in this dialect".into();
+                        return Ok(());
+                    }
+                    match plan::explain_text(&state.session.pool, &sql, &binds).await {
+                        Ok(plan) => {
+                            let mut buffer = Buffer::from_text("[plan]", &plan);
+                            buffer.read_only = true;
+                            state.open_buffer(buffer);
+                            state.status = "Plan".into();
+                        }
+                        Err(err) => state.status = format!("Failed to explain query: {err}"),
+                    }
+                    return Ok(());
+                }
+                match plan::explain(&state.session.pool, &sql, &binds, analyze).await {
+                    Ok(plan) => {
+                        state.status = plan.summary();
+                        let suggestions = suggest::suggest(&plan).len();
+                        if suggestions > 0 {
+                            let s = if suggestions == 1 { "" } else { "es" };
+                            state.status +=
+                                &format!(" · :suggest-index for {suggestions} candidate index{s}");
+                        }
+                        let id = state.buffer().id;
+                        let previous = state.plans.remove(&id).map(|(_, latest)| latest);
+                        state.plans.insert(id, (previous, plan.clone()));
+                        state.plan = Some(plan::PlanView::new(plan));
+                        state.erd = None;
+                        state.plan_diff = None;
+                        state.focus = Pane::Results;
+                    }
+                    Err(err) => state.status = format!("Failed to explain query: {err}"),
+                }
+            }
+            Command::Bench { runs, warm_up } => {
+                let text = vars::substitute(&state.buffer().text(), &state.vars);
+                let mut sql = text.trim().to_string();
+                let mut binds = Vec::new();
+                if sql.is_empty() {
+                    state.status = "Nothing to benchmark".into();
+                    return Ok(());
+                }
+                if let Some(query) = params::find(&text) {
+                    match bind_parameters(state, terminal, &query).await? {
+                        Ok(Some((query, values))) => (sql, binds) = (query, values),
+                        Ok(None) => {
+                            state.status = "Benchmark cancelled".into();
+                            return Ok(());
+                        }
+                        Err(err) => {
+                            state.status = format!("Failed to prepare query: {err}");
+                            return Ok(());
+                        }
+                    }
+                }
+                spawn_bench(state, sql, binds, runs, warm_up);
+            }
+            Command::Stats => {
+                state.dashboard = Some(Dashboard::default());
+                state.plan = None;
+                state.plan_diff = None;
+                state.erd = None;
+                state.focus = Pane::Results;
+                // Only the session's side without Postgres behind it.
+                if state.backend.is_none() && !state.demo {
+                    refresh_server_stats(state);
+                }
+            }
+            Command::Erd(scope) => {
+                match db::catalog::foreign_keys(&state.session.pool, state.session.server).await {
+                    Ok(keys) => {
+                        match Erd::new(&state.schema, &keys, scope.as_deref(), &state.search_path) {
+                            Ok(erd) => {
+                                state.status = format!(
+                                    "ERD of {}: {} tables, {} foreign keys",
+                                    erd.scope,
+                                    erd.tables(),
+                                    erd.keys()
+                                );
+                                state.erd = Some(erd);
+                                state.plan = None;
+                                state.plan_diff = None;
+                                state.focus = Pane::Results;
+                            }
+                            Err(err) => state.status = err,
+                        }
+                    }
+                    Err(err) if db::session::is_connection_error(&err) => start_reconnect(state),
+                    Err(err) => state.status = format!("Failed to read the foreign keys: {err}"),
+                }
+            }
+            Command::PlanDiff => match state.plans.get(&state.buffer().id) {
+                Some((Some(old), new)) => {
+                    state.plan_diff = Some(plan::PlanDiff::new(old.clone(), new.clone()));
+                    state.plan = None;
+                    state.erd = None;
+                    state.focus = Pane::Results;
+                }
+                _ => state.status = "Explain the query twice to compare its plans".into(),
+            },
+            Command::SuggestIndex => {
+                let suggestions = match state.plans.get(&state.buffer().id) {
+                    Some((_, plan)) if plan.analyzed() => suggest::suggest(plan),
+                    _ => {
+                        state.status = ":explain analyze the query first".into();
+                        return Ok(());
+                    }
+                };
+                if suggestions.is_empty() {
+                    state.status =
+                        "No sequential scan filtered out enough rows to suggest an index".into();
+                    return Ok(());
+                }
+                let count = suggestions.len();
+                for (i, suggestion) in suggestions.into_iter().enumerate() {
+                    let what = format!("Index {} of {count} on {}?", i + 1, suggestion.table);
+                    if let Some(sql) =
+                        preview_sql(terminal, state.dialect, &what, suggestion.statement()).await?
+                    {
+                        handle_command(Command::RunQuery(sql), state, terminal).await?;
+                    }
+                }
+            }
+            Command::Record(Some(_)) if state.recorder.is_some() => {
+                state.status = "Already recording, :record to stop".into();
+            }
+            Command::Record(Some(path)) => {
+                let file = config::expand_home(&path);
+                state.status = match record::Recorder::create(&file) {
+                    Ok(recorder) => {
+                        state.recorder = Some(recorder);
+                        format!("Recording to {}, :record to stop", file.display())
+                    }
+                    Err(err) => format!("Failed to create {}: {err}", file.display()),
+                };
+            }
+            Command::Record(None) => {
+                state.status = match state.recorder.take().map(record::Recorder::finish) {
+                    Some(Ok((path, events))) => format!(
+                        "Recorded {events} events to {}, replay with dbvi --replay",
+                        path.display()
+                    ),
+                    Some(Err(err)) => format!("Failed to finish the recording: {err}"),
+                    None => "Not recording, :record <file> to start".into(),
+                };
+            }
+            Command::Log => match logging::current_log() {
+                Some(path) => match std::fs::read_to_string(&path) {
+                    Ok(log) => {
+                        let mut buffer =
+                            Buffer::from_text(format!("[log] {}", path.display()), &log);
+                        buffer.read_only = true;
+                        buffer.bottom();
+                        state.open_buffer(buffer);
+                    }
+                    Err(err) => state.status = format!("Failed to read {}: {err}", path.display()),
+                },
+                None => state.status = "No log yet, start with --log-level debug".into(),
+            },
+            Command::Histogram(column) => {
+                let Some(results) = &state.results else {
+                    state.status = "No results".into();
+                    return Ok(());
+                };
+                let col = match column {
+                    Some(name) => match results.column_index(&name) {
+                        Some(col) => col,
+                        None => {
+                            state.status = format!("No column {name}");
+                            return Ok(());
+                        }
+                    },
+                    None => state.grid.col,
+                };
+                if state.redactions.covers(&results.columns[col].name) {
+                    state.status = format!("{} is redacted", results.columns[col].name);
+                    return Ok(());
+                }
+                let max_bars = terminal.size()?.height.saturating_sub(8).clamp(1, 30) as usize;
+                state.popup = Some(Popup::Histogram {
+                    title: results.columns[col].name.clone(),
+                    bars: stats::histogram(results, col, max_bars),
+                });
+            }
+            Command::Chart(spec) => match &state.results {
+                Some(results) => match chart::Chart::new(results, &spec) {
+                    Ok(chart) => state.popup = Some(Popup::Chart(chart)),
+                    Err(err) => state.status = err,
+                },
+                None => state.status = "No results".into(),
+            },
+            Command::CopyOut(path) => {
+                if offline(state) {
+                    return Ok(());
+                }
+                let query = state.buffer().text();
+                spawn_copy(state, query, path);
+            }
+            Command::CopyTo {
+                target,
+                table,
+                source,
+            } => copy_to(state, terminal, target, table, source).await?,
+            Command::Dump { scope, path } => {
+                if offline(state) {
+                    return Ok(());
+                }
+                let what = format!("{} to {path}", scope.describe());
+                spawn_tool(state, Kind::Dump, dump::dump_args(&scope, &path), what);
+            }
+            Command::Restore(path) => {
+                if offline(state) {
+                    return Ok(());
+                }
+                if dump::is_script(&path) {
+                    state.status = format!("{path} is a script, :source it instead");
+                    return Ok(());
+                }
+                if !std::path::Path::new(&path).is_file() {
+                    state.status = format!("No such file: {path}");
+                    return Ok(());
+                }
+                if state.read_only {
+                    state.status = "readonly is on, :set noreadonly to restore".into();
+                    return Ok(());
+                }
+                let database = state.session.options.get_database().unwrap_or_default();
+                let database = database.to_string();
+                if !confirm_write(state, terminal, &format!("Restore {path} into {database}"))? {
+                    state.status = "Restore cancelled".into();
+                    return Ok(());
+                }
+                let confirmed = prompt(
+                    terminal,
+                    &format!("Restore {path} into {database}?"),
+                    Line::from("y to go ahead, anything else cancels"),
+                    false,
+                    String::new(),
+                )?
+                .is_some_and(|answer| answer.trim().eq_ignore_ascii_case("y"));
+                if !confirmed {
+                    state.status = "Restore cancelled".into();
+                    return Ok(());
+                }
+                let what = format!("{path} into {database}");
+                spawn_tool(
+                    state,
+                    Kind::Restore,
+                    dump::restore_args(&path, &database),
+                    what,
+                );
+            }
+            Command::Report(report) => {
+                if offline(state) {
+                    return Ok(());
+                }
+                if !report.available(state.session.server) {
+                    state.status = format!(
+                        ":{} is not available on {}",
+                        report.title(),
+                        state.session.server.name()
+                    );
+                    return Ok(());
+                }
+                match report.run(&state.session.pool, state.session.server).await {
+                    Ok(results) => {
+                        state.status = format!("{} ({} rows)", report.title(), results.rows.len());
+                        if let Report::Grep(_) = report {
+                            state.quickfix.set(report.title(), grep_entries(&results));
+                        }
+                        state.show_results(Some(results));
+                        state.report = Some(report);
+                        state.report_at = Instant::now();
+                        state.focus = Pane::Results;
+                    }
+                    Err(err)
+                        if report == Report::Statements
+                            && db::monitor::is_missing_relation(&err) =>
+                    {
+                        state.status = "pg_stat_statements is not installed in this \
+                                        database, CREATE EXTENSION pg_stat_statements"
+                            .into();
+                    }
+                    Err(err) => state.status = format!("Failed to run {}: {err}", report.title()),
+                }
+            }
+            Command::SignalBackend { pid, terminate } => {
+                let (verb, action) = match terminate {
+                    false => ("Cancel the query of", "cancelled"),
+                    true => ("Terminate", "terminated"),
+                };
+                let sql = db::monitor::signal_statement(pid, terminate);
+                let what = format!("{verb} backend {pid}?");
+                let Some(sql) = preview_sql(terminal, state.dialect, &what, sql).await? else {
+                    return Ok(());
+                };
+                let started = Instant::now();
+                let outcome = db::monitor::signal_backend(&state.session.pool, &sql).await;
+                let audited = match &outcome {
+                    Ok(_) => Ok(1),
+                    Err(err) => Err(err.to_string()),
+                };
+                state.audit(&sql, &[], started.elapsed(), audited);
+                state.status = match outcome {
+                    Ok(true) => format!("Backend {pid} {action}"),
+                    Ok(false) => format!("Backend {pid} is gone or not yours to signal"),
+                    Err(err) => format!("Failed to signal backend {pid}: {err}"),
+                };
+                refresh_report(state);
+            }
+            Command::SetVal {
+                sequence,
+                value,
+                force,
+            } => {
+                if offline(state) {
+                    return Ok(());
+                }
+                let found = db::catalog::sequence(&state.session.pool, &sequence).await;
+                let sequence = match found {
+                    Ok(Some(sequence)) => sequence,
+                    Ok(None) => {
+                        state.status = format!("No sequence {sequence}");
+                        return Ok(());
+                    }
+                    Err(err) => {
+                        state.status = format!("Failed to look up {sequence}: {err}");
+                        return Ok(());
+                    }
+                };
+                if let (Some(owned_by), Some(max)) = (&sequence.owned_by, sequence.max)
+                    && value < max
+                    && !force
+                {
+                    state.status = format!(
+                        "{value} is behind max({owned_by}) = {max}, :setval! to set it anyway"
+                    );
+                    return Ok(());
+                }
+                let sql = db::monitor::setval_statement(&sequence.name, value);
+                let what = format!("Set {}?", sequence.name);
+                let Some(sql) = preview_sql(terminal, state.dialect, &what, sql).await? else {
+                    return Ok(());
+                };
+                let started = Instant::now();
+                let outcome = db::monitor::setval(&state.session.pool, &sql).await;
+                let audited = match &outcome {
+                    Ok(_) => Ok(1),
+                    Err(err) => Err(err.to_string()),
+                };
+                state.audit(&sql, &[], started.elapsed(), audited);
+                state.status = match outcome {
+                    Ok(value) => format!("{} set to {value}", sequence.name),
+                    Err(err) => format!("Failed to set {}: {err}", sequence.name),
+                };
+                refresh_report(state);
+            }
+            Command::Triggers { table, enable } => {
+                let (verb, what) = match enable {
+                    true => ("ENABLE", format!("Enable the triggers of {table}?")),
+                    false => ("DISABLE", format!("Disable the triggers of {table}?")),
+                };
+                let sql = format!("ALTER TABLE {table} {verb} TRIGGER USER;");
+                if let Some(sql) = preview_sql(terminal, state.dialect, &what, sql).await? {
+                    return handle_command(Command::RunQuery(sql), state, terminal).await;
+                }
+            }
+            Command::ValidateConstraints(table) => {
+                if offline(state) {
+                    return Ok(());
+                }
+                let summary = match db::catalog::summary(&state.session.pool, &table).await {
+                    Ok(Some(summary)) => summary,
+                    Ok(None) => {
+                        state.status = format!("No table {table}");
+                        return Ok(());
+                    }
+                    Err(err) => {
+                        state.status = format!("Failed to look up {table}: {err}");
+                        return Ok(());
+                    }
+                };
+                let sql: Vec<String> = summary
+                    .not_valid()
+                    .map(|constraint| {
+                        format!(
+                            "ALTER TABLE {table} VALIDATE CONSTRAINT {};",
+                            statements::quote_ident(&constraint.name)
+                        )
+                    })
+                    .collect();
+                if sql.is_empty() {
+                    state.status = format!("{table} has no NOT VALID constraints");
+                    return Ok(());
+                }
+                let what = format!("Validate the constraints of {table}?");
+                let sql = sql.join("\n");
+                if let Some(sql) = preview_sql(terminal, state.dialect, &what, sql).await? {
+                    return handle_command(Command::RunQuery(sql), state, terminal).await;
+                }
+            }
+            Command::Vacuum(_)
+                if matches!(
+                    state.session.server.flavor,
+                    Flavor::Cockroach | Flavor::Yugabyte
+                ) =>
+            {
+                state.status = format!("{} has no VACUUM to run", state.session.server.name());
+            }
+            Command::Vacuum(table) => {
+                let sql = db::monitor::vacuum_statement(&table);
+                let what = format!("Vacuum {table}?");
+                if let Some(sql) = preview_sql(terminal, state.dialect, &what, sql).await? {
+                    spawn_watched(state, Kind::Vacuum, table, sql);
+                }
+            }
+            Command::Migrate(action) => {
+                if offline(state) {
+                    return Ok(());
+                }
+                migrate(state, terminal, action).await?;
+            }
+            Command::RenderReport { template, output } => {
+                if offline(state) {
+                    return Ok(());
+                }
+                let Some(output) = output.or(template::output_path(&template).map(String::from))
+                else {
+                    state.status = format!("Where to? :report {template} <output>");
+                    return Ok(());
+                };
+                state.status = render_report(state, &template, &output).await;
+            }
+            Command::Refresh {
+                matview,
+                concurrently,
+            } => {
+                let sql = db::monitor::refresh_statement(&matview, concurrently);
+                let what = format!("Refresh {matview}?");
+                if let Some(sql) = preview_sql(terminal, state.dialect, &what, sql).await? {
+                    spawn_watched(state, Kind::Refresh, matview, sql);
+                }
+            }
+            Command::Import { path, table } => {
+                if offline(state) {
+                    return Ok(());
+                }
+                if !confirm_write(state, terminal, &format!("Importing {path} writes"))? {
+                    state.status = "Import cancelled".into();
+                    return Ok(());
+                }
+                import_csv(state, terminal, path, table).await?;
+            }
+            Command::Pivot(spec) => match &state.results {
+                Some(results) => match pivot::pivot(results, &spec) {
+                    Ok(pivoted) => {
+                        state.status = format!(
+                            "Pivoted {} rows into {}x{}",
+                            results.rows.len(),
+                            pivoted.rows.len(),
+                            pivoted.columns.len() - 1
+                        );
+                        state.show_results(Some(pivoted));
+                    }
+                    Err(err) => state.status = err,
+                },
+                None => state.status = "No results".into(),
+            },
+            cmd @ (Command::Lua(_) | Command::LuaAction { .. }) => lua_command(state, cmd),
+            Command::Export { exporter, path }
+                if matches!(exporter.as_str(), "csv" | "table" | "xlsx" | "html") =>
+            {
+                export_results(state, &exporter, &path)
+            }
+            cmd @ (Command::Plugins
+            | Command::Plugin { .. }
+            | Command::Transform(_)
+            | Command::Export { .. }) => plugin_command(state, cmd),
+            Command::EditExternal => {
+                if !state.editable() {
+                    return Ok(());
+                }
+                match edit_external(terminal, &state.buffer().text()).await? {
+                    Ok(text) => {
+                        let buffer = state.buffer_mut();
+                        let cursor = buffer.cursor;
+                        buffer.set_text(&text);
+                        buffer.cursor = cursor;
+                        buffer.clamp_cursor(false);
+                        state.status = format!("{} lines read back", buffer.lines.len());
+                    }
+                    Err(err) => state.status = err,
+                }
+            }
+            Command::Shell(command) => {
+                state.status = shell_out(terminal, &command).await?;
+            }
+            Command::Pipe(command) => spawn_pipe(state, command),
+            Command::Schema { refresh: true } => {
+                if state.schema_path.is_none() {
+                    state.status = "No schema to refresh here".into();
+                } else {
+                    refresh_schema(state);
+                    state.status = "Refreshing the schema…".into();
+                }
+            }
+            Command::Schema { refresh: false } if state.schema.relations.is_empty() => {
+                state.status = "No schema cached yet, :schema refresh to fetch it".into();
+            }
+            Command::Schema { refresh: false } => {
+                let mut results = ResultSet::new(vec![
+                    results::Column::new("schema", "name"),
+                    results::Column::new("name", "name"),
+                    results::Column::new("kind", "text"),
+                    results::Column::new("columns", "text"),
+                ]);
+                results.rows = state
+                    .schema
+                    .relations
+                    .values()
+                    .map(|relation| {
+                        let columns: Vec<&str> = relation
+                            .columns
+                            .iter()
+                            .map(|column| column.name.as_str())
+                            .collect();
+                        vec![
+                            Some(relation.schema.clone()),
+                            Some(relation.name.clone()),
+                            Some(relation.kind.clone()),
+                            Some(columns.join(", ")),
+                        ]
+                    })
+                    .collect();
+                state.status = format!("{} relations cached", results.rows.len());
+                state.show_results(Some(results));
+            }
+            Command::SaveQuery { name, tags, force } => {
+                let Some(dir) = state.project_queries.clone().or_else(library::queries_dir) else {
+                    state.status = "No config directory to save queries in".into();
+                    return Ok(());
+                };
+                let text = state.buffer().text();
+                state.status = if text.trim().is_empty() {
+                    "Nothing to save, the buffer is empty".into()
+                } else {
+                    match library::save(&dir, &name, &tags, &text, force) {
+                        Ok(path) => format!("Saved query {name} to \"{}\"", path.display()),
+                        Err(err) => err,
+                    }
+                };
+            }
+            Command::Prepare(name) => {
+                if offline(state) {
+                    return Ok(());
+                }
+                let text = state.buffer().text();
+                let Some(statement) = statements::split(&text)
+                    .into_iter()
+                    .rfind(|statement| !statement.trim().is_empty())
+                else {
+                    state.status = "Nothing to prepare".into();
+                    return Ok(());
+                };
+                let statement = vars::substitute(statement, &state.vars);
+                let statement = statement.trim().trim_end_matches(';');
+                // Named parameters become positional ones, in the order they
+                // are asked for on :execute.
+                let (body, labels) = match params::find(statement) {
+                    Some(query) => (query.positional(), query.labels),
+                    None => (statement.to_string(), Vec::new()),
+                };
+                let pool = &state.session.pool;
+                let exists = sqlx::query_scalar::<_, bool>(
+                    "SELECT EXISTS (SELECT FROM pg_prepared_statements WHERE name = $1)",
+                )
+                .bind(&name)
+                .fetch_one(pool)
+                .await;
+                let mut sql = Vec::new();
+                match exists {
+                    Ok(true) => sql.push(format!("DEALLOCATE {name}")),
+                    Ok(false) => {}
+                    Err(err) => {
+                        state.status = format!("Failed to prepare {name}: {err}");
+                        return Ok(());
+                    }
+                }
+                sql.push(format!("PREPARE {name} AS {body}"));
+                for sql in sql {
+                    let started = Instant::now();
+                    let outcome = sqlx::raw_sql(&sql).execute(&state.session.pool).await;
+                    let audited = match &outcome {
+                        Ok(done) => Ok(done.rows_affected()),
+                        Err(err) => Err(err.to_string()),
+                    };
+                    state.audit(&sql, &[], started.elapsed(), audited);
+                    match outcome {
+                        Ok(_) => state.session.record(&sql),
+                        Err(err) => {
+                            state.status = format!("Failed to prepare {name}: {err}");
+                            return Ok(());
+                        }
+                    }
+                }
+                state.status = match labels.len() {
+                    0 => format!("Prepared {name}, :execute {name} to run it"),
+                    1 => format!("Prepared {name} with 1 parameter"),
+                    n => format!("Prepared {name} with {n} parameters"),
+                };
+                state.prepared.insert(name, labels);
+            }
+            Command::Execute { name, args } => {
+                let args = match args {
+                    Some(args) => args,
+                    None => {
+                        let types = sqlx::query_scalar::<_, Vec<String>>(
+                            "SELECT parameter_types::text[] FROM pg_prepared_statements \
+                             WHERE name = $1",
+                        )
+                        .bind(&name)
+                        .fetch_optional(&state.session.pool)
+                        .await;
+                        let types = match types {
+                            Ok(Some(types)) => types,
+                            Ok(None) => {
+                                state.status =
+                                    format!("No statement {name}, :prepare {name} first");
+                                return Ok(());
+                            }
+                            Err(err) => {
+                                state.status = format!("Failed to look up {name}: {err}");
+                                return Ok(());
+                            }
+                        };
+                        let labels = state.prepared.get(&name).cloned().unwrap_or_default();
+                        let hint = Line::styled(
+                            "Enter to bind, \\N for NULL, Esc to cancel",
+                            Style::default().fg(Color::DarkGray),
+                        );
+                        let mut values = Vec::new();
+                        for (index, ty) in types.iter().enumerate() {
+                            let label = labels
+                                .get(index)
+                                .cloned()
+                                .unwrap_or_else(|| format!("${}", index + 1));
+                            let previous = state.binds.get(&label).cloned().unwrap_or_default();
+                            let title = format!("{name} {label} ({ty})");
+                            let Some(value) =
+                                prompt(terminal, &title, hint.clone(), false, previous)?
+                            else {
+                                state.status = "Execute cancelled".into();
+                                return Ok(());
+                            };
+                            state.binds.insert(label, value.clone());
+                            values.push(match value.as_str() {
+                                "\\N" => "NULL".to_string(),
+                                value => statements::quote_literal(value),
+                            });
+                        }
+                        values.join(", ")
+                    }
+                };
+                let sql = if args.is_empty() {
+                    format!("EXECUTE {name}")
+                } else {
+                    format!("EXECUTE {name}({args})")
+                };
+                handle_command(Command::RunQuery(sql), state, terminal).await?;
+            }
+            Command::Queries => {
+                let mut entries = Vec::new();
+                if let Some(dir) = &state.project_queries {
+                    match Library::load(dir) {
+                        Ok(project) => {
+                            entries.extend(project.into_iter().map(|entry| library::Entry {
+                                project: true,
+                                ..entry
+                            }))
+                        }
+                        Err(err) => {
+                            state.status = format!("Failed to read the project's queries: {err}");
+                            return Ok(());
+                        }
+                    }
+                }
+                let global = match library::queries_dir() {
+                    Some(dir) => Library::load(&dir),
+                    None => Ok(Vec::new()),
+                };
+                match global {
+                    Ok(global) => {
+                        entries.extend(global);
+                        state.library = Some(Library::new(entries));
+                        state.focus = Pane::Results;
+                    }
+                    Err(err) => state.status = format!("Failed to read saved queries: {err}"),
+                }
+            }
+            Command::SetVar {
+                name,
+                value: Some(value),
+            } => {
+                state.status = format!("{name} = {value}");
+                state.vars.insert(name, value);
+            }
+            Command::SetVar { name, value: None } => {
+                state.status = match state.vars.remove(&name) {
+                    Some(_) => format!("Unset {name}"),
+                    None => format!("No variable {name}"),
+                };
+            }
+            Command::Redact(None) if state.redactions.is_empty() => {
+                state.status = "Nothing is redacted, :redact <column> to mask one".into();
+            }
+            Command::Redact(None) => {
+                state.status = format!("Redacted: {}", state.redactions.patterns().join(", "));
+            }
+            Command::Redact(Some(pattern)) => {
+                state.status = if state.redact(&pattern) {
+                    format!("Redacted {pattern}")
+                } else {
+                    format!("{pattern} is already redacted")
+                };
+            }
+            Command::Unredact(pattern) => {
+                state.status = if state.redactions.remove(&pattern) {
+                    format!("Unredacted {pattern}")
+                } else {
+                    format!("{pattern} isn't redacted")
+                };
+            }
+            Command::Compare(targets) => {
+                let query = vars::substitute(&state.buffer().text(), &state.vars);
+                let query = query.trim();
+                if query.is_empty() {
+                    state.status = "Nothing to compare".into();
+                    return Ok(());
+                }
+                if statements::is_write(query) {
+                    state.status = "Only queries that don't write can be compared".into();
+                    return Ok(());
+                }
+                if params::find(query).is_some() {
+                    state.status = "Queries with parameters can't be compared".into();
+                    return Ok(());
+                }
+                let mut results = Vec::new();
+                for target in &targets {
+                    state.status = format!("Running the query on {target}…");
+                    terminal.draw(|f| draw_ui(f, state))?;
+                    match compare_on(terminal, target, query).await {
+                        Ok(result) => results.push(result),
+                        Err(err) => {
+                            state.status = format!("Failed to run the query on {target}: {err}");
+                            return Ok(());
+                        }
+                    }
+                }
+                let [(name_a, a), (name_b, b)] = &results[..] else {
+                    return Ok(());
+                };
+                let compare = Compare::new([name_a, name_b], a, b, &state.redactions);
+                state.status = match compare.differing() {
+                    0 => format!("{name_a} and {name_b} match, {} rows", a.rows.len()),
+                    1 => "1 row differs".into(),
+                    rows => format!("{rows} rows differ"),
+                };
+                state.show_results(None);
+                state.compare = Some(compare);
+                state.focus = Pane::Results;
+            }
+            Command::AsOf(AsOf::Close) => {
+                state.status = match state.snapshot.take() {
+                    Some(snapshot) => {
+                        let id = snapshot.id.clone();
+                        match snapshot.close().await {
+                            Ok(()) => format!("Let go of snapshot {id}"),
+                            Err(err) => format!("Failed to close snapshot {id}: {err}"),
+                        }
+                    }
+                    None => "No snapshot, :asof to take one".into(),
+                };
+            }
+            Command::AsOf(AsOf::Run) => {
+                let query = vars::substitute(&state.buffer().text(), &state.vars);
+                let query = query.trim();
+                let Some(snapshot) = &mut state.snapshot else {
+                    state.status = "No snapshot, :asof to take one".into();
+                    return Ok(());
+                };
+                if query.is_empty() {
+                    state.status = "Nothing to run".into();
+                    return Ok(());
+                }
+                if params::find(query).is_some() {
+                    state.status = "Queries with parameters can't run against a snapshot".into();
+                    return Ok(());
+                }
+                let started = Instant::now();
+                let outcome = snapshot.query(query).await;
+                let elapsed = started.elapsed();
+                let as_of = snapshot.describe();
+                match outcome {
+                    Ok((results, rows)) if results.is_empty() => {
+                        state.status = format!("Query ran as of {as_of}, {rows} rows affected");
+                    }
+                    Ok((results, _)) => {
+                        state.status = match results.as_slice() {
+                            [(_, only)] => {
+                                format!("Query ran as of {as_of}, {} rows", only.rows.len())
+                            }
+                            sets => format!("Query ran as of {as_of}, {} result sets", sets.len()),
+                        };
+                        state.show_batch(results);
+                        state.results_as_of = Some(as_of);
+                        state.focus = Pane::Results;
+                    }
+                    Err(err) if db::session::is_connection_error(&err) => {
+                        tracing::warn!(?elapsed, error = %err, "lost the snapshot");
+                        state.snapshot = None;
+                        state.status = format!("Lost the snapshot with its connection: {err}");
+                    }
+                    Err(err) => {
+                        tracing::warn!(?elapsed, error = %err, "query as of a snapshot failed");
+                        state.status = format!("Failed to run query: {err}");
+                    }
+                }
+            }
+            Command::AsOf(verb) => {
+                let options = state.session.options.clone();
+                let taken = match &verb {
+                    AsOf::Import(id) => asof::Snapshot::import(&options, id).await,
+                    _ => asof::Snapshot::take(&options).await,
+                };
+                match taken {
+                    Ok(snapshot) => {
+                        if let Some(old) = state.snapshot.take() {
+                            let _ = old.close().await;
+                        }
+                        state.status = format!(
+                            "Pinned {}, :asof run runs the buffer against it",
+                            snapshot.describe()
+                        );
+                        state.snapshot = Some(snapshot);
+                    }
+                    Err(err) => state.status = format!("Failed to take a snapshot: {err}"),
+                }
+            }
+            Command::RowDiff(rows) => {
+                let Some(results) = &state.results else {
+                    state.status = "No results".into();
+                    return Ok(());
+                };
+                let diff = rowdiff::pick(&state.grid, rows)
+                    .and_then(|rows| RowDiff::new(results, rows, &state.redactions));
+                match diff {
+                    Ok(diff) => {
+                        state.status = match diff.differing() {
+                            0 => "The rows are the same".into(),
+                            1 => "1 column differs".into(),
+                            columns => format!("{columns} columns differ"),
+                        };
+                        state.grid.selection = None;
+                        state.row_diff = Some(diff);
+                        state.focus = Pane::Results;
+                    }
+                    Err(err) => state.status = err,
+                }
+            }
+            Command::Vars if state.vars.is_empty() => {
+                state.status = "No variables, :setvar <name> <value> to set one".into();
+            }
+            Command::Vars => {
+                let width = state.vars.keys().map(|name| name.chars().count()).max();
+                let width = width.unwrap_or(0);
+                state.popup = Some(Popup::Text {
+                    title: "Variables".into(),
+                    lines: state
+                        .vars
+                        .iter()
+                        .map(|(name, value)| format!("{name:<width$}  {value}"))
+                        .collect(),
+                });
+            }
+            Command::SaveResult { name, force } => {
+                let Some(results) = &state.results else {
+                    state.status = "No results to save".into();
+                    return Ok(());
+                };
+                let Some(dir) = snapshot::results_dir() else {
+                    state.status = "No state directory to keep results in".into();
+                    return Ok(());
+                };
+                let snapshot = Snapshot {
+                    query: state.last_query.clone(),
+                    results: results.clone(),
+                };
+                state.status = match snapshot.save(&dir, &name, force) {
+                    Ok(_) => format!("Saved {} rows as {name}", results.rows.len()),
+                    Err(err) => err,
+                };
+            }
+            Command::LoadResult(name) => {
+                let Some(dir) = snapshot::results_dir() else {
+                    state.status = "No state directory to keep results in".into();
+                    return Ok(());
+                };
+                match Snapshot::load(&dir, &name) {
+                    Ok(Snapshot { query, results }) => {
+                        state.status = format!("Loaded {name}, {} rows", results.rows.len());
+                        state.last_query = query;
+                        state.show_results(Some(results));
+                        state.focus = Pane::Results;
+                    }
+                    Err(err) => state.status = err,
+                }
+            }
+            Command::Join(spec) => {
+                let Some(dir) = snapshot::results_dir() else {
+                    state.status = "No state directory to keep results in".into();
+                    return Ok(());
+                };
+                let load = |name: &str| match name {
+                    "%" => state
+                        .results
+                        .clone()
+                        .ok_or_else(|| "No results".to_string()),
+                    name => Snapshot::load(&dir, name).map(|snapshot| snapshot.results),
+                };
+                let joined = load(&spec.left)
+                    .and_then(|left| Ok((left, load(&spec.right)?)))
+                    .and_then(|(left, right)| join::join(&spec, &left, &right));
+                match joined {
+                    Ok(joined) => {
+                        let on = match &spec.on {
+                            (a, b) if a == b => a.clone(),
+                            (a, b) => format!("{a}={b}"),
+                        };
+                        state.status = format!(
+                            "Joined {} and {} on {on}, {} rows",
+                            spec.left,
+                            spec.right,
+                            joined.rows.len()
+                        );
+                        state.last_query = None;
+                        state.show_results(Some(joined));
+                        state.focus = Pane::Results;
+                    }
+                    Err(err) => state.status = err,
+                }
+            }
+            Command::ListResults => {
+                let names = match snapshot::results_dir() {
+                    Some(dir) => snapshot::list(&dir),
+                    None => Ok(Vec::new()),
+                };
+                match names {
+                    Ok(names) if names.is_empty() => {
+                        state.status = "No saved results, :result save <name> to save some".into();
+                    }
+                    Ok(names) => {
+                        state.popup = Some(Popup::Text {
+                            title: "Saved results".into(),
+                            lines: names,
+                        });
+                    }
+                    Err(err) => state.status = format!("Failed to list saved results: {err}"),
+                }
+            }
+            Command::Jobs => {
+                state.queue.shown = false;
+                state.quickfix.shown = false;
+                state.jobs.shown = true;
+                state.focus = Pane::Results;
+            }
+            Command::Queue => {
+                if state.dry_run {
+                    state.status = "A dry run doesn't queue, :set nodryrun first".into();
+                    return Ok(());
+                }
+                let text = vars::substitute(&state.buffer().text(), &state.vars);
+                if statements::split(&text).is_empty() {
+                    state.status = "Nothing to queue".into();
+                    return Ok(());
+                }
+                if params::find(&text).is_some() {
+                    state.status = "A queued query can't ask for parameters, :run it".into();
+                    return Ok(());
+                }
+                if statements::is_write(&text)
+                    && !confirm_write(state, terminal, "The queued query writes")?
+                {
+                    state.status = "Not queued".into();
+                    return Ok(());
+                }
+                let (id, ahead) = state.queue.push(text);
+                run_queued(state);
+                let running = state
+                    .queue
+                    .running
+                    .as_ref()
+                    .is_some_and(|(_, queued)| queued.id == id);
+                state.status = match ahead {
+                    _ if running => format!("Running queued query #{id} in the background"),
+                    0 => format!("Queued #{id}, waiting for the connection"),
+                    1 => format!("Queued #{id}, 1 ahead of it"),
+                    ahead => format!("Queued #{id}, {ahead} ahead of it"),
+                };
+            }
+            Command::Queued => {
+                state.jobs.shown = false;
+                state.quickfix.shown = false;
+                state.queue.shown = true;
+                state.focus = Pane::Results;
+            }
+            Command::OpenQuickfix => {
+                state.queue.shown = false;
+                state.jobs.shown = false;
+                state.quickfix.shown = true;
+                state.focus = Pane::Results;
+            }
+            Command::CloseQuickfix => state.quickfix.shown = false,
+            Command::Diagnostics => {
+                let buffer = state.buffer();
+                let text = buffer.text();
+                let entry = |row, col, text| Entry {
+                    target: quickfix::Target::Position {
+                        buffer: buffer.name.clone(),
+                        row,
+                        col,
+                    },
+                    text,
+                };
+                let mut entries: Vec<Entry> = lint::lint(&text, &state.schema, &state.search_path)
+                    .into_iter()
+                    .map(|diagnostic| {
+                        let (row, range) = diagnostic.locate(&text);
+                        let col = buffer.lines[row][..range.start].chars().count();
+                        entry(row, col, diagnostic.message)
+                    })
+                    .collect();
+                if let Some(lsp) = &state.lsp {
+                    entries.extend(lsp.diagnostics(buffer).iter().map(|diagnostic| {
+                        entry(diagnostic.row, diagnostic.start, diagnostic.message.clone())
+                    }));
+                }
+                entries.sort_by_key(|entry| match entry.target {
+                    quickfix::Target::Position { row, col, .. } => (row, col),
+                    _ => (0, 0),
+                });
+                if entries.is_empty() {
+                    state.status = "No problems found".into();
+                    return Ok(());
+                }
+                let title = format!("diagnostics of {}", buffer.name);
+                state.quickfix.set(title, entries);
+                handle_command(Command::OpenQuickfix, state, terminal).await?;
+            }
+            Command::Quickfix(go) => {
+                let (item, entry) = match state.quickfix.go(go) {
+                    Ok((item, entry)) => (item, entry.clone()),
+                    Err(err) => {
+                        state.status = err;
+                        return Ok(());
+                    }
+                };
+                // Going there says why if it can't.
+                let arrived = match entry.target {
+                    quickfix::Target::Object { name, definition } => {
+                        let (command, buffer) = if definition {
+                            (
+                                Command::Definition(name.clone()),
+                                format!("[definition] {name}"),
+                            )
+                        } else {
+                            (Command::Ddl(name.clone()), format!("[ddl] {name}"))
+                        };
+                        handle_command(command, state, terminal).await?;
+                        state.buffer().name == buffer
+                    }
+                    quickfix::Target::Line { path, line } => {
+                        let expanded = config::expand_home(&path);
+                        handle_command(Command::Edit(path), state, terminal).await?;
+                        let arrived = state.buffer().path.as_deref() == Some(expanded.as_path());
+                        if arrived {
+                            let buffer = state.buffer_mut();
+                            buffer.cursor = Cursor { row: line, col: 0 };
+                            buffer.clamp_cursor(false);
+                            buffer.first_non_blank();
+                        }
+                        arrived
+                    }
+                    quickfix::Target::Position { buffer, row, col } => {
+                        match state.buffers.iter().position(|b| b.name == buffer) {
+                            Some(index) => {
+                                state.current = index;
+                                let buffer = state.buffer_mut();
+                                buffer.cursor = Cursor { row, col };
+                                buffer.clamp_cursor(false);
+                                true
+                            }
+                            None => {
+                                state.status = format!("{buffer} is closed");
+                                false
+                            }
+                        }
+                    }
+                };
+                if arrived {
+                    state.focus = Pane::Editor;
+                    state.status = format!("({item} of {}) {}", state.quickfix.len(), entry.text);
+                }
+            }
+            Command::CancelJob(id) => match state.jobs.cancel(id) {
+                Ok(job) => {
+                    let kind = job.kind;
+                    state.status = format!("Cancelled job {id}");
+                    // The connection is still busy with whatever the job
+                    // sent, until the server is told to stop it.
+                    if kind.on_session()
+                        && let Err(err) = state.session.cancel().await
+                    {
+                        state.status =
+                            format!("Cancelled job {id}, failed to cancel its query: {err}");
+                    }
+                    if state
+                        .queue
+                        .running
+                        .as_ref()
+                        .is_some_and(|(job, _)| *job == id)
+                    {
+                        state.queue.running = None;
+                        run_queued(state);
+                    }
+                }
+                Err(err) => state.status = err,
+            },
+            Command::Browse { .. } if state.backend.is_some() => {
+                state.status = ":browse is only for Postgres".into();
+            }
+            Command::Browse { table, sort } => {
+                match Browser::open(&state.session.pool, &table, sort, state.page_size).await {
+                    Ok(browser) => {
+                        if preview_page(state, terminal, &browser, PageTo::Number(1)).await? {
+                            browse_from_start(state, browser).await;
+                            state.focus = Pane::Results;
+                        }
+                    }
+                    Err(err) if db::session::is_connection_error(&err) => start_reconnect(state),
+                    Err(err) => state.status = format!("Failed to browse {table}: {err}"),
+                }
+            }
+            Command::Filter(column) => {
+                let Some(browser) = &state.browser else {
+                    state.status = "Not browsing a table, :browse <table> first".into();
+                    return Ok(());
+                };
+                let mut browser = browser.clone();
+                let previous = browser
+                    .filters
+                    .iter()
+                    .find(|filter| filter.column == column)
+                    .map(Filter::text)
+                    .unwrap_or_default();
+                let hint = Line::styled(
+                    "= != < <= > >= ~ (contains) null !null, empty for none",
+                    Style::default().fg(Color::DarkGray),
+                );
+                let title = format!("Filter {column}");
+                let Some(text) = prompt(terminal, &title, hint, false, previous)? else {
+                    return Ok(());
+                };
+                let filter = if text.trim().is_empty() {
+                    None
+                } else {
+                    match Filter::parse(&column, &text) {
+                        Ok(filter) => Some(filter),
+                        Err(err) => {
+                            state.status = err;
+                            return Ok(());
+                        }
+                    }
+                };
+                browser.filter(&column, filter);
+                if preview_page(state, terminal, &browser, PageTo::Number(1)).await? {
+                    browse_from_start(state, browser).await;
+                }
+            }
+            Command::SortBy(column) => {
+                let Some(browser) = &state.browser else {
+                    state.status = "Not browsing a table, :browse <table> first".into();
+                    return Ok(());
+                };
+                let mut browser = browser.clone();
+                browser.sort_by(&column);
+                let page = PageTo::Number(browser.page + 1);
+                if !preview_page(state, terminal, &browser, page).await? {
+                    return Ok(());
+                }
+                match browser.fetch(&state.session.pool, page).await {
+                    Ok((browser, results)) => state.show_page(browser, results),
+                    Err(err) if db::session::is_connection_error(&err) => start_reconnect(state),
+                    Err(err) => state.status = format!("Failed to sort by {column}: {err}"),
+                }
+            }
+            Command::Unfilter => match &state.browser {
+                Some(browser) if !browser.filters.is_empty() => {
+                    let mut browser = browser.clone();
+                    browser.unfilter();
+                    if preview_page(state, terminal, &browser, PageTo::Number(1)).await? {
+                        browse_from_start(state, browser).await;
+                    }
+                }
+                _ => state.status = "No filters".into(),
+            },
+            Command::LookUpSignatures(_) if state.backend.is_some() => {}
+            Command::LookUpSignatures(name) => {
+                let pool = state.session.pool.clone();
+                let messages = state.messages.clone();
+                tokio::spawn(async move {
+                    match db::catalog::functions(&pool, &name).await {
+                        Ok(functions) => {
+                            let _ = messages.send(Message::Signatures(name, functions));
+                        }
+                        Err(err) => tracing::debug!(error = %err, "failed to look up {name}"),
+                    }
+                });
+            }
+            Command::Hover(target) => {
+                if let Some(lsp) = &mut state.lsp {
+                    lsp.sync_now(&state.buffers);
+                    match lsp.hover(&state.buffers[state.current]).await {
+                        Ok(Some(text)) => {
+                            state.popup = Some(Popup::Text {
+                                title: lsp.name.clone(),
+                                lines: text.lines().map(String::from).collect(),
+                            });
+                            return Ok(());
+                        }
+                        Ok(None) => {}
+                        Err(err) => tracing::debug!(error = %err, "hover failed"),
+                    }
+                }
+                if state.backend.is_some() {
+                    state.status = "Nothing to say about that without Postgres".into();
+                    return Ok(());
+                }
+                match target.describe(&state.session.pool).await {
+                    Ok(Ok((popup, table))) => {
+                        state.popup = Some(popup);
+                        state.described = table;
+                    }
+                    Ok(Err(why)) => state.status = why,
+                    Err(err) if db::session::is_connection_error(&err) => start_reconnect(state),
+                    Err(err) => state.status = format!("Failed to look it up: {err}"),
+                }
+            }
+            Command::Complete => {
+                let buffer = state.buffer();
+                if let Some((start, items)) =
+                    completion::enum_labels(&state.schema, buffer.line(), buffer.cursor.col)
+                    && !items.is_empty()
+                {
+                    state.completion = completion::Menu::at(items, start);
+                    return Ok(());
+                }
+                let items = match &mut state.lsp {
+                    Some(lsp) => {
+                        lsp.sync_now(&state.buffers);
+                        lsp.completion(&state.buffers[state.current]).await
+                    }
+                    None => {
+                        let buffer = state.buffer();
+                        let line = buffer.line();
+                        let start = completion::word_start(line, buffer.cursor.col);
+                        let prefix: String = line
+                            .chars()
+                            .skip(start)
+                            .take(buffer.cursor.col - start)
+                            .collect();
+                        if prefix.is_empty() {
+                            Ok(Vec::new())
+                        } else {
+                            Ok(completion::from_schema(&state.schema, &prefix))
+                        }
+                    }
+                };
+                match items {
+                    // Typing on while waiting leaves the menu unwanted.
+                    Ok(_) if state.mode != Mode::Insert => {}
+                    Ok(items) => match completion::Menu::new(items, state.buffer()) {
+                        Some(menu) => state.completion = Some(menu),
+                        None => state.status = "No completions".into(),
+                    },
+                    Err(err) => state.status = err,
+                }
+            }
+            Command::ColumnInfo => {
+                let Some(column) = state
+                    .results
+                    .as_ref()
+                    .and_then(|results| results.columns.get(state.grid.col))
+                else {
+                    return Ok(());
+                };
+                let origin = match column.origin {
+                    Some((table, attnum)) if state.backend.is_none() => {
+                        match db::catalog::origin(&state.session.pool, table, attnum).await {
+                            Ok(origin) => origin,
+                            Err(err) if db::session::is_connection_error(&err) => {
+                                start_reconnect(state);
+                                return Ok(());
+                            }
+                            Err(err) => {
+                                state.status = format!("Failed to look up the column: {err}");
+                                return Ok(());
+                            }
+                        }
+                    }
+                    _ => None,
+                };
+                let lines = match origin {
+                    Some(origin) => hover::column_lines(&origin),
+                    None => vec![
+                        format!("type      {}", column.ty),
+                        "from      computed by the query".into(),
+                    ],
+                };
+                state.popup = Some(Popup::Text {
+                    title: format!("Column {}", column.name),
+                    lines,
+                });
+            }
+            Command::EditCell => {
+                let Some(results) = &state.results else {
+                    return Ok(());
+                };
+                let (row, col) = (state.grid.row, state.grid.col);
+                if row >= results.rows.len() {
+                    return Ok(());
+                }
+                let edits = match state.edits.take() {
+                    Some(edits) => edits,
+                    None => match Target::resolve(&state.session.pool, results).await {
+                        Ok(Ok(target)) => Edits::new(target),
+                        Ok(Err(why)) => {
+                            state.status = why;
+                            return Ok(());
+                        }
+                        Err(err) if db::session::is_connection_error(&err) => {
+                            start_reconnect(state);
+                            return Ok(());
+                        }
+                        Err(err) => {
+                            state.status = format!("Failed to look up the table: {err}");
+                            return Ok(());
+                        }
+                    },
+                };
+                let checked = edits.check(results, col);
+                let title = format!("{} of {}", results.columns[col].name, edits.target.table);
+                let value = results
+                    .rows
+                    .cell(row, col)
+                    .unwrap_or(NULL_INPUT)
+                    .to_string();
+                state.edits = Some(edits);
+                if let Err(err) = checked {
+                    state.status = err;
+                    return Ok(());
+                }
+                // An enum takes one of its labels, so those are offered.
+                let mut labels = match results.columns[col].origin {
+                    Some((table, attnum)) => {
+                        db::catalog::column_labels(&state.session.pool, table, attnum)
+                            .await
+                            .unwrap_or_default()
+                    }
+                    None => Vec::new(),
+                };
+                let hint = Line::styled(
+                    format!("{NULL_INPUT} for NULL, Esc leaves it as it is"),
+                    Style::default().fg(Color::DarkGray),
+                );
+                let value = if labels.is_empty() {
+                    prompt(terminal, &title, hint, false, value)?
+                } else {
+                    labels.push(NULL_INPUT.into());
+                    pick(terminal, &title, hint, &labels, &value)?
+                };
+                let Some(value) = value else {
+                    return Ok(());
+                };
+                let value = (value != NULL_INPUT).then_some(value);
+                if let (Some(edits), Some(results)) = (&mut state.edits, &mut state.results)
+                    && let Err(err) = edits.change(results, row, col, value)
+                {
+                    state.status = err;
+                    return Ok(());
+                }
+                state.mark_edits();
+                if let Some(edits) = &state.edits {
+                    state.status = match edits.len() {
+                        1 => "1 cell changed, :apply to write it".into(),
+                        cells => format!("{cells} cells changed, :apply to write them"),
+                    };
+                }
+            }
+            Command::ApplyEdits => {
+                let (Some(edits), Some(results)) = (&state.edits, &state.results) else {
+                    state.status = "No changed cells".into();
+                    return Ok(());
+                };
+                if edits.is_empty() {
+                    state.status = "No changed cells".into();
+                    return Ok(());
+                }
+                if state.dry_run {
+                    state.status =
+                        "Changes aren't applied on a dry run, :set nodryrun first".into();
+                    return Ok(());
+                }
+                let table = edits.target.table.clone();
+                let what = format!("Write {} changed cells to {table}?", edits.len());
+                let sql = edits.to_sql(results);
+                let Some(sql) = preview_sql(terminal, state.dialect, &what, sql).await? else {
+                    return Ok(());
+                };
+                if !confirm_write(state, terminal, &format!("The changes write to {table}"))? {
+                    state.status = "Changes not applied".into();
+                    return Ok(());
+                }
+                let started = Instant::now();
+                let outcome = sqlx::raw_sql(&sql).execute(&state.session.pool).await;
+                let audited = match &outcome {
+                    Ok(done) => Ok(done.rows_affected()),
+                    Err(err) => Err(err.to_string()),
+                };
+                state.audit(&sql, &[], started.elapsed(), audited);
+                state.transaction = state.transaction.after(&sql, outcome.is_ok());
+                match outcome {
+                    Ok(done) => {
+                        state.edits = None;
+                        state.mark_edits();
+                        state.status = format!("Updated {} rows of {table}", done.rows_affected());
+                    }
+                    Err(err) if db::session::is_connection_error(&err) => start_reconnect(state),
+                    Err(err) => state.status = format!("Failed to write the changes: {err}"),
+                }
+            }
+            Command::DiscardEdits => match (state.edits.take(), &mut state.results) {
+                (Some(edits), Some(results)) if !edits.is_empty() => {
+                    edits.discard(results);
+                    state.mark_edits();
+                    state.status = match edits.len() {
+                        1 => "1 change discarded".into(),
+                        cells => format!("{cells} changes discarded"),
+                    };
+                }
+                _ => state.status = "No changed cells".into(),
+            },
+            Command::TurnPage(to) => {
+                let Some(browser) = &state.browser else {
+                    state.status = "Not browsing a table, :browse <table> first".into();
+                    return Ok(());
+                };
+                if browser.target(to) == browser.page {
+                    return Ok(());
+                }
+                match browser.fetch(&state.session.pool, to).await {
+                    Ok((_, results)) if results.rows.is_empty() => {
+                        state.status = "No rows past the last page".into();
+                    }
+                    Ok((browser, results)) => {
+                        let rows = results.rows.len();
+                        state.show_page(browser, results);
+                        if to == PageTo::Last {
+                            state.grid.row = rows.saturating_sub(1);
+                        }
+                    }
+                    Err(err) if db::session::is_connection_error(&err) => start_reconnect(state),
+                    Err(err) => state.status = format!("Failed to turn the page: {err}"),
+                }
+            }
+            Command::Page => match &state.results {
+                Some(results) => {
+                    let mut table = Vec::new();
+                    export::write_table(&state.redactions.apply(results), &mut table)?;
+                    if let Err(err) = page(terminal, &table).await? {
+                        state.status = err;
+                    }
+                }
+                None => state.status = "No results".into(),
+            },
+            Command::NextBuffer => state.current = (state.current + 1) % state.buffers.len(),
+            Command::PreviousBuffer => {
+                state.current = (state.current + state.buffers.len() - 1) % state.buffers.len()
+            }
+            Command::DeleteBuffer { force } => {
+                if !force && state.buffer().unsaved() {
+                    state.status = "No write since last change (add ! to override)".into();
+                    return Ok(());
+                }
+                state.buffers.remove(state.current);
+                if state.buffers.is_empty() {
+                    state.buffers.push(Buffer::new("[query]"));
+                }
+                state.current = state.current.min(state.buffers.len() - 1);
+            }
+            Command::Set { option, value } => set_option(state, &option, value.as_deref()).await,
+            Command::Connect(url) => {
+                state.status = format!("Connecting to {url}…");
+                terminal.draw(|f| draw_ui(f, state))?;
+                match db::backend::Backend::connect(&url).await {
+                    Ok(backend) => {
+                        tracing::info!(to = %backend.name(), "attached backend");
+                        state.status = format!(
+                            "Connected to {}, :disconnect to go back to Postgres",
+                            backend.name()
+                        );
+                        #[cfg(feature = "lua")]
+                        if let Some(lua) = &state.lua {
+                            lua.on_connect(&[("backend", backend.name())]);
+                        }
+                        state.backend = Some(backend);
+                        state.show_results(None);
+                    }
+                    Err(err) => state.status = format!("Failed to connect: {err}"),
+                }
+            }
+            Command::SwitchDatabase(database) => {
+                if state.transaction != Transaction::Idle {
+                    state.status = "Commit or roll back the transaction first".into();
+                    return Ok(());
+                }
+                if state.jobs.on_session() {
+                    state.status = "Jobs are still running on this connection, see :jobs".into();
+                    return Ok(());
+                }
+                state.status = format!("Connecting to {database}…");
+                terminal.draw(|f| draw_ui(f, state))?;
+                match state.session.switch(&database).await {
+                    Ok(session) => {
+                        let old = std::mem::replace(&mut state.session, session);
+                        old.pool.close().await;
+                        spawn_health_check(state.session.clone(), state.messages.clone());
+                        let connection = db::profile_key(&state.session.options);
+                        tracing::info!(to = %connection, "switched database");
+                        if let Some(audit) = &mut state.audit {
+                            audit.set_connection(connection);
+                        }
+                        state.show_results(None);
+                        state.schema = Default::default();
+                        state.schema_notice = None;
+                        state.capabilities = Default::default();
+                        load_schema(state);
+                        check_search_path(state);
+                        check_capabilities(state);
+                        state.signatures.clear();
+                        state.status = format!("Connected to {database}");
+                        #[cfg(feature = "lua")]
+                        if let Some(lua) = &state.lua {
+                            lua.on_connect(&connect_info(&state.session));
+                        }
+                    }
+                    Err(err) => state.status = format!("Failed to connect to {database}: {err}"),
+                }
+            }
+            Command::Disconnect if state.demo => {
+                state.status = "There is no Postgres to go back to in the demo".into();
+            }
+            Command::Disconnect => match state.backend.take() {
+                Some(backend) => {
+                    state.show_results(None);
+                    state.status = format!("Disconnected from {}", backend.name());
+                }
+                None => state.status = "Not connected to anything but Postgres".into(),
+            },
+            Command::KillSwitch => {
+                let jobs = state.jobs.cancel_all();
+                let queued = state.queue.clear();
+                let mut done = Vec::new();
+                if jobs > 0 {
+                    done.push(format!(
+                        "cancelled {jobs} job{}",
+                        if jobs == 1 { "" } else { "s" }
+                    ));
+                }
+                if queued > 0 {
+                    done.push(format!("dropped {queued} queued"));
+                }
+                if let Some(backend) = state.backend.take()
+                    && !state.demo
+                {
+                    done.push(format!("disconnected from {}", backend.name()));
+                }
+                // Ending the backend stops whatever it runs and rolls back
+                // its transaction, busy or not, before the pool lets go.
+                if !state.demo && !state.session.pool.is_closed() {
+                    if state.session.pid() != 0
+                        && let Err(err) = state.session.terminate().await
+                    {
+                        tracing::warn!(error = %err, "kill switch failed to end the backend");
+                        done.push(format!("failed to end the backend ({err})"));
+                    }
+                    if state.transaction != Transaction::Idle {
+                        done.push("rolled back the transaction".into());
+                    }
+                    state.session.pool.close().await;
+                    state.transaction = Transaction::Idle;
+                    state.connected = false;
+                    state.latency = None;
+                    done.push("disconnected, :reconnect to connect again".into());
+                }
+                tracing::warn!(jobs, queued, "kill switch");
+                state.status = match done.is_empty() {
+                    true => "Kill switch: nothing was running".into(),
+                    false => format!("Kill switch: {}", done.join(", ")),
+                };
+            }
+            Command::Reconnect if state.demo => {
+                state.status = "There is no Postgres to connect to in the demo".into();
+            }
+            Command::Reconnect if !state.session.pool.is_closed() => {
+                state.status = match state.connected {
+                    true => "Already connected".into(),
+                    false => "Not connected yet, still reconnecting…".into(),
+                };
+            }
+            Command::Reconnect => {
+                state.status = "Reconnecting…".into();
+                terminal.draw(|f| draw_ui(f, state))?;
+                match state.session.reopen().await {
+                    Ok(session) => {
+                        state.session = session;
+                        spawn_health_check(state.session.clone(), state.messages.clone());
+                        state.connected = true;
+                        state.status = "Reconnected".into();
+                        refresh_schema(state);
+                        check_search_path(state);
+                        check_capabilities(state);
+                    }
+                    Err(err) => state.status = format!("Failed to reconnect: {err}"),
+                }
+            }
+            Command::Tables => match &mut state.backend {
+                Some(backend) => match backend.tables().await {
+                    Ok(tables) => {
+                        state.status = format!("{} tables", tables.rows.len());
+                        state.show_results(Some(tables));
+                    }
+                    Err(err) => state.status = format!("Failed to list tables: {err}"),
+                },
+                None => {
+                    return handle_command(Command::Report(Report::Sizes(None)), state, terminal)
+                        .await;
+                }
+            },
+            Command::ConnInfo => match state.session.conninfo().await {
+                Ok(info) => {
+                    let mut results = ResultSet::new(vec![
+                        results::Column::new("setting", "text"),
+                        results::Column::new("value", "text"),
+                    ]);
+                    results.rows = info
+                        .into_iter()
+                        .map(|(label, value)| vec![Some(label.to_string()), Some(value)])
+                        .collect();
+                    state.show_results(Some(results));
+                    state.status = "Connection info".into();
+                }
+                Err(err) if db::session::is_connection_error(&err) => start_reconnect(state),
+                Err(err) => state.status = format!("Failed to get connection info: {err}"),
+            },
+            Command::Capabilities => {
+                if offline(state) {
+                    return Ok(());
+                }
+                state.capabilities = Capabilities::probe(&state.session.pool).await;
+                let mut results = ResultSet::new(vec![
+                    results::Column::new("capability", "text"),
+                    results::Column::new("available", "text"),
+                    results::Column::new("needed by", "text"),
+                ]);
+                results.rows = state
+                    .capabilities
+                    .report(state.session.server)
+                    .into_iter()
+                    .map(|row| row.map(Some).to_vec())
+                    .collect();
+                state.show_results(Some(results));
+                state.status = "Capabilities".into();
+            }
+            Command::Quit { force } => {
+                let modified = state.buffers.iter().position(Buffer::unsaved);
+                match modified {
+                    Some(index) if !force => {
+                        state.current = index;
+                        state.status = format!(
+                            "No write since last change for buffer \"{}\" (add ! to override)",
+                            state.buffer().name
+                        );
+                    }
+                    _ => state.is_running = false,
+                }
+            }
+            Command::None => {}
+            Command::Chain(cmds) => {
+                for cmd in cmds {
+                    handle_command(cmd, state, terminal).await?;
+                }
+            }
+        }
+        Ok(())
+    })
+}
+
+#[cfg(not(feature = "lua"))]
+fn lua_command(state: &mut State, _: Command) {
+    state.status = "Built without Lua, see the lua cargo feature".into();
+}
+
+#[cfg(feature = "lua")]
+fn lua_command(state: &mut State, cmd: Command) {
+    let Some(lua) = &state.lua else {
+        state.status = "No Lua, there is no $XDG_CONFIG_HOME/dbvi/init.lua".into();
+        return;
+    };
+    match cmd {
+        Command::Lua(code) => match lua.eval(&code) {
+            Ok(output) => state.status = output,
+            Err(err) => state.status = format!("Lua: {err}"),
+        },
+        Command::LuaAction { name, args } => lua.action(&name, &args),
+        _ => {}
+    }
+}
+
+/// `:export csv` and `:export table`, which need no plugin.
+/// `:migrate`: lists the project's migrations, or applies those pending or
+/// undoes the last one, each in a transaction of its own, showing a log of
+/// how it went. A dry run does it all in one transaction and rolls it back.
+async fn migrate(
+    state: &mut State,
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    action: migrate::Action,
+) -> io::Result<()> {
+    let dir = state
+        .migrations
+        .clone()
+        .unwrap_or_else(migrate::default_dir);
+    let migrations = match migrate::load(&dir) {
+        Ok(migrations) => migrations,
+        Err(err) => {
+            state.status = format!("Failed to read the migrations: {err}");
+            return Ok(());
+        }
+    };
+    let pool = state.session.pool.clone();
+    let applied = match migrate::applied(&pool).await {
+        Ok(applied) => applied,
+        Err(err) => {
+            state.status = format!("Failed to read {}: {err}", migrate::TABLE);
+            return Ok(());
+        }
+    };
+    if action == migrate::Action::Status {
+        let pending = migrate::pending(&migrations, &applied).len();
+        state.show_results(Some(migrate::status(&migrations, &applied)));
+        state.status = format!(
+            "{} applied, {pending} pending in {}",
+            applied.len(),
+            dir.display()
+        );
+        return Ok(());
+    }
+    if state.transaction != Transaction::Idle {
+        state.status = "Commit or roll back the transaction first".into();
+        return Ok(());
+    }
+    if state.jobs.on_session() {
+        state.status = "Jobs are still running on this connection, see :jobs".into();
+        return Ok(());
+    }
+    let commit = !state.dry_run;
+    let dry_run = if commit { "" } else { " -- dry run" };
+    let (direction, steps) = if action == migrate::Action::Up {
+        let pending = migrate::pending(&migrations, &applied);
+        if pending.is_empty() {
+            state.status = format!("Nothing to migrate, {} applied", applied.len());
+            return Ok(());
+        }
+        let s = if pending.len() == 1 { "" } else { "s" };
+        let what = format!("Apply {} migration{s}", pending.len());
+        if !confirm_write(state, terminal, &what)? {
+            state.status = "Migrate cancelled".into();
+            return Ok(());
+        }
+        let mut runner = match migrate::Runner::new(&pool, !commit).await {
+            Ok(runner) => runner,
+            Err(err) => {
+                state.status = format!("Failed to migrate: {err}");
+                return Ok(());
+            }
+        };
+        if let Err(err) = migrate::ensure_table(runner.connection()).await {
+            state.status = format!("Failed to create {}: {err}", migrate::TABLE);
+            return Ok(());
+        }
+        let mut steps = Vec::new();
+        for (i, migration) in pending.iter().enumerate() {
+            state.status = format!(
+                "Applying {} {} ({} of {})…",
+                migration.version,
+                migration.name,
+                i + 1,
+                pending.len()
+            );
+            terminal.draw(|f| draw_ui(f, state))?;
+            let step = migrate::apply(runner.connection(), migration).await;
+            state.audit(
+                &format!(
+                    "{} -- :migrate up {}{dry_run}",
+                    migration.up, migration.version
+                ),
+                &[],
+                step.elapsed,
+                step.outcome.clone().map(|()| 0),
+            );
+            let failed = step.outcome.is_err();
+            steps.push(step);
+            if failed {
+                break;
+            }
+        }
+        if let Err(err) = runner.finish().await {
+            state.status = format!("Failed to roll back the dry run: {err}");
+            return Ok(());
+        }
+        ("up", steps)
+    } else {
+        let Some(last) = applied.last() else {
+            state.status = "No migrations applied to undo".into();
+            return Ok(());
+        };
+        let Some(migration) = migrations.iter().find(|m| m.version == last.version) else {
+            state.status = format!(
+                "Migration {} {} isn't in {} to undo it with",
+                last.version,
+                last.name,
+                dir.display()
+            );
+            return Ok(());
+        };
+        let Some(down) = &migration.down else {
+            state.status = format!(
+                "Migration {} {} has no -- migrate:down section",
+                migration.version, migration.name
+            );
+            return Ok(());
+        };
+        let what = format!("Undo migration {} {}?", migration.version, migration.name);
+        let Some(down) = preview_sql(terminal, state.dialect, &what, down.clone()).await? else {
+            return Ok(());
+        };
+        let migration = migrate::Migration {
+            down: Some(down),
+            ..migration.clone()
+        };
+        let mut runner = match migrate::Runner::new(&pool, !commit).await {
+            Ok(runner) => runner,
+            Err(err) => {
+                state.status = format!("Failed to migrate: {err}");
+                return Ok(());
+            }
+        };
+        let step = migrate::revert(runner.connection(), &migration).await;
+        state.audit(
+            &format!(
+                "{} -- :migrate down {}{dry_run}",
+                migration.down.as_deref().unwrap_or_default(),
+                migration.version
+            ),
+            &[],
+            step.elapsed,
+            step.outcome.clone().map(|()| 0),
+        );
+        if let Err(err) = runner.finish().await {
+            state.status = format!("Failed to roll back the dry run: {err}");
+            return Ok(());
+        }
+        ("down", vec![step])
+    };
+    state.show_results(Some(migrate::log(direction, &steps)));
+    let done = steps.iter().filter(|step| step.outcome.is_ok()).count();
+    let s = if done == 1 { "" } else { "s" };
+    state.status = match steps.last() {
+        Some(migrate::Step {
+            version,
+            name,
+            outcome: Err(err),
+            ..
+        }) => format!("Migration {version} {name} failed: {err}"),
+        _ if direction == "up" => format!("Applied {done} migration{s}"),
+        _ => format!("Undid {done} migration{s}"),
+    };
+    if done > 0 && steps.len() > done {
+        state.status += &format!(", {done} applied before it");
+    }
+    if !commit {
+        state.status += ", rolled back for the dry run";
+    }
+    Ok(())
+}
+
+/// Runs the saved queries `template` names and writes what it makes of
+/// them to `output`, saying how that went.
+async fn render_report(state: &State, template: &str, output: &str) -> String {
+    let source = match std::fs::read_to_string(config::expand_home(template)) {
+        Ok(source) => source,
+        Err(err) => return format!("Failed to read {template}: {err}"),
+    };
+    let parsed = match template::Template::parse(&source) {
+        Ok(parsed) => parsed,
+        Err(err) => return err,
+    };
+    let dirs: Vec<std::path::PathBuf> = state
+        .project_queries
+        .iter()
+        .cloned()
+        .chain(library::queries_dir())
+        .collect();
+    let mut results = Vec::new();
+    for (_, name) in &parsed.queries {
+        let sql = match library::read(&dirs, name) {
+            Ok(sql) => sql,
+            Err(err) => return err,
+        };
+        match results::fetch(&state.session.pool, &sql, &[]).await {
+            Ok(fetched) => results.push(state.redactions.apply(&fetched).into_owned()),
+            Err(err) => return format!("Query {name} failed: {err}"),
+        }
+    }
+    let options = &state.session.options;
+    let database = options.get_database().unwrap_or(options.get_username());
+    let generated = chrono::Local::now().format("%Y-%m-%d %H:%M").to_string();
+    let html = template::is_html(output);
+    let text = match parsed.render(&results, database, &generated, html) {
+        Ok(text) => text,
+        Err(err) => return format!("Failed to render {template}: {err}"),
+    };
+    let file = config::expand_home(output);
+    match std::fs::write(&file, &text) {
+        Ok(()) => format!(
+            "Wrote {} from {} quer{} ({})",
+            file.display(),
+            results.len(),
+            if results.len() == 1 { "y" } else { "ies" },
+            format_bytes(text.len() as u64)
+        ),
+        Err(err) => format!("Failed to write {}: {err}", file.display()),
+    }
+}
+
+fn export_results(state: &mut State, exporter: &str, path: &str) {
+    let Some(results) = &state.results else {
+        state.status = "No results".into();
+        return;
+    };
+    // Just the selection or the marked rows if there are any.
+    let selected;
+    let results = if state.grid.selection.is_some() || !state.grid.marked.is_empty() {
+        selected = selected_results(results, &state.grid);
+        &selected
+    } else {
+        results
+    };
+    let results = state.redactions.apply(results);
+    let results = results.as_ref();
+    let file = config::expand_home(path);
+    let mut output = Vec::new();
+    let written = match exporter {
+        "csv" => export::write_csv(results, &mut output),
+        "xlsx" => export::write_xlsx(results, &mut output),
+        "html" => export::write_html(results, state.html_sort, &mut output),
+        _ => export::write_table(results, &mut output),
+    }
+    .and_then(|()| std::fs::write(&file, &output));
+    state.status = match written {
+        Ok(()) => format!(
+            "Exported {} rows to {} ({})",
+            results.rows.len(),
+            file.display(),
+            format_bytes(output.len() as u64)
+        ),
+        Err(err) => format!("Failed to write {}: {err}", file.display()),
+    };
+}
+
+#[cfg(not(feature = "plugins"))]
+fn plugin_command(state: &mut State, _: Command) {
+    state.status = "Built without plugins, see the plugins cargo feature".into();
+}
+
+#[cfg(feature = "plugins")]
+fn plugin_command(state: &mut State, cmd: Command) {
+    use crate::plugin::Kind;
+
+    match cmd {
+        Command::Plugins if state.plugins.is_empty() => {
+            state.status = "No plugins, they go in $XDG_CONFIG_HOME/dbvi/plugins".into();
+        }
+        Command::Plugins => {
+            state.popup = Some(Popup::Text {
+                title: "Plugins".into(),
+                lines: state.plugins.describe().lines().map(String::from).collect(),
+            });
+        }
+        Command::Plugin { name, args } => {
+            let kind = state.plugins.command(&name).unwrap_or(Kind::Command);
+            match state.plugins.run(
+                kind,
+                &name,
+                &args,
+                state
+                    .results
+                    .as_ref()
+                    .map(|results| state.redactions.apply(results))
+                    .as_deref(),
+            ) {
+                Ok(output) if kind == Kind::Pane => {
+                    let mut buffer = Buffer::from_text(format!("[{name}]"), &output);
+                    buffer.read_only = true;
+                    state.open_buffer(buffer);
+                }
+                Ok(output) if output.trim_end().contains('\n') => {
+                    state.popup = Some(Popup::Text {
+                        title: name,
+                        lines: output.trim_end().lines().map(String::from).collect(),
+                    });
+                }
+                Ok(output) => state.status = output.trim_end().to_string(),
+                Err(err) => state.status = err,
+            }
+        }
+        Command::Transform(name) => match &state.results {
+            Some(results) => match state
+                .plugins
+                .transform(&name, &state.redactions.apply(results))
+            {
+                Ok(transformed) => {
+                    state.status = format!(
+                        "{name}: {} rows into {}",
+                        results.rows.len(),
+                        transformed.rows.len()
+                    );
+                    state.show_results(Some(transformed));
+                }
+                Err(err) => state.status = err,
+            },
+            None => state.status = "No results".into(),
+        },
+        Command::Export { exporter, path } => {
+            let Some(results) = &state.results else {
+                state.status = "No results".into();
+                return;
+            };
+            let file = config::expand_home(&path);
+            state.status = match state
+                .plugins
+                .run(
+                    Kind::Export,
+                    &exporter,
+                    &path,
+                    Some(&state.redactions.apply(results)),
+                )
+                .and_then(|output| {
+                    std::fs::write(&file, &output)
+                        .map(|()| output.len())
+                        .map_err(|err| format!("Failed to write {}: {err}", file.display()))
+                }) {
+                Ok(bytes) => format!(
+                    "Exported {} rows to {} ({})",
+                    results.rows.len(),
+                    file.display(),
+                    format_bytes(bytes as u64)
+                ),
+                Err(err) => err,
+            };
+        }
+        _ => {}
+    }
+}
+
+/// Suspends the UI to edit `text` in the user's editor, returning what it
+/// was saved as. The inner error is for the status line.
+async fn edit_external(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    text: &str,
+) -> io::Result<Result<String, String>> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".into());
+    // `$EDITOR` may carry arguments, like `code --wait`.
+    let mut words = editor.split_whitespace();
+    let Some(program) = words.next() else {
+        return Ok(Err("$EDITOR is empty".into()));
+    };
+    let path = std::env::temp_dir().join(format!("dbvi-{}.sql", std::process::id()));
+    if let Err(err) = std::fs::write(&path, format!("{text}\n")) {
+        return Ok(Err(format!("Failed to write {}: {err}", path.display())));
+    }
+
+    restore_terminal_state()?;
+    let status = tokio::process::Command::new(program)
+        .args(words)
+        .arg(&path)
+        .status()
+        .await;
+    enable_raw_mode()?;
+    execute!(
+        io::stdout(),
+        EnterAlternateScreen,
+        EnableMouseCapture,
+        EnableBracketedPaste
+    )?;
+    terminal.clear()?;
+
+    let edited = match status {
+        Ok(status) if status.success() => std::fs::read_to_string(&path)
+            .map_err(|err| format!("Failed to read {}: {err}", path.display())),
+        Ok(status) => Err(format!("{program} exited with {status}, buffer left as is")),
+        Err(err) => Err(format!("Failed to run {program}: {err}")),
+    };
+    let _ = std::fs::remove_file(&path);
+    Ok(edited)
+}
+
+/// Suspends the UI to show `text` in `$PAGER`, `less -S` without one so wide
+/// rows scroll sideways instead of wrapping. The inner error is for the
+/// status line.
+async fn page(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    text: &[u8],
+) -> io::Result<Result<(), String>> {
+    let pager = std::env::var("PAGER")
+        .ok()
+        .filter(|pager| !pager.trim().is_empty())
+        .unwrap_or_else(|| "less -S".into());
+    let mut words = pager.split_whitespace();
+    let program = words.next().expect("pager is not blank");
+    let path = std::env::temp_dir().join(format!("dbvi-{}.txt", std::process::id()));
+    if let Err(err) = std::fs::write(&path, text) {
+        return Ok(Err(format!("Failed to write {}: {err}", path.display())));
+    }
+
+    restore_terminal_state()?;
+    let status = tokio::process::Command::new(program)
+        .args(words)
+        .arg(&path)
+        .status()
+        .await;
+    enable_raw_mode()?;
+    execute!(
+        io::stdout(),
+        EnterAlternateScreen,
+        EnableMouseCapture,
+        EnableBracketedPaste
+    )?;
+    terminal.clear()?;
+
+    let _ = std::fs::remove_file(&path);
+    Ok(match status {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(format!("{program} exited with {status}")),
+        Err(err) => Err(format!("Failed to run {program}: {err}")),
+    })
+}
+
+/// Suspends the UI to run `command` in the user's shell, then waits for
+/// Enter as vim's `:!` does so its output can be read. Returns the status.
+async fn shell_out(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    command: &str,
+) -> io::Result<String> {
+    restore_terminal_state()?;
+    let status = tokio::process::Command::new(shell::program())
+        .arg("-c")
+        .arg(command)
+        .status()
+        .await;
+    print!("\nPress ENTER to continue");
+    io::stdout().flush()?;
+    io::stdin().read_line(&mut String::new())?;
+    enable_raw_mode()?;
+    execute!(
+        io::stdout(),
+        EnterAlternateScreen,
+        EnableMouseCapture,
+        EnableBracketedPaste
+    )?;
+    terminal.clear()?;
+
+    Ok(match status {
+        Ok(status) if status.success() => String::new(),
+        Ok(status) => format!("{command} exited with {status}"),
+        Err(err) => format!("Failed to run {command}: {err}"),
+    })
+}
+
+/// Asks for a line of input in a box titled `title`, with `message` below
+/// it. Returns `None` if the user backs out with `Esc`.
+fn prompt(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    title: &str,
+    message: Line,
+    masked: bool,
+    mut input: String,
+) -> io::Result<Option<String>> {
+    loop {
+        terminal.draw(|f| {
+            let [area] = Layout::vertical([Constraint::Length(4)])
+                .flex(Flex::Center)
+                .areas(f.area());
+            let [area] = Layout::horizontal([Constraint::Percentage(60)])
+                .flex(Flex::Center)
+                .areas(area);
+            let text = if masked {
+                "*".repeat(input.chars().count())
+            } else {
+                input.clone()
+            };
+            let prompt = Paragraph::new(vec![Line::from(text), message.clone()]).block(
+                Block::default()
+                    .title(Line::from(title).centered())
+                    .borders(Borders::ALL),
+            );
+            f.render_widget(Clear, area);
+            f.render_widget(prompt, area);
+            ui::finish(f);
+        })?;
+
+        let CEvent::Key(key) = event::read()? else {
+            continue;
+        };
+        match key.code {
+            KeyCode::Enter => return Ok(Some(input)),
+            KeyCode::Esc => return Ok(None),
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Char(c) => input.push(c),
+            _ => {}
+        }
+    }
+}
+
+/// Asks for one of `choices`, starting on `current`: typing narrows them
+/// down, the arrows (or `<C-n>` and `<C-p>`) move and Enter takes the one
+/// picked. `None` if backed out of with Esc.
+fn pick(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    title: &str,
+    message: Line,
+    choices: &[String],
+    current: &str,
+) -> io::Result<Option<String>> {
+    const SHOWN: usize = 10;
+    let mut filter = String::new();
+    let mut selected = choices
+        .iter()
+        .position(|choice| choice == current)
+        .unwrap_or_default();
+    loop {
+        let needle = filter.to_lowercase();
+        let matching: Vec<&String> = choices
+            .iter()
+            .filter(|choice| choice.to_lowercase().contains(&needle))
+            .collect();
+        selected = selected.min(matching.len().saturating_sub(1));
+        terminal.draw(|f| {
+            let shown = matching.len().clamp(1, SHOWN);
+            let [area] = Layout::vertical([Constraint::Length(shown as u16 + 4)])
+                .flex(Flex::Center)
+                .areas(f.area());
+            let [area] = Layout::horizontal([Constraint::Percentage(60)])
+                .flex(Flex::Center)
+                .areas(area);
+            let first = selected.saturating_sub(SHOWN - 1);
+            let mut lines = vec![Line::from(format!("/{filter}"))];
+            lines.extend(
+                matching
+                    .iter()
+                    .enumerate()
+                    .skip(first)
+                    .take(SHOWN)
+                    .map(|(i, choice)| {
+                        let line = Line::from(format!("  {choice}"));
+                        match i == selected {
+                            true => line.style(Style::default().add_modifier(Modifier::REVERSED)),
+                            false => line,
+                        }
+                    }),
+            );
+            if matching.is_empty() {
+                lines.push(Line::styled(
+                    "  no match",
+                    Style::default().fg(Color::DarkGray),
+                ));
+            }
+            lines.push(message.clone());
+            let prompt = Paragraph::new(lines).block(
+                Block::default()
+                    .title(Line::from(title).centered())
+                    .borders(Borders::ALL),
+            );
+            f.render_widget(Clear, area);
+            f.render_widget(prompt, area);
+            ui::finish(f);
+        })?;
+
+        let CEvent::Key(key) = event::read()? else {
+            continue;
+        };
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Enter => {
+                if let Some(choice) = matching.get(selected) {
+                    return Ok(Some(choice.to_string()));
+                }
+            }
+            KeyCode::Esc => return Ok(None),
+            KeyCode::Up => selected = selected.saturating_sub(1),
+            KeyCode::Char('p') if ctrl => selected = selected.saturating_sub(1),
+            KeyCode::Down | KeyCode::Tab => selected += 1,
+            KeyCode::Char('n') if ctrl => selected += 1,
+            KeyCode::Backspace => {
+                filter.pop();
+            }
+            KeyCode::Char(c) => {
+                filter.push(c);
+                selected = 0;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Shows `sql`, which dbvi is about to run to do `what`, for a look first:
+/// Enter runs it, `e` edits it in `$EDITOR` and Esc backs out. Returns what
+/// to run, `None` to run nothing.
+async fn preview_sql(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    dialect: dialect::Dialect,
+    what: &str,
+    mut sql: String,
+) -> io::Result<Option<String>> {
+    let hint = Line::styled(
+        "Enter runs it, e edits it first, Esc backs out",
+        Style::default().fg(Color::DarkGray),
+    );
+    let mut message = hint.clone();
+    loop {
+        terminal.draw(|f| {
+            let mut highlight = highlight::HighlightState::new(dialect);
+            let mut lines: Vec<Line> = sql
+                .lines()
+                .map(|line| highlight::highlight_line(line, &mut highlight))
+                .collect();
+            lines.push(Line::default());
+            lines.push(message.clone());
+            let height = (lines.len() as u16 + 2).min(f.area().height);
+            let [area] = Layout::vertical([Constraint::Length(height)])
+                .flex(Flex::Center)
+                .areas(f.area());
+            let [area] = Layout::horizontal([Constraint::Percentage(80)])
+                .flex(Flex::Center)
+                .areas(area);
+            let preview = Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+                Block::default()
+                    .title(Line::from(what).centered())
+                    .borders(Borders::ALL),
+            );
+            f.render_widget(Clear, area);
+            f.render_widget(preview, area);
+            ui::finish(f);
+        })?;
+
+        let CEvent::Key(key) = event::read()? else {
+            continue;
+        };
+        match key.code {
+            KeyCode::Enter if sql.trim().is_empty() => return Ok(None),
+            KeyCode::Enter => return Ok(Some(sql)),
+            KeyCode::Esc | KeyCode::Char('q') => return Ok(None),
+            KeyCode::Char('e') => match edit_external(terminal, &sql).await? {
+                Ok(edited) => {
+                    sql = edited.trim_end().to_string();
+                    message = hint.clone();
+                }
+                Err(err) => message = Line::styled(err, Style::default().fg(Color::Red)),
+            },
+            _ => {}
+        }
+    }
+}
+
+/// Asks for a password in a masked input box. Returns `None` if the user
+/// backs out with `Esc`.
+/// The `.dbvi.toml` of the current directory, asking first whether to
+/// trust it unless it was trusted as it is now, and what to tell about it.
+/// A broken or ignored file is only worth a status message.
+fn load_workspace(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+) -> io::Result<(Option<Workspace>, Option<String>)> {
+    let Some(path) = env::current_dir()
+        .ok()
+        .and_then(|dir| workspace::find(&dir))
+    else {
+        return Ok((None, None));
+    };
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(err) => {
+            return Ok((
+                None,
+                Some(format!("Failed to read {}: {err}", path.display())),
+            ));
+        }
+    };
+    let workspace = match Workspace::parse(&path, &contents) {
+        Ok(workspace) => workspace,
+        Err(err) => return Ok((None, Some(format!("Ignored {err}")))),
+    };
+    let trusted = workspace::load_trusted().unwrap_or_else(|err| {
+        tracing::warn!(error = %err, "failed to read the trusted workspaces");
+        String::new()
+    });
+    if !workspace::is_trusted(&trusted, &path, &contents) {
+        let mut says: Vec<String> = Vec::new();
+        if let Some(profile) = &workspace.profile {
+            says.push(format!("profile {profile}"));
+        }
+        if !workspace.options.is_empty() {
+            let names: Vec<&str> = workspace.options.keys().map(String::as_str).collect();
+            says.push(format!("sets {}", names.join(", ")));
+        }
+        if let Some(queries) = &workspace.queries {
+            says.push(format!("queries in {}", queries.display()));
+        }
+        let message = Line::from(format!("{}. y to trust it", says.join("; ")));
+        let answer = prompt(
+            terminal,
+            &format!("Trust {}?", path.display()),
+            message,
+            false,
+            String::new(),
+        )?;
+        if !answer.is_some_and(|answer| answer.trim().eq_ignore_ascii_case("y")) {
+            return Ok((
+                None,
+                Some(format!("Ignored the untrusted {}", path.display())),
+            ));
+        }
+        if let Err(err) = workspace::save_trusted(&workspace::trust(&trusted, &path, &contents)) {
+            tracing::warn!(error = %err, "failed to save the trusted workspaces");
+        }
+    }
+    tracing::info!(path = %path.display(), "loaded workspace");
+    Ok((Some(workspace), None))
+}
+
+fn prompt_password(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    profile: &str,
+    error: Option<&str>,
+) -> io::Result<Option<String>> {
+    let message = error.map_or(Line::from(""), |err| {
+        Line::styled(err.to_string(), Style::default().fg(Color::Red))
+    });
+    prompt(
+        terminal,
+        &format!("Password for {profile}"),
+        message,
+        true,
+        String::new(),
+    )
+}
+
+/// Connects with `options`, falling back to the keyring and then, with a
+/// `terminal` to ask on, to an interactive prompt when the server rejects
+/// the (possibly missing) password.
+async fn connect(
+    terminal: Option<&mut Terminal<CrosstermBackend<io::Stdout>>>,
+    options: PgConnectOptions,
+    init: &[String],
+    profile: &str,
+    save_password: bool,
+) -> io::Result<(Session, Option<String>)> {
+    let mut error = match Session::connect(options.clone(), init).await {
+        Ok(pool) => return Ok((pool, None)),
+        Err(err) if db::is_auth_error(&err) => err,
+        Err(err) => return Err(io::Error::other(err)),
+    };
+
+    if let Some(password) = db::credentials::load(profile) {
+        match Session::connect(options.clone().password(&password), init).await {
+            Ok(pool) => return Ok((pool, None)),
+            Err(err) if db::is_auth_error(&err) => error = err,
+            Err(err) => return Err(io::Error::other(err)),
+        }
+    }
+
+    let Some(terminal) = terminal else {
+        return Err(io::Error::other(error));
+    };
+    // The first attempt may simply have had no password to send, so only
+    // complain once the user has actually typed one.
+    let mut message = None;
+    loop {
+        let Some(password) = prompt_password(terminal, profile, message.as_deref())? else {
+            return Err(io::Error::other(error));
+        };
+        match Session::connect(options.clone().password(&password), init).await {
+            Ok(pool) => {
+                let status = save_password
+                    .then(|| db::credentials::store(profile, &password).err())
+                    .flatten()
+                    .map(|err| format!("Failed to save password: {err}"));
+                return Ok((pool, status));
+            }
+            Err(err) if db::is_auth_error(&err) => {
+                message = err.as_database_error().map(|err| err.message().to_string());
+                error = err;
+            }
+            Err(err) => return Err(io::Error::other(err)),
+        }
+    }
+}
+
+/// Runs `query` for `:compare` on a connection of its own to `target`, a
+/// profile or else a URL or keyword/value string, returning the rows of its
+/// last statement that returned any, along with the name of the profile or
+/// else of the server, which leaves out any password in the URL.
+async fn compare_on(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    target: &str,
+    query: &str,
+) -> io::Result<(String, ResultSet)> {
+    let (name, session, _tunnel) = connect_to(terminal, target).await?;
+    let outcome = run_query(&session.pool, query, &[]).await;
+    session.pool.close().await;
+    let (mut results, ..) = outcome.map_err(io::Error::other)?;
+    let results = results
+        .pop()
+        .map(|(_, results)| results)
+        .ok_or_else(|| io::Error::other("the query returned no rows"))?;
+    Ok((name, results))
+}
+
+/// A connection of its own to `target`, a profile or a URL, named as the
+/// profile or without the password, and its SSH tunnel if it has one,
+/// to keep until done with it.
+async fn connect_to(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    target: &str,
+) -> io::Result<(String, Session, Option<db::tunnel::Tunnel>)> {
+    let known = config::Config::load()?.profiles.contains_key(target);
+    let args = match known {
+        true => <Args as clap::Parser>::try_parse_from(["dbvi", "--profile", target]),
+        false => <Args as clap::Parser>::try_parse_from(["dbvi", target]),
+    }
+    .map_err(io::Error::other)?;
+    let (session, tunnel, _, _) = open_connection(Some(terminal), &args, None).await?;
+    let name = match known {
+        true => target.to_string(),
+        false => db::profile_key(&session.options),
+    };
+    Ok((name, session, tunnel))
+}
+
+/// Resolves the connection from the command line and the selected profile,
+/// opening the profile's SSH tunnel first if it has one. `password` is one
+/// just typed into the setup, tried before any other. Without a `terminal`
+/// nothing is asked for.
+pub(crate) async fn open_connection(
+    terminal: Option<&mut Terminal<CrosstermBackend<io::Stdout>>>,
+    args: &Args,
+    password: Option<&str>,
+) -> io::Result<(
+    Session,
+    Option<db::tunnel::Tunnel>,
+    Option<String>,
+    config::Config,
+)> {
+    let config = config::Config::load()?;
+    let profile = match &args.profile {
+        Some(name) => Some(
+            config
+                .profiles
+                .get(name)
+                .ok_or_else(|| io::Error::other(format!("Unknown profile `{name}`")))?,
+        ),
+        None => None,
+    };
+
+    let target = args.target().or(profile.and_then(|p| p.url.as_deref()));
+    let service = args
+        .service
+        .as_deref()
+        .or(profile.and_then(|p| p.service.as_deref()));
+    let mut options = db::connect_options(target, service).map_err(io::Error::other)?;
+    if let Some(password) = password {
+        options = options.password(password);
+    }
+    // Passwords are keyed by profile, falling back to the server identity,
+    // which has to be captured before a tunnel rewrites the host.
+    let key = args
+        .profile
+        .clone()
+        .unwrap_or_else(|| db::profile_key(&options));
+
+    let tunnel = match profile.and_then(|p| p.ssh.as_ref()) {
+        Some(ssh) => {
+            let tunnel =
+                db::tunnel::Tunnel::open(ssh, options.get_host(), options.get_port()).await?;
+            options = options.host("127.0.0.1").port(tunnel.local_port);
+            Some(tunnel)
+        }
+        None => None,
+    };
+
+    let init = config.init_statements(profile)?;
+    let (session, status) = connect(terminal, options, &init, &key, args.save_password).await?;
+    Ok((session, tunnel, status, config))
+}
+
+/// Shows the first-run setup form until it is filled in or skipped.
+fn run_setup(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>) -> io::Result<Option<Wizard>> {
+    let mut wizard = Wizard::default();
+    loop {
+        terminal.draw(|f| {
+            wizard.render(f);
+            ui::finish(f);
+        })?;
+        let CEvent::Key(key) = event::read()? else {
+            continue;
+        };
+        match wizard.key(key) {
+            Step::Continue => {}
+            Step::Done => return Ok(Some(wizard)),
+            Step::Cancel => return Ok(None),
+        }
+    }
+}
+
+/// Writes the config with the profile from `wizard`, and points `args` at
+/// it. The password goes in the keyring, not the file. Returns what to say
+/// about it.
+fn save_setup(wizard: &Wizard, args: &mut Args) -> Option<String> {
+    let path = config::config_path()?;
+    let written = path
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|()| std::fs::write(&path, wizard.config()));
+    if let Err(err) = written {
+        args.url = Some(wizard.url());
+        return Some(format!("Failed to write {}: {err}", path.display()));
+    }
+    args.profile = Some(wizard.name().to_string());
+    let mut status = format!("Saved profile {} to {}", wizard.name(), path.display());
+    if !wizard.password().is_empty()
+        && let Err(err) = db::credentials::store(wizard.name(), wizard.password())
+    {
+        status.push_str(&format!(", but not the password: {err}"));
+    }
+    Some(status)
+}
+
+pub struct App {
+    terminal: Terminal<CrosstermBackend<io::Stdout>>,
+    session: Session,
+    config: config::Config,
+    status: Option<String>,
+    dialect: dialect::Dialect,
+    /// What the profile connected with says the database is.
+    environment: Option<config::Environment>,
+    /// What the profile connected with says to redact.
+    redact: Vec<String>,
+    /// Started with `--demo`, with no server behind the session.
+    demo: bool,
+    /// Started with `--tutor`, which is the demo with lessons.
+    tutor: bool,
+    /// The `.dbvi.toml` of the current directory, once trusted.
+    workspace: Option<Workspace>,
+    /// The events of `--replay`, played once the session starts.
+    replay: Vec<(Duration, CEvent)>,
+    /// Started with `--no-color`, see `:set plain`.
+    plain: bool,
+    // Kept alive for the whole session; dropping it closes the tunnel.
+    _tunnel: Option<db::tunnel::Tunnel>,
+}
+
+impl App {
+    pub async fn new(args: &Args) -> io::Result<Self> {
+        // Read before the terminal is taken over, so a bad file is reported
+        // on the shell.
+        let replay = match &args.replay {
+            Some(path) => {
+                let text = std::fs::read_to_string(path)
+                    .map_err(|err| io::Error::new(err.kind(), format!("{path}: {err}")))?;
+                record::parse(&text).map_err(|err| io::Error::other(format!("{path}: {err}")))?
+            }
+            None => Vec::new(),
+        };
+        // Before the first prompt is drawn.
+        ui::set_plain(args.no_color);
+        enable_raw_mode()?;
+        let mut stdout = io::stdout();
+        execute!(
+            stdout,
+            EnterAlternateScreen,
+            EnableMouseCapture,
+            EnableBracketedPaste
+        )?;
+        let backend = CrosstermBackend::new(stdout);
+        let mut terminal = Terminal::new(backend)?;
+
+        let (workspace, workspace_status) = match load_workspace(&mut terminal) {
+            Ok(loaded) => loaded,
+            Err(err) => {
+                restore_terminal_state()?;
+                return Err(err);
+            }
+        };
+        if args.demo || args.tutor {
+            let mut config = match config::Config::load() {
+                Ok(config) => config,
+                Err(err) => {
+                    restore_terminal_state()?;
+                    return Err(err);
+                }
+            };
+            if let Some(workspace) = &workspace {
+                config.options.extend(workspace.options.clone());
+            }
+            tracing::info!("demo mode");
+            return Ok(Self {
+                terminal,
+                // Never connected: every query goes to the demo backend.
+                session: Session::lazy(PgConnectOptions::new_without_pgpass()),
+                config,
+                status: Some(if args.tutor {
+                    "Welcome to the tutor, follow the lessons at the top".into()
+                } else {
+                    "Demo mode: made-up data, try :tables or select * from orders".into()
+                }),
+                dialect: dialect::Dialect::default(),
+                environment: None,
+                redact: Vec::new(),
+                demo: true,
+                tutor: args.tutor,
+                workspace,
+                replay,
+                plain: args.no_color,
+                _tunnel: None,
+            });
+        }
+        let mut args = args.clone();
+        if let Some(profile) = workspace.as_ref().and_then(|w| w.profile.clone())
+            && args.conninfo.is_none()
+            && args.url.is_none()
+            && args.service.is_none()
+            && args.profile.is_none()
+        {
+            args.profile = Some(profile);
+        }
+        let (mut setup, mut password) = (None, None);
+        if wizard::wanted(&args, config::config_path().as_deref()) {
+            match run_setup(&mut terminal) {
+                Ok(Some(wizard)) => {
+                    setup = save_setup(&wizard, &mut args);
+                    password = Some(wizard.password().to_string()).filter(|p| !p.is_empty());
+                }
+                Ok(None) => {}
+                Err(err) => {
+                    restore_terminal_state()?;
+                    return Err(err);
+                }
+            }
+        }
+        let (session, tunnel, status, mut config) =
+            match open_connection(Some(&mut terminal), &args, password.as_deref()).await {
+                Ok((session, tunnel, status, config)) => (
+                    session,
+                    tunnel,
+                    status.or(setup).or(workspace_status),
+                    config,
+                ),
+                Err(err) => {
+                    tracing::error!(error = %err, "failed to connect");
+                    restore_terminal_state()?;
+                    return Err(err);
+                }
+            };
+
+        tracing::info!(
+            to = %db::profile_key(&session.options),
+            server = session.server.name(),
+            "connected"
+        );
+        if let Some(workspace) = &workspace {
+            config.options.extend(workspace.options.clone());
+        }
+        let profile = args
+            .profile
+            .as_ref()
+            .and_then(|name| config.profiles.get(name));
+        let dialect = profile
+            .as_ref()
+            .and_then(|profile| profile.dialect)
+            .unwrap_or_else(|| dialect::Dialect::detect(session.server));
+        let environment = profile.as_ref().and_then(|profile| profile.environment());
+        let redact = profile
+            .as_ref()
+            .map(|profile| profile.redact.clone())
+            .unwrap_or_default();
+        // The connection's own say goes over the config's and workspace's.
+        if let Some(name) = &args.profile {
+            config.apply_profile(name);
+        }
+        Ok(Self {
+            terminal,
+            session,
+            config,
+            status,
+            dialect,
+            environment,
+            redact,
+            demo: false,
+            tutor: false,
+            workspace,
+            replay,
+            plain: args.no_color,
+            _tunnel: tunnel,
+        })
+    }
+
+    pub async fn run(mut self) -> io::Result<()> {
+        let (sender, receiver) = action::channel();
+        if !self.demo {
+            spawn_health_check(self.session.clone(), sender.clone());
+        }
+        let mut state = State::new(self.session.clone(), sender);
+        if self.demo {
+            state.demo = true;
+            state.backend = Some(db::backend::Backend::Demo(db::demo::Demo::new()));
+        }
+        if self.tutor {
+            state.tutor = Some(Tutor::default());
+        }
+        match Clipboard::from_name(self.config.clipboard.as_deref().unwrap_or("auto")) {
+            Ok(clipboard) => state.clipboard = clipboard,
+            Err(err) => state.status = err,
+        }
+        state.snippets = std::mem::take(&mut self.config.snippets);
+        if let Some(keys) = &self.config.kill_switch {
+            state.keymap.set_kill_switch(Some(keys));
+        }
+        self.config.map.apply(&mut state.keymap);
+        state.auto_pairs = self.config.auto_pairs;
+        state.statusline = std::mem::take(&mut self.config.statusline);
+        state.dialect = self.dialect;
+        state.environment = self.environment;
+        state.redactions = Redactions::new(std::mem::take(&mut self.redact));
+        state.plain = self.plain;
+        state.project_queries = self.workspace.as_ref().and_then(Workspace::queries_dir);
+        state.migrations = self.workspace.as_ref().and_then(Workspace::migrations_dir);
+        if let Some(config) = &self.config.audit {
+            let connection = db::profile_key(&self.session.options);
+            match audit::AuditLog::new(config, connection) {
+                Ok(audit) => state.audit = Some(audit),
+                Err(err) => state.status = format!("Audit log disabled: {err}"),
+            }
+        }
+        if let Some(timeout) = self.config.statement_timeout.as_deref()
+            && !self.demo
+        {
+            set_option(&mut state, "statement_timeout", Some(timeout)).await;
+        }
+        for (name, value) in &self.config.options {
+            let value = match value {
+                toml::Value::String(value) => value.clone(),
+                value => value.to_string(),
+            };
+            let outcome = match options::request(&state, name, Some(&value)) {
+                Ok(Request::Set(opt, _)) if opt.on_server() => {
+                    if !self.demo {
+                        set_option(&mut state, name, Some(&value)).await;
+                    }
+                    continue;
+                }
+                Ok(Request::Set(opt, value)) => opt.set(&mut state, value),
+                Ok(_) => continue,
+                Err(err) => Err(err),
+            };
+            if let Err(err) = outcome {
+                state.status = format!("Config option {name}: {err}");
+            }
+        }
+        if let Some(dir) = swap::swap_dir() {
+            match swap::Swap::new(dir.clone()) {
+                Ok(swap) => state.swap = Some(swap),
+                Err(err) => state.status = format!("Swap files disabled: {err}"),
+            }
+            let leftovers = swap::leftovers(&dir).len();
+            if leftovers > 0 {
+                state.status = format!(
+                    "Found {leftovers} swap files from a crashed session, \
+                     :recover to open them or :recover discard"
+                );
+            }
+        }
+        #[cfg(feature = "plugins")]
+        if let Some(dir) = config::config_dir() {
+            let (plugins, errors) = crate::plugin::Plugins::load(&dir.join("plugins"));
+            state.plugins = plugins;
+            if !errors.is_empty() {
+                state.status = format!("Failed to load plugins: {}", errors.join("; "));
+            }
+        }
+        #[cfg(feature = "lua")]
+        if let Some(path) = config::config_dir().map(|dir| dir.join("init.lua"))
+            && path.exists()
+        {
+            match crate::lua::Lua::new(state.bus()) {
+                Ok(lua) => {
+                    if let Some(leader) = &self.config.map.leader {
+                        lua.set_leader(leader);
+                    }
+                    if let Err(err) = lua.run_file(&path) {
+                        state.status = format!("init.lua: {err}");
+                    }
+                    lua.on_connect(&match &state.backend {
+                        Some(backend) => vec![("backend", backend.name())],
+                        None => connect_info(&self.session),
+                    });
+                    state.lua = Some(lua);
+                }
+                Err(err) => state.status = format!("Failed to start Lua: {err}"),
+            }
+        }
+        if let Some(lsp) = &self.config.lsp {
+            match lsp::Client::spawn(&lsp.command, state.bus()) {
+                Ok(client) => state.lsp = Some(client),
+                Err(err) => state.status = format!("Failed to start the language server: {err}"),
+            }
+        }
+        if !self.demo {
+            load_schema(&mut state);
+            check_search_path(&state);
+            check_capabilities(&state);
+        }
+        if let Some(status) = self.status.take() {
+            state.status = status;
+        }
+        if !self.replay.is_empty() {
+            record::replay(std::mem::take(&mut self.replay), state.bus());
+        }
+        run_app(&mut self.terminal, state, receiver).await
+    }
+}
+
+#[inline(always)]
+fn restore_terminal_state() -> io::Result<()> {
+    disable_raw_mode()?;
+    execute!(
+        io::stdout(),
+        LeaveAlternateScreen,
+        DisableMouseCapture,
+        DisableBracketedPaste,
+        SetCursorStyle::DefaultUserShape,
+        Show
+    )?;
+    Ok(())
+}
+
+impl Drop for App {
+    fn drop(&mut self) {
+        // Panicking here while unwinding from another panic would abort, and
+        // there is nothing better to do about a terminal that won't restore.
+        let _ = restore_terminal_state();
+    }
+}
+
+/// Restores the terminal before the panic message is printed, so it isn't
+/// lost on the alternate screen and the shell isn't left in raw mode.
+pub fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        // Background tasks run on tokio's worker threads and their panics
+        // don't take the UI down, so only the main thread restores.
+        if std::thread::current().name() == Some("main") {
+            let _ = restore_terminal_state();
+        }
+        tracing::error!("{info}");
+        default_hook(info);
+    }));
+}
+
+#[derive(Clone, clap::Parser)]
+pub struct Args {
+    /// Connection URL or keyword/value string, e.g. `service=mydb`. Falls
+    /// back to `DATABASE_URL`, then to the standard `PG*` environment
+    /// variables.
+    #[clap(conflicts_with = "url")]
+    pub conninfo: Option<String>,
+    /// Same as the positional conninfo.
+    #[clap(short, long)]
+    pub url: Option<String>,
+    /// Connect using a service from `~/.pg_service.conf` (or `PGSERVICEFILE`).
+    #[clap(long, conflicts_with_all = ["conninfo", "url"])]
+    pub service: Option<String>,
+    /// Connect using a profile from the config file.
+    #[clap(short, long)]
+    pub profile: Option<String>,
+    /// Store a password entered at the prompt in the OS keyring.
+    #[clap(long)]
+    pub save_password: bool,
+    /// Log at this level (`error`, `warn`, `info`, `debug` or `trace`) to
+    /// `$XDG_STATE_HOME/dbvi/log`, see `:log`.
+    #[clap(long, value_name = "LEVEL", value_parser = ["error", "warn", "info", "debug", "trace"])]
+    pub log_level: Option<String>,
+    /// Browse a made-up shop database instead of connecting to a server.
+    #[clap(long, conflicts_with_all = ["conninfo", "url", "service", "profile"])]
+    pub demo: bool,
+    /// Learn the keys in lessons on the demo database, like vimtutor.
+    #[clap(long, conflicts_with_all = ["conninfo", "url", "service", "profile"])]
+    pub tutor: bool,
+    /// Play back the keys of a `:record` file, to reproduce a bug or for
+    /// a demo.
+    #[clap(long, value_name = "FILE")]
+    pub replay: Option<String>,
+    /// Draw without colors or box drawing and say changes of mode on the
+    /// status line, for screen readers, like `:set plain`.
+    #[clap(long)]
+    pub no_color: bool,
+    /// Run the ex commands in FILE without the TUI, `connect`, `edit`,
+    /// `run`, `export` and the like, stopping at the first that fails with
+    /// 1, or 2 for the script, 3 the connection, 4 SQL and 5 a timeout.
+    #[clap(long, value_name = "FILE", conflicts_with_all = ["demo", "tutor", "replay"])]
+    pub script: Option<String>,
+    /// How `--script` reports on stderr, `json` for an object a line.
+    #[clap(long, value_name = "FORMAT", value_parser = ["text", "json"], default_value = "text", requires = "script")]
+    pub format: String,
+}
+
+impl Args {
+    /// The connection string given, positionally or with `--url`.
+    pub fn target(&self) -> Option<&str> {
+        self.conninfo.as_deref().or(self.url.as_deref())
+    }
+}
@@ -0,0 +1,113 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The audit log: every statement run, appended with when, where, how long
+//! it took and what came of it. Unlike the debug log it is never rotated or
+//! truncated by dbvi.
+
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::config::{self, AuditFormat};
+
+#[derive(Debug, Clone)]
+pub struct AuditLog {
+    path: PathBuf,
+    format: AuditFormat,
+    /// `user@host:port/db` of the session.
+    connection: String,
+}
+
+impl AuditLog {
+    pub fn new(config: &config::Audit, connection: String) -> io::Result<Self> {
+        let path = match &config.path {
+            Some(path) => config::expand_home(path),
+            None => config::state_dir()
+                .ok_or_else(|| io::Error::other("no directory for the audit log"))?
+                .join("audit.log"),
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        Ok(Self {
+            path,
+            format: config.format,
+            connection,
+        })
+    }
+
+    /// Records what follows against `connection`, after `:db` switched to
+    /// another database.
+    pub fn set_connection(&mut self, connection: String) {
+        self.connection = connection;
+    }
+
+    /// Appends `statement`, run with `binds`, and its outcome: the rows
+    /// returned or affected, or the error.
+    pub fn record(
+        &self,
+        statement: &str,
+        binds: &[Option<String>],
+        elapsed: Duration,
+        outcome: Result<u64, String>,
+    ) -> io::Result<()> {
+        let time = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+        let entry = match self.format {
+            AuditFormat::Sql => {
+                let outcome = match outcome {
+                    Ok(rows) => format!("{rows} rows"),
+                    Err(err) => format!("ERROR {}", err.replace('\n', " ")),
+                };
+                let mut entry = format!("-- {time} {} {elapsed:.1?} {outcome}\n", self.connection);
+                if !binds.is_empty() {
+                    let binds: Vec<String> = binds
+                        .iter()
+                        .enumerate()
+                        .map(|(index, value)| match value {
+                            Some(value) => {
+                                format!("${}='{}'", index + 1, value.replace('\'', "''"))
+                            }
+                            None => format!("${}=NULL", index + 1),
+                        })
+                        .collect();
+                    entry.push_str(&format!("-- binds: {}\n", binds.join(", ")));
+                }
+                format!("{entry}{};\n\n", statement.trim_end().trim_end_matches(';'))
+            }
+            AuditFormat::Json => {
+                let (rows, error) = match outcome {
+                    Ok(rows) => (Some(rows), None),
+                    Err(err) => (None, Some(err)),
+                };
+                let entry = serde_json::json!({
+                    "time": time,
+                    "connection": self.connection,
+                    "duration_ms": elapsed.as_secs_f64() * 1000.0,
+                    "rows": rows,
+                    "error": error,
+                    "statement": statement,
+                    "binds": binds,
+                });
+                format!("{entry}\n")
+            }
+        };
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?
+            .write_all(entry.as_bytes())
+    }
+}
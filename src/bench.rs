@@ -0,0 +1,82 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `:bench`, timing a query over several runs.
+
+use std::time::{Duration, Instant};
+
+use sqlx::postgres::PgConnectOptions;
+use sqlx::{Connection, Executor, PgConnection};
+
+/// Runs `query` `runs` times on a connection of its own, after an untimed
+/// run first if `warm_up`, calling `progress` with the runs done. Rows are
+/// fetched and thrown away, and every run is rolled back, so benchmarking an
+/// `UPDATE` changes nothing.
+pub async fn run(
+    options: &PgConnectOptions,
+    query: &str,
+    binds: &[Option<String>],
+    runs: usize,
+    warm_up: bool,
+    mut progress: impl FnMut(usize),
+) -> Result<Vec<Duration>, sqlx::Error> {
+    let mut conn = PgConnection::connect_with(options).await?;
+    let mut timings = Vec::with_capacity(runs);
+    for run in 0..runs + usize::from(warm_up) {
+        let mut transaction = conn.begin().await?;
+        let started = Instant::now();
+        if binds.is_empty() {
+            transaction.execute(sqlx::raw_sql(query)).await?;
+        } else {
+            binds
+                .iter()
+                .fold(sqlx::query(query), |query, value| query.bind(value.clone()))
+                .fetch_all(&mut *transaction)
+                .await?;
+        }
+        let elapsed = started.elapsed();
+        transaction.rollback().await?;
+        if run > 0 || !warm_up {
+            timings.push(elapsed);
+            progress(timings.len());
+        }
+    }
+    Ok(timings)
+}
+
+/// Min, median, p95, max, mean and standard deviation of `timings`, a line
+/// each.
+pub fn summary(timings: &[Duration]) -> Vec<String> {
+    let mut ms: Vec<f64> = timings
+        .iter()
+        .map(|timing| timing.as_secs_f64() * 1000.0)
+        .collect();
+    if ms.is_empty() {
+        return Vec::new();
+    }
+    ms.sort_by(f64::total_cmp);
+    // Nearest rank.
+    let percentile = |p: f64| ms[((p * ms.len() as f64).ceil() as usize).clamp(1, ms.len()) - 1];
+    let mean = ms.iter().sum::<f64>() / ms.len() as f64;
+    let variance = ms.iter().map(|ms| (ms - mean).powi(2)).sum::<f64>() / ms.len() as f64;
+    vec![
+        format!("runs    {}", ms.len()),
+        format!("min     {:.3} ms", ms[0]),
+        format!("median  {:.3} ms", percentile(0.5)),
+        format!("p95     {:.3} ms", percentile(0.95)),
+        format!("max     {:.3} ms", ms[ms.len() - 1]),
+        format!("mean    {mean:.3} ms"),
+        format!("stddev  {:.3} ms", variance.sqrt()),
+    ]
+}
@@ -0,0 +1,582 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The data browser, `:browse <table> [by <column> [desc]]`: a table a page
+//! of `pagesize` rows at a time, in the order of the column if given and of
+//! its primary key, so that pages keep their rows. `<C-f>` and `<C-b>` turn
+//! a page, or a count of them, `G` goes to the last page and `:page N` to
+//! page N.
+//!
+//! `s` sorts by the column under the cursor, ascending and then descending,
+//! on the server rather than just the page shown. `f` filters on it, with
+//! a value to match or an operator and a value: `>= 10`, `~ smith` for text
+//! containing it, `null` or `!null`. Each column has one filter, the title
+//! shows them all, and `f` again changes one or, left empty, removes it.
+//! With `:set preview`, the query of the page to show after any of these
+//! comes up first, to run, edit or back out of.
+//!
+//! `D` and `U` write a `DELETE` of the selected rows, or an `UPDATE` of the
+//! column under the cursor in them, by their key, into a buffer of its own
+//! to look over and run.
+//!
+//! The title shows the page and how many there are. Up to a million rows
+//! are counted; past that the count is the planner's estimate, which is
+//! good enough to page by.
+//!
+//! Pages of a table with a primary key are found by seeking rather than
+//! by `OFFSET`, which reads and throws away every row before the page:
+//! each page fetched leaves the first and last values of the order behind,
+//! and the next one starts after those of the closest page fetched, which
+//! an index finds straight away however deep into the table it is. The
+//! last page is the first one from the other end.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use sqlx::PgPool;
+
+use crate::plan;
+use crate::results::{self, Cell, ResultSet};
+use crate::statements::{quote_ident, quote_literal};
+
+/// Rows estimated to be fewer than this are counted exactly.
+const COUNT_BELOW: f64 = 1_000_000.0;
+
+/// Columns of the primary key of `$1`, in key order.
+const PRIMARY_KEY: &str = "\
+SELECT a.attname::text
+  FROM pg_index i
+  JOIN pg_attribute a ON a.attrelid = i.indrelid AND a.attnum = ANY (i.indkey)
+ WHERE i.indrelid = $1::regclass AND i.indisprimary
+ ORDER BY array_position(i.indkey::int2[], a.attnum)";
+
+/// Where to turn to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageTo {
+    /// This many pages on.
+    Next(u64),
+    /// This many pages back.
+    Previous(u64),
+    /// Page number, from 1.
+    Number(u64),
+    Last,
+}
+
+/// How many rows the table has.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Total {
+    pub rows: u64,
+    /// Whether `rows` was counted rather than estimated.
+    pub exact: bool,
+}
+
+/// How a [`Filter`] compares.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    /// Text containing the value, whatever the case.
+    Contains,
+    Null,
+    NotNull,
+}
+
+/// Operators as typed, longest first so that `>=` isn't taken for `>`.
+const OPS: [(&str, Op); 8] = [
+    (">=", Op::Ge),
+    ("<=", Op::Le),
+    ("!=", Op::Ne),
+    ("<>", Op::Ne),
+    ("=", Op::Eq),
+    ("<", Op::Lt),
+    (">", Op::Gt),
+    ("~", Op::Contains),
+];
+
+/// A condition on a column of the table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Filter {
+    pub column: String,
+    pub op: Op,
+    pub value: String,
+}
+
+impl Filter {
+    /// Reads what was typed for `column`: `null`, `!null`, or a value
+    /// after an operator, `=` if there is none.
+    pub fn parse(column: &str, text: &str) -> Result<Self, String> {
+        let text = text.trim();
+        let (op, value) = if text.eq_ignore_ascii_case("null") {
+            (Op::Null, "")
+        } else if text.eq_ignore_ascii_case("!null") {
+            (Op::NotNull, "")
+        } else {
+            let (op, value) = OPS
+                .iter()
+                .find_map(|(op, kind)| Some((*kind, text.strip_prefix(op)?)))
+                .unwrap_or((Op::Eq, text));
+            let value = value.trim_start();
+            if value.is_empty() {
+                return Err(format!("No value to compare {column} with"));
+            }
+            (op, value)
+        };
+        Ok(Self {
+            column: column.to_string(),
+            op,
+            value: value.to_string(),
+        })
+    }
+
+    /// It as it would be typed, to change it.
+    pub fn text(&self) -> String {
+        match self.op {
+            Op::Null => "null".into(),
+            Op::NotNull => "!null".into(),
+            op => {
+                let (typed, _) = OPS
+                    .iter()
+                    .find(|(_, kind)| *kind == op)
+                    .expect("an operator");
+                format!("{typed} {}", self.value)
+            }
+        }
+    }
+
+    pub fn sql(&self) -> String {
+        let column = quote_ident(&self.column);
+        let value = quote_literal(&self.value);
+        match self.op {
+            Op::Eq => format!("{column} = {value}"),
+            Op::Ne => format!("{column} <> {value}"),
+            Op::Lt => format!("{column} < {value}"),
+            Op::Le => format!("{column} <= {value}"),
+            Op::Gt => format!("{column} > {value}"),
+            Op::Ge => format!("{column} >= {value}"),
+            Op::Contains => {
+                let pattern = self
+                    .value
+                    .replace('\\', "\\\\")
+                    .replace('%', "\\%")
+                    .replace('_', "\\_");
+                format!(
+                    "{column}::text ILIKE {}",
+                    quote_literal(&format!("%{pattern}%"))
+                )
+            }
+            Op::Null => format!("{column} IS NULL"),
+            Op::NotNull => format!("{column} IS NOT NULL"),
+        }
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.column, self.text())
+    }
+}
+
+/// Values of the order columns in the first and last rows of a page.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Bounds {
+    first: Vec<Cell>,
+    last: Vec<Cell>,
+}
+
+/// A column of the order.
+struct Ordered<'a> {
+    column: &'a str,
+    descending: bool,
+    /// Whether it may be `NULL`, which no key column may.
+    nullable: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Browser {
+    /// The table, quoted as the server would.
+    pub table: String,
+    /// Columns of its primary key, which order the rows after `sort`.
+    pub key: Vec<String>,
+    /// A column to order by first, descending with `true`.
+    pub sort: Option<(String, bool)>,
+    /// Conditions the rows must all meet.
+    pub filters: Vec<Filter>,
+    /// The page shown, from 0.
+    pub page: u64,
+    /// Rows to a page.
+    pub size: u64,
+    pub total: Total,
+    /// The bounds of the pages fetched so far, to seek from.
+    bounds: BTreeMap<u64, Bounds>,
+}
+
+impl Browser {
+    pub fn new(table: impl Into<String>, key: Vec<String>, size: u64, total: Total) -> Self {
+        Self {
+            table: table.into(),
+            key,
+            sort: None,
+            filters: Vec::new(),
+            page: 0,
+            size: size.max(1),
+            total,
+            bounds: BTreeMap::new(),
+        }
+    }
+
+    /// Looks `table` up: its name and key. The rows are [counted] after.
+    ///
+    /// [counted]: Self::count
+    pub async fn open(
+        pool: &PgPool,
+        table: &str,
+        sort: Option<(String, bool)>,
+        size: u64,
+    ) -> Result<Self, sqlx::Error> {
+        let table: String = sqlx::query_scalar("SELECT $1::regclass::text")
+            .bind(table)
+            .fetch_one(pool)
+            .await?;
+        let key = sqlx::query_scalar(PRIMARY_KEY)
+            .bind(&table)
+            .fetch_all(pool)
+            .await?;
+        let total = Total {
+            rows: 0,
+            exact: false,
+        };
+        let mut browser = Self::new(table, key, size, total);
+        browser.sort = sort;
+        Ok(browser)
+    }
+
+    /// How many rows pass the filters: counted if the planner expects few
+    /// enough, or else its estimate.
+    pub async fn count(&self, pool: &PgPool) -> Result<Total, sqlx::Error> {
+        let sql = self.select(None, false, 0, None);
+        // With no plan to go by, as on CockroachDB, count them.
+        let estimate = match plan::explain(pool, &sql, &[], false).await {
+            Ok(plan) => plan.nodes.first().map_or(0.0, |node| node.plan_rows),
+            Err(_) => 0.0,
+        };
+        if estimate >= COUNT_BELOW {
+            return Ok(Total {
+                rows: estimate as u64,
+                exact: false,
+            });
+        }
+        let rows: i64 = sqlx::query_scalar(&format!("SELECT count(*) FROM ({sql}) AS counted"))
+            .fetch_one(pool)
+            .await?;
+        Ok(Total {
+            rows: rows as u64,
+            exact: true,
+        })
+    }
+
+    /// Filters `column` with `filter`, in place of any filter it had, or
+    /// not at all with `None`. Back to the first page, and the rows are to
+    /// be counted again.
+    pub fn filter(&mut self, column: &str, filter: Option<Filter>) {
+        self.filters.retain(|filter| filter.column != column);
+        self.filters.extend(filter);
+        self.page = 0;
+        self.bounds.clear();
+    }
+
+    /// Sorts by `column`, ascending or, if it was already, descending. The
+    /// page stays, now of the rows in the new order.
+    pub fn sort_by(&mut self, column: &str) {
+        let descending = self.sort.as_ref() == Some(&(column.to_string(), false));
+        self.sort = Some((column.to_string(), descending));
+        self.bounds.clear();
+    }
+
+    /// Drops every filter, as [`filter`](Self::filter) drops one.
+    pub fn unfilter(&mut self) {
+        self.filters.clear();
+        self.page = 0;
+        self.bounds.clear();
+    }
+
+    /// How many pages there are, at least one.
+    pub fn pages(&self) -> u64 {
+        self.total.rows.div_ceil(self.size).max(1)
+    }
+
+    /// The page `to` turns to from this one. Pages past an estimated end
+    /// may yet have rows, so only counted tables stop at the last one.
+    pub fn target(&self, to: PageTo) -> u64 {
+        let last = self.pages() - 1;
+        let page = match to {
+            PageTo::Next(pages) => self.page.saturating_add(pages),
+            PageTo::Previous(pages) => self.page.saturating_sub(pages),
+            PageTo::Number(number) => number.saturating_sub(1),
+            PageTo::Last => last,
+        };
+        if self.total.exact {
+            page.min(last)
+        } else {
+            page
+        }
+    }
+
+    /// The columns the rows are in order of: `sort`, then the key.
+    fn order(&self) -> Vec<Ordered<'_>> {
+        let sort = self.sort.as_ref().map(|(column, descending)| Ordered {
+            column,
+            descending: *descending,
+            nullable: !self.key.contains(column),
+        });
+        let key = self
+            .key
+            .iter()
+            .filter(|column| self.sort.as_ref().is_none_or(|(sort, _)| sort != *column))
+            .map(|column| Ordered {
+                column,
+                descending: false,
+                nullable: false,
+            });
+        sort.into_iter().chain(key).collect()
+    }
+
+    /// Whether pages can be sought, which takes an order with no ties.
+    fn keyset(&self) -> bool {
+        !self.key.is_empty()
+    }
+
+    fn order_by(&self, reversed: bool) -> String {
+        let columns: Vec<String> = self
+            .order()
+            .iter()
+            .map(|ordered| {
+                let column = quote_ident(ordered.column);
+                if ordered.descending != reversed {
+                    format!("{column} DESC")
+                } else {
+                    column
+                }
+            })
+            .collect();
+        if columns.is_empty() {
+            String::new()
+        } else {
+            format!(" ORDER BY {}", columns.join(", "))
+        }
+    }
+
+    /// The condition for rows after those with the order values `values`,
+    /// or before them with `before`.
+    fn seek(&self, values: &[Cell], before: bool) -> String {
+        let order = self.order();
+        let plain = order
+            .iter()
+            .all(|ordered| !ordered.nullable && ordered.descending == order[0].descending);
+        if plain {
+            // A row comparison, which an index on the order takes as is.
+            let columns: Vec<String> = order
+                .iter()
+                .map(|ordered| quote_ident(ordered.column))
+                .collect();
+            let values: Vec<String> = values
+                .iter()
+                .map(|value| quote_literal(value.as_deref().unwrap_or_default()))
+                .collect();
+            let operator = if order[0].descending != before {
+                "<"
+            } else {
+                ">"
+            };
+            return format!(
+                "({}) {operator} ({})",
+                columns.join(", "),
+                values.join(", ")
+            );
+        }
+        let mut alternatives = Vec::new();
+        for (index, ordered) in order.iter().enumerate() {
+            let Some(past) = past(ordered, &values[index], before) else {
+                continue;
+            };
+            let mut terms: Vec<String> = order[..index]
+                .iter()
+                .zip(values)
+                .map(|(ordered, value)| equal(ordered.column, value))
+                .collect();
+            terms.push(past);
+            alternatives.push(format!("({})", terms.join(" AND ")));
+        }
+        if alternatives.is_empty() {
+            "false".into()
+        } else {
+            alternatives.join(" OR ")
+        }
+    }
+
+    /// The query for a page of `limit` rows, `offset` into those that pass
+    /// the filters and `seek`, read from the end of the order with
+    /// `reversed`. Without a `limit`, all of them in no order.
+    fn select(
+        &self,
+        seek: Option<String>,
+        reversed: bool,
+        offset: u64,
+        limit: Option<u64>,
+    ) -> String {
+        let mut sql = format!("SELECT * FROM {}", self.table);
+        let mut conditions: Vec<String> = self.filters.iter().map(Filter::sql).collect();
+        if let Some(seek) = seek {
+            if conditions.is_empty() {
+                conditions.push(seek);
+            } else {
+                conditions.push(format!("({seek})"));
+            }
+        }
+        if !conditions.is_empty() {
+            sql.push_str(&format!(" WHERE {}", conditions.join(" AND ")));
+        }
+        let Some(limit) = limit else {
+            return sql;
+        };
+        sql.push_str(&self.order_by(reversed));
+        sql.push_str(&format!(" LIMIT {limit}"));
+        if offset > 0 {
+            sql.push_str(&format!(" OFFSET {offset}"));
+        }
+        if reversed {
+            sql = format!("SELECT * FROM ({sql}) AS page{}", self.order_by(false));
+        }
+        sql
+    }
+
+    /// The page `to` turns to and the query for it.
+    pub fn query(&self, to: PageTo) -> (u64, String) {
+        let page = self.target(to);
+        let size = self.size;
+        if !self.keyset() {
+            let offset = page.saturating_mul(size);
+            return (page, self.select(None, false, offset, Some(size)));
+        }
+        if to == PageTo::Last {
+            // Short when counted, as the last page of pages from the start
+            // would be.
+            let rows = match self.total {
+                Total { rows, exact: true } if rows % size != 0 => rows % size,
+                _ => size,
+            };
+            return (page, self.select(None, true, 0, Some(rows)));
+        }
+        // From the start, or else from the closest page fetched on either
+        // side.
+        let mut best = (page, None, false);
+        if let Some((fetched, bounds)) = self.bounds.range(..page).next_back() {
+            let skip = page - fetched - 1;
+            if skip < best.0 {
+                best = (skip, Some(self.seek(&bounds.last, false)), false);
+            }
+        }
+        if let Some((fetched, bounds)) = self.bounds.range(page + 1..).next() {
+            let skip = fetched - page - 1;
+            if skip < best.0 {
+                best = (skip, Some(self.seek(&bounds.first, true)), true);
+            }
+        }
+        let (skip, seek, reversed) = best;
+        let offset = skip.saturating_mul(size);
+        (page, self.select(seek, reversed, offset, Some(size)))
+    }
+
+    /// Notes where `page` starts and ends in the order, from its `results`.
+    pub fn remember(&mut self, page: u64, results: &ResultSet) {
+        let columns: Option<Vec<usize>> = self
+            .order()
+            .iter()
+            .map(|ordered| results.column_index(ordered.column))
+            .collect();
+        let (Some(columns), Some(last)) = (columns, results.rows.len().checked_sub(1)) else {
+            return;
+        };
+        let values = |row| {
+            columns
+                .iter()
+                .map(|col| results.rows.cell(row, *col).map(str::to_string))
+                .collect()
+        };
+        let bounds = Bounds {
+            first: values(0),
+            last: values(last),
+        };
+        self.bounds.insert(page, bounds);
+    }
+
+    /// Fetches the page `to` turns to, returning the browser on it. Past
+    /// the end of an estimated table, that page is empty.
+    pub async fn fetch(&self, pool: &PgPool, to: PageTo) -> Result<(Self, ResultSet), sqlx::Error> {
+        let (page, sql) = self.query(to);
+        let results = results::fetch(pool, &sql, &[]).await?;
+        let mut browser = self.clone();
+        browser.page = page;
+        browser.remember(page, &results);
+        Ok((browser, results))
+    }
+
+    /// The title of the results pane, like `public.orders [total > 10],
+    /// page 3 of 12`.
+    pub fn title(&self) -> String {
+        let estimated = if self.total.exact { "" } else { "~" };
+        let filters: String = self
+            .filters
+            .iter()
+            .map(|filter| format!(" [{filter}]"))
+            .collect();
+        format!(
+            "{}{filters}, page {} of {estimated}{}",
+            self.table,
+            self.page + 1,
+            self.pages()
+        )
+    }
+}
+
+fn equal(column: &str, value: &Cell) -> String {
+    let column = quote_ident(column);
+    match value {
+        Some(value) => format!("{column} = {}", quote_literal(value)),
+        None => format!("{column} IS NULL"),
+    }
+}
+
+/// The condition for values of `ordered` past `value`, or before it with
+/// `before`, `None` if there are none. Nulls come last in ascending order
+/// and first in descending order, as the server sorts them.
+fn past(ordered: &Ordered, value: &Cell, before: bool) -> Option<String> {
+    let column = quote_ident(ordered.column);
+    let descending = ordered.descending != before;
+    match value {
+        Some(value) => {
+            let value = quote_literal(value);
+            Some(match (descending, ordered.nullable) {
+                (false, true) => format!("({column} > {value} OR {column} IS NULL)"),
+                (false, false) => format!("{column} > {value}"),
+                (true, _) => format!("{column} < {value}"),
+            })
+        }
+        None if descending => Some(format!("{column} IS NOT NULL")),
+        None => None,
+    }
+}
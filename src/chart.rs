@@ -0,0 +1,317 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Charts of result columns, `:chart x=<col> y=<col>[,<col>...]
+//! [type=line|bar]`, for the time series a monitoring query returns.
+
+use std::str::FromStr;
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use ratatui::{
+    Frame,
+    layout::Rect,
+    style::{Color, Style},
+    symbols::Marker,
+    text::{Line, Span},
+    widgets::{Axis, Bar, BarChart, BarGroup, Block, Dataset, GraphType},
+};
+
+use crate::results::ResultSet;
+
+const COLORS: [Color; 6] = [
+    Color::Cyan,
+    Color::Yellow,
+    Color::Magenta,
+    Color::Green,
+    Color::Red,
+    Color::Blue,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChartKind {
+    #[default]
+    Line,
+    Bar,
+}
+
+impl FromStr for ChartKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "line" => Ok(Self::Line),
+            "bar" => Ok(Self::Bar),
+            _ => Err(format!("Unknown chart type \"{s}\", use line or bar")),
+        }
+    }
+}
+
+/// What `:chart` was asked to plot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChartSpec {
+    pub x: String,
+    pub y: Vec<String>,
+    pub kind: ChartKind,
+}
+
+impl FromStr for ChartSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const USAGE: &str = "Usage: chart x=<column> y=<column>[,<column>...] [type=line|bar]";
+        let (mut x, mut y, mut kind) = (None, Vec::new(), ChartKind::default());
+        for arg in s.split_whitespace() {
+            match arg.split_once('=') {
+                Some(("x", column)) => x = Some(column.to_string()),
+                Some(("y", columns)) => y.extend(columns.split(',').map(str::to_string)),
+                Some(("type", name)) => kind = name.parse()?,
+                _ => return Err(USAGE.into()),
+            }
+        }
+        match x {
+            Some(x) if !y.is_empty() => Ok(Self { x, y, kind }),
+            _ => Err(USAGE.into()),
+        }
+    }
+}
+
+/// Reads a timestamp or date in Postgres' default output format as seconds
+/// since the epoch.
+fn parse_time(text: &str) -> Option<f64> {
+    if let Ok(time) = DateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S%.f%#z") {
+        return Some(time.timestamp_millis() as f64 / 1000.0);
+    }
+    let time = NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S%.f")
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(text, "%Y-%m-%d")
+                .ok()?
+                .and_hms_opt(0, 0, 0)
+        })?;
+    Some(time.and_utc().timestamp_millis() as f64 / 1000.0)
+}
+
+/// How the x values are placed along the axis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum XAxis {
+    Number,
+    Time,
+    /// Anything else: one point per row, in order.
+    Row,
+}
+
+#[derive(Debug, Clone)]
+pub struct Chart {
+    title: String,
+    kind: ChartKind,
+    x_axis: XAxis,
+    /// The x value of every row as text, for bar labels and `XAxis::Row`.
+    x_labels: Vec<String>,
+    /// A name and `(x, y)` points for each y column.
+    series: Vec<(String, Vec<(f64, f64)>)>,
+}
+
+impl Chart {
+    pub fn new(results: &ResultSet, spec: &ChartSpec) -> Result<Self, String> {
+        let column = |name: &str| {
+            results
+                .column_index(name)
+                .ok_or_else(|| format!("No column {name}"))
+        };
+        let x = column(&spec.x)?;
+        let ys = spec
+            .y
+            .iter()
+            .map(|name| column(name))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let x_labels: Vec<String> = results
+            .rows
+            .iter()
+            .map(|row| row.get(x).map(String::from).unwrap_or_default())
+            .collect();
+        let all = |parse: fn(&str) -> Option<f64>| -> Option<Vec<f64>> {
+            x_labels.iter().map(|label| parse(label)).collect()
+        };
+        let (x_axis, xs) = if let Some(xs) = all(|text| text.parse().ok()) {
+            (XAxis::Number, xs)
+        } else if let Some(xs) = all(parse_time) {
+            (XAxis::Time, xs)
+        } else {
+            (
+                XAxis::Row,
+                (0..x_labels.len()).map(|row| row as f64).collect(),
+            )
+        };
+
+        let series = ys
+            .iter()
+            .map(|&y| {
+                let points = results
+                    .rows
+                    .iter()
+                    .zip(&xs)
+                    .filter_map(|(row, x)| Some((*x, row.get(y)?.parse::<f64>().ok()?)))
+                    .filter(|(_, y)| y.is_finite())
+                    .collect();
+                (results.columns[y].name.clone(), points)
+            })
+            .collect();
+        Ok(Self {
+            title: format!("{} by {}", spec.y.join(", "), results.columns[x].name),
+            kind: spec.kind,
+            x_axis,
+            x_labels,
+            series,
+        })
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    fn x_label(&self, x: f64) -> String {
+        match self.x_axis {
+            XAxis::Number => format_number(x),
+            XAxis::Time => DateTime::from_timestamp_millis((x * 1000.0) as i64)
+                .map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string())
+                .unwrap_or_default(),
+            XAxis::Row => self
+                .x_labels
+                .get(x.round() as usize)
+                .cloned()
+                .unwrap_or_default(),
+        }
+    }
+
+    pub fn render(&self, f: &mut Frame, area: Rect, block: Block) {
+        match self.kind {
+            ChartKind::Line => self.render_line(f, area, block),
+            ChartKind::Bar => self.render_bar(f, area, block),
+        }
+    }
+
+    fn render_line(&self, f: &mut Frame, area: Rect, block: Block) {
+        let points = self.series.iter().flat_map(|(_, points)| points);
+        let bounds = |values: Vec<f64>| {
+            let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+            let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+            match (min.is_finite(), min < max) {
+                (false, _) => [0.0, 1.0],
+                (true, false) => [min - 1.0, max + 1.0],
+                (true, true) => [min, max],
+            }
+        };
+        let x_bounds = bounds(points.clone().map(|(x, _)| *x).collect());
+        let y_bounds = bounds(points.map(|(_, y)| *y).collect());
+        let labels = |[min, max]: [f64; 2], label: &dyn Fn(f64) -> String| -> Vec<Span> {
+            [min, (min + max) / 2.0, max]
+                .into_iter()
+                .map(|value| Span::raw(label(value)))
+                .collect()
+        };
+
+        let datasets = self
+            .series
+            .iter()
+            .zip(COLORS.iter().cycle())
+            .map(|((name, points), color)| {
+                Dataset::default()
+                    .name(name.as_str())
+                    .marker(Marker::Braille)
+                    .graph_type(GraphType::Line)
+                    .style(Style::default().fg(*color))
+                    .data(points)
+            })
+            .collect();
+        let chart = ratatui::widgets::Chart::new(datasets)
+            .block(block)
+            .x_axis(
+                Axis::default()
+                    .bounds(x_bounds)
+                    .labels(labels(x_bounds, &|x| self.x_label(x))),
+            )
+            .y_axis(
+                Axis::default()
+                    .bounds(y_bounds)
+                    .labels(labels(y_bounds, &format_number)),
+            );
+        f.render_widget(chart, area);
+    }
+
+    /// One group of bars per row, one bar per y column. Bars can't go below
+    /// zero, so negative values show as empty bars.
+    fn render_bar(&self, f: &mut Frame, area: Rect, block: Block) {
+        let rows = self.series.iter().map(|(_, p)| p.len()).max().unwrap_or(0);
+        let max = self
+            .series
+            .iter()
+            .flat_map(|(_, points)| points.iter().map(|(_, y)| y.abs()))
+            .fold(0.0, f64::max);
+        // Bars take whole numbers, so scale fractions up.
+        let scale = if max > 0.0 { 1_000_000.0 / max } else { 1.0 };
+        let inner = block.inner(area);
+        let series = self.series.len().max(1) as u16;
+        let per_group = inner.width / rows.max(1) as u16;
+        let bar_width = (per_group.saturating_sub(1) / series).max(1);
+        let groups: Vec<BarGroup> = (0..rows)
+            .map(|row| {
+                let bars: Vec<Bar> = self
+                    .series
+                    .iter()
+                    .zip(COLORS.iter().cycle())
+                    .filter_map(|((_, points), color)| {
+                        let (_, y) = points.get(row)?;
+                        // Values that don't fit the bar would run into the
+                        // next one.
+                        let text = format_number(*y);
+                        let text = if text.len() <= bar_width as usize {
+                            text
+                        } else {
+                            String::new()
+                        };
+                        Some(
+                            Bar::default()
+                                .value((y.max(0.0) * scale) as u64)
+                                .text_value(text)
+                                .style(Style::default().fg(*color)),
+                        )
+                    })
+                    .collect();
+                let x = self.series[0].1.get(row).map_or(row as f64, |(x, _)| *x);
+                BarGroup::default()
+                    .label(Line::from(self.x_label(x)))
+                    .bars(&bars)
+            })
+            .collect();
+
+        let mut chart = BarChart::default()
+            .block(block)
+            .bar_width(bar_width)
+            .bar_gap(0)
+            .group_gap(1);
+        for group in groups {
+            chart = chart.data(group);
+        }
+        f.render_widget(chart, area);
+    }
+}
+
+fn format_number(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        format!("{value:.0}")
+    } else {
+        format!("{value:.2}")
+    }
+}
@@ -0,0 +1,127 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The clipboard yanks are copied to and `p` pastes from: the desktop's, or
+//! the terminal's through OSC 52 when dbvi runs over SSH.
+
+use std::fmt;
+use std::io::{self, Write};
+
+use base64::Engine;
+
+pub enum Clipboard {
+    /// Yanks stay in dbvi, as in tests.
+    None,
+    /// The desktop clipboard.
+    #[cfg(feature = "clipboard")]
+    System(arboard::Clipboard),
+    /// Copies are written to the terminal as OSC 52 escapes, which it puts
+    /// in the clipboard of the machine it runs on. Terminals won't say
+    /// what is in there, so there is nothing to paste.
+    Osc52,
+}
+
+impl fmt::Debug for Clipboard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl Clipboard {
+    /// OSC 52 over SSH, where the desktop clipboard would be the server's,
+    /// otherwise the desktop clipboard if there is one to talk to.
+    pub fn detect() -> Self {
+        if std::env::var_os("SSH_TTY").is_some() || std::env::var_os("SSH_CONNECTION").is_some() {
+            return Self::Osc52;
+        }
+        #[cfg(feature = "clipboard")]
+        if let Ok(clipboard) = arboard::Clipboard::new() {
+            return Self::System(clipboard);
+        }
+        Self::Osc52
+    }
+
+    /// The clipboard `:set clipboard=` and the config name: `auto` to
+    /// [`detect`](Self::detect), `system`, `osc52` or `none`.
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name {
+            "auto" => Ok(Self::detect()),
+            #[cfg(feature = "clipboard")]
+            "system" => arboard::Clipboard::new()
+                .map(Self::System)
+                .map_err(|err| format!("No system clipboard: {err}")),
+            #[cfg(not(feature = "clipboard"))]
+            "system" => Err("Built without the system clipboard, see the clipboard feature".into()),
+            "osc52" => Ok(Self::Osc52),
+            "none" => Ok(Self::None),
+            _ => Err(format!(
+                "Unknown clipboard {name}, expected auto, system, osc52 or none"
+            )),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::None => "none",
+            #[cfg(feature = "clipboard")]
+            Self::System(_) => "system",
+            Self::Osc52 => "osc52",
+        }
+    }
+
+    pub fn copy(&mut self, text: &str) -> Result<(), String> {
+        match self {
+            Self::None => Ok(()),
+            #[cfg(feature = "clipboard")]
+            Self::System(clipboard) => clipboard
+                .set_text(text)
+                .map_err(|err| format!("Failed to copy to the clipboard: {err}")),
+            Self::Osc52 => {
+                let mut escape = osc52(text);
+                // tmux takes the plain escape with `set-clipboard on`, and
+                // hands the wrapped one to the outer terminal with
+                // `allow-passthrough on`. Either may be off.
+                if std::env::var_os("TMUX").is_some() {
+                    escape += &tmux_passthrough(&escape);
+                }
+                let mut stdout = io::stdout();
+                stdout
+                    .write_all(escape.as_bytes())
+                    .and_then(|()| stdout.flush())
+                    .map_err(|err| format!("Failed to copy to the clipboard: {err}"))
+            }
+        }
+    }
+
+    /// What is in the clipboard, `None` if it is empty or can't be read.
+    pub fn paste(&mut self) -> Option<String> {
+        match self {
+            #[cfg(feature = "clipboard")]
+            Self::System(clipboard) => clipboard.get_text().ok().filter(|text| !text.is_empty()),
+            _ => None,
+        }
+    }
+}
+
+/// The escape that sets the clipboard to `text`.
+pub fn osc52(text: &str) -> String {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(text);
+    format!("\x1b]52;c;{encoded}\x07")
+}
+
+/// `escape` wrapped for tmux to pass on to the terminal it runs in, which
+/// it does with `allow-passthrough` on. Escapes inside are doubled.
+pub fn tmux_passthrough(escape: &str) -> String {
+    format!("\x1bPtmux;{}\x1b\\", escape.replace('\x1b', "\x1b\x1b"))
+}
@@ -0,0 +1,422 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Ex commands, the things typed after `:`.
+
+use crate::Command;
+use crate::browse::PageTo;
+use crate::db::asof::{self, AsOf};
+use crate::db::monitor::Report;
+use crate::quickfix::Go;
+use crate::{dump, join, migrate, substitute, transfer, vars};
+
+/// Parses an ex command line (without the leading `:`).
+pub fn parse(input: &str) -> Result<Command, String> {
+    let input = input.trim();
+    if let Some(substitute) = substitute::parse(input) {
+        return substitute.map(Command::Substitute);
+    }
+    if let Some(command) = input.strip_prefix('!') {
+        return match command.trim() {
+            "" => Err("Usage: !<command>".into()),
+            command => Ok(Command::Shell(command.to_string())),
+        };
+    }
+    let (name, args) = input
+        .split_once(char::is_whitespace)
+        .map_or((input, ""), |(name, args)| (name, args.trim()));
+    match name {
+        "" => Ok(Command::None),
+        "q" | "quit" => Ok(Command::Quit { force: false }),
+        "q!" | "quit!" => Ok(Command::Quit { force: true }),
+        "wq" | "x" | "xit" => Ok(Command::Chain(vec![
+            Command::Write((!args.is_empty()).then(|| args.to_string())),
+            Command::Quit { force: false },
+        ])),
+        "e" | "edit" if !args.is_empty() => Ok(Command::Edit(args.to_string())),
+        "e" | "edit" => Err("Usage: edit <path>".into()),
+        "conninfo" => Ok(Command::ConnInfo),
+        "db" if args.is_empty() => Ok(Command::Report(Report::Databases)),
+        "db" => Ok(Command::SwitchDatabase(args.to_string())),
+        "connect" if !args.is_empty() => Ok(Command::Connect(args.to_string())),
+        "connect" => Err("Usage: connect <url>".into()),
+        "disconnect" => Ok(Command::Disconnect),
+        "killswitch" => Ok(Command::KillSwitch),
+        "reconnect" => Ok(Command::Reconnect),
+        "tables" => Ok(Command::Tables),
+        "definition" | "def" if !args.is_empty() => Ok(Command::Definition(args.to_string())),
+        "definition" | "def" => Err("Usage: definition <view or function>".into()),
+        "ddl" if !args.is_empty() => Ok(Command::Ddl(args.to_string())),
+        "ddl" => Err("Usage: ddl <table>".into()),
+        "generate" | "gen" => match args.split_once(char::is_whitespace) {
+            Some((kind, table)) => Ok(Command::Generate(kind.parse()?, table.trim().to_string())),
+            None => Err("Usage: generate select|insert|update <table>".into()),
+        },
+        "%y" | "%yank" => Ok(Command::YankBuffer),
+        "w" | "write" => Ok(Command::Write((!args.is_empty()).then(|| args.to_string()))),
+        "so" | "source" => {
+            let force = args.split_whitespace().any(|arg| arg == "--force");
+            let path: Vec<&str> = args
+                .split_whitespace()
+                .filter(|arg| *arg != "--force")
+                .collect();
+            match path.as_slice() {
+                [path] => Ok(Command::Source {
+                    path: path.to_string(),
+                    force,
+                }),
+                _ => Err("Usage: source [--force] <path>".into()),
+            }
+        }
+        "copy" if !args.is_empty() => Ok(Command::CopyOut(args.to_string())),
+        "copy-to" => {
+            let (target, table, source) = match args.split_whitespace().collect::<Vec<_>>()[..] {
+                [target, table] => (target, table, transfer::Source::Query),
+                [target, table, "results"] => (target, table, transfer::Source::Results),
+                _ => return Err("Usage: copy-to <profile or url> <table> [results]".into()),
+            };
+            Ok(Command::CopyTo {
+                target: target.to_string(),
+                table: table.to_string(),
+                source,
+            })
+        }
+        "copy" => Err("Usage: copy <path>".into()),
+        "dump" => {
+            let (scope, path) = match args.split_whitespace().collect::<Vec<_>>()[..] {
+                ["table", table, path] => (dump::Scope::Table(table.to_string()), path),
+                ["schema", schema, path] => (dump::Scope::Schema(schema.to_string()), path),
+                ["db", path] => (dump::Scope::Database, path),
+                _ => {
+                    return Err(
+                        "Usage: dump table <table> <path> | schema <schema> <path> | db <path>"
+                            .into(),
+                    );
+                }
+            };
+            Ok(Command::Dump {
+                scope,
+                path: path.to_string(),
+            })
+        }
+        "restore" if !args.is_empty() => Ok(Command::Restore(args.to_string())),
+        "restore" => Err("Usage: restore <path>".into()),
+        "import" => match args.split_whitespace().collect::<Vec<_>>().as_slice() {
+            ["csv", path] => Ok(Command::Import {
+                path: path.to_string(),
+                table: None,
+            }),
+            ["csv", path, table] => Ok(Command::Import {
+                path: path.to_string(),
+                table: Some(table.to_string()),
+            }),
+            _ => Err("Usage: import csv <file> [table]".into()),
+        },
+        "recover" => match args {
+            "" => Ok(Command::Recover { discard: false }),
+            "discard" => Ok(Command::Recover { discard: true }),
+            _ => Err("Usage: recover [discard]".into()),
+        },
+        "log" => Ok(Command::Log),
+        "record" if args.is_empty() => Ok(Command::Record(None)),
+        "record" => Ok(Command::Record(Some(args.to_string()))),
+        "noh" | "nohl" | "nohlsearch" => Ok(Command::NoHighlight),
+        "explain" => match args {
+            "" => Ok(Command::Explain { analyze: false }),
+            "analyze" => Ok(Command::Explain { analyze: true }),
+            _ => Err("Usage: explain [analyze]".into()),
+        },
+        "plandiff" => Ok(Command::PlanDiff),
+        "suggest-index" => Ok(Command::SuggestIndex),
+        "stats" => Ok(Command::Stats),
+        "capabilities" | "caps" => Ok(Command::Capabilities),
+        "erd" => Ok(Command::Erd((!args.is_empty()).then(|| args.to_string()))),
+        "bench" => {
+            const USAGE: &str = "Usage: bench <runs> [warmup]";
+            match args.split_whitespace().collect::<Vec<_>>().as_slice() {
+                [runs, rest @ ..] if rest.is_empty() || rest == ["warmup"] => match runs.parse() {
+                    Ok(runs) if runs > 0 => Ok(Command::Bench {
+                        runs,
+                        warm_up: !rest.is_empty(),
+                    }),
+                    _ => Err(USAGE.into()),
+                },
+                _ => Err(USAGE.into()),
+            }
+        }
+        "activity" => Ok(Command::Report(Report::Activity)),
+        "locks" => Ok(Command::Report(Report::Locks)),
+        "statements" => Ok(Command::Report(Report::Statements)),
+        "maintenance" | "bloat" => Ok(Command::Report(Report::Maintenance)),
+        "roles" => Ok(Command::Report(Report::Roles)),
+        "replication" => Ok(Command::Report(Report::Replication)),
+        "setval" | "setval!" => match args.split_whitespace().collect::<Vec<_>>().as_slice() {
+            [sequence, value] => match value.parse() {
+                Ok(value) => Ok(Command::SetVal {
+                    sequence: sequence.to_string(),
+                    value,
+                    force: name.ends_with('!'),
+                }),
+                Err(_) => Err(format!("{value} isn't a number")),
+            },
+            _ => Err(format!("Usage: {name} <sequence> <value>")),
+        },
+        "sequences" => Ok(Command::Report(Report::Sequences)),
+        "extensions" | "ext" => Ok(Command::Report(Report::Extensions)),
+        "privileges" | "grants" if !args.is_empty() => {
+            Ok(Command::Report(Report::Privileges(args.to_string())))
+        }
+        "privileges" | "grants" => Err("Usage: privileges <table>".into()),
+        "partitions" if !args.is_empty() => {
+            Ok(Command::Report(Report::Partitions(args.to_string())))
+        }
+        "partitions" => Err("Usage: partitions <table>".into()),
+        "matviews" => Ok(Command::Report(Report::MatViews)),
+        "report" => match args.split_whitespace().collect::<Vec<_>>().as_slice() {
+            [template] => Ok(Command::RenderReport {
+                template: template.to_string(),
+                output: None,
+            }),
+            [template, output] => Ok(Command::RenderReport {
+                template: template.to_string(),
+                output: Some(output.to_string()),
+            }),
+            _ => Err("Usage: report <template> [output]".into()),
+        },
+        "refresh" => match args.split_whitespace().collect::<Vec<_>>().as_slice() {
+            [matview] => Ok(Command::Refresh {
+                matview: matview.to_string(),
+                concurrently: false,
+            }),
+            ["concurrently", matview] => Ok(Command::Refresh {
+                matview: matview.to_string(),
+                concurrently: true,
+            }),
+            _ => Err("Usage: refresh [concurrently] <matview>".into()),
+        },
+        "grep-schema" if !args.is_empty() => Ok(Command::Report(Report::Grep(args.to_string()))),
+        "grep-schema" => Err("Usage: grep-schema <pattern>".into()),
+        "sizes" => Ok(Command::Report(Report::Sizes(
+            (!args.is_empty()).then(|| args.to_string()),
+        ))),
+        "chart" => Ok(Command::Chart(args.parse()?)),
+        "pivot" => Ok(Command::Pivot(args.parse()?)),
+        "histogram" | "hist" => Ok(Command::Histogram(
+            (!args.is_empty()).then(|| args.to_string()),
+        )),
+        "plugins" => Ok(Command::Plugins),
+        "transform" if !args.is_empty() => Ok(Command::Transform(args.to_string())),
+        "transform" => Err("Usage: transform <plugin transform>".into()),
+        "export" => match args.split_once(char::is_whitespace) {
+            Some((exporter, path)) => Ok(Command::Export {
+                exporter: exporter.to_string(),
+                path: path.trim().to_string(),
+            }),
+            None => Err("Usage: export csv|table|xlsx|html|<plugin exporter> <file>".into()),
+        },
+        "lua" if !args.is_empty() => Ok(Command::Lua(args.to_string())),
+        "lua" => Err("Usage: lua <code>".into()),
+        "edit!" => Ok(Command::EditExternal),
+        "pipe" if !args.is_empty() => Ok(Command::Pipe(args.to_string())),
+        "pipe" => Err("Usage: pipe <command>".into()),
+        "page" if args.is_empty() => Ok(Command::Page),
+        "page" => match args.parse() {
+            Ok(number) if number > 0 => Ok(Command::TurnPage(PageTo::Number(number))),
+            _ => Err("Usage: page [number]".into()),
+        },
+        "nofilter" => Ok(Command::Unfilter),
+        "apply" => Ok(Command::ApplyEdits),
+        "discard" => Ok(Command::DiscardEdits),
+        "browse" => {
+            let words: Vec<&str> = args.split_whitespace().collect();
+            let (table, sort) = match words.as_slice() {
+                [table] => (table, None),
+                [table, "by", column] => (table, Some((column.to_string(), false))),
+                [table, "by", column, order @ ("asc" | "desc")] => {
+                    (table, Some((column.to_string(), *order == "desc")))
+                }
+                _ => return Err("Usage: browse <table> [by <column> [asc|desc]]".into()),
+            };
+            Ok(Command::Browse {
+                table: table.to_string(),
+                sort,
+            })
+        }
+        "savepoint" | "release" if vars::is_name(args) => {
+            let verb = match name {
+                "savepoint" => "SAVEPOINT",
+                _ => "RELEASE SAVEPOINT",
+            };
+            Ok(Command::RunQuery(format!("{verb} {args}")))
+        }
+        "savepoint" | "release" => Err(format!("Usage: {name} <name>")),
+        "rollback" => match args.split_whitespace().collect::<Vec<_>>().as_slice() {
+            ["to", savepoint] | ["to", "savepoint", savepoint] if vars::is_name(savepoint) => Ok(
+                Command::RunQuery(format!("ROLLBACK TO SAVEPOINT {savepoint}")),
+            ),
+            _ => Err("Usage: rollback to <savepoint>".into()),
+        },
+        "run" => match args {
+            "" => Ok(Command::Run { expect: None }),
+            _ => match args.strip_prefix("expect=").map(str::parse) {
+                Some(Ok(rows)) => Ok(Command::Run { expect: Some(rows) }),
+                _ => Err("Usage: run [expect=<rows>]".into()),
+            },
+        },
+        "jobs" => Ok(Command::Jobs),
+        "migrate" => migrate::Action::parse(args).map(Command::Migrate),
+        "queue" => Ok(Command::Queue),
+        "queued" => Ok(Command::Queued),
+        "copen" => Ok(Command::OpenQuickfix),
+        "diagnostics" => Ok(Command::Diagnostics),
+        "cclose" => Ok(Command::CloseQuickfix),
+        "cnext" | "cn" => Ok(Command::Quickfix(Go::Next)),
+        "cprev" | "cp" | "cNext" | "cN" => Ok(Command::Quickfix(Go::Prev)),
+        "cfirst" | "crewind" => Ok(Command::Quickfix(Go::First)),
+        "clast" => Ok(Command::Quickfix(Go::Last)),
+        "cc" => match args {
+            "" => Ok(Command::Quickfix(Go::Current)),
+            _ => args
+                .parse()
+                .map(|n| Command::Quickfix(Go::Nth(n)))
+                .map_err(|_| "Usage: cc [<item>]".into()),
+        },
+        "save-query" | "save-query!" => {
+            let (tags, words): (Vec<&str>, Vec<&str>) = args
+                .split_whitespace()
+                .partition(|word| word.starts_with('#'));
+            match words.as_slice() {
+                [query] => Ok(Command::SaveQuery {
+                    name: query.to_string(),
+                    tags: tags.iter().map(|tag| tag[1..].to_string()).collect(),
+                    force: name.ends_with('!'),
+                }),
+                _ => Err("Usage: save-query <name> [#tag...]".into()),
+            }
+        }
+        "queries" => Ok(Command::Queries),
+        "prepare" if vars::is_name(args) => Ok(Command::Prepare(args.to_lowercase())),
+        "prepare" => Err("Usage: prepare <name>".into()),
+        "execute" => {
+            let (statement, rest) = args
+                .split_once('(')
+                .map_or((args, None), |(statement, rest)| {
+                    (statement.trim(), Some(rest))
+                });
+            let args = match rest.map(|rest| rest.trim_end().strip_suffix(')')) {
+                None => None,
+                Some(Some(args)) => Some(args.trim().to_string()),
+                Some(None) => return Err("Usage: execute <name> [(args…)]".into()),
+            };
+            if !vars::is_name(statement) {
+                return Err("Usage: execute <name> [(args…)]".into());
+            }
+            Ok(Command::Execute {
+                name: statement.to_lowercase(),
+                args,
+            })
+        }
+        "prepared" => Ok(Command::Report(Report::Prepared)),
+        "setvar" | "unsetvar" => {
+            let (var, value) = args
+                .split_once(char::is_whitespace)
+                .map_or((args, ""), |(var, value)| (var, value.trim()));
+            match (name, value) {
+                ("setvar", _) if !vars::is_name(var) => Err("Usage: setvar <name> [value]".into()),
+                ("unsetvar", "") if vars::is_name(var) => Ok(Command::SetVar {
+                    name: var.to_string(),
+                    value: None,
+                }),
+                ("unsetvar", _) => Err("Usage: unsetvar <name>".into()),
+                _ => Ok(Command::SetVar {
+                    name: var.to_string(),
+                    value: Some(vars::parse_value(value)?),
+                }),
+            }
+        }
+        "vars" => Ok(Command::Vars),
+        "redact" if args.is_empty() => Ok(Command::Redact(None)),
+        "redact" => Ok(Command::Redact(Some(args.to_string()))),
+        "unredact" if args.is_empty() => Err("Usage: unredact <column>".into()),
+        "unredact" => Ok(Command::Unredact(args.to_string())),
+        "compare" => match args.split_whitespace().collect::<Vec<_>>().as_slice() {
+            [a, b] => Ok(Command::Compare([a.to_string(), b.to_string()])),
+            _ => Err("Usage: compare <profile or URL> <profile or URL>".into()),
+        },
+        "asof" => match args {
+            "" => Ok(Command::AsOf(AsOf::Take)),
+            "run" => Ok(Command::AsOf(AsOf::Run)),
+            "close" => Ok(Command::AsOf(AsOf::Close)),
+            id if asof::is_id(id) => Ok(Command::AsOf(AsOf::Import(id.to_string()))),
+            _ => Err("Usage: asof [<snapshot id>], asof run or asof close".into()),
+        },
+        "rowdiff" => match args.split_whitespace().collect::<Vec<_>>().as_slice() {
+            [] => Ok(Command::RowDiff(None)),
+            [a, b] => match (a.parse(), b.parse()) {
+                (Ok(a @ 1..), Ok(b @ 1..)) => Ok(Command::RowDiff(Some((a, b)))),
+                _ => Err("Usage: rowdiff [<row> <row>]".into()),
+            },
+            _ => Err("Usage: rowdiff [<row> <row>]".into()),
+        },
+        "result" => match args.split_whitespace().collect::<Vec<_>>().as_slice() {
+            [verb @ ("save" | "save!"), result] => Ok(Command::SaveResult {
+                name: result.to_string(),
+                force: verb.ends_with('!'),
+            }),
+            ["load", result] => Ok(Command::LoadResult(result.to_string())),
+            [] | ["list"] => Ok(Command::ListResults),
+            _ => Err("Usage: result save[!]|load <name>, or result list".into()),
+        },
+        "join" => join::Spec::parse(args).map(Command::Join),
+        "schema" => match args {
+            "" => Ok(Command::Schema { refresh: false }),
+            "refresh" => Ok(Command::Schema { refresh: true }),
+            _ => {
+                // Set the session's search_path, which is replayed on
+                // reconnect like any other SET.
+                let names: Vec<&str> = args.split(',').map(str::trim).collect();
+                let quoted = |name: &str| {
+                    name.len() > 2
+                        && name.starts_with('"')
+                        && name.ends_with('"')
+                        && !name[1..name.len() - 1].contains('"')
+                };
+                if names.iter().all(|name| vars::is_name(name) || quoted(name)) {
+                    Ok(Command::RunQuery(format!(
+                        "SET search_path TO {}",
+                        names.join(", ")
+                    )))
+                } else {
+                    Err("Usage: schema [refresh | <name>, ...]".into())
+                }
+            }
+        },
+        "bn" | "bnext" => Ok(Command::NextBuffer),
+        "bp" | "bprevious" => Ok(Command::PreviousBuffer),
+        "bd" | "bdelete" => Ok(Command::DeleteBuffer { force: false }),
+        "bd!" | "bdelete!" => Ok(Command::DeleteBuffer { force: true }),
+        "set" => {
+            let (option, value) = args
+                .split_once(['=', ' '])
+                .map_or((args, None), |(option, value)| {
+                    (option, Some(value.trim().to_string()))
+                });
+            Ok(Command::Set {
+                option: option.to_string(),
+                value,
+            })
+        }
+        _ => Err(format!("Not an editor command: {input}")),
+    }
+}
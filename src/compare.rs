@@ -0,0 +1,325 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `:compare`: the buffer's query run against two connections, the rows
+//! side by side with those that differ highlighted, to check that staging
+//! matches prod after a sync. Rows are matched by their first column, the
+//! key as far as the compare knows, so the two needn't come back in the
+//! same order or with the same rows.
+
+use std::collections::{HashMap, VecDeque};
+
+use ratatui::{
+    Frame,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::Paragraph,
+};
+
+use crate::grid;
+use crate::redact::Redactions;
+use crate::results::ResultSet;
+use crate::width;
+
+/// Columns are no wider than this, to fit more of them in half a pane.
+const MAX_WIDTH: usize = 24;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    Same,
+    /// The same key with other values.
+    Changed,
+    /// A key only the first connection has.
+    Removed,
+    /// A key only the second connection has.
+    Added,
+}
+
+#[derive(Debug)]
+struct Pair {
+    rows: [Option<usize>; 2],
+    change: Change,
+}
+
+#[derive(Debug)]
+pub struct Compare {
+    pub title: String,
+    /// As shown, masked if redacted.
+    results: [ResultSet; 2],
+    pairs: Vec<Pair>,
+    widths: Vec<usize>,
+    /// Only the rows that differ, `d` switches.
+    pub only_differing: bool,
+    /// The first column shown, `h` and `l` scroll.
+    pub first_col: usize,
+    pub cursor: usize,
+    scroll: usize,
+}
+
+impl Compare {
+    /// `a` from the connection named `names[0]` against `b` from the one
+    /// named `names[1]`.
+    pub fn new(names: [&str; 2], a: &ResultSet, b: &ResultSet, redactions: &Redactions) -> Self {
+        let pairs = pair(a, b);
+        let count = |change| pairs.iter().filter(|pair| pair.change == change).count();
+        let title = format!(
+            "{} │ {}: {} same, {} changed, {} only in {}, {} only in {} (d for only those)",
+            names[0],
+            names[1],
+            count(Change::Same),
+            count(Change::Changed),
+            count(Change::Removed),
+            names[0],
+            count(Change::Added),
+            names[1],
+        );
+        let results = [
+            redactions.apply(a).into_owned(),
+            redactions.apply(b).into_owned(),
+        ];
+        let columns = a.columns.len().max(b.columns.len());
+        let widths = (0..columns)
+            .map(|col| {
+                results
+                    .iter()
+                    .flat_map(|results| {
+                        let name = results.columns.get(col).map(|column| column.name.as_str());
+                        let cells = (0..results.rows.len())
+                            .filter(move |_| col < results.columns.len())
+                            .map(move |row| shown(results.rows.cell(row, col)));
+                        name.map(width::width)
+                            .into_iter()
+                            .chain(cells.map(|cell| width::width(&cell)))
+                    })
+                    .max()
+                    .unwrap_or(0)
+                    .clamp(4, MAX_WIDTH)
+            })
+            .collect();
+        Self {
+            title,
+            results,
+            pairs,
+            widths,
+            only_differing: false,
+            first_col: 0,
+            cursor: 0,
+            scroll: 0,
+        }
+    }
+
+    /// How many rows differ, changed or only on one side.
+    pub fn differing(&self) -> usize {
+        self.pairs
+            .iter()
+            .filter(|pair| pair.change != Change::Same)
+            .count()
+    }
+
+    /// What became of each row, in the order shown.
+    pub fn changes(&self) -> Vec<Change> {
+        self.shown().iter().map(|pair| pair.change).collect()
+    }
+
+    /// The rows shown, all of them or the differing ones.
+    fn shown(&self) -> Vec<&Pair> {
+        self.pairs
+            .iter()
+            .filter(|pair| pair.change != Change::Same || !self.only_differing)
+            .collect()
+    }
+
+    pub fn toggle_only_differing(&mut self) {
+        self.only_differing = !self.only_differing;
+        self.cursor = 0;
+    }
+
+    pub fn move_by(&mut self, rows: isize) {
+        let last = self.shown().len().saturating_sub(1);
+        self.cursor = self.cursor.saturating_add_signed(rows).min(last);
+    }
+
+    pub fn scroll_cols(&mut self, cols: isize) {
+        let last = self.widths.len().saturating_sub(1);
+        self.first_col = self.first_col.saturating_add_signed(cols).min(last);
+    }
+
+    pub fn top(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn bottom(&mut self) {
+        self.cursor = self.shown().len().saturating_sub(1);
+    }
+
+    /// The names over the columns, then a line a row: `~` and the cells
+    /// that differ in yellow for a changed row, `-` in red and `+` in green
+    /// for one only a side has.
+    pub fn render(&mut self, f: &mut Frame, area: Rect, focused: bool) {
+        let pairs = self.shown();
+        let height = (area.height as usize).saturating_sub(1);
+        let cursor = self.cursor.min(pairs.len().saturating_sub(1));
+        let mut scroll = self.scroll;
+        if cursor < scroll {
+            scroll = cursor;
+        } else if height > 0 && cursor >= scroll + height {
+            scroll = cursor + 1 - height;
+        }
+        let half = (area.width as usize).saturating_sub(5) / 2;
+        let separator = Span::styled(" │ ", Style::default().fg(Color::DarkGray));
+
+        // A side of a line: cells, or names with `row` None, cut to `half`.
+        let side = |side: usize, row: Option<usize>, style: &dyn Fn(usize) -> Style| {
+            let results = &self.results[side];
+            let mut spans = Vec::new();
+            let mut room = half;
+            for (col, &width) in self.widths.iter().enumerate().skip(self.first_col) {
+                if room == 0 {
+                    break;
+                }
+                let text = match row {
+                    _ if col >= results.columns.len() => String::new(),
+                    Some(row) => shown(results.rows.cell(row, col)),
+                    None => results.columns[col].name.clone(),
+                };
+                let width = width.min(room);
+                spans.push(Span::styled(
+                    width::pad(&width::truncate(&text, width), width, false),
+                    style(col),
+                ));
+                room -= width;
+                if room > 0 {
+                    spans.push(Span::raw(" "));
+                    room -= 1;
+                }
+            }
+            if room > 0 {
+                spans.push(Span::raw(" ".repeat(room)));
+            }
+            spans
+        };
+
+        let bold = |_| Style::default().add_modifier(Modifier::BOLD);
+        let mut header = vec![Span::raw("  ")];
+        header.extend(side(0, None, &bold));
+        header.push(separator.clone());
+        header.extend(side(1, None, &bold));
+        let mut lines = vec![Line::from(header)];
+        for (index, pair) in pairs.iter().enumerate().skip(scroll).take(height) {
+            let [a, b] = pair.rows;
+            let differs = |col: usize| match (a, b) {
+                (Some(a), Some(b)) => {
+                    cell(&self.results[0], a, col) != cell(&self.results[1], b, col)
+                }
+                _ => true,
+            };
+            let style = |col: usize| match pair.change {
+                Change::Same => Style::default(),
+                Change::Changed if differs(col) => Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+                Change::Changed => Style::default(),
+                Change::Removed => Style::default().fg(Color::Red),
+                Change::Added => Style::default().fg(Color::Green),
+            };
+            let marker = match pair.change {
+                Change::Same => "  ",
+                Change::Changed => "~ ",
+                Change::Removed => "- ",
+                Change::Added => "+ ",
+            };
+            let mut spans = vec![Span::raw(marker)];
+            for (which, row) in [a, b].into_iter().enumerate() {
+                if which == 1 {
+                    spans.push(separator.clone());
+                }
+                match row {
+                    Some(_) => spans.extend(side(which, row, &style)),
+                    None => spans.push(Span::raw(" ".repeat(half))),
+                }
+            }
+            let mut line = Line::from(spans);
+            if focused && index == cursor {
+                line = line.patch_style(Style::default().add_modifier(Modifier::REVERSED));
+            }
+            lines.push(line);
+        }
+        f.render_widget(Paragraph::new(lines), area);
+        self.cursor = cursor;
+        self.scroll = scroll;
+    }
+}
+
+fn cell(results: &ResultSet, row: usize, col: usize) -> Option<&str> {
+    match col < results.columns.len() {
+        true => results.rows.cell(row, col),
+        false => None,
+    }
+}
+
+fn shown(cell: Option<&str>) -> String {
+    match cell {
+        Some(cell) => grid::display(cell),
+        None => "NULL".to_string(),
+    }
+}
+
+/// Matches the rows of `a` with those of `b` by their first column, in the
+/// order of `a` with the rows only `b` has where `b` has them.
+fn pair(a: &ResultSet, b: &ResultSet) -> Vec<Pair> {
+    let mut by_key: HashMap<Option<&str>, VecDeque<usize>> = HashMap::new();
+    for row in 0..b.rows.len() {
+        by_key.entry(cell(b, row, 0)).or_default().push_back(row);
+    }
+    let matches: Vec<Option<usize>> = (0..a.rows.len())
+        .map(|row| by_key.get_mut(&cell(a, row, 0))?.pop_front())
+        .collect();
+    let mut matched = vec![false; b.rows.len()];
+    for &other in matches.iter().flatten() {
+        matched[other] = true;
+    }
+    let columns = a.columns.len().max(b.columns.len());
+    let mut pairs = Vec::new();
+    let mut next = 0;
+    let added = |pairs: &mut Vec<Pair>, until: usize, next: &mut usize| {
+        pairs.extend(
+            (*next..until)
+                .filter(|&other| !matched[other])
+                .map(|other| Pair {
+                    rows: [None, Some(other)],
+                    change: Change::Added,
+                }),
+        );
+        *next = (*next).max(until);
+    };
+    for (row, other) in matches.into_iter().enumerate() {
+        let Some(other) = other else {
+            pairs.push(Pair {
+                rows: [Some(row), None],
+                change: Change::Removed,
+            });
+            continue;
+        };
+        added(&mut pairs, other, &mut next);
+        next = next.max(other + 1);
+        let same = (0..columns).all(|col| cell(a, row, col) == cell(b, other, col));
+        pairs.push(Pair {
+            rows: [Some(row), Some(other)],
+            change: if same { Change::Same } else { Change::Changed },
+        });
+    }
+    added(&mut pairs, b.rows.len(), &mut next);
+    pairs
+}
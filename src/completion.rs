@@ -0,0 +1,229 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The insert mode completion menu of `<C-n>`: what the language server
+//! offers for the word before the cursor or, without one, the names of the
+//! cached schema that start with it. Inside the quotes of `status = '`,
+//! it is the labels of the enum column compared to. `<C-n>` and `<C-p>` pick, `<Tab>` or
+//! `<CR>` takes the pick, and any other key closes the menu and goes on as
+//! typed.
+
+use ratatui::{
+    Frame,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+};
+
+use crate::db::schema::{self, Schema};
+use crate::editor::Buffer;
+use crate::lsp::Completion;
+use crate::width;
+
+/// Items shown at once.
+const HEIGHT: usize = 8;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Menu {
+    pub items: Vec<Completion>,
+    pub selected: usize,
+    /// The char column the word being completed starts at.
+    pub start: usize,
+}
+
+/// The char column the word ending at char `col` of `line` starts at.
+pub fn word_start(line: &str, col: usize) -> usize {
+    let chars: Vec<char> = line.chars().take(col).collect();
+    chars
+        .iter()
+        .rposition(|c| !(c.is_alphanumeric() || *c == '_' || *c == '$' || !c.is_ascii()))
+        .map_or(0, |i| i + 1)
+}
+
+/// The relations and columns of `schema` whose names start with `prefix`,
+/// ignoring case.
+pub fn from_schema(schema: &Schema, prefix: &str) -> Vec<Completion> {
+    let prefix = prefix.to_lowercase();
+    let mut items: Vec<Completion> = Vec::new();
+    for relation in schema.relations.values() {
+        let name = relation.qualified();
+        if relation.name.to_lowercase().starts_with(&prefix)
+            || name.to_lowercase().starts_with(&prefix)
+        {
+            items.push(Completion {
+                label: name.clone(),
+                insert: name,
+                detail: Some(relation.kind.clone()),
+            });
+        }
+        for column in &relation.columns {
+            let bare = column.name.trim_matches('"').to_lowercase();
+            if bare.starts_with(&prefix) && !items.iter().any(|item| item.label == column.name) {
+                items.push(Completion {
+                    label: column.name.clone(),
+                    insert: column.name.clone(),
+                    detail: Some(column.ty.clone()),
+                });
+            }
+        }
+    }
+    items.sort_by(|a, b| a.label.cmp(&b.label));
+    items
+}
+
+/// The labels of the enum column the string the cursor is in, at char `col`
+/// of `line`, is compared to, as in `status = 'sh`: where the label starts,
+/// and those that start with what is typed of it, ignoring case. `None`
+/// outside such a string.
+pub fn enum_labels(schema: &Schema, line: &str, col: usize) -> Option<(usize, Vec<Completion>)> {
+    let chars: Vec<char> = line.chars().collect();
+    let before = &chars[..col.min(chars.len())];
+    let mut open = None;
+    let mut i = 0;
+    while i < before.len() {
+        match open {
+            _ if before[i] != '\'' => {}
+            None => open = Some(i),
+            // A doubled quote is one in the string.
+            Some(_) if before.get(i + 1) == Some(&'\'') => i += 1,
+            Some(_) => open = None,
+        }
+        i += 1;
+    }
+    let quote = open?;
+    let left: String = before[..quote].iter().collect();
+    let left = left.trim_end();
+    let left = ["=", "<>", "!="]
+        .iter()
+        .find_map(|op| left.strip_suffix(op))?
+        .trim_end();
+    let start = left
+        .rfind(|c: char| !(c.is_alphanumeric() || matches!(c, '_' | '$' | '"')))
+        .map_or(0, |i| i + 1);
+    let name = schema::fold(&left[start..]);
+    let typed = before[quote + 1..]
+        .iter()
+        .collect::<String>()
+        .replace("''", "'")
+        .to_lowercase();
+    let closed = chars.get(col) == Some(&'\'');
+
+    let mut items: Vec<Completion> = Vec::new();
+    let columns = schema
+        .relations
+        .values()
+        .flat_map(|relation| &relation.columns)
+        .filter(|column| schema::fold(&column.name) == name);
+    for column in columns {
+        for label in &column.labels {
+            if label.to_lowercase().starts_with(&typed)
+                && !items.iter().any(|item| item.label == *label)
+            {
+                let quoted = label.replace('\'', "''");
+                items.push(Completion {
+                    label: label.clone(),
+                    insert: if closed { quoted } else { format!("{quoted}'") },
+                    detail: Some(column.ty.clone()),
+                });
+            }
+        }
+    }
+    Some((quote + 1, items))
+}
+
+impl Menu {
+    /// The menu of `items` for the word before the cursor of `buffer`,
+    /// `None` if there is nothing to offer.
+    pub fn new(items: Vec<Completion>, buffer: &Buffer) -> Option<Self> {
+        Self::at(items, word_start(buffer.line(), buffer.cursor.col))
+    }
+
+    /// The menu of `items` for what was typed since char column `start`.
+    pub fn at(items: Vec<Completion>, start: usize) -> Option<Self> {
+        if items.is_empty() {
+            return None;
+        }
+        Some(Self {
+            items,
+            selected: 0,
+            start,
+        })
+    }
+
+    pub fn move_by(&mut self, delta: isize) {
+        let len = self.items.len() as isize;
+        self.selected = (self.selected as isize + delta).rem_euclid(len) as usize;
+    }
+
+    /// Puts the pick in place of the word before the cursor of `buffer`.
+    pub fn accept(&self, buffer: &mut Buffer) {
+        let Some(item) = self.items.get(self.selected) else {
+            return;
+        };
+        for _ in self.start..buffer.cursor.col {
+            buffer.backspace();
+        }
+        buffer.insert_str(&item.insert);
+    }
+
+    /// Draws the menu just below the cursor at `x`, `y` (above it if there
+    /// is no room), within `bounds`.
+    pub fn render(&self, f: &mut Frame, bounds: Rect, x: u16, y: u16) {
+        let first = self.selected.saturating_sub(HEIGHT - 1);
+        let lines: Vec<Line> = self
+            .items
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(HEIGHT)
+            .map(|(i, item)| {
+                let style = if i == self.selected {
+                    Style::default().add_modifier(Modifier::REVERSED)
+                } else {
+                    Style::default()
+                };
+                let mut spans = vec![Span::styled(item.label.clone(), style)];
+                if let Some(detail) = &item.detail {
+                    spans.push(Span::styled(
+                        format!("  {}", width::truncate(detail, 30)),
+                        Style::default().fg(Color::DarkGray),
+                    ));
+                }
+                Line::from(spans)
+            })
+            .collect();
+        let width = lines.iter().map(Line::width).max().unwrap_or(0) as u16 + 2;
+        let width = width.min(bounds.width);
+        let height = (lines.len() as u16 + 2).min(bounds.height);
+        let y = if y + 1 + height <= bounds.bottom() {
+            y + 1
+        } else {
+            y.saturating_sub(height).max(bounds.y)
+        };
+        let x = x.min(bounds.right().saturating_sub(width));
+        let area = Rect::new(x, y, width, height);
+        let title = format!("{} of {}", self.selected + 1, self.items.len());
+        f.render_widget(Clear, area);
+        f.render_widget(
+            Paragraph::new(lines).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(Color::DarkGray))
+                    .title(title),
+            ),
+            area,
+        );
+    }
+}
@@ -0,0 +1,360 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The user config file, `$XDG_CONFIG_HOME/dbvi/config.toml`. An `init.sql`
+//! next to it is run on every new connection, and an `init.lua` once at
+//! startup with the `lua` feature, see `lua.rs`.
+//!
+//! ```toml
+//! statement_timeout = "30s"
+//! auto_pairs = true
+//! clipboard = "osc52"
+//!
+//! [options]
+//! wrap = true
+//! split = 40
+//!
+//! [statusline]
+//! right = ["transaction", "timing", "connection", "clock"]
+//!
+//! [map]
+//! leader = "<Space>"
+//!
+//! [map.normal]
+//! "<leader>e" = { command = "export csv /tmp/out.csv" }
+//!
+//! [map.insert]
+//! jk = "<Esc>"
+//!
+//! [audit]
+//! path = "~/dbvi-audit.sql"
+//! format = "sql"
+//!
+//! [lsp]
+//! command = ["sqls"]
+//!
+//! [snippets]
+//! bday = "SELECT * FROM users WHERE created_at > now() - interval '${1:1 day}'$0"
+//!
+//! [profiles.prod]
+//! url = "postgres://app@db.internal/app"
+//! env = "prod"
+//! init = ["SET search_path = app, public", "SET TIME ZONE 'UTC'"]
+//!
+//! [profiles.prod.options]
+//! autolimit = 500
+//! statement_timeout = "10s"
+//!
+//! [profiles.prod.map.normal]
+//! "<leader>r" = { command = "queue" }
+//!
+//! [profiles.prod.ssh]
+//! host = "bastion.example.com"
+//! user = "me"
+//! key = "~/.ssh/id_ed25519"
+//! jump = "jump.example.com"
+//!
+//! [profiles.warehouse]
+//! url = "postgres://analyst@cluster.redshift.amazonaws.com:5439/dev"
+//! dialect = "redshift"
+//! ```
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    env, fs, io,
+    path::PathBuf,
+    time::Duration,
+};
+
+use ratatui::style::Color;
+use serde::Deserialize;
+
+use crate::dialect::Dialect;
+use crate::keymap::Mappings;
+use crate::statusline::{self, StatusLine};
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Default `statement_timeout`, e.g. `"30s"`.
+    pub statement_timeout: Option<String>,
+    /// Close `(`, `'` and `"` as they are typed in insert mode.
+    pub auto_pairs: bool,
+    /// Where yanks are copied, as for `:set clipboard=`. Picked at startup
+    /// if unset.
+    pub clipboard: Option<String>,
+    /// Insert mode snippets by trigger word, see `snippet`.
+    pub snippets: HashMap<String, String>,
+    /// Log every statement run, off unless the table is there.
+    pub audit: Option<Audit>,
+    /// Segments and colors of the status line, see `statusline`.
+    pub statusline: StatusLine,
+    /// Key mappings by mode, see `keymap`.
+    pub map: Mappings,
+    /// Keys for `:killswitch`, `ZQ` unless set, none if empty.
+    pub kill_switch: Option<String>,
+    /// Values for `:set` options at startup, by option name.
+    pub options: BTreeMap<String, toml::Value>,
+    /// A language server to run, see `lsp`.
+    pub lsp: Option<Lsp>,
+    pub profiles: HashMap<String, Profile>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Audit {
+    /// Defaults to `$XDG_STATE_HOME/dbvi/audit.log`, `~` is expanded.
+    pub path: Option<String>,
+    pub format: AuditFormat,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditFormat {
+    /// Statements with a comment header each, a script that can be read (or
+    /// `:source`d) back.
+    #[default]
+    Sql,
+    /// One JSON object per line.
+    Json,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Lsp {
+    /// The server and its arguments, `~` is expanded.
+    pub command: Vec<String>,
+}
+
+/// A named connection.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    /// URL or keyword/value connection string.
+    pub url: Option<String>,
+    /// Service from `pg_service.conf`.
+    pub service: Option<String>,
+    /// Reach the database through an SSH tunnel.
+    pub ssh: Option<SshTunnel>,
+    /// Statements run on every new connection, after `init_file`.
+    pub init: Vec<String>,
+    /// SQL file run on every new connection, `~` is expanded.
+    pub init_file: Option<String>,
+    /// `postgres`, `redshift` or `greenplum`, when the server doesn't say.
+    pub dialect: Option<Dialect>,
+    /// What the database is: `dev`, `staging` or `prod`.
+    pub env: Option<Env>,
+    /// Color of the environment in the status line and of the `prod`
+    /// banner, by name or as `"#rrggbb"`.
+    #[serde(deserialize_with = "statusline::color")]
+    pub color: Option<Color>,
+    /// Make every transaction read-only, on each connection to it and with
+    /// the `readonly` option. On by default for `prod`, where writes ask
+    /// first when it's off.
+    pub read_only: Option<bool>,
+    /// Values for `:set` options, over those of the config and workspace.
+    pub options: BTreeMap<String, toml::Value>,
+    /// Key mappings by mode, over those of the config.
+    pub map: Mappings,
+    /// Columns masked in the grid and everything that leaves it, by name
+    /// or glob like `"*password*"`, as with `:redact`.
+    pub redact: Vec<String>,
+}
+
+impl Profile {
+    pub fn environment(&self) -> Option<Environment> {
+        let env = self.env?;
+        Some(Environment {
+            env,
+            color: self.color.unwrap_or(env.color()),
+        })
+    }
+
+    pub fn read_only(&self) -> bool {
+        self.read_only.unwrap_or(self.env == Some(Env::Prod))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Env {
+    Dev,
+    Staging,
+    Prod,
+}
+
+impl Env {
+    pub fn name(self) -> &'static str {
+        match self {
+            Env::Dev => "dev",
+            Env::Staging => "staging",
+            Env::Prod => "prod",
+        }
+    }
+
+    fn color(self) -> Color {
+        match self {
+            Env::Dev => Color::Green,
+            Env::Staging => Color::Yellow,
+            Env::Prod => Color::Red,
+        }
+    }
+}
+
+/// The environment of the profile connected with, and its color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Environment {
+    pub env: Env,
+    pub color: Color,
+}
+
+impl Environment {
+    pub fn is_prod(&self) -> bool {
+        self.env == Env::Prod
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SshTunnel {
+    pub host: String,
+    pub user: Option<String>,
+    pub port: Option<u16>,
+    /// Private key file, `~` is expanded.
+    pub key: Option<String>,
+    /// Jump host(s) in `ssh -J` syntax.
+    pub jump: Option<String>,
+}
+
+/// Parses durations the way Postgres does for `statement_timeout`: a number
+/// with an optional `ms`, `s`, `min`, `h` or `d` unit, milliseconds if none.
+pub fn parse_duration(input: &str) -> Result<Duration, String> {
+    let input = input.trim();
+    let split = input
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(input.len());
+    let (number, unit) = input.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("Invalid duration: {input}"))?;
+    let millis = match unit.trim() {
+        "" | "ms" => 1.0,
+        "s" => 1_000.0,
+        "min" => 60_000.0,
+        "h" => 3_600_000.0,
+        "d" => 86_400_000.0,
+        unit => {
+            return Err(format!(
+                "Invalid duration unit \"{unit}\", use ms, s, min, h or d"
+            ));
+        }
+    };
+    Ok(Duration::from_secs_f64(number * millis / 1000.0))
+}
+
+/// `$XDG_CONFIG_HOME/dbvi`, falling back to `~/.config/dbvi`.
+pub fn config_dir() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::home_dir().map(|home| home.join(".config")))
+        .map(|dir| dir.join("dbvi"))
+}
+
+/// The config file, `config.toml` in [`config_dir`].
+pub fn config_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("config.toml"))
+}
+
+/// `$XDG_STATE_HOME/dbvi`, or `~/.local/state/dbvi`.
+pub fn state_dir() -> Option<PathBuf> {
+    env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::home_dir().map(|home| home.join(".local/state")))
+        .map(|dir| dir.join("dbvi"))
+}
+
+/// `$XDG_CACHE_HOME/dbvi`, or `~/.cache/dbvi`.
+pub fn cache_dir() -> Option<PathBuf> {
+    env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::home_dir().map(|home| home.join(".cache")))
+        .map(|dir| dir.join("dbvi"))
+}
+
+/// Expands a leading `~/` to the home directory.
+pub fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), env::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+impl Config {
+    /// Loads the config file. A missing file is an empty config.
+    pub fn load() -> io::Result<Self> {
+        let Some(path) = config_path() else {
+            return Ok(Self::default());
+        };
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(err),
+        };
+        toml::from_str(&contents)
+            .map_err(|err| io::Error::other(format!("{}: {err}", path.display())))
+    }
+
+    /// Lets the profile `name` have its say over the options and mappings,
+    /// moving its own into them: its `read_only` turns `readonly` on.
+    pub fn apply_profile(&mut self, name: &str) {
+        let Some(profile) = self.profiles.get_mut(name) else {
+            return;
+        };
+        if profile.read_only() {
+            let on = toml::Value::Boolean(true);
+            self.options.insert("readonly".to_string(), on);
+        }
+        self.options.append(&mut profile.options);
+        self.map.extend(std::mem::take(&mut profile.map));
+    }
+
+    /// What to run on every new connection: the global `init.sql`, then the
+    /// profile's `init_file` and `init` statements, and for a read-only
+    /// profile `default_transaction_read_only`.
+    pub fn init_statements(&self, profile: Option<&Profile>) -> io::Result<Vec<String>> {
+        let mut statements = Vec::new();
+        let global = config_dir().map(|dir| dir.join("init.sql"));
+        match global.map(fs::read_to_string) {
+            Some(Ok(sql)) => statements.push(sql),
+            Some(Err(err)) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+            _ => {}
+        }
+        if let Some(profile) = profile {
+            if let Some(path) = &profile.init_file {
+                let path = expand_home(path);
+                let sql = fs::read_to_string(&path)
+                    .map_err(|err| io::Error::other(format!("{}: {err}", path.display())))?;
+                statements.push(sql);
+            }
+            statements.extend(profile.init.iter().cloned());
+            // Last, so only a `SET` made in the session turns it off again.
+            // Every connection to the profile gets it, not only the one the
+            // `readonly` option is set on.
+            if profile.read_only() {
+                statements.push("SET default_transaction_read_only = on".into());
+            }
+        }
+        Ok(statements)
+    }
+}
@@ -0,0 +1,268 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `:stats`: what this session has run, and how the database it is
+//! connected to is doing, redrawn as both change.
+
+use std::time::{Duration, Instant};
+
+use ratatui::{
+    Frame,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{LineGauge, Paragraph},
+};
+use sqlx::PgPool;
+
+/// How often the server's side is asked for again while shown.
+pub const INTERVAL: Duration = Duration::from_secs(2);
+
+/// Width of the labels in front of the numbers and gauges.
+const LABEL_WIDTH: usize = 13;
+
+/// The statements the user ran this session, counted as they finish.
+#[derive(Debug, Clone)]
+pub struct Activity {
+    pub queries: u64,
+    pub failed: u64,
+    /// Rows returned or changed, all told.
+    pub rows: u64,
+    /// Time spent waiting on them, all told.
+    pub time: Duration,
+    /// Since the session connected or last reconnected.
+    pub connected_at: Instant,
+}
+
+impl Default for Activity {
+    fn default() -> Self {
+        Self {
+            queries: 0,
+            failed: 0,
+            rows: 0,
+            time: Duration::ZERO,
+            connected_at: Instant::now(),
+        }
+    }
+}
+
+impl Activity {
+    /// Counts a statement that took `elapsed` and returned or changed the
+    /// rows of `outcome`.
+    pub fn record(&mut self, elapsed: Duration, outcome: &Result<u64, String>) {
+        self.queries += 1;
+        self.time += elapsed;
+        match outcome {
+            Ok(rows) => self.rows += rows,
+            Err(_) => self.failed += 1,
+        }
+    }
+
+    /// The time the statements took on average, `None` before any.
+    pub fn average(&self) -> Option<Duration> {
+        (self.queries > 0).then(|| self.time / self.queries as u32)
+    }
+}
+
+/// The server's side, from `pg_stat_database` for the current database and
+/// `pg_stat_activity`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ServerStats {
+    pub blocks_hit: i64,
+    pub blocks_read: i64,
+    pub commits: i64,
+    pub rollbacks: i64,
+    pub connections: i64,
+    pub max_connections: i64,
+}
+
+impl ServerStats {
+    pub async fn fetch(pool: &PgPool) -> Result<Self, sqlx::Error> {
+        let (blocks_hit, blocks_read, commits, rollbacks, connections, max_connections) =
+            sqlx::query_as(
+                "SELECT d.blks_hit, d.blks_read, d.xact_commit, d.xact_rollback,
+                        (SELECT count(*) FROM pg_stat_activity WHERE backend_type = 'client backend'),
+                        current_setting('max_connections')::int8
+                 FROM pg_stat_database d
+                 WHERE d.datname = current_database()",
+            )
+            .fetch_one(pool)
+            .await?;
+        Ok(Self {
+            blocks_hit,
+            blocks_read,
+            commits,
+            rollbacks,
+            connections,
+            max_connections,
+        })
+    }
+
+    /// The share of blocks read that were found in shared buffers.
+    pub fn cache_hit_ratio(&self) -> Option<f64> {
+        ratio(self.blocks_hit, self.blocks_hit + self.blocks_read)
+    }
+
+    /// The share of transactions that committed rather than rolled back.
+    pub fn commit_ratio(&self) -> Option<f64> {
+        ratio(self.commits, self.commits + self.rollbacks)
+    }
+
+    /// The share of `max_connections` taken.
+    pub fn connection_ratio(&self) -> Option<f64> {
+        ratio(self.connections, self.max_connections)
+    }
+}
+
+fn ratio(part: i64, whole: i64) -> Option<f64> {
+    (whole > 0).then(|| (part as f64 / whole as f64).clamp(0.0, 1.0))
+}
+
+/// `1h 02m 13s`, or less for shorter spans.
+pub fn uptime(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    match (hours, minutes) {
+        (0, 0) => format!("{seconds}s"),
+        (0, _) => format!("{minutes}m {seconds:02}s"),
+        _ => format!("{hours}h {minutes:02}m {seconds:02}s"),
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Dashboard {
+    /// `None` until the server first answers, or without a server to ask.
+    pub server: Option<Result<ServerStats, String>>,
+    /// When the server was last asked, `None` if it never is.
+    pub fetched_at: Option<Instant>,
+}
+
+impl Dashboard {
+    /// Whether it is time to ask the server again.
+    pub fn due(&self) -> bool {
+        self.fetched_at
+            .is_some_and(|fetched_at| fetched_at.elapsed() >= INTERVAL)
+    }
+
+    pub fn render(&self, f: &mut Frame, area: Rect, activity: &Activity) {
+        let label = |text: &str| {
+            Span::styled(
+                format!("{text:<LABEL_WIDTH$}"),
+                Style::default().fg(Color::DarkGray),
+            )
+        };
+        let failed = match activity.failed {
+            0 => String::new(),
+            failed => format!(", {failed} failed"),
+        };
+        let average = activity
+            .average()
+            .map(|average| format!(", {average:.1?} on average"))
+            .unwrap_or_default();
+        let mut lines = vec![
+            Line::from(vec![
+                label("Queries"),
+                Span::raw(format!("{}{failed}", activity.queries)),
+            ]),
+            Line::from(vec![
+                label("Rows"),
+                Span::raw(format!("{} returned or changed", activity.rows)),
+            ]),
+            Line::from(vec![
+                label("Query time"),
+                Span::raw(format!("{:.1?}{average}", activity.time)),
+            ]),
+            Line::from(vec![
+                label("Connected"),
+                Span::raw(uptime(activity.connected_at.elapsed())),
+            ]),
+            Line::default(),
+        ];
+        let stats = match &self.server {
+            Some(Ok(stats)) => *stats,
+            Some(Err(err)) => {
+                lines.push(Line::styled(
+                    format!("Server statistics unavailable: {err}"),
+                    Style::default().fg(Color::Red),
+                ));
+                f.render_widget(Paragraph::new(lines), area);
+                return;
+            }
+            None => {
+                let waiting = match self.fetched_at {
+                    Some(_) => "Asking the server…",
+                    None => "No server statistics without a Postgres connection",
+                };
+                lines.push(Line::styled(waiting, Style::default().fg(Color::DarkGray)));
+                f.render_widget(Paragraph::new(lines), area);
+                return;
+            }
+        };
+        let summary = lines.len() as u16;
+        let [summary_area, gauges_area] =
+            Layout::vertical([Constraint::Length(summary), Constraint::Min(0)]).areas(area);
+        f.render_widget(Paragraph::new(lines), summary_area);
+
+        let gauges = [
+            (
+                "Cache hits",
+                stats.cache_hit_ratio(),
+                format!("{} hit, {} read", stats.blocks_hit, stats.blocks_read),
+            ),
+            (
+                "Commits",
+                stats.commit_ratio(),
+                format!(
+                    "{} committed, {} rolled back",
+                    stats.commits, stats.rollbacks
+                ),
+            ),
+            (
+                "Connections",
+                stats.connection_ratio(),
+                format!("{} of {}", stats.connections, stats.max_connections),
+            ),
+        ];
+        let rows =
+            Layout::vertical(gauges.iter().map(|_| Constraint::Length(1))).split(gauges_area);
+        for ((name, ratio, detail), row) in gauges.into_iter().zip(rows.iter()) {
+            let [name_area, gauge_area, detail_area] = Layout::horizontal([
+                Constraint::Length(LABEL_WIDTH as u16),
+                Constraint::Percentage(50),
+                Constraint::Min(0),
+            ])
+            .areas(*row);
+            f.render_widget(Paragraph::new(label(name)), name_area);
+            let color = match ratio {
+                Some(ratio) if name == "Connections" && ratio > 0.8 => Color::Red,
+                Some(ratio) if name != "Connections" && ratio < 0.9 => Color::Yellow,
+                _ => Color::Green,
+            };
+            f.render_widget(
+                LineGauge::default()
+                    .ratio(ratio.unwrap_or(0.0))
+                    .filled_style(Style::default().fg(color))
+                    .label(match ratio {
+                        Some(ratio) => format!("{:>5.1}%", ratio * 100.0),
+                        None => "    -".into(),
+                    }),
+                gauge_area,
+            );
+            f.render_widget(
+                Paragraph::new(format!(" {detail}")).style(Style::default().fg(Color::DarkGray)),
+                detail_area,
+            );
+        }
+    }
+}
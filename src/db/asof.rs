@@ -0,0 +1,131 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `:asof`, a read-only connection of its own pinned to a snapshot of the
+//! database, to look at the data as it was while a migration changes it in
+//! another buffer. The snapshot is a repeatable read transaction held open
+//! until `:asof close`; its id, from `pg_export_snapshot()`, lets other
+//! sessions see the same data with `SET TRANSACTION SNAPSHOT`, and `:asof
+//! <id>` does that for a snapshot exported elsewhere.
+
+use chrono::{DateTime, Local};
+use sqlx::{Connection, Executor, PgConnection, postgres::PgConnectOptions};
+
+use super::session::{STATEMENT_SAVEPOINT, Transaction};
+use crate::results::{self, ResultSet};
+
+/// What `:asof` does.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AsOf {
+    /// `:asof`: take a snapshot, letting go of the one before.
+    Take,
+    /// `:asof <id>`: attach to a snapshot exported elsewhere.
+    Import(String),
+    /// `:asof run`: run the buffer against the snapshot.
+    Run,
+    /// `:asof close`: let go of the snapshot.
+    Close,
+}
+
+const BEGIN: &str = "BEGIN ISOLATION LEVEL REPEATABLE READ READ ONLY";
+
+#[derive(Debug)]
+pub struct Snapshot {
+    conn: PgConnection,
+    /// As `pg_export_snapshot()` returns it, like `00000003-0000001B-1`.
+    pub id: String,
+    /// When it was taken, `None` for one exported elsewhere.
+    pub taken: Option<DateTime<Local>>,
+}
+
+impl Snapshot {
+    /// Takes a snapshot of the database as it is now.
+    pub async fn take(options: &PgConnectOptions) -> Result<Self, sqlx::Error> {
+        let mut conn = PgConnection::connect_with(options).await?;
+        conn.execute(BEGIN).await?;
+        let id = sqlx::query_scalar("SELECT pg_export_snapshot()")
+            .fetch_one(&mut conn)
+            .await?;
+        Ok(Self {
+            conn,
+            id,
+            taken: Some(Local::now()),
+        })
+    }
+
+    /// Attaches to `id`, the snapshot of a transaction elsewhere that
+    /// exported it and is still open.
+    pub async fn import(options: &PgConnectOptions, id: &str) -> Result<Self, sqlx::Error> {
+        if !is_id(id) {
+            return Err(sqlx::Error::Protocol(format!("{id} isn't a snapshot id")));
+        }
+        let mut conn = PgConnection::connect_with(options).await?;
+        conn.execute(BEGIN).await?;
+        // Takes no parameters, hence `is_id` first.
+        conn.execute(format!("SET TRANSACTION SNAPSHOT '{id}'").as_str())
+            .await?;
+        Ok(Self {
+            conn,
+            id: id.to_string(),
+            taken: None,
+        })
+    }
+
+    /// Runs `query` against the snapshot, like [`results::fetch_batch`].
+    /// It runs under a savepoint, so that failing doesn't lose the
+    /// snapshot.
+    pub async fn query(
+        &mut self,
+        query: &str,
+    ) -> Result<(Vec<(String, ResultSet)>, u64), sqlx::Error> {
+        if Transaction::is_control(query) {
+            return Err(sqlx::Error::Protocol(
+                "The snapshot's transaction is its own, :asof close to end it".into(),
+            ));
+        }
+        self.conn
+            .execute(format!("SAVEPOINT {STATEMENT_SAVEPOINT}").as_str())
+            .await?;
+        let outcome = results::fetch_batch(&mut self.conn, query).await;
+        let undo = match outcome {
+            Ok(_) => format!("RELEASE SAVEPOINT {STATEMENT_SAVEPOINT}"),
+            Err(_) => format!("ROLLBACK TO SAVEPOINT {STATEMENT_SAVEPOINT}"),
+        };
+        self.conn.execute(undo.as_str()).await?;
+        outcome
+    }
+
+    /// Ends the transaction, letting go of the snapshot.
+    pub async fn close(self) -> Result<(), sqlx::Error> {
+        self.conn.close().await
+    }
+
+    /// `snapshot 00000003-0000001B-1 of 14:02:11`, for titles.
+    pub fn describe(&self) -> String {
+        match self.taken {
+            Some(taken) => format!("snapshot {} of {}", self.id, taken.format("%H:%M:%S")),
+            None => format!("snapshot {}", self.id),
+        }
+    }
+}
+
+/// Whether `text` looks like what `pg_export_snapshot()` returns: groups of
+/// hex digits joined by dashes.
+pub fn is_id(text: &str) -> bool {
+    let groups: Vec<&str> = text.split('-').collect();
+    (2..=3).contains(&groups.len())
+        && groups
+            .iter()
+            .all(|group| !group.is_empty() && group.chars().all(|c| c.is_ascii_hexdigit()))
+}
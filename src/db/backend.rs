@@ -0,0 +1,105 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Databases other than Postgres, attached with `:connect <url>`. All but
+//! the demo one are behind cargo features. While one is attached the editor's queries go to
+//! it instead of the session; reports, plans and the other catalog commands
+//! stay Postgres only.
+
+use crate::results::ResultSet;
+
+#[derive(Debug)]
+pub enum Backend {
+    Demo(super::demo::Demo),
+    #[cfg(feature = "mssql")]
+    // The TDS client is big, and would make every backend as big.
+    Mssql(Box<super::mssql::Mssql>),
+    #[cfg(feature = "duckdb")]
+    Duckdb(super::duckdb::Duckdb),
+    #[cfg(feature = "clickhouse")]
+    Clickhouse(super::clickhouse::Clickhouse),
+    #[cfg(feature = "odbc")]
+    Odbc(super::odbc::Odbc),
+}
+
+impl Backend {
+    /// Connects to `url` with the backend its scheme names.
+    pub async fn connect(url: &str) -> Result<Self, String> {
+        let scheme = url.split_once("://").map_or(url, |(scheme, _)| scheme);
+        match scheme {
+            "demo" => Ok(Self::Demo(super::demo::Demo::new())),
+            #[cfg(feature = "mssql")]
+            "mssql" | "sqlserver" => Ok(Self::Mssql(Box::new(
+                super::mssql::Mssql::connect(url).await?,
+            ))),
+            #[cfg(feature = "duckdb")]
+            "duckdb" => Ok(Self::Duckdb(super::duckdb::Duckdb::open(url)?)),
+            #[cfg(feature = "clickhouse")]
+            "clickhouse" | "clickhouses" => Ok(Self::Clickhouse(
+                super::clickhouse::Clickhouse::connect(url).await?,
+            )),
+            #[cfg(feature = "odbc")]
+            "odbc" => Ok(Self::Odbc(super::odbc::Odbc::connect(url)?)),
+            _ => Err(format!(
+                "No backend for {scheme}:// URLs in this build, see the cargo features"
+            )),
+        }
+    }
+
+    /// `kind user@host/db`, for the status line.
+    pub fn name(&self) -> String {
+        match *self {
+            Self::Demo(ref db) => format!("demo {}", db.name),
+            #[cfg(feature = "mssql")]
+            Self::Mssql(ref db) => format!("mssql {}", db.name),
+            #[cfg(feature = "duckdb")]
+            Self::Duckdb(ref db) => format!("duckdb {}", db.name),
+            #[cfg(feature = "clickhouse")]
+            Self::Clickhouse(ref db) => format!("clickhouse {}", db.name),
+            #[cfg(feature = "odbc")]
+            Self::Odbc(ref db) => format!("odbc {}", db.name),
+        }
+    }
+
+    /// Runs `sql`, returning the rows of the last statement that returned
+    /// any and the number of rows returned or affected.
+    pub async fn query(&mut self, sql: &str) -> Result<(Option<ResultSet>, u64), String> {
+        match *self {
+            Self::Demo(ref mut db) => db.query(sql),
+            #[cfg(feature = "mssql")]
+            Self::Mssql(ref mut db) => db.query(sql).await.map_err(|err| err.to_string()),
+            #[cfg(feature = "duckdb")]
+            Self::Duckdb(ref mut db) => db.query(sql).map_err(|err| err.to_string()),
+            #[cfg(feature = "clickhouse")]
+            Self::Clickhouse(ref mut db) => db.query(sql).await,
+            #[cfg(feature = "odbc")]
+            Self::Odbc(ref mut db) => db.query(sql).map_err(|err| err.to_string()),
+        }
+    }
+
+    /// Every table and view, by schema.
+    pub async fn tables(&mut self) -> Result<ResultSet, String> {
+        match *self {
+            Self::Demo(ref db) => Ok(db.tables()),
+            #[cfg(feature = "mssql")]
+            Self::Mssql(ref mut db) => db.tables().await.map_err(|err| err.to_string()),
+            #[cfg(feature = "duckdb")]
+            Self::Duckdb(ref mut db) => db.tables().map_err(|err| err.to_string()),
+            #[cfg(feature = "clickhouse")]
+            Self::Clickhouse(ref mut db) => db.tables().await,
+            #[cfg(feature = "odbc")]
+            Self::Odbc(ref mut db) => db.tables().map_err(|err| err.to_string()),
+        }
+    }
+}
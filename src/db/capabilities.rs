@@ -0,0 +1,210 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! What the session's role may do on the server, asked once connected so
+//! that a feature it can't use says so up front, and `:capabilities` can
+//! list it all, instead of failing with a raw SQL error when used.
+
+use sqlx::PgPool;
+
+use super::server::Server;
+
+/// SQLSTATE `insufficient_privilege`.
+const INSUFFICIENT_PRIVILEGE: &str = "42501";
+
+/// What a feature needs of the server beyond a connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Need {
+    /// Reading `pg_catalog`, for completion, `:ddl`, `:erd` and the
+    /// schema reports.
+    Catalogs,
+    /// An extension installed in the database.
+    Extension(&'static str),
+}
+
+impl Need {
+    /// What to tell the user when the server doesn't have it.
+    pub fn refusal(&self) -> String {
+        match self {
+            Need::Catalogs => "This role cannot read the system catalogs, see :capabilities".into(),
+            Need::Extension(name) => {
+                format!("{name} is not installed in this database, CREATE EXTENSION {name}")
+            }
+        }
+    }
+}
+
+/// Each answer is `None` when the server couldn't be asked, before the
+/// probe or on a derivative without the function asked, and only a
+/// definite no turns a feature off.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    pub catalogs: Option<bool>,
+    pub temp_tables: Option<bool>,
+    /// The first schema of the `search_path` that exists, where unqualified
+    /// objects are created.
+    pub schema: Option<String>,
+    /// Whether objects can be created in `schema`.
+    pub create: Option<bool>,
+    /// Membership of `pg_read_all_stats`, to see what other roles run.
+    pub read_stats: Option<bool>,
+    /// Membership of `pg_signal_backend`, to cancel what other roles run.
+    pub signal: Option<bool>,
+    pub superuser: Option<bool>,
+    /// The extensions installed in the database, by name.
+    pub extensions: Option<Vec<String>>,
+}
+
+impl Capabilities {
+    /// Asks the server. Each question is asked on its own, since older
+    /// servers and derivatives lack some of the functions and roles.
+    pub async fn probe(pool: &PgPool) -> Self {
+        let catalogs = match sqlx::query("SELECT 1 FROM pg_catalog.pg_class LIMIT 1")
+            .fetch_optional(pool)
+            .await
+        {
+            Ok(_) => Some(true),
+            Err(err) if is_denied(&err) => Some(false),
+            Err(_) => None,
+        };
+        let schema = sqlx::query_scalar::<_, Option<String>>("SELECT current_schema()")
+            .fetch_one(pool)
+            .await
+            .ok()
+            .flatten();
+        let create = match &schema {
+            Some(schema) => {
+                sqlx::query_scalar::<_, Option<bool>>("SELECT has_schema_privilege($1, 'CREATE')")
+                    .bind(schema)
+                    .fetch_one(pool)
+                    .await
+                    .ok()
+                    .flatten()
+            }
+            None => Some(false),
+        };
+        let extensions =
+            sqlx::query_scalar::<_, String>("SELECT extname::text FROM pg_extension ORDER BY 1")
+                .fetch_all(pool)
+                .await
+                .ok();
+        Self {
+            catalogs,
+            temp_tables: ask(
+                pool,
+                "SELECT has_database_privilege(current_database(), 'TEMP')",
+            )
+            .await,
+            schema,
+            create,
+            read_stats: ask(pool, "SELECT pg_has_role('pg_read_all_stats', 'MEMBER')").await,
+            signal: ask(pool, "SELECT pg_has_role('pg_signal_backend', 'MEMBER')").await,
+            superuser: ask(pool, "SELECT current_setting('is_superuser') = 'on'").await,
+            extensions,
+        }
+    }
+
+    /// Whether the server said no to `need`.
+    pub fn lacks(&self, need: Need) -> bool {
+        match need {
+            Need::Catalogs => self.catalogs == Some(false),
+            Need::Extension(name) => self
+                .extensions
+                .as_ref()
+                .is_some_and(|extensions| !extensions.iter().any(|extension| extension == name)),
+        }
+    }
+
+    /// What to tell the user right after connecting, when something most
+    /// of the features rely on is missing.
+    pub fn notice(&self) -> Option<String> {
+        self.lacks(Need::Catalogs).then(|| {
+            "This role cannot read the system catalogs: completion, :ddl, :erd and \
+             the schema reports are off, see :capabilities"
+                .into()
+        })
+    }
+
+    /// The rows of `:capabilities`: what was asked, the answer, and what
+    /// needs it.
+    pub fn report(&self, server: Server) -> Vec<[String; 3]> {
+        let schema = match &self.schema {
+            Some(schema) => format!("Create objects in {schema}"),
+            None => "Create objects (no schema on the search_path)".into(),
+        };
+        let statements = match self.lacks(Need::Extension("pg_stat_statements")) {
+            true => Some(false),
+            false => self.extensions.as_ref().map(|_| true),
+        };
+        let extensions = match &self.extensions {
+            Some(extensions) if extensions.is_empty() => "none".into(),
+            Some(extensions) => extensions.join(", "),
+            None => "unknown".into(),
+        };
+        let server = match server.release() {
+            Some(release) => format!("{} {release}", server.name()),
+            None => server.name().into(),
+        };
+        [
+            ("Server", server, ""),
+            (
+                "Read the system catalogs",
+                answer(self.catalogs),
+                "completion, :ddl, :erd, :generate, schema reports",
+            ),
+            ("Create temporary tables", answer(self.temp_tables), ""),
+            (schema.as_str(), answer(self.create), ":migrate"),
+            (
+                "Read all statistics",
+                answer(self.read_stats),
+                "other roles' queries in :activity and :statements",
+            ),
+            (
+                "Signal other backends",
+                answer(self.signal),
+                "c and K on other roles' sessions in :activity",
+            ),
+            ("Superuser", answer(self.superuser), ""),
+            ("pg_stat_statements", answer(statements), ":statements"),
+            ("Extensions", extensions, ":extensions"),
+        ]
+        .into_iter()
+        .map(|(asked, answer, needed_by)| [asked.to_string(), answer, needed_by.to_string()])
+        .collect()
+    }
+}
+
+/// A yes or no question, `None` if the server couldn't answer it.
+async fn ask(pool: &PgPool, sql: &'static str) -> Option<bool> {
+    sqlx::query_scalar::<_, Option<bool>>(sql)
+        .fetch_one(pool)
+        .await
+        .ok()
+        .flatten()
+}
+
+fn answer(answer: Option<bool>) -> String {
+    match answer {
+        Some(true) => "yes",
+        Some(false) => "no",
+        None => "unknown",
+    }
+    .into()
+}
+
+fn is_denied(err: &sqlx::Error) -> bool {
+    err.as_database_error()
+        .and_then(|err| err.code())
+        .is_some_and(|code| code == INSUFFICIENT_PRIVILEGE)
+}
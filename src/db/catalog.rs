@@ -0,0 +1,655 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Queries against the system catalogs.
+
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+use super::server::{Flavor, Server};
+
+/// Splits `schema.name` into its parts. Unqualified names resolve through
+/// the `search_path`.
+fn split_qualified(name: &str) -> (Option<&str>, &str) {
+    match name.rsplit_once('.') {
+        Some((schema, name)) => (Some(schema), name),
+        None => (None, name),
+    }
+}
+
+/// The `CREATE` statement of a view, materialized view or function (every
+/// overload of it), or `None` if there is no such object.
+pub async fn definition(
+    pool: &PgPool,
+    server: Server,
+    name: &str,
+) -> Result<Option<String>, sqlx::Error> {
+    if server.flavor == Flavor::Cockroach {
+        return cockroach_create_statement(pool, name, "view").await;
+    }
+    let view: Option<(String,)> = sqlx::query_as(
+        "SELECT CASE c.relkind WHEN 'v' THEN 'CREATE OR REPLACE VIEW ' \
+                               ELSE 'CREATE MATERIALIZED VIEW ' END \
+                || c.oid::regclass || E' AS\\n' || pg_get_viewdef(c.oid, true) \
+           FROM pg_class c \
+          WHERE c.oid = to_regclass($1) AND c.relkind IN ('v', 'm')",
+    )
+    .bind(name)
+    .fetch_optional(pool)
+    .await?;
+    if let Some((definition,)) = view {
+        return Ok(Some(definition));
+    }
+
+    let (schema, name) = split_qualified(name);
+    let functions: Vec<(String,)> = sqlx::query_as(
+        "SELECT pg_get_functiondef(p.oid) \
+           FROM pg_proc p JOIN pg_namespace n ON n.oid = p.pronamespace \
+          WHERE p.proname = $2 AND p.prokind <> 'a' \
+            AND CASE WHEN $1::text IS NULL THEN pg_function_is_visible(p.oid) \
+                     ELSE n.nspname = $1 END \
+          ORDER BY p.oid",
+    )
+    .bind(schema)
+    .bind(name)
+    .fetch_all(pool)
+    .await?;
+    if functions.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(
+        functions
+            .into_iter()
+            .map(|(definition,)| definition.trim_end().to_string())
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+    ))
+}
+
+/// `SHOW CREATE` of a CockroachDB table or view, whose catalogs have too
+/// little to rebuild it from. Unqualified names prefer `public`.
+async fn cockroach_create_statement(
+    pool: &PgPool,
+    name: &str,
+    kind: &str,
+) -> Result<Option<String>, sqlx::Error> {
+    let (schema, name) = split_qualified(name);
+    sqlx::query_scalar(
+        "SELECT create_statement \
+           FROM crdb_internal.create_statements \
+          WHERE database_name = current_database() AND descriptor_type = $3 \
+            AND descriptor_name = $2 AND ($1::text IS NULL OR schema_name = $1) \
+          ORDER BY schema_name <> 'public' \
+          LIMIT 1",
+    )
+    .bind(schema)
+    .bind(name)
+    .bind(kind)
+    .fetch_optional(pool)
+    .await
+}
+
+/// A column as `table_ddl` needs it, with identifiers and comments already
+/// quoted by the server.
+#[derive(sqlx::FromRow)]
+struct ColumnDef {
+    name: String,
+    ty: String,
+    not_null: bool,
+    default: Option<String>,
+    identity: String,
+    generated: String,
+    comment: Option<String>,
+}
+
+/// How a table fits in a partition tree, from `pg_class c`: the key it is
+/// partitioned by, and the parent and bounds of a partition. Servers
+/// before 10 have no declarative partitioning.
+const PARTITIONING: &str = "CASE WHEN c.relkind = 'p' THEN pg_get_partkeydef(c.oid) END AS key, \
+        CASE WHEN c.relispartition THEN (SELECT i.inhparent::regclass::text \
+            FROM pg_inherits i WHERE i.inhrelid = c.oid) END AS parent, \
+        CASE WHEN c.relispartition THEN pg_get_expr(c.relpartbound, c.oid) END AS bound";
+
+/// A table as `table_ddl` needs it.
+#[derive(sqlx::FromRow)]
+struct TableDef {
+    oid: sqlx::postgres::types::Oid,
+    name: String,
+    comment: Option<String>,
+    /// `PARTITION BY`, for a partitioned table.
+    key: Option<String>,
+    /// The parent and `FOR VALUES` of a partition.
+    parent: Option<String>,
+    bound: Option<String>,
+}
+
+/// Reconstructs `CREATE TABLE` for `name` from the catalogs, with its
+/// constraints, indexes and comments, or `None` if there is no such table.
+/// A partition is created `PARTITION OF` its parent, with only what it
+/// doesn't inherit.
+pub async fn table_ddl(
+    pool: &PgPool,
+    server: Server,
+    name: &str,
+) -> Result<Option<String>, sqlx::Error> {
+    if server.flavor == Flavor::Cockroach {
+        return cockroach_create_statement(pool, name, "table").await;
+    }
+    let partitioning = match server.version {
+        100_000.. => PARTITIONING,
+        _ => "NULL::text AS key, NULL::text AS parent, NULL::text AS bound",
+    };
+    let table: Option<TableDef> = sqlx::query_as(&format!(
+        "SELECT c.oid, c.oid::regclass::text AS name, \
+                quote_literal(obj_description(c.oid, 'pg_class')) AS comment, \
+                {partitioning} \
+           FROM pg_class c \
+          WHERE c.oid = to_regclass($1) AND c.relkind IN ('r', 'p')"
+    ))
+    .bind(name)
+    .fetch_optional(pool)
+    .await?;
+    let Some(TableDef {
+        oid,
+        name: table,
+        comment,
+        key,
+        parent,
+        bound,
+    }) = table
+    else {
+        return Ok(None);
+    };
+    let partition = parent.zip(bound);
+
+    let columns: Vec<ColumnDef> = sqlx::query_as(
+        "SELECT quote_ident(a.attname) AS name, format_type(a.atttypid, a.atttypmod) AS ty, \
+                    a.attnotnull AS not_null, pg_get_expr(d.adbin, d.adrelid) AS default, \
+                    a.attidentity::text AS identity, a.attgenerated::text AS generated, \
+                    quote_literal(col_description(a.attrelid, a.attnum)) AS comment \
+               FROM pg_attribute a \
+               LEFT JOIN pg_attrdef d ON d.adrelid = a.attrelid AND d.adnum = a.attnum \
+              WHERE a.attrelid = $1 AND a.attnum > 0 AND NOT a.attisdropped \
+              ORDER BY a.attnum",
+    )
+    .bind(oid)
+    .fetch_all(pool)
+    .await?;
+    // A partition gets the constraints and indexes of its parent when
+    // attached; only its own are its to create.
+    let constraints: Vec<(String, String)> = sqlx::query_as(
+        "SELECT quote_ident(conname), pg_get_constraintdef(oid, true) \
+           FROM pg_constraint \
+          WHERE conrelid = $1 AND contype IN ('p', 'u', 'f', 'c', 'x') \
+            AND (conislocal OR NOT $2) \
+          ORDER BY contype <> 'p', contype, conname",
+    )
+    .bind(oid)
+    .bind(partition.is_some())
+    .fetch_all(pool)
+    .await?;
+    // Indexes backing a constraint are created by the constraint itself.
+    let indexes: Vec<(String,)> = sqlx::query_as(
+        "SELECT pg_get_indexdef(i.indexrelid) \
+           FROM pg_index i \
+          WHERE i.indrelid = $1 \
+            AND NOT EXISTS (SELECT 1 FROM pg_constraint c WHERE c.conindid = i.indexrelid) \
+            AND NOT ($2 AND EXISTS (SELECT 1 FROM pg_inherits h \
+                                     WHERE h.inhrelid = i.indexrelid)) \
+          ORDER BY i.indexrelid",
+    )
+    .bind(oid)
+    .bind(partition.is_some())
+    .fetch_all(pool)
+    .await?;
+
+    // The columns of a partition are those of its parent.
+    let own_columns = match partition {
+        Some(_) => &[][..],
+        None => &columns[..],
+    };
+    let mut body: Vec<String> = own_columns
+        .iter()
+        .map(|column| {
+            let ColumnDef {
+                name,
+                ty,
+                not_null,
+                default,
+                identity,
+                generated,
+                ..
+            } = column;
+            let mut column = format!("    {name} {ty}");
+            match (identity.as_str(), generated.as_str(), default) {
+                ("a", _, _) => column.push_str(" GENERATED ALWAYS AS IDENTITY"),
+                ("d", _, _) => column.push_str(" GENERATED BY DEFAULT AS IDENTITY"),
+                (_, "s", Some(expr)) => {
+                    column.push_str(&format!(" GENERATED ALWAYS AS ({expr}) STORED"))
+                }
+                (_, _, Some(default)) => column.push_str(&format!(" DEFAULT {default}")),
+                _ => {}
+            }
+            if *not_null {
+                column.push_str(" NOT NULL");
+            }
+            column
+        })
+        .collect();
+    body.extend(
+        constraints
+            .iter()
+            .map(|(name, definition)| format!("    CONSTRAINT {name} {definition}")),
+    );
+
+    let mut ddl = match &partition {
+        Some((parent, _)) => format!("CREATE TABLE {table} PARTITION OF {parent}"),
+        None => format!("CREATE TABLE {table}"),
+    };
+    if !body.is_empty() || partition.is_none() {
+        ddl.push_str(&format!(" (\n{}\n)", body.join(",\n")));
+    }
+    if let Some((_, bound)) = &partition {
+        ddl.push_str(&format!("\n{bound}"));
+    }
+    if let Some(key) = key {
+        ddl.push_str(&format!("\nPARTITION BY {key}"));
+    }
+    ddl.push_str(";\n");
+    for (index,) in &indexes {
+        ddl.push_str(&format!("\n{index};"));
+    }
+    if !indexes.is_empty() {
+        ddl.push('\n');
+    }
+    if let Some(comment) = comment {
+        ddl.push_str(&format!("\nCOMMENT ON TABLE {table} IS {comment};"));
+    }
+    for ColumnDef { name, comment, .. } in &columns {
+        if let Some(comment) = comment {
+            ddl.push_str(&format!("\nCOMMENT ON COLUMN {table}.{name} IS {comment};"));
+        }
+    }
+    Ok(Some(ddl.trim_end().to_string()))
+}
+
+/// A table column, as far as generating statements against it goes.
+#[derive(Debug, Clone, PartialEq, Eq, sqlx::FromRow, Serialize, Deserialize)]
+pub struct Column {
+    /// Already quoted if it needs to be.
+    pub name: String,
+    pub ty: String,
+    pub not_null: bool,
+    /// Identity and generated columns can't (or shouldn't) be written.
+    pub generated: bool,
+    pub primary_key: bool,
+    /// The labels of an enum column, in their order, empty for others.
+    #[serde(default)]
+    pub labels: Vec<String>,
+}
+
+/// The fields of [`Column`], from `pg_attribute a`.
+pub(crate) const COLUMN_FIELDS: &str = "quote_ident(a.attname) AS name, \
+        format_type(a.atttypid, a.atttypmod) AS ty, \
+        a.attnotnull AS not_null, \
+        a.attidentity = 'a' OR a.attgenerated <> '' AS generated, \
+        EXISTS (SELECT 1 FROM pg_constraint c \
+                 WHERE c.conrelid = a.attrelid AND c.contype = 'p' \
+                   AND a.attnum = ANY (c.conkey)) AS primary_key, \
+        ARRAY(SELECT e.enumlabel::text FROM pg_enum e \
+               WHERE e.enumtypid = a.atttypid ORDER BY e.enumsortorder) AS labels";
+
+/// The qualified name and columns of table (or view) `name`, or `None` if
+/// there is no such relation.
+pub async fn columns(
+    pool: &PgPool,
+    name: &str,
+) -> Result<Option<(String, Vec<Column>)>, sqlx::Error> {
+    let table: Option<(sqlx::postgres::types::Oid, String)> = sqlx::query_as(
+        "SELECT c.oid, c.oid::regclass::text FROM pg_class c \
+          WHERE c.oid = to_regclass($1) AND c.relkind IN ('r', 'p', 'v', 'm', 'f')",
+    )
+    .bind(name)
+    .fetch_optional(pool)
+    .await?;
+    let Some((oid, table)) = table else {
+        return Ok(None);
+    };
+    let columns = sqlx::query_as(&format!(
+        "SELECT {COLUMN_FIELDS} FROM pg_attribute a \
+          WHERE a.attrelid = $1 AND a.attnum > 0 AND NOT a.attisdropped \
+          ORDER BY a.attnum"
+    ))
+    .bind(oid)
+    .fetch_all(pool)
+    .await?;
+    Ok(Some((table, columns)))
+}
+
+/// The table column a column of some results was read from, see
+/// [`results::Column::origin`](crate::results::Column::origin).
+#[derive(Debug, Clone, PartialEq, Eq, sqlx::FromRow)]
+pub struct Origin {
+    /// The table, as `regclass` prints it.
+    pub table: String,
+    /// Quoted if it needs to be, like [`Column::name`].
+    pub column: String,
+    /// The declared type, modifiers and all, like `varchar(40)`.
+    pub ty: String,
+    pub not_null: bool,
+    pub default: Option<String>,
+    pub comment: Option<String>,
+}
+
+/// The query for an [`Origin`], short of the conditions on `pg_attribute a`.
+const ORIGIN_QUERY: &str = "SELECT a.attrelid::regclass::text AS \"table\", \
+        quote_ident(a.attname) AS \"column\", \
+        format_type(a.atttypid, a.atttypmod) AS ty, \
+        a.attnotnull AS not_null, \
+        pg_get_expr(d.adbin, d.adrelid) AS \"default\", \
+        col_description(a.attrelid, a.attnum) AS comment \
+   FROM pg_attribute a \
+   LEFT JOIN pg_attrdef d ON d.adrelid = a.attrelid AND d.adnum = a.attnum \
+  WHERE NOT a.attisdropped";
+
+/// Column `attnum` of the table with OID `table`, or `None` if it was
+/// dropped since.
+pub async fn origin(pool: &PgPool, table: u32, attnum: i16) -> Result<Option<Origin>, sqlx::Error> {
+    sqlx::query_as(&format!(
+        "{ORIGIN_QUERY} AND a.attrelid = $1 AND a.attnum = $2"
+    ))
+    .bind(sqlx::postgres::types::Oid(table))
+    .bind(attnum)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Column `column` of relation `table`, both as they would be written in a
+/// statement, or `None` if there is no such column.
+pub async fn column(
+    pool: &PgPool,
+    table: &str,
+    column: &str,
+) -> Result<Option<Origin>, sqlx::Error> {
+    sqlx::query_as(&format!(
+        "{ORIGIN_QUERY} AND a.attnum > 0 \
+           AND a.attrelid = to_regclass($1) AND a.attname = (parse_ident($2))[1]"
+    ))
+    .bind(table)
+    .bind(column)
+    .fetch_optional(pool)
+    .await
+}
+
+/// The labels of enum type `ty`, in their order, or nothing if it isn't
+/// one.
+pub async fn enum_labels(pool: &PgPool, ty: &str) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT enumlabel::text FROM pg_enum \
+          WHERE enumtypid = to_regtype($1) ORDER BY enumsortorder",
+    )
+    .bind(ty)
+    .fetch_all(pool)
+    .await
+}
+
+/// The labels of the type of column `attnum` of the table with OID
+/// `table`, see [`enum_labels`].
+pub async fn column_labels(
+    pool: &PgPool,
+    table: u32,
+    attnum: i16,
+) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT e.enumlabel::text FROM pg_attribute a \
+           JOIN pg_enum e ON e.enumtypid = a.atttypid \
+          WHERE a.attrelid = $1 AND a.attnum = $2 ORDER BY e.enumsortorder",
+    )
+    .bind(sqlx::postgres::types::Oid(table))
+    .bind(attnum)
+    .fetch_all(pool)
+    .await
+}
+
+/// A sequence, for `:setval`.
+#[derive(Debug, Clone, PartialEq, Eq, sqlx::FromRow)]
+pub struct Sequence {
+    /// As `regclass` prints it.
+    pub name: String,
+    /// The integer column owning it, `table.column`.
+    pub owned_by: Option<String>,
+    /// The highest value in that column.
+    pub max: Option<i64>,
+}
+
+/// Sequence `name`, qualified or not, or `None` if there is no such
+/// sequence.
+pub async fn sequence(pool: &PgPool, name: &str) -> Result<Option<Sequence>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT c.oid::regclass::text AS name, o.owned_by, o.max \
+           FROM pg_class c \
+           LEFT JOIN LATERAL ( \
+               SELECT d.refobjid::regclass::text || '.' || quote_ident(a.attname) AS owned_by, \
+                      (xpath('/row/max/text()', query_to_xml(format( \
+                          'SELECT max(%I) AS max FROM %s', \
+                          a.attname, d.refobjid::regclass), false, true, '')) \
+                      )[1]::text::int8 AS max \
+                 FROM pg_depend d \
+                 JOIN pg_attribute a ON a.attrelid = d.refobjid AND a.attnum = d.refobjsubid \
+                WHERE d.classid = 'pg_class'::regclass AND d.refclassid = 'pg_class'::regclass \
+                  AND d.objid = c.oid AND d.deptype IN ('a', 'i') \
+                  AND a.atttypid IN ('int2'::regtype, 'int4'::regtype, 'int8'::regtype) \
+           ) o ON true \
+          WHERE c.oid = to_regclass($1) AND c.relkind = 'S'",
+    )
+    .bind(name)
+    .fetch_optional(pool)
+    .await
+}
+
+/// What `K` shows of a relation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Summary {
+    /// As `regclass` prints it.
+    pub name: String,
+    /// `table`, `view`, `sequence` and so on.
+    pub kind: String,
+    pub comment: Option<String>,
+    pub columns: Vec<Column>,
+    /// User triggers, for tables.
+    pub triggers: Vec<Trigger>,
+    /// For tables, leaving out the not null ones.
+    pub constraints: Vec<Constraint>,
+    /// The parent and bound of a partition, like `events FOR VALUES IN (1)`.
+    pub partition_of: Option<String>,
+    /// How many partitions a partitioned table has directly.
+    pub partitions: i64,
+}
+
+impl Summary {
+    /// Whether `D`, `E` and `V` apply: the relation is a table.
+    pub fn is_table(&self) -> bool {
+        matches!(self.kind.as_str(), "table" | "partitioned table")
+    }
+
+    /// The constraints added `NOT VALID` and not validated since.
+    pub fn not_valid(&self) -> impl Iterator<Item = &Constraint> {
+        self.constraints
+            .iter()
+            .filter(|constraint| !constraint.valid)
+    }
+}
+
+/// A user trigger of a table.
+#[derive(Debug, Clone, PartialEq, Eq, sqlx::FromRow)]
+pub struct Trigger {
+    pub name: String,
+    pub enabled: bool,
+}
+
+/// A constraint of a table.
+#[derive(Debug, Clone, PartialEq, Eq, sqlx::FromRow)]
+pub struct Constraint {
+    pub name: String,
+    /// `primary key`, `foreign key`, `check` and so on.
+    pub kind: String,
+    /// False when added `NOT VALID`: existing rows weren't checked.
+    pub valid: bool,
+}
+
+/// Relation `name`, qualified or not, or `None` if there is no such
+/// relation.
+pub async fn summary(pool: &PgPool, name: &str) -> Result<Option<Summary>, sqlx::Error> {
+    let relation: Option<(String, String, Option<String>)> = sqlx::query_as(
+        "SELECT c.oid::regclass::text, \
+                CASE c.relkind WHEN 'r' THEN 'table' WHEN 'p' THEN 'partitioned table' \
+                               WHEN 'v' THEN 'view' WHEN 'm' THEN 'materialized view' \
+                               WHEN 'f' THEN 'foreign table' WHEN 'S' THEN 'sequence' \
+                               WHEN 'i' THEN 'index' WHEN 'I' THEN 'partitioned index' \
+                               ELSE 'relation' END, \
+                obj_description(c.oid, 'pg_class') \
+           FROM pg_class c WHERE c.oid = to_regclass($1)",
+    )
+    .bind(name)
+    .fetch_optional(pool)
+    .await?;
+    let Some((name, kind, comment)) = relation else {
+        return Ok(None);
+    };
+    let columns = columns(pool, &name).await?.map(|(_, columns)| columns);
+    let mut summary = Summary {
+        name,
+        kind,
+        comment,
+        columns: columns.unwrap_or_default(),
+        triggers: Vec::new(),
+        constraints: Vec::new(),
+        partition_of: None,
+        partitions: 0,
+    };
+    if summary.is_table() {
+        (summary.partition_of, summary.partitions) = sqlx::query_as(
+            "SELECT (SELECT i.inhparent::regclass::text || ' ' \
+                            || pg_get_expr(c.relpartbound, c.oid) \
+                       FROM pg_inherits i WHERE i.inhrelid = c.oid AND c.relispartition), \
+                    (SELECT count(*) FROM pg_inherits i \
+                      WHERE i.inhparent = c.oid AND c.relkind = 'p') \
+               FROM pg_class c WHERE c.oid = to_regclass($1)",
+        )
+        .bind(&summary.name)
+        .fetch_one(pool)
+        .await?;
+        summary.triggers = sqlx::query_as(
+            "SELECT tgname::text AS name, tgenabled <> 'D' AS enabled FROM pg_trigger \
+              WHERE tgrelid = to_regclass($1) AND NOT tgisinternal ORDER BY tgname",
+        )
+        .bind(&summary.name)
+        .fetch_all(pool)
+        .await?;
+        summary.constraints = sqlx::query_as(
+            "SELECT conname::text AS name, \
+                    CASE contype WHEN 'p' THEN 'primary key' WHEN 'u' THEN 'unique' \
+                                 WHEN 'f' THEN 'foreign key' WHEN 'c' THEN 'check' \
+                                 WHEN 'x' THEN 'exclusion' ELSE 'constraint' END AS kind, \
+                    convalidated AS valid \
+               FROM pg_constraint WHERE conrelid = to_regclass($1) AND contype <> 'n' \
+              ORDER BY conname",
+        )
+        .bind(&summary.name)
+        .fetch_all(pool)
+        .await?;
+    }
+    Ok(Some(summary))
+}
+
+/// An overload of a function, aggregate or procedure.
+#[derive(Debug, Clone, PartialEq, Eq, sqlx::FromRow)]
+pub struct Function {
+    /// Qualified unless it is on the `search_path`.
+    pub name: String,
+    /// The arguments with their names and defaults, as
+    /// `pg_get_function_arguments()` prints them.
+    pub arguments: String,
+    /// `None` for procedures.
+    pub result: Option<String>,
+    pub comment: Option<String>,
+}
+
+/// The overloads of function `name`, qualified or not, as it would be
+/// written in a statement.
+pub async fn functions(pool: &PgPool, name: &str) -> Result<Vec<Function>, sqlx::Error> {
+    sqlx::query_as(
+        "WITH name AS (SELECT parse_ident($1) AS parts) \
+         SELECT CASE WHEN pg_function_is_visible(p.oid) THEN quote_ident(p.proname) \
+                     ELSE quote_ident(n.nspname) || '.' || quote_ident(p.proname) END AS name, \
+                pg_get_function_arguments(p.oid) AS arguments, \
+                pg_get_function_result(p.oid) AS result, \
+                obj_description(p.oid, 'pg_proc') AS comment \
+           FROM pg_proc p \
+           JOIN pg_namespace n ON n.oid = p.pronamespace, name \
+          WHERE p.proname = parts[array_length(parts, 1)] \
+            AND CASE WHEN array_length(parts, 1) = 1 THEN pg_function_is_visible(p.oid) \
+                     ELSE n.nspname = parts[array_length(parts, 1) - 1] END \
+          ORDER BY p.pronargs, p.oid",
+    )
+    .bind(name)
+    .fetch_all(pool)
+    .await
+}
+
+/// A foreign key, with its tables keyed as [`Schema`](super::schema::Schema)
+/// keys its relations.
+#[derive(Debug, Clone, PartialEq, Eq, sqlx::FromRow)]
+pub struct ForeignKey {
+    pub name: String,
+    /// `schema.name` of the table the key is on.
+    pub table: String,
+    /// Quoted if they need to be, like [`Column::name`].
+    pub columns: Vec<String>,
+    /// `schema.name` of the table it references.
+    pub references: String,
+    pub referenced: Vec<String>,
+}
+
+/// The foreign keys of the database, those partitions inherit left out.
+pub async fn foreign_keys(pool: &PgPool, server: Server) -> Result<Vec<ForeignKey>, sqlx::Error> {
+    let inherited = match server.flavor {
+        Flavor::Cockroach => "false",
+        _ => "k.conparentid <> 0",
+    };
+    let names = |key: &str, table: &str| {
+        format!(
+            "ARRAY(SELECT quote_ident(a.attname) \
+                     FROM unnest(k.{key}) WITH ORDINALITY AS u(attnum, i) \
+                     JOIN pg_attribute a ON a.attrelid = k.{table} AND a.attnum = u.attnum \
+                    ORDER BY u.i)"
+        )
+    };
+    sqlx::query_as(&format!(
+        "SELECT k.conname::text AS name, \
+                n.nspname || '.' || t.relname AS \"table\", {} AS columns, \
+                rn.nspname || '.' || r.relname AS \"references\", {} AS referenced \
+           FROM pg_constraint k \
+           JOIN pg_class t ON t.oid = k.conrelid \
+           JOIN pg_namespace n ON n.oid = t.relnamespace \
+           JOIN pg_class r ON r.oid = k.confrelid \
+           JOIN pg_namespace rn ON rn.oid = r.relnamespace \
+          WHERE k.contype = 'f' AND NOT ({inherited}) \
+          ORDER BY 2, 1",
+        names("conkey", "conrelid"),
+        names("confkey", "confrelid"),
+    ))
+    .fetch_all(pool)
+    .await
+}
@@ -0,0 +1,44 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Password storage in the OS keyring (secret-service, keychain, credential
+//! manager). Without the `keyring` feature nothing is ever stored and every
+//! lookup misses, so callers don't need to care which build they are in.
+
+#[cfg(feature = "keyring")]
+const SERVICE: &str = "dbvi";
+
+#[cfg(feature = "keyring")]
+pub fn load(profile: &str) -> Option<String> {
+    keyring::Entry::new(SERVICE, profile)
+        .and_then(|entry| entry.get_password())
+        .ok()
+}
+
+#[cfg(feature = "keyring")]
+pub fn store(profile: &str, password: &str) -> Result<(), String> {
+    keyring::Entry::new(SERVICE, profile)
+        .and_then(|entry| entry.set_password(password))
+        .map_err(|err| err.to_string())
+}
+
+#[cfg(not(feature = "keyring"))]
+pub fn load(_profile: &str) -> Option<String> {
+    None
+}
+
+#[cfg(not(feature = "keyring"))]
+pub fn store(_profile: &str, _password: &str) -> Result<(), String> {
+    Err("dbvi was built without keyring support".into())
+}
@@ -0,0 +1,275 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A make-believe shop for `dbvi --demo` and `:connect demo://`: a few
+//! tables of made-up rows and just enough `SELECT` to browse them, so there
+//! is something to try the UI on without a server.
+
+use std::cmp::Ordering;
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use crate::results::{self, Cell, Column, ResultSet};
+
+/// `SELECT <columns> FROM <table> [WHERE <column> <op> <value>]
+/// [ORDER BY <column> [ASC|DESC]] [LIMIT <n>]`, all the SQL there is.
+static SELECT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(concat!(
+        r"(?is)^select\s+(?<columns>.+?)\s+from\s+(?:public\.)?(?<table>\w+)",
+        r"(?:\s+where\s+(?<left>\w+)\s*(?<op><>|!=|<=|>=|=|<|>)\s*(?<right>'(?:[^']|'')*'|[\w.+-]+))?",
+        r"(?:\s+order\s+by\s+(?<order>\w+)(?:\s+(?<direction>asc|desc))?)?",
+        r"(?:\s+limit\s+(?<limit>\d+))?$",
+    ))
+    .expect("valid regex")
+});
+
+const CUSTOMERS: [(&str, &str); 12] = [
+    ("Ada Lovelace", "GB"),
+    ("Grace Hopper", "US"),
+    ("Alan Turing", "GB"),
+    ("Edsger Dijkstra", "NL"),
+    ("Barbara Liskov", "US"),
+    ("Donald Knuth", "US"),
+    ("Margaret Hamilton", "US"),
+    ("Niklaus Wirth", "CH"),
+    ("Frances Allen", "US"),
+    ("Tony Hoare", "GB"),
+    ("Radia Perlman", "US"),
+    ("Bjarne Stroustrup", "DK"),
+];
+
+const PRODUCTS: [(&str, &str, u32); 8] = [
+    ("Mechanical keyboard", "89.00", 42),
+    ("Trackball", "54.50", 17),
+    ("27\" monitor", "319.99", 8),
+    ("Laptop stand", "29.95", 120),
+    ("USB-C hub", "39.00", 0),
+    ("Noise cancelling headphones", "199.00", 23),
+    ("Desk lamp", "24.99", 64),
+    ("Rubber duck", "4.99", 500),
+];
+
+/// Indexed by how many orders came in since, in sixes.
+const STATUSES: [&str; 7] = [
+    "pending",
+    "paid",
+    "shipped",
+    "delivered",
+    "delivered",
+    "delivered",
+    "delivered",
+];
+
+#[derive(Debug)]
+pub struct Demo {
+    tables: Vec<(&'static str, ResultSet)>,
+    /// Always `shop`, for the status line.
+    pub name: String,
+}
+
+impl Default for Demo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Demo {
+    pub fn new() -> Self {
+        let text = |value: &str| Some(value.to_string());
+
+        let mut customers = ResultSet::new(vec![
+            Column::new("id", "int4"),
+            Column::new("name", "text"),
+            Column::new("email", "text"),
+            Column::new("country", "text"),
+        ]);
+        for (id, (name, country)) in (1..).zip(CUSTOMERS) {
+            let email = name.to_lowercase().replace(' ', ".") + "@example.com";
+            // Not everyone left an email address.
+            let email = (id % 5 != 0).then_some(email);
+            customers
+                .rows
+                .push(vec![Some(id.to_string()), text(name), email, text(country)]);
+        }
+
+        let mut products = ResultSet::new(vec![
+            Column::new("id", "int4"),
+            Column::new("name", "text"),
+            Column::new("price", "numeric"),
+            Column::new("stock", "int4"),
+        ]);
+        for (id, (name, price, stock)) in (1..).zip(PRODUCTS) {
+            products.rows.push(vec![
+                Some(id.to_string()),
+                text(name),
+                text(price),
+                Some(stock.to_string()),
+            ]);
+        }
+
+        let mut orders = ResultSet::new(vec![
+            Column::new("id", "int4"),
+            Column::new("customer_id", "int4"),
+            Column::new("product_id", "int4"),
+            Column::new("quantity", "int4"),
+            Column::new("status", "text"),
+            Column::new("ordered_at", "timestamptz"),
+        ]);
+        for id in 1..=40usize {
+            // Scattered, but the same every time.
+            let customer = id * 7 % CUSTOMERS.len() + 1;
+            let product = id * 5 % PRODUCTS.len() + 1;
+            let quantity = id * 3 % 4 + 1;
+            // The newest orders haven't made it as far.
+            let status = STATUSES[(40 - id) / 6];
+            let month = (id - 1) / 4 + 1;
+            let day = id * 13 % 28 + 1;
+            let hour = id * 5 % 24;
+            orders.rows.push(vec![
+                Some(id.to_string()),
+                Some(customer.to_string()),
+                Some(product.to_string()),
+                Some(quantity.to_string()),
+                text(status),
+                Some(format!(
+                    "2025-{month:02}-{day:02} {hour:02}:{:02}:00+00",
+                    id * 17 % 60
+                )),
+            ]);
+        }
+
+        Self {
+            tables: vec![
+                ("customers", customers),
+                ("orders", orders),
+                ("products", products),
+            ],
+            name: "shop".into(),
+        }
+    }
+
+    /// Runs each statement of `sql`, returning the rows of the last one.
+    pub fn query(&mut self, sql: &str) -> Result<(Option<ResultSet>, u64), String> {
+        let mut last = None;
+        for statement in crate::statements::split(sql) {
+            last = Some(self.select(statement)?);
+        }
+        let rows = last.as_ref().map_or(0, |results| results.rows.len() as u64);
+        Ok((last, rows))
+    }
+
+    fn select(&self, statement: &str) -> Result<ResultSet, String> {
+        let Some(captures) = SELECT.captures(statement.trim()) else {
+            return Err("The demo database is read-only and only understands \
+                 SELECT <columns> FROM <table> [WHERE ...] [ORDER BY ...] [LIMIT ...]"
+                .into());
+        };
+        let name = captures["table"].to_lowercase();
+        let table = self
+            .tables
+            .iter()
+            .find(|(table, _)| *table == name)
+            .map(|(_, table)| table)
+            .ok_or_else(|| format!("relation \"{name}\" does not exist"))?;
+        let column = |name: &str| {
+            table
+                .column_index(name)
+                .ok_or_else(|| format!("column \"{name}\" does not exist"))
+        };
+
+        let mut results = table.clone();
+        if let Some(left) = captures.name("left") {
+            let index = column(left.as_str())?;
+            let right = captures["right"].to_string();
+            let right = match right.strip_prefix('\'').and_then(|r| r.strip_suffix('\'')) {
+                Some(quoted) => quoted.replace("''", "'"),
+                None => right,
+            };
+            let op = &captures["op"];
+            results.rows.retain(|row| {
+                let Some(value) = row.get(index) else {
+                    return false;
+                };
+                let ordering = results::compare(value, &right);
+                match op {
+                    "=" => ordering == Ordering::Equal,
+                    "<>" | "!=" => ordering != Ordering::Equal,
+                    "<" => ordering == Ordering::Less,
+                    ">" => ordering == Ordering::Greater,
+                    "<=" => ordering != Ordering::Greater,
+                    _ => ordering != Ordering::Less,
+                }
+            });
+        }
+        if let Some(order) = captures.name("order") {
+            let descending = captures
+                .name("direction")
+                .is_some_and(|direction| direction.as_str().eq_ignore_ascii_case("desc"));
+            results.sort(column(order.as_str())?, descending);
+        }
+        if let Some(limit) = captures.name("limit") {
+            results
+                .rows
+                .truncate(limit.as_str().parse().unwrap_or(usize::MAX));
+        }
+
+        let columns = captures["columns"].trim();
+        if columns == "*" {
+            return Ok(results);
+        }
+        let indexes = columns
+            .split(',')
+            .map(|name| column(name.trim()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut projected = ResultSet::new(
+            indexes
+                .iter()
+                .map(|&i| results.columns[i].clone())
+                .collect(),
+        );
+        projected.rows = results
+            .rows
+            .iter()
+            .map(|row| {
+                indexes
+                    .iter()
+                    .map(|&i| row.get(i).map(String::from))
+                    .collect::<Vec<Cell>>()
+            })
+            .collect();
+        Ok(projected)
+    }
+
+    /// The tables, with how many rows each has.
+    pub fn tables(&self) -> ResultSet {
+        let mut tables = ResultSet::new(vec![
+            Column::new("schema", "text"),
+            Column::new("name", "text"),
+            Column::new("rows", "int8"),
+        ]);
+        tables.rows = self
+            .tables
+            .iter()
+            .map(|(name, table)| {
+                vec![
+                    Some("public".into()),
+                    Some(name.to_string()),
+                    Some(table.rows.len().to_string()),
+                ]
+            })
+            .collect();
+        tables
+    }
+}
@@ -0,0 +1,124 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! DuckDB, embedded: a database file, or an in-memory database for
+//! querying Parquet and CSV files directly, as in
+//! `SELECT * FROM 'events.parquet'`.
+
+use duckdb::Connection;
+
+use crate::results::{Column, ResultSet};
+
+const TABLES: &str = "\
+SELECT table_schema AS schema, table_name AS name, table_type AS type
+FROM information_schema.tables
+ORDER BY table_schema, table_name";
+
+#[derive(Debug)]
+pub struct Duckdb {
+    connection: Connection,
+    /// The file, or `:memory:`.
+    pub name: String,
+}
+
+impl Duckdb {
+    /// Opens `duckdb://path/to/file.duckdb`, creating it if need be, or an
+    /// in-memory database for `duckdb://` alone.
+    pub fn open(url: &str) -> Result<Self, String> {
+        let path = url.strip_prefix("duckdb://").unwrap_or(url);
+        let connection = if path.is_empty() || path == ":memory:" {
+            Connection::open_in_memory()
+        } else {
+            Connection::open(crate::config::expand_home(path))
+        }
+        .map_err(|err| err.to_string())?;
+        Ok(Self {
+            connection,
+            name: if path.is_empty() { ":memory:" } else { path }.to_string(),
+        })
+    }
+
+    /// Runs `sql` statement by statement, returning the rows of the last
+    /// one that returned any and the number of rows returned or affected.
+    pub fn query(&mut self, sql: &str) -> duckdb::Result<(Option<ResultSet>, u64)> {
+        let mut results = None;
+        let mut rows = 0;
+        for statement in crate::statements::split(sql) {
+            let statement = statement.trim().trim_end_matches(';');
+            match self.fetch(statement) {
+                Some(fetched) => {
+                    let fetched = fetched?;
+                    rows = fetched.rows.len() as u64;
+                    results = Some(fetched);
+                }
+                None => rows = self.connection.execute(statement, [])? as u64,
+            }
+        }
+        Ok((results, rows))
+    }
+
+    /// Rows of `statement` with every value cast to text, the way DuckDB
+    /// itself prints them, lists and structs included. `None` if it isn't
+    /// something that can be selected from, like `INSERT` or `CREATE`.
+    fn fetch(&self, statement: &str) -> Option<duckdb::Result<ResultSet>> {
+        let mut describe = self
+            .connection
+            .prepare(&format!("DESCRIBE SELECT * FROM ({statement})"))
+            .ok()?;
+        let columns = describe
+            .query_map([], |row| {
+                let name: String = row.get(0)?;
+                let ty: String = row.get(1)?;
+                Ok(Column::new(name, type_name(&ty)))
+            })
+            .and_then(|columns| columns.collect::<duckdb::Result<Vec<_>>>())
+            .ok()?;
+        let width = columns.len();
+        let mut results = ResultSet::new(columns);
+        let mut fetch = || {
+            let mut select = self
+                .connection
+                .prepare(&format!("SELECT COLUMNS(*)::VARCHAR FROM ({statement})"))?;
+            let mut rows = select.query([])?;
+            while let Some(row) = rows.next()? {
+                let cells = (0..width)
+                    .map(|index| row.get::<_, Option<String>>(index))
+                    .collect::<duckdb::Result<Vec<_>>>()?;
+                results.rows.push(cells);
+            }
+            Ok(())
+        };
+        Some(fetch().map(|()| results))
+    }
+
+    pub fn tables(&mut self) -> duckdb::Result<ResultSet> {
+        let (results, _) = self.query(TABLES)?;
+        Ok(results.unwrap_or_default())
+    }
+}
+
+/// DuckDB's type name, or its Postgres counterpart for the numeric types
+/// so the grid aligns and sorts them as numbers.
+fn type_name(ty: &str) -> String {
+    let base = ty.split('(').next().unwrap_or(ty);
+    match base {
+        "TINYINT" | "SMALLINT" | "UTINYINT" => "int2".into(),
+        "INTEGER" | "USMALLINT" => "int4".into(),
+        "BIGINT" | "UINTEGER" => "int8".into(),
+        "HUGEINT" | "UBIGINT" | "UHUGEINT" | "DECIMAL" => "numeric".into(),
+        "FLOAT" => "float4".into(),
+        "DOUBLE" => "float8".into(),
+        _ => ty.to_lowercase(),
+    }
+}
@@ -0,0 +1,107 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+pub mod asof;
+pub mod backend;
+pub mod capabilities;
+pub mod catalog;
+#[cfg(feature = "clickhouse")]
+pub mod clickhouse;
+pub mod credentials;
+pub mod demo;
+#[cfg(feature = "duckdb")]
+pub mod duckdb;
+pub mod monitor;
+#[cfg(feature = "mssql")]
+pub mod mssql;
+#[cfg(feature = "odbc")]
+pub mod odbc;
+pub mod schema;
+pub mod server;
+pub mod service;
+pub mod session;
+pub mod tunnel;
+
+use std::env;
+
+use sqlx::postgres::PgConnectOptions;
+
+/// SQLSTATE `invalid_password`, returned both for a wrong password and for
+/// a password that was never sent.
+const INVALID_PASSWORD: &str = "28P01";
+
+/// Builds connection options the way libpq would. `target` is either a URL
+/// or a keyword/value string such as `service=mydb host=db`; its parameters
+/// win over those of the service (from `target`, `service` or `PGSERVICE`),
+/// which in turn win over `DATABASE_URL` and the standard `PG*` environment
+/// variables. Either way a missing password is looked up in `~/.pgpass` (or
+/// `PGPASSFILE`).
+pub fn connect_options(
+    target: Option<&str>,
+    service: Option<&str>,
+) -> Result<PgConnectOptions, sqlx::Error> {
+    let explicit = match target {
+        Some(url) if url.starts_with("postgres://") || url.starts_with("postgresql://") => {
+            return url.parse();
+        }
+        Some(conninfo) => service::parse_conninfo(conninfo).map_err(config_error)?,
+        None => Vec::new(),
+    };
+
+    let service = explicit
+        .iter()
+        .find(|(key, _)| key == "service")
+        .map(|(_, name)| name.clone())
+        .or(service.map(str::to_owned))
+        .or_else(|| env::var("PGSERVICE").ok());
+    let mut params = match service {
+        Some(name) => service::lookup(&name).map_err(config_error)?,
+        None if explicit.is_empty() => {
+            return match env::var("DATABASE_URL") {
+                Ok(url) => url.parse(),
+                Err(_) => Ok(PgConnectOptions::new()),
+            };
+        }
+        None => Vec::new(),
+    };
+    params.extend(explicit.into_iter().filter(|(key, _)| key != "service"));
+
+    // sqlx understands every libpq keyword we care about as a URL query
+    // parameter, and parsing a URL is also what applies `.pgpass`.
+    let mut url = url::Url::parse("postgres://").expect("static URL is valid");
+    url.query_pairs_mut().extend_pairs(params);
+    url.as_str().parse()
+}
+
+fn config_error(message: String) -> sqlx::Error {
+    sqlx::Error::Configuration(message.into())
+}
+
+/// The key credentials are stored under, e.g. `postgres@localhost:5432/shop`.
+pub fn profile_key(options: &PgConnectOptions) -> String {
+    format!(
+        "{}@{}:{}/{}",
+        options.get_username(),
+        options.get_host(),
+        options.get_port(),
+        options.get_database().unwrap_or(options.get_username()),
+    )
+}
+
+/// Whether connecting failed because the server wants a (different) password.
+pub fn is_auth_error(err: &sqlx::Error) -> bool {
+    err.as_database_error()
+        .and_then(|err| err.code())
+        .is_some_and(|code| code == INVALID_PASSWORD)
+}
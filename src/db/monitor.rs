@@ -0,0 +1,821 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Reports on what the server is up to, shown in the results grid and
+//! refreshed while they are up.
+
+use std::future::Future;
+use std::time::Duration;
+
+use sqlx::postgres::PgConnectOptions;
+use sqlx::{Connection, Executor, PgConnection, PgPool};
+
+use super::server::{Flavor, Server};
+use super::session::Session;
+
+use crate::results::{self, ResultSet};
+use crate::statements;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Report {
+    /// `:activity`, the client backends and what they are running.
+    Activity,
+    /// `:locks`, backends waiting on locks under the ones holding them.
+    Locks,
+    /// `:sizes [schema]`, tables by the space they take.
+    Sizes(Option<String>),
+    /// `:statements`, the top queries of this database according to
+    /// `pg_stat_statements`.
+    Statements,
+    /// `:maintenance`, dead tuples and when tables were last vacuumed and
+    /// analyzed.
+    Maintenance,
+    /// `:roles`, with their attributes and the roles they are members of.
+    Roles,
+    /// `:privileges <table>`, what each role may do with a table, counting
+    /// what it inherits through its memberships.
+    Privileges(String),
+    /// `:replication`, the replicas streaming from this server and its
+    /// replication slots, with how far behind they are.
+    Replication,
+    /// `:grep-schema <pattern>`, the names, comments and view and function
+    /// bodies the pattern matches, a regular expression taken without
+    /// regard to case.
+    Grep(String),
+    /// `:db`, the databases on the server one may connect to, with their
+    /// sizes.
+    Databases,
+    /// `:prepared`, the statements prepared in this session.
+    Prepared,
+    /// `:extensions`, the extensions installed with the updates available
+    /// for them, then the foreign servers and foreign tables.
+    Extensions,
+    /// `:sequences`, with their last values and, for those owned by an
+    /// integer column, whether they are behind its highest value, as after
+    /// a restore that copied rows but not sequences.
+    Sequences,
+    /// `:partitions <table>`, the partition tree of a partitioned table
+    /// with the bounds, estimated rows and size under each partition.
+    Partitions(String),
+    /// `:matviews`, the materialized views, whether they can be refreshed
+    /// concurrently and, where the server lets on, when their data was
+    /// last written.
+    MatViews,
+}
+
+impl Report {
+    pub fn title(&self) -> String {
+        match self {
+            Report::Activity => "activity".into(),
+            Report::Locks => "locks".into(),
+            Report::Sizes(None) => "sizes".into(),
+            Report::Sizes(Some(schema)) => format!("sizes of {schema}"),
+            Report::Statements => "statements".into(),
+            Report::Maintenance => "maintenance".into(),
+            Report::Roles => "roles".into(),
+            Report::Replication => "replication".into(),
+            Report::Privileges(table) => format!("privileges on {table}"),
+            Report::Grep(pattern) => format!("grep-schema {pattern}"),
+            Report::Databases => "databases".into(),
+            Report::Prepared => "prepared".into(),
+            Report::Extensions => "extensions".into(),
+            Report::Sequences => "sequences".into(),
+            Report::Partitions(table) => format!("partitions of {table}"),
+            Report::MatViews => "matviews".into(),
+        }
+    }
+
+    /// How often the report refreshes itself.
+    pub fn interval(&self) -> Option<Duration> {
+        match self {
+            Report::Activity | Report::Locks | Report::Replication => Some(Duration::from_secs(2)),
+            Report::Maintenance => Some(Duration::from_secs(5)),
+            Report::Sizes(_)
+            | Report::Statements
+            | Report::Roles
+            | Report::Privileges(_)
+            | Report::Grep(_)
+            | Report::Databases
+            | Report::Prepared
+            | Report::Extensions
+            | Report::Sequences
+            | Report::Partitions(_)
+            | Report::MatViews => None,
+        }
+    }
+
+    /// Whether `server` has what the report reads from.
+    pub fn available(&self, server: Server) -> bool {
+        self.sql(server).is_some()
+    }
+
+    fn sql(&self, server: Server) -> Option<&'static str> {
+        let sql = match (self, server.flavor) {
+            // CockroachDB has no pg_stat_activity worth the name, no
+            // pg_stat_statements and no relation sizes; its crdb_internal
+            // tables have what there is. Locks, vacuuming and replication
+            // work nothing like Postgres there.
+            (Report::Activity, Flavor::Cockroach) => {
+                "SELECT session_id, user_name AS user, application_name AS application, \
+                        client_address AS client, status AS state, \
+                        date_trunc('second', now() - active_query_start) AS duration, \
+                        active_queries AS query \
+                 FROM crdb_internal.cluster_sessions \
+                 WHERE status <> 'CLOSED' \
+                 ORDER BY active_queries <> '' DESC, duration DESC NULLS LAST"
+            }
+            (Report::Statements, Flavor::Cockroach) => {
+                "SELECT count AS calls, \
+                        round((service_lat_avg * count * 1000)::numeric, 1) AS total_ms, \
+                        round((service_lat_avg * 1000)::numeric, 2) AS mean_ms, \
+                        round((100 * service_lat_avg * count \
+                            / nullif(sum(service_lat_avg * count) OVER (), 0))::numeric, 1) \
+                            AS percent, \
+                        round(rows_avg * count)::int8 AS rows, \
+                        application_name AS application, key AS query \
+                 FROM crdb_internal.node_statement_statistics \
+                 ORDER BY service_lat_avg * count DESC \
+                 LIMIT 200"
+            }
+            (Report::Sizes(_), Flavor::Cockroach) => {
+                "SELECT t.schema_name AS schema, t.name, \
+                        s.estimated_row_count AS approx_rows \
+                 FROM crdb_internal.tables t \
+                 LEFT JOIN crdb_internal.table_row_statistics s ON s.table_id = t.table_id \
+                 WHERE t.database_name = current_database() AND t.state = 'PUBLIC' \
+                   AND ($1::text IS NULL OR t.schema_name = $1) \
+                 ORDER BY s.estimated_row_count DESC NULLS LAST, t.schema_name, t.name"
+            }
+            (
+                Report::Locks
+                | Report::Maintenance
+                | Report::Replication
+                | Report::Grep(_)
+                | Report::Extensions
+                | Report::Sequences
+                | Report::Partitions(_)
+                | Report::MatViews,
+                Flavor::Cockroach,
+            ) => {
+                return None;
+            }
+            // YugabyteDB keeps its data in DocDB: VACUUM does nothing and
+            // there is no WAL streaming to report on.
+            (Report::Maintenance | Report::Replication, Flavor::Yugabyte) => return None,
+            // A hypertable's rows live in chunks in `_timescaledb_internal`;
+            // count them with their hypertable instead of on their own.
+            (Report::Sizes(_), Flavor::Timescale) => {
+                "SELECT n.nspname AS schema, c.relname AS name, \
+                        CASE WHEN h.hypertable_name IS NOT NULL THEN 'hypertable' \
+                            WHEN c.relkind = 'r' THEN 'table' WHEN c.relkind = 'p' \
+                            THEN 'partitioned' WHEN c.relkind = 'm' THEN 'matview' END AS kind, \
+                        pg_size_pretty(s.total) AS total, \
+                        pg_size_pretty(CASE WHEN h.hypertable_name IS NULL \
+                            THEN pg_table_size(c.oid) END) AS table, \
+                        pg_size_pretty(CASE WHEN h.hypertable_name IS NULL \
+                            THEN pg_indexes_size(c.oid) END) AS indexes, \
+                        CASE WHEN h.hypertable_name IS NOT NULL \
+                            THEN approximate_row_count(c.oid) \
+                            WHEN c.reltuples < 0 THEN st.n_live_tup \
+                            ELSE c.reltuples::bigint END AS approx_rows \
+                 FROM pg_class c \
+                 JOIN pg_namespace n ON n.oid = c.relnamespace \
+                 LEFT JOIN pg_stat_user_tables st ON st.relid = c.oid \
+                 LEFT JOIN timescaledb_information.hypertables h \
+                     ON h.hypertable_schema = n.nspname AND h.hypertable_name = c.relname \
+                 CROSS JOIN LATERAL (SELECT CASE WHEN h.hypertable_name IS NOT NULL \
+                     THEN hypertable_size(c.oid) ELSE pg_total_relation_size(c.oid) END \
+                     AS total) s \
+                 WHERE c.relkind IN ('r', 'p', 'm') \
+                   AND n.nspname NOT IN ('pg_catalog', 'information_schema') \
+                   AND n.nspname NOT LIKE 'pg_toast%' \
+                   AND n.nspname NOT LIKE '\\_timescaledb%' \
+                   AND n.nspname <> 'timescaledb_information' \
+                   AND ($1::text IS NULL OR n.nspname = $1) \
+                 ORDER BY s.total DESC"
+            }
+            // Before Postgres 13 `total_exec_time` was `total_time`, and so
+            // on. YugabyteDB 2.x is Postgres 11.
+            (Report::Statements, _) if (1..130000).contains(&server.version) => {
+                "SELECT s.calls, \
+                        round(s.total_time::numeric, 1) AS total_ms, \
+                        round(s.mean_time::numeric, 2) AS mean_ms, \
+                        round((100 * s.total_time \
+                            / nullif(sum(s.total_time) OVER (), 0))::numeric, 1) AS percent, \
+                        s.rows, r.rolname AS user, s.query \
+                 FROM pg_stat_statements s \
+                 LEFT JOIN pg_roles r ON r.oid = s.userid \
+                 WHERE s.dbid = (SELECT oid FROM pg_database WHERE datname = current_database()) \
+                 ORDER BY s.total_time DESC \
+                 LIMIT 200"
+            }
+            (Report::Activity, _) => {
+                "SELECT pid, usename AS user, datname AS database, state, \
+                        concat_ws(':', wait_event_type, wait_event) AS wait, \
+                        date_trunc('second', now() - CASE WHEN state = 'active' \
+                            THEN query_start ELSE state_change END) AS duration, \
+                        query \
+                 FROM pg_stat_activity \
+                 WHERE backend_type = 'client backend' AND pid <> pg_backend_pid() \
+                 ORDER BY state = 'active' DESC, duration DESC NULLS LAST"
+            }
+            // Roots are backends that block others without waiting
+            // themselves; each waiting backend is listed under every one
+            // it waits for.
+            (Report::Locks, _) => {
+                "WITH RECURSIVE waiting AS ( \
+                     SELECT pid, pg_blocking_pids(pid) AS blockers \
+                     FROM pg_stat_activity \
+                     WHERE cardinality(pg_blocking_pids(pid)) > 0 \
+                 ), tree AS ( \
+                     SELECT pid, 0 AS depth, ARRAY[pid] AS path \
+                     FROM pg_stat_activity \
+                     WHERE pid IN (SELECT unnest(blockers) FROM waiting) \
+                       AND pid NOT IN (SELECT pid FROM waiting) \
+                     UNION ALL \
+                     SELECT w.pid, t.depth + 1, t.path || w.pid \
+                     FROM waiting w JOIN tree t ON t.pid = ANY (w.blockers) \
+                     WHERE w.pid <> ALL (t.path) \
+                 ) \
+                 SELECT repeat('  ', t.depth) || CASE WHEN t.depth > 0 THEN '└ ' ELSE '' END \
+                            || t.pid AS tree, \
+                        t.pid, a.usename AS user, a.state, \
+                        (SELECT string_agg(l.mode || ' on ' \
+                                    || coalesce(l.relation::regclass::text, l.locktype), ', ') \
+                         FROM pg_locks l WHERE l.pid = t.pid AND NOT l.granted) AS waiting_for, \
+                        date_trunc('second', now() - a.xact_start) AS xact_age, \
+                        a.query \
+                 FROM tree t JOIN pg_stat_activity a USING (pid) \
+                 ORDER BY t.path"
+            }
+            // Row counts are the planner's estimate, or the live tuples
+            // counted since if the table was never analyzed.
+            (Report::Sizes(_), _) => {
+                "SELECT n.nspname AS schema, c.relname AS name, \
+                        CASE c.relkind WHEN 'r' THEN 'table' WHEN 'p' THEN 'partitioned' \
+                            WHEN 'm' THEN 'matview' END AS kind, \
+                        pg_size_pretty(pg_total_relation_size(c.oid)) AS total, \
+                        pg_size_pretty(pg_table_size(c.oid)) AS table, \
+                        pg_size_pretty(pg_indexes_size(c.oid)) AS indexes, \
+                        CASE WHEN c.reltuples < 0 THEN s.n_live_tup \
+                            ELSE c.reltuples::bigint END AS approx_rows \
+                 FROM pg_class c \
+                 JOIN pg_namespace n ON n.oid = c.relnamespace \
+                 LEFT JOIN pg_stat_user_tables s ON s.relid = c.oid \
+                 WHERE c.relkind IN ('r', 'p', 'm') \
+                   AND n.nspname NOT IN ('pg_catalog', 'information_schema') \
+                   AND n.nspname NOT LIKE 'pg_toast%' \
+                   AND ($1::text IS NULL OR n.nspname = $1) \
+                 ORDER BY pg_total_relation_size(c.oid) DESC"
+            }
+            (Report::Maintenance, _) => {
+                "SELECT schemaname AS schema, relname AS table, \
+                        n_live_tup AS live, n_dead_tup AS dead, \
+                        round(100.0 * n_dead_tup / nullif(n_live_tup + n_dead_tup, 0), 1) \
+                            AS dead_percent, \
+                        date_trunc('second', greatest(last_vacuum, last_autovacuum)) \
+                            AS last_vacuum, \
+                        date_trunc('second', greatest(last_analyze, last_autoanalyze)) \
+                            AS last_analyze, \
+                        n_mod_since_analyze AS modified_since_analyze \
+                 FROM pg_stat_user_tables \
+                 ORDER BY n_dead_tup DESC, schemaname, relname"
+            }
+            // Lag is measured from the latest WAL position: the one written
+            // on a primary, the one received on a standby.
+            (Report::Replication, _) => {
+                "WITH current AS ( \
+                     SELECT CASE WHEN pg_is_in_recovery() THEN pg_last_wal_receive_lsn() \
+                         ELSE pg_current_wal_lsn() END AS lsn \
+                 ) \
+                 SELECT 'replica' AS kind, \
+                        coalesce(nullif(r.application_name, ''), r.client_addr::text) AS name, \
+                        r.state, r.sync_state AS sync, \
+                        pg_size_pretty(pg_wal_lsn_diff(c.lsn, r.replay_lsn)) AS lag, \
+                        r.replay_lag AS lag_time, \
+                        r.client_addr::text AS detail \
+                 FROM pg_stat_replication r, current c \
+                 UNION ALL \
+                 SELECT 'slot', s.slot_name, \
+                        CASE WHEN s.active THEN 'active' ELSE 'inactive' END, \
+                        s.slot_type, \
+                        pg_size_pretty(pg_wal_lsn_diff(c.lsn, s.restart_lsn)), \
+                        NULL::interval, \
+                        coalesce(s.database, s.plugin) \
+                 FROM pg_replication_slots s, current c"
+            }
+            (Report::Roles, _) => {
+                "SELECT r.rolname AS role, r.rolcanlogin AS login, r.rolsuper AS superuser, \
+                        r.rolcreatedb AS createdb, r.rolcreaterole AS createrole, \
+                        r.rolinherit AS inherit, r.rolreplication AS replication, \
+                        r.rolbypassrls AS bypassrls, \
+                        nullif(r.rolconnlimit, -1) AS connection_limit, \
+                        r.rolvaliduntil AS valid_until, \
+                        (SELECT string_agg(g.rolname, ', ' ORDER BY g.rolname) \
+                         FROM pg_auth_members m JOIN pg_roles g ON g.oid = m.roleid \
+                         WHERE m.member = r.oid) AS member_of \
+                 FROM pg_roles r \
+                 WHERE r.rolname !~ '^pg_' \
+                 ORDER BY r.rolname"
+            }
+            (Report::Privileges(_), _) => {
+                "SELECT r.rolname AS role, \
+                        pg_get_userbyid(c.relowner) = r.rolname AS owner, \
+                        has_table_privilege(r.oid, c.oid, 'SELECT') AS select, \
+                        has_table_privilege(r.oid, c.oid, 'INSERT') AS insert, \
+                        has_table_privilege(r.oid, c.oid, 'UPDATE') AS update, \
+                        has_table_privilege(r.oid, c.oid, 'DELETE') AS delete, \
+                        has_table_privilege(r.oid, c.oid, 'TRUNCATE') AS truncate, \
+                        has_table_privilege(r.oid, c.oid, 'REFERENCES') AS references, \
+                        has_table_privilege(r.oid, c.oid, 'TRIGGER') AS trigger, \
+                        has_schema_privilege(r.oid, c.relnamespace, 'USAGE') AS schema_usage \
+                 FROM pg_roles r, pg_class c \
+                 WHERE c.oid = $1::regclass AND r.rolname !~ '^pg_' \
+                 ORDER BY r.rolname"
+            }
+            // Names first, then comments, then the lines of bodies; `object`
+            // is what `:ddl` or `:definition` takes.
+            (Report::Grep(_), _) => {
+                "WITH relations AS ( \
+                     SELECT c.oid, c.oid::regclass::text AS object, c.relname, \
+                            CASE c.relkind WHEN 'v' THEN 'view' WHEN 'm' THEN 'matview' \
+                                WHEN 'f' THEN 'foreign table' ELSE 'table' END AS kind \
+                     FROM pg_class c JOIN pg_namespace n ON n.oid = c.relnamespace \
+                     WHERE c.relkind IN ('r', 'p', 'v', 'm', 'f') \
+                       AND n.nspname NOT IN ('pg_catalog', 'information_schema') \
+                       AND n.nspname !~ '^pg_toast'), \
+                 functions AS ( \
+                     SELECT p.oid, quote_ident(n.nspname) || '.' || quote_ident(p.proname) \
+                                AS object, \
+                            p.oid::regprocedure::text AS signature, p.proname, p.prosrc \
+                     FROM pg_proc p JOIN pg_namespace n ON n.oid = p.pronamespace \
+                     WHERE p.prokind <> 'a' \
+                       AND n.nspname NOT IN ('pg_catalog', 'information_schema')) \
+                 SELECT kind, object, \"column\", \"in\", text FROM ( \
+                     SELECT 1 AS rank, kind, object, NULL AS \"column\", 'name' AS \"in\", \
+                            relname::text AS text, 0::bigint AS line_no \
+                     FROM relations WHERE relname ~* $1 \
+                     UNION ALL \
+                     SELECT 1, 'column', r.object, a.attname::text, 'name', \
+                            a.attname || ' ' || format_type(a.atttypid, a.atttypmod), 0 \
+                     FROM relations r JOIN pg_attribute a ON a.attrelid = r.oid \
+                     WHERE a.attnum > 0 AND NOT a.attisdropped AND a.attname ~* $1 \
+                     UNION ALL \
+                     SELECT 1, 'function', object, NULL, 'name', signature, 0 \
+                     FROM functions WHERE proname ~* $1 \
+                     UNION ALL \
+                     SELECT 2, CASE WHEN d.objsubid = 0 THEN r.kind ELSE 'column' END, \
+                            r.object, a.attname::text, 'comment', d.description, 0 \
+                     FROM pg_description d \
+                     JOIN relations r ON d.classoid = 'pg_class'::regclass AND r.oid = d.objoid \
+                     LEFT JOIN pg_attribute a \
+                            ON a.attrelid = r.oid AND a.attnum = d.objsubid AND d.objsubid > 0 \
+                     WHERE d.description ~* $1 \
+                     UNION ALL \
+                     SELECT 2, 'function', f.object, NULL, 'comment', d.description, 0 \
+                     FROM pg_description d \
+                     JOIN functions f ON d.classoid = 'pg_proc'::regclass AND f.oid = d.objoid \
+                     WHERE d.description ~* $1 \
+                     UNION ALL \
+                     SELECT 3, r.kind, r.object, NULL, 'definition', trim(line), n \
+                     FROM relations r, \
+                          regexp_split_to_table(pg_get_viewdef(r.oid, true), E'\\n') \
+                              WITH ORDINALITY AS body(line, n) \
+                     WHERE r.kind IN ('view', 'matview') AND line ~* $1 \
+                     UNION ALL \
+                     SELECT 3, 'function', f.object, NULL, 'definition', trim(line), n \
+                     FROM functions f, \
+                          regexp_split_to_table(f.prosrc, E'\\n') WITH ORDINALITY AS body(line, n) \
+                     WHERE line ~* $1 \
+                 ) matches \
+                 ORDER BY rank, object, \"column\" NULLS FIRST, line_no \
+                 LIMIT 1000"
+            }
+            (Report::Databases, Flavor::Cockroach) => {
+                "SELECT database_name AS database, owner, \
+                        database_name = current_database() AS current \
+                 FROM [SHOW DATABASES] \
+                 ORDER BY database_name"
+            }
+            // Sizing a database takes being allowed to connect to it.
+            (Report::Databases, _) => {
+                "SELECT d.datname AS database, \
+                        CASE WHEN has_database_privilege(d.oid, 'CONNECT') \
+                            THEN pg_size_pretty(pg_database_size(d.oid)) END AS size, \
+                        pg_get_userbyid(d.datdba) AS owner, \
+                        pg_encoding_to_char(d.encoding) AS encoding, \
+                        d.datname = current_database() AS current \
+                 FROM pg_database d \
+                 WHERE d.datallowconn AND NOT d.datistemplate \
+                 ORDER BY d.datname"
+            }
+            // `update` is the version `ALTER EXTENSION ... UPDATE` goes to,
+            // if not the one installed. Foreign servers show their options,
+            // which are where they connect to; passwords are in the user
+            // mappings.
+            (Report::Extensions, _) => {
+                "SELECT kind, name, schema, version, \"update\", detail FROM ( \
+                     SELECT 1 AS rank, 'extension' AS kind, e.extname::text AS name, \
+                            n.nspname::text AS schema, e.extversion AS version, \
+                            nullif(a.default_version, e.extversion) AS update, \
+                            a.comment AS detail \
+                     FROM pg_extension e \
+                     JOIN pg_namespace n ON n.oid = e.extnamespace \
+                     LEFT JOIN pg_available_extensions a ON a.name = e.extname \
+                     UNION ALL \
+                     SELECT 2, 'server', s.srvname, NULL, s.srvversion, NULL, \
+                            concat_ws(', ', 'wrapper ' || w.fdwname, \
+                                array_to_string(s.srvoptions, ', ')) \
+                     FROM pg_foreign_server s \
+                     JOIN pg_foreign_data_wrapper w ON w.oid = s.srvfdw \
+                     UNION ALL \
+                     SELECT 3, 'foreign table', c.relname, n.nspname, NULL, NULL, \
+                            'on ' || s.srvname \
+                     FROM pg_foreign_table f \
+                     JOIN pg_class c ON c.oid = f.ftrelid \
+                     JOIN pg_namespace n ON n.oid = c.relnamespace \
+                     JOIN pg_foreign_server s ON s.oid = f.ftserver \
+                 ) objects \
+                 ORDER BY rank, schema NULLS FIRST, name"
+            }
+            // `pg_sequences` is Postgres 10 and up.
+            (Report::Sequences, _) if (1..100000).contains(&server.version) => return None,
+            // A sequence is behind when the value `nextval` hands out next
+            // is taken already. `last_value` is NULL before the first
+            // `nextval` and without the privilege to read it.
+            (Report::Sequences, _) => {
+                "SELECT s.schemaname AS schema, s.sequencename AS sequence, s.last_value, \
+                        s.increment_by AS increment, o.owned_by, o.max, \
+                        s.increment_by > 0 \
+                            AND o.max >= coalesce(s.last_value + s.increment_by, s.start_value) \
+                            AS behind \
+                 FROM pg_sequences s \
+                 LEFT JOIN LATERAL ( \
+                     SELECT d.refobjid::regclass::text || '.' || quote_ident(a.attname) \
+                                AS owned_by, \
+                            (xpath('/row/max/text()', query_to_xml(format( \
+                                'SELECT max(%I) AS max FROM %s', \
+                                a.attname, d.refobjid::regclass), false, true, '')) \
+                            )[1]::text::int8 AS max \
+                     FROM pg_depend d \
+                     JOIN pg_attribute a ON a.attrelid = d.refobjid AND a.attnum = d.refobjsubid \
+                     WHERE d.classid = 'pg_class'::regclass \
+                       AND d.refclassid = 'pg_class'::regclass \
+                       AND d.objid = format('%I.%I', s.schemaname, s.sequencename)::regclass \
+                       AND d.deptype IN ('a', 'i') \
+                       AND a.atttypid IN ('int2'::regtype, 'int4'::regtype, 'int8'::regtype) \
+                       AND has_table_privilege(d.refobjid, 'SELECT') \
+                 ) o ON true \
+                 ORDER BY behind DESC NULLS LAST, s.schemaname, s.sequencename"
+            }
+            // `pg_partition_tree` is Postgres 12 and up.
+            (Report::Partitions(_), _) if (1..120000).contains(&server.version) => return None,
+            // Partitions under their parents, indented by level; the rows
+            // and size of a partitioned table are those of the leaves under
+            // it, as it has none of its own.
+            (Report::Partitions(_), _) => {
+                "SELECT repeat('  ', t.level) || t.relid::text AS partition, \
+                        coalesce(pg_get_expr(c.relpartbound, c.oid), '') AS bound, \
+                        l.rows, pg_size_pretty(l.bytes) AS size \
+                 FROM pg_partition_tree($1::regclass) t \
+                 JOIN pg_class c ON c.oid = t.relid \
+                 CROSS JOIN LATERAL ( \
+                     SELECT sum(greatest(p.reltuples, 0))::int8 AS rows, \
+                            sum(pg_total_relation_size(p.oid))::int8 AS bytes \
+                     FROM pg_partition_tree(t.relid) leaf \
+                     JOIN pg_class p ON p.oid = leaf.relid \
+                     WHERE leaf.isleaf \
+                 ) l \
+                 ORDER BY ARRAY( \
+                     SELECT a.relid::text \
+                     FROM pg_partition_ancestors(t.relid) WITH ORDINALITY a(relid, n) \
+                     ORDER BY n DESC)"
+            }
+            // Postgres keeps no time of the last refresh; the last write to
+            // the data file is the nearest thing, a refresh showing there by
+            // the next checkpoint. Reading it takes `pg_stat_file`, which
+            // only superusers and those granted it may call. A concurrent
+            // refresh needs a unique index without a `WHERE` and the view
+            // populated already.
+            (Report::MatViews, _) => {
+                "SELECT m.schemaname AS schema, m.matviewname AS matview, \
+                        m.ispopulated AS populated, \
+                        m.ispopulated AND EXISTS ( \
+                            SELECT FROM pg_index i \
+                            WHERE i.indrelid = c.oid AND i.indisunique \
+                              AND i.indpred IS NULL) AS concurrently, \
+                        pg_size_pretty(pg_total_relation_size(c.oid)) AS size, \
+                        CASE WHEN has_function_privilege('pg_stat_file(text, boolean)', \
+                                                         'EXECUTE') \
+                             THEN date_trunc('second', (pg_stat_file( \
+                                 pg_relation_filepath(c.oid), true)).modification) \
+                        END AS written \
+                 FROM pg_matviews m \
+                 JOIN pg_class c ON c.oid = format('%I.%I', m.schemaname, m.matviewname)::regclass \
+                 ORDER BY m.schemaname, m.matviewname"
+            }
+            // Leaving out the ones sqlx prepares behind the scenes.
+            (Report::Prepared, _) => {
+                "SELECT name, parameter_types::text[] AS parameters, \
+                        date_trunc('second', prepare_time) AS prepared, statement \
+                 FROM pg_prepared_statements \
+                 WHERE from_sql \
+                 ORDER BY name"
+            }
+            // The `*_exec_time` columns are Postgres 13 and up.
+            (Report::Statements, _) => {
+                "SELECT s.calls, \
+                        round(s.total_exec_time::numeric, 1) AS total_ms, \
+                        round(s.mean_exec_time::numeric, 2) AS mean_ms, \
+                        round((100 * s.total_exec_time \
+                            / nullif(sum(s.total_exec_time) OVER (), 0))::numeric, 1) AS percent, \
+                        s.rows, r.rolname AS user, s.query \
+                 FROM pg_stat_statements s \
+                 LEFT JOIN pg_roles r ON r.oid = s.userid \
+                 WHERE s.dbid = (SELECT oid FROM pg_database WHERE datname = current_database()) \
+                 ORDER BY s.total_exec_time DESC \
+                 LIMIT 200"
+            }
+        };
+        Some(sql)
+    }
+
+    pub async fn run(&self, pool: &PgPool, server: Server) -> Result<ResultSet, sqlx::Error> {
+        let Some(sql) = self.sql(server) else {
+            return Err(sqlx::Error::Configuration(
+                format!(":{} is not available on {}", self.title(), server.name()).into(),
+            ));
+        };
+        let binds = match self {
+            Report::Sizes(schema) => vec![schema.clone()],
+            Report::Privileges(table) | Report::Partitions(table) => vec![Some(table.clone())],
+            Report::Grep(pattern) => vec![Some(pattern.clone())],
+            _ => Vec::new(),
+        };
+        results::fetch(pool, sql, &binds).await
+    }
+}
+
+/// The `VACUUM` that `:vacuum` runs on `table`.
+pub fn vacuum_statement(table: &str) -> String {
+    format!("VACUUM (ANALYZE) {table}")
+}
+
+/// The statement that `:refresh` runs on materialized view `name`.
+pub fn refresh_statement(name: &str, concurrently: bool) -> String {
+    match concurrently {
+        false => format!("REFRESH MATERIALIZED VIEW {name}"),
+        true => format!("REFRESH MATERIALIZED VIEW CONCURRENTLY {name}"),
+    }
+}
+
+/// Runs `sql`, a `VACUUM` like [`vacuum_statement`], calling `progress`
+/// with how far along it is every so often.
+pub async fn vacuum(
+    session: &Session,
+    sql: String,
+    progress: impl FnMut(Progress),
+) -> Result<(), sqlx::Error> {
+    watched(session, sql, progress).await
+}
+
+/// Runs `sql`, a refresh like [`refresh_statement`]. Refreshes have no
+/// progress view, so `progress` only hears of the locks they wait on.
+pub async fn refresh(
+    session: &Session,
+    sql: String,
+    progress: impl FnMut(Progress),
+) -> Result<(), sqlx::Error> {
+    watched(session, sql, progress).await
+}
+
+/// Runs `sql` on a connection of its own, so the session stays free
+/// meanwhile, under [`watch`].
+async fn watched(
+    session: &Session,
+    sql: String,
+    progress: impl FnMut(Progress),
+) -> Result<(), sqlx::Error> {
+    let mut conn = PgConnection::connect_with(&session.options).await?;
+    let pid: i32 = sqlx::query_scalar("SELECT pg_backend_pid()")
+        .fetch_one(&mut conn)
+        .await?;
+    let task = async move { (&mut conn).execute(sql.as_str()).await };
+    watch(&session.options, session.server, pid, task, progress)
+        .await
+        .map(|_| ())
+}
+
+/// How far along a long command is, from the `pg_stat_progress_*` view
+/// for it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Progress {
+    /// Like `CREATE INDEX` or `COPY FROM`.
+    pub command: String,
+    /// Empty for commands without phases, like `COPY`.
+    pub phase: String,
+    pub done: i64,
+    /// `None` when there is no telling, like copying from the client.
+    pub total: Option<i64>,
+    /// What `done` and `total` count, like `blocks`.
+    pub unit: String,
+}
+
+impl Progress {
+    /// Percent done, if there is a total to go by.
+    pub fn percent(&self) -> Option<i64> {
+        let total = self.total.filter(|total| *total > 0)?;
+        Some((self.done * 100 / total).clamp(0, 100))
+    }
+
+    /// The command and its phase, and how much is done if there is no
+    /// telling how much is left.
+    pub fn describe(&self) -> String {
+        let mut text = self.command.clone();
+        if !self.phase.is_empty() {
+            text += &format!(": {}", self.phase);
+        }
+        if self.percent().is_none() {
+            text += &format!(", {} {}", self.done, self.unit);
+        }
+        text
+    }
+}
+
+/// The progress views `server` has, with the version each came in, as
+/// `command, phase, done, total, unit` for the backend `$1`.
+const PROGRESS_VIEWS: [(u32, &str); 5] = [
+    (
+        120000,
+        "SELECT command, phase, \
+                CASE WHEN blocks_total > 0 THEN blocks_done ELSE tuples_done END, \
+                nullif(CASE WHEN blocks_total > 0 THEN blocks_total ELSE tuples_total END, 0), \
+                CASE WHEN blocks_total > 0 THEN 'blocks' ELSE 'tuples' END \
+         FROM pg_stat_progress_create_index WHERE pid = $1",
+    ),
+    (
+        140000,
+        "SELECT command || ' ' || type, '', \
+                CASE WHEN bytes_total > 0 THEN bytes_processed ELSE tuples_processed END, \
+                nullif(bytes_total, 0), \
+                CASE WHEN bytes_total > 0 THEN 'bytes' ELSE 'rows' END \
+         FROM pg_stat_progress_copy WHERE pid = $1",
+    ),
+    (
+        90600,
+        "SELECT 'VACUUM', phase, heap_blks_scanned, nullif(heap_blks_total, 0), 'blocks' \
+         FROM pg_stat_progress_vacuum WHERE pid = $1",
+    ),
+    (
+        120000,
+        "SELECT command, phase, heap_blks_scanned, nullif(heap_blks_total, 0), 'blocks' \
+         FROM pg_stat_progress_cluster WHERE pid = $1",
+    ),
+    (
+        130000,
+        "SELECT 'ANALYZE', phase, sample_blks_scanned, nullif(sample_blks_total, 0), 'blocks' \
+         FROM pg_stat_progress_analyze WHERE pid = $1",
+    ),
+];
+
+/// For a backend no progress view reports on: whether it is waiting on
+/// a lock, who holds it and for how long it has been running.
+const LOCK_WAIT: &str = "SELECT 'waiting for a lock', \
+                                'held by ' || array_to_string(pg_blocking_pids(pid), ', '), \
+                                extract(epoch FROM now() - query_start)::int8, \
+                                NULL::int8, 'seconds in' \
+                         FROM pg_stat_activity \
+                         WHERE pid = $1 AND wait_event_type = 'Lock'";
+
+/// What the progress views say backend `pid` is doing, if it is doing
+/// anything they report on, or the lock it waits on.
+pub async fn progress(
+    conn: &mut PgConnection,
+    server: Server,
+    pid: i32,
+) -> Result<Option<Progress>, sqlx::Error> {
+    if matches!(server.flavor, Flavor::Cockroach | Flavor::Redshift) {
+        return Ok(None);
+    }
+    let sql = PROGRESS_VIEWS
+        .iter()
+        .filter(|(since, _)| server.version >= *since)
+        .map(|(_, sql)| *sql)
+        .collect::<Vec<_>>()
+        .join(" UNION ALL ");
+    if sql.is_empty() {
+        return Ok(None);
+    }
+    let mut row: Option<(String, String, i64, Option<i64>, String)> = sqlx::query_as(&sql)
+        .bind(pid)
+        .fetch_optional(&mut *conn)
+        .await?;
+    if row.is_none() {
+        row = sqlx::query_as(LOCK_WAIT)
+            .bind(pid)
+            .fetch_optional(conn)
+            .await?;
+    }
+    Ok(row.map(|(command, phase, done, total, unit)| Progress {
+        command,
+        phase,
+        done,
+        total,
+        unit,
+    }))
+}
+
+/// Runs `task`, which keeps backend `pid` busy, calling `report` with what
+/// the progress views say about it every so often. They are read over a
+/// connection of their own, the one `task` uses being busy.
+pub async fn watch<T>(
+    options: &PgConnectOptions,
+    server: Server,
+    pid: i32,
+    task: impl Future<Output = T>,
+    mut report: impl FnMut(Progress),
+) -> T {
+    tokio::pin!(task);
+    let mut poll = tokio::time::interval(Duration::from_secs(1));
+    // Most commands are done before the first look.
+    poll.tick().await;
+    let mut conn: Option<PgConnection> = None;
+    let mut connected = false;
+    loop {
+        tokio::select! {
+            done = &mut task => {
+                if let Some(conn) = conn {
+                    let _ = conn.close().await;
+                }
+                return done;
+            }
+            _ = poll.tick() => {
+                if !connected {
+                    connected = true;
+                    match PgConnection::connect_with(options).await {
+                        Ok(new) => conn = Some(new),
+                        Err(err) => tracing::debug!(error = %err, "can't watch progress"),
+                    }
+                }
+                let Some(conn) = &mut conn else {
+                    continue;
+                };
+                match progress(conn, server, pid).await {
+                    Ok(Some(progress)) => report(progress),
+                    Ok(None) => {}
+                    Err(err) => tracing::debug!(error = %err, "failed to read progress"),
+                }
+            }
+        }
+    }
+}
+
+/// Whether `err` says the view a report reads from doesn't exist, as when
+/// an extension isn't installed.
+pub fn is_missing_relation(err: &sqlx::Error) -> bool {
+    err.as_database_error()
+        .and_then(|err| err.code())
+        .as_deref()
+        == Some("42P01")
+}
+
+/// The query that cancels the query running in backend `pid`, or ends its
+/// session altogether if `terminate`.
+pub fn signal_statement(pid: i32, terminate: bool) -> String {
+    match terminate {
+        false => format!("SELECT pg_cancel_backend({pid})"),
+        true => format!("SELECT pg_terminate_backend({pid})"),
+    }
+}
+
+/// The query that sets sequence `name` to `value`, `nextval` handing out
+/// the value after it.
+pub fn setval_statement(name: &str, value: i64) -> String {
+    format!(
+        "SELECT setval({}, {value})",
+        statements::quote_literal(name)
+    )
+}
+
+/// Runs `sql`, a [`setval_statement`] or the user's edit of one. Returns
+/// the value set.
+pub async fn setval(pool: &PgPool, sql: &str) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar(sql).fetch_one(pool).await
+}
+
+/// Runs `sql`, a [`signal_statement`] or the user's edit of one. Returns
+/// whether the signal was sent.
+pub async fn signal_backend(pool: &PgPool, sql: &str) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar(sql).fetch_one(pool).await
+}
@@ -0,0 +1,138 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Anything with an ODBC driver, for databases without a driver of their
+//! own here. Every value is fetched as text, which the driver renders.
+
+use odbc_api::buffers::TextRowSet;
+use odbc_api::{Connection, ConnectionOptions, Cursor, DataType, Error};
+
+use crate::results::{Column, ResultSet};
+
+/// Rows fetched per round trip.
+const BATCH: usize = 1000;
+
+/// Longest text kept of a value whose size the driver doesn't know, like a
+/// `TEXT` column.
+const MAX_TEXT: usize = 64 * 1024;
+
+#[derive(Debug)]
+pub struct Odbc {
+    connection: Connection<'static>,
+    /// The DSN or driver connected through.
+    pub name: String,
+}
+
+impl Odbc {
+    /// Connects with an ODBC connection string, as in
+    /// `odbc://DSN=warehouse;UID=me;PWD=secret` or
+    /// `odbc://Driver={SQLite3};Database=app.db`.
+    pub fn connect(url: &str) -> Result<Self, String> {
+        let connection_string = url.strip_prefix("odbc://").unwrap_or(url);
+        let connection = odbc_api::environment()
+            .and_then(|environment| {
+                environment
+                    .connect_with_connection_string(connection_string, ConnectionOptions::default())
+            })
+            .map_err(|err| err.to_string())?;
+        // The DSN or driver says where without giving the password away.
+        let name = connection_string
+            .split(';')
+            .find_map(|pair| {
+                let (key, value) = pair.split_once('=')?;
+                ["dsn", "driver"]
+                    .contains(&key.trim().to_lowercase().as_str())
+                    .then(|| value.trim().to_string())
+            })
+            .unwrap_or_default();
+        Ok(Self { connection, name })
+    }
+
+    /// Runs `sql` statement by statement, returning the rows of the last
+    /// one that returned any and the number of rows returned or affected.
+    pub fn query(&mut self, sql: &str) -> Result<(Option<ResultSet>, u64), Error> {
+        let mut statement = self.connection.preallocate()?;
+        let mut results = None;
+        let mut rows = 0;
+        for sql in crate::statements::split(sql) {
+            let sql = sql.trim().trim_end_matches(';');
+            let fetched = match statement.execute(sql, ())? {
+                Some(cursor) => Some(fetch(cursor)?),
+                None => None,
+            };
+            match fetched {
+                Some(fetched) => {
+                    rows = fetched.rows.len() as u64;
+                    results = Some(fetched);
+                }
+                None => rows = statement.row_count()?.unwrap_or_default() as u64,
+            }
+        }
+        Ok((results, rows))
+    }
+
+    /// Every table and view, as the driver's catalog function lists them.
+    pub fn tables(&mut self) -> Result<ResultSet, Error> {
+        let cursor = self.connection.tables("", "", "", "TABLE,VIEW")?;
+        let mut tables = fetch(cursor)?;
+        // Catalog, schema, name and type, then remarks nobody fills in.
+        tables.columns.truncate(4);
+        let kept: Vec<usize> = (0..tables.columns.len()).collect();
+        tables.rows = tables.rows.project(&kept);
+        Ok(tables)
+    }
+}
+
+fn fetch(mut cursor: impl Cursor) -> Result<ResultSet, Error> {
+    let count = cursor.num_result_cols()? as u16;
+    let mut columns = Vec::with_capacity(count as usize);
+    for index in 1..=count {
+        let ty = type_name(cursor.col_data_type(index)?);
+        columns.push(Column::new(cursor.col_name(index)?, ty));
+    }
+    let mut results = ResultSet::new(columns);
+    let buffer = TextRowSet::for_cursor(BATCH, &mut cursor, Some(MAX_TEXT))?;
+    let mut cursor = cursor.bind_buffer(buffer)?;
+    while let Some(batch) = cursor.fetch()? {
+        for row in 0..batch.num_rows() {
+            results.rows.push((0..count as usize).map(|column| {
+                batch
+                    .at(column, row)
+                    .map(|bytes| String::from_utf8_lossy(bytes))
+            }));
+        }
+    }
+    Ok(results)
+}
+
+/// The ODBC type name, or its Postgres counterpart for the numeric types
+/// so the grid aligns and sorts them as numbers.
+fn type_name(ty: DataType) -> String {
+    match ty {
+        DataType::TinyInt | DataType::SmallInt => "int2".into(),
+        DataType::Integer => "int4".into(),
+        DataType::BigInt => "int8".into(),
+        DataType::Real => "float4".into(),
+        DataType::Float { .. } | DataType::Double => "float8".into(),
+        DataType::Numeric { .. } | DataType::Decimal { .. } => "numeric".into(),
+        // `Varchar { length: .. }` and the like, without the details.
+        ty => {
+            let name = format!("{ty:?}");
+            name.split([' ', '{', '('])
+                .next()
+                .unwrap_or_default()
+                .to_lowercase()
+        }
+    }
+}
@@ -0,0 +1,429 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A local copy of the catalog: the relations of the database and their
+//! columns, kept per connection in `$XDG_CACHE_HOME/dbvi/schema` so it is
+//! there as soon as dbvi starts.
+//!
+//! Refreshing only fetches the columns of relations whose catalog rows
+//! changed since, going by the transaction ids (`xmin`) that last wrote
+//! them.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgConnectOptions;
+use sqlx::postgres::types::Oid;
+use sqlx::{Connection, PgConnection};
+
+use super::catalog::{COLUMN_FIELDS, Column};
+use super::server::{Flavor, Server};
+use crate::{config, statements};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Relation {
+    pub schema: String,
+    pub name: String,
+    /// `table`, `view`, `materialized view`, `foreign table` or
+    /// `partitioned table`.
+    pub kind: String,
+    /// What the catalog rows of the relation were last written by, empty
+    /// when the server can't tell.
+    signature: String,
+    pub columns: Vec<Column>,
+}
+
+impl Relation {
+    /// The name to write in a statement, qualified unless it is in
+    /// `public`.
+    pub fn qualified(&self) -> String {
+        match self.schema.as_str() {
+            "public" => ident(&self.name),
+            schema => format!("{}.{}", ident(schema), ident(&self.name)),
+        }
+    }
+
+    /// Column `name`, quoted or not.
+    pub fn column(&self, name: &str) -> Option<&Column> {
+        let name = fold(name);
+        self.columns
+            .iter()
+            .find(|column| fold(&column.name) == name)
+    }
+}
+
+/// `name`, quoted only if it has to be, like the server's `quote_ident()`
+/// short of knowing the keywords.
+pub fn ident(name: &str) -> String {
+    let plain = name.starts_with(|c: char| c.is_ascii_lowercase() || c == '_')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '$');
+    if plain {
+        name.to_string()
+    } else {
+        statements::quote_ident(name)
+    }
+}
+
+/// What a refresh found.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Changes {
+    pub added: usize,
+    pub changed: usize,
+    pub dropped: usize,
+}
+
+impl Changes {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl fmt::Display for Changes {
+    /// `2 new, 1 dropped`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts: Vec<String> = [
+            (self.added, "new"),
+            (self.changed, "changed"),
+            (self.dropped, "dropped"),
+        ]
+        .into_iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, what)| format!("{count} {what}"))
+        .collect();
+        if parts.is_empty() {
+            f.write_str("no changes")
+        } else {
+            f.write_str(&parts.join(", "))
+        }
+    }
+}
+
+/// The relations of a database, by `schema.name`.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Schema {
+    pub relations: BTreeMap<String, Relation>,
+}
+
+/// Where the schema of the database behind `key` (a
+/// [`profile_key`](super::profile_key)) is cached.
+pub fn cache_path(key: &str) -> Option<PathBuf> {
+    let file: String = key
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '.' | '@' => c,
+            _ => '_',
+        })
+        .collect();
+    config::cache_dir().map(|dir| dir.join("schema").join(format!("{file}.json")))
+}
+
+/// `name` as the catalog has it: unquoted names fold to lower case.
+pub fn fold(name: &str) -> String {
+    match name
+        .strip_prefix('"')
+        .and_then(|name| name.strip_suffix('"'))
+    {
+        Some(quoted) => quoted.replace("\"\"", "\""),
+        None => name.to_lowercase(),
+    }
+}
+
+/// Splits `schema.name` into its folded parts.
+fn split(name: &str) -> (Option<String>, String) {
+    let mut parts = Vec::new();
+    let (mut start, mut quoted) = (0, false);
+    for (i, c) in name.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '.' if !quoted => {
+                parts.push(&name[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&name[start..]);
+    match parts.as_slice() {
+        [.., schema, name] => (Some(fold(schema)), fold(name)),
+        _ => (None, fold(name)),
+    }
+}
+
+impl Schema {
+    /// The cached schema at `path`, empty if there is none yet.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(json) => serde_json::from_str(&json).map_err(io::Error::other),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_string(self).map_err(io::Error::other)?;
+        fs::write(path, json)
+    }
+
+    /// Relation `name`, qualified or not. Unqualified names prefer
+    /// `public`, like the default `search_path`.
+    pub fn find(&self, name: &str) -> Option<&Relation> {
+        self.resolve(name, &[])
+    }
+
+    /// Relation `name`, qualified or not. Unqualified names are looked up
+    /// in the schemas of `search_path` in turn, `public` when it is empty,
+    /// then in any schema.
+    pub fn resolve(&self, name: &str, search_path: &[String]) -> Option<&Relation> {
+        match split(name) {
+            (Some(schema), name) => self.relations.get(&format!("{schema}.{name}")),
+            (None, name) => {
+                let public = ["public".to_string()];
+                let path = if search_path.is_empty() {
+                    &public[..]
+                } else {
+                    search_path
+                };
+                path.iter()
+                    .find_map(|schema| self.relations.get(&format!("{schema}.{name}")))
+                    .or_else(|| self.relations.values().find(|rel| rel.name == name))
+            }
+        }
+    }
+
+    /// Forgets the relations `names`, until the next refresh fetches them
+    /// again. Returns how many were cached.
+    pub fn invalidate(&mut self, names: &[String]) -> usize {
+        let before = self.relations.len();
+        for name in names {
+            let key = self
+                .find(name)
+                .map(|rel| format!("{}.{}", rel.schema, rel.name));
+            if let Some(key) = key {
+                self.relations.remove(&key);
+            }
+        }
+        before - self.relations.len()
+    }
+
+    /// How the database differs from the cache, going by its
+    /// [`signatures`]. Relations the server can't sign count as unchanged.
+    pub fn changes(&self, signatures: &[(String, String)]) -> Changes {
+        let mut changes = Changes::default();
+        let mut cached = 0;
+        for (key, signature) in signatures {
+            match self.relations.get(key) {
+                Some(relation) => {
+                    cached += 1;
+                    if !signature.is_empty() && relation.signature != *signature {
+                        changes.changed += 1;
+                    }
+                }
+                None => changes.added += 1,
+            }
+        }
+        changes.dropped = self.relations.len() - cached;
+        changes
+    }
+
+    /// Brings the cache up to date over a connection of its own, so as not
+    /// to wait on (or show up in) whatever the session is doing. Columns are
+    /// only fetched for relations that are new or changed.
+    pub async fn refresh(
+        &self,
+        options: &PgConnectOptions,
+        server: Server,
+    ) -> Result<(Schema, Changes), sqlx::Error> {
+        let mut conn = PgConnection::connect_with(options).await?;
+        let listed = list(&mut conn, server).await?;
+
+        let mut changes = Changes::default();
+        let mut relations = BTreeMap::new();
+        let mut stale = Vec::new();
+        for (oid, schema, name, kind, signature) in listed {
+            let key = format!("{schema}.{name}");
+            match self.relations.get(&key) {
+                Some(cached) if !signature.is_empty() && cached.signature == signature => {
+                    relations.insert(key, cached.clone());
+                    continue;
+                }
+                Some(_) => changes.changed += 1,
+                None => changes.added += 1,
+            }
+            stale.push((oid, key.clone()));
+            relations.insert(
+                key,
+                Relation {
+                    schema,
+                    name,
+                    kind,
+                    signature,
+                    columns: Vec::new(),
+                },
+            );
+        }
+        changes.dropped = self
+            .relations
+            .keys()
+            .filter(|key| !relations.contains_key(*key))
+            .count();
+
+        if !stale.is_empty() {
+            #[derive(sqlx::FromRow)]
+            struct Row {
+                relid: Oid,
+                #[sqlx(flatten)]
+                column: Column,
+            }
+            let oids: Vec<Oid> = stale.iter().map(|(oid, _)| *oid).collect();
+            let rows: Vec<Row> = sqlx::query_as(&format!(
+                "SELECT a.attrelid AS relid, {COLUMN_FIELDS} FROM pg_attribute a \
+                  WHERE a.attrelid = ANY ($1) AND a.attnum > 0 AND NOT a.attisdropped \
+                  ORDER BY a.attrelid, a.attnum"
+            ))
+            .bind(&oids)
+            .fetch_all(&mut conn)
+            .await?;
+            let keys: HashMap<Oid, String> = stale.into_iter().collect();
+            for Row { relid, column } in rows {
+                if let Some(relation) = keys.get(&relid).and_then(|key| relations.get_mut(key)) {
+                    relation.columns.push(column);
+                }
+            }
+        }
+        conn.close().await?;
+        Ok((Schema { relations }, changes))
+    }
+}
+
+/// The signature of each relation of the database, by `schema.name`: cheap
+/// enough to check every so often whether the cache is still current.
+pub async fn signatures(
+    options: &PgConnectOptions,
+    server: Server,
+) -> Result<Vec<(String, String)>, sqlx::Error> {
+    let mut conn = PgConnection::connect_with(options).await?;
+    let listed = list(&mut conn, server).await?;
+    conn.close().await?;
+    Ok(listed
+        .into_iter()
+        .map(|(_, schema, name, _, signature)| (format!("{schema}.{name}"), signature))
+        .collect())
+}
+
+/// The relations of the database: oid, schema, name, kind and signature.
+async fn list(
+    conn: &mut PgConnection,
+    server: Server,
+) -> Result<Vec<(Oid, String, String, String, String)>, sqlx::Error> {
+    // CockroachDB has no xmin, so nothing can be told apart there.
+    let signature = match server.flavor {
+        Flavor::Cockroach => "''",
+        _ => {
+            "concat_ws('/', c.xmin, \
+                (SELECT max(a.xmin::text::bigint) FROM pg_attribute a \
+                  WHERE a.attrelid = c.oid), \
+                (SELECT max(k.xmin::text::bigint) FROM pg_constraint k \
+                  WHERE k.conrelid = c.oid))"
+        }
+    };
+    sqlx::query_as(&format!(
+        "SELECT c.oid, n.nspname::text, c.relname::text, \
+                CASE c.relkind WHEN 'r' THEN 'table' WHEN 'v' THEN 'view' \
+                     WHEN 'm' THEN 'materialized view' WHEN 'f' THEN 'foreign table' \
+                     ELSE 'partitioned table' END, \
+                {signature} \
+           FROM pg_class c JOIN pg_namespace n ON n.oid = c.relnamespace \
+          WHERE c.relkind IN ('r', 'p', 'v', 'm', 'f') \
+            AND n.nspname NOT IN ('pg_catalog', 'information_schema') \
+            AND n.nspname NOT LIKE 'pg\\_toast%' AND n.nspname NOT LIKE 'pg\\_temp%' \
+            AND n.nspname <> 'crdb_internal'"
+    ))
+    .fetch_all(conn)
+    .await
+}
+
+/// The relations the DDL in `sql` may have changed, or `None` if there is no
+/// DDL in it. Names are as written; the list is empty when the change can't
+/// be pinned on a relation, like `DROP SCHEMA`.
+pub fn ddl_targets(sql: &str) -> Option<Vec<String>> {
+    const MODIFIERS: [&str; 11] = [
+        "or",
+        "replace",
+        "temp",
+        "temporary",
+        "unlogged",
+        "global",
+        "local",
+        "materialized",
+        "recursive",
+        "foreign",
+        "concurrently",
+    ];
+    const RELATIONS: [&str; 2] = ["table", "view"];
+    let mut targets = None;
+    for statement in statements::split(sql) {
+        let words: Vec<&str> = statement
+            .lines()
+            .filter(|line| !line.trim_start().starts_with("--"))
+            .flat_map(|line| line.split(|c: char| c.is_whitespace() || c == '('))
+            .map(|word| word.trim_end_matches(';'))
+            .filter(|word| !word.is_empty())
+            .collect();
+        let Some(verb) = words.first().map(|word| word.to_lowercase()) else {
+            continue;
+        };
+        if !matches!(verb.as_str(), "create" | "alter" | "drop") {
+            continue;
+        }
+        let names: &mut Vec<String> = targets.get_or_insert_default();
+        let mut rest = words[1..]
+            .iter()
+            .skip_while(|word| MODIFIERS.contains(&word.to_lowercase().as_str()));
+        if !rest
+            .next()
+            .is_some_and(|object| RELATIONS.contains(&object.to_lowercase().as_str()))
+        {
+            continue;
+        }
+        let mut rest = rest.skip_while(|word| {
+            ["if", "not", "exists", "only"].contains(&word.to_lowercase().as_str())
+        });
+        match verb.as_str() {
+            // DROP TABLE a, b CASCADE
+            "drop" => {
+                for word in rest {
+                    names.extend(
+                        word.split(',')
+                            .filter(|name| !name.is_empty())
+                            .map(str::to_string),
+                    );
+                    if !word.ends_with(',') {
+                        break;
+                    }
+                }
+            }
+            _ => names.extend(rest.next().map(|name| name.to_string())),
+        }
+    }
+    targets
+}
@@ -0,0 +1,113 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! What is on the other end of the connection. CockroachDB and YugabyteDB
+//! speak the Postgres protocol with catalogs of their own, and TimescaleDB
+//! keeps hypertable data in chunks the Postgres catalogs don't add up; the
+//! reports and catalog queries ask for what the server actually has.
+
+use sqlx::PgPool;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Flavor {
+    #[default]
+    Postgres,
+    Cockroach,
+    Yugabyte,
+    /// Postgres with the `timescaledb` extension.
+    Timescale,
+    Redshift,
+    Greenplum,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Server {
+    pub flavor: Flavor,
+    /// `server_version_num` of the Postgres it is (or claims to be), like
+    /// 160004.
+    pub version: u32,
+}
+
+impl Server {
+    /// Works out what the server is from `version()` and its extensions.
+    /// Anything unexpected counts as plain Postgres.
+    pub async fn detect(pool: &PgPool) -> Self {
+        let Ok(version) = sqlx::query_scalar::<_, String>("SELECT version()")
+            .fetch_one(pool)
+            .await
+        else {
+            return Self::default();
+        };
+        // Neither of these is there on every derivative (Redshift has
+        // neither), so failing just means no.
+        let number =
+            sqlx::query_scalar::<_, String>("SELECT current_setting('server_version_num')")
+                .fetch_one(pool)
+                .await
+                .ok()
+                .and_then(|number| number.parse().ok());
+        let timescale = || async {
+            sqlx::query_scalar::<_, bool>(
+                "SELECT EXISTS (SELECT 1 FROM pg_extension WHERE extname = 'timescaledb')",
+            )
+            .fetch_one(pool)
+            .await
+            .unwrap_or(false)
+        };
+        let flavor = if version.contains("CockroachDB") {
+            Flavor::Cockroach
+        } else if version.contains("-YB-") {
+            Flavor::Yugabyte
+        } else if version.contains("Redshift") {
+            Flavor::Redshift
+        } else if version.contains("Greenplum") {
+            Flavor::Greenplum
+        } else if timescale().await {
+            Flavor::Timescale
+        } else {
+            Flavor::Postgres
+        };
+        Self {
+            flavor,
+            version: number.unwrap_or_default(),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self.flavor {
+            Flavor::Postgres => "Postgres",
+            Flavor::Cockroach => "CockroachDB",
+            Flavor::Yugabyte => "YugabyteDB",
+            Flavor::Timescale => "TimescaleDB",
+            Flavor::Redshift => "Redshift",
+            Flavor::Greenplum => "Greenplum",
+        }
+    }
+
+    /// The release `version` stands for, like `16.4` or `9.6.24`, `None`
+    /// if the server didn't say.
+    pub fn release(&self) -> Option<String> {
+        match self.version {
+            0 => None,
+            // Before 10 the first two numbers made the major version.
+            version if version < 100_000 => Some(format!(
+                "{}.{}.{}",
+                version / 10_000,
+                version / 100 % 100,
+                version % 100
+            )),
+            version => Some(format!("{}.{}", version / 10_000, version % 10_000)),
+        }
+    }
+}
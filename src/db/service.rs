@@ -0,0 +1,112 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! libpq keyword/value connection strings and `pg_service.conf` lookup.
+
+use std::{env, fs, path::PathBuf};
+
+/// A connection parameter such as `("dbname", "shop")`.
+pub type Param = (String, String);
+
+/// Parses a keyword/value connection string like
+/// `host=db port=5432 dbname='my db'`. Values may be single quoted, with `\`
+/// escaping a quote or backslash, just like libpq.
+pub fn parse_conninfo(input: &str) -> Result<Vec<Param>, String> {
+    let mut params = Vec::new();
+    let mut chars = input.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        if chars.peek().is_none() {
+            return Ok(params);
+        }
+
+        let key: String =
+            std::iter::from_fn(|| chars.next_if(|c| *c != '=' && !c.is_whitespace())).collect();
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        if chars.next() != Some('=') {
+            return Err(format!(
+                "missing \"=\" after \"{key}\" in connection string"
+            ));
+        }
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+        let mut value = String::new();
+        if chars.next_if_eq(&'\'').is_some() {
+            loop {
+                match chars.next() {
+                    Some('\'') => break,
+                    Some('\\') => value.extend(chars.next()),
+                    Some(c) => value.push(c),
+                    None => return Err(format!("unterminated quoted value for \"{key}\"")),
+                }
+            }
+        } else {
+            while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                if c == '\\' {
+                    value.extend(chars.next());
+                } else {
+                    value.push(c);
+                }
+            }
+        }
+        params.push((key, value));
+    }
+}
+
+/// The service files to search, in the order psql does: the user's file
+/// (`PGSERVICEFILE` or `~/.pg_service.conf`) and then the system-wide one in
+/// `PGSYSCONFDIR`.
+fn service_files() -> Vec<PathBuf> {
+    let user = env::var_os("PGSERVICEFILE")
+        .map(PathBuf::from)
+        .or_else(|| env::home_dir().map(|home| home.join(".pg_service.conf")));
+    let system = env::var_os("PGSYSCONFDIR").map(|dir| PathBuf::from(dir).join("pg_service.conf"));
+    user.into_iter().chain(system).collect()
+}
+
+/// Finds the `[name]` section in an INI style service file.
+fn find_service(contents: &str, name: &str) -> Option<Vec<Param>> {
+    let mut params = None;
+    for line in contents.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(section) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            if params.is_some() {
+                break;
+            }
+            if section.trim() == name {
+                params = Some(Vec::new());
+            }
+            continue;
+        }
+        if let (Some(params), Some((key, value))) = (params.as_mut(), line.split_once('=')) {
+            params.push((key.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    params
+}
+
+/// Looks up the parameters of service `name`; the first file defining it wins.
+pub fn lookup(name: &str) -> Result<Vec<Param>, String> {
+    for path in service_files() {
+        let Ok(contents) = fs::read_to_string(&path) else {
+            continue;
+        };
+        if let Some(params) = find_service(&contents, name) {
+            return Ok(params);
+        }
+    }
+    Err(format!("definition of service \"{name}\" not found"))
+}
@@ -0,0 +1,421 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The interactive session. It runs on a single pooled connection so that
+//! `SET`s and transactions stick between queries, and the pool transparently
+//! opens a new connection after the old one dies, replaying the session
+//! settings the user made so far.
+
+use std::{
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicI32, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use sqlx::{
+    Connection, Executor, PgConnection, PgPool,
+    postgres::{PgConnectOptions, PgPoolOptions},
+};
+
+use super::server::{Flavor, Server};
+
+const MIN_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Statements that configure the session and must be replayed on every new
+/// connection: `SET`, `LISTEN`, `PREPARE`, and what undoes them.
+#[derive(Debug, Default, Clone)]
+pub struct SessionSettings {
+    statements: Vec<String>,
+}
+
+impl SessionSettings {
+    /// Records `sql` if it changes session state. Later statements replace
+    /// earlier ones for the same setting or channel.
+    pub fn record(&mut self, sql: &str) {
+        let sql = sql.trim().trim_end_matches(';').trim();
+        let words: Vec<String> = sql
+            .split_whitespace()
+            .take(3)
+            .map(str::to_lowercase)
+            .collect();
+        let words: Vec<&str> = words.iter().map(String::as_str).collect();
+        match words.as_slice() {
+            ["set", "local", ..] | ["set", "transaction", ..] => {}
+            ["set", "session", name, ..] | ["set", name, ..] => {
+                let name = setting_name(name);
+                self.remove("set", &name);
+                self.statements.push(sql.to_string());
+            }
+            ["reset", "all"] => self.statements.retain(|s| !starts_with_word(s, "set")),
+            ["reset", name] => self.remove("set", &setting_name(name)),
+            ["listen", channel] => {
+                self.remove("listen", channel);
+                self.statements.push(sql.to_string());
+            }
+            ["unlisten", "*"] => self.statements.retain(|s| !starts_with_word(s, "listen")),
+            ["unlisten", channel] => self.remove("listen", channel),
+            ["prepare", name, ..] => {
+                let name = statement_name(name);
+                self.remove("prepare", &name);
+                self.statements.push(sql.to_string());
+            }
+            ["deallocate", "all"] | ["deallocate", "prepare", "all"] => {
+                self.statements.retain(|s| !starts_with_word(s, "prepare"))
+            }
+            ["deallocate", "prepare", name] | ["deallocate", name] => {
+                self.remove("prepare", &statement_name(name))
+            }
+            _ => {}
+        }
+    }
+
+    fn remove(&mut self, verb: &str, name: &str) {
+        self.statements.retain(|statement| {
+            let mut words = statement
+                .split_whitespace()
+                .map(str::to_lowercase)
+                .filter(|word| word != "session");
+            !(words.next().as_deref() == Some(verb)
+                && words.next().is_some_and(|word| match verb {
+                    "prepare" => statement_name(&word) == name,
+                    _ => setting_name(&word) == name,
+                }))
+        });
+    }
+
+    pub fn statements(&self) -> &[String] {
+        &self.statements
+    }
+}
+
+/// `search_path=public` and `search_path` both name `search_path`.
+fn setting_name(word: &str) -> String {
+    word.split(['=', ' ']).next().unwrap_or(word).to_string()
+}
+
+/// `name(integer)` and `name` both name `name`.
+fn statement_name(word: &str) -> String {
+    word.split('(').next().unwrap_or(word).to_string()
+}
+
+fn starts_with_word(statement: &str, word: &str) -> bool {
+    statement
+        .split_whitespace()
+        .next()
+        .is_some_and(|first| first.eq_ignore_ascii_case(word))
+}
+
+#[derive(Debug, Clone)]
+pub struct Session {
+    pub pool: PgPool,
+    pub settings: Arc<Mutex<SessionSettings>>,
+    pub options: PgConnectOptions,
+    /// What the server turned out to be on connect.
+    pub server: Server,
+    /// PID of the backend serving the session, for cancelling its queries.
+    backend_pid: Arc<AtomicI32>,
+    /// Run on every new connection before the settings are replayed.
+    init: Arc<[String]>,
+}
+
+impl Session {
+    /// Connects with `options`. `init` is run first on every new connection,
+    /// before the recorded session settings are replayed.
+    pub async fn connect(options: PgConnectOptions, init: &[String]) -> Result<Self, sqlx::Error> {
+        Self::open(options, init.into(), SessionSettings::default()).await
+    }
+
+    /// A session on `database` of the same server, as the same user, with
+    /// the same init statements and the settings made so far, like psql's
+    /// `\c`. This one is left as it is.
+    pub async fn switch(&self, database: &str) -> Result<Self, sqlx::Error> {
+        let settings = self.settings.lock().expect("settings lock").clone();
+        let options = self.options.clone().database(database);
+        Self::open(options, Arc::clone(&self.init), settings).await
+    }
+
+    /// A new session in place of this one, say once its pool is closed,
+    /// with the same options, init statements and settings.
+    pub async fn reopen(&self) -> Result<Self, sqlx::Error> {
+        let settings = self.settings.lock().expect("settings lock").clone();
+        Self::open(self.options.clone(), Arc::clone(&self.init), settings).await
+    }
+
+    async fn open(
+        options: PgConnectOptions,
+        init: Arc<[String]>,
+        settings: SessionSettings,
+    ) -> Result<Self, sqlx::Error> {
+        let settings = Arc::new(Mutex::new(settings));
+        let replay = Arc::clone(&settings);
+        let backend_pid = Arc::new(AtomicI32::new(0));
+        let pid = Arc::clone(&backend_pid);
+        let replay_init = Arc::clone(&init);
+        let pool = PgPoolOptions::new()
+            .max_connections(1)
+            // Fail fast while the server is down instead of freezing the UI
+            // for the default 30 seconds.
+            .acquire_timeout(Duration::from_secs(5))
+            .after_connect(move |conn, _| {
+                let statements = replay.lock().expect("settings lock").statements().to_vec();
+                let pid = Arc::clone(&pid);
+                let init = Arc::clone(&replay_init);
+                Box::pin(async move {
+                    let (backend_pid,): (i32,) = sqlx::query_as("SELECT pg_backend_pid()")
+                        .fetch_one(&mut *conn)
+                        .await?;
+                    pid.store(backend_pid, Ordering::Relaxed);
+                    for statement in init.iter().chain(&statements) {
+                        conn.execute(sqlx::raw_sql(statement)).await?;
+                    }
+                    Ok(())
+                })
+            })
+            .connect_with(options.clone())
+            .await?;
+        let server = Server::detect(&pool).await;
+        Ok(Self {
+            pool,
+            settings,
+            options,
+            server,
+            backend_pid,
+            init,
+        })
+    }
+
+    /// A session that doesn't connect until it is first used, taking the
+    /// server to be plain Postgres. For embedding and
+    /// tests, where there may be no server at all.
+    pub fn lazy(options: PgConnectOptions) -> Self {
+        let pool = PgPoolOptions::new()
+            .max_connections(1)
+            .acquire_timeout(Duration::from_secs(5))
+            // Reaping idle connections takes a task, and so an async runtime
+            // just to create the session.
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect_lazy_with(options.clone());
+        Self {
+            pool,
+            settings: Arc::default(),
+            options,
+            server: Server::default(),
+            backend_pid: Arc::default(),
+            init: Arc::new([]),
+        }
+    }
+
+    /// PID of the backend serving the session, 0 before it first connects.
+    pub fn pid(&self) -> i32 {
+        self.backend_pid.load(Ordering::Relaxed)
+    }
+
+    /// Asks the server to cancel whatever the session is running. This goes
+    /// through a separate connection since the session's own is busy.
+    pub async fn cancel(&self) -> Result<bool, sqlx::Error> {
+        self.signal("SELECT pg_cancel_backend($1)").await
+    }
+
+    /// Asks the server to end the session's backend, which stops what it
+    /// runs and rolls back its transaction.
+    pub async fn terminate(&self) -> Result<bool, sqlx::Error> {
+        self.signal("SELECT pg_terminate_backend($1)").await
+    }
+
+    async fn signal(&self, query: &'static str) -> Result<bool, sqlx::Error> {
+        let mut conn = PgConnection::connect_with(&self.options).await?;
+        let (signalled,): (bool,) = sqlx::query_as(query)
+            .bind(self.backend_pid.load(Ordering::Relaxed))
+            .fetch_one(&mut conn)
+            .await?;
+        conn.close().await?;
+        Ok(signalled)
+    }
+
+    /// Round trip time of a trivial query, or `None` if the connection is
+    /// busy running something else.
+    pub async fn ping(&self) -> Option<Result<Duration, sqlx::Error>> {
+        let mut conn = self.pool.try_acquire()?;
+        let started = Instant::now();
+        Some(conn.execute("SELECT 1").await.map(|_| started.elapsed()))
+    }
+
+    /// The schemas of the `search_path` that exist, in order, `pg_catalog`
+    /// left out.
+    pub async fn search_path(&self) -> Result<Vec<String>, sqlx::Error> {
+        sqlx::query_scalar("SELECT current_schemas(false)::text[]")
+            .fetch_one(&self.pool)
+            .await
+    }
+
+    /// Labelled connection details for `:conninfo`.
+    pub async fn conninfo(&self) -> Result<Vec<(&'static str, String)>, sqlx::Error> {
+        let (version, database, user, address, port, pid, ssl): (
+            String,
+            String,
+            String,
+            Option<String>,
+            Option<i32>,
+            i32,
+            Option<String>,
+        ) = sqlx::query_as(match self.server.flavor {
+            // No inet_server_addr() or pg_stat_ssl there.
+            Flavor::Cockroach => {
+                "SELECT version(), current_database(), current_user, \
+                        NULL::text, NULL::int4, pg_backend_pid(), NULL::text"
+            }
+            _ => {
+                "SELECT version(), current_database(), current_user, \
+                        host(inet_server_addr()), inet_server_port(), pg_backend_pid(), \
+                        (SELECT CASE WHEN ssl THEN version || ' ' || cipher END \
+                           FROM pg_stat_ssl WHERE pid = pg_backend_pid())"
+            }
+        })
+        .fetch_one(&self.pool)
+        .await?;
+        let host = match self.options.get_socket() {
+            Some(socket) => socket.display().to_string(),
+            None => self.options.get_host().to_string(),
+        };
+        Ok(vec![
+            ("Database", database),
+            ("User", user),
+            ("Host", host),
+            ("Port", self.options.get_port().to_string()),
+            (
+                "Server address",
+                address.unwrap_or_else(|| "local socket".into()),
+            ),
+            (
+                "Server port",
+                port.map_or_else(|| "-".into(), |port| port.to_string()),
+            ),
+            ("Backend PID", pid.to_string()),
+            ("SSL", ssl.unwrap_or_else(|| "off".into())),
+            ("Server", self.server.name().to_string()),
+            ("Server version", version),
+        ])
+    }
+
+    /// Remembers `sql` for replay if it changes session state.
+    pub fn record(&self, sql: &str) {
+        self.settings.lock().expect("settings lock").record(sql);
+    }
+
+    /// Retries with exponential backoff until a connection can be
+    /// established, calling `report` after each failed attempt. Gives up
+    /// once the pool is closed.
+    pub async fn reconnect(&self, mut report: impl FnMut(u32, Duration, &sqlx::Error)) {
+        let mut delay = MIN_BACKOFF;
+        for attempt in 1.. {
+            match self.pool.acquire().await {
+                Ok(_) | Err(sqlx::Error::PoolClosed) => return,
+                Err(err) => report(attempt, delay, &err),
+            }
+            tokio::time::sleep(delay).await;
+            delay = (delay * 2).min(MAX_BACKOFF);
+        }
+    }
+}
+
+/// Whether the session is in a transaction, as far as the statements run
+/// through it tell.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Transaction {
+    #[default]
+    Idle,
+    Open,
+    /// A statement failed in the transaction, which now only takes
+    /// `ROLLBACK`.
+    Failed,
+}
+
+/// The savepoint each statement run in a transaction is wrapped in with
+/// `:set onerrorrollback`, so that one failing only undoes itself instead
+/// of leaving the transaction good for nothing but `ROLLBACK`.
+pub const STATEMENT_SAVEPOINT: &str = "dbvi_statement";
+
+/// The first two words of `statement`, lowercase.
+fn leading_words(statement: &str) -> Vec<String> {
+    statement
+        .split_whitespace()
+        .take(2)
+        .map(|word| word.trim_end_matches(';').to_lowercase())
+        .collect()
+}
+
+impl Transaction {
+    /// Whether `sql` begins or ends a transaction or works with savepoints,
+    /// which a [`STATEMENT_SAVEPOINT`] around it would get in the way of.
+    pub fn is_control(sql: &str) -> bool {
+        crate::statements::split(sql).iter().any(|statement| {
+            let words = leading_words(statement);
+            let words: Vec<&str> = words.iter().map(String::as_str).collect();
+            matches!(
+                words.as_slice(),
+                [
+                    "begin" | "start" | "commit" | "end" | "rollback" | "abort",
+                    ..
+                ] | ["savepoint" | "release", ..]
+                    | ["prepare", "transaction"]
+            )
+        })
+    }
+
+    /// Where the session is after running `sql`, which failed if not `ok`.
+    pub fn after(self, sql: &str, ok: bool) -> Self {
+        let mut transaction = self;
+        for statement in crate::statements::split(sql) {
+            let words = leading_words(statement);
+            let words: Vec<&str> = words.iter().map(String::as_str).collect();
+            transaction = match words.as_slice() {
+                ["begin", ..] | ["start", "transaction"] => Self::Open,
+                ["rollback", "to"] if transaction != Self::Idle => Self::Open,
+                ["rollback", "to"] => transaction,
+                ["commit" | "end" | "rollback" | "abort", ..] => Self::Idle,
+                _ => transaction,
+            };
+        }
+        if !ok && transaction != Self::Idle {
+            Self::Failed
+        } else {
+            transaction
+        }
+    }
+}
+
+/// Whether the server cancelled the query, e.g. for exceeding
+/// `statement_timeout` (SQLSTATE `query_canceled`).
+pub fn is_query_canceled(err: &sqlx::Error) -> bool {
+    err.as_database_error()
+        .and_then(|err| err.code())
+        .is_some_and(|code| code == "57014")
+}
+
+/// Whether `err` means the connection is gone rather than the query being
+/// wrong: I/O failures, pool timeouts, and the server shutting down or
+/// dropping us (SQLSTATE classes 08 and 57P).
+pub fn is_connection_error(err: &sqlx::Error) -> bool {
+    match err {
+        sqlx::Error::Io(_) | sqlx::Error::PoolTimedOut | sqlx::Error::Tls(_) => true,
+        sqlx::Error::Database(err) => err
+            .code()
+            .is_some_and(|code| code.starts_with("08") || code.starts_with("57P")),
+        _ => false,
+    }
+}
@@ -0,0 +1,112 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! SSH port forwarding through the system `ssh` binary, so the user's
+//! `~/.ssh/config`, agent and known hosts all apply.
+
+use std::{
+    io::{self, Read},
+    net::{Ipv4Addr, TcpListener},
+    process::{Child, Command, Stdio},
+    time::{Duration, Instant},
+};
+
+use crate::config::{SshTunnel, expand_home};
+
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// A running `ssh -L` forward. The ssh process is killed when dropped.
+#[derive(Debug)]
+pub struct Tunnel {
+    child: Child,
+    pub local_port: u16,
+}
+
+impl Tunnel {
+    /// Forwards a free local port to `remote_host:remote_port` as seen from
+    /// the SSH host, waiting until the forward accepts connections.
+    pub async fn open(config: &SshTunnel, remote_host: &str, remote_port: u16) -> io::Result<Self> {
+        // Grab a free port from the OS. There is a small window before ssh
+        // binds it, but that is the best we can do without a socket passing
+        // protocol.
+        let local_port = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?
+            .local_addr()?
+            .port();
+
+        let mut command = Command::new("ssh");
+        command
+            .arg("-N")
+            // Never prompt: the TUI owns the terminal.
+            .args(["-o", "BatchMode=yes", "-o", "ExitOnForwardFailure=yes"])
+            .arg("-L")
+            .arg(format!(
+                "127.0.0.1:{local_port}:{remote_host}:{remote_port}"
+            ));
+        if let Some(port) = config.port {
+            command.arg("-p").arg(port.to_string());
+        }
+        if let Some(key) = &config.key {
+            command.arg("-i").arg(expand_home(key));
+        }
+        if let Some(jump) = &config.jump {
+            command.arg("-J").arg(jump);
+        }
+        match &config.user {
+            Some(user) => command.arg(format!("{user}@{}", config.host)),
+            None => command.arg(&config.host),
+        };
+        let child = command
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let mut tunnel = Self { child, local_port };
+
+        let started = Instant::now();
+        loop {
+            if let Some(status) = tunnel.child.try_wait()? {
+                let mut stderr = String::new();
+                if let Some(mut pipe) = tunnel.child.stderr.take() {
+                    pipe.read_to_string(&mut stderr)?;
+                }
+                return Err(io::Error::other(format!(
+                    "ssh tunnel to {} exited with {status}: {}",
+                    config.host,
+                    stderr.trim()
+                )));
+            }
+            if tokio::net::TcpStream::connect((Ipv4Addr::LOCALHOST, local_port))
+                .await
+                .is_ok()
+            {
+                return Ok(tunnel);
+            }
+            if started.elapsed() > STARTUP_TIMEOUT {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!("timed out opening ssh tunnel to {}", config.host),
+                ));
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+}
+
+impl Drop for Tunnel {
+    fn drop(&mut self) {
+        // The process may already be gone, nothing useful to do about errors.
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
@@ -0,0 +1,67 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! SQL dialects of the Postgres derivatives: the keywords they add on top
+//! of Postgres', and whether EXPLAIN gives plans as JSON. Picked from what
+//! the server says it is, or the profile's `dialect`.
+
+use serde::Deserialize;
+
+use crate::db::server::{Flavor, Server};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Dialect {
+    #[default]
+    Postgres,
+    Redshift,
+    Greenplum,
+}
+
+#[rustfmt::skip]
+const REDSHIFT_KEYWORDS: &[&str] = &[
+    "auto", "backup", "compound", "credentials", "distkey", "diststyle", "encode", "even",
+    "iam_role", "interleaved", "manifest", "parquet", "sortkey", "unload",
+];
+
+#[rustfmt::skip]
+const GREENPLUM_KEYWORDS: &[&str] = &[
+    "distributed", "errors", "external", "gpfdist", "log", "randomly", "readable", "reject",
+    "replicated", "segment", "web", "writable",
+];
+
+impl Dialect {
+    pub fn detect(server: Server) -> Self {
+        match server.flavor {
+            Flavor::Redshift => Dialect::Redshift,
+            Flavor::Greenplum => Dialect::Greenplum,
+            _ => Dialect::Postgres,
+        }
+    }
+
+    /// Keywords of the dialect that Postgres doesn't have.
+    pub fn keywords(self) -> &'static [&'static str] {
+        match self {
+            Dialect::Postgres => &[],
+            Dialect::Redshift => REDSHIFT_KEYWORDS,
+            Dialect::Greenplum => GREENPLUM_KEYWORDS,
+        }
+    }
+
+    /// Whether `EXPLAIN (FORMAT JSON)` works, which the plan tree needs.
+    /// Redshift only explains as text, and can't `ANALYZE`.
+    pub fn json_plans(self) -> bool {
+        self != Dialect::Redshift
+    }
+}
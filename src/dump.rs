@@ -0,0 +1,168 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `:dump` and `:restore`: `pg_dump` and `pg_restore` run with the
+//! session's connection, handed over in `PG*` variables so the password
+//! stays off the command line.
+
+use std::path::Path;
+use std::process::{ExitStatus, Stdio};
+
+use sqlx::ConnectOptions;
+use sqlx::postgres::PgConnectOptions;
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+/// What `:dump` writes out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Scope {
+    Table(String),
+    Schema(String),
+    Database,
+}
+
+impl Scope {
+    pub fn describe(&self) -> String {
+        match self {
+            Scope::Table(table) => format!("table {table}"),
+            Scope::Schema(schema) => format!("schema {schema}"),
+            Scope::Database => "the database".into(),
+        }
+    }
+}
+
+/// Whether `path` is a plain SQL script rather than an archive for
+/// `pg_restore`.
+pub fn is_script(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("sql"))
+}
+
+/// The `pg_dump` arguments for `scope` into `path`: an archive in the
+/// custom format, or a script if `path` ends in `.sql`.
+pub fn dump_args(scope: &Scope, path: &str) -> Vec<String> {
+    let format = if is_script(path) { "plain" } else { "custom" };
+    let mut args = vec![
+        "--verbose".to_string(),
+        format!("--format={format}"),
+        format!("--file={path}"),
+    ];
+    match scope {
+        Scope::Table(table) => args.push(format!("--table={table}")),
+        Scope::Schema(schema) => args.push(format!("--schema={schema}")),
+        Scope::Database => {}
+    }
+    args
+}
+
+/// The `pg_restore` arguments for the archive at `path` into `database`,
+/// stopping at the first error rather than leaving half of it behind
+/// unnoticed.
+pub fn restore_args(path: &str, database: &str) -> Vec<String> {
+    vec![
+        "--verbose".into(),
+        "--exit-on-error".into(),
+        format!("--dbname={database}"),
+        path.into(),
+    ]
+}
+
+/// The `PG*` variables that connect as `options` does, and read-only if
+/// `read_only`, as the tool's connection is none of the session's.
+pub fn env(options: &PgConnectOptions, read_only: bool) -> Vec<(&'static str, String)> {
+    let host = match options.get_socket() {
+        Some(socket) => socket.display().to_string(),
+        None => options.get_host().to_string(),
+    };
+    let mut env = vec![
+        ("PGHOST", host),
+        ("PGPORT", options.get_port().to_string()),
+        ("PGUSER", options.get_username().to_string()),
+    ];
+    if let Some(database) = options.get_database() {
+        env.push(("PGDATABASE", database.to_string()));
+    }
+    // The options only give up the password through their URL.
+    let url = options.to_url_lossy();
+    if let Some(password) = url.password() {
+        env.push(("PGPASSWORD", percent_decode(password)));
+    }
+    if let Some((_, mode)) = url.query_pairs().find(|(key, _)| key == "sslmode") {
+        env.push(("PGSSLMODE", mode.into_owned()));
+    }
+    if read_only {
+        env.push(("PGOPTIONS", "-c default_transaction_read_only=on".into()));
+    }
+    env
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match hex {
+            Some(byte) if bytes[i] == b'%' => {
+                out.push(byte);
+                i += 3;
+            }
+            _ => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Runs `program` with `args` and `env`, calling `line` with each line it
+/// prints on stdout or stderr as it comes, and returns how it exited.
+pub async fn run(
+    program: &str,
+    args: &[String],
+    env: Vec<(&'static str, String)>,
+    mut line: impl FnMut(String),
+) -> Result<ExitStatus, String> {
+    let mut child = tokio::process::Command::new(program)
+        .args(args)
+        .envs(env)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|err| format!("Failed to run {program}: {err}"))?;
+    let mut stdout = BufReader::new(child.stdout.take().expect("piped")).lines();
+    let mut stderr = BufReader::new(child.stderr.take().expect("piped")).lines();
+    let (mut out_open, mut err_open) = (true, true);
+    while out_open || err_open {
+        let (next, open) = tokio::select! {
+            next = stdout.next_line(), if out_open => (next, &mut out_open),
+            next = stderr.next_line(), if err_open => (next, &mut err_open),
+        };
+        match next {
+            Ok(Some(text)) => line(text),
+            Ok(None) => *open = false,
+            Err(err) => return Err(format!("Failed to read from {program}: {err}")),
+        }
+    }
+    child
+        .wait()
+        .await
+        .map_err(|err| format!("Failed to run {program}: {err}"))
+}
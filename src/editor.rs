@@ -0,0 +1,700 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The query editor: text buffers and the edits and motions on them.
+//!
+//! Columns are counted in chars, not bytes, and the cursor moves a
+//! grapheme at a time; [`crate::width`] works out where they are on screen.
+
+use std::ops::{Range, RangeInclusive};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::width;
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Cursor {
+    pub row: usize,
+    pub col: usize,
+}
+
+/// What `y`, `d` and `p` operate on.
+#[derive(Debug, Clone, Default)]
+pub struct Register {
+    pub text: String,
+    /// Whole lines, pasted below/above the cursor line rather than inline.
+    pub linewise: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Buffer {
+    /// Unique for the life of the process, names the buffer's swap file.
+    pub id: usize,
+    pub name: String,
+    pub lines: Vec<String>,
+    pub cursor: Cursor,
+    /// First visible line.
+    pub scroll: usize,
+    pub read_only: bool,
+    /// The file the buffer was opened from or last written to.
+    pub path: Option<PathBuf>,
+    /// Changed since it was opened or last written.
+    pub modified: bool,
+}
+
+/// What `<Tab>` inserts and a line after an open paren is indented by.
+pub const INDENT: &str = "    ";
+
+/// Byte offset of char `col` in `line`, clamped to the end of the line.
+pub fn byte_index(line: &str, col: usize) -> usize {
+    line.char_indices()
+        .nth(col)
+        .map_or(line.len(), |(index, _)| index)
+}
+
+impl Buffer {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            name: name.into(),
+            lines: vec![String::new()],
+            cursor: Cursor::default(),
+            scroll: 0,
+            read_only: false,
+            path: None,
+            modified: false,
+        }
+    }
+
+    pub fn from_text(name: impl Into<String>, text: &str) -> Self {
+        let mut buffer = Self::new(name);
+        buffer.set_text(text);
+        buffer.modified = false;
+        buffer
+    }
+
+    /// Opens `path`, or an empty buffer for it if it doesn't exist yet.
+    pub fn open(path: &Path) -> std::io::Result<Self> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err),
+        };
+        let mut buffer = Self::from_text(path.display().to_string(), &text);
+        buffer.path = Some(path.to_path_buf());
+        Ok(buffer)
+    }
+
+    /// Writes the buffer to `path`, which becomes its file if it has none.
+    pub fn write(&mut self, path: &Path) -> std::io::Result<()> {
+        std::fs::write(path, self.text() + "\n")?;
+        if self.path.is_none() {
+            self.name = path.display().to_string();
+            self.path = Some(path.to_path_buf());
+        }
+        if self.path.as_deref() == Some(path) {
+            self.modified = false;
+        }
+        Ok(())
+    }
+
+    /// Whether closing the buffer would lose changes to its file. Scratch
+    /// buffers have no file to lose changes to.
+    pub fn unsaved(&self) -> bool {
+        self.modified && self.path.is_some()
+    }
+
+    pub fn text(&self) -> String {
+        self.lines.join("\n")
+    }
+
+    /// Replaces the contents, moving the cursor to the top.
+    pub fn set_text(&mut self, text: &str) {
+        self.lines = text.lines().map(str::to_owned).collect();
+        if self.lines.is_empty() {
+            self.lines.push(String::new());
+        }
+        self.cursor = Cursor::default();
+        self.scroll = 0;
+        self.modified = true;
+    }
+
+    pub fn line(&self) -> &str {
+        &self.lines[self.cursor.row]
+    }
+
+    fn line_len(&self, row: usize) -> usize {
+        self.lines[row].chars().count()
+    }
+
+    /// Keeps the cursor on a char in normal mode; insert mode may also sit
+    /// just past the end of the line.
+    pub fn clamp_cursor(&mut self, insert: bool) {
+        self.cursor.row = self.cursor.row.min(self.lines.len() - 1);
+        let len = self.line_len(self.cursor.row);
+        let max = if insert { len } else { len.saturating_sub(1) };
+        self.cursor.col = self.cursor.col.min(max);
+        if self.cursor.col < len {
+            self.cursor.col = width::grapheme_start(self.line(), self.cursor.col);
+        }
+    }
+
+    /// Scrolls back up as far as a window `height` lines tall can still
+    /// be filled, for when the window grows.
+    pub fn fill_window(&mut self, height: usize) {
+        self.scroll = self.scroll.min(self.lines.len().saturating_sub(height));
+    }
+
+    /// Adjusts `scroll` so the cursor is visible in a window `height` lines
+    /// tall.
+    pub fn scroll_to_cursor(&mut self, height: usize) {
+        if self.cursor.row < self.scroll {
+            self.scroll = self.cursor.row;
+        } else if height > 0 && self.cursor.row >= self.scroll + height {
+            self.scroll = self.cursor.row + 1 - height;
+        }
+    }
+
+    /// Screen lines that line `row` takes when wrapped at `width` columns.
+    pub fn visual_rows(&self, row: usize, width: usize) -> usize {
+        width::wrap(&self.lines[row], width).len()
+    }
+
+    /// The screen line (counted from the top of `cursor.row`) and column
+    /// of `cursor`, with lines wrapped at `width` columns if given.
+    pub fn screen_position(&self, cursor: Cursor, wrap: Option<usize>) -> (usize, usize) {
+        let line = &self.lines[cursor.row];
+        match wrap {
+            Some(width) => width::locate(line, cursor.col, width),
+            None => (0, width::columns(line, cursor.col)),
+        }
+    }
+
+    /// `scroll_to_cursor` with lines wrapped at `width` columns.
+    pub fn scroll_to_cursor_wrapped(&mut self, height: usize, width: usize) {
+        self.scroll_to_cursor(height);
+        let (line, _) = self.screen_position(self.cursor, Some(width));
+        while self.scroll < self.cursor.row
+            && (self.scroll..self.cursor.row)
+                .map(|row| self.visual_rows(row, width))
+                .sum::<usize>()
+                + line
+                >= height
+        {
+            self.scroll += 1;
+        }
+    }
+
+    /// Char `col` of screen line `index` of line `row` wrapped at `width`
+    /// columns: the one shown at column `x`, or the last of that screen
+    /// line if it is shorter.
+    fn wrapped_col(&self, row: usize, index: usize, x: usize, width: usize) -> usize {
+        let line = &self.lines[row];
+        let starts = width::wrap(line, width);
+        let index = index.min(starts.len() - 1);
+        let start = starts[index];
+        let end = starts.get(index + 1).copied();
+        let piece: String = line
+            .chars()
+            .skip(start)
+            .take(end.map_or(usize::MAX, |end| end - start))
+            .collect();
+        let col = start + width::char_at(&piece, x);
+        match end {
+            Some(end) => col.min(end - 1),
+            None => col,
+        }
+    }
+
+    /// The position shown at screen line `line` and column `column` of the
+    /// window, with lines wrapped at `wrap` columns if given.
+    pub fn position_at(&self, line: usize, column: usize, wrap: Option<usize>) -> Cursor {
+        let Some(width) = wrap else {
+            let row = self.scroll + line;
+            let col = match self.lines.get(row) {
+                Some(text) => width::char_at(text, column),
+                None => column,
+            };
+            return Cursor { row, col };
+        };
+        let mut line = line;
+        for row in self.scroll..self.lines.len() {
+            let rows = self.visual_rows(row, width);
+            if line < rows {
+                return Cursor {
+                    row,
+                    col: self.wrapped_col(row, line, column, width),
+                };
+            }
+            line -= rows;
+        }
+        Cursor {
+            row: self.lines.len() - 1,
+            col: usize::MAX,
+        }
+    }
+
+    /// `gj` and `gk`: down or up a screen line of lines wrapped at `width`
+    /// columns, keeping the column on screen.
+    pub fn move_visual(&mut self, down: bool, width: usize) {
+        let row = self.cursor.row;
+        let (line, x) = self.screen_position(self.cursor, Some(width));
+        if down {
+            if line + 1 < self.visual_rows(row, width) {
+                self.cursor.col = self.wrapped_col(row, line + 1, x, width);
+            } else if row + 1 < self.lines.len() {
+                self.cursor = Cursor {
+                    row: row + 1,
+                    col: self.wrapped_col(row + 1, 0, x, width),
+                };
+            }
+        } else if line > 0 {
+            self.cursor.col = self.wrapped_col(row, line - 1, x, width);
+        } else if row > 0 {
+            let last = self.visual_rows(row - 1, width) - 1;
+            self.cursor = Cursor {
+                row: row - 1,
+                col: self.wrapped_col(row - 1, last, x, width),
+            };
+        }
+        self.clamp_cursor(false);
+    }
+
+    /// Scrolls by `lines` in a window `height` lines tall, keeping the cursor
+    /// in view.
+    pub fn scroll_by(&mut self, lines: isize, height: usize) {
+        self.scroll = self
+            .scroll
+            .saturating_add_signed(lines)
+            .min(self.lines.len() - 1);
+        let bottom = self.scroll + height.saturating_sub(1);
+        self.cursor.row = self.cursor.row.clamp(self.scroll, bottom.max(self.scroll));
+        self.clamp_cursor(false);
+    }
+
+    pub fn move_left(&mut self) {
+        self.cursor.col = width::previous_grapheme(self.line(), self.cursor.col);
+    }
+
+    pub fn move_right(&mut self, insert: bool) {
+        self.cursor.col = width::next_grapheme(self.line(), self.cursor.col);
+        self.clamp_cursor(insert);
+    }
+
+    pub fn move_up(&mut self, insert: bool) {
+        self.cursor.row = self.cursor.row.saturating_sub(1);
+        self.clamp_cursor(insert);
+    }
+
+    pub fn move_down(&mut self, insert: bool) {
+        self.cursor.row += 1;
+        self.clamp_cursor(insert);
+    }
+
+    pub fn line_start(&mut self) {
+        self.cursor.col = 0;
+    }
+
+    pub fn line_end(&mut self, insert: bool) {
+        self.cursor.col = usize::MAX;
+        self.clamp_cursor(insert);
+    }
+
+    /// First non-blank char of the line, like `^`.
+    pub fn first_non_blank(&mut self) {
+        self.cursor.col = self
+            .line()
+            .chars()
+            .position(|c| !c.is_whitespace())
+            .unwrap_or(0);
+    }
+
+    pub fn top(&mut self) {
+        self.cursor = Cursor::default();
+    }
+
+    pub fn bottom(&mut self) {
+        self.cursor.row = self.lines.len() - 1;
+        self.first_non_blank();
+    }
+
+    pub fn insert_char(&mut self, c: char) {
+        let Cursor { row, col } = self.cursor;
+        let index = byte_index(&self.lines[row], col);
+        self.lines[row].insert(index, c);
+        self.cursor.col += 1;
+        self.modified = true;
+    }
+
+    /// Puts `c` in place of the character under the cursor, as `r` does,
+    /// returning the one it replaced. Nothing on an empty line.
+    pub fn replace_char(&mut self, c: char) -> Option<char> {
+        let Cursor { row, col } = self.cursor;
+        if col >= self.line_len(row) {
+            return None;
+        }
+        let index = byte_index(&self.lines[row], col);
+        let replaced = self.lines[row].remove(index);
+        self.lines[row].insert(index, c);
+        self.modified = true;
+        Some(replaced)
+    }
+
+    /// Types `c` over the character under the cursor, or after the end of
+    /// the line, as in Replace mode. Returns what it went over.
+    pub fn overwrite_char(&mut self, c: char) -> Option<char> {
+        let replaced = self.replace_char(c);
+        match replaced {
+            Some(_) => self.cursor.col += 1,
+            None => self.insert_char(c),
+        }
+        replaced
+    }
+
+    pub fn insert_str(&mut self, text: &str) {
+        for c in text.chars() {
+            if c == '\n' {
+                self.insert_newline();
+            } else if c != '\r' {
+                self.insert_char(c);
+            }
+        }
+    }
+
+    pub fn insert_newline(&mut self) {
+        let Cursor { row, col } = self.cursor;
+        let index = byte_index(&self.lines[row], col);
+        let rest = self.lines[row].split_off(index);
+        self.lines.insert(row + 1, rest);
+        self.cursor = Cursor {
+            row: row + 1,
+            col: 0,
+        };
+        self.modified = true;
+    }
+
+    /// `<Enter>` in insert mode: a new line indented like this one, one
+    /// level deeper after an open paren. Between `(` and `)` the `)` goes
+    /// on a line of its own.
+    pub fn insert_newline_indented(&mut self) {
+        let line = self.line();
+        let indent: String = line.chars().take_while(|c| c.is_whitespace()).collect();
+        let before: String = line.chars().take(self.cursor.col).collect();
+        let after = line.chars().nth(self.cursor.col);
+        let opened = before.trim_end().ends_with('(');
+        self.insert_newline();
+        if opened {
+            self.insert_str(&format!("{indent}{INDENT}"));
+            if after == Some(')') {
+                let cursor = self.cursor;
+                self.insert_newline();
+                self.insert_str(&indent);
+                self.cursor = cursor;
+            }
+        } else {
+            self.insert_str(&indent);
+        }
+    }
+
+    /// Types `c`, closing brackets and quotes as they are opened and
+    /// typing over the closing ones.
+    pub fn insert_char_paired(&mut self, c: char) {
+        let line = self.line();
+        let next = line.chars().nth(self.cursor.col);
+        let previous = self
+            .cursor
+            .col
+            .checked_sub(1)
+            .and_then(|col| line.chars().nth(col));
+        match c {
+            ')' | '\'' | '"' if next == Some(c) => self.cursor.col += 1,
+            '(' => {
+                self.insert_str("()");
+                self.cursor.col -= 1;
+            }
+            // Not for the apostrophe in a word or the second quote of `''`.
+            '\'' | '"'
+                if !previous.is_some_and(|p| p.is_alphanumeric() || p == c)
+                    && !next.is_some_and(char::is_alphanumeric) =>
+            {
+                self.insert_str(&format!("{c}{c}"));
+                self.cursor.col -= 1;
+            }
+            c => self.insert_char(c),
+        }
+    }
+
+    /// `<BS>` with auto pairs: the closing half of an empty pair goes along
+    /// with the opening one.
+    pub fn backspace_paired(&mut self) {
+        let line = self.line();
+        let col = self.cursor.col;
+        let pair = col
+            .checked_sub(1)
+            .and_then(|col| line.chars().nth(col))
+            .zip(line.chars().nth(col));
+        if matches!(pair, Some(('(', ')') | ('\'', '\'') | ('"', '"'))) {
+            let row = self.cursor.row;
+            let index = byte_index(&self.lines[row], col);
+            self.lines[row].remove(index);
+        }
+        self.backspace();
+    }
+
+    /// Deletes the grapheme before the cursor, joining lines at the start
+    /// of one.
+    pub fn backspace(&mut self) {
+        let Cursor { row, col } = self.cursor;
+        if col > 0 {
+            let line = &self.lines[row];
+            let previous = width::previous_grapheme(line, col);
+            let range = byte_index(line, previous)..byte_index(line, col);
+            self.lines[row].replace_range(range, "");
+            self.cursor.col = previous;
+            self.modified = true;
+        } else if row > 0 {
+            let line = self.lines.remove(row);
+            self.cursor = Cursor {
+                row: row - 1,
+                col: self.line_len(row - 1),
+            };
+            self.lines[row - 1].push_str(&line);
+            self.modified = true;
+        }
+    }
+
+    /// Deletes the char under the cursor, like `x`.
+    pub fn delete_char(&mut self) -> Option<char> {
+        let Cursor { row, col } = self.cursor;
+        if col >= self.line_len(row) {
+            return None;
+        }
+        let index = byte_index(&self.lines[row], col);
+        let c = self.lines[row].remove(index);
+        self.clamp_cursor(false);
+        self.modified = true;
+        Some(c)
+    }
+
+    /// Deletes the cursor line, like `dd`, returning it.
+    pub fn delete_line(&mut self) -> String {
+        let line = if self.lines.len() == 1 {
+            std::mem::take(&mut self.lines[0])
+        } else {
+            self.lines.remove(self.cursor.row)
+        };
+        self.clamp_cursor(false);
+        self.first_non_blank();
+        self.modified = true;
+        line
+    }
+
+    /// Byte offset of `cursor` in `text()`.
+    pub fn offset(&self, cursor: Cursor) -> usize {
+        self.lines[..cursor.row]
+            .iter()
+            .map(|line| line.len() + 1)
+            .sum::<usize>()
+            + byte_index(&self.lines[cursor.row], cursor.col)
+    }
+
+    /// The cursor at byte `offset` of `text()`.
+    pub fn cursor_at(&self, mut offset: usize) -> Cursor {
+        for (row, line) in self.lines.iter().enumerate() {
+            if offset <= line.len() {
+                return Cursor {
+                    row,
+                    col: line[..offset].chars().count(),
+                };
+            }
+            offset -= line.len() + 1;
+        }
+        Cursor {
+            row: self.lines.len() - 1,
+            col: self.line_len(self.lines.len() - 1),
+        }
+    }
+
+    /// Where the next match of `pattern` after `from` starts, or the one
+    /// before it going `back`, wrapping around the ends of the text. A
+    /// match at `from` itself counts with `here`. Also whether it wrapped.
+    pub fn find(
+        &self,
+        pattern: &regex::Regex,
+        from: Cursor,
+        back: bool,
+        here: bool,
+    ) -> Option<(Cursor, bool)> {
+        let text = self.text();
+        let at = self.offset(from);
+        let starts: Vec<usize> = pattern.find_iter(&text).map(|m| m.start()).collect();
+        let found = match back {
+            false => starts
+                .iter()
+                .find(|&&start| start > at || (here && start == at))
+                .map(|&start| (start, false)),
+            true => starts
+                .iter()
+                .rev()
+                .find(|&&start| start < at)
+                .map(|&start| (start, false)),
+        };
+        let wrapped = match back {
+            false => starts.first(),
+            true => starts.last(),
+        };
+        found
+            .or_else(|| wrapped.map(|&start| (start, true)))
+            .map(|(start, wrapped)| (self.cursor_at(start), wrapped))
+    }
+
+    /// The word of letters, digits and `_` under the cursor, as `*` takes
+    /// it.
+    pub fn word(&self) -> Option<&str> {
+        let line = self.line();
+        let at = byte_index(line, self.cursor.col);
+        let part = |c: char| c.is_alphanumeric() || c == '_';
+        if !line[at..].starts_with(part) {
+            return None;
+        }
+        let start = line[..at]
+            .char_indices()
+            .rev()
+            .take_while(|(_, c)| part(*c))
+            .last()
+            .map_or(at, |(i, _)| i);
+        let end = line[at..].find(|c| !part(c)).map_or(line.len(), |i| at + i);
+        Some(&line[start..end])
+    }
+
+    /// Deletes bytes `range` of `text()`, returning them, and leaves the
+    /// cursor where they were.
+    pub fn delete_range(&mut self, range: Range<usize>) -> String {
+        let cursor = self.cursor_at(range.start);
+        let mut text = self.text();
+        let deleted = text.drain(range).collect();
+        self.lines = text.split('\n').map(str::to_owned).collect();
+        self.cursor = cursor;
+        self.modified = true;
+        deleted
+    }
+
+    /// Comments out `rows` with `--`, or uncomments them if every non-blank
+    /// one already is, like `gc`.
+    pub fn toggle_line_comments(&mut self, rows: RangeInclusive<usize>) {
+        let rows = *rows.start()..=(*rows.end()).min(self.lines.len() - 1);
+        let code: Vec<usize> = rows
+            .filter(|row| !self.lines[*row].trim().is_empty())
+            .collect();
+        if code.is_empty() {
+            return;
+        }
+        let indent = |line: &str| line.len() - line.trim_start().len();
+        if code
+            .iter()
+            .all(|row| self.lines[*row].trim_start().starts_with("--"))
+        {
+            for row in code {
+                let line = &mut self.lines[row];
+                let start = indent(line);
+                let end = if line[start + 2..].starts_with(' ') {
+                    start + 3
+                } else {
+                    start + 2
+                };
+                line.replace_range(start..end, "");
+            }
+        } else {
+            // At the shallowest indent, so the comments line up.
+            let column = code
+                .iter()
+                .map(|row| indent(&self.lines[*row]))
+                .min()
+                .unwrap_or_default();
+            for row in code {
+                self.lines[row].insert_str(column, "-- ");
+            }
+        }
+        self.clamp_cursor(false);
+        self.modified = true;
+    }
+
+    /// Wraps bytes `range` of `text()` in `/* */`, or unwraps them if that's
+    /// what they already are, like `gb`.
+    pub fn toggle_block_comment(&mut self, range: Range<usize>) {
+        let text = self.text();
+        let slice = &text[range.clone()];
+        let trimmed = slice.trim();
+        let start = range.start + (slice.len() - slice.trim_start().len());
+        let end = start + trimmed.len();
+        let replacement = match trimmed
+            .strip_prefix("/*")
+            .and_then(|inner| inner.strip_suffix("*/"))
+        {
+            Some(inner) => {
+                let inner = inner.strip_prefix(' ').unwrap_or(inner);
+                inner.strip_suffix(' ').unwrap_or(inner).to_string()
+            }
+            None if trimmed.is_empty() => return,
+            None => format!("/* {trimmed} */"),
+        };
+        let cursor = self.cursor;
+        let mut text = text;
+        text.replace_range(start..end, &replacement);
+        self.lines = text.split('\n').map(str::to_owned).collect();
+        self.cursor = cursor;
+        self.clamp_cursor(false);
+        self.modified = true;
+    }
+
+    /// Opens a new line below (or above) the cursor line and moves onto it.
+    pub fn open_line(&mut self, above: bool) {
+        let row = if above {
+            self.cursor.row
+        } else {
+            self.cursor.row + 1
+        };
+        self.lines.insert(row, String::new());
+        self.cursor = Cursor { row, col: 0 };
+        self.modified = true;
+    }
+
+    /// Pastes `register` after (or before) the cursor, like `p` and `P`.
+    pub fn paste(&mut self, register: &Register, before: bool) {
+        if register.text.is_empty() {
+            return;
+        }
+        if register.linewise {
+            let row = if before {
+                self.cursor.row
+            } else {
+                self.cursor.row + 1
+            };
+            for (offset, line) in register.text.lines().enumerate() {
+                self.lines.insert(row + offset, line.to_string());
+            }
+            self.cursor = Cursor { row, col: 0 };
+            self.first_non_blank();
+            self.modified = true;
+        } else {
+            if !before && !self.line().is_empty() {
+                self.cursor.col += 1;
+            }
+            self.insert_str(&register.text);
+            self.move_left();
+        }
+    }
+}
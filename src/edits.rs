@@ -0,0 +1,237 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Changing query results in the grid. When the columns come straight from
+//! one table, with all of its primary key among them, `c` changes the cell
+//! under the cursor. Changed cells stand out until `:apply` shows the
+//! `UPDATE`s that write them, one a row, to look over, edit and run, or
+//! `:discard` puts the old values back. Another query drops them.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use sqlx::PgPool;
+use sqlx::postgres::types::Oid;
+
+use crate::export::sql_literal;
+use crate::results::{Cell, ResultSet};
+use crate::statements::quote_ident;
+
+/// The table results can be written back to.
+#[derive(Debug, Clone)]
+pub struct Target {
+    /// The table, as `regclass` prints it.
+    pub table: String,
+    /// The column of the table each column of the results is, `None` for
+    /// those that aren't one.
+    pub columns: Vec<Option<String>>,
+    /// The columns of the results that make up the primary key.
+    pub key: Vec<usize>,
+}
+
+impl Target {
+    /// The table the columns of `results` come from. The inner error says
+    /// why they can't be written back.
+    pub async fn resolve(
+        pool: &PgPool,
+        results: &ResultSet,
+    ) -> Result<Result<Self, String>, sqlx::Error> {
+        let tables: BTreeSet<u32> = results
+            .columns
+            .iter()
+            .filter_map(|column| Some(column.origin?.0))
+            .collect();
+        let oid = match tables.into_iter().collect::<Vec<_>>().as_slice() {
+            [] => return Ok(Err("The results aren't the columns of a table".into())),
+            [oid] => *oid,
+            _ => return Ok(Err("The results come from more than one table".into())),
+        };
+        let table: String = sqlx::query_scalar("SELECT $1::regclass::text")
+            .bind(Oid(oid))
+            .fetch_one(pool)
+            .await?;
+        let names: BTreeMap<i16, String> = sqlx::query_as(
+            "SELECT attnum, attname::text FROM pg_attribute \
+             WHERE attrelid = $1 AND attnum > 0 AND NOT attisdropped",
+        )
+        .bind(Oid(oid))
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .collect();
+        let primary_key: Vec<i16> = sqlx::query_scalar(
+            "SELECT unnest(indkey)::int2 FROM pg_index WHERE indrelid = $1 AND indisprimary",
+        )
+        .bind(Oid(oid))
+        .fetch_all(pool)
+        .await?;
+        if primary_key.is_empty() {
+            return Ok(Err(format!(
+                "{table} has no primary key to find the rows by"
+            )));
+        }
+        let mut key = Vec::with_capacity(primary_key.len());
+        for attnum in primary_key {
+            let col = results
+                .columns
+                .iter()
+                .position(|column| column.origin == Some((oid, attnum)));
+            match col {
+                Some(col) => key.push(col),
+                None => {
+                    let name = names.get(&attnum).map_or("?", String::as_str);
+                    return Ok(Err(format!(
+                        "The results need {name} of the primary key of {table}"
+                    )));
+                }
+            }
+        }
+        let columns = results
+            .columns
+            .iter()
+            .map(|column| match column.origin {
+                Some((table, attnum)) if table == oid => names.get(&attnum).cloned(),
+                _ => None,
+            })
+            .collect();
+        Ok(Ok(Self {
+            table,
+            columns,
+            key,
+        }))
+    }
+}
+
+/// The cells changed in the results, to be written back to their table.
+#[derive(Debug, Clone)]
+pub struct Edits {
+    pub target: Target,
+    /// The old and new values by the key of the row and then the column.
+    changes: BTreeMap<Vec<Cell>, BTreeMap<usize, (Cell, Cell)>>,
+}
+
+impl Edits {
+    pub fn new(target: Target) -> Self {
+        Self {
+            target,
+            changes: BTreeMap::new(),
+        }
+    }
+
+    /// How many cells are changed.
+    pub fn len(&self) -> usize {
+        self.changes.values().map(BTreeMap::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Why column `col` of the results can't be changed, if it can't.
+    pub fn check(&self, results: &ResultSet, col: usize) -> Result<(), String> {
+        let name = &results.columns[col].name;
+        if self.target.key.contains(&col) {
+            return Err(format!("{name} finds the row, so it stays as it is"));
+        }
+        match self.target.columns.get(col) {
+            Some(Some(_)) => Ok(()),
+            _ => Err(format!("{name} isn't a column of {}", self.target.table)),
+        }
+    }
+
+    fn key(&self, results: &ResultSet, row: usize) -> Vec<Cell> {
+        self.target
+            .key
+            .iter()
+            .map(|&col| results.rows.cell(row, col).map(str::to_string))
+            .collect()
+    }
+
+    /// Changes the cell at `row` and `col` of `results` to `value`.
+    pub fn change(
+        &mut self,
+        results: &mut ResultSet,
+        row: usize,
+        col: usize,
+        value: Cell,
+    ) -> Result<(), String> {
+        self.check(results, col)?;
+        let old = results.rows.cell(row, col).map(str::to_string);
+        let key = self.key(results, row);
+        let change = self
+            .changes
+            .entry(key)
+            .or_default()
+            .entry(col)
+            .or_insert((old, None));
+        change.1 = value;
+        results.rows.set(row, col, change.1.as_deref());
+        Ok(())
+    }
+
+    /// Puts the old values back in `results`.
+    pub fn discard(&self, results: &mut ResultSet) {
+        for (row, col) in self.cells(results) {
+            let key = self.key(results, row);
+            if let Some((old, _)) = self.changes.get(&key).and_then(|row| row.get(&col)) {
+                results.rows.set(row, col, old.as_deref());
+            }
+        }
+    }
+
+    /// Where the changed cells are in `results`, as rows and columns.
+    pub fn cells(&self, results: &ResultSet) -> BTreeSet<(usize, usize)> {
+        if self.changes.is_empty() {
+            return BTreeSet::new();
+        }
+        (0..results.rows.len())
+            .filter_map(|row| Some((row, self.changes.get(&self.key(results, row))?)))
+            .flat_map(|(row, cols)| cols.keys().map(move |&col| (row, col)))
+            .collect()
+    }
+
+    /// The `UPDATE`s that write the changes, one a row.
+    pub fn to_sql(&self, results: &ResultSet) -> String {
+        let name = |col: usize| {
+            let name = self.target.columns[col].as_deref().unwrap_or_default();
+            quote_ident(name)
+        };
+        let mut sql = String::new();
+        for (key, cols) in &self.changes {
+            let set: Vec<String> = cols
+                .iter()
+                .map(|(&col, (_, new))| {
+                    let value = sql_literal(&results.columns[col], new.as_deref(), false);
+                    format!("{} = {value}", name(col))
+                })
+                .collect();
+            let find: Vec<String> = self
+                .target
+                .key
+                .iter()
+                .zip(key)
+                .map(|(&col, value)| {
+                    let value = sql_literal(&results.columns[col], value.as_deref(), false);
+                    format!("{} = {value}", name(col))
+                })
+                .collect();
+            sql.push_str(&format!(
+                "UPDATE {} SET {} WHERE {};\n",
+                self.target.table,
+                set.join(", "),
+                find.join(" AND ")
+            ));
+        }
+        sql
+    }
+}
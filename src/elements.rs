@@ -0,0 +1,371 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Arrays and composite values, `{1,2,3}` and `(1,"a b")` in Postgres' text
+//! format, taken apart: shown with their elements spaced out in the grid,
+//! and listed one per line by the viewer `Enter` opens on such a cell,
+//! where nested ones expand and each element can be yanked on its own.
+
+use std::collections::BTreeSet;
+use std::iter::Peekable;
+use std::str::Chars;
+
+use ratatui::{
+    Frame,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::Paragraph,
+};
+
+use crate::results::{self, Column};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Node {
+    /// A plain element, `None` for `NULL`.
+    Value(Option<String>),
+    Array(Vec<Node>),
+    Record(Vec<Node>),
+}
+
+/// `text` taken apart if `column` holds arrays or composite values. Those of
+/// types the driver has no name for, like user-defined composites, are
+/// told by their braces and parentheses.
+pub fn parse(column: &Column, text: &str) -> Option<Node> {
+    parse_typed(&column.ty, text)
+}
+
+fn parse_typed(ty: &str, text: &str) -> Option<Node> {
+    let unknown = ty == "?";
+    match ty.strip_suffix("[]") {
+        Some(element) => array(text, element),
+        None if unknown && text.starts_with(['{', '[']) => array(text, "?"),
+        None if ty == "record" || unknown && text.starts_with('(') => record(text),
+        None => None,
+    }
+}
+
+/// An array, after the `[1:3]=` that says its bounds when they aren't 1.
+fn array(text: &str, element: &str) -> Option<Node> {
+    let text = match text.starts_with('[') {
+        true => &text[text.find('=')? + 1..],
+        false => text,
+    };
+    let mut chars = text.chars().peekable();
+    let node = elements(&mut chars, element)?;
+    chars.next().is_none().then_some(node)
+}
+
+fn elements(chars: &mut Peekable<Chars>, element: &str) -> Option<Node> {
+    if chars.next()? != '{' {
+        return None;
+    }
+    let mut nodes = Vec::new();
+    if chars.peek() == Some(&'}') {
+        chars.next();
+        return Some(Node::Array(nodes));
+    }
+    loop {
+        let node = match chars.peek()? {
+            '{' => elements(chars, element)?,
+            '"' => {
+                chars.next();
+                let mut text = String::new();
+                loop {
+                    match chars.next()? {
+                        '\\' => text.push(chars.next()?),
+                        '"' => break,
+                        c => text.push(c),
+                    }
+                }
+                match element {
+                    "record" | "?" if text.starts_with('(') => record(&text)?,
+                    _ => Node::Value(Some(text)),
+                }
+            }
+            _ => {
+                let mut text = String::new();
+                while let Some(&c) = chars.peek()
+                    && c != ','
+                    && c != '}'
+                {
+                    text.push(c);
+                    chars.next();
+                }
+                let text = text.trim();
+                Node::Value((!text.eq_ignore_ascii_case("null")).then(|| text.to_string()))
+            }
+        };
+        nodes.push(node);
+        match chars.next()? {
+            ',' => {}
+            '}' => return Some(Node::Array(nodes)),
+            _ => return None,
+        }
+    }
+}
+
+/// A composite value. Its fields don't say their types, so one that looks
+/// like an array or a composite value is taken for one.
+fn record(text: &str) -> Option<Node> {
+    if !(text.starts_with('(') && text.ends_with(')')) {
+        return None;
+    }
+    let fields = results::parse_record(text)
+        .into_iter()
+        .map(|field| match field {
+            Some(text) if text.starts_with(['{', '(']) => {
+                parse_typed("?", &text).unwrap_or(Node::Value(Some(text)))
+            }
+            field => Node::Value(field),
+        })
+        .collect();
+    Some(Node::Record(fields))
+}
+
+/// `text` between double quotes, with `"` and `\` escaped as Postgres
+/// reads them in arrays and composite values.
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn needs_quotes(text: &str, special: &[char]) -> bool {
+    text.is_empty()
+        || text.eq_ignore_ascii_case("null")
+        || text.contains(special)
+        || text.contains(char::is_whitespace)
+}
+
+impl Node {
+    pub fn children(&self) -> Option<&[Node]> {
+        match self {
+            Node::Value(_) => None,
+            Node::Array(nodes) | Node::Record(nodes) => Some(nodes),
+        }
+    }
+
+    /// How the grid shows it: elements spaced out, quoted only where they
+    /// would read as something else.
+    pub fn render(&self) -> String {
+        match self {
+            Node::Value(None) => "NULL".into(),
+            Node::Value(Some(text)) if needs_quotes(text, &['{', '}', '(', ')', ',', '"']) => {
+                format!("{text:?}")
+            }
+            Node::Value(Some(text)) => text.clone(),
+            Node::Array(nodes) => format!("{{{}}}", join(nodes, ", ", Node::render)),
+            Node::Record(nodes) => format!("({})", join(nodes, ", ", Node::render)),
+        }
+    }
+
+    /// The value as a Postgres literal: the text of an element, or an
+    /// array or composite value as the server writes one.
+    pub fn literal(&self) -> String {
+        match self {
+            Node::Value(text) => text.clone().unwrap_or_else(|| "NULL".into()),
+            Node::Array(nodes) => format!(
+                "{{{}}}",
+                join(nodes, ",", |node| match node {
+                    Node::Value(None) => "NULL".into(),
+                    Node::Value(Some(text)) if needs_quotes(text, &['{', '}', ',', '"', '\\']) => {
+                        quote(text)
+                    }
+                    Node::Value(Some(text)) => text.clone(),
+                    Node::Array(_) => node.literal(),
+                    Node::Record(_) => quote(&node.literal()),
+                })
+            ),
+            Node::Record(nodes) => format!(
+                "({})",
+                join(nodes, ",", |node| match node {
+                    Node::Value(None) => String::new(),
+                    Node::Value(Some(text)) if needs_quotes(text, &['(', ')', ',', '"', '\\']) => {
+                        quote(text)
+                    }
+                    Node::Value(Some(text)) => text.clone(),
+                    node => quote(&node.literal()),
+                })
+            ),
+        }
+    }
+
+    /// What an expanded one says instead of its elements.
+    fn summary(&self) -> String {
+        match self {
+            Node::Value(_) => self.render(),
+            Node::Array(nodes) => format!("array of {}", nodes.len()),
+            Node::Record(nodes) => format!("composite of {} fields", nodes.len()),
+        }
+    }
+
+    fn at(&self, path: &[usize]) -> Option<&Node> {
+        match path.split_first() {
+            None => Some(self),
+            Some((first, rest)) => self.children()?.get(*first)?.at(rest),
+        }
+    }
+}
+
+fn join(nodes: &[Node], separator: &str, render: impl Fn(&Node) -> String) -> String {
+    nodes.iter().map(render).collect::<Vec<_>>().join(separator)
+}
+
+/// The elements of a cell, one per line, those of nested arrays and
+/// composite values under them once expanded.
+#[derive(Debug)]
+pub struct Elements {
+    pub title: String,
+    root: Node,
+    /// Paths of the nested ones expanded.
+    expanded: BTreeSet<Vec<usize>>,
+    pub cursor: usize,
+    scroll: usize,
+}
+
+/// A line of the viewer: where the element is and how deep.
+struct Entry {
+    path: Vec<usize>,
+    label: String,
+}
+
+impl Elements {
+    pub fn new(title: String, root: Node) -> Self {
+        Self {
+            title,
+            root,
+            expanded: BTreeSet::new(),
+            cursor: 0,
+            scroll: 0,
+        }
+    }
+
+    fn entries(&self) -> Vec<Entry> {
+        let mut entries = Vec::new();
+        self.walk(&self.root, &mut Vec::new(), &mut entries);
+        entries
+    }
+
+    fn walk(&self, node: &Node, path: &mut Vec<usize>, entries: &mut Vec<Entry>) {
+        for (i, child) in node.children().unwrap_or_default().iter().enumerate() {
+            path.push(i);
+            let label = match node {
+                // Postgres counts from 1.
+                Node::Array(_) => format!("[{}]", i + 1),
+                _ => format!(".{}", i + 1),
+            };
+            entries.push(Entry {
+                path: path.clone(),
+                label,
+            });
+            if self.expanded.contains(path.as_slice()) {
+                self.walk(child, path, entries);
+            }
+            path.pop();
+        }
+    }
+
+    /// The element under the cursor.
+    pub fn selected(&self) -> Option<&Node> {
+        let entry = self.entries().into_iter().nth(self.cursor)?;
+        self.root.at(&entry.path)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn move_by(&mut self, rows: isize) {
+        let last = self.len().saturating_sub(1);
+        self.cursor = self.cursor.saturating_add_signed(rows).min(last);
+    }
+
+    pub fn top(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn bottom(&mut self) {
+        self.cursor = self.len().saturating_sub(1);
+    }
+
+    /// Shows the elements of the one under the cursor.
+    pub fn expand(&mut self) {
+        if let Some(entry) = self.entries().into_iter().nth(self.cursor)
+            && self.root.at(&entry.path).and_then(Node::children).is_some()
+        {
+            self.expanded.insert(entry.path);
+        }
+    }
+
+    /// Hides the elements of the one under the cursor, or of the one it is
+    /// in, moving there.
+    pub fn collapse(&mut self) {
+        let entries = self.entries();
+        let Some(entry) = entries.get(self.cursor) else {
+            return;
+        };
+        if self.expanded.remove(&entry.path) {
+            return;
+        }
+        let parent = &entry.path[..entry.path.len() - 1];
+        if let Some(row) = entries.iter().position(|entry| entry.path == parent) {
+            self.expanded.remove(parent);
+            self.cursor = row;
+        }
+    }
+
+    pub fn render(&mut self, f: &mut Frame, area: Rect, focused: bool) {
+        let entries = self.entries();
+        let height = area.height as usize;
+        self.cursor = self.cursor.min(entries.len().saturating_sub(1));
+        if self.cursor < self.scroll {
+            self.scroll = self.cursor;
+        } else if height > 0 && self.cursor >= self.scroll + height {
+            self.scroll = self.cursor + 1 - height;
+        }
+        let lines: Vec<Line> = entries
+            .iter()
+            .enumerate()
+            .skip(self.scroll)
+            .take(height)
+            .map(|(row, entry)| {
+                let node = self.root.at(&entry.path);
+                let expanded = self.expanded.contains(&entry.path);
+                let (marker, text) = match node {
+                    Some(node) if node.children().is_none() => ("  ", node.render()),
+                    Some(node) if expanded => ("▾ ", node.summary()),
+                    Some(node) => ("▸ ", node.render()),
+                    None => ("  ", String::new()),
+                };
+                let indent = "  ".repeat(entry.path.len() - 1);
+                let mut line = Line::from(vec![
+                    Span::raw(format!("{indent}{marker}")),
+                    Span::styled(
+                        format!("{:<6}", entry.label),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                    Span::raw(text),
+                ]);
+                if focused && row == self.cursor {
+                    line = line.patch_style(Style::default().add_modifier(Modifier::REVERSED));
+                }
+                line
+            })
+            .collect();
+        f.render_widget(Paragraph::new(lines), area);
+    }
+}
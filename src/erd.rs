@@ -0,0 +1,513 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `:erd`, the tables of the database, of a schema or around one table,
+//! drawn as boxes with their foreign keys as wires between them. Tables
+//! come after the ones they reference, so wires mostly run up to the key
+//! they point at.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use ratatui::{
+    Frame,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::Paragraph,
+};
+
+use crate::db::catalog::{Column, ForeignKey};
+use crate::db::schema::{Relation, Schema};
+use crate::width;
+
+/// How much of each table its box shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Detail {
+    Names,
+    /// The primary and foreign key columns.
+    Keys,
+    Columns,
+}
+
+impl Detail {
+    pub fn name(self) -> &'static str {
+        match self {
+            Detail::Names => "names",
+            Detail::Keys => "keys",
+            Detail::Columns => "columns",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Part {
+    Border,
+    Title,
+    Column,
+    Type,
+    Tag,
+    Wire,
+}
+
+impl Part {
+    fn style(self) -> Style {
+        match self {
+            Part::Border => Style::default().fg(Color::DarkGray),
+            Part::Title => Style::default().add_modifier(Modifier::BOLD),
+            Part::Column => Style::default(),
+            Part::Type => Style::default().fg(Color::DarkGray),
+            Part::Tag => Style::default().fg(Color::Yellow),
+            Part::Wire => Style::default().fg(Color::Cyan),
+        }
+    }
+}
+
+/// A cell of the drawing; `'\0'` is the second half of a wide character.
+type Cell = (char, Part);
+
+#[derive(Debug, Clone)]
+struct Table {
+    name: String,
+    columns: Vec<Column>,
+}
+
+#[derive(Debug, Clone)]
+struct Key {
+    table: usize,
+    columns: Vec<String>,
+    references: usize,
+    referenced: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct Erd {
+    /// What it shows, like `public` or the table it is drawn around.
+    pub scope: String,
+    tables: Vec<Table>,
+    keys: Vec<Key>,
+    pub detail: Detail,
+    canvas: Vec<Vec<Cell>>,
+    /// The first row and column in view.
+    row: usize,
+    col: usize,
+    /// The size of the area last drawn to.
+    area: (usize, usize),
+}
+
+fn is_table(relation: &Relation) -> bool {
+    matches!(
+        relation.kind.as_str(),
+        "table" | "partitioned table" | "foreign table"
+    )
+}
+
+impl Erd {
+    /// The diagram of the tables in `scope`, a schema or a table (looked
+    /// up along `search_path`) and those it shares a key with, or of every
+    /// table without one.
+    pub fn new(
+        schema: &Schema,
+        keys: &[ForeignKey],
+        scope: Option<&str>,
+        search_path: &[String],
+    ) -> Result<Self, String> {
+        let tables: BTreeMap<&str, &Relation> = schema
+            .relations
+            .iter()
+            .filter(|(_, relation)| is_table(relation))
+            .map(|(key, relation)| (key.as_str(), relation))
+            .collect();
+        let (scope, shown): (String, BTreeSet<&str>) = match scope {
+            None => ("all tables".into(), tables.keys().copied().collect()),
+            Some(name) if tables.values().any(|relation| relation.schema == name) => (
+                name.to_string(),
+                tables
+                    .iter()
+                    .filter(|(_, relation)| relation.schema == name)
+                    .map(|(key, _)| *key)
+                    .collect(),
+            ),
+            Some(name) => {
+                let Some(relation) = schema
+                    .resolve(name, search_path)
+                    .filter(|relation| is_table(relation))
+                else {
+                    return Err(format!("No schema or table named {name}"));
+                };
+                let center = format!("{}.{}", relation.schema, relation.name);
+                let mut shown: BTreeSet<&str> = keys
+                    .iter()
+                    .filter_map(|key| match (&key.table, &key.references) {
+                        (table, references) if *table == center => Some(references.as_str()),
+                        (table, references) if *references == center => Some(table.as_str()),
+                        _ => None,
+                    })
+                    .filter(|name| tables.contains_key(name))
+                    .collect();
+                shown.extend(tables.get_key_value(center.as_str()).map(|(key, _)| *key));
+                (relation.qualified(), shown)
+            }
+        };
+        if shown.is_empty() {
+            return Err("No tables to draw, the schema may not be cached yet".into());
+        }
+        let keys: Vec<&ForeignKey> = keys
+            .iter()
+            .filter(|key| {
+                shown.contains(key.table.as_str()) && shown.contains(key.references.as_str())
+            })
+            .collect();
+
+        // How far down a chain of references each table is; cycles stop
+        // growing once longer than there are tables.
+        let mut depth: HashMap<&str, usize> = shown.iter().map(|name| (*name, 0)).collect();
+        for _ in 0..shown.len() {
+            let mut changed = false;
+            for key in keys.iter().filter(|key| key.table != key.references) {
+                let below = depth[key.references.as_str()] + 1;
+                let table = depth.get_mut(key.table.as_str()).expect("shown");
+                if *table < below && below <= shown.len() {
+                    (*table, changed) = (below, true);
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+        let mut order: Vec<&str> = shown.into_iter().collect();
+        order.sort_by_key(|name| depth[name]);
+        let index: HashMap<&str, usize> = order.iter().enumerate().map(|(i, n)| (*n, i)).collect();
+
+        let mut erd = Self {
+            scope,
+            tables: order
+                .iter()
+                .map(|name| Table {
+                    name: tables[name].qualified(),
+                    columns: tables[name].columns.clone(),
+                })
+                .collect(),
+            keys: keys
+                .iter()
+                .map(|key| Key {
+                    table: index[key.table.as_str()],
+                    columns: key.columns.clone(),
+                    references: index[key.references.as_str()],
+                    referenced: key.referenced.clone(),
+                })
+                .collect(),
+            detail: Detail::Columns,
+            canvas: Vec::new(),
+            row: 0,
+            col: 0,
+            area: (0, 0),
+        };
+        erd.draw();
+        Ok(erd)
+    }
+
+    pub fn tables(&self) -> usize {
+        self.tables.len()
+    }
+
+    pub fn keys(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Shows more of each table, or less.
+    pub fn zoom(&mut self, more: bool) {
+        self.detail = match (self.detail, more) {
+            (Detail::Names, true) | (Detail::Columns, false) => Detail::Keys,
+            (Detail::Keys | Detail::Columns, true) => Detail::Columns,
+            (Detail::Keys | Detail::Names, false) => Detail::Names,
+        };
+        self.draw();
+        self.scroll_by(0, 0);
+    }
+
+    pub fn scroll_by(&mut self, rows: isize, cols: isize) {
+        let (height, width) = self.area;
+        let width_drawn = self.canvas.iter().map(Vec::len).max().unwrap_or_default();
+        self.row = self
+            .row
+            .saturating_add_signed(rows)
+            .min(self.canvas.len().saturating_sub(height.max(1)));
+        self.col = self
+            .col
+            .saturating_add_signed(cols)
+            .min(width_drawn.saturating_sub(width.max(1)));
+    }
+
+    pub fn top(&mut self) {
+        self.row = 0;
+    }
+
+    pub fn bottom(&mut self) {
+        self.scroll_by(isize::MAX, 0);
+    }
+
+    /// Scrolls back to the first column.
+    pub fn left(&mut self) {
+        self.col = 0;
+    }
+
+    /// The drawing as text, each line trimmed.
+    pub fn text(&self) -> String {
+        self.canvas
+            .iter()
+            .map(|cells| {
+                let line: String = cells
+                    .iter()
+                    .map(|(c, _)| *c)
+                    .filter(|c| *c != '\0')
+                    .collect();
+                line.trim_end().to_string()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Lays the tables out at the current detail.
+    fn draw(&mut self) {
+        let keyed: BTreeSet<(usize, &str)> = self
+            .keys
+            .iter()
+            .flat_map(|key| {
+                key.columns
+                    .iter()
+                    .map(|column| (key.table, column.as_str()))
+            })
+            .collect();
+        // The rows of each box: column, type and whether it is a key.
+        let rows: Vec<Vec<(&str, &str, &str)>> = self
+            .tables
+            .iter()
+            .enumerate()
+            .map(|(t, table)| {
+                table
+                    .columns
+                    .iter()
+                    .map(|column| {
+                        let foreign = keyed.contains(&(t, column.name.as_str()));
+                        let tag = match (column.primary_key, foreign) {
+                            (true, true) => "PK FK",
+                            (true, false) => "PK",
+                            (false, true) => "FK",
+                            (false, false) => "",
+                        };
+                        (column.name.as_str(), column.ty.as_str(), tag)
+                    })
+                    .filter(|(_, _, tag)| match self.detail {
+                        Detail::Names => false,
+                        Detail::Keys => !tag.is_empty(),
+                        Detail::Columns => true,
+                    })
+                    .collect()
+            })
+            .collect();
+        let widest = |field: usize| {
+            rows.iter()
+                .flatten()
+                .map(|&(name, ty, tag)| width::width([name, ty, tag][field]))
+                .max()
+                .unwrap_or_default()
+        };
+        let (name_width, type_width, tag_width) = (widest(0), widest(1), widest(2));
+        let inner = name_width + 2 + type_width + if tag_width > 0 { 2 + tag_width } else { 0 };
+        let title_width = self
+            .tables
+            .iter()
+            .map(|table| width::width(&table.name))
+            .max()
+            .unwrap_or_default();
+        // `┌─ name ─┐` and `│ column  type  PK │`.
+        let box_width = (inner + 4).max(title_width + 6);
+
+        let mut canvas: Vec<Vec<Cell>> = Vec::new();
+        // Where each table's box starts, and the row of each column shown.
+        let mut tops = Vec::new();
+        let mut column_rows: HashMap<(usize, &str), usize> = HashMap::new();
+        for (t, table) in self.tables.iter().enumerate() {
+            if t > 0 {
+                canvas.push(Vec::new());
+            }
+            tops.push(canvas.len());
+            let mut top = Vec::new();
+            put(&mut top, "┌─ ", Part::Border);
+            put(&mut top, &table.name, Part::Title);
+            put(&mut top, " ", Part::Border);
+            let rest = box_width - 1 - top.len();
+            put(&mut top, &"─".repeat(rest), Part::Border);
+            put(&mut top, "┐", Part::Border);
+            canvas.push(top);
+            for (name, ty, tag) in &rows[t] {
+                column_rows.insert((t, name), canvas.len());
+                let mut line = Vec::new();
+                put(&mut line, "│ ", Part::Border);
+                put(
+                    &mut line,
+                    &width::pad(name, name_width, false),
+                    Part::Column,
+                );
+                put(&mut line, "  ", Part::Column);
+                put(&mut line, &width::pad(ty, type_width, false), Part::Type);
+                if tag_width > 0 {
+                    put(&mut line, "  ", Part::Column);
+                    put(&mut line, &width::pad(tag, tag_width, false), Part::Tag);
+                }
+                let rest = box_width - 1 - line.len();
+                put(&mut line, &" ".repeat(rest), Part::Column);
+                put(&mut line, "│", Part::Border);
+                canvas.push(line);
+            }
+            let mut bottom = Vec::new();
+            put(&mut bottom, "└", Part::Border);
+            put(&mut bottom, &"─".repeat(box_width - 2), Part::Border);
+            put(&mut bottom, "┘", Part::Border);
+            canvas.push(bottom);
+        }
+
+        // Each key runs from its first column to the first it references,
+        // or from title to title when those aren't shown, in a lane of its
+        // own to the right of the boxes; shorter wires get the lanes closer
+        // in.
+        let anchor = |table: usize, column: Option<&String>| {
+            column
+                .and_then(|column| column_rows.get(&(table, column.as_str())))
+                .copied()
+                .unwrap_or(tops[table])
+        };
+        let mut wires: Vec<(usize, usize)> = self
+            .keys
+            .iter()
+            .map(|key| {
+                (
+                    anchor(key.table, key.columns.first()),
+                    anchor(key.references, key.referenced.first()),
+                )
+            })
+            .filter(|(from, to)| from != to)
+            .collect();
+        wires.sort_by_key(|(from, to)| from.abs_diff(*to));
+        let mut lanes: Vec<Vec<(usize, usize)>> = Vec::new();
+        // Which ways the wire goes out of each cell: up, down, left, right.
+        let mut links: HashMap<(usize, usize), u8> = HashMap::new();
+        let mut arrows = BTreeSet::new();
+        for (from, to) in wires {
+            let (low, high) = (from.min(to), from.max(to));
+            let lane = lanes
+                .iter()
+                .position(|taken| taken.iter().all(|&(l, h)| high < l || low > h))
+                .unwrap_or_else(|| {
+                    lanes.push(Vec::new());
+                    lanes.len() - 1
+                });
+            lanes[lane].push((low, high));
+            let x = box_width + 2 + 2 * lane;
+            for row in [from, to] {
+                for column in box_width..=x {
+                    let link = links.entry((row, column)).or_default();
+                    *link |= if column > box_width { LEFT } else { 0 };
+                    *link |= if column < x { RIGHT } else { 0 };
+                }
+            }
+            for row in low..=high {
+                let link = links.entry((row, x)).or_default();
+                *link |= if row > low { UP } else { 0 } | if row < high { DOWN } else { 0 };
+            }
+            arrows.insert(to);
+            if let Some(border) = canvas[from].get_mut(box_width - 1)
+                && border.0 == '│'
+            {
+                border.0 = '├';
+            }
+        }
+        for ((row, column), link) in links {
+            let line = &mut canvas[row];
+            if line.len() <= column {
+                line.resize(column + 1, (' ', Part::Wire));
+            }
+            line[column] = (glyph(link), Part::Wire);
+        }
+        for row in arrows {
+            canvas[row][box_width] = ('◀', Part::Wire);
+        }
+        self.canvas = canvas;
+    }
+
+    pub fn render(&mut self, f: &mut Frame, area: Rect) {
+        self.area = (area.height as usize, area.width as usize);
+        self.scroll_by(0, 0);
+        let lines: Vec<Line> = self
+            .canvas
+            .iter()
+            .skip(self.row)
+            .take(area.height as usize)
+            .map(|cells| {
+                let mut spans: Vec<Span> = Vec::new();
+                let mut run = String::new();
+                let mut style = None;
+                for &(c, part) in cells.iter().skip(self.col).take(area.width as usize) {
+                    if c == '\0' {
+                        continue;
+                    }
+                    if style != Some(part) && !run.is_empty() {
+                        let part: Part = style.unwrap_or(part);
+                        spans.push(Span::styled(std::mem::take(&mut run), part.style()));
+                    }
+                    style = Some(part);
+                    run.push(c);
+                }
+                if let Some(part) = style {
+                    spans.push(Span::styled(run, part.style()));
+                }
+                Line::from(spans)
+            })
+            .collect();
+        f.render_widget(Paragraph::new(lines), area);
+    }
+}
+
+const UP: u8 = 1;
+const DOWN: u8 = 2;
+const LEFT: u8 = 4;
+const RIGHT: u8 = 8;
+
+/// The box-drawing character joining the ways out of a cell.
+fn glyph(link: u8) -> char {
+    match link {
+        l if l == UP | DOWN | LEFT | RIGHT => '┼',
+        l if l == UP | DOWN | LEFT => '┤',
+        l if l == UP | DOWN | RIGHT => '├',
+        l if l == LEFT | RIGHT | UP => '┴',
+        l if l == LEFT | RIGHT | DOWN => '┬',
+        l if l == DOWN | RIGHT => '┌',
+        l if l == DOWN | LEFT => '┐',
+        l if l == UP | RIGHT => '└',
+        l if l == UP | LEFT => '┘',
+        l if l & (UP | DOWN) != 0 => '│',
+        _ => '─',
+    }
+}
+
+/// Appends `text` to `line`, wide characters taking two cells.
+fn put(line: &mut Vec<Cell>, text: &str, part: Part) {
+    for c in text.chars() {
+        match width::width(c.encode_utf8(&mut [0; 4])) {
+            0 => {}
+            1 => line.push((c, part)),
+            _ => line.extend([(c, part), ('\0', part)]),
+        }
+    }
+}
@@ -0,0 +1,626 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Writing query results to files.
+
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use futures_util::StreamExt;
+use sqlx::PgPool;
+use sqlx::postgres::PgPoolCopyExt;
+use tokio::io::AsyncWriteExt;
+
+use crate::grid;
+use crate::results::{Column, ResultSet};
+use crate::statements::quote_ident;
+use crate::width;
+
+/// How often progress is reported while copying.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Streams the output of `query` as CSV with a header straight from the
+/// server into the file at `path`, with `COPY ... TO STDOUT`, so no rows
+/// are held in memory. Calls `progress` with the bytes written every so
+/// often and returns the total.
+pub async fn copy_csv(
+    pool: &PgPool,
+    query: &str,
+    path: &Path,
+    mut progress: impl FnMut(u64),
+) -> Result<u64, sqlx::Error> {
+    let query = query.trim().trim_end_matches(';');
+    let statement = format!("COPY ({query}\n) TO STDOUT WITH (FORMAT csv, HEADER true)");
+    let mut stream = pool.copy_out_raw(&statement).await?;
+    let mut file = tokio::io::BufWriter::new(tokio::fs::File::create(path).await?);
+    let mut written = 0;
+    let mut reported = Instant::now();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk?;
+        file.write_all(&chunk).await?;
+        written += chunk.len() as u64;
+        if reported.elapsed() >= PROGRESS_INTERVAL {
+            reported = Instant::now();
+            progress(written);
+        }
+    }
+    file.flush().await?;
+    Ok(written)
+}
+
+/// Writes `results` as CSV with a header, as `COPY ... CSV` would: `NULL` as
+/// an empty field and the empty string as `""`.
+pub fn write_csv(results: &ResultSet, out: &mut impl Write) -> io::Result<()> {
+    let field = |value: &str| {
+        if value.is_empty() || value.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", value.replace('"', "\"\""))
+        } else {
+            value.to_string()
+        }
+    };
+    let header: Vec<String> = results.columns.iter().map(|c| field(&c.name)).collect();
+    writeln!(out, "{}", header.join(","))?;
+    for row in &results.rows {
+        let cells: Vec<String> = row
+            .iter()
+            .map(|cell| cell.map(field).unwrap_or_default())
+            .collect();
+        writeln!(out, "{}", cells.join(","))?;
+    }
+    Ok(())
+}
+
+/// Writes `results` as an aligned table, as psql prints them, with numbers
+/// to the right and the row count at the end. Cells are kept to one line as
+/// in the grid.
+pub fn write_table(results: &ResultSet, out: &mut impl Write) -> io::Result<()> {
+    let rows: Vec<Vec<String>> = results
+        .rows
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|cell| cell.map_or("NULL".into(), grid::display))
+                .collect()
+        })
+        .collect();
+    let widths: Vec<usize> = results
+        .columns
+        .iter()
+        .enumerate()
+        .map(|(index, column)| {
+            rows.iter()
+                .map(|row| width::width(&row[index]))
+                .fold(width::width(&column.name), usize::max)
+        })
+        .collect();
+
+    let header: Vec<String> = results
+        .columns
+        .iter()
+        .zip(&widths)
+        .map(|(column, &width)| {
+            let padding = width - width::width(&column.name);
+            let left = " ".repeat(padding / 2);
+            format!("{left}{}{}", column.name, " ".repeat(padding - padding / 2))
+        })
+        .collect();
+    writeln!(out, " {}", header.join(" | ").trim_end())?;
+    let rule: Vec<String> = widths.iter().map(|&width| "-".repeat(width + 2)).collect();
+    writeln!(out, "{}", rule.join("+"))?;
+    for row in &rows {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(&results.columns)
+            .zip(&widths)
+            .map(|((cell, column), &width)| width::pad(cell, width, column.is_numeric()))
+            .collect();
+        writeln!(out, " {}", cells.join(" | ").trim_end())?;
+    }
+    match results.rows.len() {
+        1 => writeln!(out, "(1 row)"),
+        rows => writeln!(out, "({rows} rows)"),
+    }
+}
+
+/// How a value goes into a spreadsheet cell.
+enum XlsxCell {
+    Number(String),
+    Bool(bool),
+    /// Days since 1899-12-30, formatted with style `style`.
+    Date(f64, usize),
+    Text(String),
+}
+
+/// Style indexes in `XLSX_STYLES`.
+const XLSX_HEADER: usize = 1;
+const XLSX_DATE: usize = 2;
+const XLSX_TIMESTAMP: usize = 3;
+
+const XLSX_STYLES: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<styleSheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><numFmts count="2"><numFmt numFmtId="164" formatCode="yyyy\-mm\-dd"/><numFmt numFmtId="165" formatCode="yyyy\-mm\-dd\ hh:mm:ss"/></numFmts><fonts count="2"><font><sz val="11"/><name val="Calibri"/></font><font><b/><sz val="11"/><name val="Calibri"/></font></fonts><fills count="2"><fill><patternFill patternType="none"/></fill><fill><patternFill patternType="gray125"/></fill></fills><borders count="1"><border><left/><right/><top/><bottom/><diagonal/></border></borders><cellStyleXfs count="1"><xf numFmtId="0" fontId="0" fillId="0" borderId="0"/></cellStyleXfs><cellXfs count="4"><xf numFmtId="0" fontId="0" fillId="0" borderId="0" xfId="0"/><xf numFmtId="0" fontId="1" fillId="0" borderId="0" xfId="0" applyFont="1"/><xf numFmtId="164" fontId="0" fillId="0" borderId="0" xfId="0" applyNumberFormat="1"/><xf numFmtId="165" fontId="0" fillId="0" borderId="0" xfId="0" applyNumberFormat="1"/></cellXfs></styleSheet>"#;
+
+const XLSX_CONTENT_TYPES: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/><Override PartName="/xl/worksheets/sheet1.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/><Override PartName="/xl/styles.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.styles+xml"/></Types>"#;
+
+const XLSX_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/></Relationships>"#;
+
+const XLSX_WORKBOOK: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><sheets><sheet name="Results" sheetId="1" r:id="rId1"/></sheets></workbook>"#;
+
+const XLSX_WORKBOOK_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet1.xml"/><Relationship Id="rId2" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles" Target="styles.xml"/></Relationships>"#;
+
+/// Widest a column gets, in characters, however long its values.
+const XLSX_MAX_WIDTH: usize = 60;
+
+/// `text` escaped for XML, without the control characters XML can't hold.
+pub(crate) fn xml_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c.is_control() => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// `A` for column 0, `Z`, `AA` and so on.
+fn column_letters(mut index: usize) -> String {
+    let mut letters = Vec::new();
+    loop {
+        letters.push(b'A' + (index % 26) as u8);
+        if index < 26 {
+            break;
+        }
+        index = index / 26 - 1;
+    }
+    letters.iter().rev().map(|&c| c as char).collect()
+}
+
+/// Days since 1899-12-30, which spreadsheets count dates in.
+fn spreadsheet_days(time: chrono::NaiveDateTime) -> f64 {
+    let epoch = chrono::NaiveDate::from_ymd_opt(1899, 12, 30)
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .unwrap_or_default();
+    (time - epoch).num_milliseconds() as f64 / 86_400_000.0
+}
+
+fn xlsx_cell(ty: &str, value: &str) -> XlsxCell {
+    use chrono::{DateTime, NaiveDate, NaiveDateTime};
+
+    let number = || value.parse::<f64>().ok().filter(|n| n.is_finite());
+    match ty {
+        "int2" | "int4" | "int8" | "float4" | "float8" | "numeric" | "oid"
+            if number().is_some() =>
+        {
+            XlsxCell::Number(value.to_string())
+        }
+        "bool" => XlsxCell::Bool(value == "t" || value == "true"),
+        "date" => match NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+            Ok(date) => XlsxCell::Date(
+                spreadsheet_days(date.and_time(Default::default())),
+                XLSX_DATE,
+            ),
+            Err(_) => XlsxCell::Text(value.to_string()),
+        },
+        // The time as shown, in whatever zone the server wrote it in.
+        "timestamp" | "timestamptz" => {
+            match DateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f%#z")
+                .map(|time| time.naive_local())
+                .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f"))
+            {
+                Ok(time) => XlsxCell::Date(spreadsheet_days(time), XLSX_TIMESTAMP),
+                Err(_) => XlsxCell::Text(value.to_string()),
+            }
+        }
+        _ => XlsxCell::Text(value.to_string()),
+    }
+}
+
+fn write_xlsx_sheet(results: &ResultSet, out: &mut impl Write) -> io::Result<()> {
+    let widths: Vec<usize> = results
+        .columns
+        .iter()
+        .enumerate()
+        .map(|(index, column)| {
+            results
+                .rows
+                .iter()
+                .filter_map(|row| row.get(index))
+                .map(|value| width::width(&grid::display(value)))
+                .fold(width::width(&column.name), usize::max)
+                .min(XLSX_MAX_WIDTH)
+        })
+        .collect();
+    write!(
+        out,
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><sheetViews><sheetView workbookViewId="0"><pane ySplit="1" topLeftCell="A2" activePane="bottomLeft" state="frozen"/></sheetView></sheetViews>"#
+    )?;
+    if !widths.is_empty() {
+        write!(out, "<cols>")?;
+        for (index, width) in widths.iter().enumerate() {
+            write!(
+                out,
+                r#"<col min="{n}" max="{n}" width="{}" customWidth="1"/>"#,
+                width + 2,
+                n = index + 1
+            )?;
+        }
+        write!(out, "</cols>")?;
+    }
+    write!(out, r#"<sheetData><row r="1">"#)?;
+    for (index, column) in results.columns.iter().enumerate() {
+        write!(
+            out,
+            r#"<c r="{}1" s="{XLSX_HEADER}" t="inlineStr"><is><t>{}</t></is></c>"#,
+            column_letters(index),
+            xml_escape(&column.name)
+        )?;
+    }
+    write!(out, "</row>")?;
+    for (number, row) in results.rows.iter().enumerate() {
+        let number = number + 2;
+        write!(out, r#"<row r="{number}">"#)?;
+        for (index, (cell, column)) in row.iter().zip(&results.columns).enumerate() {
+            let Some(value) = cell else {
+                continue;
+            };
+            let at = format!("{}{number}", column_letters(index));
+            match xlsx_cell(&column.ty, value) {
+                XlsxCell::Number(value) => write!(out, r#"<c r="{at}"><v>{value}</v></c>"#)?,
+                XlsxCell::Bool(value) => {
+                    write!(out, r#"<c r="{at}" t="b"><v>{}</v></c>"#, u8::from(value))?
+                }
+                XlsxCell::Date(days, style) => {
+                    write!(out, r#"<c r="{at}" s="{style}"><v>{days}</v></c>"#)?
+                }
+                XlsxCell::Text(value) => write!(
+                    out,
+                    r#"<c r="{at}" t="inlineStr"><is><t xml:space="preserve">{}</t></is></c>"#,
+                    xml_escape(&value)
+                )?,
+            }
+        }
+        write!(out, "</row>")?;
+    }
+    write!(out, "</sheetData></worksheet>")
+}
+
+/// Writes `results` as an Excel workbook with one sheet: numbers, booleans
+/// and dates as such, everything else as text, with a bold header row
+/// that stays in view and columns as wide as their values.
+pub fn write_xlsx(results: &ResultSet, out: &mut Vec<u8>) -> io::Result<()> {
+    use zip::write::SimpleFileOptions;
+
+    let mut zip = zip::ZipWriter::new(io::Cursor::new(out));
+    let options = SimpleFileOptions::default();
+    for (name, contents) in [
+        ("[Content_Types].xml", XLSX_CONTENT_TYPES),
+        ("_rels/.rels", XLSX_RELS),
+        ("xl/workbook.xml", XLSX_WORKBOOK),
+        ("xl/_rels/workbook.xml.rels", XLSX_WORKBOOK_RELS),
+        ("xl/styles.xml", XLSX_STYLES),
+    ] {
+        zip.start_file(name, options)?;
+        zip.write_all(contents.as_bytes())?;
+    }
+    zip.start_file("xl/worksheets/sheet1.xml", options)?;
+    let mut sheet = io::BufWriter::new(&mut zip);
+    write_xlsx_sheet(results, &mut sheet)?;
+    sheet.flush()?;
+    drop(sheet);
+    zip.finish()?;
+    Ok(())
+}
+
+const HTML_STYLE: &str = "\
+body { font-family: system-ui, sans-serif; margin: 1.5em; color: #222; }
+table { border-collapse: collapse; font-size: 14px; }
+caption { caption-side: bottom; padding-top: 0.5em; color: #777; text-align: left; }
+th, td { border: 1px solid #ddd; padding: 4px 8px; vertical-align: top; white-space: pre-wrap; }
+th { background: #f3f3f3; text-align: left; position: sticky; top: 0; }
+tr:nth-child(even) td { background: #fafafa; }
+td.number { text-align: right; font-variant-numeric: tabular-nums; }
+td.null { color: #aaa; font-style: italic; }
+th.sortable { cursor: pointer; user-select: none; }
+th[data-order=asc]::after { content: \" \\25B2\"; }
+th[data-order=desc]::after { content: \" \\25BC\"; }
+";
+
+/// Sorts the table by a column when its header is clicked, as numbers if
+/// both values are, with `NULL`s last.
+const HTML_SORT: &str = "\
+document.querySelectorAll('th.sortable').forEach(function (th, col) {
+  th.addEventListener('click', function () {
+    var body = th.closest('table').tBodies[0];
+    var desc = th.dataset.order === 'asc';
+    th.parentNode.querySelectorAll('th').forEach(function (other) { delete other.dataset.order; });
+    th.dataset.order = desc ? 'desc' : 'asc';
+    var key = function (row) {
+      var cell = row.cells[col];
+      return cell.classList.contains('null') ? null : cell.textContent;
+    };
+    var rows = Array.from(body.rows).sort(function (a, b) {
+      var x = key(a), y = key(b);
+      if (x === null || y === null) return (x === null) - (y === null);
+      var n = parseFloat(x), m = parseFloat(y);
+      var order = !isNaN(n) && !isNaN(m) && isFinite(x) && isFinite(y) ? n - m : x.localeCompare(y);
+      return desc ? -order : order;
+    });
+    rows.forEach(function (row) { body.appendChild(row); });
+  });
+});
+";
+
+/// Writes `results` as a self-contained HTML page with one table, numbers
+/// to the right, with a script to sort by a column if `sortable`.
+pub fn write_html(results: &ResultSet, sortable: bool, out: &mut impl Write) -> io::Result<()> {
+    writeln!(out, "<!DOCTYPE html>")?;
+    writeln!(out, "<html>\n<head>\n<meta charset=\"utf-8\">")?;
+    writeln!(out, "<title>Query results</title>")?;
+    writeln!(out, "<style>\n{HTML_STYLE}</style>\n</head>\n<body>")?;
+    write_html_table(results, sortable, out)?;
+    if sortable {
+        writeln!(out, "<script>\n{HTML_SORT}</script>")?;
+    }
+    writeln!(out, "</body>\n</html>")
+}
+
+/// Writes just the `<table>` of [`write_html`], for a page of one's own.
+pub fn write_html_table(
+    results: &ResultSet,
+    sortable: bool,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    writeln!(out, "<table>")?;
+    match results.rows.len() {
+        1 => writeln!(out, "<caption>1 row</caption>")?,
+        rows => writeln!(out, "<caption>{rows} rows</caption>")?,
+    }
+    write!(out, "<thead>\n<tr>")?;
+    let class = if sortable { " class=\"sortable\"" } else { "" };
+    for column in &results.columns {
+        write!(
+            out,
+            "<th{class} title=\"{}\">{}</th>",
+            xml_escape(&column.ty),
+            xml_escape(&column.name)
+        )?;
+    }
+    writeln!(out, "</tr>\n</thead>\n<tbody>")?;
+    for row in &results.rows {
+        write!(out, "<tr>")?;
+        for (cell, column) in row.iter().zip(&results.columns) {
+            match cell {
+                None => write!(out, "<td class=\"null\">NULL</td>")?,
+                Some(value) if column.is_numeric() => {
+                    write!(out, "<td class=\"number\">{}</td>", xml_escape(value))?
+                }
+                Some(value) => write!(out, "<td>{}</td>", xml_escape(value))?,
+            }
+        }
+        writeln!(out, "</tr>")?;
+    }
+    writeln!(out, "</tbody>\n</table>")
+}
+
+/// Writes `results` as a Markdown table, numbers to the right. Pipes are
+/// escaped and line breaks become `<br>`, a cell having to stay on its
+/// line; `NULL` is left empty.
+pub fn write_markdown(results: &ResultSet, out: &mut impl Write) -> io::Result<()> {
+    let escape = |text: &str| {
+        text.replace('|', "\\|")
+            .replace("\r\n", "<br>")
+            .replace('\n', "<br>")
+    };
+    let names: Vec<String> = results.columns.iter().map(|c| escape(&c.name)).collect();
+    writeln!(out, "| {} |", names.join(" | "))?;
+    let rule: Vec<&str> = results
+        .columns
+        .iter()
+        .map(|column| if column.is_numeric() { "---:" } else { "---" })
+        .collect();
+    writeln!(out, "| {} |", rule.join(" | "))?;
+    for row in &results.rows {
+        let cells: Vec<String> = row.iter().map(|cell| escape(cell.unwrap_or(""))).collect();
+        writeln!(out, "| {} |", cells.join(" | "))?;
+    }
+    Ok(())
+}
+
+/// `value` as a number literal, if `column` holds numbers and it is one
+/// that both Python and SQL can read as it is.
+fn number_literal<'a>(column: &Column, value: &'a str) -> Option<&'a str> {
+    let finite = value.parse::<f64>().is_ok_and(f64::is_finite);
+    (column.is_numeric() && column.ty != "money" && finite).then_some(value)
+}
+
+fn python_literal(column: &Column, cell: Option<&str>) -> String {
+    let Some(value) = cell else {
+        return "None".into();
+    };
+    if let Some(number) = number_literal(column, value) {
+        return number.to_string();
+    }
+    match (column.ty.as_str(), value) {
+        ("bool", "t") => return "True".into(),
+        ("bool", "f") => return "False".into(),
+        _ => {}
+    }
+    let mut literal = String::from('"');
+    for c in value.chars() {
+        match c {
+            '\\' => literal.push_str("\\\\"),
+            '"' => literal.push_str("\\\""),
+            '\n' => literal.push_str("\\n"),
+            '\r' => literal.push_str("\\r"),
+            '\t' => literal.push_str("\\t"),
+            c => literal.push(c),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+/// `results` as a `pandas.DataFrame(...)` constructor, one list per
+/// column. Numbers and booleans stay so, `NULL` is `None` and everything
+/// else a string.
+pub fn to_pandas(results: &ResultSet) -> String {
+    let mut out = String::from("pandas.DataFrame({\n");
+    for (index, column) in results.columns.iter().enumerate() {
+        let values: Vec<String> = results
+            .rows
+            .iter()
+            .map(|row| python_literal(column, row.get(index)))
+            .collect();
+        out.push_str(&format!(
+            "    {}: [{}],\n",
+            python_literal(&Column::new("", "text"), Some(&column.name)),
+            values.join(", ")
+        ));
+    }
+    out.push_str("})\n");
+    out
+}
+
+pub(crate) fn sql_literal(column: &Column, cell: Option<&str>, cast: bool) -> String {
+    let Some(value) = cell else {
+        return "NULL".into();
+    };
+    if let Some(number) = number_literal(column, value) {
+        return number.to_string();
+    }
+    match (column.ty.as_str(), value) {
+        ("bool", "t") => return "TRUE".into(),
+        ("bool", "f") => return "FALSE".into(),
+        _ => {}
+    }
+    let literal = format!("'{}'", value.replace('\'', "''"));
+    match column.ty.as_str() {
+        "text" | "varchar" | "bpchar" | "name" | "unknown" | "" => literal,
+        ty if cast => format!("{literal}::{ty}"),
+        _ => literal,
+    }
+}
+
+/// `results` as a `VALUES` list. Literals of types other than text are
+/// cast in the first row, which is enough for the server to type the
+/// columns.
+pub fn to_values(results: &ResultSet) -> String {
+    let rows: Vec<String> = results
+        .rows
+        .iter()
+        .enumerate()
+        .map(|(number, row)| {
+            let values: Vec<String> = row
+                .iter()
+                .zip(&results.columns)
+                .map(|(cell, column)| sql_literal(column, cell, number == 0))
+                .collect();
+            format!("    ({})", values.join(", "))
+        })
+        .collect();
+    format!("VALUES\n{}\n", rows.join(",\n"))
+}
+
+/// `results` as an `INSERT` into `table` for each row.
+pub fn to_inserts(results: &ResultSet, table: &str) -> String {
+    let columns: Vec<String> = results
+        .columns
+        .iter()
+        .map(|column| quote_ident(&column.name))
+        .collect();
+    let columns = columns.join(", ");
+    results
+        .rows
+        .iter()
+        .map(|row| {
+            let values: Vec<String> = row
+                .iter()
+                .zip(&results.columns)
+                .map(|(cell, column)| sql_literal(column, cell, false))
+                .collect();
+            format!(
+                "INSERT INTO {table} ({columns}) VALUES ({});\n",
+                values.join(", ")
+            )
+        })
+        .collect()
+}
+
+/// The condition for the rows of `results` by the values of their `key`
+/// columns, for `DELETE` and `UPDATE`.
+fn where_key(results: &ResultSet, key: &[String]) -> Result<String, String> {
+    if key.is_empty() {
+        return Err("No primary key to tell the rows apart by".into());
+    }
+    let columns = key
+        .iter()
+        .map(|name| {
+            results
+                .column_index(name)
+                .ok_or_else(|| format!("No {name} column in the rows"))
+        })
+        .collect::<Result<Vec<usize>, String>>()?;
+    let names: Vec<String> = key.iter().map(|name| quote_ident(name)).collect();
+    let rows: Vec<String> = (0..results.rows.len())
+        .map(|row| {
+            let values: Vec<String> = columns
+                .iter()
+                .map(|col| sql_literal(&results.columns[*col], results.rows.cell(row, *col), false))
+                .collect();
+            format!("    ({})", values.join(", "))
+        })
+        .collect();
+    Ok(format!(
+        "WHERE ({}) IN (\n{}\n)",
+        names.join(", "),
+        rows.join(",\n")
+    ))
+}
+
+/// A `DELETE` of the rows of `results` from `table`, which has the primary
+/// key `key`.
+pub fn to_delete(results: &ResultSet, table: &str, key: &[String]) -> Result<String, String> {
+    Ok(format!(
+        "DELETE FROM {table}\n{};\n",
+        where_key(results, key)?
+    ))
+}
+
+/// An `UPDATE` of the rows of `results` in `table`, setting the column
+/// `col` to its value in the first of them, to be edited.
+pub fn to_update(
+    results: &ResultSet,
+    table: &str,
+    key: &[String],
+    col: usize,
+) -> Result<String, String> {
+    let column = &results.columns[col];
+    let value = sql_literal(column, results.rows.cell(0, col), false);
+    Ok(format!(
+        "UPDATE {table}\nSET {} = {value}\n{};\n",
+        quote_ident(&column.name),
+        where_key(results, key)?
+    ))
+}
@@ -0,0 +1,132 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Statement skeletons for `:generate`. Values are `$n` placeholders with the
+//! column type alongside, ready to be filled in.
+
+use std::str::FromStr;
+
+use crate::db::catalog::Column;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Skeleton {
+    Select,
+    Insert,
+    Update,
+}
+
+impl FromStr for Skeleton {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "select" => Ok(Self::Select),
+            "insert" => Ok(Self::Insert),
+            "update" => Ok(Self::Update),
+            _ => Err(format!(
+                "Can't generate \"{s}\", use select, insert or update"
+            )),
+        }
+    }
+}
+
+fn describe(column: &Column) -> String {
+    if column.not_null {
+        format!("{} NOT NULL", column.ty)
+    } else {
+        column.ty.clone()
+    }
+}
+
+/// `WHERE pk = $n AND ...`, numbering placeholders from `next`, or a
+/// placeholder condition if the table has no primary key.
+fn where_clause(columns: &[Column], mut next: usize) -> String {
+    let keys: Vec<String> = columns
+        .iter()
+        .filter(|column| column.primary_key)
+        .map(|column| {
+            let condition = format!("{} = ${next}", column.name);
+            next += 1;
+            condition
+        })
+        .collect();
+    if keys.is_empty() {
+        "WHERE <condition>".into()
+    } else {
+        format!("WHERE {}", keys.join(" AND "))
+    }
+}
+
+/// Renders a `kind` statement for `table`, listing every column.
+pub fn generate(kind: Skeleton, table: &str, columns: &[Column]) -> String {
+    match kind {
+        Skeleton::Select => {
+            let list: Vec<String> = columns
+                .iter()
+                .map(|column| format!("    {}", column.name))
+                .collect();
+            format!(
+                "SELECT\n{}\nFROM {table}\n{};",
+                list.join(",\n"),
+                where_clause(columns, 1)
+            )
+        }
+        Skeleton::Insert => {
+            let writable: Vec<&Column> = columns.iter().filter(|c| !c.generated).collect();
+            let names: Vec<String> = writable.iter().map(|c| format!("    {}", c.name)).collect();
+            let values: Vec<String> = writable
+                .iter()
+                .enumerate()
+                .map(|(i, column)| {
+                    let comma = if i + 1 < writable.len() { "," } else { "" };
+                    format!(
+                        "    ${}{comma} -- {} {}",
+                        i + 1,
+                        column.name,
+                        describe(column)
+                    )
+                })
+                .collect();
+            format!(
+                "INSERT INTO {table} (\n{}\n) VALUES (\n{}\n);",
+                names.join(",\n"),
+                values.join("\n")
+            )
+        }
+        Skeleton::Update => {
+            let writable: Vec<&Column> = columns
+                .iter()
+                .filter(|c| !c.generated && !c.primary_key)
+                .collect();
+            let assignments: Vec<String> = writable
+                .iter()
+                .enumerate()
+                .map(|(i, column)| {
+                    let comma = if i + 1 < writable.len() { "," } else { "" };
+                    format!(
+                        "    {} = ${}{comma} -- {}",
+                        column.name,
+                        i + 1,
+                        describe(column)
+                    )
+                })
+                .collect();
+            format!(
+                "UPDATE {table} SET\n{}\n{};",
+                assignments.join("\n"),
+                where_clause(columns, writable.len() + 1)
+            )
+        }
+    }
+}
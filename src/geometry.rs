@@ -0,0 +1,421 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! PostGIS values, which the server sends as hex EWKB: read into shapes,
+//! shown in the grid as a shortened WKT, and plotted in characters by the
+//! viewer `Enter` opens on one, to see roughly where and what they are.
+
+use std::fmt::Write;
+
+use ratatui::{
+    Frame,
+    layout::{Constraint, Flex, Layout},
+    style::{Color, Style},
+    text::Line,
+    widgets::{Block, Borders, Clear, Padding, Paragraph},
+};
+
+use crate::results::Column;
+
+/// Coordinates listed per run in the grid before the rest are cut.
+const SUMMARY_COORDS: usize = 3;
+
+/// `x y`, and `z` and `m` if the value has them.
+pub type Coord = Vec<f64>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Geometry {
+    /// `None` for `POINT EMPTY`.
+    Point(Option<Coord>),
+    LineString(Vec<Coord>),
+    /// The outer ring, then the holes.
+    Polygon(Vec<Vec<Coord>>),
+    /// `MULTIPOINT`, `MULTILINESTRING`, `MULTIPOLYGON` or
+    /// `GEOMETRYCOLLECTION`, by that name.
+    Multi(&'static str, Vec<Geometry>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shape {
+    pub srid: Option<u32>,
+    /// `""`, `" Z"`, `" M"` or `" ZM"`, as WKT writes it after the name.
+    pub dims: &'static str,
+    pub geometry: Geometry,
+}
+
+/// The shape in `text` if `column` holds geometries or geographies. Types
+/// the driver has no name for, as PostGIS ones are to it, are taken for
+/// geometries when the whole value reads as one.
+pub fn parse(column: &Column, text: &str) -> Option<Shape> {
+    match column.ty.as_str() {
+        "geometry" | "geography" | "?" => from_hex(text),
+        _ => None,
+    }
+}
+
+/// A shape from hex EWKB (or plain WKB), all of it.
+pub fn from_hex(hex: &str) -> Option<Shape> {
+    if hex.len() < 10 || !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    let bytes = (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    let mut reader = Reader {
+        bytes: &bytes,
+        at: 0,
+        little: true,
+    };
+    let (srid, dims, geometry) = reader.geometry(0)?;
+    (reader.at == bytes.len()).then_some(Shape {
+        srid,
+        dims,
+        geometry,
+    })
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    at: usize,
+    little: bool,
+}
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let bytes = self.bytes.get(self.at..self.at + N)?.try_into().ok()?;
+        self.at += N;
+        Some(bytes)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        let bytes = self.take()?;
+        Some(match self.little {
+            true => u32::from_le_bytes(bytes),
+            false => u32::from_be_bytes(bytes),
+        })
+    }
+
+    fn f64(&mut self) -> Option<f64> {
+        let bytes = self.take()?;
+        Some(match self.little {
+            true => f64::from_le_bytes(bytes),
+            false => f64::from_be_bytes(bytes),
+        })
+    }
+
+    /// A count of things at least `size` bytes each, no more than are left.
+    fn count(&mut self, size: usize) -> Option<usize> {
+        let count = self.u32()? as usize;
+        (count.checked_mul(size)? <= self.bytes.len() - self.at).then_some(count)
+    }
+
+    fn coord(&mut self, dims: usize) -> Option<Coord> {
+        (0..dims).map(|_| self.f64()).collect()
+    }
+
+    fn coords(&mut self, dims: usize) -> Option<Vec<Coord>> {
+        let count = self.count(dims * 8)?;
+        (0..count).map(|_| self.coord(dims)).collect()
+    }
+
+    /// A geometry and what its header says, nested `depth` deep.
+    fn geometry(&mut self, depth: usize) -> Option<(Option<u32>, &'static str, Geometry)> {
+        if depth > 16 {
+            return None;
+        }
+        self.little = match self.take::<1>()? {
+            [0] => false,
+            [1] => true,
+            _ => return None,
+        };
+        let header = self.u32()?;
+        // EWKB flags the extra dimensions and the SRID in the high bits, ISO
+        // WKB adds 1000, 2000 or 3000 to the type.
+        let (z, m) = match (header & 0xffff) / 1000 {
+            1 => (true, false),
+            2 => (false, true),
+            3 => (true, true),
+            _ => (header & 0x8000_0000 != 0, header & 0x4000_0000 != 0),
+        };
+        let srid = match header & 0x2000_0000 {
+            0 => None,
+            _ => Some(self.u32()?),
+        };
+        let dims = match (z, m) {
+            (false, false) => "",
+            (true, false) => " Z",
+            (false, true) => " M",
+            (true, true) => " ZM",
+        };
+        let size = 2 + z as usize + m as usize;
+        let geometry = match (header & 0xffff) % 1000 {
+            1 => {
+                let coord = self.coord(size)?;
+                Geometry::Point((!coord.iter().all(|n| n.is_nan())).then_some(coord))
+            }
+            2 => Geometry::LineString(self.coords(size)?),
+            3 => {
+                let rings = self.count(4)?;
+                Geometry::Polygon(
+                    (0..rings)
+                        .map(|_| self.coords(size))
+                        .collect::<Option<_>>()?,
+                )
+            }
+            kind @ 4..=7 => {
+                let name = [
+                    "MULTIPOINT",
+                    "MULTILINESTRING",
+                    "MULTIPOLYGON",
+                    "GEOMETRYCOLLECTION",
+                ][kind as usize - 4];
+                let parts = self.count(5)?;
+                let parts = (0..parts)
+                    .map(|_| Some(self.geometry(depth + 1)?.2))
+                    .collect::<Option<_>>()?;
+                Geometry::Multi(name, parts)
+            }
+            _ => return None,
+        };
+        Some((srid, dims, geometry))
+    }
+}
+
+impl Geometry {
+    fn name(&self) -> &'static str {
+        match self {
+            Geometry::Point(_) => "POINT",
+            Geometry::LineString(_) => "LINESTRING",
+            Geometry::Polygon(_) => "POLYGON",
+            Geometry::Multi(name, _) => name,
+        }
+    }
+
+    /// The WKT after the name, runs of coordinates cut after `limit`.
+    fn body(&self, limit: Option<usize>) -> String {
+        fn coord(coord: &Coord) -> String {
+            let numbers: Vec<String> = coord.iter().map(f64::to_string).collect();
+            numbers.join(" ")
+        }
+        fn run(coords: &[Coord], limit: Option<usize>) -> String {
+            let shown = limit.unwrap_or(coords.len()).min(coords.len());
+            let mut text: Vec<String> = coords[..shown].iter().map(coord).collect();
+            if shown < coords.len() {
+                text.push("…".into());
+            }
+            format!("({})", text.join(","))
+        }
+        match self {
+            Geometry::Point(None) => "EMPTY".into(),
+            Geometry::Point(Some(point)) => format!("({})", coord(point)),
+            Geometry::LineString(coords) if coords.is_empty() => "EMPTY".into(),
+            Geometry::LineString(coords) => run(coords, limit),
+            Geometry::Polygon(rings) if rings.is_empty() => "EMPTY".into(),
+            Geometry::Polygon(rings) => {
+                let rings: Vec<String> = rings.iter().map(|ring| run(ring, limit)).collect();
+                format!("({})", rings.join(","))
+            }
+            Geometry::Multi(_, parts) if parts.is_empty() => "EMPTY".into(),
+            Geometry::Multi(name, parts) => {
+                let parts: Vec<String> = parts
+                    .iter()
+                    .map(|part| match *name {
+                        "GEOMETRYCOLLECTION" => format!("{}{}", part.name(), part.body(limit)),
+                        _ => part.body(limit),
+                    })
+                    .collect();
+                format!("({})", parts.join(","))
+            }
+        }
+    }
+
+    /// Every point, vertex and segment end, `x y` first.
+    fn coords(&self) -> Vec<&Coord> {
+        match self {
+            Geometry::Point(point) => point.iter().collect(),
+            Geometry::LineString(coords) => coords.iter().collect(),
+            Geometry::Polygon(rings) => rings.iter().flatten().collect(),
+            Geometry::Multi(_, parts) => parts.iter().flat_map(Geometry::coords).collect(),
+        }
+    }
+
+    /// The runs of coordinates joined by lines, and the points on their own.
+    fn strokes<'a>(&'a self, lines: &mut Vec<&'a [Coord]>, points: &mut Vec<&'a Coord>) {
+        match self {
+            Geometry::Point(point) => points.extend(point),
+            Geometry::LineString(coords) => lines.push(coords),
+            Geometry::Polygon(rings) => lines.extend(rings.iter().map(Vec::as_slice)),
+            Geometry::Multi(_, parts) => {
+                for part in parts {
+                    part.strokes(lines, points);
+                }
+            }
+        }
+    }
+}
+
+impl Shape {
+    /// `POLYGON`, `MULTIPOINT` and so on.
+    pub fn kind(&self) -> &'static str {
+        self.geometry.name()
+    }
+
+    /// The whole of it as EWKT, `SRID=4326;POINT(1 2)`.
+    pub fn wkt(&self) -> String {
+        self.text(None)
+    }
+
+    /// What the grid shows: the EWKT with no more than a few coordinates of
+    /// each run.
+    pub fn summary(&self) -> String {
+        self.text(Some(SUMMARY_COORDS))
+    }
+
+    fn text(&self, limit: Option<usize>) -> String {
+        let mut text = String::new();
+        if let Some(srid) = self.srid {
+            let _ = write!(text, "SRID={srid};");
+        }
+        let body = self.geometry.body(limit);
+        let space = if body == "EMPTY" { " " } else { "" };
+        let _ = write!(text, "{}{}{space}{body}", self.geometry.name(), self.dims);
+        text
+    }
+
+    /// The smallest and largest `x` and `y`, `None` when empty.
+    pub fn bounds(&self) -> Option<((f64, f64), (f64, f64))> {
+        let coords = self.geometry.coords();
+        let first = coords.first()?;
+        Some(coords.iter().fold(
+            ((first[0], first[1]), (first[0], first[1])),
+            |((x0, y0), (x1, y1)), c| ((x0.min(c[0]), y0.min(c[1])), (x1.max(c[0]), y1.max(c[1]))),
+        ))
+    }
+
+    /// A rough drawing of it in `width` by `height` characters, north up and
+    /// scaled the same both ways, taking a character to be twice as tall as
+    /// it is wide: `*` for points, `+` for vertices and `-|/\` between.
+    pub fn plot(&self, width: usize, height: usize) -> Vec<String> {
+        let mut canvas = vec![vec![' '; width]; height];
+        let Some(((x0, y0), (x1, y1))) = self.bounds() else {
+            return Vec::new();
+        };
+        if width == 0 || height == 0 {
+            return Vec::new();
+        }
+        let (cols, rows) = ((width - 1) as f64, (height - 1) as f64);
+        let scale = match (x1 - x0, y1 - y0) {
+            (dx, dy) if dx > 0.0 && dy > 0.0 => (cols / dx).min(2.0 * rows / dy),
+            (dx, _) if dx > 0.0 => cols / dx,
+            (_, dy) if dy > 0.0 => 2.0 * rows / dy,
+            _ => 0.0,
+        };
+        // Centred in whatever room the other way leaves.
+        let left = (cols - (x1 - x0) * scale) / 2.0;
+        let top = (rows - (y1 - y0) * scale / 2.0) / 2.0;
+        let cell = |c: &Coord| {
+            (
+                (left + (c[0] - x0) * scale).round() as isize,
+                (top + (y1 - c[1]) * scale / 2.0).round() as isize,
+            )
+        };
+        let mut put = |(col, row): (isize, isize), c: char| {
+            if let Some(slot) = usize::try_from(row)
+                .ok()
+                .and_then(|row| canvas.get_mut(row)?.get_mut(usize::try_from(col).ok()?))
+            {
+                *slot = c;
+            }
+        };
+
+        let (mut lines, mut points) = (Vec::new(), Vec::new());
+        self.geometry.strokes(&mut lines, &mut points);
+        for run in &lines {
+            for pair in run.windows(2) {
+                let (from, to) = (cell(&pair[0]), cell(&pair[1]));
+                let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+                // A row is as tall as two columns are wide.
+                let c = match (dx.abs(), dy.abs()) {
+                    (x, y) if x >= 4 * y => '-',
+                    (x, y) if y >= x => '|',
+                    _ if (dx > 0) == (dy > 0) => '\\',
+                    _ => '/',
+                };
+                let steps = dx.abs().max(dy.abs());
+                for step in 1..steps {
+                    let t = step as f64 / steps as f64;
+                    put(
+                        (
+                            from.0 + (dx as f64 * t).round() as isize,
+                            from.1 + (dy as f64 * t).round() as isize,
+                        ),
+                        c,
+                    );
+                }
+            }
+        }
+        for coord in lines.iter().copied().flatten() {
+            put(cell(coord), '+');
+        }
+        for point in points {
+            put(cell(point), '*');
+        }
+        canvas
+            .into_iter()
+            .map(|row| row.into_iter().collect::<String>().trim_end().to_string())
+            .collect()
+    }
+
+    /// The viewer: the plot over most of the screen, and under it how far
+    /// it goes and the full WKT, cut to fit.
+    pub fn render(&self, f: &mut Frame, title: &str) {
+        let [area] = Layout::vertical([Constraint::Percentage(80)])
+            .flex(Flex::Center)
+            .areas(f.area());
+        let [area] = Layout::horizontal([Constraint::Percentage(90)])
+            .flex(Flex::Center)
+            .areas(area);
+        let block = Block::default()
+            .title(Line::from(title).centered())
+            .borders(Borders::ALL)
+            .padding(Padding::horizontal(1));
+        let inner = block.inner(area);
+        let [canvas, footer] =
+            Layout::vertical([Constraint::Fill(1), Constraint::Length(2)]).areas(inner);
+        let plot: Vec<Line> = self
+            .plot(canvas.width as usize, canvas.height as usize)
+            .into_iter()
+            .map(Line::from)
+            .collect();
+        let extent = match self.bounds() {
+            Some(((x0, y0), (x1, y1))) => format!(
+                "x {x0} … {x1}, y {y0} … {y1}, {} points",
+                self.geometry.coords().len()
+            ),
+            None => "empty".into(),
+        };
+        let footer_lines = vec![
+            Line::styled(extent, Style::default().fg(Color::DarkGray)),
+            Line::styled(self.wkt(), Style::default().fg(Color::DarkGray)),
+        ];
+        f.render_widget(Clear, area);
+        f.render_widget(block, area);
+        f.render_widget(
+            Paragraph::new(plot).style(Style::default().fg(Color::Cyan)),
+            canvas,
+        );
+        f.render_widget(Paragraph::new(footer_lines), footer);
+    }
+}
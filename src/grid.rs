@@ -0,0 +1,721 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The results grid: a cell cursor over a `ResultSet`, scrolled so the
+//! cursor stays in view, with the column header always on top.
+//!
+//! Only the columns in view are measured and drawn, so results hundreds of
+//! columns wide draw as fast as narrow ones.
+
+use std::collections::{BTreeSet, HashSet};
+
+use chrono_tz::Tz;
+use ratatui::{
+    Frame,
+    layout::{Alignment, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::Paragraph,
+};
+
+use crate::elements;
+use crate::geometry;
+use crate::redact::{self, Redactions};
+use crate::results::{Column, ResultSet};
+use crate::sparkline::{self, Sparkline};
+use crate::{values, width};
+
+/// Columns wider than this are truncated, unless widened by hand.
+const MAX_WIDTH: usize = 40;
+const MAX_RESIZED_WIDTH: usize = 200;
+/// Rows looked at to size the columns.
+const SAMPLE_ROWS: usize = 1000;
+const SEPARATOR: &str = " │ ";
+const SEPARATOR_WIDTH: usize = 3;
+
+/// Row numbers left of the grid (or line numbers left of the editor),
+/// `:set number` and `:set relativenumber`. With both, the cursor row shows
+/// its absolute number and the others their distance from it, as in vim.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Gutter {
+    pub number: bool,
+    pub relative: bool,
+}
+
+impl Gutter {
+    /// Columns taken, including the space after the numbers.
+    pub fn width(self, rows: usize) -> u16 {
+        if self.number || self.relative {
+            rows.max(1).to_string().len().max(3) as u16 + 1
+        } else {
+            0
+        }
+    }
+
+    pub fn label(self, row: usize, cursor: usize) -> String {
+        match (self.number, self.relative) {
+            (true, true) if row == cursor => format!("{}", row + 1),
+            (_, true) => format!("{}", row.abs_diff(cursor)),
+            _ => format!("{}", row + 1),
+        }
+    }
+}
+
+/// Where a visual selection started, `v` for a block of cells or `V` for
+/// whole rows. It spans from there to the cursor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Selection {
+    pub row: usize,
+    pub col: usize,
+    pub rows_only: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Grid {
+    /// Cell cursor.
+    pub row: usize,
+    pub col: usize,
+    /// First visible row and column.
+    pub scroll_row: usize,
+    pub scroll_col: usize,
+    /// `None` until the column is first shown.
+    pub widths: Vec<Option<usize>>,
+    /// Columns hidden with `zc`.
+    pub hidden: HashSet<usize>,
+    /// The first `pinned` columns stay put when scrolling sideways.
+    pub pinned: usize,
+    pub selection: Option<Selection>,
+    /// The column the rows are sorted by, and whether descending.
+    pub sort: Option<(usize, bool)>,
+    /// Cells changed since the results came, as rows and columns.
+    pub edited: BTreeSet<(usize, usize)>,
+    /// Rows marked with `m`, which yanks and exports take on their own.
+    pub marked: BTreeSet<usize>,
+    /// Columns with a sparkline under their name, `zs`.
+    pub sparklines: BTreeSet<usize>,
+    /// Where the last render put each visible column, `(column, x, width)`.
+    layout: Vec<(usize, u16, u16)>,
+    /// Where the last render put the first data row, and how many fit.
+    rows_y: u16,
+    height: usize,
+    /// Fill the area anew on the next render, which is a different size.
+    refit: bool,
+    /// Whether the widths were measured with identifiers shortened.
+    shorten_ids: bool,
+    /// The zone timestamps were measured in.
+    timezone: Option<Tz>,
+    /// Which columns were masked when the widths were measured.
+    redacted: Vec<bool>,
+}
+
+/// What the options say about how cells are shown.
+#[derive(Debug, Clone, Copy)]
+pub struct Look<'a> {
+    /// `:set shorten-ids`.
+    pub shorten_ids: bool,
+    /// `:set timezone`.
+    pub timezone: Option<Tz>,
+    /// The columns to mask, `:redact`.
+    pub redactions: &'a Redactions,
+}
+
+/// How a cell is shown on one line.
+pub(crate) fn display(cell: &str) -> String {
+    cell.replace('\n', "↵").replace('\t', " ")
+}
+
+/// How a cell of `column` is shown, arrays and composite values with their
+/// elements spaced out, geometries as WKT, intervals and ranges tidied up
+/// and, with `shorten_ids`, UUIDs and hashes cut short. Timestamps are
+/// moved to `timezone`, if set. Redacted ones are masked.
+fn shown(
+    column: &Column,
+    cell: &str,
+    shorten_ids: bool,
+    timezone: Option<Tz>,
+    redacted: bool,
+) -> String {
+    if redacted {
+        return redact::MASK.to_string();
+    }
+    if let Some(zone) = timezone
+        && let Some(moved) = values::in_zone(column, cell, zone)
+    {
+        return moved;
+    }
+    if shorten_ids && let Some(id) = values::short_id(column, cell) {
+        return id;
+    }
+    if let Some(node) = elements::parse(column, cell) {
+        return display(&node.render());
+    }
+    if let Some(shape) = geometry::parse(column, cell) {
+        return shape.summary();
+    }
+    match values::render(column, cell) {
+        Some(text) => display(&text),
+        None => display(cell),
+    }
+}
+
+/// The header of `column`, with the zone its timestamps are shown in.
+fn label(column: &Column, timezone: Option<Tz>) -> String {
+    match timezone {
+        Some(zone) if column.ty == "timestamptz" => format!("{} ({})", column.name, zone.name()),
+        _ => column.name.clone(),
+    }
+}
+
+impl Grid {
+    pub fn new(results: &ResultSet) -> Self {
+        Self {
+            widths: vec![None; results.columns.len()],
+            ..Self::default()
+        }
+    }
+
+    /// The width of column `col`, measured from the first rows the first
+    /// time it is asked for.
+    fn width(&mut self, results: &ResultSet, col: usize) -> usize {
+        if let Some(width) = self.widths[col] {
+            return width;
+        }
+        let width = results
+            .rows
+            .iter()
+            .take(SAMPLE_ROWS)
+            .filter_map(|row| row.get(col))
+            .map(|cell| {
+                let redacted = self.redacted.get(col).copied().unwrap_or(false);
+                width::width(&shown(
+                    &results.columns[col],
+                    cell,
+                    self.shorten_ids,
+                    self.timezone,
+                    redacted,
+                ))
+            })
+            .chain([
+                width::width(&label(&results.columns[col], self.timezone)),
+                "NULL".len(),
+                match self.sparklines.contains(&col) {
+                    true => sparkline::MIN_WIDTH,
+                    false => 0,
+                },
+            ])
+            .max()
+            .unwrap_or(1)
+            .min(MAX_WIDTH);
+        self.widths[col] = Some(width);
+        width
+    }
+
+    /// A grid for `results` refreshed from the same query, keeping the
+    /// cursor, scroll position and column settings of this one.
+    pub fn refreshed(&self, results: &ResultSet) -> Self {
+        let mut grid = Self::new(results);
+        if grid.widths.len() == self.widths.len() {
+            grid.hidden = self.hidden.clone();
+            grid.sort = self.sort;
+            grid.fit_sort_arrow(results);
+            grid.pinned = self.pinned;
+            grid.sparklines = self.sparklines.clone();
+            grid.col = self.col;
+            grid.scroll_col = self.scroll_col;
+        }
+        grid.row = self.row.min(results.rows.len().saturating_sub(1));
+        grid.scroll_row = self.scroll_row.min(grid.row);
+        grid
+    }
+
+    fn visible(&self) -> impl DoubleEndedIterator<Item = usize> + '_ {
+        (0..self.widths.len()).filter(|col| !self.hidden.contains(col))
+    }
+
+    /// Moves the cursor by `rows` and `cols`, staying inside the results
+    /// and skipping hidden columns.
+    pub fn move_by(&mut self, results: &ResultSet, rows: isize, cols: isize) {
+        self.row = self
+            .row
+            .saturating_add_signed(rows)
+            .min(results.rows.len().saturating_sub(1));
+        let steps = cols.unsigned_abs();
+        let col = if cols < 0 {
+            self.visible()
+                .rev()
+                .filter(|col| *col < self.col)
+                .take(steps)
+                .last()
+        } else {
+            self.visible()
+                .filter(|col| *col > self.col)
+                .take(steps)
+                .last()
+        };
+        self.col = col.unwrap_or(self.col);
+    }
+
+    pub fn first_col(&mut self) {
+        let first = self.visible().next();
+        self.col = first.unwrap_or(0);
+        self.scroll_col = 0;
+    }
+
+    pub fn last_col(&mut self) {
+        let last = self.visible().last();
+        self.col = last.unwrap_or(0);
+    }
+
+    /// Widens (or narrows) the cursor column by `delta`.
+    pub fn resize(&mut self, results: &ResultSet, delta: isize) {
+        if self.col < self.widths.len() {
+            let width = self.width(results, self.col);
+            self.widths[self.col] = Some(
+                width
+                    .saturating_add_signed(delta)
+                    .clamp(1, MAX_RESIZED_WIDTH),
+            );
+        }
+    }
+
+    /// Hides the cursor column and moves to the next one. The last visible
+    /// column stays.
+    pub fn hide(&mut self) -> bool {
+        if self.visible().count() <= 1 {
+            return false;
+        }
+        self.hidden.insert(self.col);
+        let next = self.visible().find(|col| *col > self.col);
+        self.col = next.or_else(|| self.visible().last()).unwrap_or(0);
+        true
+    }
+
+    pub fn show_all(&mut self) {
+        self.hidden.clear();
+    }
+
+    /// Sorts the rows by the cursor column, ascending first and descending
+    /// if it already was.
+    /// Returns where each row came from, as [`ResultSet::sort`] does.
+    pub fn sort(&mut self, results: &mut ResultSet) -> Vec<usize> {
+        let descending = self.sort == Some((self.col, false));
+        self.sort = Some((self.col, descending));
+        let order = results.sort(self.col, descending);
+        self.fit_sort_arrow(results);
+        // The marks go along with their rows.
+        self.marked = order
+            .iter()
+            .enumerate()
+            .filter(|(_, from)| self.marked.contains(from))
+            .map(|(row, _)| row)
+            .collect();
+        order
+    }
+
+    /// Shows a sparkline under the name of the cursor column, or stops.
+    /// Returns it, `None` if it stopped or the column has no numbers.
+    pub fn toggle_sparkline(&mut self, results: &ResultSet) -> Option<Sparkline> {
+        self.widths[self.col] = None;
+        if !self.sparklines.insert(self.col) {
+            self.sparklines.remove(&self.col);
+            return None;
+        }
+        let sparkline = Sparkline::of(results, self.col);
+        if sparkline.is_none() {
+            self.sparklines.remove(&self.col);
+        }
+        sparkline
+    }
+
+    /// Marks the rows of the selection, or the row under the cursor, or
+    /// takes its mark away. Returns how many rows are marked.
+    pub fn toggle_mark(&mut self) -> usize {
+        match self.selected() {
+            Some((rows, _)) => {
+                self.marked.extend(rows);
+                self.selection = None;
+            }
+            None if self.marked.remove(&self.row) => {}
+            None => {
+                self.marked.insert(self.row);
+            }
+        }
+        self.marked.len()
+    }
+
+    /// Marks the rows as sorted by `sort`, a column and whether descending,
+    /// as they came from the server.
+    pub fn sorted_by(&mut self, results: &ResultSet, sort: Option<(usize, bool)>) {
+        self.sort = sort;
+        self.fit_sort_arrow(results);
+    }
+
+    /// Makes room for the arrow in the header of the sorted column.
+    fn fit_sort_arrow(&mut self, results: &ResultSet) {
+        if let Some((col, _)) = self.sort {
+            let name = width::width(&label(&results.columns[col], self.timezone)) + 2;
+            let width = self.width(results, col);
+            self.widths[col] = Some(width.max(name.min(MAX_WIDTH)));
+        }
+    }
+
+    /// Starts a selection at the cursor, or ends it if one of the same kind
+    /// is under way.
+    pub fn toggle_selection(&mut self, rows_only: bool) {
+        self.selection = match self.selection {
+            Some(selection) if selection.rows_only == rows_only => None,
+            _ => Some(Selection {
+                row: self.row,
+                col: self.col,
+                rows_only,
+            }),
+        };
+    }
+
+    /// The selected rows and columns, both inclusive.
+    pub fn selected(
+        &self,
+    ) -> Option<(
+        std::ops::RangeInclusive<usize>,
+        std::ops::RangeInclusive<usize>,
+    )> {
+        let selection = self.selection?;
+        let rows = selection.row.min(self.row)..=selection.row.max(self.row);
+        let cols = if selection.rows_only {
+            0..=self.widths.len().saturating_sub(1)
+        } else {
+            selection.col.min(self.col)..=selection.col.max(self.col)
+        };
+        Some((rows, cols))
+    }
+
+    /// Count, sum, average, min and max of the numbers in the selection,
+    /// like a spreadsheet's status bar. `None` without a selection.
+    pub fn aggregates(&self, results: &ResultSet) -> Option<String> {
+        let (rows, cols) = self.selected()?;
+        let mut count = 0;
+        let mut numbers = Vec::new();
+        for row in rows.filter_map(|row| results.rows.get(row)) {
+            for col in cols.clone().filter(|col| !self.hidden.contains(col)) {
+                let Some(cell) = row.get(col) else {
+                    continue;
+                };
+                count += 1;
+                if let Some(n) = values::number(&results.columns[col], cell)
+                    && n.is_finite()
+                {
+                    numbers.push(n);
+                }
+            }
+        }
+        let mut line = format!("count={count}");
+        if !numbers.is_empty() {
+            let sum: f64 = numbers.iter().sum();
+            let min = numbers.iter().copied().fold(f64::INFINITY, f64::min);
+            let max = numbers.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+            let avg = sum / numbers.len() as f64;
+            let avg = format!("{avg:.4}");
+            let avg = avg.trim_end_matches('0').trim_end_matches('.');
+            line.push_str(&format!(" sum={sum} avg={avg} min={min} max={max}"));
+        }
+        Some(line)
+    }
+
+    /// Lays the grid out afresh on the next render, scrolled back as far as
+    /// the cursor allows so a bigger area shows more rather than blanks.
+    pub fn resized(&mut self) {
+        self.refit = true;
+    }
+
+    /// Scrolls the view by `rows`, dragging the cursor along if it would
+    /// leave the view, like the wheel does.
+    pub fn scroll(&mut self, results: &ResultSet, rows: isize) {
+        let last = results.rows.len().saturating_sub(1);
+        self.scroll_row = self.scroll_row.saturating_add_signed(rows).min(last);
+        let bottom = self.scroll_row + self.height.saturating_sub(1);
+        self.row = self
+            .row
+            .clamp(self.scroll_row, bottom.max(self.scroll_row))
+            .min(last);
+    }
+
+    /// The `(row, column)` of the cell at screen position `x`, `y`.
+    pub fn hit(&self, x: u16, y: u16) -> Option<(usize, usize)> {
+        let row = self.scroll_row + y.checked_sub(self.rows_y)? as usize;
+        if y >= self.rows_y + self.height as u16 {
+            return None;
+        }
+        let (col, ..) = self
+            .layout
+            .iter()
+            .find(|(_, start, width)| (*start..start + width).contains(&x))?;
+        Some((row, *col))
+    }
+
+    /// Width taken by the visible columns in `columns`.
+    fn span(&mut self, results: &ResultSet, columns: std::ops::Range<usize>) -> usize {
+        let mut span = 0;
+        for col in columns {
+            if !self.hidden.contains(&col) {
+                span += self.width(results, col) + SEPARATOR_WIDTH;
+            }
+        }
+        span
+    }
+
+    /// The rows the last render showed.
+    pub fn shown_rows(&self) -> std::ops::Range<usize> {
+        self.scroll_row..self.scroll_row + self.height
+    }
+
+    /// Keeps the cursor in view of `area` given the column widths.
+    fn scroll_to_cursor(&mut self, results: &ResultSet, width: usize) {
+        if self.row < self.scroll_row {
+            self.scroll_row = self.row;
+        } else if self.height > 0 && self.row >= self.scroll_row + self.height {
+            self.scroll_row = self.row + 1 - self.height;
+        }
+        self.scroll_col = self.scroll_col.max(self.pinned);
+        if self.col < self.pinned || self.col >= self.widths.len() {
+            return;
+        }
+        if self.col < self.scroll_col {
+            self.scroll_col = self.col;
+        }
+        let room = width.saturating_sub(self.span(results, 0..self.pinned));
+        // Back from the cursor to the first column that still fits, which
+        // only measures the columns that end up in view.
+        let mut used = 0;
+        for col in (self.scroll_col..self.col + 1).rev() {
+            if self.hidden.contains(&col) {
+                continue;
+            }
+            used += self.width(results, col) + SEPARATOR_WIDTH;
+            if used > room && col < self.col {
+                self.scroll_col = (col + 1..=self.col)
+                    .find(|col| !self.hidden.contains(col))
+                    .unwrap_or(self.col);
+                return;
+            }
+        }
+    }
+
+    pub fn render(
+        &mut self,
+        f: &mut Frame,
+        area: Rect,
+        results: &ResultSet,
+        focused: bool,
+        gutter: Gutter,
+        look: Look,
+    ) {
+        let redacted = look.redactions.columns(results);
+        if self.shorten_ids != look.shorten_ids
+            || self.timezone != look.timezone
+            || self.redacted != redacted
+        {
+            self.shorten_ids = look.shorten_ids;
+            self.timezone = look.timezone;
+            self.redacted = redacted;
+            self.widths.fill(None);
+        }
+        // The selection's aggregates take the bottom line.
+        let aggregates = self.aggregates(results);
+        let area = match &aggregates {
+            Some(line) => {
+                let footer = Rect {
+                    y: area.bottom().saturating_sub(1),
+                    height: area.height.min(1),
+                    ..area
+                };
+                f.render_widget(
+                    Paragraph::new(line.as_str()).style(Style::default().fg(Color::Yellow)),
+                    footer,
+                );
+                Rect {
+                    height: area.height.saturating_sub(1),
+                    ..area
+                }
+            }
+            None => area,
+        };
+        let gutter_width = gutter.width(results.rows.len()).min(area.width);
+        let (gutter_area, area) = (
+            Rect {
+                width: gutter_width,
+                ..area
+            },
+            Rect {
+                x: area.x + gutter_width,
+                width: area.width - gutter_width,
+                ..area
+            },
+        );
+        // Sparklines take a line under the names.
+        let header = if self.sparklines.is_empty() { 1 } else { 2 };
+        self.height = area.height.saturating_sub(header) as usize;
+        self.rows_y = area.y + header;
+        if std::mem::take(&mut self.refit) {
+            let rows = results.rows.len();
+            self.scroll_row = self.scroll_row.min(rows.saturating_sub(self.height));
+            self.scroll_col = self.pinned;
+        }
+        self.scroll_to_cursor(results, area.width as usize);
+
+        // Lay out the pinned columns and then the ones that fit after them,
+        // cutting the last one short.
+        self.layout.clear();
+        let mut x = area.x;
+        let columns = (0..self.pinned).chain(self.scroll_col..results.columns.len());
+        for col in columns {
+            if self.hidden.contains(&col) {
+                continue;
+            }
+            let room = area.right().saturating_sub(x);
+            if room == 0 {
+                break;
+            }
+            let width = (self.width(results, col) as u16).min(room);
+            self.layout.push((col, x, width));
+            x = x.saturating_add(width + SEPARATOR_WIDTH as u16);
+        }
+
+        let fit = |text: &str, width: u16, alignment: Alignment| {
+            let width = width as usize;
+            width::pad(
+                &width::truncate(text, width),
+                width,
+                alignment == Alignment::Right,
+            )
+        };
+        let separator = Span::styled(SEPARATOR, Style::default().fg(Color::DarkGray));
+
+        let mut lines = Vec::with_capacity(self.height + 1);
+        let mut header = Vec::new();
+        for (col, _, width) in &self.layout {
+            let column = &results.columns[*col];
+            let alignment = if column.is_numeric() {
+                Alignment::Right
+            } else {
+                Alignment::Left
+            };
+            let name = label(column, look.timezone);
+            let name = match self.sort {
+                Some((sorted, descending)) if sorted == *col => {
+                    format!("{name} {}", if descending { "▼" } else { "▲" })
+                }
+                _ => name,
+            };
+            header.push(Span::styled(
+                fit(&name, *width, alignment),
+                Style::default().add_modifier(Modifier::BOLD),
+            ));
+            header.push(separator.clone());
+        }
+        lines.push(Line::from(header));
+        if !self.sparklines.is_empty() {
+            let mut spans = Vec::new();
+            for (col, _, width) in &self.layout {
+                let line = match self.sparklines.contains(col) {
+                    true => Sparkline::of(results, *col)
+                        .map(|sparkline| sparkline.render(*width as usize))
+                        .unwrap_or_default(),
+                    false => String::new(),
+                };
+                spans.push(Span::styled(
+                    fit(&line, *width, Alignment::Left),
+                    Style::default().fg(Color::Green),
+                ));
+                spans.push(separator.clone());
+            }
+            lines.push(Line::from(spans));
+        }
+
+        for (row, cells) in results
+            .rows
+            .iter()
+            .enumerate()
+            .skip(self.scroll_row)
+            .take(self.height)
+        {
+            let mut spans = Vec::new();
+            for (col, _, width) in &self.layout {
+                let column = &results.columns[*col];
+                let alignment = if column.is_numeric() {
+                    Alignment::Right
+                } else {
+                    Alignment::Left
+                };
+                let (text, mut style) = match cells.get(*col) {
+                    Some(cell) => (
+                        fit(
+                            &shown(
+                                column,
+                                cell,
+                                look.shorten_ids,
+                                look.timezone,
+                                self.redacted[*col],
+                            ),
+                            *width,
+                            alignment,
+                        ),
+                        Style::default(),
+                    ),
+                    None => (
+                        fit("NULL", *width, alignment),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                };
+                if self.edited.contains(&(row, *col)) {
+                    style = style.fg(Color::Yellow).add_modifier(Modifier::ITALIC);
+                }
+                if self.marked.contains(&row) {
+                    style = style.bg(Color::Magenta);
+                }
+                if focused && row == self.row && *col == self.col {
+                    style = style.add_modifier(Modifier::REVERSED);
+                } else if let Some((rows, cols)) = self.selected()
+                    && rows.contains(&row)
+                    && cols.contains(col)
+                {
+                    style = style.bg(Color::Blue);
+                }
+                spans.push(Span::styled(text, style));
+                spans.push(separator.clone());
+            }
+            lines.push(Line::from(spans));
+        }
+        f.render_widget(Paragraph::new(lines), area);
+
+        if gutter_width > 0 {
+            let header = if self.sparklines.is_empty() { 1 } else { 2 };
+            let numbers: Vec<Line> = std::iter::repeat_n(Line::default(), header)
+                .chain(
+                    (self.scroll_row..results.rows.len())
+                        .take(self.height)
+                        .map(|row| {
+                            let style = if row == self.row {
+                                Style::default().fg(Color::Yellow)
+                            } else {
+                                Style::default().fg(Color::DarkGray)
+                            };
+                            let width = gutter_width as usize - 1;
+                            Line::styled(format!("{:>width$} ", gutter.label(row, self.row)), style)
+                        }),
+                )
+                .collect();
+            f.render_widget(Paragraph::new(numbers), gutter_area);
+        }
+    }
+}
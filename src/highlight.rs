@@ -0,0 +1,239 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A small SQL highlighter. It only knows about keywords, literals and
+//! comments, which is all the editor needs to be readable.
+
+use std::ops::Range;
+
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::dialect::Dialect;
+
+#[rustfmt::skip]
+pub const KEYWORDS: &[&str] = &[
+    "add", "all", "alter", "and", "any", "as", "asc", "begin", "between", "by", "cascade", "case",
+    "check", "column", "commit", "constraint", "create", "cross", "default", "delete", "desc",
+    "distinct", "do", "drop", "else", "end", "exists", "false", "for", "foreign", "from", "full",
+    "function", "grant", "group", "having", "if", "in", "index", "inner", "insert", "into", "is",
+    "join", "key", "language", "left", "like", "limit", "materialized", "not", "null", "offset",
+    "on", "or", "order", "outer", "primary", "procedure", "references", "replace", "returning",
+    "returns", "revoke", "right", "rollback", "select", "set", "table", "then", "to", "trigger",
+    "true", "union", "unique", "update", "using", "values", "view", "when", "where", "with",
+];
+
+/// Carried from one line to the next so block comments can span lines.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HighlightState {
+    in_block_comment: bool,
+    dialect: Dialect,
+}
+
+impl HighlightState {
+    pub fn new(dialect: Dialect) -> Self {
+        Self {
+            in_block_comment: false,
+            dialect,
+        }
+    }
+}
+
+fn keyword_style() -> Style {
+    Style::default()
+        .fg(Color::Blue)
+        .add_modifier(Modifier::BOLD)
+}
+
+fn string_style() -> Style {
+    Style::default().fg(Color::Green)
+}
+
+fn number_style() -> Style {
+    Style::default().fg(Color::Magenta)
+}
+
+fn comment_style() -> Style {
+    Style::default().fg(Color::DarkGray)
+}
+
+/// Highlights one line of SQL.
+pub fn highlight_line(line: &str, state: &mut HighlightState) -> Line<'static> {
+    let mut spans = Vec::new();
+    let mut plain = String::new();
+    let chars: Vec<char> = line.chars().collect();
+    let mut i = 0;
+
+    let flush = |plain: &mut String, spans: &mut Vec<Span<'static>>| {
+        if !plain.is_empty() {
+            spans.push(Span::raw(std::mem::take(plain)));
+        }
+    };
+
+    while i < chars.len() {
+        if state.in_block_comment {
+            let start = i;
+            while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                i += 1;
+            }
+            if i < chars.len() {
+                i += 2;
+                state.in_block_comment = false;
+            }
+            let text: String = chars[start..i.min(chars.len())].iter().collect();
+            spans.push(Span::styled(text, comment_style()));
+            continue;
+        }
+
+        let c = chars[i];
+        if c == '-' && chars.get(i + 1) == Some(&'-') {
+            flush(&mut plain, &mut spans);
+            let text: String = chars[i..].iter().collect();
+            spans.push(Span::styled(text, comment_style()));
+            break;
+        }
+        if c == '/' && chars.get(i + 1) == Some(&'*') {
+            flush(&mut plain, &mut spans);
+            state.in_block_comment = true;
+            let text: String = chars[i..i + 2].iter().collect();
+            spans.push(Span::styled(text, comment_style()));
+            i += 2;
+            continue;
+        }
+        if c == '\'' {
+            flush(&mut plain, &mut spans);
+            let start = i;
+            i += 1;
+            while i < chars.len() {
+                if chars[i] == '\'' {
+                    // '' is an escaped quote inside the string.
+                    if chars.get(i + 1) == Some(&'\'') {
+                        i += 2;
+                        continue;
+                    }
+                    i += 1;
+                    break;
+                }
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().collect();
+            spans.push(Span::styled(text, string_style()));
+            continue;
+        }
+        if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().collect();
+            let lower = word.to_lowercase();
+            if KEYWORDS.contains(&lower.as_str())
+                || state.dialect.keywords().contains(&lower.as_str())
+            {
+                flush(&mut plain, &mut spans);
+                spans.push(Span::styled(word, keyword_style()));
+            } else {
+                plain.push_str(&word);
+            }
+            continue;
+        }
+        if c.is_ascii_digit() {
+            flush(&mut plain, &mut spans);
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().collect();
+            spans.push(Span::styled(text, number_style()));
+            continue;
+        }
+        plain.push(c);
+        i += 1;
+    }
+    flush(&mut plain, &mut spans);
+    Line::from(spans)
+}
+
+/// Splits a line into lines at most `width` columns wide, for soft
+/// wrapping, at the same places as [`width::wrap`](crate::width::wrap).
+pub fn wrap(line: Line<'static>, width: usize) -> Vec<Line<'static>> {
+    let width = width.max(1);
+    let mut lines = vec![Line::default()];
+    let mut used = 0;
+    for span in line.spans {
+        let mut piece = String::new();
+        for grapheme in span.content.graphemes(true) {
+            let columns = grapheme.width();
+            if used > 0 && used + columns > width {
+                if let Some(line) = lines.last_mut()
+                    && !piece.is_empty()
+                {
+                    line.push_span(Span::styled(std::mem::take(&mut piece), span.style));
+                }
+                lines.push(Line::default());
+                used = 0;
+            }
+            piece.push_str(grapheme);
+            used += columns;
+        }
+        if let Some(line) = lines.last_mut()
+            && !piece.is_empty()
+        {
+            line.push_span(Span::styled(piece, span.style));
+        }
+    }
+    lines
+}
+
+/// Restyles the byte ranges `marks` of a highlighted line, for search
+/// matches.
+pub fn mark(line: Line<'static>, marks: &[Range<usize>], style: Style) -> Line<'static> {
+    if marks.is_empty() {
+        return line;
+    }
+    let mut spans = Vec::new();
+    let mut offset = 0;
+    for span in line.spans {
+        let text = span.content.as_ref();
+        let end = offset + text.len();
+        // Cut the span at every mark boundary inside it.
+        let mut cuts: Vec<usize> = marks
+            .iter()
+            .flat_map(|mark| [mark.start, mark.end])
+            .filter(|cut| *cut > offset && *cut < end)
+            .map(|cut| cut - offset)
+            .collect();
+        cuts.sort_unstable();
+        cuts.dedup();
+        let mut start = 0;
+        for cut in cuts.into_iter().chain([text.len()]) {
+            let marked = marks
+                .iter()
+                .any(|mark| mark.start <= offset + start && offset + start < mark.end);
+            let piece = text[start..cut].to_string();
+            spans.push(if marked {
+                Span::styled(piece, span.style.patch(style))
+            } else {
+                Span::styled(piece, span.style)
+            });
+            start = cut;
+        }
+        offset = end;
+    }
+    Line::from(spans)
+}
@@ -0,0 +1,390 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `K` in the editor: what the name under the cursor is, worked out from the
+//! statement around it (its tables and their aliases) and the cached schema,
+//! for the app to describe from the catalog.
+
+use sqlx::PgPool;
+
+use crate::db::catalog::{self, Function, Origin, Summary};
+use crate::db::schema::{Schema, fold};
+use crate::popup::Popup;
+use crate::statements::{is_ident, quote_ident, words};
+
+/// What a name refers to, as far as the statement and the cached schema
+/// tell.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    /// A relation, qualified or not.
+    Relation(String),
+    /// A column of relation `table`.
+    Column { table: String, column: String },
+    /// Anything else, to look up as a relation and then a function.
+    Name(String),
+}
+
+/// Words that end the table list of a `FROM`, and can't be an alias.
+const CLAUSES: [&str; 27] = [
+    "where",
+    "join",
+    "inner",
+    "left",
+    "right",
+    "full",
+    "cross",
+    "natural",
+    "on",
+    "using",
+    "group",
+    "order",
+    "limit",
+    "offset",
+    "having",
+    "window",
+    "union",
+    "intersect",
+    "except",
+    "returning",
+    "set",
+    "values",
+    "select",
+    "default",
+    "lateral",
+    "fetch",
+    "for",
+];
+
+/// The (possibly qualified) name at char `col` of `line`, up to the part
+/// the cursor is on: `o` on the `o` of `o.total`, `o.total` on `total`.
+pub fn name_at(line: &str, col: usize) -> Option<String> {
+    let bytes = line.as_bytes();
+    let at = crate::editor::byte_index(line, col);
+    let part = |c: u8| is_ident(c) || c == b'"';
+    if !bytes.get(at).copied().is_some_and(part) {
+        return None;
+    }
+    let start = (0..at)
+        .rev()
+        .find(|&i| !(part(bytes[i]) || bytes[i] == b'.'))
+        .map_or(0, |i| i + 1);
+    let end = (at..bytes.len())
+        .find(|&i| !part(bytes[i]))
+        .unwrap_or(bytes.len());
+    let name = line[start..end].trim_matches('.');
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    Some(name.to_string())
+}
+
+/// A table a statement reads or writes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Table {
+    /// As written.
+    pub name: String,
+    pub alias: Option<String>,
+    /// Where the name starts in the statement.
+    pub at: usize,
+}
+
+/// The tables `statement` reads or writes, with their aliases: those after
+/// `FROM` (and the commas after it), `JOIN`, `UPDATE` and `INTO`, short of
+/// set returning functions.
+pub fn tables(statement: &str) -> Vec<Table> {
+    let words = words(statement);
+    let is_name = |word: &str| {
+        word.starts_with(|c: char| c == '"' || is_ident(c as u8) && !c.is_ascii_digit())
+            && !CLAUSES.contains(&word.to_lowercase().as_str())
+    };
+    let mut tables = Vec::new();
+    let mut in_from = false;
+    for (i, (_, word)) in words.iter().enumerate() {
+        let lower = word.to_lowercase();
+        let starts = match lower.as_str() {
+            "from" | "join" | "update" | "into" => {
+                in_from = lower == "from";
+                true
+            }
+            "," => in_from,
+            _ => {
+                in_from &= !CLAUSES.contains(&lower.as_str()) && *word != ")";
+                false
+            }
+        };
+        let Some(&(at, name)) = words.get(i + 1).filter(|(_, word)| starts && is_name(word)) else {
+            continue;
+        };
+        let mut rest = words[i + 2..].iter().map(|(_, word)| *word).peekable();
+        if rest.peek() == Some(&"(") {
+            continue;
+        }
+        let mut alias = rest.next();
+        if alias.is_some_and(|word| word.eq_ignore_ascii_case("as")) {
+            alias = rest.next();
+        }
+        tables.push(Table {
+            name: name.to_string(),
+            alias: alias.filter(|alias| is_name(alias)).map(String::from),
+            at,
+        });
+    }
+    tables
+}
+
+/// Splits `name` on the dots outside quotes.
+fn parts(name: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut start, mut quoted) = (0, false);
+    for (i, c) in name.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '.' if !quoted => {
+                parts.push(&name[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&name[start..]);
+    parts
+}
+
+/// What `name`, written in `statement`, refers to.
+pub fn target(statement: &str, name: &str, schema: &Schema, search_path: &[String]) -> Target {
+    let tables = tables(statement);
+    let aliased = |alias: &str| {
+        tables.iter().find_map(|table| {
+            table
+                .alias
+                .as_deref()
+                .is_some_and(|name| fold(name) == fold(alias))
+                .then(|| table.name.clone())
+        })
+    };
+    let qualified = |relation: &crate::db::schema::Relation| {
+        format!(
+            "{}.{}",
+            quote_ident(&relation.schema),
+            quote_ident(&relation.name)
+        )
+    };
+    let parts = parts(name);
+    if let [prefix @ .., column] = parts.as_slice()
+        && !prefix.is_empty()
+    {
+        let prefix = prefix.join(".");
+        if let Some(table) = aliased(&prefix) {
+            return Target::Column {
+                table,
+                column: column.to_string(),
+            };
+        }
+        if let Some(relation) = schema.resolve(name, search_path) {
+            return Target::Relation(qualified(relation));
+        }
+        if let Some(relation) = schema.resolve(&prefix, search_path) {
+            return Target::Column {
+                table: qualified(relation),
+                column: column.to_string(),
+            };
+        }
+        if tables
+            .iter()
+            .any(|table| fold(&table.name) == fold(&prefix))
+        {
+            return Target::Column {
+                table: prefix,
+                column: column.to_string(),
+            };
+        }
+        return Target::Name(name.to_string());
+    }
+    if let Some(table) = aliased(name) {
+        return Target::Relation(table);
+    }
+    if let Some(relation) = schema.resolve(name, search_path) {
+        return Target::Relation(qualified(relation));
+    }
+    let column = tables.iter().find_map(|table| {
+        let relation = schema.resolve(&table.name, search_path)?;
+        relation.column(name).map(|_| qualified(relation))
+    });
+    match column {
+        Some(table) => Target::Column {
+            table,
+            column: name.to_string(),
+        },
+        None => Target::Name(name.to_string()),
+    }
+}
+
+impl Target {
+    /// The popup describing the target, from the catalog, and the table
+    /// when it describes one, for `D`, `E` and `V` to act on. The inner
+    /// error says why there is nothing to describe.
+    pub async fn describe(
+        &self,
+        pool: &PgPool,
+    ) -> Result<Result<(Popup, Option<String>), String>, sqlx::Error> {
+        let name = match self {
+            Target::Column { table, column } => {
+                return Ok(match catalog::column(pool, table, column).await? {
+                    Some(origin) => Ok((
+                        Popup::Text {
+                            title: format!("Column {}.{}", origin.table, origin.column),
+                            lines: column_lines(&origin),
+                        },
+                        None,
+                    )),
+                    None => Err(format!("No column {column} in {table}")),
+                });
+            }
+            Target::Relation(name) | Target::Name(name) => name,
+        };
+        if let Some(summary) = catalog::summary(pool, name).await? {
+            let table = summary.is_table().then(|| summary.name.clone());
+            return Ok(Ok((relation_popup(&summary), table)));
+        }
+        let functions = catalog::functions(pool, name).await?;
+        if functions.is_empty() {
+            return Ok(Err(format!("Nothing called {name} in the catalog")));
+        }
+        Ok(Ok((functions_popup(&functions), None)))
+    }
+}
+
+/// The type, source table, nullability, default and comment of a column.
+pub fn column_lines(origin: &Origin) -> Vec<String> {
+    let mut lines = vec![
+        format!("type      {}", origin.ty),
+        format!("from      {}.{}", origin.table, origin.column),
+        format!("nullable  {}", if origin.not_null { "no" } else { "yes" }),
+    ];
+    if let Some(default) = &origin.default {
+        lines.push(format!("default   {default}"));
+    }
+    for (i, line) in origin.comment.iter().flat_map(|c| c.lines()).enumerate() {
+        let label = if i == 0 { "comment" } else { "" };
+        lines.push(format!("{label:<10}{line}"));
+    }
+    lines
+}
+
+/// What a relation partitions or is a partition of, its comment and
+/// columns, then for a table its triggers and constraints, with the
+/// disabled and not valid ones marked.
+pub fn relation_popup(summary: &Summary) -> Popup {
+    let mut lines: Vec<String> = summary
+        .partition_of
+        .iter()
+        .map(|parent| format!("partition of {parent}"))
+        .collect();
+    if summary.partitions > 0 {
+        let s = if summary.partitions == 1 { "" } else { "s" };
+        lines.push(format!(
+            "{} partition{s}, :partitions {} for the tree",
+            summary.partitions, summary.name
+        ));
+    }
+    lines.extend(
+        summary
+            .comment
+            .iter()
+            .flat_map(|comment| comment.lines())
+            .map(String::from),
+    );
+    if !lines.is_empty() && !summary.columns.is_empty() {
+        lines.push(String::new());
+    }
+    let width = summary
+        .columns
+        .iter()
+        .map(|column| column.name.chars().count())
+        .max()
+        .unwrap_or(0);
+    lines.extend(summary.columns.iter().map(|column| {
+        let not_null = if column.not_null { " not null" } else { "" };
+        format!("{:<width$}  {}{not_null}", column.name, column.ty)
+    }));
+    if !summary.triggers.is_empty() {
+        if !lines.is_empty() {
+            lines.push(String::new());
+        }
+        lines.push("triggers".into());
+        lines.extend(summary.triggers.iter().map(|trigger| {
+            let disabled = if trigger.enabled { "" } else { "  DISABLED" };
+            format!("  {}{disabled}", trigger.name)
+        }));
+    }
+    if !summary.constraints.is_empty() {
+        let width = summary
+            .constraints
+            .iter()
+            .map(|constraint| constraint.name.chars().count())
+            .max()
+            .unwrap_or(0);
+        if !lines.is_empty() {
+            lines.push(String::new());
+        }
+        lines.push("constraints".into());
+        lines.extend(summary.constraints.iter().map(|constraint| {
+            let not_valid = if constraint.valid { "" } else { "  NOT VALID" };
+            format!(
+                "  {:<width$}  {}{not_valid}",
+                constraint.name, constraint.kind
+            )
+        }));
+    }
+    let mut keys = Vec::new();
+    if !summary.triggers.is_empty() {
+        keys.push("D disables the triggers, E enables them");
+    }
+    if summary.not_valid().next().is_some() {
+        keys.push("V validates the constraints");
+    }
+    if !keys.is_empty() {
+        lines.push(String::new());
+        lines.push(keys.join(", "));
+    }
+    Popup::Text {
+        title: format!("{} {}", summary.kind, summary.name),
+        lines,
+    }
+}
+
+fn functions_popup(functions: &[Function]) -> Popup {
+    let mut lines = Vec::new();
+    for function in functions {
+        lines.push(match &function.result {
+            Some(result) => format!("{}({}) → {result}", function.name, function.arguments),
+            None => format!("{}({})", function.name, function.arguments),
+        });
+        lines.extend(
+            function
+                .comment
+                .iter()
+                .flat_map(|comment| comment.lines())
+                .map(|line| format!("    {line}")),
+        );
+    }
+    let kind = match functions {
+        [function] if function.result.is_none() => "Procedure",
+        _ => "Function",
+    };
+    Popup::Text {
+        title: format!("{kind} {}", functions[0].name),
+        lines,
+    }
+}
@@ -0,0 +1,163 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `:import csv <file> [table]`: guesses a type for each column from a
+//! sample of the file, creates the table if asked to and streams the file
+//! to the server with `COPY`. Both statements show in the SQL preview
+//! first, where they can be edited, say to add a primary key.
+
+use std::io;
+use std::path::Path;
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use sqlx::PgPool;
+use sqlx::postgres::PgPoolCopyExt;
+use tokio::io::AsyncReadExt;
+
+use crate::statements::quote_ident;
+
+/// Rows looked at to guess the column types.
+const SAMPLE_ROWS: usize = 1000;
+const CHUNK_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct CsvFile {
+    /// Column names, made into plain lowercase identifiers.
+    pub columns: Vec<String>,
+    /// A guessed Postgres type for each column.
+    pub types: Vec<&'static str>,
+}
+
+/// `header` as a column name that needs no quoting: `Order Date` becomes
+/// `order_date`.
+pub fn column_name(header: &str) -> String {
+    let mut name: String = header
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+    name
+}
+
+/// The narrowest type every non-empty value fits, `text` if nothing else.
+fn guess_type<'a>(values: impl Iterator<Item = &'a str> + Clone) -> &'static str {
+    let mut values = values.filter(|value| !value.is_empty()).peekable();
+    if values.peek().is_none() {
+        return "text";
+    }
+    let all = |check: fn(&str) -> bool| values.clone().all(check);
+    if all(|v| matches!(v.to_lowercase().as_str(), "true" | "false" | "t" | "f")) {
+        "boolean"
+    } else if all(|v| v.parse::<i64>().is_ok()) {
+        "bigint"
+    } else if all(|v| v.parse::<f64>().is_ok_and(f64::is_finite)) {
+        "numeric"
+    } else if all(|v| NaiveDate::parse_from_str(v, "%Y-%m-%d").is_ok()) {
+        "date"
+    } else if all(|v| {
+        NaiveDateTime::parse_from_str(v, "%Y-%m-%d %H:%M:%S%.f").is_ok()
+            || NaiveDateTime::parse_from_str(v, "%Y-%m-%dT%H:%M:%S%.f").is_ok()
+    }) {
+        "timestamp"
+    } else if all(|v| {
+        DateTime::parse_from_rfc3339(v).is_ok()
+            || DateTime::parse_from_str(v, "%Y-%m-%d %H:%M:%S%.f%#z").is_ok()
+    }) {
+        "timestamptz"
+    } else {
+        "text"
+    }
+}
+
+/// Reads the header and a sample of the rows of the CSV file at `path`.
+pub fn inspect(path: &Path) -> io::Result<CsvFile> {
+    let mut reader = csv::Reader::from_path(path).map_err(io::Error::other)?;
+    let headers = reader.headers().map_err(io::Error::other)?.clone();
+    let sample = reader
+        .records()
+        .take(SAMPLE_ROWS)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(io::Error::other)?;
+    let types = (0..headers.len())
+        .map(|col| {
+            guess_type(
+                sample
+                    .iter()
+                    .map(move |row| row.get(col).unwrap_or_default()),
+            )
+        })
+        .collect();
+    Ok(CsvFile {
+        columns: headers.iter().map(column_name).collect(),
+        types,
+    })
+}
+
+pub fn create_table(table: &str, columns: &[String], types: &[String]) -> String {
+    let columns: Vec<String> = columns
+        .iter()
+        .zip(types)
+        .map(|(name, ty)| format!("    {} {ty}", quote_ident(name)))
+        .collect();
+    format!("CREATE TABLE {table} (\n{}\n)", columns.join(",\n"))
+}
+
+/// The `COPY` that loads a CSV file with a header line into `columns` of
+/// `table`. Empty fields load as `NULL`.
+pub fn copy_statement(table: &str, columns: &[String]) -> String {
+    let columns: Vec<String> = columns.iter().map(|name| quote_ident(name)).collect();
+    format!(
+        "COPY {table} ({}) FROM STDIN (FORMAT csv, HEADER true)",
+        columns.join(", ")
+    )
+}
+
+/// Streams the file at `path` to `statement`, a `COPY ... FROM STDIN`,
+/// calling `progress` with the bytes sent so far and the size of the file.
+/// Returns the rows loaded.
+pub async fn load(
+    pool: &PgPool,
+    statement: &str,
+    path: &Path,
+    mut progress: impl FnMut(u64, u64),
+) -> Result<u64, sqlx::Error> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let total = file.metadata().await?.len();
+    let mut copy = pool.copy_in_raw(statement).await?;
+    let mut buf = vec![0; CHUNK_SIZE];
+    let mut sent = 0;
+    loop {
+        let read = match file.read(&mut buf).await {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(err) => {
+                let _ = copy.abort(err.to_string()).await;
+                return Err(err.into());
+            }
+        };
+        copy.send(&buf[..read]).await?;
+        sent += read as u64;
+        progress(sent, total);
+    }
+    copy.finish().await
+}
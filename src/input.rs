@@ -0,0 +1,1541 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Keys and mouse events, turned into edits or [`Command`]s.
+
+use std::ops::RangeInclusive;
+
+use crossterm::event::{
+    Event as CEvent, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
+use ratatui::layout::Position;
+
+use crate::app::refresh_report;
+use crate::browse::PageTo;
+use crate::db::monitor::Report;
+use crate::editor::{Buffer, Cursor, Register};
+use crate::elements::{self, Elements};
+use crate::grid::Grid;
+use crate::jobs::{JobState, Output};
+use crate::keymap::{self, Rhs};
+use crate::popup::Popup;
+use crate::quickfix::Go;
+use crate::results::ResultSet;
+use crate::snippet::ActiveSnippet;
+use crate::state::{Command, Mode, Pane, State};
+use crate::{
+    commands, editor, export, geometry, hover, inspect, keys, snippet, statements, stats,
+    textobject,
+};
+
+/// Pending key for `<C-w>` window commands.
+const CTRL_W: char = '\u{17}';
+
+/// Pending keys for the comment operators, `gc` for `--` on every line and
+/// `gb` for a `/* */` around it all.
+const LINE_COMMENT: char = '\u{e000}';
+const BLOCK_COMMENT: char = '\u{e001}';
+
+/// Pending key for the "copy as" menu of the results, `Y`.
+const COPY_AS: char = '\u{e002}';
+
+/// Keys typed toward a command that is not complete yet, as vim's
+/// `showcmd` shows them: the count, the `d` of `dd`, the `di` of `di(`,
+/// and keys held for a mapping.
+pub(crate) fn pending_keys(state: &State) -> String {
+    let shown = |key: char| match key {
+        CTRL_W => "<C-w>".to_string(),
+        LINE_COMMENT => "gc".to_string(),
+        BLOCK_COMMENT => "gb".to_string(),
+        COPY_AS => "Y".to_string(),
+        key => key.to_string(),
+    };
+    let mut typed = state
+        .count
+        .map(|count| count.to_string())
+        .unwrap_or_default();
+    if let Some(pending) = state.pending {
+        typed.push_str(&shown(pending));
+    }
+    if let Some((operator, kind)) = state.text_object {
+        typed.push_str(&shown(operator));
+        typed.push(kind);
+    }
+    for key in state.resolver.pending() {
+        typed.push_str(&keys::notation(key));
+    }
+    typed
+}
+
+/// Handles a typed `key` through the user's mappings, returning the
+/// commands it comes to. Keys that may be the start of a mapping are held
+/// until it is clear whether they are.
+pub fn press(state: &mut State, key: KeyEvent) -> Vec<Command> {
+    state.resolver.push(key);
+    resolve(state, false)
+}
+
+/// Lets keys held for a mapping through once `timeoutlen` has passed
+/// without the rest of it.
+pub fn flush_keys(state: &mut State) -> Vec<Command> {
+    resolve(state, true)
+}
+
+fn resolve(state: &mut State, flush: bool) -> Vec<Command> {
+    let mut resolver = std::mem::take(&mut state.resolver);
+    let mut commands = Vec::new();
+    while let Some(output) = resolver.next(flush, |keys| state.lookup(keys)) {
+        let keys = match output {
+            keymap::Output::Key(key) => vec![key],
+            keymap::Output::Mapped(Rhs::Keys(keys)) => keys,
+            keymap::Output::Mapped(Rhs::Command(line)) => {
+                match ex_command(state, &line) {
+                    Command::None => {}
+                    command => commands.push(command),
+                }
+                continue;
+            }
+            keymap::Output::Mapped(Rhs::Insert(template)) => {
+                insert_template(state, &template);
+                continue;
+            }
+            #[cfg(feature = "lua")]
+            keymap::Output::Mapped(Rhs::Lua(function)) => {
+                if let Some(lua) = &state.lua {
+                    lua.call(&function);
+                }
+                continue;
+            }
+        };
+        for key in keys {
+            match handle_input(state, CEvent::Key(key)) {
+                Command::None => {}
+                command => commands.push(command),
+            }
+        }
+    }
+    state.resolver = resolver;
+    commands
+}
+
+pub fn handle_input(state: &mut State, event: CEvent) -> Command {
+    let key = match event {
+        CEvent::Key(key) => key,
+        CEvent::Mouse(mouse) => {
+            handle_mouse(state, mouse);
+            return Command::None;
+        }
+        CEvent::Resize(..) => {
+            state.resized = true;
+            return Command::None;
+        }
+        CEvent::Paste(text) => {
+            paste(state, &text);
+            return Command::None;
+        }
+        _ => return Command::None,
+    };
+    // Over a table `K` describes, D, E and V write statements for it.
+    if let Some(table) = state.described.take()
+        && state.popup.take().is_some()
+    {
+        return match key.code {
+            KeyCode::Char('D') => Command::Triggers {
+                table,
+                enable: false,
+            },
+            KeyCode::Char('E') => Command::Triggers {
+                table,
+                enable: true,
+            },
+            KeyCode::Char('V') => Command::ValidateConstraints(table),
+            _ => Command::None,
+        };
+    }
+    // Any key closes a popup, except that the copy as menu also takes it.
+    if state.popup.take().is_some() && state.pending != Some(COPY_AS) {
+        return Command::None;
+    }
+
+    let before = (state.mode, state.focus);
+    let command = handle_key(state, key);
+    if state.plain {
+        announce(state, before);
+    }
+    command
+}
+
+/// With `:set plain`, a change of mode or pane is put in words on the
+/// status line, where a screen reader picks it up. Leaving the command
+/// line is not, as the command says how it went.
+fn announce(state: &mut State, (mode, focus): (Mode, Pane)) {
+    if matches!(mode, Mode::Command | Mode::Search) {
+        return;
+    }
+    if state.mode != mode {
+        match state.mode {
+            Mode::Insert => state.status = "Insert mode".into(),
+            Mode::Replace => state.status = "Replace mode".into(),
+            Mode::Normal => state.status = "Normal mode".into(),
+            Mode::Command | Mode::Search => {}
+        }
+    } else if state.focus != focus {
+        state.status = match state.focus {
+            Pane::Results => "Results pane".into(),
+            Pane::Editor => "Editor pane".into(),
+        };
+    }
+}
+
+fn handle_key(state: &mut State, key: KeyEvent) -> Command {
+    let mode = state.mode;
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    match mode {
+        Mode::Normal if ctrl && key.code == KeyCode::Char('e') => Command::EditExternal,
+        Mode::Normal if ctrl && key.code == KeyCode::Char('w') => {
+            state.pending = Some(CTRL_W);
+            Command::None
+        }
+        Mode::Normal if state.pending == Some(CTRL_W) => {
+            state.pending = None;
+            state.focus = match (key.code, state.focus) {
+                (KeyCode::Char('k') | KeyCode::Up, _) => Pane::Results,
+                (KeyCode::Char('j') | KeyCode::Down, _) => Pane::Editor,
+                (KeyCode::Char('w' | 'p'), Pane::Editor) => Pane::Results,
+                (KeyCode::Char('w' | 'p'), Pane::Results) => Pane::Editor,
+                (_, focus) => focus,
+            };
+            Command::None
+        }
+        Mode::Normal if ctrl && state.focus == Pane::Results && state.library.is_some() => {
+            if let Some(library) = &mut state.library {
+                match key.code {
+                    KeyCode::Char('n' | 'j') => library.move_by(1),
+                    KeyCode::Char('p' | 'k') => library.move_by(-1),
+                    _ => {}
+                }
+            }
+            Command::None
+        }
+        Mode::Normal if ctrl && state.focus == Pane::Results && state.browser.is_some() => {
+            let pages = state.count.take().unwrap_or(1) as u64;
+            match key.code {
+                KeyCode::Char('f') => Command::TurnPage(PageTo::Next(pages)),
+                KeyCode::Char('b') => Command::TurnPage(PageTo::Previous(pages)),
+                _ => Command::None,
+            }
+        }
+        Mode::Normal if state.focus == Pane::Results => handle_results_key(state, key.code),
+        Mode::Normal => handle_normal_key(state, key.code),
+        Mode::Command => match key.code {
+            KeyCode::Esc => {
+                state.mode = Mode::Normal;
+                Command::None
+            }
+            KeyCode::Char(c) => {
+                state.command_line.push(c);
+                Command::None
+            }
+            KeyCode::Backspace => {
+                if state.command_line.pop().is_none() {
+                    state.mode = Mode::Normal;
+                }
+                Command::None
+            }
+            KeyCode::Enter => {
+                state.mode = Mode::Normal;
+                let line = std::mem::take(&mut state.command_line);
+                if !line.trim().is_empty() {
+                    state.last_ex = Some(line.clone());
+                }
+                let command = ex_command(state, &line);
+                state.command_line = line;
+                command
+            }
+            _ => Command::None,
+        },
+        Mode::Search => {
+            match key.code {
+                KeyCode::Esc => give_up_search(state),
+                KeyCode::Char(c) => {
+                    state.command_line.push(c);
+                    search_as_typed(state);
+                }
+                KeyCode::Backspace => {
+                    if state.command_line.pop().is_none() {
+                        give_up_search(state);
+                    } else {
+                        search_as_typed(state);
+                    }
+                }
+                KeyCode::Enter => {
+                    state.mode = Mode::Normal;
+                    let Some((from, previous)) = state.search_origin.take() else {
+                        return Command::None;
+                    };
+                    // An empty pattern searches for the last one again.
+                    if state.command_line.is_empty() {
+                        state.search = previous;
+                        state.buffer_mut().cursor = from;
+                        search_next(state, false);
+                        return Command::None;
+                    }
+                    match regex::Regex::new(&state.command_line) {
+                        Ok(pattern) => {
+                            let buffer = &mut state.buffers[state.current];
+                            if buffer.find(&pattern, from, false, false).is_none() {
+                                buffer.cursor = from;
+                                state.status = format!("Pattern not found: {pattern}");
+                            }
+                            state.search = Some(pattern);
+                        }
+                        Err(err) => {
+                            state.buffer_mut().cursor = from;
+                            state.search = previous;
+                            state.status = format!("Invalid pattern: {err}");
+                        }
+                    }
+                }
+                _ => {}
+            }
+            Command::None
+        }
+        Mode::Replace => {
+            let buffer = &mut state.buffers[state.current];
+            match key.code {
+                KeyCode::Esc => {
+                    buffer.move_left();
+                    state.mode = Mode::Normal;
+                    state.replaced.clear();
+                }
+                KeyCode::Char(c) => state.replaced.push(buffer.overwrite_char(c)),
+                KeyCode::Enter => {
+                    // Not put back by `<BS>`, nor anything typed before it.
+                    buffer.insert_newline_indented();
+                    state.replaced.clear();
+                }
+                KeyCode::Backspace => match state.replaced.pop() {
+                    Some(Some(c)) => {
+                        buffer.move_left();
+                        buffer.replace_char(c);
+                    }
+                    Some(None) => buffer.backspace(),
+                    None => buffer.move_left(),
+                },
+                KeyCode::Left => buffer.move_left(),
+                KeyCode::Right => buffer.move_right(true),
+                KeyCode::Up => buffer.move_up(true),
+                KeyCode::Down => buffer.move_down(true),
+                _ => {}
+            }
+            Command::None
+        }
+        Mode::Insert if state.completion.is_some() => {
+            let Some(menu) = &mut state.completion else {
+                return Command::None;
+            };
+            match key.code {
+                KeyCode::Char('n') if ctrl => menu.move_by(1),
+                KeyCode::Char('p') if ctrl => menu.move_by(-1),
+                KeyCode::Down => menu.move_by(1),
+                KeyCode::Up => menu.move_by(-1),
+                KeyCode::Tab | KeyCode::Enter => {
+                    let menu = state.completion.take();
+                    if let Some(menu) = menu {
+                        menu.accept(state.buffer_mut());
+                    }
+                }
+                _ => {
+                    state.completion = None;
+                    return handle_input(state, CEvent::Key(key));
+                }
+            }
+            Command::None
+        }
+        Mode::Insert if ctrl && key.code == KeyCode::Char('n') => Command::Complete,
+        Mode::Insert if ctrl && key.code == KeyCode::Char('v') => {
+            let text = state.pasted().text;
+            state.buffer_mut().insert_str(&text);
+            Command::None
+        }
+        Mode::Insert => {
+            if key.code == KeyCode::Tab {
+                expand_snippet_or_tab(state);
+                return Command::None;
+            }
+            if let KeyCode::Char(_) = key.code
+                && let Some(snippet) = &mut state.snippet
+            {
+                snippet.overwrite(&mut state.buffers[state.current]);
+            }
+            let auto_pairs = state.auto_pairs;
+            let buffer = state.buffer_mut();
+            match key.code {
+                KeyCode::Esc => {
+                    buffer.move_left();
+                    state.mode = Mode::Normal;
+                    state.snippet = None;
+                    state.signatures.call = None;
+                    return Command::None;
+                }
+                KeyCode::Char(c) if auto_pairs => buffer.insert_char_paired(c),
+                KeyCode::Char(c) => buffer.insert_char(c),
+                KeyCode::Enter => buffer.insert_newline_indented(),
+                KeyCode::Backspace if auto_pairs => buffer.backspace_paired(),
+                KeyCode::Backspace => buffer.backspace(),
+                KeyCode::Left => buffer.move_left(),
+                KeyCode::Right => buffer.move_right(true),
+                KeyCode::Up => buffer.move_up(true),
+                KeyCode::Down => buffer.move_down(true),
+                KeyCode::Home => buffer.line_start(),
+                KeyCode::End => buffer.line_end(true),
+                _ => {}
+            }
+            let buffer = state.buffer();
+            let at = buffer.offset(buffer.cursor);
+            match state.signatures.update(&buffer.text(), at) {
+                Some(name) => Command::LookUpSignatures(name),
+                None => Command::None,
+            }
+        }
+    }
+}
+
+/// Moves to the first match of the pattern typed after `/` so far, and
+/// highlights them all. A pattern that is not finished, like `(a`, leaves
+/// the last one that was.
+fn search_as_typed(state: &mut State) {
+    let Some((from, previous)) = &state.search_origin else {
+        return;
+    };
+    let from = *from;
+    if state.command_line.is_empty() {
+        state.search = previous.clone();
+        state.buffer_mut().cursor = from;
+        return;
+    }
+    let Ok(pattern) = regex::Regex::new(&state.command_line) else {
+        return;
+    };
+    let buffer = state.buffer_mut();
+    buffer.cursor = buffer
+        .find(&pattern, from, false, false)
+        .map_or(from, |(found, _)| found);
+    state.search = Some(pattern);
+}
+
+/// `<Esc>` after `/`: back to where the cursor was and what was
+/// highlighted.
+fn give_up_search(state: &mut State) {
+    state.mode = Mode::Normal;
+    if let Some((from, previous)) = state.search_origin.take() {
+        state.buffer_mut().cursor = from;
+        state.search = previous;
+    }
+}
+
+/// `n`, or `N` going `back`: to the next match of the last search.
+fn search_next(state: &mut State, back: bool) {
+    let Some(pattern) = &state.search else {
+        state.status = "No previous search".into();
+        return;
+    };
+    let buffer = &mut state.buffers[state.current];
+    match buffer.find(pattern, buffer.cursor, back, false) {
+        Some((found, wrapped)) => {
+            buffer.cursor = found;
+            if wrapped {
+                state.status = match back {
+                    false => "Search hit the bottom, continuing at the top".into(),
+                    true => "Search hit the top, continuing at the bottom".into(),
+                };
+            }
+        }
+        None => state.status = format!("Pattern not found: {pattern}"),
+    }
+}
+
+/// Text pasted into the terminal, which bracketed paste delivers in one
+/// piece: typed into the editor as is, without indenting it or running
+/// anything on its line breaks, and onto the command line as one line.
+fn paste(state: &mut State, text: &str) {
+    // Terminals send the line breaks of a paste as `\r`.
+    let text = text.replace("\r\n", "\n").replace('\r', "\n");
+    match state.mode {
+        Mode::Command | Mode::Search => {
+            let line = text.trim_end_matches('\n').replace('\n', " ");
+            state.command_line.push_str(&line);
+            if state.mode == Mode::Search {
+                search_as_typed(state);
+            }
+        }
+        Mode::Insert | Mode::Replace => state.buffer_mut().insert_str(&text),
+        // Like `P`, but with what was pasted rather than the register.
+        Mode::Normal => {
+            if state.focus == Pane::Editor && state.editable() {
+                let buffer = state.buffer_mut();
+                buffer.insert_str(&text);
+                buffer.clamp_cursor(false);
+            }
+        }
+    }
+}
+
+/// `<Tab>` in insert mode: jump to the next stop of the active snippet,
+/// expand the snippet named by the word before the cursor, or indent.
+fn expand_snippet_or_tab(state: &mut State) {
+    if let Some(mut snippet) = state.snippet.take() {
+        if snippet.next(state.buffer_mut()) {
+            state.snippet = Some(snippet);
+        }
+        return;
+    }
+
+    let buffer = state.buffer();
+    let before: Vec<char> = buffer.line().chars().take(buffer.cursor.col).collect();
+    let start = before
+        .iter()
+        .rposition(|c| !(c.is_alphanumeric() || *c == '_'))
+        .map_or(0, |index| index + 1);
+    let word: String = before[start..].iter().collect();
+    let Some(template) = state.snippets.get(&word) else {
+        state.buffer_mut().insert_str(editor::INDENT);
+        return;
+    };
+    let expansion = snippet::expand(template);
+    let buffer = state.buffer_mut();
+    for _ in word.chars() {
+        buffer.backspace();
+    }
+    state.snippet = ActiveSnippet::insert(buffer, &expansion);
+}
+
+/// The command for the ex command `line`, which may also be one that
+/// `init.lua` or a plugin adds.
+fn ex_command(state: &mut State, line: &str) -> Command {
+    commands::parse(line).unwrap_or_else(|err| {
+        #[cfg(feature = "lua")]
+        if let Some(command) = state.lua.as_ref().and_then(|lua| lua.parse(line)) {
+            return command;
+        }
+        #[cfg(feature = "plugins")]
+        if let Some(command) = state.plugins.parse(line) {
+            return command;
+        }
+        state.status = err;
+        Command::None
+    })
+}
+
+/// Inserts the snippet `template` of a mapping: onto the command line in
+/// command mode, else at the cursor of the editor, going on in insert mode
+/// at its first tab stop.
+fn insert_template(state: &mut State, template: &str) {
+    let expansion = snippet::expand(template);
+    if state.mode == Mode::Command {
+        state.command_line.push_str(&expansion.text);
+        return;
+    }
+    state.focus = Pane::Editor;
+    if !state.editable() {
+        return;
+    }
+    state.mode = Mode::Insert;
+    state.snippet = ActiveSnippet::insert(state.buffer_mut(), &expansion);
+}
+
+/// Shows what the job under the cursor of `:jobs` produced.
+fn jump_to_job(state: &mut State) {
+    let Some(job) = state.jobs.selected() else {
+        return;
+    };
+    match job.output().cloned() {
+        Some(Output::Buffer(name)) => {
+            match state.buffers.iter().rposition(|buffer| buffer.name == name) {
+                Some(index) => {
+                    state.current = index;
+                    state.focus = Pane::Editor;
+                }
+                None => state.status = format!("{name} was closed"),
+            }
+        }
+        Some(Output::Popup(popup)) => state.popup = Some(*popup),
+        None => {
+            state.status = match &job.state {
+                JobState::Running => format!("Job {} is still running", job.id),
+                JobState::Failed(err) => err.clone(),
+                _ => format!("Job {} has nothing to show", job.id),
+            }
+        }
+    }
+}
+
+/// Normal mode keys while the results grid has focus.
+fn handle_results_key(state: &mut State, code: KeyCode) -> Command {
+    // Typing narrows the saved queries down, so this goes first.
+    if let Some(library) = &mut state.library {
+        match code {
+            KeyCode::Char(c) => library.push(c),
+            KeyCode::Backspace => library.pop(),
+            KeyCode::Up => library.move_by(-1),
+            KeyCode::Down => library.move_by(1),
+            KeyCode::Enter => {
+                if let Some(entry) = library.selected() {
+                    let path = entry.path.display().to_string();
+                    state.library = None;
+                    state.focus = Pane::Editor;
+                    return Command::Edit(path);
+                }
+            }
+            KeyCode::Esc => state.library = None,
+            _ => {}
+        }
+        return Command::None;
+    }
+    match code {
+        KeyCode::Char('q') => return Command::Quit { force: false },
+        KeyCode::Char(':') => {
+            state.mode = Mode::Command;
+            state.command_line.clear();
+            return Command::None;
+        }
+        _ => {}
+    }
+    if state.jobs.shown {
+        match (state.pending.take(), code) {
+            (Some('g'), KeyCode::Char('g')) => state.jobs.top(),
+            (Some(_), _) => {}
+            (None, KeyCode::Char('k') | KeyCode::Up) => state.jobs.move_by(-1),
+            (None, KeyCode::Char('j') | KeyCode::Down) => state.jobs.move_by(1),
+            (None, KeyCode::Char('G')) => state.jobs.bottom(),
+            (None, KeyCode::Char('g')) => state.pending = Some('g'),
+            (None, KeyCode::Char('c')) => {
+                if let Some(job) = state.jobs.selected() {
+                    return Command::CancelJob(job.id);
+                }
+            }
+            (None, KeyCode::Enter) => jump_to_job(state),
+            (None, KeyCode::Esc) => state.jobs.shown = false,
+            _ => {}
+        }
+        return Command::None;
+    }
+    if state.queue.shown {
+        match (state.pending.take(), code) {
+            (Some('g'), KeyCode::Char('g')) => state.queue.top(),
+            (Some(_), _) => {}
+            (None, KeyCode::Char('k') | KeyCode::Up) => state.queue.move_by(-1),
+            (None, KeyCode::Char('j') | KeyCode::Down) => state.queue.move_by(1),
+            (None, KeyCode::Char('G')) => state.queue.bottom(),
+            (None, KeyCode::Char('g')) => state.pending = Some('g'),
+            (None, KeyCode::Char('K')) => state.queue.move_selected(-1),
+            (None, KeyCode::Char('J')) => state.queue.move_selected(1),
+            (None, KeyCode::Char('d' | 'x')) => {
+                if let Some(queued) = state.queue.remove_selected() {
+                    state.status = format!("Dropped #{} from the queue", queued.id);
+                }
+            }
+            (None, KeyCode::Char('c')) => {
+                if let Some((job, _)) = &state.queue.running {
+                    return Command::CancelJob(*job);
+                }
+            }
+            (None, KeyCode::Esc) => state.queue.shown = false,
+            _ => {}
+        }
+        return Command::None;
+    }
+    if state.quickfix.shown {
+        match (state.pending.take(), code) {
+            (Some('g'), KeyCode::Char('g')) => state.quickfix.top(),
+            (Some(_), _) => {}
+            (None, KeyCode::Char('k') | KeyCode::Up) => state.quickfix.move_by(-1),
+            (None, KeyCode::Char('j') | KeyCode::Down) => state.quickfix.move_by(1),
+            (None, KeyCode::Char('G')) => state.quickfix.bottom(),
+            (None, KeyCode::Char('g')) => state.pending = Some('g'),
+            (None, KeyCode::Enter) => return Command::Quickfix(Go::Selected),
+            (None, KeyCode::Esc) => state.quickfix.shown = false,
+            _ => {}
+        }
+        return Command::None;
+    }
+    if let Some(compare) = &mut state.compare {
+        match (state.pending.take(), code) {
+            (Some('g'), KeyCode::Char('g')) => compare.top(),
+            (Some(_), _) => {}
+            (None, KeyCode::Char('k') | KeyCode::Up) => compare.move_by(-1),
+            (None, KeyCode::Char('j') | KeyCode::Down) => compare.move_by(1),
+            (None, KeyCode::Char('h') | KeyCode::Left) => compare.scroll_cols(-1),
+            (None, KeyCode::Char('l') | KeyCode::Right) => compare.scroll_cols(1),
+            (None, KeyCode::Char('G')) => compare.bottom(),
+            (None, KeyCode::Char('g')) => state.pending = Some('g'),
+            (None, KeyCode::Char('d')) => compare.toggle_only_differing(),
+            (None, KeyCode::Esc) => state.compare = None,
+            _ => {}
+        }
+        return Command::None;
+    }
+    if let Some(diff) = &mut state.row_diff {
+        match (state.pending.take(), code) {
+            (Some('g'), KeyCode::Char('g')) => diff.top(),
+            (Some(_), _) => {}
+            (None, KeyCode::Char('k') | KeyCode::Up) => diff.move_by(-1),
+            (None, KeyCode::Char('j') | KeyCode::Down) => diff.move_by(1),
+            (None, KeyCode::Char('G')) => diff.bottom(),
+            (None, KeyCode::Char('g')) => state.pending = Some('g'),
+            (None, KeyCode::Char('d')) => diff.toggle_only_differing(),
+            (None, KeyCode::Esc) => state.row_diff = None,
+            _ => {}
+        }
+        return Command::None;
+    }
+    if let Some(elements) = &mut state.elements {
+        match (state.pending.take(), code) {
+            (Some('g'), KeyCode::Char('g')) => elements.top(),
+            (Some(_), _) => {}
+            (None, KeyCode::Char('k') | KeyCode::Up) => elements.move_by(-1),
+            (None, KeyCode::Char('j') | KeyCode::Down) => elements.move_by(1),
+            (None, KeyCode::Char('G')) => elements.bottom(),
+            (None, KeyCode::Char('g')) => state.pending = Some('g'),
+            (None, KeyCode::Char('l') | KeyCode::Right | KeyCode::Enter) => elements.expand(),
+            (None, KeyCode::Char('h') | KeyCode::Left) => elements.collapse(),
+            (None, KeyCode::Char('y')) => {
+                if let Some(node) = elements.selected() {
+                    let text = node.literal();
+                    state.status = "Element yanked".into();
+                    state.yank(Register {
+                        text,
+                        linewise: false,
+                    });
+                }
+            }
+            (None, KeyCode::Esc) => state.elements = None,
+            _ => {}
+        }
+        return Command::None;
+    }
+    match code {
+        KeyCode::Char('s') if state.pending.is_none() && state.browser.is_some() => {
+            if let Some(results) = &state.results {
+                return Command::SortBy(results.columns[state.grid.col].name.clone());
+            }
+        }
+        KeyCode::Char('s') if state.pending.is_none() => {
+            if let Some(results) = &mut state.results {
+                let order = state.grid.sort(results);
+                if let Some((_, _, rows)) = &mut state.all_rows {
+                    *rows = order.iter().map(|from| rows[*from]).collect();
+                }
+            }
+            state.mark_edits();
+            return Command::None;
+        }
+        KeyCode::Char('r') if state.report.is_some() && state.pending.is_none() => {
+            refresh_report(state);
+            return Command::None;
+        }
+        _ => {}
+    }
+    if let Some(plan) = &mut state.plan {
+        match (state.pending.take(), code) {
+            (Some('g'), KeyCode::Char('g')) => plan.top(),
+            (Some('z'), KeyCode::Char('R')) => plan.expand_all(),
+            (Some(_), _) => {}
+            (None, KeyCode::Char('k') | KeyCode::Up) => plan.move_by(-1),
+            (None, KeyCode::Char('j') | KeyCode::Down) => plan.move_by(1),
+            (None, KeyCode::Char('G')) => plan.bottom(),
+            (None, KeyCode::Enter | KeyCode::Char(' ' | 'o')) => plan.toggle(),
+            (None, KeyCode::Char(c @ ('g' | 'z'))) => state.pending = Some(c),
+            (None, KeyCode::Esc) => state.plan = None,
+            _ => {}
+        }
+        return Command::None;
+    }
+    if state.dashboard.is_some() {
+        if code == KeyCode::Esc {
+            state.dashboard = None;
+        }
+        return Command::None;
+    }
+    if let Some(erd) = &mut state.erd {
+        match (state.pending.take(), code) {
+            (Some('g'), KeyCode::Char('g')) => erd.top(),
+            (Some(_), _) => {}
+            (None, KeyCode::Char('k') | KeyCode::Up) => erd.scroll_by(-1, 0),
+            (None, KeyCode::Char('j') | KeyCode::Down) => erd.scroll_by(1, 0),
+            (None, KeyCode::Char('h') | KeyCode::Left) => erd.scroll_by(0, -4),
+            (None, KeyCode::Char('l') | KeyCode::Right) => erd.scroll_by(0, 4),
+            (None, KeyCode::Char('G')) => erd.bottom(),
+            (None, KeyCode::Char('0')) => erd.left(),
+            (None, KeyCode::Char('+' | '=')) => erd.zoom(true),
+            (None, KeyCode::Char('-')) => erd.zoom(false),
+            (None, KeyCode::Char('g')) => state.pending = Some('g'),
+            (None, KeyCode::Esc) => state.erd = None,
+            _ => {}
+        }
+        return Command::None;
+    }
+    if let Some(diff) = &mut state.plan_diff {
+        match (state.pending.take(), code) {
+            (Some('g'), KeyCode::Char('g')) => diff.top(),
+            (Some(_), _) => {}
+            (None, KeyCode::Char('k') | KeyCode::Up) => diff.move_by(-1),
+            (None, KeyCode::Char('j') | KeyCode::Down) => diff.move_by(1),
+            (None, KeyCode::Char('G')) => diff.bottom(),
+            (None, KeyCode::Char('g')) => state.pending = Some('g'),
+            (None, KeyCode::Esc) => state.plan_diff = None,
+            _ => {}
+        }
+        return Command::None;
+    }
+    if state.pending == Some('g') && code == KeyCode::Char('m') {
+        state.pending = None;
+        state.count = None;
+        state.toggle_marked_rows();
+        return Command::None;
+    }
+    if let Some(bracket @ (']' | '[')) = state.pending {
+        state.pending = None;
+        if code == KeyCode::Char('r') {
+            state.switch_result(if bracket == ']' { 1 } else { -1 });
+        }
+        return Command::None;
+    }
+    let Some(results) = &state.results else {
+        return Command::None;
+    };
+    let grid = &mut state.grid;
+    if let KeyCode::Char(digit @ '0'..='9') = code
+        && (digit != '0' || state.count.is_some())
+    {
+        let digit = digit.to_digit(10).unwrap_or_default() as usize;
+        state.count = Some(
+            state
+                .count
+                .unwrap_or_default()
+                .saturating_mul(10)
+                .saturating_add(digit),
+        );
+        return Command::None;
+    }
+    let count = state.count.take();
+    let steps = count.unwrap_or(1) as isize;
+    if let Some(pending) = state.pending.take() {
+        match (pending, code) {
+            ('g', KeyCode::Char('g')) => grid.row = count.map_or(0, |n| n.saturating_sub(1)),
+            ('g', KeyCode::Char('i')) => {
+                if let Some(column) = results.columns.get(grid.col)
+                    && state.redactions.covers(&column.name)
+                {
+                    state.status = format!("{} is redacted", column.name);
+                } else if let Some(column) = results.columns.get(grid.col)
+                    && grid.row < results.rows.len()
+                {
+                    let value = results.rows.cell(grid.row, grid.col);
+                    state.popup = Some(Popup::Text {
+                        title: format!("Cell {}, row {}", column.name, grid.row + 1),
+                        lines: inspect::lines(column, value),
+                    });
+                }
+            }
+            ('z', KeyCode::Char('c')) if !grid.hide() => {
+                state.status = "Can't hide the last column".into();
+            }
+            ('z', KeyCode::Char('R')) => grid.show_all(),
+            (COPY_AS, KeyCode::Char(format @ ('t' | 'c' | 'p' | 'v' | 'i'))) => {
+                let selected = selected_results(results, grid);
+                let selected = state.redactions.apply(&selected);
+                let table = state
+                    .browser
+                    .as_ref()
+                    .map_or("<table>", |browser| browser.table.as_str());
+                let text = match format {
+                    't' => tab_separated(&selected),
+                    'c' => {
+                        let mut out = Vec::new();
+                        let _ = export::write_csv(&selected, &mut out);
+                        String::from_utf8_lossy(&out).into_owned()
+                    }
+                    'p' => export::to_pandas(&selected),
+                    'i' => export::to_inserts(&selected, table),
+                    _ => export::to_values(&selected),
+                };
+                grid.selection = None;
+                state.status = match selected.rows.len() {
+                    1 => "1 row copied".into(),
+                    rows => format!("{rows} rows copied"),
+                };
+                state.yank(Register {
+                    text,
+                    linewise: false,
+                });
+            }
+            ('z', KeyCode::Char('s')) => {
+                if let Some(column) = results.columns.get(grid.col) {
+                    let name = column.name.clone();
+                    let shown = grid.sparklines.contains(&grid.col);
+                    if !shown && !column.is_numeric() {
+                        state.status = format!("{name} isn't numeric");
+                    } else if let Some(sparkline) = grid.toggle_sparkline(results) {
+                        state.status = format!("Sparkline of {name}: {}", sparkline.summary());
+                    } else if !shown {
+                        state.status = format!("{name} has no numbers");
+                    }
+                }
+            }
+            ('z', KeyCode::Char('p')) => {
+                grid.pinned = if grid.pinned > grid.col {
+                    0
+                } else {
+                    grid.col + 1
+                };
+            }
+            _ => {}
+        }
+        return Command::None;
+    }
+    match code {
+        KeyCode::Char('h') | KeyCode::Left => grid.move_by(results, 0, -steps),
+        KeyCode::Char('l') | KeyCode::Right => grid.move_by(results, 0, steps),
+        KeyCode::Char('k') | KeyCode::Up => grid.move_by(results, -steps, 0),
+        KeyCode::Char('j') | KeyCode::Down => grid.move_by(results, steps, 0),
+        KeyCode::Char('0' | '^') | KeyCode::Home => grid.first_col(),
+        KeyCode::Char('$') | KeyCode::End => grid.last_col(),
+        KeyCode::Char('<') => grid.resize(results, -1),
+        KeyCode::Char('>') => grid.resize(results, 1),
+        KeyCode::Char('y') => {
+            let selected = selected_results(results, grid);
+            let selected = state.redactions.apply(&selected);
+            let cells = selected.rows.len() * selected.columns.len();
+            grid.selection = None;
+            state.status = match cells {
+                1 => "1 cell yanked".into(),
+                cells => format!("{cells} cells yanked"),
+            };
+            state.yank(Register {
+                text: tab_separated(&selected),
+                linewise: false,
+            });
+        }
+        KeyCode::Char('Y') => {
+            state.pending = Some(COPY_AS);
+            state.popup = Some(Popup::Text {
+                title: "Copy as".into(),
+                lines: vec![
+                    "t  tab separated, like y".into(),
+                    "c  CSV with a header".into(),
+                    "p  pandas.DataFrame(...)".into(),
+                    "v  SQL VALUES (...), (...)".into(),
+                    "i  INSERT INTO ... for each row".into(),
+                ],
+            });
+        }
+        KeyCode::Char(c @ ('D' | 'U')) if state.browser.is_some() => {
+            let rows = selected_rows(results, grid);
+            grid.selection = None;
+            let Some(browser) = &state.browser else {
+                return Command::None;
+            };
+            // The statements spell out the key, and the cell for `U`.
+            let current = (c == 'U').then(|| &results.columns[grid.col].name);
+            if let Some(column) = browser
+                .key
+                .iter()
+                .chain(current)
+                .find(|column| state.redactions.covers(column))
+            {
+                state.status = format!("{column} is redacted");
+                return Command::None;
+            }
+            let (name, sql) = if c == 'D' {
+                let sql = export::to_delete(&rows, &browser.table, &browser.key);
+                ("delete", sql)
+            } else {
+                let sql = export::to_update(&rows, &browser.table, &browser.key, grid.col);
+                ("update", sql)
+            };
+            match sql {
+                Ok(sql) => {
+                    let name = format!("[{name}] {}", browser.table);
+                    state.open_buffer(Buffer::from_text(name, &sql));
+                    state.focus = Pane::Editor;
+                }
+                Err(err) => state.status = err,
+            }
+        }
+        KeyCode::Char('v') => grid.toggle_selection(false),
+        KeyCode::Char('V') => grid.toggle_selection(true),
+        KeyCode::Char('m') => {
+            state.status = match grid.toggle_mark() {
+                1 => "1 row marked".into(),
+                rows => format!("{rows} rows marked"),
+            };
+        }
+        KeyCode::Char('M') => {
+            grid.marked.clear();
+            state.status = "Marks cleared".into();
+        }
+        KeyCode::Esc => grid.selection = None,
+        KeyCode::Char(c @ ('c' | 'K'))
+            if matches!(state.report, Some(Report::Activity | Report::Locks)) =>
+        {
+            let pid = results
+                .column_index("pid")
+                .and_then(|col| results.rows.get(grid.row)?.get(col)?.parse().ok());
+            if let Some(pid) = pid {
+                return Command::SignalBackend {
+                    pid,
+                    terminate: c == 'K',
+                };
+            }
+        }
+        // Each of these shows the value whole.
+        KeyCode::Char('c' | 'S') | KeyCode::Enter
+            if state.report.is_none()
+                && results
+                    .columns
+                    .get(grid.col)
+                    .is_some_and(|column| state.redactions.covers(&column.name)) =>
+        {
+            state.status = format!("{} is redacted", results.columns[grid.col].name);
+        }
+        KeyCode::Char('c') if state.report.is_none() => return Command::EditCell,
+        // The partition on the row, or the table they partition.
+        KeyCode::Char('K') | KeyCode::Enter
+            if matches!(state.report, Some(Report::Partitions(_)))
+                && let Some(partition) = results
+                    .column_index("partition")
+                    .and_then(|col| results.rows.get(grid.row)?.get(col)) =>
+        {
+            let table = partition.trim_start().to_string();
+            return match code {
+                KeyCode::Enter => Command::Browse { table, sort: None },
+                _ => Command::Hover(hover::Target::Relation(table)),
+            };
+        }
+        KeyCode::Char('K') => return Command::ColumnInfo,
+        KeyCode::Char('A') if state.report == Some(Report::Maintenance) => {
+            let cell = |name| {
+                let col = results.column_index(name)?;
+                results.rows.get(grid.row)?.get(col).map(String::from)
+            };
+            if let (Some(schema), Some(table)) = (cell("schema"), cell("table")) {
+                return Command::Vacuum(format!(
+                    "{}.{}",
+                    statements::quote_ident(&schema),
+                    statements::quote_ident(&table)
+                ));
+            }
+        }
+        KeyCode::Char(c @ ('u' | 'U')) if state.report == Some(Report::MatViews) => {
+            let cell = |name| {
+                let col = results.column_index(name)?;
+                results.rows.get(grid.row)?.get(col).map(String::from)
+            };
+            match (cell("schema"), cell("matview"), c) {
+                (_, Some(matview), 'U') if cell("concurrently").as_deref() != Some("t") => {
+                    state.status = format!(
+                        "{matview} can't be refreshed concurrently without a unique index and data"
+                    );
+                }
+                (Some(schema), Some(matview), _) => {
+                    return Command::Refresh {
+                        matview: format!(
+                            "{}.{}",
+                            statements::quote_ident(&schema),
+                            statements::quote_ident(&matview)
+                        ),
+                        concurrently: c == 'U',
+                    };
+                }
+                _ => {}
+            }
+        }
+        KeyCode::Char('e') if state.report == Some(Report::Statements) => {
+            let query = results
+                .column_index("query")
+                .and_then(|col| results.rows.get(grid.row)?.get(col).map(String::from));
+            if let Some(query) = query {
+                let text = format!("EXPLAIN\n{}\n", query.trim());
+                state.open_buffer(Buffer::from_text("[statement]", &text));
+                state.focus = Pane::Editor;
+            }
+        }
+        KeyCode::Char('u') if state.report == Some(Report::Sequences) => {
+            let cell = |name| {
+                let col = results.column_index(name)?;
+                results.rows.get(grid.row)?.get(col).map(String::from)
+            };
+            match (cell("schema"), cell("sequence"), cell("max")) {
+                (_, Some(sequence), _) if cell("behind").as_deref() != Some("t") => {
+                    state.status = format!("{sequence} isn't behind");
+                }
+                (Some(schema), Some(sequence), Some(max)) if let Ok(value) = max.parse() => {
+                    return Command::SetVal {
+                        sequence: format!(
+                            "{}.{}",
+                            statements::quote_ident(&schema),
+                            statements::quote_ident(&sequence)
+                        ),
+                        value,
+                        force: false,
+                    };
+                }
+                _ => {}
+            }
+        }
+        KeyCode::Char('u') if state.report == Some(Report::Extensions) => {
+            let cell = |name| {
+                let col = results.column_index(name)?;
+                results.rows.get(grid.row)?.get(col).map(String::from)
+            };
+            match (cell("kind").as_deref(), cell("name"), cell("update")) {
+                (Some("extension"), Some(name), Some(version)) => {
+                    let text = format!(
+                        "ALTER EXTENSION {} UPDATE TO {};\n",
+                        statements::quote_ident(&name),
+                        statements::quote_literal(&version)
+                    );
+                    state.open_buffer(Buffer::from_text("[extension]", &text));
+                    state.focus = Pane::Editor;
+                }
+                (Some("extension"), Some(name), None) => {
+                    state.status = format!("{name} is up to date");
+                }
+                _ => state.status = "Only extensions update".into(),
+            }
+        }
+        KeyCode::Enter if state.report == Some(Report::Databases) => {
+            let database = results
+                .column_index("database")
+                .and_then(|col| results.rows.get(grid.row)?.get(col).map(String::from));
+            if let Some(database) = database {
+                return Command::SwitchDatabase(database);
+            }
+        }
+        KeyCode::Enter if matches!(state.report, Some(Report::Grep(_))) => {
+            let cell = |name| {
+                let col = results.column_index(name)?;
+                results.rows.get(grid.row)?.get(col).map(String::from)
+            };
+            if let (Some(kind), Some(object)) = (cell("kind"), cell("object")) {
+                return match kind.as_str() {
+                    "view" | "matview" | "function" => Command::Definition(object),
+                    _ => Command::Ddl(object),
+                };
+            }
+        }
+        KeyCode::Enter if state.report.is_none() => {
+            if let Some(column) = results.columns.get(grid.col)
+                && let Some(cell) = results.rows.cell(grid.row, grid.col)
+                && let Some(node) = elements::parse(column, cell)
+            {
+                let title = format!("Elements of {}, row {}", column.name, grid.row + 1);
+                state.elements = Some(Elements::new(title, node));
+            } else if let Some(column) = results.columns.get(grid.col)
+                && let Some(cell) = results.rows.cell(grid.row, grid.col)
+                && let Some(shape) = geometry::parse(column, cell)
+            {
+                state.popup = Some(Popup::Geometry {
+                    title: format!("{} of {}, row {}", shape.kind(), column.name, grid.row + 1),
+                    shape,
+                });
+            }
+        }
+        KeyCode::Char('F') => {
+            let query = match &state.result_tabs {
+                Some(tabs) => Some(tabs.sql()),
+                None => state.last_query.as_deref(),
+            };
+            let Some(query) = query.and_then(|query| {
+                statements::split(query)
+                    .into_iter()
+                    .last()
+                    .map(|statement| {
+                        statement
+                            .trim()
+                            .trim_end_matches(';')
+                            .trim_end()
+                            .to_string()
+                    })
+            }) else {
+                return Command::None;
+            };
+            let column = &results.columns[grid.col].name;
+            let sql = format!(
+                "SELECT {}, count(*)\nFROM (\n{query}\n) AS t\nGROUP BY 1\nORDER BY 2 DESC\n",
+                statements::quote_ident(column)
+            );
+            state.open_buffer(Buffer::from_text(format!("[frequency] {column}"), &sql));
+            return Command::RunQuery(sql);
+        }
+        KeyCode::Char('S') => {
+            let stats = stats::ColumnStats::compute(results, grid.col);
+            state.popup = Some(Popup::Text {
+                title: results.columns[grid.col].name.clone(),
+                lines: stats.lines(),
+            });
+        }
+        KeyCode::Char('f') if state.browser.is_some() => {
+            return Command::Filter(results.columns[grid.col].name.clone());
+        }
+        KeyCode::Char('G') if count.is_none() && state.browser.is_some() => {
+            return Command::TurnPage(PageTo::Last);
+        }
+        KeyCode::Char('G') => {
+            let last = results.rows.len().saturating_sub(1);
+            grid.row = count.map_or(last, |n| n.saturating_sub(1).min(last));
+        }
+        KeyCode::Char(c @ ('g' | 'z' | ']' | '[')) => {
+            state.pending = Some(c);
+            state.count = count;
+        }
+        _ => {}
+    }
+    Command::None
+}
+
+/// The selected rows of the results, or the marked ones, or the one under
+/// the cursor, with all their columns.
+fn selected_rows(results: &ResultSet, grid: &Grid) -> ResultSet {
+    if grid.selection.is_none() && !grid.marked.is_empty() {
+        return marked_rows(results, grid);
+    }
+    let rows = grid
+        .selected()
+        .map_or(grid.row..=grid.row, |(rows, _)| rows);
+    let mut selected = ResultSet::new(results.columns.clone());
+    selected.rows = results
+        .rows
+        .select(rows.filter(|row| *row < results.rows.len()));
+    selected
+}
+
+/// The rows marked with `m`, with all their columns.
+fn marked_rows(results: &ResultSet, grid: &Grid) -> ResultSet {
+    let mut marked = ResultSet::new(results.columns.clone());
+    marked.rows = results.rows.select(
+        grid.marked
+            .iter()
+            .copied()
+            .filter(|row| *row < results.rows.len()),
+    );
+    marked
+}
+
+/// The selected cells of the results, or the marked rows, or the cell
+/// under the cursor.
+pub(crate) fn selected_results(results: &ResultSet, grid: &Grid) -> ResultSet {
+    if grid.selection.is_none() && !grid.marked.is_empty() {
+        return marked_rows(results, grid);
+    }
+    let (rows, cols) = grid
+        .selected()
+        .unwrap_or((grid.row..=grid.row, grid.col..=grid.col));
+    let mut selected = ResultSet::new(results.columns[cols.clone()].to_vec());
+    let rows = rows.filter(|row| *row < results.rows.len());
+    selected.rows = results.rows.select(rows).project(&cols.collect::<Vec<_>>());
+    selected
+}
+
+/// Tab separated, which spreadsheets paste into cells.
+fn tab_separated(results: &ResultSet) -> String {
+    let lines: Vec<String> = results
+        .rows
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(Option::unwrap_or_default)
+                .collect::<Vec<_>>()
+                .join("\t")
+        })
+        .collect();
+    lines.join("\n")
+}
+
+/// Clicks focus a pane and move its cursor, the wheel scrolls, and dragging
+/// the border above the editor resizes the panes.
+fn handle_mouse(state: &mut State, mouse: MouseEvent) {
+    let position = Position::new(mouse.column, mouse.row);
+    let in_results = state.results_area.contains(position);
+    let in_editor = state.editor_area.contains(position);
+    let editor_height = state.editor_area.height.saturating_sub(1) as usize;
+    match mouse.kind {
+        MouseEventKind::Down(MouseButton::Left)
+            if in_editor && mouse.row == state.editor_area.y =>
+        {
+            state.resizing = true;
+        }
+        MouseEventKind::Down(MouseButton::Left) if in_results => {
+            state.focus = Pane::Results;
+            if let Some(results) = &state.results
+                && let Some((row, col)) = state.grid.hit(mouse.column, mouse.row)
+                && row < results.rows.len()
+            {
+                (state.grid.row, state.grid.col) = (row, col);
+            }
+        }
+        MouseEventKind::Down(MouseButton::Left) if in_editor => {
+            state.focus = Pane::Editor;
+            let insert = matches!(state.mode, Mode::Insert | Mode::Replace);
+            let top = state.editor_area.y + 1;
+            let left = state.editor_area.x
+                + state
+                    .editor_gutter
+                    .width(state.buffer().lines.len())
+                    .min(state.editor_area.width);
+            let wrap = state.wrap.then(|| state.editor_width());
+            let buffer = state.buffer_mut();
+            buffer.cursor = buffer.position_at(
+                (mouse.row - top) as usize,
+                mouse.column.saturating_sub(left) as usize,
+                wrap,
+            );
+            buffer.clamp_cursor(insert);
+        }
+        MouseEventKind::Drag(MouseButton::Left) if state.resizing => {
+            let top = state.results_area.y;
+            let height = state.results_area.height + state.editor_area.height;
+            let offset = mouse.row.saturating_sub(top) as u32 * 100 / height.max(1) as u32;
+            state.split = (offset as u16).clamp(10, 90);
+        }
+        MouseEventKind::Up(MouseButton::Left) => state.resizing = false,
+        MouseEventKind::ScrollDown | MouseEventKind::ScrollUp => {
+            let lines = if mouse.kind == MouseEventKind::ScrollDown {
+                3
+            } else {
+                -3
+            };
+            if in_results && let Some(results) = &state.results {
+                state.grid.scroll(results, lines);
+            } else if in_editor {
+                state.buffer_mut().scroll_by(lines, editor_height);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn handle_normal_key(state: &mut State, code: KeyCode) -> Command {
+    if let Some((operator, kind)) = state.text_object.take() {
+        if let KeyCode::Char(object) = code {
+            apply_text_object(state, operator, object, kind == 'i');
+        }
+        return Command::None;
+    }
+    if let Some(pending) = state.pending.take() {
+        match (pending, code) {
+            (
+                operator @ ('d' | 'c' | 'y' | LINE_COMMENT | BLOCK_COMMENT),
+                KeyCode::Char(kind @ ('i' | 'a')),
+            ) => {
+                state.text_object = Some((operator, kind));
+            }
+            ('g', KeyCode::Char('c')) => state.pending = Some(LINE_COMMENT),
+            ('g', KeyCode::Char('b')) => state.pending = Some(BLOCK_COMMENT),
+            (operator @ (LINE_COMMENT | BLOCK_COMMENT), KeyCode::Char(motion))
+                if state.editable() =>
+            {
+                let buffer = state.buffer();
+                let row = buffer.cursor.row;
+                let last = buffer.lines.len() - 1;
+                let rows = match motion {
+                    'c' | 'b' => row..=row,
+                    'j' => row..=(row + 1).min(last),
+                    'k' => row.saturating_sub(1)..=row,
+                    'G' => row..=last,
+                    _ => return Command::None,
+                };
+                comment_rows(state, operator, rows);
+            }
+            ('g', KeyCode::Char('g')) => state.buffer_mut().top(),
+            ('r', KeyCode::Char(c)) if state.editable() => {
+                state.buffer_mut().replace_char(c);
+            }
+            (bracket @ (']' | '['), KeyCode::Char('r')) => {
+                state.switch_result(if bracket == ']' { 1 } else { -1 })
+            }
+            ('g', KeyCode::Char(c @ ('j' | 'k'))) => {
+                if state.wrap {
+                    let width = state.editor_width();
+                    state.buffer_mut().move_visual(c == 'j', width);
+                } else if c == 'j' {
+                    state.buffer_mut().move_down(false);
+                } else {
+                    state.buffer_mut().move_up(false);
+                }
+            }
+            ('d', KeyCode::Char('d')) if state.editable() => {
+                let line = state.buffer_mut().delete_line();
+                state.register = Register {
+                    text: line,
+                    linewise: true,
+                };
+            }
+            ('y', KeyCode::Char('y')) => state.yank(Register {
+                text: state.buffer().line().to_string(),
+                linewise: true,
+            }),
+            _ => {}
+        }
+        return Command::None;
+    }
+
+    let insert = |state: &mut State, position: fn(&mut Buffer)| {
+        if state.editable() {
+            position(state.buffer_mut());
+            state.mode = Mode::Insert;
+        }
+    };
+    match code {
+        KeyCode::Char('q') => return Command::Quit { force: false },
+        KeyCode::Char(':') => {
+            state.mode = Mode::Command;
+            state.command_line.clear();
+        }
+        KeyCode::Char('/') => {
+            state.mode = Mode::Search;
+            state.command_line.clear();
+            state.search_origin = Some((state.buffer().cursor, state.search.clone()));
+        }
+        KeyCode::Char(c @ ('n' | 'N')) => search_next(state, c == 'N'),
+        KeyCode::Char('*') => {
+            let Some(word) = state.buffer().word() else {
+                state.status = "No word under the cursor".into();
+                return Command::None;
+            };
+            let pattern = format!(r"\b{}\b", regex::escape(word));
+            state.search = regex::Regex::new(&pattern).ok();
+            search_next(state, false);
+        }
+        KeyCode::Enter => return Command::RunQuery(state.buffer().text()),
+        KeyCode::Char('i') => insert(state, |_| {}),
+        KeyCode::Char('a') => insert(state, |b| b.move_right(true)),
+        KeyCode::Char('A') => insert(state, |b| b.line_end(true)),
+        KeyCode::Char('I') => insert(state, Buffer::first_non_blank),
+        KeyCode::Char('o') => insert(state, |b| b.open_line(false)),
+        KeyCode::Char('O') => insert(state, |b| b.open_line(true)),
+        KeyCode::Char('R') if state.editable() => {
+            state.mode = Mode::Replace;
+            state.replaced.clear();
+        }
+        KeyCode::Char('h') | KeyCode::Left => state.buffer_mut().move_left(),
+        KeyCode::Char('l') | KeyCode::Right => state.buffer_mut().move_right(false),
+        KeyCode::Char('k') | KeyCode::Up => state.buffer_mut().move_up(false),
+        KeyCode::Char('j') | KeyCode::Down => state.buffer_mut().move_down(false),
+        KeyCode::Char('0') | KeyCode::Home => state.buffer_mut().line_start(),
+        KeyCode::Char('^') => state.buffer_mut().first_non_blank(),
+        KeyCode::Char('$') | KeyCode::End => state.buffer_mut().line_end(false),
+        KeyCode::Char('G') => state.buffer_mut().bottom(),
+        KeyCode::Char('K') => {
+            let buffer = state.buffer();
+            let Some(name) = hover::name_at(buffer.line(), buffer.cursor.col) else {
+                return Command::None;
+            };
+            let text = buffer.text();
+            let statement = textobject::find(&text, buffer.offset(buffer.cursor), 's', true)
+                .map_or(text.as_str(), |range| &text[range]);
+            return Command::Hover(hover::target(
+                statement,
+                &name,
+                &state.schema,
+                &state.search_path,
+            ));
+        }
+        KeyCode::Char('%') => {
+            let buffer = state.buffer_mut();
+            let text = buffer.text();
+            if let Some(offset) = textobject::matching(&text, buffer.offset(buffer.cursor)) {
+                buffer.cursor = buffer.cursor_at(offset);
+            }
+        }
+        KeyCode::Char('x') if state.editable() => {
+            if let Some(c) = state.buffer_mut().delete_char() {
+                state.register = Register {
+                    text: c.to_string(),
+                    linewise: false,
+                };
+            }
+        }
+        KeyCode::Char(c @ ('p' | 'P')) if state.editable() => {
+            let register = state.pasted();
+            state.buffer_mut().paste(&register, c == 'P');
+        }
+        KeyCode::Char(c @ ('g' | 'd' | 'c' | 'y' | 'r' | ']' | '[')) => state.pending = Some(c),
+        _ => {}
+    }
+    Command::None
+}
+
+/// `gc` or `gb` on whole lines.
+fn comment_rows(state: &mut State, operator: char, rows: RangeInclusive<usize>) {
+    let buffer = state.buffer_mut();
+    if operator == LINE_COMMENT {
+        buffer.toggle_line_comments(rows);
+    } else {
+        let start = buffer.offset(Cursor {
+            row: *rows.start(),
+            col: 0,
+        });
+        let end = buffer.offset(Cursor {
+            row: *rows.end(),
+            col: usize::MAX,
+        });
+        buffer.toggle_block_comment(start..end);
+    }
+}
+
+/// Yanks, deletes or changes (`y`, `d`, `c`) a text object, like `ci(`.
+fn apply_text_object(state: &mut State, operator: char, object: char, inner: bool) {
+    let buffer = state.buffer();
+    let text = buffer.text();
+    let Some(range) = textobject::find(&text, buffer.offset(buffer.cursor), object, inner) else {
+        return;
+    };
+    if operator == LINE_COMMENT || operator == BLOCK_COMMENT {
+        if !state.editable() {
+            return;
+        }
+        let buffer = state.buffer_mut();
+        if operator == LINE_COMMENT {
+            let rows = buffer.cursor_at(range.start).row..=buffer.cursor_at(range.end).row;
+            buffer.toggle_line_comments(rows);
+        } else {
+            buffer.toggle_block_comment(range);
+        }
+        return;
+    }
+    if operator == 'y' {
+        let cursor = buffer.cursor_at(range.start);
+        state.yank(Register {
+            text: text[range].to_string(),
+            linewise: false,
+        });
+        state.buffer_mut().cursor = cursor;
+        return;
+    }
+    if !state.editable() {
+        return;
+    }
+    let deleted = state.buffer_mut().delete_range(range);
+    state.register = Register {
+        text: deleted,
+        linewise: false,
+    };
+    if operator == 'c' {
+        state.mode = Mode::Insert;
+    } else {
+        state.buffer_mut().clamp_cursor(false);
+    }
+}
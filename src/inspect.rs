@@ -0,0 +1,221 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! What `gi` shows about the cell under the cursor of the results: the
+//! value as it came over the wire, the type sqlx would decode it to, its
+//! size, and other ways to read it for the types where those help, like
+//! epoch seconds for a timestamp or the `f64` a `numeric` rounds to.
+
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime};
+
+use crate::results::Column;
+use crate::values::{self, Interval};
+
+/// Longest stretch of the raw value shown, in characters.
+const MAX_RAW: usize = 60;
+
+/// The Rust type sqlx decodes a column of type `ty` to.
+pub fn rust_type(ty: &str) -> String {
+    if let Some(element) = ty.strip_prefix('_').or_else(|| ty.strip_suffix("[]")) {
+        return format!("Vec<{}>", rust_type(element));
+    }
+    match ty {
+        "bool" => "bool",
+        "int2" => "i16",
+        "int4" => "i32",
+        "int8" => "i64",
+        "float4" => "f32",
+        "float8" => "f64",
+        "numeric" => "BigDecimal",
+        "oid" => "Oid",
+        "text" | "varchar" | "bpchar" | "name" | "citext" => "String",
+        "bytea" => "Vec<u8>",
+        "date" => "NaiveDate",
+        "time" => "NaiveTime",
+        "timestamp" => "NaiveDateTime",
+        "timestamptz" => "DateTime<Utc>",
+        "interval" => "PgInterval",
+        "uuid" => "Uuid",
+        "json" | "jsonb" => "serde_json::Value",
+        "inet" | "cidr" => "IpNetwork",
+        "money" => "PgMoney",
+        _ => "String, as text",
+    }
+    .to_string()
+}
+
+/// The lines about `value`, a cell of `column`, `None` for `NULL`.
+pub fn lines(column: &Column, value: Option<&str>) -> Vec<String> {
+    let mut lines = vec![format!(
+        "type      {} → {}",
+        column.ty,
+        rust_type(&column.ty)
+    )];
+    let Some(value) = value else {
+        lines.push("raw       NULL, no value on the wire".into());
+        return lines;
+    };
+    let escaped = format!("{value:?}");
+    lines.push(match escaped.chars().count() {
+        n if n > MAX_RAW => {
+            let shown: String = escaped.chars().take(MAX_RAW).collect();
+            format!("raw       {shown}…")
+        }
+        _ => format!("raw       {escaped}"),
+    });
+    let chars = value.chars().count();
+    lines.push(match chars == value.len() {
+        true => format!("length    {} bytes", value.len()),
+        false => format!("length    {} bytes, {chars} characters", value.len()),
+    });
+    lines.extend(renderings(&column.ty, value));
+    lines
+}
+
+/// Other ways to read `value`, for the types that have them.
+fn renderings(ty: &str, value: &str) -> Vec<String> {
+    match ty {
+        "int2" | "int4" | "int8" | "oid" => match value.parse::<i64>() {
+            Ok(n) => vec![
+                format!("hex       {n:#x}"),
+                format!("sci       {:e}", n as f64),
+            ],
+            Err(_) => Vec::new(),
+        },
+        "float4" | "float8" => match value.parse::<f64>() {
+            Ok(float) => vec![format!("sci       {float:e}")],
+            Err(_) => Vec::new(),
+        },
+        "numeric" => numeric(value),
+        "timestamptz" => DateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f%#z")
+            .map(instant)
+            .unwrap_or_default(),
+        "timestamp" => NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f")
+            .map(|time| instant(time.and_utc().fixed_offset()))
+            .unwrap_or_default(),
+        "date" => NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .map(|date| {
+                let epoch = date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc();
+                vec![format!("epoch     {} (midnight UTC)", epoch.timestamp())]
+            })
+            .unwrap_or_default(),
+        "bytea" => bytes(value),
+        "bool" => match value {
+            "t" => vec!["decoded   true".into()],
+            "f" => vec!["decoded   false".into()],
+            _ => Vec::new(),
+        },
+        "json" | "jsonb" => match serde_json::from_str::<serde_json::Value>(value) {
+            Ok(serde_json::Value::Object(object)) => {
+                vec![format!("json      object of {} keys", object.len())]
+            }
+            Ok(serde_json::Value::Array(array)) => {
+                vec![format!("json      array of {} items", array.len())]
+            }
+            Ok(_) => vec!["json      a scalar".into()],
+            Err(err) => vec![format!("json      invalid: {err}")],
+        },
+        "interval" => match Interval::parse(value) {
+            Some(interval) => vec![
+                format!("reads     {}", interval.render()),
+                format!(
+                    "seconds   {} (30-day months)",
+                    interval.micros() as f64 / 1e6
+                ),
+            ],
+            None => Vec::new(),
+        },
+        "money" => match values::money(value) {
+            Some(amount) => vec![format!("amount    {amount}")],
+            None => Vec::new(),
+        },
+        "uuid" => match value.chars().nth(14) {
+            Some(version) => vec![format!("version   {version}")],
+            None => Vec::new(),
+        },
+        _ => Vec::new(),
+    }
+}
+
+/// Digits, scientific notation and what an `f64` makes of a `numeric`.
+fn numeric(value: &str) -> Vec<String> {
+    let Ok(float) = value.parse::<f64>() else {
+        // NaN and Infinity parse, so this is something else.
+        return Vec::new();
+    };
+    let mut lines = Vec::new();
+    let digits = value.trim_start_matches('-');
+    if let Some((whole, fraction)) = digits.split_once('.') {
+        let precision = whole.trim_start_matches('0').len() + fraction.len();
+        lines.push(format!(
+            "digits    {precision}, {} after the point",
+            fraction.len()
+        ));
+    }
+    lines.push(format!("sci       {float:e}"));
+    // An f64 writes the shortest digits that read back as it, so those
+    // differ from the value's if it doesn't fit.
+    let exact = same_number(&float.to_string(), value);
+    lines.push(format!(
+        "as f64    {float}{}",
+        if exact { "" } else { ", rounded" }
+    ));
+    lines
+}
+
+/// Whether `a` and `b` write the same decimal number, `1.50` as `1.5`.
+fn same_number(a: &str, b: &str) -> bool {
+    let normal = |text: &str| {
+        let text = text.trim_start_matches('+');
+        match text.contains('.') {
+            true => text.trim_end_matches('0').trim_end_matches('.').to_string(),
+            false => text.to_string(),
+        }
+    };
+    normal(a) == normal(b)
+}
+
+/// A point in time as ISO 8601, in UTC and in epoch seconds.
+fn instant(time: DateTime<FixedOffset>) -> Vec<String> {
+    let utc = time.to_utc();
+    vec![
+        format!("iso 8601  {}", time.to_rfc3339()),
+        format!("utc       {}", utc.to_rfc3339()),
+        format!(
+            "epoch     {}.{:03}",
+            utc.timestamp(),
+            utc.timestamp_subsec_millis()
+        ),
+    ]
+}
+
+/// What a `bytea` in hex format holds.
+fn bytes(value: &str) -> Vec<String> {
+    let Some(hex) = value.strip_prefix("\\x") else {
+        return Vec::new();
+    };
+    let decoded: Option<Vec<u8>> = (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect();
+    let Some(decoded) = decoded else {
+        return Vec::new();
+    };
+    let mut lines = vec![format!("decoded   {} bytes", decoded.len())];
+    if let Ok(text) = std::str::from_utf8(&decoded) {
+        let text: String = format!("{text:?}").chars().take(MAX_RAW).collect();
+        lines.push(format!("utf-8     {text}"));
+    }
+    lines
+}
@@ -0,0 +1,419 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Background operations, for `:jobs`: what each one is doing, for how
+//! long, how it ended and where to see what it produced.
+
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use ratatui::{
+    Frame,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::Paragraph,
+};
+use tokio::task::AbortHandle;
+
+use crate::action::Bus;
+use crate::db::monitor::Progress;
+use crate::popup::Popup;
+use crate::state::Message;
+
+/// Finished jobs kept around for the list, oldest dropped first.
+const KEEP: usize = 50;
+
+/// Cells in a running job's progress bar.
+const BAR_WIDTH: usize = 20;
+
+/// A progress bar `percent` full.
+fn bar(percent: i64) -> String {
+    let filled = (percent.clamp(0, 100) as usize * BAR_WIDTH) / 100;
+    format!("[{}{}]", "█".repeat(filled), "░".repeat(BAR_WIDTH - filled))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Copy,
+    /// `:copy-to`, into a table on another connection.
+    CopyTo,
+    Import,
+    /// `pg_dump`.
+    Dump,
+    /// `pg_restore`.
+    Restore,
+    Source,
+    Vacuum,
+    /// `REFRESH MATERIALIZED VIEW`.
+    Refresh,
+    Bench,
+    Pipe,
+    /// A query from the queue.
+    Query,
+    /// Bringing the cached schema up to date.
+    Schema,
+}
+
+impl Kind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Kind::Copy => "copy",
+            Kind::CopyTo => "copy-to",
+            Kind::Import => "import",
+            Kind::Dump => "dump",
+            Kind::Restore => "restore",
+            Kind::Source => "source",
+            Kind::Vacuum => "vacuum",
+            Kind::Refresh => "refresh",
+            Kind::Bench => "bench",
+            Kind::Pipe => "pipe",
+            Kind::Query => "query",
+            Kind::Schema => "schema",
+        }
+    }
+
+    /// Whether the job runs on the session's connection, so cancelling it
+    /// has to tell the server too.
+    pub fn on_session(self) -> bool {
+        matches!(
+            self,
+            Kind::Copy
+                | Kind::CopyTo
+                | Kind::Import
+                | Kind::Source
+                | Kind::Vacuum
+                | Kind::Refresh
+                | Kind::Query
+        )
+    }
+}
+
+/// Where to see what a job produced.
+#[derive(Debug, Clone)]
+pub enum Output {
+    /// A buffer it opened, by name.
+    Buffer(String),
+    Popup(Box<Popup>),
+}
+
+/// How a job that ran to the end went.
+#[derive(Debug, Clone)]
+pub struct Done {
+    pub summary: String,
+    pub output: Option<Output>,
+}
+
+impl From<String> for Done {
+    fn from(summary: String) -> Self {
+        Self {
+            summary,
+            output: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum JobState {
+    Running,
+    Done(Done),
+    Failed(String),
+    Cancelled,
+}
+
+#[derive(Debug)]
+pub struct Job {
+    pub id: usize,
+    pub kind: Kind,
+    /// What it is working on, like the file or table.
+    pub what: String,
+    pub state: JobState,
+    /// How far along it is, for the jobs that can tell.
+    pub progress: Option<Progress>,
+    started: Instant,
+    /// How long it ran, once it stopped.
+    finished: Option<Duration>,
+    abort: AbortHandle,
+}
+
+impl Job {
+    pub fn elapsed(&self) -> Duration {
+        self.finished.unwrap_or_else(|| self.started.elapsed())
+    }
+
+    pub fn output(&self) -> Option<&Output> {
+        match &self.state {
+            JobState::Done(done) => done.output.as_ref(),
+            _ => None,
+        }
+    }
+}
+
+/// Handed to a job's task to tell the list how far along it is.
+#[derive(Debug, Clone)]
+pub struct Reporter {
+    id: usize,
+    messages: Bus,
+}
+
+impl Reporter {
+    pub fn progress(&self, progress: Progress) {
+        let _ = self.messages.send(Message::JobProgress {
+            id: self.id,
+            progress,
+        });
+    }
+}
+
+/// The jobs started this session, and the `:jobs` list of them.
+#[derive(Debug, Default)]
+pub struct Jobs {
+    jobs: Vec<Job>,
+    next: usize,
+    /// Shown instead of the results until Esc.
+    pub shown: bool,
+    pub cursor: usize,
+    scroll: usize,
+}
+
+impl Jobs {
+    /// Runs the task `start` makes in the background as a new job, which
+    /// reports how it went as [`Message::JobDone`].
+    pub fn spawn<F>(
+        &mut self,
+        messages: &Bus,
+        kind: Kind,
+        what: String,
+        start: impl FnOnce(Reporter) -> F,
+    ) -> usize
+    where
+        F: Future<Output = Result<Done, String>> + Send + 'static,
+    {
+        self.next += 1;
+        let id = self.next;
+        let messages = messages.clone();
+        let task = start(Reporter {
+            id,
+            messages: messages.clone(),
+        });
+        let handle = tokio::spawn(async move {
+            let started = Instant::now();
+            let outcome = task.await;
+            let elapsed = started.elapsed();
+            let _ = messages.send(Message::JobDone {
+                id,
+                elapsed,
+                outcome,
+            });
+        });
+        self.jobs.push(Job {
+            id,
+            kind,
+            what,
+            state: JobState::Running,
+            progress: None,
+            started: Instant::now(),
+            finished: None,
+            abort: handle.abort_handle(),
+        });
+        self.prune();
+        id
+    }
+
+    /// Records how job `id` went after running for `elapsed`. Jobs cancelled
+    /// in the meantime stay that way.
+    pub fn finish(&mut self, id: usize, elapsed: Duration, outcome: Result<Done, String>) {
+        let Some(job) = self.get_mut(id) else {
+            return;
+        };
+        if !matches!(job.state, JobState::Running) {
+            return;
+        }
+        job.finished = Some(elapsed);
+        job.state = match outcome {
+            Ok(done) => JobState::Done(done),
+            Err(err) => JobState::Failed(err),
+        };
+    }
+
+    /// Records how far along job `id` is, while it is running.
+    pub fn progress(&mut self, id: usize, progress: Progress) {
+        if let Some(job) = self.get_mut(id)
+            && matches!(job.state, JobState::Running)
+        {
+            job.progress = Some(progress);
+        }
+    }
+
+    /// Stops job `id` if it is still running.
+    pub fn cancel(&mut self, id: usize) -> Result<&Job, String> {
+        let job = self.get_mut(id).ok_or_else(|| format!("No job {id}"))?;
+        if !matches!(job.state, JobState::Running) {
+            return Err(format!("Job {id} isn't running"));
+        }
+        job.abort.abort();
+        job.finished = Some(job.started.elapsed());
+        job.state = JobState::Cancelled;
+        Ok(job)
+    }
+
+    /// Stops every running job, returning how many there were.
+    pub fn cancel_all(&mut self) -> usize {
+        let running: Vec<usize> = self
+            .jobs
+            .iter()
+            .filter(|job| matches!(job.state, JobState::Running))
+            .map(|job| job.id)
+            .collect();
+        for &id in &running {
+            let _ = self.cancel(id);
+        }
+        running.len()
+    }
+
+    pub fn get(&self, id: usize) -> Option<&Job> {
+        self.jobs.iter().find(|job| job.id == id)
+    }
+
+    fn get_mut(&mut self, id: usize) -> Option<&mut Job> {
+        self.jobs.iter_mut().find(|job| job.id == id)
+    }
+
+    /// Newest first, as listed.
+    pub fn iter(&self) -> impl Iterator<Item = &Job> {
+        self.jobs.iter().rev()
+    }
+
+    pub fn running(&self) -> usize {
+        self.jobs
+            .iter()
+            .filter(|job| matches!(job.state, JobState::Running))
+            .count()
+    }
+
+    /// Whether a job of `kind` is running.
+    pub fn is_running(&self, kind: Kind) -> bool {
+        self.jobs
+            .iter()
+            .any(|job| job.kind == kind && matches!(job.state, JobState::Running))
+    }
+
+    /// Whether a job is using the session's connection.
+    pub fn on_session(&self) -> bool {
+        self.jobs
+            .iter()
+            .any(|job| job.kind.on_session() && matches!(job.state, JobState::Running))
+    }
+
+    /// The job under the cursor of the list.
+    pub fn selected(&self) -> Option<&Job> {
+        self.iter().nth(self.cursor)
+    }
+
+    pub fn move_by(&mut self, rows: isize) {
+        let last = self.jobs.len().saturating_sub(1);
+        self.cursor = self.cursor.saturating_add_signed(rows).min(last);
+    }
+
+    pub fn top(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn bottom(&mut self) {
+        self.cursor = self.jobs.len().saturating_sub(1);
+    }
+
+    fn prune(&mut self) {
+        let finished = self.jobs.len() - self.running();
+        let mut excess = finished.saturating_sub(KEEP);
+        self.jobs.retain(|job| {
+            let drop = excess > 0 && !matches!(job.state, JobState::Running);
+            excess -= usize::from(drop);
+            !drop
+        });
+    }
+
+    pub fn render(&mut self, f: &mut Frame, area: Rect, focused: bool) {
+        if self.jobs.is_empty() {
+            f.render_widget(
+                Paragraph::new("No jobs yet").style(Style::default().fg(Color::DarkGray)),
+                area,
+            );
+            return;
+        }
+        let height = area.height.saturating_sub(1) as usize;
+        self.cursor = self.cursor.min(self.jobs.len() - 1);
+        if self.cursor < self.scroll {
+            self.scroll = self.cursor;
+        } else if height > 0 && self.cursor >= self.scroll + height {
+            self.scroll = self.cursor + 1 - height;
+        }
+
+        let mut lines = vec![Line::styled(
+            format!(
+                "{:>4}  {:<7} {:<9} {:>9}  what",
+                "id", "kind", "state", "elapsed"
+            ),
+            Style::default().fg(Color::DarkGray),
+        )];
+        for (row, job) in self.iter().enumerate().skip(self.scroll).take(height) {
+            let progress = job.progress.as_ref().map(|progress| {
+                let bar = match progress.percent() {
+                    Some(percent) => format!("{} {percent:>3}%", bar(percent)),
+                    None => format!("[{}]", "?".repeat(BAR_WIDTH)),
+                };
+                format!("{bar}  {}", progress.describe())
+            });
+            let (state, style, detail) = match &job.state {
+                JobState::Running => (
+                    "running",
+                    Style::default().fg(Color::Yellow),
+                    progress.as_deref(),
+                ),
+                JobState::Done(done) => (
+                    "done",
+                    Style::default().fg(Color::Green),
+                    Some(done.summary.as_str()),
+                ),
+                JobState::Failed(err) => (
+                    "failed",
+                    Style::default().fg(Color::Red),
+                    Some(err.as_str()),
+                ),
+                JobState::Cancelled => ("cancelled", Style::default().fg(Color::DarkGray), None),
+            };
+            let mut spans = vec![
+                Span::raw(format!("{:>4}  {:<7} ", job.id, job.kind.name())),
+                Span::styled(format!("{state:<9}"), style),
+                Span::raw(format!(
+                    " {:>9}  {}",
+                    format!("{:.1?}", job.elapsed()),
+                    job.what
+                )),
+            ];
+            if let Some(detail) = detail {
+                spans.push(Span::styled(
+                    format!("  {}", detail.lines().next().unwrap_or_default()),
+                    Style::default().fg(Color::DarkGray),
+                ));
+            }
+            let mut line = Line::from(spans);
+            if focused && row == self.cursor {
+                line = line.patch_style(Style::default().add_modifier(Modifier::REVERSED));
+            }
+            lines.push(line);
+        }
+        f.render_widget(Paragraph::new(lines), area);
+    }
+}
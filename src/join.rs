@@ -0,0 +1,136 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `:join`: two result sets joined on the client, for when they came
+//! from different servers and no query can join them.
+
+use std::collections::HashMap;
+
+use crate::results::{Column, ResultSet};
+
+/// Which rows without a match are kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Inner,
+    /// The left rows without a match too.
+    Left,
+    /// The rows of either side without a match too.
+    Full,
+}
+
+/// `:join <left> <right> on <column>[=<column>] [left|full]`; `%` is the
+/// results shown, any other name a saved result set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Spec {
+    pub left: String,
+    pub right: String,
+    /// The column of each side to match on.
+    pub on: (String, String),
+    pub kind: Kind,
+}
+
+impl Spec {
+    pub fn parse(args: &str) -> Result<Self, String> {
+        const USAGE: &str = "Usage: join <result> <result> on <column>[=<column>] [left|full]";
+        let words: Vec<&str> = args.split_whitespace().collect();
+        let (left, right, on, kind) = match words.as_slice() {
+            [left, right, "on", on] => (left, right, on, Kind::Inner),
+            [left, right, "on", on, "left"] => (left, right, on, Kind::Left),
+            [left, right, "on", on, "full"] => (left, right, on, Kind::Full),
+            _ => return Err(USAGE.into()),
+        };
+        let on = match on.split_once('=') {
+            Some((a, b)) if !a.is_empty() && !b.is_empty() => (a.to_string(), b.to_string()),
+            Some(_) => return Err(USAGE.into()),
+            None => (on.to_string(), on.to_string()),
+        };
+        Ok(Self {
+            left: left.to_string(),
+            right: right.to_string(),
+            on,
+            kind,
+        })
+    }
+}
+
+/// `left` and `right` joined as `spec` says, matching keys as text and
+/// never on `NULL`. The columns are those of `left` then those of `right`,
+/// but for its key when it has the same name; a name both have is
+/// qualified with the right one's name. Rows come in the order of `left`,
+/// those only `right` has last.
+pub fn join(spec: &Spec, left: &ResultSet, right: &ResultSet) -> Result<ResultSet, String> {
+    let key = |results: &ResultSet, side: &str, name: &str| {
+        results
+            .column_index(name)
+            .ok_or_else(|| format!("{side} has no column {name}"))
+    };
+    let left_key = key(left, &spec.left, &spec.on.0)?;
+    let right_key = key(right, &spec.right, &spec.on.1)?;
+    let shared_key = spec.on.0 == spec.on.1;
+    // The columns of `right` that are kept, by index.
+    let kept: Vec<usize> = (0..right.columns.len())
+        .filter(|&col| !(shared_key && col == right_key))
+        .collect();
+
+    let mut columns = left.columns.clone();
+    for &col in &kept {
+        let column = &right.columns[col];
+        let mut name = column.name.clone();
+        if left.column_index(&name).is_some() {
+            name = format!("{}.{name}", spec.right);
+        }
+        columns.push(Column::new(name, column.ty.clone()));
+    }
+
+    let mut matches: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (index, row) in right.rows.iter().enumerate() {
+        if let Some(key) = row.get(right_key) {
+            matches.entry(key).or_default().push(index);
+        }
+    }
+
+    let mut joined = ResultSet::new(columns);
+    let mut matched = vec![false; right.rows.len()];
+    for row in &left.rows {
+        let found = row
+            .get(left_key)
+            .and_then(|key| matches.get(key))
+            .map_or(&[][..], Vec::as_slice);
+        for &index in found {
+            matched[index] = true;
+            let other = right.rows.get(index).expect("indexes come from the rows");
+            joined
+                .rows
+                .push(row.iter().chain(kept.iter().map(|&col| other.get(col))));
+        }
+        if found.is_empty() && spec.kind != Kind::Inner {
+            let nulls = kept.iter().map(|_| None);
+            joined.rows.push(row.iter().chain(nulls));
+        }
+    }
+    if spec.kind == Kind::Full {
+        for (index, other) in right.rows.iter().enumerate() {
+            if matched[index] {
+                continue;
+            }
+            // The key only shows on the left when both call it the same.
+            let key = other.get(right_key).filter(|_| shared_key);
+            let lefts = (0..left.columns.len()).map(|col| if col == left_key { key } else { None });
+            joined
+                .rows
+                .push(lefts.chain(kept.iter().map(|&col| other.get(col))));
+        }
+    }
+    Ok(joined)
+}
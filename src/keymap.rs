@@ -0,0 +1,396 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! User key mappings: sequences of keys, like `gb` or `<C-w>x`, that stand
+//! for other keys, from the `[map]` tables of the config and `dbvi.map` in
+//! `init.lua`:
+//!
+//! ```toml
+//! [map.normal]
+//! gb = "<C-w>k"
+//! "<C-t>" = ":tables<CR>"
+//!
+//! [map.insert]
+//! jk = "<Esc>"
+//! ```
+//!
+//! Instead of keys, a mapping may run an ex command or insert a snippet
+//! template into the editor, and `<leader>` in it stands for the `leader`
+//! key, `\` unless set otherwise:
+//!
+//! ```toml
+//! [map]
+//! leader = "<Space>"
+//!
+//! [map.normal]
+//! "<leader>e" = { command = "export csv /tmp/out.csv" }
+//! "<leader>s" = { insert = "SELECT * FROM ${1:table} WHERE $0;" }
+//! ```
+//!
+//! What a mapping stands for is not mapped again. Typed keys go through
+//! the [`Resolver`] first: a key that starts no mapping goes straight on,
+//! while one that could, like `g` with `gb` mapped, waits for the next key
+//! or `timeoutlen`. `b` then runs the mapping and anything else lets `g`
+//! through to vim's own `gg`, `gt` and so on. A mapping that is also the
+//! start of a longer one runs once the timeout passes without the rest.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::time::{Duration, Instant};
+
+use crossterm::event::{Event, KeyEvent, KeyEventKind, KeyModifiers};
+use futures_util::{Stream, StreamExt};
+use serde::Deserialize;
+
+use crate::action::Bus;
+use crate::keys;
+use crate::state::Mode;
+
+/// What a mapping stands for.
+#[derive(Debug, Clone)]
+pub enum Rhs {
+    /// Keys, handled as if typed.
+    Keys(Vec<KeyEvent>),
+    /// An ex command, run as if typed after `:`.
+    Command(String),
+    /// A snippet template, inserted at the cursor of the editor.
+    Insert(String),
+    /// A function from `init.lua`.
+    #[cfg(feature = "lua")]
+    Lua(mlua::Function),
+}
+
+/// Whether `a` and `b` are the same key, minding that terminals report
+/// `Q` as shift and `Q` or not.
+fn same_key(a: &KeyEvent, b: &KeyEvent) -> bool {
+    let modifiers = |key: &KeyEvent| key.modifiers - KeyModifiers::SHIFT;
+    a.code == b.code && modifiers(a) == modifiers(b)
+}
+
+fn same_keys(a: &[KeyEvent], b: &[KeyEvent]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(a, b)| same_key(a, b))
+}
+
+/// What a sequence of keys is in a keymap.
+#[derive(Debug)]
+pub struct Lookup {
+    /// The mapping of exactly these keys.
+    pub exact: Option<Rhs>,
+    /// Whether longer mappings start with them.
+    pub longer: bool,
+}
+
+impl Lookup {
+    /// This, with `other` for what this hasn't got.
+    pub fn or(self, other: Lookup) -> Lookup {
+        Lookup {
+            exact: self.exact.or(other.exact),
+            longer: self.longer || other.longer,
+        }
+    }
+}
+
+/// The leader key unless the config says otherwise, as in vim.
+pub const LEADER: &str = "\\";
+
+/// Keys for `:killswitch` unless the config says otherwise, vim's quit
+/// without saving. A normal mode mapping, so they work in every pane.
+pub const KILL_SWITCH: &str = "ZQ";
+
+#[derive(Debug, Clone, Default)]
+pub struct Keymap {
+    mappings: Vec<(Mode, Vec<KeyEvent>, Rhs)>,
+    leader: Option<String>,
+}
+
+impl Keymap {
+    /// What dbvi starts with before the config: just the kill switch.
+    pub fn builtin() -> Self {
+        let mut keymap = Self::default();
+        keymap.set_kill_switch(Some(KILL_SWITCH));
+        keymap
+    }
+
+    /// Moves the kill switch from where it was to `keys`, or nowhere.
+    pub fn set_kill_switch(&mut self, keys: Option<&str>) {
+        self.mappings
+            .retain(|(_, _, rhs)| !matches!(rhs, Rhs::Command(line) if line == "killswitch"));
+        if let Some(keys) = keys.filter(|keys| !keys.is_empty()) {
+            let lhs = self.parse(keys);
+            self.map(Mode::Normal, lhs, Rhs::Command("killswitch".into()));
+        }
+    }
+
+    /// The keys of the kill switch in `mode`, if it has any there.
+    pub fn kill_switch(&self, mode: Mode) -> Option<&[KeyEvent]> {
+        self.mappings
+            .iter()
+            .find(|(m, _, rhs)| {
+                *m == mode && matches!(rhs, Rhs::Command(line) if line == "killswitch")
+            })
+            .map(|(_, lhs, _)| lhs.as_slice())
+    }
+
+    /// Sets what `<leader>` stands for in mappings made from now on.
+    pub fn set_leader(&mut self, leader: &str) {
+        self.leader = Some(leader.to_string());
+    }
+
+    /// Parses `keys` as [`keys::parse`] does, with `<leader>` in them for
+    /// the leader key.
+    pub fn parse(&self, keys: &str) -> Vec<KeyEvent> {
+        let leader = self.leader.as_deref().unwrap_or(LEADER);
+        let mut expanded = String::new();
+        let mut rest = keys;
+        while let Some(start) = rest.find('<') {
+            expanded.push_str(&rest[..start]);
+            rest = &rest[start..];
+            match rest.get(..8) {
+                Some(name) if name.eq_ignore_ascii_case("<leader>") => {
+                    expanded.push_str(leader);
+                    rest = &rest[8..];
+                }
+                _ => {
+                    expanded.push('<');
+                    rest = &rest[1..];
+                }
+            }
+        }
+        expanded.push_str(rest);
+        keys::parse(&expanded)
+    }
+
+    /// Maps `lhs` to `rhs` in `mode`, in place of any mapping of it there.
+    pub fn map(&mut self, mode: Mode, lhs: Vec<KeyEvent>, rhs: Rhs) {
+        self.unmap(mode, &lhs);
+        self.mappings.push((mode, lhs, rhs));
+    }
+
+    /// Removes the mapping of `lhs` in `mode`, returning whether there was
+    /// one.
+    pub fn unmap(&mut self, mode: Mode, lhs: &[KeyEvent]) -> bool {
+        let before = self.mappings.len();
+        self.mappings
+            .retain(|(m, keys, _)| !(*m == mode && same_keys(keys, lhs)));
+        self.mappings.len() < before
+    }
+
+    pub fn lookup(&self, mode: Mode, keys: &[KeyEvent]) -> Lookup {
+        let mut lookup = Lookup {
+            exact: None,
+            longer: false,
+        };
+        for (_, lhs, rhs) in self.mappings.iter().filter(|(m, ..)| *m == mode) {
+            if same_keys(lhs, keys) {
+                lookup.exact = Some(rhs.clone());
+            } else if lhs.len() > keys.len() && same_keys(&lhs[..keys.len()], keys) {
+                lookup.longer = true;
+            }
+        }
+        lookup
+    }
+}
+
+/// What a mapping in the config stands for: keys, or a table with a
+/// `command` or an `insert`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Mapping {
+    Keys(String),
+    Command { command: String },
+    Insert { insert: String },
+}
+
+impl Mapping {
+    pub fn rhs(&self, keymap: &Keymap) -> Rhs {
+        match self {
+            Mapping::Keys(keys) => Rhs::Keys(keymap.parse(keys)),
+            Mapping::Command { command } => {
+                Rhs::Command(command.strip_prefix(':').unwrap_or(command).to_string())
+            }
+            Mapping::Insert { insert } => Rhs::Insert(insert.clone()),
+        }
+    }
+}
+
+impl From<&str> for Mapping {
+    fn from(keys: &str) -> Self {
+        Mapping::Keys(keys.to_string())
+    }
+}
+
+/// The `[map]` table of the config: the leader key and the mappings by
+/// mode.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Mappings {
+    pub leader: Option<String>,
+    pub normal: BTreeMap<String, Mapping>,
+    pub insert: BTreeMap<String, Mapping>,
+    pub command: BTreeMap<String, Mapping>,
+}
+
+impl Mappings {
+    /// Takes the leader key and the mappings of `other` over these.
+    pub fn extend(&mut self, other: Mappings) {
+        self.leader = other.leader.or(self.leader.take());
+        self.normal.extend(other.normal);
+        self.insert.extend(other.insert);
+        self.command.extend(other.command);
+    }
+
+    /// Adds them to `keymap`, setting its leader key first.
+    pub fn apply(&self, keymap: &mut Keymap) {
+        if let Some(leader) = &self.leader {
+            keymap.set_leader(leader);
+        }
+        let modes = [
+            (Mode::Normal, &self.normal),
+            (Mode::Insert, &self.insert),
+            (Mode::Command, &self.command),
+        ];
+        for (mode, mappings) in modes {
+            for (lhs, rhs) in mappings {
+                let rhs = rhs.rhs(keymap);
+                keymap.map(mode, keymap.parse(lhs), rhs);
+            }
+        }
+    }
+}
+
+/// What comes out of the resolver.
+#[derive(Debug)]
+pub enum Output {
+    /// A key no mapping took.
+    Key(KeyEvent),
+    Mapped(Rhs),
+}
+
+/// Typed keys held while they may still be the start of a mapping.
+#[derive(Debug, Default)]
+pub struct Resolver {
+    pending: Vec<KeyEvent>,
+    /// When the last of them was typed.
+    since: Option<Instant>,
+}
+
+impl Resolver {
+    pub fn push(&mut self, key: KeyEvent) {
+        self.pending.push(key);
+        self.since = Some(Instant::now());
+    }
+
+    /// The keys held so far.
+    pub fn pending(&self) -> &[KeyEvent] {
+        &self.pending
+    }
+
+    /// Whether keys have been held for longer than `timeout`, so that
+    /// [`next`](Self::next) should give up waiting for the rest.
+    pub fn timed_out(&self, timeout: Option<Duration>) -> bool {
+        match (self.since, timeout) {
+            (Some(since), Some(timeout)) => since.elapsed() >= timeout,
+            _ => false,
+        }
+    }
+
+    /// When the held keys time out, `None` without any or a timeout.
+    pub fn deadline(&self, timeout: Option<Duration>) -> Option<Instant> {
+        Some(self.since? + timeout?)
+    }
+
+    /// What the held keys come to next, `None` once there is nothing left
+    /// or they have to wait for more keys. With `flush`, nothing waits. Is
+    /// called again after handling each output, since that may change the
+    /// mode and so what `lookup` finds.
+    pub fn next(&mut self, flush: bool, lookup: impl Fn(&[KeyEvent]) -> Lookup) -> Option<Output> {
+        if self.pending.is_empty() {
+            self.since = None;
+            return None;
+        }
+        let found = lookup(&self.pending);
+        if found.longer && !flush {
+            return None;
+        }
+        if let Some(rhs) = found.exact {
+            self.pending.clear();
+            return Some(Output::Mapped(rhs));
+        }
+        // Nothing is or starts with all of them: the longest mapping they
+        // start with runs, or else the first key goes through as typed,
+        // and the rest is looked up again.
+        let (len, output) = (1..self.pending.len())
+            .rev()
+            .find_map(|len| {
+                let rhs = lookup(&self.pending[..len]).exact?;
+                Some((len, Output::Mapped(rhs)))
+            })
+            .unwrap_or((1, Output::Key(self.pending[0])));
+        self.pending.drain(..len);
+        Some(output)
+    }
+}
+
+/// Awaits `run` while reading the terminal's `events`, which the loop can't
+/// while a query runs in the foreground. Should the kill switch of `mode`
+/// be typed, `run` is given up on and `None` returned for the switch to be
+/// pulled. Every other event goes on the bus, to be handled once `run` is
+/// done as if typed then.
+pub async fn unless_killed<T>(
+    run: impl Future<Output = T>,
+    mut events: impl Stream<Item = io::Result<Event>> + Unpin,
+    keymap: &Keymap,
+    mode: Mode,
+    bus: &Bus,
+) -> Option<T> {
+    let switch = keymap.kill_switch(mode).unwrap_or_default();
+    let mut run = std::pin::pin!(run);
+    // Keys that may yet turn out to be the switch, held back until they
+    // don't.
+    let mut held: Vec<KeyEvent> = Vec::new();
+    let outcome = loop {
+        tokio::select! {
+            outcome = &mut run => break outcome,
+            Some(Ok(event)) = events.next() => {
+                let key = match event {
+                    Event::Key(key) if !switch.is_empty() && key.kind == KeyEventKind::Press => key,
+                    event => {
+                        let _ = bus.send(event);
+                        continue;
+                    }
+                };
+                held.push(key);
+                if held.len() >= switch.len()
+                    && same_keys(&held[held.len() - switch.len()..], switch)
+                {
+                    return None;
+                }
+                // The longest tail that could still become the switch.
+                let start = (0..=held.len())
+                    .find(|&start| {
+                        let tail = &held[start..];
+                        tail.len() < switch.len() && same_keys(tail, &switch[..tail.len()])
+                    })
+                    .unwrap_or(held.len());
+                for key in held.drain(..start) {
+                    let _ = bus.send(Event::Key(key));
+                }
+            }
+        }
+    };
+    for key in held {
+        let _ = bus.send(Event::Key(key));
+    }
+    Some(outcome)
+}
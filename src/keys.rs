@@ -0,0 +1,117 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Keys written as in vim mappings, for scripts and tests.
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// Parses `keys`, written as in a vim mapping: `ihello<Esc>`, `<C-w>j`,
+/// `:q<CR>`. `<lt>` is a `<` and `<Space>` a space; anything else in `<>`
+/// that isn't a key name is taken literally.
+pub fn parse(keys: &str) -> Vec<KeyEvent> {
+    let mut events = Vec::new();
+    let mut rest = keys;
+    while let Some(c) = rest.chars().next() {
+        let special = rest
+            .strip_prefix('<')
+            .and_then(|tail| tail.split_once('>'))
+            .and_then(|(name, tail)| Some((special_key(name)?, tail)));
+        match special {
+            Some((key, tail)) => {
+                events.push(key);
+                rest = tail;
+            }
+            None => {
+                events.push(KeyEvent::from(KeyCode::Char(c)));
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    events
+}
+
+/// Keys with a name between `<>`, the first name of each being the one
+/// [`notation`] writes.
+const NAMES: [(&str, KeyCode); 18] = [
+    ("Esc", KeyCode::Esc),
+    ("CR", KeyCode::Enter),
+    ("Enter", KeyCode::Enter),
+    ("Tab", KeyCode::Tab),
+    ("S-Tab", KeyCode::BackTab),
+    ("Space", KeyCode::Char(' ')),
+    ("BS", KeyCode::Backspace),
+    ("Del", KeyCode::Delete),
+    ("Insert", KeyCode::Insert),
+    ("Up", KeyCode::Up),
+    ("Down", KeyCode::Down),
+    ("Left", KeyCode::Left),
+    ("Right", KeyCode::Right),
+    ("Home", KeyCode::Home),
+    ("End", KeyCode::End),
+    ("PageUp", KeyCode::PageUp),
+    ("PageDown", KeyCode::PageDown),
+    ("lt", KeyCode::Char('<')),
+];
+
+/// The key called `name` between `<>`, like `Esc`, `F5`, `C-w` or `M-x`.
+fn special_key(name: &str) -> Option<KeyEvent> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut name = name;
+    loop {
+        if let Some(rest) = name.strip_prefix("C-").filter(|rest| !rest.is_empty()) {
+            modifiers |= KeyModifiers::CONTROL;
+            name = rest;
+        } else if let Some(rest) = name
+            .strip_prefix("M-")
+            .or_else(|| name.strip_prefix("A-"))
+            .filter(|rest| !rest.is_empty())
+        {
+            modifiers |= KeyModifiers::ALT;
+            name = rest;
+        } else {
+            break;
+        }
+    }
+    let mut chars = name.chars();
+    let code = match (chars.next(), chars.next()) {
+        (Some(c), None) if !modifiers.is_empty() => KeyCode::Char(c),
+        _ => match NAMES.iter().find(|(known, _)| *known == name) {
+            Some((_, code)) => *code,
+            None => KeyCode::F(name.strip_prefix('F')?.parse().ok()?),
+        },
+    };
+    Some(KeyEvent::new(code, modifiers))
+}
+
+/// `key` written as [`parse`] reads it back: `x`, `<C-w>`, `<lt>` or
+/// `<F5>`. Empty for keys that have no name.
+pub fn notation(key: &KeyEvent) -> String {
+    let mut prefix = String::new();
+    if key.modifiers.contains(KeyModifiers::CONTROL) {
+        prefix.push_str("C-");
+    }
+    if key.modifiers.contains(KeyModifiers::ALT) {
+        prefix.push_str("M-");
+    }
+    let name = match key.code {
+        KeyCode::Char(c) if c != '<' && c != ' ' && prefix.is_empty() => return c.to_string(),
+        KeyCode::Char(c) if c != '<' && c != ' ' => c.to_string(),
+        KeyCode::F(n) => format!("F{n}"),
+        code => match NAMES.iter().find(|(_, known)| *known == code) {
+            Some((name, _)) => name.to_string(),
+            None => return String::new(),
+        },
+    };
+    format!("<{prefix}{name}>")
+}
@@ -0,0 +1,98 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! dbvi as a library: the editor, the database session and the UI, for the
+//! `dbvi` binary and anything else that wants to drive them.
+
+pub mod action;
+pub mod app;
+pub mod audit;
+pub mod bench;
+pub mod browse;
+pub mod chart;
+pub mod clipboard;
+pub mod commands;
+pub mod compare;
+pub mod completion;
+pub mod config;
+pub mod dashboard;
+pub mod db;
+pub mod dialect;
+pub mod dump;
+pub mod editor;
+pub mod edits;
+pub mod elements;
+pub mod erd;
+pub mod export;
+pub mod generate;
+pub mod geometry;
+pub mod grid;
+pub mod highlight;
+pub mod hover;
+pub mod import;
+pub mod input;
+pub mod inspect;
+pub mod jobs;
+pub mod join;
+pub mod keymap;
+pub mod keys;
+pub mod library;
+pub mod lint;
+pub mod logging;
+pub mod lsp;
+#[cfg(feature = "lua")]
+pub mod lua;
+pub mod migrate;
+pub mod options;
+pub mod params;
+pub mod pivot;
+pub mod plan;
+#[cfg(feature = "plugins")]
+pub mod plugin;
+pub mod popup;
+pub mod queue;
+pub mod quickfix;
+pub mod record;
+pub mod redact;
+pub mod results;
+pub mod rowdiff;
+pub mod script;
+pub mod shell;
+pub mod signature;
+pub mod snapshot;
+pub mod snippet;
+pub mod sparkline;
+pub mod state;
+pub mod statements;
+pub mod stats;
+pub mod statusline;
+pub mod substitute;
+pub mod suggest;
+pub mod swap;
+pub mod tabs;
+pub mod template;
+pub mod textobject;
+pub mod transfer;
+pub mod tutor;
+pub mod ui;
+pub mod values;
+pub mod vars;
+pub mod width;
+pub mod wizard;
+pub mod workspace;
+
+pub use action::{Action, Bus};
+pub use app::{App, Args, install_panic_hook};
+pub use input::{flush_keys, handle_input, press};
+pub use state::{Command, Message, Mode, State};
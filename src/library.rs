@@ -0,0 +1,324 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Saved queries, for `:save-query` and the `:queries` library.
+//!
+//! Each query is a file in `$XDG_CONFIG_HOME/dbvi/queries`, named after the
+//! query with `.sql` added; a `/` in the name makes folders. A first line
+//! like `-- tags: finance, weekly` tags it. A project can keep its own
+//! queries too, see `workspace`; those are listed first.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use ratatui::{
+    Frame,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::Paragraph,
+};
+
+use crate::config;
+
+const TAGS: &str = "-- tags:";
+
+/// Where saved queries live.
+pub fn queries_dir() -> Option<PathBuf> {
+    config::config_dir().map(|dir| dir.join("queries"))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// Relative to the library, without `.sql`: `weekly/revenue`.
+    pub name: String,
+    pub path: PathBuf,
+    pub tags: Vec<String>,
+    /// The first line of the query itself.
+    pub summary: String,
+    /// From the project's `.dbvi.toml` rather than the config directory.
+    pub project: bool,
+}
+
+impl Entry {
+    fn read(dir: &Path, path: PathBuf) -> io::Result<Self> {
+        let text = fs::read_to_string(&path)?;
+        let name = path
+            .strip_prefix(dir)
+            .unwrap_or(&path)
+            .with_extension("")
+            .to_string_lossy()
+            .replace('\\', "/");
+        let (tags, body) = split_tags(&text);
+        let summary = body
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty() && !line.starts_with("--"))
+            .unwrap_or_default()
+            .to_string();
+        Ok(Self {
+            name,
+            path,
+            tags,
+            summary,
+            project: false,
+        })
+    }
+
+    /// What the filter is matched against.
+    fn haystack(&self) -> String {
+        let mut haystack = self.name.clone();
+        for tag in &self.tags {
+            haystack.push_str(" #");
+            haystack.push_str(tag);
+        }
+        haystack
+    }
+}
+
+/// The tags of a query and the rest of it.
+fn split_tags(text: &str) -> (Vec<String>, &str) {
+    let (first, rest) = text.split_once('\n').unwrap_or((text, ""));
+    match first.trim().strip_prefix(TAGS) {
+        Some(tags) => (
+            tags.split(',')
+                .map(str::trim)
+                .filter(|tag| !tag.is_empty())
+                .map(str::to_string)
+                .collect(),
+            rest,
+        ),
+        None => (Vec::new(), text),
+    }
+}
+
+/// Checks a query name: folders and a file name, nothing that leaves the
+/// library.
+fn check_name(name: &str) -> Result<(), String> {
+    let bad = name.is_empty()
+        || name.starts_with('/')
+        || name.contains('\\')
+        || name
+            .split('/')
+            .any(|part| part.is_empty() || part == "." || part == "..");
+    if bad {
+        return Err(format!("Invalid query name: {name}"));
+    }
+    Ok(())
+}
+
+/// The text of saved query `name`, without its tags, from the first of
+/// `dirs` that has it.
+pub fn read(dirs: &[PathBuf], name: &str) -> Result<String, String> {
+    check_name(name)?;
+    for dir in dirs {
+        match fs::read_to_string(dir.join(format!("{name}.sql"))) {
+            Ok(text) => return Ok(split_tags(&text).1.to_string()),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => return Err(format!("Failed to read query {name}: {err}")),
+        }
+    }
+    Err(format!("No saved query {name}"))
+}
+
+/// Saves `text` as query `name` in `dir`, tagged with `tags` (replacing
+/// the tags it has). Refuses to replace a saved query unless `force`.
+pub fn save(
+    dir: &Path,
+    name: &str,
+    tags: &[String],
+    text: &str,
+    force: bool,
+) -> Result<PathBuf, String> {
+    check_name(name)?;
+    let path = dir.join(format!("{name}.sql"));
+    if path.exists() && !force {
+        return Err(format!("Query {name} exists (add ! to replace it)"));
+    }
+    let (_, body) = split_tags(text);
+    let contents = match tags {
+        [] => format!("{}\n", body.trim_end()),
+        tags => format!("{TAGS} {}\n{}\n", tags.join(", "), body.trim_end()),
+    };
+    let write = || {
+        fs::create_dir_all(path.parent().unwrap_or(dir))?;
+        fs::write(&path, contents)
+    };
+    write().map_err(|err| format!("Failed to save query {name}: {err}"))?;
+    Ok(path)
+}
+
+/// How well `pattern` matches `text`, the higher the better, or `None` if
+/// its characters don't all appear in `text` in order. Case is ignored;
+/// runs of characters and matches at the start of words count for more.
+pub fn fuzzy(pattern: &str, text: &str) -> Option<i64> {
+    let text: Vec<char> = text.chars().collect();
+    let mut score = 0;
+    let mut at = 0;
+    let mut last: Option<usize> = None;
+    for wanted in pattern.chars().filter(|c| !c.is_whitespace()) {
+        let wanted = wanted.to_lowercase().next().unwrap_or(wanted);
+        let found = (at..text.len())
+            .find(|&i| text[i].to_lowercase().next().unwrap_or(text[i]) == wanted)?;
+        score += 1;
+        if last.is_some_and(|last| last + 1 == found) {
+            score += 5;
+        }
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+        score -= (found - at).min(10) as i64;
+        last = Some(found);
+        at = found + 1;
+    }
+    Some(score)
+}
+
+/// The `:queries` pane: the saved queries, narrowed down by what is typed.
+#[derive(Debug, Default)]
+pub struct Library {
+    entries: Vec<Entry>,
+    pub filter: String,
+    /// Indexes into `entries` of those matching, best first.
+    matches: Vec<usize>,
+    pub cursor: usize,
+    scroll: usize,
+}
+
+impl Library {
+    pub fn new(mut entries: Vec<Entry>) -> Self {
+        entries.sort_by(|a, b| b.project.cmp(&a.project).then_with(|| a.name.cmp(&b.name)));
+        let mut library = Self {
+            entries,
+            ..Self::default()
+        };
+        library.update();
+        library
+    }
+
+    /// Every query saved in `dir`, which may not exist yet.
+    pub fn load(dir: &Path) -> io::Result<Vec<Entry>> {
+        let mut entries = Vec::new();
+        let mut dirs = vec![dir.to_path_buf()];
+        while let Some(next) = dirs.pop() {
+            let listing = match fs::read_dir(&next) {
+                Ok(listing) => listing,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(err),
+            };
+            for item in listing {
+                let path = item?.path();
+                if path.is_dir() {
+                    dirs.push(path);
+                } else if path.extension().is_some_and(|ext| ext == "sql") {
+                    entries.push(Entry::read(dir, path)?);
+                }
+            }
+        }
+        Ok(entries)
+    }
+
+    pub fn push(&mut self, c: char) {
+        self.filter.push(c);
+        self.update();
+    }
+
+    pub fn pop(&mut self) {
+        self.filter.pop();
+        self.update();
+    }
+
+    fn update(&mut self) {
+        let mut scored: Vec<(i64, usize)> = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter_map(|(index, entry)| Some((fuzzy(&self.filter, &entry.haystack())?, index)))
+            .collect();
+        // Best first, in name order among equals.
+        scored.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+        self.matches = scored.into_iter().map(|(_, index)| index).collect();
+        self.cursor = 0;
+    }
+
+    /// The queries matching the filter, best first.
+    pub fn matches(&self) -> impl Iterator<Item = &Entry> {
+        self.matches.iter().map(|&index| &self.entries[index])
+    }
+
+    pub fn selected(&self) -> Option<&Entry> {
+        self.matches
+            .get(self.cursor)
+            .map(|&index| &self.entries[index])
+    }
+
+    pub fn move_by(&mut self, rows: isize) {
+        let last = self.matches.len().saturating_sub(1);
+        self.cursor = self.cursor.saturating_add_signed(rows).min(last);
+    }
+
+    pub fn render(&mut self, f: &mut Frame, area: Rect, focused: bool) {
+        let height = area.height.saturating_sub(1) as usize;
+        if self.cursor < self.scroll {
+            self.scroll = self.cursor;
+        } else if height > 0 && self.cursor >= self.scroll + height {
+            self.scroll = self.cursor + 1 - height;
+        }
+        let mut lines = vec![Line::from(vec![
+            Span::styled("> ", Style::default().fg(Color::Cyan)),
+            Span::raw(self.filter.as_str()),
+            Span::styled(
+                format!("  {}/{}", self.matches.len(), self.entries.len()),
+                Style::default().fg(Color::DarkGray),
+            ),
+        ])];
+        if self.entries.is_empty() {
+            lines.push(Line::styled(
+                "No saved queries, :save-query <name> saves the buffer",
+                Style::default().fg(Color::DarkGray),
+            ));
+        }
+        let width = self.matches().map(|entry| entry.name.chars().count()).max();
+        let width = width.unwrap_or_default().min(40);
+        for (row, index) in self
+            .matches
+            .iter()
+            .enumerate()
+            .skip(self.scroll)
+            .take(height)
+        {
+            let entry = &self.entries[*index];
+            let tags: String = entry.tags.iter().map(|tag| format!(" #{tag}")).collect();
+            let name = if entry.project {
+                Style::default().fg(Color::Cyan)
+            } else {
+                Style::default()
+            };
+            let mut line = Line::from(vec![
+                Span::styled(format!("{:<width$}", entry.name), name),
+                Span::styled(tags, Style::default().fg(Color::Yellow)),
+                Span::styled(
+                    format!("  {}", entry.summary),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ]);
+            if focused && row == self.cursor {
+                line = line.patch_style(Style::default().add_modifier(Modifier::REVERSED));
+            }
+            lines.push(line);
+        }
+        f.render_widget(Paragraph::new(lines), area);
+    }
+}
@@ -0,0 +1,246 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A quick look over the buffer for the mistakes that would otherwise cost
+//! a round trip to find: literals and parens left open, trailing commas,
+//! `DELETE` and `UPDATE` without a `WHERE`, and tables and columns the
+//! cached schema doesn't have. Found without the server, so the last two
+//! are only as good as the cache.
+
+use std::ops::Range;
+
+use crate::db::schema::{self, Schema, fold};
+use crate::hover;
+use crate::statements::{self, skip_quoted, words};
+
+/// Something wrong with a span of the buffer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Bytes of the buffer's text.
+    pub range: Range<usize>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// The row of `text` the diagnostic starts on and its bytes in that
+    /// row, cut off at the end of it.
+    pub fn locate(&self, text: &str) -> (usize, Range<usize>) {
+        let before = &text[..self.range.start];
+        let row = before.matches('\n').count();
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        let line_end = text[line_start..]
+            .find('\n')
+            .map_or(text.len(), |i| line_start + i);
+        let end = self.range.end.min(line_end).max(self.range.start);
+        (row, self.range.start - line_start..end - line_start)
+    }
+}
+
+/// Words a list can't end right before.
+const LIST_ENDS: [&str; 12] = [
+    ")",
+    "from",
+    "where",
+    "group",
+    "order",
+    "having",
+    "limit",
+    "union",
+    "returning",
+    "into",
+    "values",
+    "on",
+];
+
+/// Statements whose tables are looked up in the cached schema; those of DDL
+/// may not exist yet.
+const QUERIES: [&str; 8] = [
+    "select", "with", "insert", "update", "delete", "merge", "table", "explain",
+];
+
+/// The problems found in `text`, in order.
+pub fn lint(text: &str, schema: &Schema, search_path: &[String]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    // Tables the text itself creates aren't missing.
+    let created: Vec<String> = schema::ddl_targets(text)
+        .unwrap_or_default()
+        .iter()
+        .map(|name| fold(name))
+        .collect();
+    for statement in statements::split(text) {
+        let offset = statement.as_ptr() as usize - text.as_ptr() as usize;
+        let start = diagnostics.len();
+        lexical(statement, &mut diagnostics);
+        // The rest makes no sense of a statement that doesn't lex.
+        if diagnostics.len() == start {
+            structure(statement, &mut diagnostics);
+            if !schema.relations.is_empty() {
+                names(statement, schema, search_path, &created, &mut diagnostics);
+            }
+        }
+        for diagnostic in &mut diagnostics[start..] {
+            diagnostic.range = diagnostic.range.start + offset..diagnostic.range.end + offset;
+        }
+    }
+    diagnostics.sort_by_key(|diagnostic| diagnostic.range.start);
+    diagnostics
+}
+
+fn push(diagnostics: &mut Vec<Diagnostic>, range: Range<usize>, message: impl Into<String>) {
+    diagnostics.push(Diagnostic {
+        range,
+        message: message.into(),
+    });
+}
+
+/// Literals, quoted names and comments left open, and parens that don't
+/// match.
+fn lexical(sql: &str, diagnostics: &mut Vec<Diagnostic>) {
+    let bytes = sql.as_bytes();
+    let mut open = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        if let Some(end) = skip_quoted(sql, i) {
+            if let Some(what) = unterminated(sql, i, end) {
+                push(diagnostics, i..i + 1, format!("Unterminated {what}"));
+                return;
+            }
+            i = end;
+            continue;
+        }
+        match bytes[i] {
+            b'(' => open.push(i),
+            b')' if open.pop().is_none() => push(diagnostics, i..i + 1, "Unmatched )"),
+            _ => {}
+        }
+        i += 1;
+    }
+    for paren in open {
+        push(diagnostics, paren..paren + 1, "Unclosed (");
+    }
+}
+
+/// What the token `skip_quoted` found at `start` of `sql` is, if it runs to
+/// `end` without being closed.
+fn unterminated(sql: &str, start: usize, end: usize) -> Option<&'static str> {
+    let token = &sql[start..end];
+    let closed = match token.as_bytes()[0] {
+        b'-' => true,
+        b'/' => token.len() >= 4 && token.ends_with("*/"),
+        b'"' => token.len() >= 2 && token.ends_with('"'),
+        // Quotes are doubled inside, so an odd run of them closes it.
+        b'\'' => token[1..].bytes().rev().take_while(|c| *c == b'\'').count() % 2 == 1,
+        _ => {
+            let tag = &token[..token[1..].find('$').map_or(token.len(), |i| i + 2)];
+            token.len() >= 2 * tag.len() && token.ends_with(tag)
+        }
+    };
+    let what = match token.as_bytes()[0] {
+        b'/' => "comment",
+        b'"' => "quoted name",
+        b'\'' => "string",
+        _ => "dollar quoted string",
+    };
+    (!closed).then_some(what)
+}
+
+/// Trailing commas and writes to every row.
+fn structure(sql: &str, diagnostics: &mut Vec<Diagnostic>) {
+    let words = words(sql);
+    for (i, &(at, word)) in words.iter().enumerate() {
+        let next = words.get(i + 1).map(|(_, word)| word.to_lowercase());
+        if word == "," && next.as_deref().is_none_or(|next| LIST_ENDS.contains(&next)) {
+            push(diagnostics, at..at + 1, "Trailing comma");
+        }
+    }
+    let Some(&(at, verb)) = words.first() else {
+        return;
+    };
+    let verb = verb.to_lowercase();
+    let has_where = words
+        .iter()
+        .any(|(_, word)| word.eq_ignore_ascii_case("where"));
+    if !has_where && matches!(verb.as_str(), "delete" | "update") {
+        let message = match verb.as_str() {
+            "delete" => "DELETE without WHERE deletes every row",
+            _ => "UPDATE without WHERE changes every row",
+        };
+        push(diagnostics, at..at + verb.len(), message);
+    }
+}
+
+/// Tables and columns the cached schema doesn't have.
+fn names(
+    sql: &str,
+    schema: &Schema,
+    search_path: &[String],
+    created: &[String],
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let words = words(sql);
+    let is_query = words
+        .first()
+        .is_some_and(|(_, verb)| QUERIES.contains(&verb.to_lowercase().as_str()));
+    if !is_query {
+        return;
+    }
+    // `WITH name AS (`
+    let ctes: Vec<String> = words
+        .windows(3)
+        .filter(|window| window[1].1.eq_ignore_ascii_case("as") && window[2].1 == "(")
+        .map(|window| fold(window[0].1))
+        .collect();
+    let tables = hover::tables(sql);
+    let mut known = Vec::new();
+    for table in &tables {
+        let name = fold(&table.name);
+        let system = name.starts_with("pg_")
+            || name.starts_with("information_schema.")
+            || name.starts_with("pg_catalog.");
+        if system || ctes.contains(&name) || created.contains(&name) {
+            continue;
+        }
+        match schema.resolve(&table.name, search_path) {
+            Some(relation) => known.push((table, relation)),
+            None => push(
+                diagnostics,
+                table.at..table.at + table.name.len(),
+                format!("No table {} in the cached schema", table.name),
+            ),
+        }
+    }
+    for (i, &(at, word)) in words.iter().enumerate() {
+        let Some((prefix, column)) = word.rsplit_once('.') else {
+            continue;
+        };
+        if column == "*" || column.is_empty() || words.get(i + 1).is_some_and(|(_, w)| *w == "(") {
+            continue;
+        }
+        let prefix = fold(prefix);
+        let relation = known.iter().find_map(|(table, relation)| {
+            let alias = table.alias.as_deref().map(fold);
+            (alias.as_ref() == Some(&prefix) || fold(&table.name) == prefix).then_some(*relation)
+        });
+        if let Some(relation) = relation
+            && relation.column(column).is_none()
+        {
+            let at = at + word.len() - column.len();
+            push(
+                diagnostics,
+                at..at + column.len(),
+                format!("No column {column} in {}", relation.qualified()),
+            );
+        }
+    }
+}
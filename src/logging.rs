@@ -0,0 +1,64 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Debug logging to `$XDG_STATE_HOME/dbvi/log/dbvi.<date>.log`, rotated
+//! daily. Never to stdout or stderr, the UI owns the terminal.
+
+use std::io;
+use std::path::PathBuf;
+
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::EnvFilter;
+
+use crate::config;
+
+/// Days of logs kept around.
+const MAX_LOG_FILES: usize = 7;
+
+pub fn log_dir() -> Option<PathBuf> {
+    config::state_dir().map(|dir| dir.join("log"))
+}
+
+/// Starts logging at `level` (`error`, `warn`, `info`, `debug` or `trace`)
+/// for dbvi itself and `warn` for its dependencies. Logs are written from a
+/// background thread that stops when the returned guard is dropped.
+pub fn init(level: &str) -> io::Result<WorkerGuard> {
+    let dir = log_dir().ok_or_else(|| io::Error::other("no directory to log to"))?;
+    let filter = EnvFilter::try_new(format!("warn,dbvi={level}"))
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+    let appender = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix("dbvi")
+        .filename_suffix("log")
+        .max_log_files(MAX_LOG_FILES)
+        .build(dir)
+        .map_err(io::Error::other)?;
+    let (writer, guard) = tracing_appender::non_blocking(appender);
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(writer)
+        .with_ansi(false)
+        .init();
+    Ok(guard)
+}
+
+/// The log file being written to, the most recent one.
+pub fn current_log() -> Option<PathBuf> {
+    std::fs::read_dir(log_dir()?)
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "log"))
+        .max()
+}
@@ -0,0 +1,457 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A client for an external SQL language server, like `sqls` or
+//! `postgres-language-server`, for those who already run one:
+//!
+//! ```toml
+//! [lsp]
+//! command = ["sqls", "-config", "~/.config/sqls/config.yml"]
+//! ```
+//!
+//! The server runs over stdio for the whole session. Every buffer is kept
+//! open in it, synced whole a moment after it changes; what it publishes as
+//! diagnostics is underlined with the linter's, `K` asks it before the
+//! catalog and `<C-n>` asks it for completions. Positions are counted in
+//! chars, which is what servers count in for all but astral plane text.
+
+use std::collections::HashMap;
+use std::io;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde_json::{Value, json};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, ChildStdout};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::action::Bus;
+use crate::config;
+use crate::editor::Buffer;
+use crate::state::Message;
+
+/// How long after a change a buffer is sent to the server.
+const SYNC_INTERVAL: Duration = Duration::from_millis(300);
+/// How long a hover or completion is waited for.
+const TIMEOUT: Duration = Duration::from_secs(3);
+
+/// A problem the server found in a buffer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub row: usize,
+    /// Chars of `row`; a range running past it ends with the row.
+    pub start: usize,
+    pub end: usize,
+    pub message: String,
+}
+
+/// A completion the server offered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+    pub label: String,
+    /// What to put in place of the word being typed.
+    pub insert: String,
+    pub detail: Option<String>,
+}
+
+/// What the server was last sent of a buffer.
+#[derive(Debug)]
+struct Document {
+    uri: String,
+    version: i64,
+    text: String,
+}
+
+type Pending = Arc<Mutex<HashMap<i64, oneshot::Sender<Value>>>>;
+
+#[derive(Debug)]
+pub struct Client {
+    /// The server's command, to say who failed.
+    pub name: String,
+    outgoing: mpsc::UnboundedSender<Value>,
+    pending: Pending,
+    next_id: AtomicI64,
+    /// Set once the server answered `initialize`.
+    ready: Arc<AtomicBool>,
+    _child: Child,
+    /// By buffer id.
+    documents: HashMap<usize, Document>,
+    synced_at: Instant,
+    /// As last published, by URI.
+    diagnostics: HashMap<String, Vec<Diagnostic>>,
+}
+
+impl Client {
+    /// Starts `command` and introduces dbvi to it. What it publishes, and
+    /// its exit, come back on `bus`.
+    pub fn spawn(command: &[String], bus: Bus) -> io::Result<Self> {
+        let Some((program, args)) = command.split_first() else {
+            return Err(io::Error::other("the command is empty"));
+        };
+        let mut child = tokio::process::Command::new(config::expand_home(program))
+            .args(args.iter().map(|arg| config::expand_home(arg)))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()?;
+        let (stdin, stdout) = match (child.stdin.take(), child.stdout.take()) {
+            (Some(stdin), Some(stdout)) => (stdin, stdout),
+            _ => return Err(io::Error::other("no pipes to the server")),
+        };
+        let (outgoing, queue) = mpsc::unbounded_channel();
+        let pending: Pending = Arc::default();
+        let ready = Arc::new(AtomicBool::new(false));
+        tokio::spawn(write_messages(stdin, queue));
+        tokio::spawn(read_messages(
+            stdout,
+            outgoing.clone(),
+            pending.clone(),
+            ready.clone(),
+            bus,
+        ));
+        let root = std::env::current_dir()
+            .ok()
+            .and_then(|dir| url::Url::from_file_path(dir).ok())
+            .map(String::from);
+        let _ = outgoing.send(json!({
+            "jsonrpc": "2.0",
+            "id": 0,
+            "method": "initialize",
+            "params": {
+                "processId": std::process::id(),
+                "clientInfo": { "name": "dbvi" },
+                "rootUri": root,
+                "capabilities": {
+                    "textDocument": {
+                        "synchronization": { "didSave": false },
+                        "hover": { "contentFormat": ["plaintext", "markdown"] },
+                        "completion": { "completionItem": { "snippetSupport": false } },
+                        "publishDiagnostics": {},
+                    },
+                },
+            },
+        }));
+        Ok(Self {
+            name: program.clone(),
+            outgoing,
+            pending,
+            next_id: AtomicI64::new(1),
+            ready,
+            _child: child,
+            documents: HashMap::new(),
+            synced_at: Instant::now(),
+            diagnostics: HashMap::new(),
+        })
+    }
+
+    fn notify(&self, method: &str, params: Value) {
+        let _ = self.outgoing.send(json!({
+            "jsonrpc": "2.0",
+            "method": method,
+            "params": params,
+        }));
+    }
+
+    async fn request(&self, method: &str, params: Value) -> Result<Value, String> {
+        if !self.ready.load(Ordering::Relaxed) {
+            return Err(format!("{} is still starting", self.name));
+        }
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = oneshot::channel();
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.insert(id, sender);
+        }
+        let _ = self.outgoing.send(json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": params,
+        }));
+        let response = match tokio::time::timeout(TIMEOUT, receiver).await {
+            Ok(Ok(response)) => response,
+            Ok(Err(_)) => return Err(format!("{} exited", self.name)),
+            Err(_) => {
+                if let Ok(mut pending) = self.pending.lock() {
+                    pending.remove(&id);
+                }
+                return Err(format!("{} didn't answer in time", self.name));
+            }
+        };
+        match response.get("error") {
+            Some(error) => Err(format!(
+                "{}: {}",
+                self.name,
+                error["message"].as_str().unwrap_or("request failed")
+            )),
+            None => Ok(response["result"].clone()),
+        }
+    }
+
+    /// Sends the buffers that changed since they were last sent, if it's
+    /// time to, and closes those that were closed since.
+    pub fn sync(&mut self, buffers: &[Buffer]) {
+        if self.synced_at.elapsed() >= SYNC_INTERVAL {
+            self.sync_now(buffers);
+        }
+    }
+
+    /// Sends the buffers that changed since they were last sent.
+    pub fn sync_now(&mut self, buffers: &[Buffer]) {
+        if !self.ready.load(Ordering::Relaxed) {
+            return;
+        }
+        self.synced_at = Instant::now();
+        for buffer in buffers {
+            let text = buffer.text();
+            match self.documents.get_mut(&buffer.id) {
+                Some(document) if document.text == text => {}
+                Some(document) => {
+                    document.version += 1;
+                    let params = json!({
+                        "textDocument": { "uri": document.uri, "version": document.version },
+                        "contentChanges": [{ "text": text }],
+                    });
+                    document.text = text;
+                    self.notify("textDocument/didChange", params);
+                }
+                None => {
+                    let document = Document {
+                        uri: uri(buffer),
+                        version: 1,
+                        text,
+                    };
+                    self.notify(
+                        "textDocument/didOpen",
+                        json!({
+                            "textDocument": {
+                                "uri": document.uri,
+                                "languageId": "sql",
+                                "version": document.version,
+                                "text": document.text,
+                            },
+                        }),
+                    );
+                    self.documents.insert(buffer.id, document);
+                }
+            }
+        }
+        let closed: Vec<usize> = self
+            .documents
+            .keys()
+            .filter(|id| !buffers.iter().any(|buffer| buffer.id == **id))
+            .copied()
+            .collect();
+        for id in closed {
+            if let Some(document) = self.documents.remove(&id) {
+                self.diagnostics.remove(&document.uri);
+                self.notify(
+                    "textDocument/didClose",
+                    json!({ "textDocument": { "uri": document.uri } }),
+                );
+            }
+        }
+    }
+
+    /// Keeps what the server published for `uri`.
+    pub fn published(&mut self, uri: String, diagnostics: Vec<Diagnostic>) {
+        self.diagnostics.insert(uri, diagnostics);
+    }
+
+    /// What the server last published for `buffer`.
+    pub fn diagnostics(&self, buffer: &Buffer) -> &[Diagnostic] {
+        self.documents
+            .get(&buffer.id)
+            .and_then(|document| self.diagnostics.get(&document.uri))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    fn position(buffer: &Buffer) -> Value {
+        json!({
+            "textDocument": { "uri": uri(buffer) },
+            "position": { "line": buffer.cursor.row, "character": buffer.cursor.col },
+        })
+    }
+
+    /// What the server says about the word under the cursor of `buffer`.
+    pub async fn hover(&self, buffer: &Buffer) -> Result<Option<String>, String> {
+        let result = self
+            .request("textDocument/hover", Self::position(buffer))
+            .await?;
+        let text = match &result["contents"] {
+            Value::Array(parts) => parts.iter().map(marked).collect::<Vec<_>>().join("\n\n"),
+            contents => marked(contents),
+        };
+        Ok(Some(text).filter(|text| !text.trim().is_empty()))
+    }
+
+    /// The completions the server offers at the cursor of `buffer`.
+    pub async fn completion(&self, buffer: &Buffer) -> Result<Vec<Completion>, String> {
+        let result = self
+            .request("textDocument/completion", Self::position(buffer))
+            .await?;
+        let items = match &result {
+            Value::Array(items) => items,
+            Value::Object(list) => match list.get("items") {
+                Some(Value::Array(items)) => items,
+                _ => return Ok(Vec::new()),
+            },
+            _ => return Ok(Vec::new()),
+        };
+        Ok(items
+            .iter()
+            .filter_map(|item| {
+                let label = item["label"].as_str()?.to_string();
+                let insert = item["textEdit"]["newText"]
+                    .as_str()
+                    .or(item["insertText"].as_str())
+                    .unwrap_or(&label)
+                    .to_string();
+                Some(Completion {
+                    label,
+                    insert,
+                    detail: item["detail"].as_str().map(String::from),
+                })
+            })
+            .collect())
+    }
+}
+
+/// How `buffer` is known to the server: its file, or an untitled document.
+fn uri(buffer: &Buffer) -> String {
+    buffer
+        .path
+        .as_ref()
+        .and_then(|path| std::path::absolute(path).ok())
+        .and_then(|path| url::Url::from_file_path(path).ok())
+        .map_or_else(|| format!("untitled:buffer-{}", buffer.id), String::from)
+}
+
+/// The text of a `MarkedString` or `MarkupContent`.
+fn marked(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        value => value["value"].as_str().unwrap_or_default().to_string(),
+    }
+}
+
+async fn write_messages(mut stdin: ChildStdin, mut queue: mpsc::UnboundedReceiver<Value>) {
+    while let Some(message) = queue.recv().await {
+        let body = message.to_string();
+        let framed = format!("Content-Length: {}\r\n\r\n{body}", body.len());
+        if stdin.write_all(framed.as_bytes()).await.is_err() || stdin.flush().await.is_err() {
+            break;
+        }
+    }
+}
+
+/// One message off `stdout`, `None` once the server is gone.
+async fn read_message(stdout: &mut BufReader<ChildStdout>) -> Option<Value> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if stdout.read_line(&mut header).await.ok()? == 0 {
+            return None;
+        }
+        let header = header.trim();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("content-length")
+        {
+            length = value.trim().parse().ok();
+        }
+    }
+    let mut body = vec![0; length?];
+    stdout.read_exact(&mut body).await.ok()?;
+    // A message that doesn't parse is skipped rather than the server.
+    Some(serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+async fn read_messages(
+    stdout: ChildStdout,
+    outgoing: mpsc::UnboundedSender<Value>,
+    pending: Pending,
+    ready: Arc<AtomicBool>,
+    bus: Bus,
+) {
+    let mut stdout = BufReader::new(stdout);
+    while let Some(message) = read_message(&mut stdout).await {
+        let method = message["method"].as_str();
+        match (&message["id"], method) {
+            // The answer to `initialize`.
+            (Value::Number(id), None) if id.as_i64() == Some(0) => {
+                let _ = outgoing.send(json!({
+                    "jsonrpc": "2.0",
+                    "method": "initialized",
+                    "params": {},
+                }));
+                ready.store(true, Ordering::Relaxed);
+            }
+            (Value::Number(id), None) => {
+                let sender = id.as_i64().and_then(|id| pending.lock().ok()?.remove(&id));
+                if let Some(sender) = sender {
+                    let _ = sender.send(message);
+                }
+            }
+            // Requests from the server, like `workspace/configuration`, get
+            // an empty answer.
+            (id @ (Value::Number(_) | Value::String(_)), Some(_)) => {
+                let _ = outgoing.send(json!({ "jsonrpc": "2.0", "id": id, "result": null }));
+            }
+            (_, Some("textDocument/publishDiagnostics")) => {
+                let params = &message["params"];
+                let Some(uri) = params["uri"].as_str() else {
+                    continue;
+                };
+                let diagnostics = params["diagnostics"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(diagnostic)
+                    .collect();
+                let _ = bus.send(Message::LspDiagnostics {
+                    uri: uri.to_string(),
+                    diagnostics,
+                });
+            }
+            _ => {}
+        }
+    }
+    let _ = bus.send(Message::LspExited);
+}
+
+fn diagnostic(value: &Value) -> Option<Diagnostic> {
+    let range = &value["range"];
+    let number = |value: &Value| value.as_u64().map(|n| n as usize);
+    let row = number(&range["start"]["line"])?;
+    let start = number(&range["start"]["character"])?;
+    let end = match number(&range["end"]["line"]) {
+        Some(line) if line == row => number(&range["end"]["character"]).unwrap_or(start + 1),
+        _ => usize::MAX,
+    };
+    let message = value["message"].as_str()?;
+    Some(Diagnostic {
+        row,
+        start,
+        end: end.max(start + 1),
+        message: match value["source"].as_str() {
+            Some(source) => format!("{source}: {message}"),
+            None => message.to_string(),
+        },
+    })
+}
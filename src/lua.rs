@@ -0,0 +1,348 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `$XDG_CONFIG_HOME/dbvi/init.lua`, for configuration that outgrows
+//! `config.toml`. Built with the `lua` feature.
+//!
+//! Scripts get a `dbvi` table:
+//!
+//! - `dbvi.map(mode, lhs, rhs)` maps the keys `lhs` (`"Q"`, `"<C-r>"`,
+//!   `"gb"`, `"<leader>e"`) in mode `"n"`, `"i"` or `"c"` to the keys
+//!   `rhs`, which aren't mapped again, to a function, or to a table with a
+//!   `command` or an `insert` as in `config.toml`. See `keymap.rs` for how
+//!   sequences are waited for.
+//! - `dbvi.action(name, fn)` adds an ex command `:name`, called with the
+//!   rest of the line.
+//! - `dbvi.on(event, fn)` hooks `fn` to `"on_connect"` (with a table about
+//!   the connection), `"pre_query"` (with the SQL, returning other SQL to
+//!   run instead or `false` to not run it) or `"post_query"` (with the SQL
+//!   and a table of `rows`, `ms` and `error`).
+//! - `dbvi.command(line)`, `dbvi.feedkeys(keys)` and `dbvi.status(text)` do
+//!   what typing `:line`, typing `keys` and a message in the status line
+//!   would. They are queued, so take effect once the script returns.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use crossterm::event::KeyEvent;
+use mlua::{Function, Value};
+
+use crate::action::{Action, Bus};
+use crate::keymap::{Keymap, Lookup, Mapping, Rhs};
+use crate::state::{Command, Message, Mode};
+use crate::{commands, keys};
+
+const HOOKS: [&str; 3] = ["on_connect", "pre_query", "post_query"];
+
+#[derive(Debug, Default)]
+struct Registry {
+    maps: Keymap,
+    actions: HashMap<String, Function>,
+    hooks: HashMap<&'static str, Vec<Function>>,
+}
+
+impl Registry {
+    fn hooks(&self, hook: &str) -> Vec<Function> {
+        self.hooks.get(hook).cloned().unwrap_or_default()
+    }
+}
+
+/// The first line of `err`, without the traceback, for the status line.
+fn message(err: &mlua::Error) -> String {
+    let err = err.to_string();
+    err.lines().next().unwrap_or_default().to_string()
+}
+
+fn parse_mode(mode: &str) -> mlua::Result<Mode> {
+    match mode {
+        "n" => Ok(Mode::Normal),
+        "i" => Ok(Mode::Insert),
+        "c" => Ok(Mode::Command),
+        _ => Err(mlua::Error::runtime(format!(
+            "no mode `{mode}`, only n, i and c"
+        ))),
+    }
+}
+
+#[derive(Debug)]
+pub struct Lua {
+    lua: mlua::Lua,
+    registry: Arc<Mutex<Registry>>,
+    bus: Bus,
+}
+
+impl Lua {
+    /// A Lua state with the `dbvi` table, queueing what scripts do on `bus`.
+    pub fn new(bus: Bus) -> mlua::Result<Self> {
+        let lua = mlua::Lua::new();
+        let registry = Arc::new(Mutex::new(Registry::default()));
+        let dbvi = lua.create_table()?;
+
+        let maps = Arc::clone(&registry);
+        dbvi.set(
+            "map",
+            lua.create_function(move |_, (mode, lhs, rhs): (String, String, Value)| {
+                let mode = parse_mode(&mode)?;
+                let mut registry = maps.lock().expect("lua registry lock");
+                let lhs = registry.maps.parse(&lhs);
+                if lhs.is_empty() {
+                    return Err(mlua::Error::runtime("map some keys"));
+                }
+                let rhs = match rhs {
+                    Value::String(keys) => Rhs::Keys(registry.maps.parse(&keys.to_str()?)),
+                    Value::Function(function) => Rhs::Lua(function),
+                    Value::Table(table) => {
+                        if let Some(command) = table.get::<Option<String>>("command")? {
+                            Mapping::Command { command }.rhs(&registry.maps)
+                        } else if let Some(insert) = table.get::<Option<String>>("insert")? {
+                            Mapping::Insert { insert }.rhs(&registry.maps)
+                        } else {
+                            return Err(mlua::Error::runtime("map to a command or an insert"));
+                        }
+                    }
+                    _ => return Err(mlua::Error::runtime("map to keys or a function")),
+                };
+                registry.maps.map(mode, lhs, rhs);
+                Ok(())
+            })?,
+        )?;
+
+        let actions = Arc::clone(&registry);
+        dbvi.set(
+            "action",
+            lua.create_function(move |_, (name, function): (String, Function)| {
+                actions
+                    .lock()
+                    .expect("lua registry lock")
+                    .actions
+                    .insert(name, function);
+                Ok(())
+            })?,
+        )?;
+
+        let hooks = Arc::clone(&registry);
+        dbvi.set(
+            "on",
+            lua.create_function(move |_, (event, function): (String, Function)| {
+                let Some(hook) = HOOKS.iter().find(|hook| **hook == event) else {
+                    return Err(mlua::Error::runtime(format!(
+                        "no event `{event}`, only {}",
+                        HOOKS.join(", ")
+                    )));
+                };
+                hooks
+                    .lock()
+                    .expect("lua registry lock")
+                    .hooks
+                    .entry(hook)
+                    .or_default()
+                    .push(function);
+                Ok(())
+            })?,
+        )?;
+
+        let sender = bus.clone();
+        dbvi.set(
+            "command",
+            lua.create_function(move |_, line: String| {
+                let command =
+                    commands::parse(line.trim_start_matches(':')).map_err(mlua::Error::runtime)?;
+                let _ = sender.send(command);
+                Ok(())
+            })?,
+        )?;
+
+        let sender = bus.clone();
+        dbvi.set(
+            "feedkeys",
+            lua.create_function(move |_, keys: String| {
+                let _ = sender.send(Action::Keys(keys::parse(&keys)));
+                Ok(())
+            })?,
+        )?;
+
+        let sender = bus.clone();
+        dbvi.set(
+            "status",
+            lua.create_function(move |_, text: String| {
+                let _ = sender.send(Message::Status(text));
+                Ok(())
+            })?,
+        )?;
+
+        lua.globals().set("dbvi", dbvi)?;
+        Ok(Self { lua, registry, bus })
+    }
+
+    /// Runs the script at `path`.
+    pub fn run_file(&self, path: &Path) -> Result<(), String> {
+        let script = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+        self.lua
+            .load(script)
+            .set_name(format!("@{}", path.display()))
+            .exec()
+            .map_err(|err| message(&err))
+    }
+
+    /// Runs `code`, returning what it evaluates to, for `:lua`.
+    pub fn eval(&self, code: &str) -> Result<String, String> {
+        let values: mlua::MultiValue = self
+            .lua
+            .load(code)
+            .set_name("=:lua")
+            .eval()
+            .map_err(|err| message(&err))?;
+        let values: Vec<String> = values
+            .iter()
+            .map(|value| value.to_string().unwrap_or_else(|err| err.to_string()))
+            .collect();
+        Ok(values.join("  "))
+    }
+
+    /// Sets the leader key of the mappings made from now on.
+    pub fn set_leader(&self, leader: &str) {
+        let mut registry = self.registry.lock().expect("lua registry lock");
+        registry.maps.set_leader(leader);
+    }
+
+    /// What `keys` are among the mappings of `mode`.
+    pub fn lookup(&self, mode: Mode, keys: &[KeyEvent]) -> Lookup {
+        let registry = self.registry.lock().expect("lua registry lock");
+        registry.maps.lookup(mode, keys)
+    }
+
+    /// Calls `function`, a mapping's.
+    pub fn call(&self, function: &Function) {
+        self.report(function.call::<()>(()));
+    }
+
+    /// The command for an ex command `line` that an action defines.
+    pub fn parse(&self, line: &str) -> Option<Command> {
+        let line = line.trim();
+        let (name, args) = line.split_once(' ').unwrap_or((line, ""));
+        let registry = self.registry.lock().expect("lua registry lock");
+        registry
+            .actions
+            .contains_key(name)
+            .then(|| Command::LuaAction {
+                name: name.to_string(),
+                args: args.trim().to_string(),
+            })
+    }
+
+    /// Runs the action `name` with `args`.
+    pub fn action(&self, name: &str, args: &str) {
+        let function = self
+            .registry
+            .lock()
+            .expect("lua registry lock")
+            .actions
+            .get(name)
+            .cloned();
+        if let Some(function) = function {
+            self.report(function.call::<()>(args));
+        }
+    }
+
+    /// Calls the `on_connect` hooks with `info` about the connection.
+    pub fn on_connect(&self, info: &[(&str, String)]) {
+        let hooks = self
+            .registry
+            .lock()
+            .expect("lua registry lock")
+            .hooks("on_connect");
+        if hooks.is_empty() {
+            return;
+        }
+        let table = self.lua.create_table().and_then(|table| {
+            for (key, value) in info {
+                table.set(*key, value.as_str())?;
+            }
+            Ok(table)
+        });
+        match table {
+            Ok(table) => {
+                for hook in hooks {
+                    self.report(hook.call::<()>(&table));
+                }
+            }
+            Err(err) => self.report::<()>(Err(err)),
+        }
+    }
+
+    /// Passes `sql` through the `pre_query` hooks: `Ok` with what to run,
+    /// or `Err` with why not to run anything.
+    pub fn pre_query(&self, sql: String) -> Result<String, String> {
+        let hooks = self
+            .registry
+            .lock()
+            .expect("lua registry lock")
+            .hooks("pre_query");
+        let mut sql = sql;
+        for hook in hooks {
+            match hook.call::<Value>(sql.as_str()) {
+                Ok(Value::Nil | Value::Boolean(true)) => {}
+                Ok(Value::Boolean(false)) => return Err("Query stopped by a pre_query hook".into()),
+                Ok(Value::String(replacement)) => sql = replacement.to_string_lossy(),
+                Ok(other) => {
+                    return Err(format!(
+                        "pre_query hook returned a {}, not SQL or false",
+                        other.type_name()
+                    ));
+                }
+                Err(err) => return Err(format!("pre_query hook failed: {}", message(&err))),
+            }
+        }
+        Ok(sql)
+    }
+
+    /// Calls the `post_query` hooks about a statement that was run.
+    pub fn post_query(&self, sql: &str, elapsed_ms: f64, outcome: &Result<u64, String>) {
+        let hooks = self
+            .registry
+            .lock()
+            .expect("lua registry lock")
+            .hooks("post_query");
+        if hooks.is_empty() {
+            return;
+        }
+        let table = self.lua.create_table().and_then(|table| {
+            table.set("ms", elapsed_ms)?;
+            match outcome {
+                Ok(rows) => table.set("rows", *rows)?,
+                Err(err) => table.set("error", err.as_str())?,
+            }
+            Ok(table)
+        });
+        match table {
+            Ok(table) => {
+                for hook in hooks {
+                    self.report(hook.call::<()>((sql, &table)));
+                }
+            }
+            Err(err) => self.report::<()>(Err(err)),
+        }
+    }
+
+    /// Shows a script's error in the status line.
+    fn report<T>(&self, result: mlua::Result<T>) {
+        if let Err(err) = result {
+            tracing::warn!(error = %err, "lua error");
+            let _ = self
+                .bus
+                .send(Message::Status(format!("Lua: {}", message(&err))));
+        }
+    }
+}
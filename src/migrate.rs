@@ -0,0 +1,363 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `:migrate`: a directory of numbered SQL files, applied in order and
+//! recorded in a table, enough for a project that doesn't want a separate
+//! tool.
+//!
+//! Each file is named for its version and what it does, like
+//! `0003_add_status.sql`. What follows a `-- migrate:down` line, if there
+//! is one, undoes the rest:
+//!
+//! ```sql
+//! ALTER TABLE orders ADD COLUMN status text;
+//!
+//! -- migrate:down
+//! ALTER TABLE orders DROP COLUMN status;
+//! ```
+//!
+//! Each migration runs in a transaction of its own with the row recording
+//! it, so one that fails leaves nothing behind and stops the rest. A dry run
+//! runs them all in a single transaction, the table recording them
+//! included, each under a savepoint, and rolls it back at the end.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use sha2::{Digest, Sha256};
+use sqlx::pool::PoolConnection;
+use sqlx::{Acquire, PgConnection, PgPool, Postgres, Transaction};
+
+use crate::results::{Column, ResultSet};
+
+/// Where applied migrations are recorded.
+pub const TABLE: &str = "dbvi_migrations";
+
+/// The line that starts the part undoing a migration.
+const DOWN: &str = "-- migrate:down";
+
+/// What `:migrate` does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// List the migrations, applied or not.
+    Status,
+    /// Apply every migration not applied yet.
+    Up,
+    /// Undo the last migration applied.
+    Down,
+}
+
+impl Action {
+    pub fn parse(args: &str) -> Result<Self, String> {
+        match args.trim() {
+            "" | "status" => Ok(Self::Status),
+            "up" => Ok(Self::Up),
+            "down" => Ok(Self::Down),
+            _ => Err("Usage: migrate [status|up|down]".into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Migration {
+    pub version: i64,
+    pub name: String,
+    pub up: String,
+    pub down: Option<String>,
+    /// Of the whole file, to tell when one applied has been changed since.
+    pub checksum: String,
+}
+
+impl Migration {
+    /// Parses the migration in the file `file_name` with `text`.
+    pub fn parse(file_name: &str, text: &str) -> Result<Self, String> {
+        let stem = file_name.strip_suffix(".sql").unwrap_or(file_name);
+        let digits = stem.chars().take_while(char::is_ascii_digit).count();
+        let version = stem[..digits]
+            .parse()
+            .map_err(|_| format!("{file_name} doesn't start with a version number"))?;
+        let name = stem[digits..].trim_start_matches(['_', '-']).to_string();
+        let (up, down) = match text.lines().position(|line| line.trim() == DOWN) {
+            Some(at) => {
+                let lines: Vec<&str> = text.lines().collect();
+                let down = lines[at + 1..].join("\n");
+                (lines[..at].join("\n"), Some(down.trim().to_string()))
+            }
+            None => (text.to_string(), None),
+        };
+        let checksum = Sha256::digest(text.as_bytes())
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+        Ok(Self {
+            version,
+            name,
+            up: up.trim().to_string(),
+            down: down.filter(|down| !down.is_empty()),
+            checksum,
+        })
+    }
+}
+
+/// The directory of migrations when the workspace doesn't name one.
+pub fn default_dir() -> PathBuf {
+    PathBuf::from("migrations")
+}
+
+/// The migrations in `dir`, by version.
+pub fn load(dir: &Path) -> Result<Vec<Migration>, String> {
+    let entries = fs::read_dir(dir).map_err(|err| format!("{}: {err}", dir.display()))?;
+    let mut migrations: Vec<Migration> = Vec::new();
+    for entry in entries {
+        let path = entry
+            .map_err(|err| format!("{}: {err}", dir.display()))?
+            .path();
+        let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if !file_name.ends_with(".sql") {
+            continue;
+        }
+        let text = fs::read_to_string(&path).map_err(|err| format!("{}: {err}", path.display()))?;
+        migrations.push(Migration::parse(file_name, &text)?);
+    }
+    migrations.sort_by_key(|migration| migration.version);
+    if let Some(pair) = migrations
+        .windows(2)
+        .find(|pair| pair[0].version == pair[1].version)
+    {
+        return Err(format!(
+            "Two migrations are version {}: {} and {}",
+            pair[0].version, pair[0].name, pair[1].name
+        ));
+    }
+    Ok(migrations)
+}
+
+/// A migration recorded as applied.
+#[derive(Debug, Clone, PartialEq, Eq, sqlx::FromRow)]
+pub struct Applied {
+    pub version: i64,
+    pub name: String,
+    pub checksum: String,
+    pub applied_at: String,
+}
+
+/// Where `:migrate up` and `:migrate down` run: a connection of the pool,
+/// or for a dry run a transaction on one, for the lot to be rolled back by
+/// [`Runner::finish`].
+pub enum Runner {
+    Connection(PoolConnection<Postgres>),
+    DryRun(Transaction<'static, Postgres>),
+}
+
+impl Runner {
+    pub async fn new(pool: &PgPool, dry_run: bool) -> Result<Self, sqlx::Error> {
+        Ok(match dry_run {
+            true => Self::DryRun(pool.begin().await?),
+            false => Self::Connection(pool.acquire().await?),
+        })
+    }
+
+    pub fn connection(&mut self) -> &mut PgConnection {
+        match self {
+            Self::Connection(connection) => connection,
+            Self::DryRun(transaction) => transaction,
+        }
+    }
+
+    /// Rolls back everything a dry run did.
+    pub async fn finish(self) -> Result<(), sqlx::Error> {
+        match self {
+            Self::Connection(_) => Ok(()),
+            Self::DryRun(transaction) => transaction.rollback().await,
+        }
+    }
+}
+
+/// Creates the table recording the migrations if it isn't there yet.
+pub async fn ensure_table(connection: &mut PgConnection) -> Result<(), sqlx::Error> {
+    sqlx::raw_sql(&format!(
+        "CREATE TABLE IF NOT EXISTS {TABLE} (\
+            version bigint PRIMARY KEY, \
+            name text NOT NULL, \
+            checksum text NOT NULL, \
+            applied_at timestamptz NOT NULL DEFAULT now())"
+    ))
+    .execute(connection)
+    .await?;
+    Ok(())
+}
+
+/// The migrations applied so far, by version, none if the table isn't
+/// there yet.
+pub async fn applied(pool: &PgPool) -> Result<Vec<Applied>, sqlx::Error> {
+    let exists: bool = sqlx::query_scalar("SELECT to_regclass($1) IS NOT NULL")
+        .bind(TABLE)
+        .fetch_one(pool)
+        .await?;
+    if !exists {
+        return Ok(Vec::new());
+    }
+    sqlx::query_as(&format!(
+        "SELECT version, name, checksum, \
+            to_char(applied_at, 'YYYY-MM-DD HH24:MI:SS') AS applied_at \
+         FROM {TABLE} ORDER BY version"
+    ))
+    .fetch_all(pool)
+    .await
+}
+
+/// The migrations not applied yet, in the order to apply them.
+pub fn pending<'a>(migrations: &'a [Migration], applied: &[Applied]) -> Vec<&'a Migration> {
+    migrations
+        .iter()
+        .filter(|migration| !applied.iter().any(|a| a.version == migration.version))
+        .collect()
+}
+
+/// Every migration in the directory or applied, with whether it is
+/// `applied`, `pending`, `changed` since it was applied, or `missing` from
+/// the directory.
+pub fn status(migrations: &[Migration], applied: &[Applied]) -> ResultSet {
+    let by_version: HashMap<i64, &Applied> = applied.iter().map(|a| (a.version, a)).collect();
+    let mut rows: Vec<(i64, &str, &str, Option<&str>)> = migrations
+        .iter()
+        .map(|migration| match by_version.get(&migration.version) {
+            Some(a) if a.checksum != migration.checksum => (
+                migration.version,
+                migration.name.as_str(),
+                "changed",
+                Some(a.applied_at.as_str()),
+            ),
+            Some(a) => (
+                migration.version,
+                migration.name.as_str(),
+                "applied",
+                Some(a.applied_at.as_str()),
+            ),
+            None => (migration.version, migration.name.as_str(), "pending", None),
+        })
+        .collect();
+    for a in applied {
+        if !migrations.iter().any(|m| m.version == a.version) {
+            rows.push((a.version, &a.name, "missing", Some(&a.applied_at)));
+        }
+    }
+    rows.sort_by_key(|row| row.0);
+    let mut results = ResultSet::new(vec![
+        Column::new("version", "int8"),
+        Column::new("name", "text"),
+        Column::new("state", "text"),
+        Column::new("applied_at", "timestamptz"),
+    ]);
+    for (version, name, state, applied_at) in rows {
+        let version = version.to_string();
+        results
+            .rows
+            .push([Some(version.as_str()), Some(name), Some(state), applied_at]);
+    }
+    results
+}
+
+/// How running one migration went, a row of the log `:migrate up` and
+/// `:migrate down` show.
+#[derive(Debug, Clone)]
+pub struct Step {
+    pub version: i64,
+    pub name: String,
+    pub elapsed: Duration,
+    pub outcome: Result<(), String>,
+}
+
+/// The log of `steps`, `direction` being `up` or `down`.
+pub fn log(direction: &str, steps: &[Step]) -> ResultSet {
+    let mut results = ResultSet::new(vec![
+        Column::new("version", "int8"),
+        Column::new("name", "text"),
+        Column::new("direction", "text"),
+        Column::new("elapsed", "text"),
+        Column::new("outcome", "text"),
+    ]);
+    for step in steps {
+        let outcome = match &step.outcome {
+            Ok(()) => "ok".to_string(),
+            Err(err) => err.clone(),
+        };
+        results.rows.push([
+            Some(step.version.to_string()),
+            Some(step.name.clone()),
+            Some(direction.to_string()),
+            Some(format!("{:.1?}", step.elapsed)),
+            Some(outcome),
+        ]);
+    }
+    results
+}
+
+/// Applies `migration` and records it, in one transaction, or under a
+/// savepoint in the one `connection` has open.
+pub async fn apply(connection: &mut PgConnection, migration: &Migration) -> Step {
+    let started = Instant::now();
+    let outcome = async {
+        let mut transaction = connection.begin().await?;
+        sqlx::raw_sql(&migration.up)
+            .execute(&mut *transaction)
+            .await?;
+        sqlx::query(&format!(
+            "INSERT INTO {TABLE} (version, name, checksum) VALUES ($1, $2, $3)"
+        ))
+        .bind(migration.version)
+        .bind(&migration.name)
+        .bind(&migration.checksum)
+        .execute(&mut *transaction)
+        .await?;
+        transaction.commit().await
+    }
+    .await;
+    Step {
+        version: migration.version,
+        name: migration.name.clone(),
+        elapsed: started.elapsed(),
+        outcome: outcome.map_err(|err| err.to_string()),
+    }
+}
+
+/// Undoes `migration` and forgets it was applied, in one transaction, or
+/// under a savepoint in the one `connection` has open.
+pub async fn revert(connection: &mut PgConnection, migration: &Migration) -> Step {
+    let started = Instant::now();
+    let outcome = match &migration.down {
+        Some(down) => async {
+            let mut transaction = connection.begin().await?;
+            sqlx::raw_sql(down).execute(&mut *transaction).await?;
+            sqlx::query(&format!("DELETE FROM {TABLE} WHERE version = $1"))
+                .bind(migration.version)
+                .execute(&mut *transaction)
+                .await?;
+            transaction.commit().await
+        }
+        .await
+        .map_err(|err| err.to_string()),
+        None => Err(format!("no {DOWN} section to undo it with")),
+    };
+    Step {
+        version: migration.version,
+        name: migration.name.clone(),
+        elapsed: started.elapsed(),
+        outcome,
+    }
+}
@@ -0,0 +1,544 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Options, set with `:set` as in vim or from the `[options]` table of the
+//! config:
+//!
+//! - `:set wrap`, `:set nowrap`, `:set wrap!` turn a boolean on, off or
+//!   around.
+//! - `:set split=40` (or `:set split 40`) sets a number or a choice.
+//! - `:set split?` shows the value, as does `:set split` for anything but
+//!   a boolean.
+//! - `:set` alone lists them all.
+
+use std::fmt;
+use std::time::Duration;
+
+use chrono_tz::Tz;
+
+use crate::clipboard::Clipboard;
+use crate::config;
+use crate::dialect::Dialect;
+use crate::results;
+use crate::state::State;
+use crate::ui;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Bool,
+    Number {
+        min: i64,
+        max: i64,
+    },
+    Choice(&'static [&'static str]),
+    /// Like `30s` or `500ms`, `0` to turn it off.
+    Duration,
+    /// An IANA time zone like `America/Chicago`, or `default` for the
+    /// server's own.
+    Zone,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Bool(bool),
+    Number(i64),
+    Choice(&'static str),
+    Duration(Option<Duration>),
+    Zone(Option<Tz>),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bool(value) => write!(f, "{value}"),
+            Self::Number(value) => write!(f, "{value}"),
+            Self::Choice(value) => f.write_str(value),
+            Self::Duration(Some(value)) => write!(f, "{value:?}"),
+            Self::Duration(None) => f.write_str("0 (disabled)"),
+            Self::Zone(Some(zone)) => f.write_str(zone.name()),
+            Self::Zone(None) => f.write_str("default"),
+        }
+    }
+}
+
+impl Kind {
+    /// Parses `text` as a value of this kind.
+    pub fn parse(self, text: &str) -> Result<Value, String> {
+        let text = text.trim();
+        match self {
+            Self::Bool => match text {
+                "true" | "on" | "yes" | "1" => Ok(Value::Bool(true)),
+                "false" | "off" | "no" | "0" => Ok(Value::Bool(false)),
+                _ => Err(format!("Expected true or false, not {text}")),
+            },
+            Self::Number { min, max } => match text.parse() {
+                Ok(value) if (min..=max).contains(&value) => Ok(Value::Number(value)),
+                _ => Err(format!("Expected a number from {min} to {max}, not {text}")),
+            },
+            Self::Choice(choices) => choices
+                .iter()
+                .find(|choice| **choice == text)
+                .map(|choice| Value::Choice(choice))
+                .ok_or_else(|| format!("Expected one of {}, not {text}", choices.join(", "))),
+            Self::Duration => config::parse_duration(text)
+                .map(|duration| Value::Duration((!duration.is_zero()).then_some(duration))),
+            Self::Zone if text == "default" => Ok(Value::Zone(None)),
+            Self::Zone => text
+                .parse::<Tz>()
+                .map(|zone| Value::Zone(Some(zone)))
+                .map_err(|_| format!("Unknown time zone {text}")),
+        }
+    }
+}
+
+pub struct Opt {
+    pub name: &'static str,
+    /// Abbreviation, like `nu` for `number`.
+    pub short: Option<&'static str>,
+    pub kind: Kind,
+    get: fn(&State) -> Value,
+    set: fn(&mut State, Value) -> Result<(), String>,
+}
+
+impl Opt {
+    /// Whether setting it takes a `SET` on the server, [`Opt::sql`].
+    pub fn on_server(&self) -> bool {
+        matches!(self.name, "statement_timeout" | "readonly" | "timezone")
+    }
+
+    /// The `SET` that makes `value` so on the server, for the options that
+    /// are settings there too.
+    pub fn sql(&self, value: &Value) -> Option<String> {
+        match (self.name, value) {
+            ("statement_timeout", Value::Duration(timeout)) => Some(format!(
+                "SET statement_timeout = {}",
+                timeout.unwrap_or_default().as_millis()
+            )),
+            ("readonly", Value::Bool(on)) => Some(format!(
+                "SET default_transaction_read_only = {}",
+                if *on { "on" } else { "off" }
+            )),
+            ("timezone", Value::Zone(Some(zone))) => {
+                Some(format!("SET TimeZone = '{}'", zone.name()))
+            }
+            ("timezone", Value::Zone(None)) => Some("RESET TimeZone".into()),
+            _ => None,
+        }
+    }
+
+    pub fn get(&self, state: &State) -> Value {
+        (self.get)(state)
+    }
+
+    /// Sets the option to `value`, which must be of its kind.
+    pub fn set(&self, state: &mut State, value: Value) -> Result<(), String> {
+        (self.set)(state, value)
+    }
+
+    /// The option as `:set` shows it: `wrap` or `nowrap` for a boolean,
+    /// `name=value` otherwise.
+    pub fn show(&self, state: &State) -> String {
+        match self.get(state) {
+            Value::Bool(true) => self.name.to_string(),
+            Value::Bool(false) => format!("no{}", self.name),
+            value => format!("{}={value}", self.name),
+        }
+    }
+}
+
+impl fmt::Debug for Opt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name)
+    }
+}
+
+pub const OPTIONS: &[Opt] = &[
+    // A query that reads without a `LIMIT` of its own gets this one, 0 for
+    // none.
+    Opt {
+        name: "autolimit",
+        short: None,
+        kind: Kind::Number {
+            min: 0,
+            max: i64::MAX,
+        },
+        get: |state| Value::Number(state.auto_limit as i64),
+        set: |state, value| {
+            if let Value::Number(rows) = value {
+                state.auto_limit = rows as u64;
+            }
+            Ok(())
+        },
+    },
+    Opt {
+        name: "autopairs",
+        short: Some("ap"),
+        kind: Kind::Bool,
+        get: |state| Value::Bool(state.auto_pairs),
+        set: |state, value| {
+            state.auto_pairs = value == Value::Bool(true);
+            Ok(())
+        },
+    },
+    Opt {
+        name: "clipboard",
+        short: Some("cb"),
+        kind: Kind::Choice(&["auto", "system", "osc52", "none"]),
+        get: |state| Value::Choice(state.clipboard.name()),
+        set: |state, value| {
+            state.clipboard = Clipboard::from_name(&value.to_string())?;
+            Ok(())
+        },
+    },
+    // A query the planner expects to cost more than this asks before it
+    // runs, 0 for no limit; the plain `EXPLAIN` that finds out costs little.
+    Opt {
+        name: "costguard",
+        short: None,
+        kind: Kind::Number {
+            min: 0,
+            max: i64::MAX,
+        },
+        get: |state| Value::Number(state.cost_guard as i64),
+        set: |state, value| {
+            if let Value::Number(cost) = value {
+                state.cost_guard = cost as u64;
+            }
+            Ok(())
+        },
+    },
+    // Whether the terminal's cursor changes shape with the mode.
+    Opt {
+        name: "cursorshape",
+        short: None,
+        kind: Kind::Bool,
+        get: |state| Value::Bool(state.cursor_shape),
+        set: |state, value| {
+            state.cursor_shape = value == Value::Bool(true);
+            Ok(())
+        },
+    },
+    Opt {
+        name: "dialect",
+        short: None,
+        kind: Kind::Choice(&["postgres", "redshift", "greenplum"]),
+        get: |state| {
+            Value::Choice(match state.dialect {
+                Dialect::Postgres => "postgres",
+                Dialect::Redshift => "redshift",
+                Dialect::Greenplum => "greenplum",
+            })
+        },
+        set: |state, value| {
+            state.dialect = match value {
+                Value::Choice("redshift") => Dialect::Redshift,
+                Value::Choice("greenplum") => Dialect::Greenplum,
+                _ => Dialect::Postgres,
+            };
+            Ok(())
+        },
+    },
+    // Queries run in a transaction that is rolled back straight after, to
+    // see what a data fix would touch. Only sequences and what else isn't
+    // transactional keep what they did.
+    Opt {
+        name: "dryrun",
+        short: None,
+        kind: Kind::Bool,
+        get: |state| Value::Bool(state.dry_run),
+        set: |state, value| {
+            state.dry_run = value == Value::Bool(true);
+            Ok(())
+        },
+    },
+    // Whether `:export html` adds a script to sort by a column.
+    // Like `costguard`, for the rows the planner expects.
+    Opt {
+        name: "estrowguard",
+        short: None,
+        kind: Kind::Number {
+            min: 0,
+            max: i64::MAX,
+        },
+        get: |state| Value::Number(state.est_row_guard as i64),
+        set: |state, value| {
+            if let Value::Number(rows) = value {
+                state.est_row_guard = rows as u64;
+            }
+            Ok(())
+        },
+    },
+    Opt {
+        name: "htmlsort",
+        short: None,
+        kind: Kind::Bool,
+        get: |state| Value::Bool(state.html_sort),
+        set: |state, value| {
+            state.html_sort = value == Value::Bool(true);
+            Ok(())
+        },
+    },
+    // Of the focused pane, as vim's are of the window.
+    Opt {
+        name: "lint",
+        short: None,
+        kind: Kind::Bool,
+        get: |state| Value::Bool(state.lint),
+        set: |state, value| {
+            state.lint = value == Value::Bool(true);
+            Ok(())
+        },
+    },
+    Opt {
+        name: "number",
+        short: Some("nu"),
+        kind: Kind::Bool,
+        get: |state| Value::Bool(state.gutter().number),
+        set: |state, value| {
+            state.gutter_mut().number = value == Value::Bool(true);
+            Ok(())
+        },
+    },
+    // A failed statement in a transaction rolls back to a savepoint taken
+    // just before it, as psql's ON_ERROR_ROLLBACK does.
+    Opt {
+        name: "onerrorrollback",
+        short: Some("oer"),
+        kind: Kind::Bool,
+        get: |state| Value::Bool(state.on_error_rollback),
+        set: |state, value| {
+            state.on_error_rollback = value == Value::Bool(true);
+            Ok(())
+        },
+    },
+    // Rows to a page of the next `:browse`.
+    Opt {
+        name: "pagesize",
+        short: None,
+        kind: Kind::Number {
+            min: 1,
+            max: 100_000,
+        },
+        get: |state| Value::Number(state.page_size as i64),
+        set: |state, value| {
+            if let Value::Number(size) = value {
+                state.page_size = size as u64;
+            }
+            Ok(())
+        },
+    },
+    // No colors or box drawing, for screen readers and limited terminals.
+    Opt {
+        name: "plain",
+        short: None,
+        kind: Kind::Bool,
+        get: |state| Value::Bool(state.plain),
+        set: |state, value| {
+            state.plain = value == Value::Bool(true);
+            ui::set_plain(state.plain);
+            Ok(())
+        },
+    },
+    // Reads of the UI's own making go through the SQL preview as well.
+    Opt {
+        name: "preview",
+        short: None,
+        kind: Kind::Bool,
+        get: |state| Value::Bool(state.preview),
+        set: |state, value| {
+            state.preview = value == Value::Bool(true);
+            Ok(())
+        },
+    },
+    // `default_transaction_read_only` on the server, which the caller sets
+    // first.
+    Opt {
+        name: "readonly",
+        short: Some("ro"),
+        kind: Kind::Bool,
+        get: |state| Value::Bool(state.read_only),
+        set: |state, value| {
+            state.read_only = value == Value::Bool(true);
+            Ok(())
+        },
+    },
+    Opt {
+        name: "relativenumber",
+        short: Some("rnu"),
+        kind: Kind::Bool,
+        get: |state| Value::Bool(state.gutter().relative),
+        set: |state, value| {
+            state.gutter_mut().relative = value == Value::Bool(true);
+            Ok(())
+        },
+    },
+    // MiB of a result's rows kept in memory; past it the older ones go to a
+    // temporary file and are read back as the grid scrolls to them.
+    Opt {
+        name: "resultmem",
+        short: None,
+        kind: Kind::Number {
+            min: 0,
+            max: 1 << 30,
+        },
+        get: |state| Value::Number(state.result_memory as i64),
+        set: |state, value| {
+            if let Value::Number(mib) = value {
+                state.result_memory = mib as u64;
+                results::set_memory_limit((mib as usize) << 20);
+            }
+            Ok(())
+        },
+    },
+    // An `UPDATE` or `DELETE` changing more rows than this waits to be
+    // committed or rolled back. 0 turns the guard off.
+    Opt {
+        name: "rowguard",
+        short: None,
+        kind: Kind::Number {
+            min: 0,
+            max: i64::MAX,
+        },
+        get: |state| Value::Number(state.row_guard as i64),
+        set: |state, value| {
+            if let Value::Number(rows) = value {
+                state.row_guard = rows as u64;
+            }
+            Ok(())
+        },
+    },
+    // Only in the grid: yanks and exports take the whole value.
+    Opt {
+        name: "shorten-ids",
+        short: None,
+        kind: Kind::Bool,
+        get: |state| Value::Bool(state.shorten_ids),
+        set: |state, value| {
+            state.shorten_ids = value == Value::Bool(true);
+            Ok(())
+        },
+    },
+    Opt {
+        name: "split",
+        short: None,
+        kind: Kind::Number { min: 10, max: 90 },
+        get: |state| Value::Number(state.split.into()),
+        set: |state, value| {
+            if let Value::Number(split) = value {
+                state.split = split as u16;
+            }
+            Ok(())
+        },
+    },
+    // Also `SET` on the server, which the caller sees to first.
+    Opt {
+        name: "statement_timeout",
+        short: None,
+        kind: Kind::Duration,
+        get: |state| Value::Duration(state.statement_timeout),
+        set: |state, value| {
+            if let Value::Duration(timeout) = value {
+                state.statement_timeout = timeout;
+            }
+            Ok(())
+        },
+    },
+    // How long keys that may be the start of a mapping wait for the rest.
+    Opt {
+        name: "timeoutlen",
+        short: Some("tm"),
+        kind: Kind::Duration,
+        get: |state| Value::Duration(state.timeout_len),
+        set: |state, value| {
+            if let Value::Duration(timeout) = value {
+                state.timeout_len = timeout;
+            }
+            Ok(())
+        },
+    },
+    // Also `SET` on the server; the grid moves timestamps already fetched.
+    Opt {
+        name: "timezone",
+        short: Some("tz"),
+        kind: Kind::Zone,
+        get: |state| Value::Zone(state.timezone),
+        set: |state, value| {
+            if let Value::Zone(zone) = value {
+                state.timezone = zone;
+            }
+            Ok(())
+        },
+    },
+    Opt {
+        name: "wrap",
+        short: None,
+        kind: Kind::Bool,
+        get: |state| Value::Bool(state.wrap),
+        set: |state, value| {
+            state.wrap = value == Value::Bool(true);
+            Ok(())
+        },
+    },
+];
+
+/// The option called `name`, or abbreviated so.
+pub fn find(name: &str) -> Option<&'static Opt> {
+    OPTIONS
+        .iter()
+        .find(|opt| opt.name == name || opt.short == Some(name))
+}
+
+/// What a `:set` asks for.
+#[derive(Debug)]
+pub enum Request {
+    /// List every option.
+    All,
+    Show(&'static Opt),
+    Set(&'static Opt, Value),
+}
+
+/// Works out what `:set option[=value]` asks for, which for `:set wrap!`
+/// depends on the current value.
+pub fn request(state: &State, option: &str, value: Option<&str>) -> Result<Request, String> {
+    if option.is_empty() || option == "all" {
+        return Ok(Request::All);
+    }
+    let unknown = || format!("Unknown option: {option}");
+    if let Some(value) = value {
+        let opt = find(option).ok_or_else(unknown)?;
+        return Ok(Request::Set(opt, opt.kind.parse(value)?));
+    }
+    if let Some(name) = option.strip_suffix('?') {
+        return find(name).map(Request::Show).ok_or_else(unknown);
+    }
+    let toggle = |opt: &'static Opt| match opt.get(state) {
+        Value::Bool(on) => Ok(Request::Set(opt, Value::Bool(!on))),
+        _ => Err(format!("{} is not a boolean option", opt.name)),
+    };
+    if let Some(name) = option.strip_suffix('!') {
+        return toggle(find(name).ok_or_else(unknown)?);
+    }
+    if let Some(opt) = option.strip_prefix("inv").and_then(find) {
+        return toggle(opt);
+    }
+    match find(option) {
+        Some(opt) if opt.kind == Kind::Bool => Ok(Request::Set(opt, Value::Bool(true))),
+        Some(opt) => Ok(Request::Show(opt)),
+        None => match option.strip_prefix("no").and_then(find) {
+            Some(opt) if opt.kind == Kind::Bool => Ok(Request::Set(opt, Value::Bool(false))),
+            Some(opt) => Err(format!("{} is not a boolean option", opt.name)),
+            None => Err(unknown()),
+        },
+    }
+}
@@ -0,0 +1,116 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Bind parameters in queries: positional `$1` and named `:name`.
+//!
+//! Named parameters are numbered after the highest positional one, and
+//! every occurrence of a name shares one number, so the query can be sent to
+//! the server as a regular prepared statement.
+
+use std::ops::Range;
+
+use crate::statements::{is_ident, skip_quoted};
+
+/// A query with at least one placeholder.
+#[derive(Debug)]
+pub struct Parameterized {
+    sql: String,
+    /// `$1` or `:name`, for parameter `n` at index `n - 1`.
+    pub labels: Vec<String>,
+    /// Byte range of each placeholder in `sql` and its parameter number.
+    placeholders: Vec<(Range<usize>, usize)>,
+}
+
+/// Finds the placeholders in `sql`, skipping over literals, quoted
+/// identifiers, comments and `::` casts. Returns `None` if there are none.
+pub fn find(sql: &str) -> Option<Parameterized> {
+    let bytes = sql.as_bytes();
+    let mut positional = Vec::new();
+    let mut named: Vec<(Range<usize>, &str)> = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let previous = i.checked_sub(1).map(|i| bytes[i]);
+        let next = bytes.get(i + 1).copied();
+        if let Some(end) = skip_quoted(sql, i) {
+            i = end;
+            continue;
+        }
+        match (bytes[i], next) {
+            (b'$', Some(next)) if next.is_ascii_digit() && !previous.is_some_and(is_ident) => {
+                let start = i;
+                i += 1;
+                while i < bytes.len() && bytes[i].is_ascii_digit() {
+                    i += 1;
+                }
+                if let Ok(number) = sql[start + 1..i].parse::<usize>() {
+                    positional.push((start..i, number));
+                }
+            }
+            (b':', Some(b':')) => i += 2,
+            (b':', Some(next)) if next.is_ascii_alphabetic() || next == b'_' => {
+                let start = i;
+                i += 1;
+                while i < bytes.len() && is_ident(bytes[i]) {
+                    i += 1;
+                }
+                named.push((start..i, &sql[start + 1..i]));
+            }
+            _ => i += 1,
+        }
+    }
+
+    if positional.is_empty() && named.is_empty() {
+        return None;
+    }
+    let highest = positional.iter().map(|(_, n)| *n).max().unwrap_or(0);
+    let mut labels: Vec<String> = (1..=highest).map(|n| format!("${n}")).collect();
+    let mut placeholders = positional;
+    for (range, name) in named {
+        let label = format!(":{name}");
+        let number = match labels.iter().position(|l| *l == label) {
+            Some(index) => index + 1,
+            None => {
+                labels.push(label);
+                labels.len()
+            }
+        };
+        placeholders.push((range, number));
+    }
+    placeholders.sort_by_key(|(range, _)| range.start);
+    Some(Parameterized {
+        sql: sql.to_string(),
+        labels,
+        placeholders,
+    })
+}
+
+impl Parameterized {
+    /// The query with each placeholder replaced by `placeholder(n)`.
+    pub fn render(&self, placeholder: impl Fn(usize) -> String) -> String {
+        let mut sql = String::new();
+        let mut last = 0;
+        for (range, number) in &self.placeholders {
+            sql.push_str(&self.sql[last..range.start]);
+            sql.push_str(&placeholder(*number));
+            last = range.end;
+        }
+        sql.push_str(&self.sql[last..]);
+        sql
+    }
+
+    /// The query with only positional `$n` placeholders.
+    pub fn positional(&self) -> String {
+        self.render(|n| format!("${n}"))
+    }
+}
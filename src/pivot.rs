@@ -0,0 +1,186 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Client side crosstabs of the loaded results, `:pivot rows=a cols=b
+//! values=sum(c)`, for the quick summaries `crosstab()` is a chore for.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use crate::results::{Column, ResultSet};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aggregate {
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+/// What `:pivot` was asked for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PivotSpec {
+    pub rows: String,
+    pub cols: String,
+    pub aggregate: Aggregate,
+    /// `None` for `count(*)`.
+    pub values: Option<String>,
+}
+
+impl FromStr for PivotSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const USAGE: &str =
+            "Usage: pivot rows=<column> cols=<column> [values=sum|avg|min|max|count(<column>)]";
+        let (mut rows, mut cols) = (None, None);
+        let (mut aggregate, mut values) = (Aggregate::Count, None);
+        for arg in s.split_whitespace() {
+            match arg.split_once('=') {
+                Some(("rows", column)) => rows = Some(column.to_string()),
+                Some(("cols", column)) => cols = Some(column.to_string()),
+                Some(("values", value)) => {
+                    let (function, column) = value
+                        .strip_suffix(')')
+                        .and_then(|value| value.split_once('('))
+                        .ok_or(USAGE)?;
+                    aggregate = match function.to_lowercase().as_str() {
+                        "count" => Aggregate::Count,
+                        "sum" => Aggregate::Sum,
+                        "avg" => Aggregate::Avg,
+                        "min" => Aggregate::Min,
+                        "max" => Aggregate::Max,
+                        _ => return Err(format!("Unknown aggregate {function}")),
+                    };
+                    values = (column != "*").then(|| column.to_string());
+                }
+                _ => return Err(USAGE.into()),
+            }
+        }
+        if aggregate != Aggregate::Count && values.is_none() {
+            return Err(USAGE.into());
+        }
+        match (rows, cols) {
+            (Some(rows), Some(cols)) => Ok(Self {
+                rows,
+                cols,
+                aggregate,
+                values,
+            }),
+            _ => Err(USAGE.into()),
+        }
+    }
+}
+
+/// Running total of one crosstab cell.
+#[derive(Debug, Clone, Copy, Default)]
+struct Accumulator {
+    count: usize,
+    sum: f64,
+    min: Option<f64>,
+    max: Option<f64>,
+}
+
+impl Accumulator {
+    fn add(&mut self, value: Option<f64>) {
+        // Like SQL, count(*) counts every row and the others skip NULLs.
+        let Some(value) = value else {
+            return;
+        };
+        self.count += 1;
+        self.sum += value;
+        self.min = Some(self.min.map_or(value, |min| min.min(value)));
+        self.max = Some(self.max.map_or(value, |max| max.max(value)));
+    }
+
+    fn result(&self, aggregate: Aggregate) -> Option<f64> {
+        match aggregate {
+            Aggregate::Count => Some(self.count as f64),
+            _ if self.count == 0 => None,
+            Aggregate::Sum => Some(self.sum),
+            Aggregate::Avg => Some(self.sum / self.count as f64),
+            Aggregate::Min => self.min,
+            Aggregate::Max => self.max,
+        }
+    }
+}
+
+/// Position of `key` in `keys`, added at the end if it's new.
+fn index<'a>(keys: &mut Vec<Option<&'a str>>, key: Option<&'a str>) -> usize {
+    match keys.iter().position(|k| *k == key) {
+        Some(index) => index,
+        None => {
+            keys.push(key);
+            keys.len() - 1
+        }
+    }
+}
+
+/// Reshapes `results` into one row per distinct value of `spec.rows` and
+/// one column per distinct value of `spec.cols`, both in the order they
+/// first appear.
+pub fn pivot(results: &ResultSet, spec: &PivotSpec) -> Result<ResultSet, String> {
+    let column = |name: &str| {
+        results
+            .column_index(name)
+            .ok_or_else(|| format!("No column {name}"))
+    };
+    let rows = column(&spec.rows)?;
+    let cols = column(&spec.cols)?;
+    let values = spec.values.as_deref().map(column).transpose()?;
+
+    let mut row_keys: Vec<Option<&str>> = Vec::new();
+    let mut col_keys: Vec<Option<&str>> = Vec::new();
+    let mut cells: HashMap<(usize, usize), Accumulator> = HashMap::new();
+    for row in &results.rows {
+        let value = match values {
+            Some(values) => match row.get(values) {
+                Some(value) => Some(
+                    value
+                        .parse::<f64>()
+                        .map_err(|_| format!("Not a number: {value}"))?,
+                ),
+                None => None,
+            },
+            None => Some(1.0),
+        };
+        let r = index(&mut row_keys, row.get(rows));
+        let c = index(&mut col_keys, row.get(cols));
+        cells.entry((r, c)).or_default().add(value);
+    }
+
+    let ty = match spec.aggregate {
+        Aggregate::Count => "int8",
+        _ => "numeric",
+    };
+    let mut columns = vec![results.columns[rows].clone()];
+    columns.extend(
+        col_keys
+            .iter()
+            .map(|key| Column::new(key.unwrap_or("NULL"), ty)),
+    );
+    let mut pivoted = ResultSet::new(columns);
+    for (r, key) in row_keys.iter().enumerate() {
+        let mut row = vec![key.map(str::to_string)];
+        row.extend((0..col_keys.len()).map(|c| {
+            cells
+                .get(&(r, c))
+                .and_then(|cell| cell.result(spec.aggregate))
+                .map(|value| value.to_string())
+        }));
+        pivoted.rows.push(row);
+    }
+    Ok(pivoted)
+}
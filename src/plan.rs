@@ -0,0 +1,697 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Query plans from `EXPLAIN (FORMAT JSON)`, shown as a tree in place of the
+//! results grid: estimated against actual rows, time spent in each node and
+//! the costliest ones highlighted.
+
+use std::collections::HashSet;
+
+use ratatui::{
+    Frame,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::Paragraph,
+};
+use serde_json::Value;
+use sqlx::{PgPool, Row};
+
+use crate::db::schema;
+use crate::width;
+
+/// Node fields worth a line under the tree when the node is selected.
+const DETAILS: [&str; 15] = [
+    "Filter",
+    "Index Cond",
+    "Recheck Cond",
+    "Hash Cond",
+    "Merge Cond",
+    "Join Filter",
+    "Sort Key",
+    "Group Key",
+    "Sort Method",
+    "Rows Removed by Filter",
+    "Rows Removed by Join Filter",
+    "Rows Removed by Index Recheck",
+    "Heap Fetches",
+    "Shared Hit Blocks",
+    "Shared Read Blocks",
+];
+
+/// Width of the bar showing a node's share of the plan.
+const BAR_WIDTH: usize = 10;
+
+#[derive(Debug, Clone)]
+pub struct Node {
+    /// Levels below the root.
+    pub depth: usize,
+    /// `Index Scan using orders_pkey on orders o`, like `EXPLAIN` prints it.
+    pub label: String,
+    /// `Index Scan`, what the node is without the rest of the label.
+    pub node_type: String,
+    /// The table it scans, schema-qualified if the plan said which.
+    pub relation: Option<String>,
+    /// What the query calls the table it scans.
+    pub alias: Option<String>,
+    pub details: Vec<String>,
+    pub total_cost: f64,
+    /// Cost of this node without its children.
+    pub self_cost: f64,
+    /// Estimated rows per loop.
+    pub plan_rows: f64,
+    /// Rows per loop, with `ANALYZE`.
+    pub actual_rows: Option<f64>,
+    pub loops: f64,
+    /// Milliseconds over all loops spent in this node alone, with
+    /// `ANALYZE`.
+    pub self_ms: Option<f64>,
+}
+
+impl Node {
+    /// The value of detail `key`, like the condition of `Filter`.
+    pub fn detail(&self, key: &str) -> Option<&str> {
+        self.details
+            .iter()
+            .find_map(|detail| detail.strip_prefix(key)?.strip_prefix(": "))
+    }
+
+    /// What the heatmap goes by: time if the plan was analyzed, cost if not.
+    pub fn weight(&self) -> f64 {
+        self.self_ms.unwrap_or(self.self_cost)
+    }
+
+    /// How far off the row estimate was, as a factor, and whether it was
+    /// too low.
+    pub fn misestimate(&self) -> Option<(f64, bool)> {
+        let actual = self.actual_rows?.max(1.0);
+        let planned = self.plan_rows.max(1.0);
+        Some(if actual >= planned {
+            (actual / planned, true)
+        } else {
+            (planned / actual, false)
+        })
+    }
+}
+
+/// A plan's nodes in depth-first order, each after its parent.
+#[derive(Debug, Clone)]
+pub struct Plan {
+    pub nodes: Vec<Node>,
+    pub planning_ms: Option<f64>,
+    pub execution_ms: Option<f64>,
+}
+
+impl Plan {
+    /// Reads the output of `EXPLAIN (FORMAT JSON)`.
+    pub fn parse(json: &Value) -> Result<Self, String> {
+        let top = json.get(0).ok_or_else(|| "Empty plan".to_string())?;
+        let root = top
+            .get("Plan")
+            .ok_or_else(|| "No \"Plan\" in the EXPLAIN output".to_string())?;
+        let mut nodes = Vec::new();
+        flatten(root, 0, &mut nodes);
+        Ok(Self {
+            nodes,
+            planning_ms: top.get("Planning Time").and_then(Value::as_f64),
+            execution_ms: top.get("Execution Time").and_then(Value::as_f64),
+        })
+    }
+
+    /// The total weight, for each node's share of it.
+    pub fn total_weight(&self) -> f64 {
+        self.nodes.iter().map(Node::weight).sum()
+    }
+
+    /// Index of the node after `index` and its descendants.
+    pub fn subtree_end(&self, index: usize) -> usize {
+        let depth = self.nodes[index].depth;
+        self.nodes[index + 1..]
+            .iter()
+            .position(|node| node.depth <= depth)
+            .map_or(self.nodes.len(), |offset| index + 1 + offset)
+    }
+
+    pub fn has_children(&self, index: usize) -> bool {
+        self.nodes
+            .get(index + 1)
+            .is_some_and(|next| next.depth > self.nodes[index].depth)
+    }
+
+    pub fn analyzed(&self) -> bool {
+        self.nodes
+            .first()
+            .is_some_and(|node| node.self_ms.is_some())
+    }
+
+    /// `Planning 0.12 ms · Execution 3.40 ms`, or the plan's cost if it
+    /// wasn't run.
+    pub fn summary(&self) -> String {
+        match (self.planning_ms, self.execution_ms) {
+            (Some(planning), Some(execution)) => {
+                format!("Planning {planning:.3} ms · Execution {execution:.3} ms")
+            }
+            _ => format!(
+                "Estimated cost {:.2}, :explain analyze for timings",
+                self.nodes.first().map_or(0.0, |node| node.total_cost)
+            ),
+        }
+    }
+}
+
+/// Appends `json` and its children to `nodes`, returning its total time and
+/// cost.
+fn flatten(json: &Value, depth: usize, nodes: &mut Vec<Node>) -> (Option<f64>, f64) {
+    let number = |key| json.get(key).and_then(Value::as_f64);
+    let text = |key| json.get(key).and_then(Value::as_str);
+
+    let mut label = text("Node Type").unwrap_or("?").to_string();
+    match text("Strategy") {
+        Some("Hashed") if label == "Aggregate" => label = "HashAggregate".into(),
+        Some("Sorted") if label == "Aggregate" => label = "GroupAggregate".into(),
+        _ => {}
+    }
+    if let Some(join) = text("Join Type").filter(|join| *join != "Inner") {
+        label = match label.strip_suffix(" Join") {
+            Some(kind) => format!("{kind} {join} Join"),
+            None => format!("{label} {join} Join"),
+        };
+    }
+    if let Some(index) = text("Index Name") {
+        label.push_str(&format!(" using {index}"));
+    }
+    if let Some(relation) = text("Relation Name")
+        .or_else(|| text("CTE Name"))
+        .or_else(|| text("Function Name"))
+    {
+        label.push_str(&format!(" on {relation}"));
+        if let Some(alias) = text("Alias").filter(|alias| *alias != relation) {
+            label.push_str(&format!(" {alias}"));
+        }
+    }
+    // Greenplum's motions move rows between segments, N senders to M
+    // receivers.
+    if let (Some(senders), Some(receivers)) = (number("Senders"), number("Receivers")) {
+        label.push_str(&format!(" {senders}:{receivers}"));
+    }
+    if let Some(subplan) = text("Subplan Name") {
+        label = format!("{subplan}: {label}");
+    }
+
+    let details = DETAILS
+        .iter()
+        .filter_map(|key| {
+            let value = match json.get(*key)? {
+                Value::String(value) => value.clone(),
+                Value::Array(values) => values
+                    .iter()
+                    .map(|value| {
+                        value
+                            .as_str()
+                            .map_or_else(|| value.to_string(), str::to_string)
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                Value::Number(value) if value.as_f64() == Some(0.0) => return None,
+                value => value.to_string(),
+            };
+            Some(format!("{key}: {value}"))
+        })
+        .collect();
+
+    let loops = number("Actual Loops").unwrap_or(1.0);
+    let total_ms = number("Actual Total Time").map(|ms| ms * loops);
+    let total_cost = number("Total Cost").unwrap_or_default();
+    let index = nodes.len();
+    nodes.push(Node {
+        depth,
+        label,
+        node_type: text("Node Type").unwrap_or("?").to_string(),
+        relation: text("Relation Name").map(|relation| match text("Schema") {
+            Some(schema) => format!("{}.{}", schema::ident(schema), schema::ident(relation)),
+            None => schema::ident(relation),
+        }),
+        alias: text("Alias").map(str::to_string),
+        details,
+        total_cost,
+        self_cost: total_cost,
+        plan_rows: number("Plan Rows").unwrap_or_default(),
+        actual_rows: number("Actual Rows"),
+        loops,
+        self_ms: total_ms,
+    });
+
+    let (mut children_ms, mut children_cost) = (0.0, 0.0);
+    for child in json
+        .get("Plans")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        let (ms, cost) = flatten(child, depth + 1, nodes);
+        children_ms += ms.unwrap_or_default();
+        children_cost += cost;
+    }
+    let node = &mut nodes[index];
+    node.self_cost = (total_cost - children_cost).max(0.0);
+    node.self_ms = total_ms.map(|ms| (ms - children_ms).max(0.0));
+    (total_ms, total_cost)
+}
+
+/// A plan in the results pane, with some nodes folded away.
+#[derive(Debug, Clone)]
+pub struct PlanView {
+    pub plan: Plan,
+    collapsed: HashSet<usize>,
+    /// Index into the visible nodes.
+    pub cursor: usize,
+    scroll: usize,
+}
+
+impl PlanView {
+    pub fn new(plan: Plan) -> Self {
+        Self {
+            plan,
+            collapsed: HashSet::new(),
+            cursor: 0,
+            scroll: 0,
+        }
+    }
+
+    /// Indexes of the nodes not folded into a collapsed parent.
+    pub fn visible(&self) -> Vec<usize> {
+        let mut visible = Vec::new();
+        let mut index = 0;
+        while index < self.plan.nodes.len() {
+            visible.push(index);
+            index = if self.collapsed.contains(&index) {
+                self.plan.subtree_end(index)
+            } else {
+                index + 1
+            };
+        }
+        visible
+    }
+
+    pub fn move_by(&mut self, rows: isize) {
+        let last = self.visible().len().saturating_sub(1);
+        self.cursor = self.cursor.saturating_add_signed(rows).min(last);
+    }
+
+    pub fn top(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn bottom(&mut self) {
+        self.cursor = self.visible().len().saturating_sub(1);
+    }
+
+    /// Folds or unfolds the node under the cursor.
+    pub fn toggle(&mut self) {
+        let Some(&index) = self.visible().get(self.cursor) else {
+            return;
+        };
+        if !self.collapsed.remove(&index) && self.plan.has_children(index) {
+            self.collapsed.insert(index);
+        }
+    }
+
+    /// Unfolds every node.
+    pub fn expand_all(&mut self) {
+        self.collapsed.clear();
+    }
+
+    pub fn render(&mut self, f: &mut Frame, area: Rect, focused: bool) {
+        let visible = self.visible();
+        let selected = visible
+            .get(self.cursor)
+            .map(|&index| &self.plan.nodes[index]);
+        let details = selected.map_or(&[][..], |node| node.details.as_slice());
+        let details_height = details.len().min(4) as u16;
+        let tree_height = area.height.saturating_sub(1 + details_height) as usize;
+        self.scroll = follow(self.cursor, self.scroll, tree_height);
+
+        let total = self.plan.total_weight().max(f64::EPSILON);
+        let stats_width = 24 + BAR_WIDTH;
+        let label_width = visible
+            .iter()
+            .map(|&index| {
+                let node = &self.plan.nodes[index];
+                node.depth * 2 + 2 + width::width(&node.label)
+            })
+            .max()
+            .unwrap_or_default()
+            .min(
+                (area.width as usize)
+                    .saturating_sub(stats_width + 1)
+                    .max(10),
+            );
+
+        let mut lines = vec![Line::styled(
+            self.plan.summary(),
+            Style::default().fg(Color::DarkGray),
+        )];
+        for (row, &index) in visible
+            .iter()
+            .enumerate()
+            .skip(self.scroll)
+            .take(tree_height)
+        {
+            let node = &self.plan.nodes[index];
+            let marker = if self.collapsed.contains(&index) {
+                '▸'
+            } else if self.plan.has_children(index) {
+                '▾'
+            } else {
+                '•'
+            };
+            let label = format!("{}{marker} {}", "  ".repeat(node.depth), node.label);
+            let label = width::pad(&width::truncate(&label, label_width), label_width, false);
+            let share = node.weight() / total;
+            let heat = if share >= 0.5 {
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
+            } else if share >= 0.1 {
+                Style::default().fg(Color::Yellow)
+            } else {
+                Style::default()
+            };
+            let amount = match node.self_ms {
+                Some(ms) => format!("{ms:>10.3} ms"),
+                None => format!("{:>13.2}", node.self_cost),
+            };
+            let filled = (share * BAR_WIDTH as f64).round() as usize;
+            let bar = format!(
+                "{}{}",
+                "█".repeat(filled.min(BAR_WIDTH)),
+                "░".repeat(BAR_WIDTH.saturating_sub(filled))
+            );
+            let mut spans = vec![
+                Span::styled(format!("{label} "), heat),
+                Span::raw(amount),
+                Span::raw(format!(" {:>3.0}% ", share * 100.0)),
+                Span::styled(bar, heat),
+                Span::raw(format!("  {}", rows(node))),
+            ];
+            if let Some((factor, under)) = node.misestimate()
+                && factor >= 10.0
+            {
+                let arrow = if under { '↑' } else { '↓' };
+                spans.push(Span::styled(
+                    format!(" {arrow}{factor:.0}×"),
+                    Style::default().fg(Color::Magenta),
+                ));
+            }
+            let mut line = Line::from(spans);
+            if focused && row == self.cursor {
+                line = line.patch_style(Style::default().add_modifier(Modifier::REVERSED));
+            }
+            lines.push(line);
+        }
+        f.render_widget(Paragraph::new(lines), area);
+
+        let details: Vec<Line> = details
+            .iter()
+            .take(details_height as usize)
+            .map(|detail| Line::styled(detail.as_str(), Style::default().fg(Color::Cyan)))
+            .collect();
+        f.render_widget(
+            Paragraph::new(details),
+            Rect {
+                y: area.bottom().saturating_sub(details_height),
+                height: details_height,
+                ..area
+            },
+        );
+    }
+}
+
+/// Two plans of the same query side by side, `:plandiff`.
+#[derive(Debug, Clone)]
+pub struct PlanDiff {
+    old: Plan,
+    new: Plan,
+    /// Lines of the diff: a node of either plan or one in both.
+    rows: Vec<(Option<usize>, Option<usize>)>,
+    pub cursor: usize,
+    scroll: usize,
+}
+
+impl PlanDiff {
+    /// Lines up the nodes the plans have in common, in order, by label.
+    pub fn new(old: Plan, new: Plan) -> Self {
+        let (n, m) = (old.nodes.len(), new.nodes.len());
+        // Longest common subsequence of the labels, from the back.
+        let mut common = vec![vec![0usize; m + 1]; n + 1];
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                common[i][j] = if old.nodes[i].label == new.nodes[j].label {
+                    common[i + 1][j + 1] + 1
+                } else {
+                    common[i + 1][j].max(common[i][j + 1])
+                };
+            }
+        }
+        let mut rows = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while i < n || j < m {
+            if i < n && j < m && old.nodes[i].label == new.nodes[j].label {
+                rows.push((Some(i), Some(j)));
+                (i, j) = (i + 1, j + 1);
+            } else if j == m || (i < n && common[i + 1][j] >= common[i][j + 1]) {
+                rows.push((Some(i), None));
+                i += 1;
+            } else {
+                rows.push((None, Some(j)));
+                j += 1;
+            }
+        }
+        Self {
+            old,
+            new,
+            rows,
+            cursor: 0,
+            scroll: 0,
+        }
+    }
+
+    pub fn move_by(&mut self, rows: isize) {
+        let last = self.rows.len().saturating_sub(1);
+        self.cursor = self.cursor.saturating_add_signed(rows).min(last);
+    }
+
+    pub fn top(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn bottom(&mut self) {
+        self.cursor = self.rows.len().saturating_sub(1);
+    }
+
+    pub fn render(&mut self, f: &mut Frame, area: Rect, focused: bool) {
+        // Time only compares with time: fall back to cost unless both plans
+        // were analyzed.
+        let timed = self.old.analyzed() && self.new.analyzed();
+        let amount = |node: &Node| match node.self_ms {
+            Some(ms) if timed => ms,
+            _ => node.self_cost,
+        };
+        let format_amount = |amount: f64| {
+            if timed {
+                format!("{amount:.3} ms")
+            } else {
+                format!("{amount:.2}")
+            }
+        };
+        let half = (area.width as usize).saturating_sub(3) / 2;
+        let height = area.height.saturating_sub(1) as usize;
+        self.scroll = follow(self.cursor, self.scroll, height);
+
+        let mut lines = vec![Line::styled(
+            format!("{}  →  {}", self.old.summary(), self.new.summary()),
+            Style::default().fg(Color::DarkGray),
+        )];
+        for (row, &(old, new)) in self.rows.iter().enumerate().skip(self.scroll).take(height) {
+            let side = |plan: &Plan, index: Option<usize>, change: &str| {
+                let Some(node) = index.map(|index| &plan.nodes[index]) else {
+                    return " ".repeat(half);
+                };
+                let right = format!(" {}{change}", format_amount(amount(node)));
+                let room = half.saturating_sub(width::width(&right));
+                let label = format!("{}{}", "  ".repeat(node.depth), node.label);
+                let label = width::pad(&width::truncate(&label, room), room, false);
+                format!("{label}{right}")
+            };
+            let (change, style) = match (old, new) {
+                (Some(old), Some(new)) => {
+                    let (before, after) =
+                        (amount(&self.old.nodes[old]), amount(&self.new.nodes[new]));
+                    let ratio = (after - before) / before.max(f64::EPSILON);
+                    let style = if after > before && ratio > 0.1 {
+                        Style::default().fg(Color::Red)
+                    } else if after < before && ratio < -0.1 {
+                        Style::default().fg(Color::Green)
+                    } else {
+                        Style::default()
+                    };
+                    let change = if before > 0.0 && ratio.abs() > 0.1 {
+                        format!(" {:+.0}%", ratio * 100.0)
+                    } else {
+                        String::new()
+                    };
+                    (change, style)
+                }
+                // Nodes only the old plan has are gone, ones only the new
+                // plan has were added.
+                (Some(_), None) => (String::new(), Style::default().fg(Color::DarkGray)),
+                _ => (String::new(), Style::default().fg(Color::Cyan)),
+            };
+            let mut line = Line::from(vec![
+                Span::styled(
+                    side(&self.old, old, ""),
+                    if new.is_none() {
+                        style
+                    } else {
+                        Style::default()
+                    },
+                ),
+                Span::styled(" │ ", Style::default().fg(Color::DarkGray)),
+                Span::styled(side(&self.new, new, &change), style),
+            ]);
+            if focused && row == self.cursor {
+                line = line.patch_style(Style::default().add_modifier(Modifier::REVERSED));
+            }
+            lines.push(line);
+        }
+        f.render_widget(Paragraph::new(lines), area);
+    }
+}
+
+/// The first line to show for `cursor` to be on screen, scrolling as little
+/// as possible from `scroll`.
+fn follow(cursor: usize, scroll: usize, height: usize) -> usize {
+    if cursor < scroll {
+        cursor
+    } else if height > 0 && cursor >= scroll + height {
+        cursor + 1 - height
+    } else {
+        scroll
+    }
+}
+
+/// `rows 120 of 100 est`, per loop.
+fn rows(node: &Node) -> String {
+    let loops = if node.loops > 1.0 {
+        format!(" ×{} loops", node.loops)
+    } else {
+        String::new()
+    };
+    match node.actual_rows {
+        Some(actual) => format!("rows {actual} of {} est{loops}", node.plan_rows),
+        None => format!("est {} rows", node.plan_rows),
+    }
+}
+
+/// What the planner expects of a query as a whole, for `costguard` and
+/// `estrowguard` to weigh before it runs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Estimate {
+    pub cost: f64,
+    pub rows: f64,
+}
+
+impl Estimate {
+    /// The estimate of the root of `plan`.
+    pub fn of(plan: &Plan) -> Option<Self> {
+        let root = plan.nodes.first()?;
+        Some(Self {
+            cost: root.total_cost,
+            rows: root.plan_rows,
+        })
+    }
+
+    /// Why the query looks too big to run without asking, if it does: its
+    /// cost over `cost_guard` or its rows over `row_guard`, 0 for no limit.
+    pub fn exceeds(&self, cost_guard: u64, row_guard: u64) -> Option<String> {
+        let mut over = Vec::new();
+        if cost_guard > 0 && self.cost > cost_guard as f64 {
+            over.push(format!("cost {:.0} > costguard={cost_guard}", self.cost));
+        }
+        if row_guard > 0 && self.rows > row_guard as f64 {
+            over.push(format!("{:.0} rows > estrowguard={row_guard}", self.rows));
+        }
+        (!over.is_empty()).then(|| format!("Estimated {}", over.join(", ")))
+    }
+}
+
+/// Plans `query` with a plain `EXPLAIN`, which doesn't run it, for the
+/// [`Estimate`] of the whole.
+pub async fn estimate(
+    pool: &PgPool,
+    query: &str,
+    binds: &[Option<String>],
+) -> Result<Option<Estimate>, sqlx::Error> {
+    let sql = format!(
+        "EXPLAIN (FORMAT JSON) {}",
+        query.trim().trim_end_matches(';')
+    );
+    let query = binds
+        .iter()
+        .fold(sqlx::query(&sql), |query, value| query.bind(value.clone()));
+    let json: Value = query.fetch_one(pool).await?.try_get(0)?;
+    let plan = Plan::parse(&json).map_err(sqlx::Error::Protocol)?;
+    Ok(Estimate::of(&plan))
+}
+
+/// Runs `EXPLAIN (FORMAT JSON)` on `query`. With `analyze` the query really
+/// runs, in a transaction that is rolled back so that it changes nothing.
+pub async fn explain(
+    pool: &PgPool,
+    query: &str,
+    binds: &[Option<String>],
+    analyze: bool,
+) -> Result<Plan, sqlx::Error> {
+    let options = if analyze {
+        "FORMAT JSON, ANALYZE, BUFFERS"
+    } else {
+        "FORMAT JSON"
+    };
+    let sql = format!("EXPLAIN ({options}) {}", query.trim().trim_end_matches(';'));
+    let query = binds
+        .iter()
+        .fold(sqlx::query(&sql), |query, value| query.bind(value.clone()));
+    let mut transaction = pool.begin().await?;
+    let row = query.fetch_one(&mut *transaction).await?;
+    transaction.rollback().await?;
+    let json: Value = row.try_get(0)?;
+    Plan::parse(&json).map_err(sqlx::Error::Protocol)
+}
+
+/// The plan of `query` as text, for servers that can't give it as JSON.
+pub async fn explain_text(
+    pool: &PgPool,
+    query: &str,
+    binds: &[Option<String>],
+) -> Result<String, sqlx::Error> {
+    let sql = format!("EXPLAIN {}", query.trim().trim_end_matches(';'));
+    let query = binds
+        .iter()
+        .fold(sqlx::query(&sql), |query, value| query.bind(value.clone()));
+    let lines: Vec<String> = query
+        .fetch_all(pool)
+        .await?
+        .iter()
+        .map(|row| row.try_get(0))
+        .collect::<Result<_, _>>()?;
+    Ok(lines.join("\n"))
+}
@@ -0,0 +1,338 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! WASM plugins, loaded from `$XDG_CONFIG_HOME/dbvi/plugins`, for the
+//! exporters and company-specific tooling that don't belong in dbvi itself.
+//! Built with the `plugins` feature.
+//!
+//! A plugin is a `.wasm` (or `.wat`) module exporting its `memory` and
+//! three functions. Text goes both ways as UTF-8 in the plugin's memory,
+//! returned as `ptr << 32 | len` in an `i64`.
+//!
+//! - `dbvi_alloc(len: i32) -> i32` makes room for a request of `len` bytes.
+//! - `dbvi_manifest() -> i64` lists what the plugin provides, one per line:
+//!   - `command <name>`: an ex command, `:<name> [args]`, whose output goes
+//!     to the status line, or a popup if there is more than a line of it.
+//!   - `pane <name>`: an ex command whose output opens in a read-only buffer.
+//!   - `transform <name>`: `:transform <name>` replaces the results with
+//!     the CSV it returns.
+//!   - `export <name>`: `:export <name> <file>` writes its output to `file`.
+//! - `dbvi_run(ptr: i32, len: i32) -> i64` handles a request: a line
+//!   `<kind> <name> <args>`, then the results as CSV with a header, if
+//!   there are any. Output starting with `error: ` is an error.
+//!
+//! Plugins get no imports, so nothing but what they are sent, and run with
+//! a fuel limit so one stuck in a loop can't take the UI with it.
+
+use std::fmt;
+use std::path::Path;
+
+use wasmi::{Config, Engine, Linker, Memory, Module, Store, TypedFunc};
+
+use crate::Command;
+use crate::export;
+use crate::results::{Column, ResultSet};
+
+/// Instructions a plugin may run per request by default, give or take; a
+/// few seconds' worth.
+pub const FUEL: u64 = 1_000_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Command,
+    Pane,
+    Transform,
+    Export,
+}
+
+impl Kind {
+    fn parse(word: &str) -> Option<Self> {
+        match word {
+            "command" => Some(Self::Command),
+            "pane" => Some(Self::Pane),
+            "transform" => Some(Self::Transform),
+            "export" => Some(Self::Export),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Command => "command",
+            Self::Pane => "pane",
+            Self::Transform => "transform",
+            Self::Export => "export",
+        })
+    }
+}
+
+struct Plugin {
+    /// The file name without its extension.
+    name: String,
+    store: Store<()>,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    run: TypedFunc<(i32, i32), i64>,
+    provides: Vec<(Kind, String)>,
+}
+
+impl fmt::Debug for Plugin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Plugin")
+            .field("name", &self.name)
+            .field("provides", &self.provides)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Plugin {
+    fn load(engine: &Engine, name: String, wasm: &[u8]) -> Result<Self, String> {
+        let module = Module::new(engine, wasm).map_err(|err| err.to_string())?;
+        let mut store = Store::new(engine, ());
+        let instance = Linker::new(engine)
+            .instantiate_and_start(&mut store, &module)
+            .map_err(|err| err.to_string())?;
+        let memory = instance
+            .get_memory(&store, "memory")
+            .ok_or("exports no memory")?;
+        let func = |name| format!("exports no {name} of the right type");
+        let alloc = instance
+            .get_typed_func(&store, "dbvi_alloc")
+            .map_err(|_| func("dbvi_alloc"))?;
+        let run = instance
+            .get_typed_func(&store, "dbvi_run")
+            .map_err(|_| func("dbvi_run"))?;
+        let manifest: TypedFunc<(), i64> = instance
+            .get_typed_func(&store, "dbvi_manifest")
+            .map_err(|_| func("dbvi_manifest"))?;
+        let mut plugin = Self {
+            name,
+            store,
+            memory,
+            alloc,
+            run,
+            provides: Vec::new(),
+        };
+        plugin.refuel(FUEL)?;
+        let packed = manifest
+            .call(&mut plugin.store, ())
+            .map_err(|err| err.to_string())?;
+        for line in plugin.read(packed)?.lines() {
+            let mut words = line.split_whitespace();
+            match (words.next().and_then(Kind::parse), words.next()) {
+                (Some(kind), Some(name)) => plugin.provides.push((kind, name.to_string())),
+                _ if line.trim().is_empty() => {}
+                _ => return Err(format!("bad manifest line `{line}`")),
+            }
+        }
+        Ok(plugin)
+    }
+
+    fn refuel(&mut self, fuel: u64) -> Result<(), String> {
+        self.store.set_fuel(fuel).map_err(|err| err.to_string())
+    }
+
+    /// The text at `ptr << 32 | len` in the plugin's memory.
+    fn read(&self, packed: i64) -> Result<String, String> {
+        let (ptr, len) = ((packed >> 32) as u32 as usize, packed as u32 as usize);
+        let bytes = self
+            .memory
+            .data(&self.store)
+            .get(ptr..ptr + len)
+            .ok_or("returned text out of bounds")?;
+        Ok(String::from_utf8_lossy(bytes).into_owned())
+    }
+
+    fn call(&mut self, request: &[u8], fuel: u64) -> Result<String, String> {
+        self.refuel(fuel)?;
+        let len = i32::try_from(request.len()).map_err(|_| "request too large")?;
+        let ptr = self
+            .alloc
+            .call(&mut self.store, len)
+            .map_err(|err| err.to_string())?;
+        self.memory
+            .write(&mut self.store, ptr as u32 as usize, request)
+            .map_err(|err| err.to_string())?;
+        let packed = self
+            .run
+            .call(&mut self.store, (ptr, len))
+            .map_err(|err| err.to_string())?;
+        let output = self.read(packed)?;
+        match output.strip_prefix("error: ") {
+            Some(err) => Err(err.trim_end().to_string()),
+            None => Ok(output),
+        }
+    }
+}
+
+/// Every plugin loaded.
+#[derive(Debug)]
+pub struct Plugins {
+    plugins: Vec<Plugin>,
+    /// Instructions each request may run.
+    pub fuel: u64,
+}
+
+impl Default for Plugins {
+    fn default() -> Self {
+        Self {
+            plugins: Vec::new(),
+            fuel: FUEL,
+        }
+    }
+}
+
+impl Plugins {
+    /// Loads every plugin in `dir`, sorted by name. Plugins that fail to
+    /// load are left out, with why in the returned errors.
+    pub fn load(dir: &Path) -> (Self, Vec<String>) {
+        let mut plugins = Self::default();
+        let mut errors = Vec::new();
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return (plugins, errors);
+        };
+        let mut paths: Vec<_> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|ext| ext == "wasm" || ext == "wat")
+            })
+            .collect();
+        paths.sort();
+
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config);
+        for path in paths {
+            let name = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let loaded = std::fs::read(&path)
+                .map_err(|err| err.to_string())
+                .and_then(|wasm| Plugin::load(&engine, name.clone(), &wasm));
+            match loaded {
+                Ok(plugin) => {
+                    tracing::info!(plugin = name, provides = ?plugin.provides, "loaded plugin");
+                    plugins.plugins.push(plugin);
+                }
+                Err(err) => {
+                    tracing::warn!(plugin = name, error = err, "failed to load plugin");
+                    errors.push(format!("{name}: {err}"));
+                }
+            }
+        }
+        (plugins, errors)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.plugins.is_empty()
+    }
+
+    /// Whether `name` is a command or a pane, and if so which.
+    pub fn command(&self, name: &str) -> Option<Kind> {
+        self.plugins
+            .iter()
+            .flat_map(|plugin| &plugin.provides)
+            .find(|(kind, provided)| matches!(kind, Kind::Command | Kind::Pane) && provided == name)
+            .map(|(kind, _)| *kind)
+    }
+
+    /// The command for an ex command `line` that a plugin provides.
+    pub fn parse(&self, line: &str) -> Option<Command> {
+        let line = line.trim();
+        let (name, args) = line.split_once(' ').unwrap_or((line, ""));
+        self.command(name)?;
+        Some(Command::Plugin {
+            name: name.to_string(),
+            args: args.trim().to_string(),
+        })
+    }
+
+    /// Runs the `kind` called `name` with `args`, sending it `results` as
+    /// CSV, and returns what it had to say.
+    pub fn run(
+        &mut self,
+        kind: Kind,
+        name: &str,
+        args: &str,
+        results: Option<&ResultSet>,
+    ) -> Result<String, String> {
+        let plugin = self
+            .plugins
+            .iter_mut()
+            .find(|plugin| plugin.provides.contains(&(kind, name.to_string())))
+            .ok_or_else(|| format!("No plugin provides a {kind} called {name}"))?;
+        let mut request = format!("{kind} {name} {args}")
+            .trim_end()
+            .as_bytes()
+            .to_vec();
+        request.push(b'\n');
+        if let Some(results) = results {
+            export::write_csv(results, &mut request).map_err(|err| err.to_string())?;
+        }
+        plugin
+            .call(&request, self.fuel)
+            .map_err(|err| format!("{}: {err}", plugin.name))
+    }
+
+    /// Runs the transform `name` on `results`, keeping the types of the
+    /// columns that come back under the same name.
+    pub fn transform(&mut self, name: &str, results: &ResultSet) -> Result<ResultSet, String> {
+        let output = self.run(Kind::Transform, name, "", Some(results))?;
+        let mut reader = csv::ReaderBuilder::new()
+            .flexible(true)
+            .from_reader(output.as_bytes());
+        let columns = reader
+            .headers()
+            .map_err(|err| format!("{name} returned bad CSV: {err}"))?
+            .iter()
+            .map(|header| {
+                let ty = results
+                    .column_index(header)
+                    .map_or("text", |index| results.columns[index].ty.as_str());
+                Column::new(header, ty)
+            })
+            .collect();
+        let mut transformed = ResultSet::new(columns);
+        for record in reader.records() {
+            let record = record.map_err(|err| format!("{name} returned bad CSV: {err}"))?;
+            let mut row: Vec<_> = record
+                .iter()
+                .map(|value| (!value.is_empty()).then(|| value.to_string()))
+                .collect();
+            row.resize(transformed.columns.len(), None);
+            transformed.rows.push(row);
+        }
+        Ok(transformed)
+    }
+
+    /// What each plugin provides, a line each, for `:plugins`.
+    pub fn describe(&self) -> String {
+        self.plugins
+            .iter()
+            .map(|plugin| {
+                let provides: Vec<String> = plugin
+                    .provides
+                    .iter()
+                    .map(|(kind, name)| format!("{kind} {name}"))
+                    .collect();
+                format!("{}: {}", plugin.name, provides.join(", "))
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
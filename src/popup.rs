@@ -0,0 +1,168 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Popups drawn over the panes, closed by the next key, and toasts that
+//! close themselves.
+
+use std::time::{Duration, Instant};
+
+use ratatui::{
+    Frame,
+    layout::{Constraint, Direction, Flex, Layout, Rect},
+    style::{Color, Style},
+    text::Line,
+    widgets::{Bar, BarChart, BarGroup, Block, Borders, Clear, Padding, Paragraph},
+};
+
+use crate::chart::Chart;
+use crate::geometry::Shape;
+use crate::width;
+
+#[derive(Debug, Clone)]
+pub enum Popup {
+    Text {
+        title: String,
+        lines: Vec<String>,
+    },
+    /// A horizontal bar chart of labelled counts.
+    Histogram {
+        title: String,
+        bars: Vec<(String, u64)>,
+    },
+    Chart(Chart),
+    /// A geometry plotted, from `Enter` on one.
+    Geometry {
+        title: String,
+        shape: Shape,
+    },
+}
+
+impl Popup {
+    pub fn render(&self, f: &mut Frame) {
+        match self {
+            Popup::Text { title, lines } => render_text(f, title, lines),
+            Popup::Histogram { title, bars } => render_histogram(f, title, bars),
+            Popup::Chart(chart) => {
+                let [area] = Layout::vertical([Constraint::Percentage(80)])
+                    .flex(Flex::Center)
+                    .areas(f.area());
+                let [area] = Layout::horizontal([Constraint::Percentage(90)])
+                    .flex(Flex::Center)
+                    .areas(area);
+                f.render_widget(Clear, area);
+                chart.render(f, area, block(chart.title()));
+            }
+            Popup::Geometry { title, shape } => shape.render(f, title),
+        }
+    }
+}
+
+/// A note in the corner that goes away by itself, and leaves the keys alone.
+#[derive(Debug, Clone)]
+pub struct Toast {
+    pub text: String,
+    until: Instant,
+}
+
+impl Toast {
+    pub fn new(text: impl Into<String>, shown_for: Duration) -> Self {
+        Self {
+            text: text.into(),
+            until: Instant::now() + shown_for,
+        }
+    }
+
+    pub fn expired(&self) -> bool {
+        Instant::now() >= self.until
+    }
+
+    /// When it goes away.
+    pub fn until(&self) -> Instant {
+        self.until
+    }
+
+    /// Draws it in the bottom right corner of `area`.
+    pub fn render(&self, f: &mut Frame, area: Rect) {
+        let width = (width::width(&self.text) as u16 + 4).min(area.width);
+        let [area] = Layout::vertical([Constraint::Length(3)])
+            .flex(Flex::End)
+            .areas(area);
+        let [area] = Layout::horizontal([Constraint::Length(width)])
+            .flex(Flex::End)
+            .areas(area);
+        f.render_widget(Clear, area);
+        f.render_widget(
+            Paragraph::new(self.text.as_str()).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(Color::Yellow))
+                    .padding(Padding::horizontal(1)),
+            ),
+            area,
+        );
+    }
+}
+
+fn block(title: &str) -> Block<'_> {
+    Block::default()
+        .title(Line::from(title).centered())
+        .borders(Borders::ALL)
+        .padding(Padding::horizontal(1))
+}
+
+fn render_histogram(f: &mut Frame, title: &str, bars: &[(String, u64)]) {
+    let [area] = Layout::vertical([Constraint::Length(bars.len() as u16 + 2)])
+        .flex(Flex::Center)
+        .areas(f.area());
+    let [area] = Layout::horizontal([Constraint::Percentage(80)])
+        .flex(Flex::Center)
+        .areas(area);
+    let bars: Vec<Bar> = bars
+        .iter()
+        .map(|(label, count)| {
+            Bar::default()
+                .label(Line::from(label.as_str()))
+                .value(*count)
+                .style(Style::default().fg(Color::Cyan))
+                .value_style(Style::default().fg(Color::Black).bg(Color::Cyan))
+        })
+        .collect();
+    let chart = BarChart::default()
+        .block(block(title))
+        .direction(Direction::Horizontal)
+        .bar_width(1)
+        .bar_gap(0)
+        .data(BarGroup::default().bars(&bars));
+    f.render_widget(Clear, area);
+    f.render_widget(chart, area);
+}
+
+fn render_text(f: &mut Frame, title: &str, lines: &[String]) {
+    let width = lines
+        .iter()
+        .map(|line| width::width(line))
+        .chain([width::width(title)])
+        .max()
+        .unwrap_or_default()
+        + 4;
+    let [area] = Layout::vertical([Constraint::Length(lines.len() as u16 + 2)])
+        .flex(Flex::Center)
+        .areas(f.area());
+    let [area] = Layout::horizontal([Constraint::Length(width as u16)])
+        .flex(Flex::Center)
+        .areas(area);
+    let text: Vec<Line> = lines.iter().map(|line| Line::from(line.as_str())).collect();
+    f.render_widget(Clear, area);
+    f.render_widget(Paragraph::new(text).block(block(title)), area);
+}
@@ -0,0 +1,175 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Queries queued to run one after another on the session's connection, in
+//! the background, and the `:queued` list of them, where they can be
+//! reordered or dropped before their turn comes.
+
+use std::collections::VecDeque;
+
+use ratatui::{
+    Frame,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::Paragraph,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Queued {
+    pub id: usize,
+    pub sql: String,
+}
+
+impl Queued {
+    /// The first line of code, for lists and messages.
+    pub fn summary(&self) -> &str {
+        self.sql
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty() && !line.starts_with("--"))
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Queue {
+    waiting: VecDeque<Queued>,
+    next: usize,
+    /// The one taken off the queue and the job running it.
+    pub running: Option<(usize, Queued)>,
+    /// Shown instead of the results until Esc.
+    pub shown: bool,
+    /// Index into the waiting ones.
+    pub cursor: usize,
+    scroll: usize,
+}
+
+impl Queue {
+    /// Adds `sql` at the back, returning its id and how many are ahead of
+    /// it, counting the one running.
+    pub fn push(&mut self, sql: String) -> (usize, usize) {
+        self.next += 1;
+        let ahead = self.waiting.len() + usize::from(self.running.is_some());
+        self.waiting.push_back(Queued { id: self.next, sql });
+        (self.next, ahead)
+    }
+
+    /// Forgets the waiting ones and the one running, returning how many
+    /// were waiting.
+    pub fn clear(&mut self) -> usize {
+        let dropped = self.waiting.len();
+        self.waiting.clear();
+        self.running = None;
+        self.cursor = 0;
+        dropped
+    }
+
+    /// Takes the one at the front for its turn.
+    pub fn pop(&mut self) -> Option<Queued> {
+        self.waiting.pop_front()
+    }
+
+    pub fn len(&self) -> usize {
+        self.waiting.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.waiting.is_empty()
+    }
+
+    /// The waiting ones, next first.
+    pub fn iter(&self) -> impl Iterator<Item = &Queued> {
+        self.waiting.iter()
+    }
+
+    /// Drops the one under the cursor from the queue.
+    pub fn remove_selected(&mut self) -> Option<Queued> {
+        let removed = self.waiting.remove(self.cursor);
+        self.cursor = self.cursor.min(self.waiting.len().saturating_sub(1));
+        removed
+    }
+
+    /// Moves the one under the cursor `by` places towards the back, the
+    /// cursor along with it.
+    pub fn move_selected(&mut self, by: isize) {
+        if self.waiting.is_empty() {
+            return;
+        }
+        let last = self.waiting.len() - 1;
+        let to = self.cursor.saturating_add_signed(by).min(last);
+        if let Some(queued) = self.waiting.remove(self.cursor) {
+            self.waiting.insert(to, queued);
+            self.cursor = to;
+        }
+    }
+
+    pub fn move_by(&mut self, rows: isize) {
+        let last = self.waiting.len().saturating_sub(1);
+        self.cursor = self.cursor.saturating_add_signed(rows).min(last);
+    }
+
+    pub fn top(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn bottom(&mut self) {
+        self.cursor = self.waiting.len().saturating_sub(1);
+    }
+
+    pub fn render(&mut self, f: &mut Frame, area: Rect, focused: bool) {
+        if self.waiting.is_empty() && self.running.is_none() {
+            f.render_widget(
+                Paragraph::new("Nothing queued, :queue adds the buffer")
+                    .style(Style::default().fg(Color::DarkGray)),
+                area,
+            );
+            return;
+        }
+        let mut lines = vec![Line::styled(
+            format!("{:>4}  {:>4}  query", "#", "id"),
+            Style::default().fg(Color::DarkGray),
+        )];
+        if let Some((job, queued)) = &self.running {
+            lines.push(Line::from(vec![
+                Span::styled(format!("{:>4}", "run"), Style::default().fg(Color::Yellow)),
+                Span::raw(format!("  {:>4}  {}", queued.id, queued.summary())),
+                Span::styled(
+                    format!("  job {job}, c cancels it"),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ]));
+        }
+        let height = area.height.saturating_sub(lines.len() as u16) as usize;
+        self.cursor = self.cursor.min(self.waiting.len().saturating_sub(1));
+        if self.cursor < self.scroll {
+            self.scroll = self.cursor;
+        } else if height > 0 && self.cursor >= self.scroll + height {
+            self.scroll = self.cursor + 1 - height;
+        }
+        for (row, queued) in self.iter().enumerate().skip(self.scroll).take(height) {
+            let mut line = Line::raw(format!(
+                "{:>4}  {:>4}  {}",
+                row + 1,
+                queued.id,
+                queued.summary()
+            ));
+            if focused && row == self.cursor {
+                line = line.patch_style(Style::default().add_modifier(Modifier::REVERSED));
+            }
+            lines.push(line);
+        }
+        f.render_widget(Paragraph::new(lines), area);
+    }
+}
@@ -0,0 +1,185 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The quickfix list: places to go through one after another, like the
+//! objects `:grep-schema` matched or the statements `:source` failed on.
+//! `:cnext` and `:cprev` walk it, `:copen` shows it in the results pane.
+
+use ratatui::{
+    Frame,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::Paragraph,
+};
+
+/// Where an entry takes you.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    /// The DDL of a table, or the definition of a view or function when
+    /// `definition`.
+    Object { name: String, definition: bool },
+    /// A line of a file, counting from 0.
+    Line { path: String, line: usize },
+    /// A place in an open buffer, counting from 0.
+    Position {
+        buffer: String,
+        row: usize,
+        col: usize,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub target: Target,
+    pub text: String,
+}
+
+impl Entry {
+    /// Where it is, as the list shows it.
+    pub fn location(&self) -> String {
+        match &self.target {
+            Target::Object { name, .. } => name.clone(),
+            Target::Line { path, line } => format!("{path}:{}", line + 1),
+            Target::Position { buffer, row, col } => format!("{buffer}:{}:{}", row + 1, col + 1),
+        }
+    }
+}
+
+/// Which entry to go to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Go {
+    Next,
+    Prev,
+    First,
+    Last,
+    /// Counting from 1, as `:cc` does.
+    Nth(usize),
+    /// The one under the cursor of the list.
+    Selected,
+    /// The current one again.
+    Current,
+}
+
+#[derive(Debug, Default)]
+pub struct Quickfix {
+    /// What filled it, like `grep-schema orders`.
+    pub title: String,
+    entries: Vec<Entry>,
+    /// The entry last gone to.
+    current: usize,
+    /// Shown instead of the results until Esc.
+    pub shown: bool,
+    pub cursor: usize,
+    scroll: usize,
+}
+
+impl Quickfix {
+    /// Replaces the entries with `entries`, from `title`.
+    pub fn set(&mut self, title: impl Into<String>, entries: Vec<Entry>) {
+        self.title = title.into();
+        self.entries = entries;
+        self.current = 0;
+        self.cursor = 0;
+        self.scroll = 0;
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// Makes the entry `go` points at the current one and returns it with
+    /// its position, counting from 1.
+    pub fn go(&mut self, go: Go) -> Result<(usize, &Entry), String> {
+        if self.entries.is_empty() {
+            return Err("The quickfix list is empty".into());
+        }
+        let last = self.entries.len() - 1;
+        self.current = match go {
+            Go::Next if self.current == last => return Err("No more items".into()),
+            Go::Next => self.current + 1,
+            Go::Prev if self.current == 0 => return Err("Already at the first item".into()),
+            Go::Prev => self.current - 1,
+            Go::First => 0,
+            Go::Last => last,
+            Go::Nth(0) => return Err("Items count from 1".into()),
+            Go::Nth(n) => (n - 1).min(last),
+            Go::Selected => self.cursor.min(last),
+            Go::Current => self.current.min(last),
+        };
+        self.cursor = self.current;
+        Ok((self.current + 1, &self.entries[self.current]))
+    }
+
+    pub fn move_by(&mut self, rows: isize) {
+        let last = self.entries.len().saturating_sub(1);
+        self.cursor = self.cursor.saturating_add_signed(rows).min(last);
+    }
+
+    pub fn top(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn bottom(&mut self) {
+        self.cursor = self.entries.len().saturating_sub(1);
+    }
+
+    pub fn render(&mut self, f: &mut Frame, area: Rect, focused: bool) {
+        if self.entries.is_empty() {
+            f.render_widget(
+                Paragraph::new("The quickfix list is empty")
+                    .style(Style::default().fg(Color::DarkGray)),
+                area,
+            );
+            return;
+        }
+        let height = area.height as usize;
+        self.cursor = self.cursor.min(self.entries.len() - 1);
+        if self.cursor < self.scroll {
+            self.scroll = self.cursor;
+        } else if height > 0 && self.cursor >= self.scroll + height {
+            self.scroll = self.cursor + 1 - height;
+        }
+
+        let lines: Vec<Line> = self
+            .entries
+            .iter()
+            .enumerate()
+            .skip(self.scroll)
+            .take(height)
+            .map(|(row, entry)| {
+                let marker = if row == self.current { "> " } else { "  " };
+                let mut line = Line::from(vec![
+                    Span::raw(marker),
+                    Span::styled(entry.location(), Style::default().fg(Color::Cyan)),
+                    Span::styled(" │ ", Style::default().fg(Color::DarkGray)),
+                    Span::raw(entry.text.lines().next().unwrap_or_default().to_string()),
+                ]);
+                if focused && row == self.cursor {
+                    line = line.patch_style(Style::default().add_modifier(Modifier::REVERSED));
+                }
+                line
+            })
+            .collect();
+        f.render_widget(Paragraph::new(lines), area);
+    }
+}
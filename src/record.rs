@@ -0,0 +1,189 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `:record` and `--replay`. A recording is a text file of the keys,
+//! pastes, clicks and resizes of a session, one to a line behind the
+//! milliseconds since it started:
+//!
+//! ```text
+//! 0 key i
+//! 180 key <C-w>
+//! 2310 paste "SELECT 1"
+//! 2900 click 12 4
+//! ```
+//!
+//! Keys are written as in mappings, so a recording can be edited by hand,
+//! and a `key` line may hold several. Each line is written as it happens,
+//! so a session that crashes still leaves the keys that led up to it.
+
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crossterm::event::{
+    Event, KeyEventKind, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
+
+use crate::action::Bus;
+use crate::keys;
+use crate::state::Message;
+
+/// A recording being written.
+#[derive(Debug)]
+pub struct Recorder {
+    file: File,
+    path: PathBuf,
+    started: Instant,
+    events: usize,
+    /// Where the last `:` was written, the events before it, and the keys
+    /// typed since, to take back the `:record` that stops the recording.
+    command: Option<(u64, usize, String)>,
+}
+
+impl Recorder {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let mut file = File::create(path)?;
+        let now = chrono::Local::now().format("%Y-%m-%d %H:%M:%S");
+        writeln!(file, "# dbvi recording, started {now}")?;
+        Ok(Self {
+            file,
+            path: path.to_path_buf(),
+            started: Instant::now(),
+            events: 0,
+            command: None,
+        })
+    }
+
+    /// How many events have been written.
+    pub fn events(&self) -> usize {
+        self.events
+    }
+
+    /// Writes `event` down, if it is one that is replayed.
+    pub fn record(&mut self, event: &Event) -> io::Result<()> {
+        let Some(line) = line(event) else {
+            return Ok(());
+        };
+        if let Event::Key(key) = event {
+            match (keys::notation(key).as_str(), &mut self.command) {
+                (":", _) => {
+                    let offset = self.file.metadata()?.len();
+                    self.command = Some((offset, self.events, String::new()));
+                }
+                (notation, Some((_, _, typed))) => typed.push_str(notation),
+                _ => {}
+            }
+        }
+        let at = self.started.elapsed().as_millis();
+        writeln!(self.file, "{at} {line}")?;
+        self.events += 1;
+        Ok(())
+    }
+
+    /// Stops recording, leaving out the keys of the `:record` that stopped
+    /// it, as vim leaves out the `q`.
+    pub fn finish(mut self) -> io::Result<(PathBuf, usize)> {
+        if let Some((offset, events, typed)) = self.command.take()
+            && typed == "record<CR>"
+        {
+            self.file.set_len(offset)?;
+            self.events = events;
+        }
+        Ok((self.path, self.events))
+    }
+}
+
+/// `event` as a line of a recording, without its time.
+pub fn line(event: &Event) -> Option<String> {
+    match event {
+        Event::Key(key) if key.kind != KeyEventKind::Release => {
+            let notation = keys::notation(key);
+            (!notation.is_empty()).then(|| format!("key {notation}"))
+        }
+        Event::Paste(text) => Some(format!("paste {}", serde_json::Value::from(text.as_str()))),
+        Event::Resize(width, height) => Some(format!("resize {width} {height}")),
+        Event::Mouse(mouse) => {
+            let kind = match mouse.kind {
+                MouseEventKind::Down(MouseButton::Left) => "click",
+                MouseEventKind::Drag(MouseButton::Left) => "drag",
+                MouseEventKind::Up(MouseButton::Left) => "release",
+                MouseEventKind::ScrollUp => "scroll-up",
+                MouseEventKind::ScrollDown => "scroll-down",
+                _ => return None,
+            };
+            Some(format!("{kind} {} {}", mouse.column, mouse.row))
+        }
+        _ => None,
+    }
+}
+
+/// The events of a recording, each with when it happened.
+pub fn parse(text: &str) -> Result<Vec<(Duration, Event)>, String> {
+    let mut events = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let parsed = parse_line(line).ok_or_else(|| format!("Line {}: {line}", number + 1))?;
+        events.extend(parsed);
+    }
+    Ok(events)
+}
+
+fn parse_line(line: &str) -> Option<Vec<(Duration, Event)>> {
+    let (at, rest) = line.split_once(' ')?;
+    let at = Duration::from_millis(at.parse().ok()?);
+    let (kind, args) = rest.split_once(' ').unwrap_or((rest, ""));
+    let events = match kind {
+        "key" if !args.is_empty() => keys::parse(args).into_iter().map(Event::Key).collect(),
+        "paste" => vec![Event::Paste(serde_json::from_str(args).ok()?)],
+        _ => {
+            let (x, y) = args.split_once(' ')?;
+            let (x, y) = (x.parse().ok()?, y.parse().ok()?);
+            let kind = match kind {
+                "resize" => return Some(vec![(at, Event::Resize(x, y))]),
+                "click" => MouseEventKind::Down(MouseButton::Left),
+                "drag" => MouseEventKind::Drag(MouseButton::Left),
+                "release" => MouseEventKind::Up(MouseButton::Left),
+                "scroll-up" => MouseEventKind::ScrollUp,
+                "scroll-down" => MouseEventKind::ScrollDown,
+                _ => return None,
+            };
+            vec![Event::Mouse(MouseEvent {
+                kind,
+                column: x,
+                row: y,
+                modifiers: KeyModifiers::NONE,
+            })]
+        }
+    };
+    Some(events.into_iter().map(|event| (at, event)).collect())
+}
+
+/// Plays `events` into the session in the background, each at its time
+/// from now, as if they were typed.
+pub fn replay(events: Vec<(Duration, Event)>, bus: Bus) {
+    tokio::spawn(async move {
+        let started = tokio::time::Instant::now();
+        for (at, event) in events {
+            tokio::time::sleep_until(started + at).await;
+            if bus.send(event).is_err() {
+                return;
+            }
+        }
+        let _ = bus.send(Message::Status("Replay finished".into()));
+    });
+}
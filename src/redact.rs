@@ -0,0 +1,188 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Columns masked for screen sharing. Rules are globs on column names,
+//! `*password*` or `ssn`, matched without regard to case; from the profile's
+//! `redact` list and `:redact`. A masked column shows `••••` in the grid and
+//! in everything that leaves it, yanks, exports, pipes and `:copy`, so a
+//! demo against real data doesn't put it on the screen. `NULL` stays `NULL`.
+
+use std::borrow::Cow;
+
+use sqlx::{Column as _, Executor, PgPool};
+
+use crate::results::ResultSet;
+use crate::statements::{quote_ident, quote_literal};
+
+/// What a redacted value shows as.
+pub const MASK: &str = "••••";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Redactions {
+    patterns: Vec<String>,
+}
+
+impl Redactions {
+    pub fn new(patterns: impl IntoIterator<Item = String>) -> Self {
+        let mut redactions = Self::default();
+        for pattern in patterns {
+            redactions.add(&pattern);
+        }
+        redactions
+    }
+
+    pub fn patterns(&self) -> &[String] {
+        &self.patterns
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    /// Adds a rule, false if there already was one like it.
+    pub fn add(&mut self, pattern: &str) -> bool {
+        let pattern = pattern.trim();
+        if pattern.is_empty()
+            || self
+                .patterns
+                .iter()
+                .any(|known| known.eq_ignore_ascii_case(pattern))
+        {
+            return false;
+        }
+        self.patterns.push(pattern.to_string());
+        true
+    }
+
+    /// Drops a rule, false if there was none like it.
+    pub fn remove(&mut self, pattern: &str) -> bool {
+        let len = self.patterns.len();
+        self.patterns
+            .retain(|known| !known.eq_ignore_ascii_case(pattern.trim()));
+        self.patterns.len() < len
+    }
+
+    /// Whether a column called `name` is masked.
+    pub fn covers(&self, name: &str) -> bool {
+        let name = name.to_lowercase();
+        self.patterns
+            .iter()
+            .any(|pattern| glob(&pattern.to_lowercase(), &name))
+    }
+
+    /// Which of the columns of `results` are masked.
+    pub fn columns(&self, results: &ResultSet) -> Vec<bool> {
+        results
+            .columns
+            .iter()
+            .map(|column| self.covers(&column.name))
+            .collect()
+    }
+
+    /// `results` with the masked columns masked, borrowed when none are.
+    pub fn apply<'a>(&self, results: &'a ResultSet) -> Cow<'a, ResultSet> {
+        let masked = self.columns(results);
+        if !masked.contains(&true) {
+            return Cow::Borrowed(results);
+        }
+        let mut results = results.clone();
+        for (col, _) in masked.iter().enumerate().filter(|(_, masked)| **masked) {
+            for row in 0..results.rows.len() {
+                if results.rows.cell(row, col).is_some() {
+                    results.rows.set(row, col, Some(MASK));
+                }
+            }
+        }
+        Cow::Owned(results)
+    }
+
+    /// `query`, whose columns are `names`, with the masked columns masked by
+    /// the server, for output that never passes through the grid. `None`
+    /// when no column is masked.
+    pub fn wrap(&self, query: &str, names: &[String]) -> Option<String> {
+        if !names.iter().any(|name| self.covers(name)) {
+            return None;
+        }
+        // Positional aliases, since the query's own names may repeat.
+        let aliases: Vec<String> = (1..=names.len()).map(|n| format!("c{n}")).collect();
+        let select: Vec<String> = names
+            .iter()
+            .zip(&aliases)
+            .map(|(name, alias)| {
+                if self.covers(name) {
+                    format!(
+                        "CASE WHEN {alias} IS NULL THEN NULL ELSE {} END AS {}",
+                        quote_literal(MASK),
+                        quote_ident(name)
+                    )
+                } else {
+                    format!("{alias} AS {}", quote_ident(name))
+                }
+            })
+            .collect();
+        Some(format!(
+            "SELECT {} FROM ({}) AS t({})",
+            select.join(", "),
+            query.trim_end().trim_end_matches(';'),
+            aliases.join(", ")
+        ))
+    }
+
+    /// `query` masked by [`Redactions::wrap`], its columns looked up on the
+    /// server first should any rule need them.
+    pub async fn masked_query(&self, pool: &PgPool, query: &str) -> Result<String, sqlx::Error> {
+        if self.is_empty() {
+            return Ok(query.to_string());
+        }
+        let described = pool.describe(query).await?;
+        let names: Vec<String> = described
+            .columns()
+            .iter()
+            .map(|column| column.name().to_string())
+            .collect();
+        Ok(self
+            .wrap(query, &names)
+            .unwrap_or_else(|| query.to_string()))
+    }
+}
+
+/// Whether `name` matches `pattern`, where `*` stands for any run of
+/// characters and `?` for one.
+fn glob(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    let mut star = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    p = star_p + 1;
+                    n = star_n + 1;
+                    star = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
@@ -0,0 +1,893 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Query results as the grid shows them: every value in Postgres' text
+//! output format, `None` for `NULL`.
+//!
+//! Rows are kept column by column, each column's values back to back in one
+//! string, so a million rows take about as much memory as their text rather
+//! than an allocation per cell.
+//!
+//! Past `:set resultmem`, the older rows are written out to a temporary file
+//! in chunks and read back a chunk at a time as the grid scrolls to them.
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read as _, Seek as _, SeekFrom, Write as _};
+use std::ops::Range;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, AtomicUsize};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use sqlx::postgres::{PgColumn, PgRow};
+use sqlx::{Column as _, Executor, PgPool, Postgres, Row as _, TypeInfo, ValueRef};
+
+use crate::statements;
+use crate::values;
+
+#[derive(Debug, Clone)]
+pub struct Column {
+    pub name: String,
+    /// Postgres type name, like `int4` or `timestamptz`.
+    pub ty: String,
+    /// The OID of the table and the number of the column of it this one
+    /// was read straight from, if it was.
+    pub origin: Option<(u32, i16)>,
+}
+
+impl Column {
+    pub fn new(name: impl Into<String>, ty: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ty: ty.into(),
+            origin: None,
+        }
+    }
+
+    fn from_pg(column: &PgColumn) -> Self {
+        Self {
+            origin: column
+                .relation_id()
+                .zip(column.relation_attribute_no())
+                .map(|(table, attnum)| (table.0, attnum)),
+            ..Self::new(column.name(), column.type_info().name().to_lowercase())
+        }
+    }
+
+    pub fn is_numeric(&self) -> bool {
+        matches!(
+            self.ty.as_str(),
+            "int2" | "int4" | "int8" | "float4" | "float8" | "numeric" | "oid" | "money"
+        )
+    }
+}
+
+pub type Cell = Option<String>;
+
+/// Bytes of rows a result keeps in memory before spilling, 0 for no limit.
+static MEMORY_LIMIT: AtomicUsize = AtomicUsize::new(DEFAULT_MEMORY_LIMIT);
+
+/// `:set resultmem` as it starts, in bytes.
+pub const DEFAULT_MEMORY_LIMIT: usize = 512 << 20;
+
+/// A spilled result writes its rows out in pieces of about this part of the
+/// limit, so scrolling back reads one piece rather than all of them.
+const CHUNKS: usize = 4;
+
+/// Sets the memory each result may take before older rows go to disk, 0 to
+/// keep them all in memory. Results already fetched keep theirs.
+pub fn set_memory_limit(bytes: usize) {
+    MEMORY_LIMIT.store(bytes, std::sync::atomic::Ordering::Relaxed);
+}
+
+/// The values of one column.
+#[derive(Debug, Clone, Default)]
+struct Values {
+    text: String,
+    /// Where each value ends in `text`.
+    ends: Vec<usize>,
+    /// A bit per value, set for `NULL`.
+    nulls: Vec<u64>,
+}
+
+impl Values {
+    fn push(&mut self, value: Option<&str>) {
+        let index = self.ends.len();
+        if index.is_multiple_of(64) {
+            self.nulls.push(0);
+        }
+        match value {
+            Some(value) => self.text.push_str(value),
+            None => self.nulls[index / 64] |= 1 << (index % 64),
+        }
+        self.ends.push(self.text.len());
+    }
+
+    fn get(&self, index: usize) -> Option<&str> {
+        let end = *self.ends.get(index)?;
+        if self.nulls[index / 64] & (1 << (index % 64)) != 0 {
+            return None;
+        }
+        let start = index
+            .checked_sub(1)
+            .map_or(0, |previous| self.ends[previous]);
+        Some(&self.text[start..end])
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        out.extend((self.text.len() as u64).to_le_bytes());
+        out.extend(self.text.as_bytes());
+        out.extend((self.ends.len() as u64).to_le_bytes());
+        out.extend(self.ends.iter().flat_map(|end| (*end as u64).to_le_bytes()));
+        out.extend((self.nulls.len() as u64).to_le_bytes());
+        out.extend(self.nulls.iter().flat_map(|bits| bits.to_le_bytes()));
+    }
+
+    /// Reads back what `write` wrote at the start of `bytes`, and the rest.
+    fn read(bytes: &[u8]) -> io::Result<(Self, &[u8])> {
+        fn take(bytes: &[u8], len: usize) -> io::Result<(&[u8], &[u8])> {
+            (len <= bytes.len())
+                .then(|| bytes.split_at(len))
+                .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))
+        }
+        fn number(bytes: &[u8]) -> io::Result<(u64, &[u8])> {
+            let (number, rest) = take(bytes, 8)?;
+            Ok((
+                u64::from_le_bytes(number.try_into().unwrap_or_default()),
+                rest,
+            ))
+        }
+        fn numbers(bytes: &[u8]) -> io::Result<(Vec<u64>, &[u8])> {
+            let (len, rest) = number(bytes)?;
+            let (numbers, rest) = take(rest, len as usize * 8)?;
+            let numbers = numbers
+                .chunks_exact(8)
+                .map(|number| u64::from_le_bytes(number.try_into().unwrap_or_default()))
+                .collect();
+            Ok((numbers, rest))
+        }
+        let (len, rest) = number(bytes)?;
+        let (text, rest) = take(rest, len as usize)?;
+        let text = String::from_utf8(text.to_vec())
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        let (ends, rest) = numbers(rest)?;
+        let (nulls, rest) = numbers(rest)?;
+        let ends = ends.into_iter().map(|end| end as usize).collect();
+        Ok((Self { text, ends, nulls }, rest))
+    }
+}
+
+/// The temporary file spilled rows are kept in, removed with the last of
+/// the rows sharing it.
+#[derive(Debug)]
+struct SpillFile {
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+impl SpillFile {
+    fn create() -> io::Result<Self> {
+        static FILES: AtomicU64 = AtomicU64::new(0);
+        let number = FILES.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        let path = std::env::temp_dir().join(format!("dbvi-{}-{number}.rows", std::process::id()));
+        let file = File::options()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        Ok(Self {
+            path,
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Appends `bytes`, returning where they start.
+    fn append(&self, bytes: &[u8]) -> io::Result<u64> {
+        let mut file = self.file.lock().unwrap_or_else(|err| err.into_inner());
+        let offset = file.seek(SeekFrom::End(0))?;
+        file.write_all(bytes)?;
+        Ok(offset)
+    }
+
+    fn read(&self, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+        let mut file = self.file.lock().unwrap_or_else(|err| err.into_inner());
+        file.seek(SeekFrom::Start(offset))?;
+        let mut bytes = vec![0; len];
+        file.read_exact(&mut bytes)?;
+        Ok(bytes)
+    }
+}
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Rows written out to the spill file, read back in when first asked for.
+#[derive(Debug, Clone)]
+struct Chunk {
+    /// The first of the rows.
+    start: usize,
+    len: usize,
+    offset: u64,
+    bytes: usize,
+    loaded: OnceLock<Vec<Values>>,
+}
+
+impl Chunk {
+    fn rows(&self) -> Range<usize> {
+        self.start..self.start + self.len
+    }
+
+    fn values(&self, file: &SpillFile, width: usize) -> &[Values] {
+        self.loaded.get_or_init(|| {
+            let read = file.read(self.offset, self.bytes).and_then(|bytes| {
+                let mut rest = bytes.as_slice();
+                let mut columns = Vec::with_capacity(width);
+                for _ in 0..width {
+                    let (values, after) = Values::read(rest)?;
+                    columns.push(values);
+                    rest = after;
+                }
+                Ok(columns)
+            });
+            read.unwrap_or_else(|err| {
+                tracing::warn!(error = %err, path = %file.path.display(), "failed to read spilled rows");
+                vec![Values::default(); width]
+            })
+        })
+    }
+}
+
+/// Where the rows go past the memory they may take.
+#[derive(Debug, Clone, Default)]
+struct Spill {
+    /// Bytes to keep in memory, `set_memory_limit`'s if `None`.
+    limit: Option<usize>,
+    file: Option<Arc<SpillFile>>,
+    /// The older rows, once they took more memory than the limit allows.
+    chunks: Vec<Chunk>,
+    /// Values set in the spilled rows since, by row and column, which the
+    /// spill file is never rewritten for.
+    set: BTreeMap<(usize, usize), Cell>,
+}
+
+/// The rows of a result set.
+#[derive(Clone, Default)]
+pub struct Rows {
+    /// The rows in memory, those after the spilled ones.
+    columns: Vec<Values>,
+    len: usize,
+    /// Roughly the bytes `columns` takes.
+    resident: usize,
+    /// Boxed, as most results never need it.
+    spill: Option<Box<Spill>>,
+}
+
+impl Rows {
+    /// Spills rows to disk past `bytes` of them rather than past
+    /// `set_memory_limit`'s, 0 for never.
+    pub fn spill_over(mut self, bytes: usize) -> Self {
+        self.spill.get_or_insert_default().limit = Some(bytes);
+        self
+    }
+
+    fn chunks(&self) -> &[Chunk] {
+        self.spill.as_deref().map_or(&[], |spill| &spill.chunks)
+    }
+
+    /// A spill with only the limit of this one, for rows made from these.
+    fn limited(&self) -> Option<Box<Spill>> {
+        let limit = self.spill.as_deref()?.limit?;
+        Some(Box::new(Spill {
+            limit: Some(limit),
+            ..Spill::default()
+        }))
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// How many of the rows are in the spill file rather than in memory.
+    pub fn on_disk(&self) -> usize {
+        self.chunks().last().map_or(0, |chunk| chunk.rows().end)
+    }
+
+    /// Appends a row, padded with `NULL`s or cut to the width of the rows
+    /// before it. The first row of empty rows sets the width.
+    pub fn push<S: AsRef<str>>(&mut self, row: impl IntoIterator<Item = Option<S>>) {
+        let mut row = row.into_iter();
+        if self.len == 0 && self.columns.is_empty() {
+            for value in row {
+                let mut values = Values::default();
+                let value = value.as_ref().map(AsRef::as_ref);
+                self.resident += value.map_or(0, str::len) + 8;
+                values.push(value);
+                self.columns.push(values);
+            }
+        } else {
+            for values in &mut self.columns {
+                let value = row.next().flatten();
+                let value = value.as_ref().map(AsRef::as_ref);
+                self.resident += value.map_or(0, str::len) + 8;
+                values.push(value);
+            }
+        }
+        self.len += 1;
+
+        let limit = self
+            .spill
+            .as_deref()
+            .and_then(|spill| spill.limit)
+            .unwrap_or_else(|| MEMORY_LIMIT.load(std::sync::atomic::Ordering::Relaxed));
+        let spilled: usize = self.chunks().iter().map(|chunk| chunk.bytes).sum();
+        if limit > 0
+            && self.resident >= limit / CHUNKS
+            && self.resident + spilled > limit
+            && let Err(err) = self.spill(limit / CHUNKS)
+        {
+            // Keep them in memory after all rather than lose them.
+            tracing::warn!(error = %err, "failed to spill rows to disk");
+            self.spill.get_or_insert_default().limit = Some(0);
+        }
+    }
+
+    /// Writes the rows in memory out to the spill file, in chunks of about
+    /// `piece` bytes.
+    fn spill(&mut self, piece: usize) -> io::Result<()> {
+        let first = self.on_disk();
+        let spill = self.spill.get_or_insert_default();
+        let file = match &spill.file {
+            Some(file) => file.clone(),
+            None => spill.file.insert(Arc::new(SpillFile::create()?)).clone(),
+        };
+        let mut chunks = Vec::new();
+        let resident = self.len - first;
+        let mut start = 0;
+        while start < resident {
+            let mut end = start;
+            let mut size = 0;
+            while end < resident && (size < piece || end == start) {
+                size += self
+                    .columns
+                    .iter()
+                    .map(|values| values.get(end).map_or(0, str::len) + 8)
+                    .sum::<usize>();
+                end += 1;
+            }
+            let mut bytes = Vec::with_capacity(size);
+            for values in &self.columns {
+                let mut chunk = Values::default();
+                for index in start..end {
+                    chunk.push(values.get(index));
+                }
+                chunk.write(&mut bytes);
+            }
+            let offset = file.append(&bytes)?;
+            chunks.push(Chunk {
+                start: first + start,
+                len: end - start,
+                offset,
+                bytes: bytes.len(),
+                loaded: OnceLock::new(),
+            });
+            start = end;
+        }
+        spill.chunks.extend(chunks);
+        self.columns = vec![Values::default(); self.columns.len()];
+        self.resident = 0;
+        Ok(())
+    }
+
+    /// Lets go of the spilled rows read back in, but for those in `keep`.
+    /// Anything reading all the rows, like a sort or an export, reads them
+    /// all back in until this is called.
+    pub fn page_out(&mut self, keep: Range<usize>) {
+        let Some(spill) = &mut self.spill else {
+            return;
+        };
+        for chunk in &mut spill.chunks {
+            let rows = chunk.rows();
+            if rows.end <= keep.start || keep.end <= rows.start {
+                chunk.loaded.take();
+            }
+        }
+    }
+
+    pub fn get(&self, index: usize) -> Option<Row<'_>> {
+        (index < self.len).then_some(Row { rows: self, index })
+    }
+
+    /// The value in row `row` of column `col`, `None` for `NULL` or outside
+    /// the rows.
+    pub fn cell(&self, row: usize, col: usize) -> Option<&str> {
+        let on_disk = self.on_disk();
+        if row >= on_disk {
+            return self.columns.get(col)?.get(row - on_disk);
+        }
+        let spill = self.spill.as_deref()?;
+        if let Some(value) = spill.set.get(&(row, col)) {
+            return value.as_deref();
+        }
+        let chunk = &spill.chunks[spill
+            .chunks
+            .partition_point(|chunk| chunk.rows().end <= row)];
+        let file = spill.file.as_ref()?;
+        chunk
+            .values(file, self.columns.len())
+            .get(col)?
+            .get(row - chunk.start)
+    }
+
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            rows: self,
+            indexes: 0..self.len,
+        }
+    }
+
+    /// No rows, of the same width and limit.
+    fn emptied(&self) -> Self {
+        Self {
+            columns: vec![Values::default(); self.columns.len()],
+            spill: self.limited(),
+            ..Self::default()
+        }
+    }
+
+    /// The rows at `indexes`, in that order.
+    pub fn select(&self, indexes: impl IntoIterator<Item = usize>) -> Self {
+        let mut rows = self.emptied();
+        for index in indexes {
+            if let Some(row) = self.get(index) {
+                rows.push(row);
+            }
+        }
+        rows
+    }
+
+    /// Puts `value` in row `row` of column `col`.
+    pub fn set(&mut self, row: usize, col: usize, value: Option<&str>) {
+        let on_disk = self.on_disk();
+        if row < on_disk {
+            if col < self.columns.len()
+                && let Some(spill) = &mut self.spill
+            {
+                spill.set.insert((row, col), value.map(String::from));
+            }
+            return;
+        }
+        let Some(values) = self.columns.get_mut(col) else {
+            return;
+        };
+        let mut changed = Values::default();
+        for index in 0..self.len - on_disk {
+            changed.push(if index == row - on_disk {
+                value
+            } else {
+                values.get(index)
+            });
+        }
+        self.resident = self.resident - values.text.len() + changed.text.len();
+        *values = changed;
+    }
+
+    /// Keeps the first `len` rows.
+    pub fn truncate(&mut self, len: usize) {
+        if len < self.len {
+            *self = self.select(0..len);
+        }
+    }
+
+    /// Keeps the rows `keep` says to.
+    pub fn retain(&mut self, mut keep: impl FnMut(Row<'_>) -> bool) {
+        let kept: Vec<usize> = self
+            .iter()
+            .filter(|row| keep(*row))
+            .map(|row| row.index)
+            .collect();
+        if kept.len() < self.len {
+            *self = self.select(kept);
+        }
+    }
+
+    /// Only the columns at `columns`, in that order.
+    pub fn project(&self, columns: &[usize]) -> Self {
+        if !self.chunks().is_empty() {
+            let mut projected = Self {
+                columns: vec![Values::default(); columns.len()],
+                spill: self.limited(),
+                ..Self::default()
+            };
+            for row in self {
+                projected.push(columns.iter().map(|&col| row.get(col)));
+            }
+            return projected;
+        }
+        let columns: Vec<Values> = columns
+            .iter()
+            .map(|&col| self.columns[col].clone())
+            .collect();
+        Self {
+            resident: columns
+                .iter()
+                .map(|values| values.text.len() + values.ends.len() * 8)
+                .sum(),
+            columns,
+            len: self.len,
+            spill: self.limited(),
+        }
+    }
+}
+
+impl PartialEq for Rows {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len
+            && self
+                .iter()
+                .zip(other.iter())
+                .all(|(a, b)| a.iter().eq(b.iter()))
+    }
+}
+
+impl fmt::Debug for Rows {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<S: AsRef<str>, R: IntoIterator<Item = Option<S>>> FromIterator<R> for Rows {
+    fn from_iter<I: IntoIterator<Item = R>>(rows: I) -> Self {
+        let mut collected = Self::default();
+        for row in rows {
+            collected.push(row);
+        }
+        collected
+    }
+}
+
+impl From<Vec<Vec<Cell>>> for Rows {
+    fn from(rows: Vec<Vec<Cell>>) -> Self {
+        rows.into_iter().collect()
+    }
+}
+
+impl<'a> IntoIterator for &'a Rows {
+    type Item = Row<'a>;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct Iter<'a> {
+    rows: &'a Rows,
+    indexes: std::ops::Range<usize>,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = Row<'a>;
+
+    fn next(&mut self) -> Option<Row<'a>> {
+        let index = self.indexes.next()?;
+        Some(Row {
+            rows: self.rows,
+            index,
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.indexes.size_hint()
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Row<'a>> {
+        let index = self.indexes.nth(n)?;
+        Some(Row {
+            rows: self.rows,
+            index,
+        })
+    }
+}
+
+impl DoubleEndedIterator for Iter<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let index = self.indexes.next_back()?;
+        Some(Row {
+            rows: self.rows,
+            index,
+        })
+    }
+}
+
+impl ExactSizeIterator for Iter<'_> {}
+
+/// One row of `Rows`.
+#[derive(Clone, Copy)]
+pub struct Row<'a> {
+    rows: &'a Rows,
+    index: usize,
+}
+
+impl<'a> Row<'a> {
+    /// The value in column `col`, `None` for `NULL`.
+    pub fn get(self, col: usize) -> Option<&'a str> {
+        self.rows.cell(self.index, col)
+    }
+
+    pub fn len(self) -> usize {
+        self.rows.columns.len()
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.rows.columns.is_empty()
+    }
+
+    pub fn iter(self) -> Cells<'a> {
+        Cells {
+            row: self,
+            cols: 0..self.len(),
+        }
+    }
+
+    pub fn to_vec(self) -> Vec<Cell> {
+        self.iter().map(|value| value.map(String::from)).collect()
+    }
+}
+
+impl<'a> IntoIterator for Row<'a> {
+    type Item = Option<&'a str>;
+    type IntoIter = Cells<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// The values of a `Row`, `None` for `NULL`.
+pub struct Cells<'a> {
+    row: Row<'a>,
+    cols: std::ops::Range<usize>,
+}
+
+impl<'a> Iterator for Cells<'a> {
+    type Item = Option<&'a str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.cols.next().map(|col| self.row.get(col))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.cols.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for Cells<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.cols.next_back().map(|col| self.row.get(col))
+    }
+}
+
+impl ExactSizeIterator for Cells<'_> {}
+
+impl fmt::Debug for Row<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ResultSet {
+    pub columns: Vec<Column>,
+    pub rows: Rows,
+}
+
+impl ResultSet {
+    pub fn new(columns: Vec<Column>) -> Self {
+        Self {
+            rows: Rows {
+                columns: vec![Values::default(); columns.len()],
+                ..Rows::default()
+            },
+            columns,
+        }
+    }
+
+    /// Sorts the rows by column `col`, `NULL`s last either way. Returns the
+    /// row each one was before.
+    pub fn sort(&mut self, col: usize, descending: bool) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.rows.len()).collect();
+        let cell = |row: usize| self.rows.cell(row, col);
+        let column = &self.columns[col];
+        order.sort_by(|&a, &b| match (cell(a), cell(b)) {
+            (Some(a), Some(b)) if descending => values::compare(column, b, a),
+            (Some(a), Some(b)) => values::compare(column, a, b),
+            (a, b) => a.is_none().cmp(&b.is_none()),
+        });
+        self.rows = self.rows.select(order.iter().copied());
+        order
+    }
+
+    /// Index of the column called `name`, ignoring case if nothing matches
+    /// exactly.
+    pub fn column_index(&self, name: &str) -> Option<usize> {
+        self.columns
+            .iter()
+            .position(|column| column.name == name)
+            .or_else(|| {
+                self.columns
+                    .iter()
+                    .position(|column| column.name.eq_ignore_ascii_case(name))
+            })
+    }
+
+    /// Columns from a row received in text format, as from `sqlx::raw_sql`.
+    pub fn from_row(row: &PgRow) -> Self {
+        Self::new(row.columns().iter().map(Column::from_pg).collect())
+    }
+
+    /// Appends a row received in text format.
+    pub fn push_text_row(&mut self, row: &PgRow) -> Result<(), sqlx::Error> {
+        let mut cells = Vec::with_capacity(row.len());
+        for index in 0..row.len() {
+            let value = row.try_get_raw(index)?;
+            cells.push(if value.is_null() {
+                None
+            } else {
+                Some(value.as_str().map_err(sqlx::Error::Decode)?)
+            });
+        }
+        self.rows.push(cells);
+        Ok(())
+    }
+}
+
+/// Reads a size as `pg_size_pretty` writes it, `8192 bytes` or `12 MB`.
+fn parse_size(text: &str) -> Option<f64> {
+    let (number, unit) = text.split_once(' ')?;
+    let power = ["bytes", "kB", "MB", "GB", "TB", "PB"]
+        .iter()
+        .position(|u| *u == unit)?;
+    Some(number.parse::<f64>().ok()? * 1024f64.powi(power as i32))
+}
+
+/// Orders values in text format: as numbers or sizes if both are, as text
+/// otherwise.
+pub(crate) fn compare(a: &str, b: &str) -> Ordering {
+    let number = |text: &str| text.parse::<f64>().ok().or_else(|| parse_size(text));
+    match (number(a), number(b)) {
+        (Some(a), Some(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+        _ => a.cmp(b),
+    }
+}
+
+/// The columns `query`, a single statement, returns, with no rows.
+pub async fn describe(pool: &PgPool, query: &str) -> Result<ResultSet, sqlx::Error> {
+    let columns = pool
+        .describe(query)
+        .await?
+        .columns()
+        .iter()
+        .map(Column::from_pg)
+        .collect();
+    Ok(ResultSet::new(columns))
+}
+
+/// Runs `query`, any number of statements, with the simple query protocol,
+/// which sends every value as text, as the grid shows it. Returns the rows
+/// of each statement that returned any, along with the statement, and the
+/// number of rows returned or affected by the last one.
+pub async fn fetch_batch<'c>(
+    executor: impl Executor<'c, Database = Postgres>,
+    query: &'c str,
+) -> Result<(Vec<(String, ResultSet)>, u64), sqlx::Error> {
+    use futures_util::TryStreamExt;
+    use sqlx::Either;
+
+    let statements = statements::split(query);
+    let mut stream = sqlx::raw_sql(query).fetch_many(executor);
+    let mut results: Vec<(String, ResultSet)> = Vec::new();
+    let mut done_statements = 0;
+    let mut statement_done = true;
+    let mut rows = 0;
+    while let Some(item) = stream.try_next().await? {
+        match item {
+            Either::Left(done) => {
+                rows = done.rows_affected();
+                done_statements += 1;
+                statement_done = true;
+            }
+            Either::Right(row) => {
+                if statement_done {
+                    let sql = statements.get(done_statements).copied();
+                    results.push((sql.unwrap_or(query).to_string(), ResultSet::from_row(&row)));
+                    statement_done = false;
+                }
+                if let Some((_, results)) = results.last_mut() {
+                    results.push_text_row(&row)?;
+                }
+            }
+        }
+    }
+    Ok((results, rows))
+}
+
+/// Runs `query`, a single statement that returns rows, as a prepared
+/// statement with `binds`.
+pub async fn fetch(
+    pool: &PgPool,
+    query: &str,
+    binds: &[Option<String>],
+) -> Result<ResultSet, sqlx::Error> {
+    // Prepared statements return binary values; have the server render each
+    // row as text instead, and split it back up here.
+    let mut results = describe(pool, query).await?;
+    let wrapped_query = format!(
+        "SELECT t::text FROM ({}) AS t",
+        query.trim_end().trim_end_matches(';')
+    );
+    let query = binds
+        .iter()
+        .fold(sqlx::query(&wrapped_query), |query, value| {
+            query.bind(value.clone())
+        });
+    for row in query.fetch_all(pool).await? {
+        let record: String = row.try_get(0)?;
+        results.rows.push(parse_record(&record));
+    }
+    Ok(results)
+}
+
+/// Splits a row value in text format, `(1,"a b",)`, into its fields.
+/// Unquoted empty fields are `NULL`.
+pub fn parse_record(text: &str) -> Vec<Cell> {
+    let inner = text
+        .strip_prefix('(')
+        .and_then(|text| text.strip_suffix(')'))
+        .unwrap_or(text);
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut was_quoted = false;
+    let mut chars = inner.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' => {
+                quoted = !quoted;
+                was_quoted = true;
+            }
+            '\\' if quoted => field.extend(chars.next()),
+            ',' if !quoted => {
+                let field = std::mem::take(&mut field);
+                fields.push((was_quoted || !field.is_empty()).then_some(field));
+                was_quoted = false;
+            }
+            c => field.push(c),
+        }
+    }
+    fields.push((was_quoted || !field.is_empty()).then_some(field));
+    fields
+}
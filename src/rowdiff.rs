@@ -0,0 +1,193 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `:rowdiff`: two rows of the results side by side, a line to a column,
+//! with the columns they differ in marked, for finding out why one order
+//! went through and the other did not.
+
+use ratatui::{
+    Frame,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::Paragraph,
+};
+
+use crate::grid::{self, Grid};
+use crate::redact::{self, Redactions};
+use crate::results::ResultSet;
+use crate::width;
+
+/// The rows `:rowdiff` compares: those given, counted from 1, or the two
+/// marked, or the two the selection spans.
+pub fn pick(grid: &Grid, rows: Option<(usize, usize)>) -> Result<(usize, usize), String> {
+    if let Some((a, b)) = rows {
+        return Ok((a.saturating_sub(1), b.saturating_sub(1)));
+    }
+    if let [a, b] = grid.marked.iter().copied().collect::<Vec<_>>()[..] {
+        return Ok((a, b));
+    }
+    match grid.selected() {
+        Some((rows, _)) if rows.clone().count() == 2 => Ok((*rows.start(), *rows.end())),
+        _ => Err("Mark two rows with m or select two with V to compare them".into()),
+    }
+}
+
+#[derive(Debug)]
+struct Field {
+    name: String,
+    /// As shown, masked if redacted.
+    values: [String; 2],
+    differs: bool,
+}
+
+#[derive(Debug)]
+pub struct RowDiff {
+    pub title: String,
+    fields: Vec<Field>,
+    /// Only the columns that differ, `d` switches.
+    pub only_differing: bool,
+    pub cursor: usize,
+    scroll: usize,
+}
+
+impl RowDiff {
+    /// Rows `a` and `b` of `results`, counted from 0.
+    pub fn new(
+        results: &ResultSet,
+        (a, b): (usize, usize),
+        redactions: &Redactions,
+    ) -> Result<Self, String> {
+        let rows = results.rows.len();
+        if a == b || a >= rows || b >= rows {
+            return Err(format!("Pick two different rows of the {rows}"));
+        }
+        let redacted = redactions.columns(results);
+        let shown = |row, col| match results.rows.cell(row, col) {
+            _ if redacted[col] => redact::MASK.to_string(),
+            Some(cell) => grid::display(cell),
+            None => "NULL".to_string(),
+        };
+        let fields: Vec<Field> = results
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(col, column)| Field {
+                name: column.name.clone(),
+                values: [shown(a, col), shown(b, col)],
+                differs: results.rows.cell(a, col) != results.rows.cell(b, col),
+            })
+            .collect();
+        let differing = fields.iter().filter(|field| field.differs).count();
+        Ok(Self {
+            title: format!(
+                "Row {} │ row {}: {differing} of {} columns differ (d for only those)",
+                a + 1,
+                b + 1,
+                fields.len()
+            ),
+            fields,
+            only_differing: false,
+            cursor: 0,
+            scroll: 0,
+        })
+    }
+
+    /// The columns shown, all of them or the differing ones.
+    fn shown(&self) -> Vec<&Field> {
+        self.fields
+            .iter()
+            .filter(|field| field.differs || !self.only_differing)
+            .collect()
+    }
+
+    /// How many columns differ.
+    pub fn differing(&self) -> usize {
+        self.fields.iter().filter(|field| field.differs).count()
+    }
+
+    pub fn toggle_only_differing(&mut self) {
+        self.only_differing = !self.only_differing;
+        self.cursor = 0;
+    }
+
+    pub fn move_by(&mut self, rows: isize) {
+        let last = self.shown().len().saturating_sub(1);
+        self.cursor = self.cursor.saturating_add_signed(rows).min(last);
+    }
+
+    pub fn top(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn bottom(&mut self) {
+        self.cursor = self.shown().len().saturating_sub(1);
+    }
+
+    /// A line a column, `*` and in yellow where the two differ.
+    pub fn render(&mut self, f: &mut Frame, area: Rect, focused: bool) {
+        let fields = self.shown();
+        let height = area.height as usize;
+        let cursor = self.cursor.min(fields.len().saturating_sub(1));
+        let mut scroll = self.scroll;
+        if cursor < scroll {
+            scroll = cursor;
+        } else if height > 0 && cursor >= scroll + height {
+            scroll = cursor + 1 - height;
+        }
+        let name_width = fields
+            .iter()
+            .map(|field| width::width(&field.name))
+            .max()
+            .unwrap_or(0)
+            .min(area.width as usize / 3);
+        let value_width = (area.width as usize).saturating_sub(name_width + 8) / 2;
+        let separator = Span::styled(" │ ", Style::default().fg(Color::DarkGray));
+        let lines: Vec<Line> = fields
+            .iter()
+            .enumerate()
+            .skip(scroll)
+            .take(height)
+            .map(|(row, field)| {
+                let style = if field.differs {
+                    Style::default()
+                        .fg(Color::Yellow)
+                        .add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default()
+                };
+                let fit =
+                    |text: &str, width| width::pad(&width::truncate(text, width), width, false);
+                let mut line = Line::from(vec![
+                    Span::raw(if field.differs { "* " } else { "  " }),
+                    Span::styled(
+                        fit(&field.name, name_width),
+                        Style::default().fg(Color::Cyan),
+                    ),
+                    separator.clone(),
+                    Span::styled(fit(&field.values[0], value_width), style),
+                    separator.clone(),
+                    Span::styled(field.values[1].clone(), style),
+                ]);
+                if focused && row == cursor {
+                    line = line.patch_style(Style::default().add_modifier(Modifier::REVERSED));
+                }
+                line
+            })
+            .collect();
+        f.render_widget(Paragraph::new(lines), area);
+        self.cursor = cursor;
+        self.scroll = scroll;
+    }
+}
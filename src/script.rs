@@ -0,0 +1,474 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `dbvi --script <file>`: ex commands run one after another without the
+//! TUI, for the likes of nightly extracts.
+//!
+//! ```text
+//! " nightly.dbvi
+//! connect warehouse
+//! setvar day '2025-06-01'
+//! edit ~/queries/orders.sql
+//! run
+//! export csv ~/extracts/orders.csv
+//! quit
+//! ```
+//!
+//! Lines starting with `"` or `#` are comments, and a leading `:` is
+//! optional. The first command that needs the database connects as the
+//! command line says, unless `connect` has named a profile or URL first;
+//! nothing is asked for, so passwords come from the URL, `.pgpass` or the
+//! keyring. `statement_timeout` from the config or profile applies.
+//!
+//! The script stops at the first command that fails, exiting with what went
+//! wrong so whatever runs it can tell:
+//!
+//! | Exit | Failure                                                   |
+//! |------|-----------------------------------------------------------|
+//! | 1    | a command, like an export that couldn't be written        |
+//! | 2    | the script, unreadable or with a command only for the TUI |
+//! | 3    | the connection, refused or lost                           |
+//! | 4    | a statement the server rejected                           |
+//! | 5    | a statement that ran past `statement_timeout`             |
+//!
+//! Nothing runs when the script itself is at fault. With `--format json`
+//! each line on stderr is a JSON object, `{"file", "line", "status"}` for
+//! a command that went well and `{"file", "line", "error", "message",
+//! "sqlstate", "exit"}` for the one that didn't, `error` naming the
+//! failure: `command`, `script`, `connection`, `sql` or `timeout`.
+
+use std::collections::BTreeMap;
+use std::process::ExitCode;
+
+use serde_json::json;
+use sqlx::Executor;
+
+use crate::app::{Args, open_connection, returns_rows, run_query};
+use crate::db::session::{Session, is_connection_error, is_query_canceled};
+use crate::db::tunnel::Tunnel;
+use crate::redact::Redactions;
+use crate::results::{self, ResultSet};
+use crate::state::Command;
+use crate::{commands, config, export, params, statements, vars};
+
+/// What kind of failure stopped a script, which is what it exits with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Failure {
+    Command,
+    Script,
+    Connection,
+    Sql,
+    Timeout,
+}
+
+impl Failure {
+    pub fn exit_code(self) -> u8 {
+        match self {
+            Self::Command => 1,
+            Self::Script => 2,
+            Self::Connection => 3,
+            Self::Sql => 4,
+            Self::Timeout => 5,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Command => "command",
+            Self::Script => "script",
+            Self::Connection => "connection",
+            Self::Sql => "sql",
+            Self::Timeout => "timeout",
+        }
+    }
+}
+
+/// Why a script stopped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    pub failure: Failure,
+    pub message: String,
+    /// The SQLSTATE of an error from the server.
+    pub sqlstate: Option<String>,
+}
+
+impl Error {
+    fn new(failure: Failure, message: impl Into<String>) -> Self {
+        Self {
+            failure,
+            message: message.into(),
+            sqlstate: None,
+        }
+    }
+
+    /// `err` from the server, told apart by what it says went wrong.
+    pub fn from_sqlx(context: &str, err: &sqlx::Error) -> Self {
+        let failure = if is_connection_error(err) {
+            Failure::Connection
+        } else if is_query_canceled(err) {
+            Failure::Timeout
+        } else if err.as_database_error().is_some() {
+            Failure::Sql
+        } else {
+            Failure::Command
+        };
+        Self {
+            failure,
+            message: format!("{context}: {err}"),
+            sqlstate: err
+                .as_database_error()
+                .and_then(|err| err.code())
+                .map(|code| code.into_owned()),
+        }
+    }
+}
+
+impl From<String> for Error {
+    fn from(message: String) -> Self {
+        Self::new(Failure::Command, message)
+    }
+}
+
+impl From<&str> for Error {
+    fn from(message: &str) -> Self {
+        Self::new(Failure::Command, message)
+    }
+}
+
+/// How `--script` reports on stderr.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Json,
+}
+
+impl Format {
+    /// The line saying how the command at `line` of `file` went, or, without
+    /// a line, how reading the script did.
+    pub fn report(self, file: &str, line: Option<usize>, outcome: Result<&str, &Error>) -> String {
+        match (self, outcome) {
+            (Self::Text, Ok(status)) => match line {
+                Some(line) => format!("{file}:{line}: {status}"),
+                None => format!("{file}: {status}"),
+            },
+            (Self::Text, Err(err)) => match line {
+                Some(line) => format!("{file}:{line}: {}", err.message),
+                None => format!("{file}: {}", err.message),
+            },
+            (Self::Json, Ok(status)) => {
+                json!({"file": file, "line": line, "status": status}).to_string()
+            }
+            (Self::Json, Err(err)) => json!({
+                "file": file,
+                "line": line,
+                "error": err.failure.name(),
+                "message": err.message,
+                "sqlstate": err.sqlstate,
+                "exit": err.failure.exit_code(),
+            })
+            .to_string(),
+        }
+    }
+}
+
+/// The commands of a script, each with its line number.
+pub fn parse(text: &str) -> Result<Vec<(usize, Command)>, (usize, String)> {
+    let mut commands = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        let line = line.strip_prefix(':').unwrap_or(line).trim_start();
+        if line.is_empty() || line.starts_with('"') || line.starts_with('#') {
+            continue;
+        }
+        let command = commands::parse(line).map_err(|err| (number + 1, err))?;
+        if !scriptable(&command) {
+            let name = line.split_whitespace().next().unwrap_or(line);
+            return Err((number + 1, format!(":{name} can't run in a script")));
+        }
+        commands.push((number + 1, command));
+    }
+    Ok(commands)
+}
+
+fn scriptable(command: &Command) -> bool {
+    matches!(
+        command,
+        Command::Connect(_)
+            | Command::Edit(_)
+            | Command::Run { .. }
+            | Command::SetVar { .. }
+            | Command::Export { .. }
+            | Command::CopyOut(_)
+            | Command::Redact(Some(_))
+            | Command::Unredact(_)
+            | Command::Quit { .. }
+    )
+}
+
+/// Runs the script at `path`, saying how each command went on stderr.
+pub async fn run(args: &Args, path: &str) -> ExitCode {
+    let format = match args.format.as_str() {
+        "json" => Format::Json,
+        _ => Format::Text,
+    };
+    let stop = |line, err: Error| {
+        eprintln!("{}", format.report(path, line, Err(&err)));
+        ExitCode::from(err.failure.exit_code())
+    };
+    let script = match std::fs::read_to_string(config::expand_home(path)) {
+        Ok(text) => parse(&text),
+        Err(err) => return stop(None, Error::new(Failure::Script, err.to_string())),
+    };
+    let script = match script {
+        Ok(script) => script,
+        Err((line, err)) => return stop(Some(line), Error::new(Failure::Script, err)),
+    };
+    let mut runner = Runner::new(args.clone());
+    for (line, command) in script {
+        if matches!(command, Command::Quit { .. }) {
+            break;
+        }
+        match runner.command(command).await {
+            Ok(status) => eprintln!("{}", format.report(path, Some(line), Ok(&status))),
+            Err(err) => return stop(Some(line), err),
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+/// What a script has to go on between commands: the connection, the query
+/// `edit` loaded and the results of the last `run`.
+struct Runner {
+    args: Args,
+    session: Option<Session>,
+    // Kept open while the session is.
+    _tunnel: Option<Tunnel>,
+    query: Option<String>,
+    results: Option<ResultSet>,
+    vars: BTreeMap<String, String>,
+    redactions: Redactions,
+}
+
+impl Runner {
+    fn new(args: Args) -> Self {
+        Self {
+            args,
+            session: None,
+            _tunnel: None,
+            query: None,
+            results: None,
+            vars: BTreeMap::new(),
+            redactions: Redactions::default(),
+        }
+    }
+
+    async fn command(&mut self, command: Command) -> Result<String, Error> {
+        match command {
+            Command::Connect(target) => {
+                let known = config::Config::load()
+                    .map_err(|err| Error::new(Failure::Connection, err.to_string()))?
+                    .profiles
+                    .contains_key(&target);
+                self.args.url = None;
+                self.args.service = None;
+                if known {
+                    self.args.conninfo = None;
+                    self.args.profile = Some(target);
+                } else {
+                    self.args.conninfo = Some(target);
+                    self.args.profile = None;
+                }
+                self.session = None;
+                self.session().await?;
+                Ok("Connected".into())
+            }
+            Command::Edit(path) => {
+                let file = config::expand_home(&path);
+                let text = std::fs::read_to_string(&file)
+                    .map_err(|err| format!("Failed to open \"{}\": {err}", file.display()))?;
+                let lines = text.lines().count();
+                self.query = Some(text);
+                Ok(format!("\"{}\" {lines} lines", file.display()))
+            }
+            Command::SetVar {
+                name,
+                value: Some(value),
+            } => {
+                let status = format!("{name} = {value}");
+                self.vars.insert(name, value);
+                Ok(status)
+            }
+            Command::SetVar { name, value: None } => match self.vars.remove(&name) {
+                Some(_) => Ok(format!("Unset {name}")),
+                None => Err(format!("No variable {name}").into()),
+            },
+            Command::Redact(Some(pattern)) => {
+                self.redactions.add(&pattern);
+                Ok(format!("Redacted {pattern}"))
+            }
+            Command::Unredact(pattern) if self.redactions.remove(&pattern) => {
+                Ok(format!("Unredacted {pattern}"))
+            }
+            Command::Unredact(pattern) => Err(format!("{pattern} isn't redacted").into()),
+            Command::Run { expect } => self.run(expect).await,
+            Command::Export { exporter, path } => self.export(&exporter, &path),
+            Command::CopyOut(path) => {
+                let query = self.query()?;
+                let session = self.session().await?;
+                let file = config::expand_home(&path);
+                let copied = async {
+                    let query = self.redactions.masked_query(&session.pool, &query).await?;
+                    export::copy_csv(&session.pool, &query, &file, |_| {}).await
+                };
+                match copied.await {
+                    Ok(bytes) => Ok(format!("Wrote {bytes} bytes to \"{path}\"")),
+                    Err(err) => Err(Error::from_sqlx(
+                        &format!("Failed to copy to \"{path}\""),
+                        &err,
+                    )),
+                }
+            }
+            _ => Err(Error::new(Failure::Script, "Can't run in a script")),
+        }
+    }
+
+    /// The session, connecting as the command line or the last `connect`
+    /// says if there is none yet.
+    async fn session(&mut self) -> Result<Session, Error> {
+        if let Some(session) = &self.session {
+            return Ok(session.clone());
+        }
+        let (session, tunnel, _, mut config) = open_connection(None, &self.args, None)
+            .await
+            .map_err(|err| Error::new(Failure::Connection, format!("Failed to connect: {err}")))?;
+        let profile = self
+            .args
+            .profile
+            .as_ref()
+            .and_then(|name| config.profiles.remove(name))
+            .unwrap_or_default();
+        let mut settings = Vec::new();
+        if profile.read_only() {
+            settings.push("SET default_transaction_read_only = on".to_string());
+        }
+        let timeout = match profile.options.get("statement_timeout") {
+            Some(toml::Value::String(timeout)) => Some(timeout.clone()),
+            Some(timeout) => Some(timeout.to_string()),
+            None => config.statement_timeout.clone(),
+        };
+        if let Some(timeout) = timeout {
+            let timeout = config::parse_duration(&timeout)?;
+            settings.push(format!("SET statement_timeout = {}", timeout.as_millis()));
+        }
+        for sql in settings {
+            session
+                .pool
+                .execute(sql.as_str())
+                .await
+                .map_err(|err| Error::from_sqlx("Failed to set up the session", &err))?;
+            session.record(&sql);
+        }
+        self.redactions = Redactions::new(profile.redact);
+        self._tunnel = tunnel;
+        self.session = Some(session.clone());
+        Ok(session)
+    }
+
+    /// The query `edit` loaded, with the variables substituted.
+    fn query(&self) -> Result<String, Error> {
+        let query = self
+            .query
+            .as_deref()
+            .ok_or("No query, :edit <file> loads one")?;
+        let query = vars::substitute(query, &self.vars);
+        if let Some(found) = params::find(&query) {
+            return Err(format!(
+                "The query takes {}, :setvar them first",
+                found.labels.join(", ")
+            )
+            .into());
+        }
+        Ok(query)
+    }
+
+    /// `run [expect=N]`, where rows changed other than `N` roll back.
+    async fn run(&mut self, expect: Option<u64>) -> Result<String, Error> {
+        let query = self.query()?;
+        let session = self.session().await?;
+        let pool = &session.pool;
+        if expect.is_some() {
+            pool.execute("BEGIN")
+                .await
+                .map_err(|err| Error::from_sqlx("Failed to begin", &err))?;
+        }
+        let outcome = run_query(pool, &query, &[]).await;
+        let end = match (&outcome, expect) {
+            (_, None) => None,
+            (Ok((_, _, rows)), Some(expect)) if *rows == expect => Some("COMMIT"),
+            _ => Some("ROLLBACK"),
+        };
+        if let Some(end) = end {
+            pool.execute(end).await.map_err(|err| {
+                Error::from_sqlx(&format!("Failed to {}", end.to_lowercase()), &err)
+            })?;
+        }
+        let (mut results, mut status, rows) =
+            outcome.map_err(|err| Error::from_sqlx("Failed to run query", &err))?;
+        if let Some(expect) = expect
+            && rows != expect
+        {
+            return Err(format!("Expected {expect} rows changed, not {rows}, rolled back").into());
+        }
+        session.record(&query);
+        self.results = match results.pop() {
+            Some((_, results)) => Some(results),
+            // Nothing came back, but an export still gets the header.
+            None => match statements::split(&query).last() {
+                Some(last) if returns_rows(last) => {
+                    status = "Query executed successfully, 0 rows".into();
+                    results::describe(pool, last).await.ok()
+                }
+                _ => None,
+            },
+        };
+        Ok(status)
+    }
+
+    /// `export csv|table|xlsx|html <file>` of the last results.
+    fn export(&self, exporter: &str, path: &str) -> Result<String, Error> {
+        let results = self.results.as_ref().ok_or("No results")?;
+        let results = self.redactions.apply(results);
+        let file = config::expand_home(path);
+        let mut output = Vec::new();
+        match exporter {
+            "csv" => export::write_csv(&results, &mut output),
+            "xlsx" => export::write_xlsx(&results, &mut output),
+            "html" => export::write_html(&results, true, &mut output),
+            "table" => export::write_table(&results, &mut output),
+            _ => {
+                return Err(format!(
+                    "No exporter {exporter}, scripts have csv, table, xlsx and html"
+                )
+                .into());
+            }
+        }
+        .and_then(|()| std::fs::write(&file, &output))
+        .map_err(|err| format!("Failed to write {}: {err}", file.display()))?;
+        Ok(format!(
+            "Exported {} rows to {}",
+            results.rows.len(),
+            file.display()
+        ))
+    }
+}
@@ -0,0 +1,56 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Shell commands, for `:!` and `:pipe`.
+
+use std::process::{ExitStatus, Stdio};
+
+use tokio::io::AsyncWriteExt;
+
+/// The shell commands run in: `$SHELL`, or `sh` without one.
+pub fn program() -> String {
+    std::env::var("SHELL")
+        .ok()
+        .filter(|shell| !shell.is_empty())
+        .unwrap_or_else(|| "sh".into())
+}
+
+/// Runs `command` with `input` on its stdin, returning its stdout followed by
+/// its stderr, and how it exited.
+pub async fn pipe(command: &str, input: Vec<u8>) -> Result<(String, ExitStatus), String> {
+    let mut child = tokio::process::Command::new(program())
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|err| format!("Failed to run {command}: {err}"))?;
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    // Feed it while reading, or a command that prints as it reads would fill
+    // its stdout and never get to the end of its input.
+    let writer = tokio::spawn(async move {
+        // Commands like `head` stop reading early, which is fine.
+        let _ = stdin.write_all(&input).await;
+    });
+    let output = child
+        .wait_with_output()
+        .await
+        .map_err(|err| format!("Failed to run {command}: {err}"))?;
+    let _ = writer.await;
+    let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+    text.push_str(&String::from_utf8_lossy(&output.stderr));
+    Ok((text, output.status))
+}
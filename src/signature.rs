@@ -0,0 +1,229 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Signature help: while typing the arguments of a function call in insert
+//! mode, a box over the cursor lists the overloads of the function with the
+//! argument being typed picked out. Overloads are looked up once per name.
+
+use std::collections::HashMap;
+
+use ratatui::{
+    Frame,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+};
+
+use crate::db::catalog::Function;
+use crate::statements::{is_ident, skip_quoted};
+use crate::width;
+
+/// Overloads shown at most.
+const MAX_OVERLOADS: usize = 6;
+
+/// Words a `(` follows that don't make a function call.
+const NOT_CALLS: [&str; 18] = [
+    "in", "values", "exists", "as", "on", "using", "from", "join", "into", "over", "filter",
+    "table", "select", "where", "and", "or", "not", "with",
+];
+
+/// The call the cursor is in and the overloads of its function, by name.
+#[derive(Debug, Default)]
+pub struct Signatures {
+    /// The function and the argument (from 0) the cursor is in, if any.
+    pub call: Option<(String, usize)>,
+    /// Overloads by the name as written, empty while they are looked up or
+    /// when there is no such function.
+    cache: HashMap<String, Vec<Function>>,
+}
+
+impl Signatures {
+    /// Follows the cursor at byte `at` of `text`. Returns the name to look
+    /// up if it is new.
+    pub fn update(&mut self, text: &str, at: usize) -> Option<String> {
+        self.call = call_at(text, at);
+        let (name, _) = self.call.as_ref()?;
+        if self.cache.contains_key(name) {
+            return None;
+        }
+        self.cache.insert(name.clone(), Vec::new());
+        Some(name.clone())
+    }
+
+    pub fn found(&mut self, name: String, functions: Vec<Function>) {
+        self.cache.insert(name, functions);
+    }
+
+    /// Forgets the overloads looked up, as after connecting elsewhere.
+    pub fn clear(&mut self) {
+        self.cache.clear();
+    }
+
+    /// The overloads of the call the cursor is in and the argument it is on.
+    pub fn current(&self) -> Option<(&[Function], usize)> {
+        let (name, argument) = self.call.as_ref()?;
+        let functions = self.cache.get(name).filter(|f| !f.is_empty())?;
+        Some((functions, *argument))
+    }
+
+    /// Draws the box just above the cursor at `x`, `y` (below it if there is
+    /// no room), within `bounds`.
+    pub fn render(&self, f: &mut Frame, bounds: Rect, x: u16, y: u16) {
+        let Some((functions, argument)) = self.current() else {
+            return;
+        };
+        let lines: Vec<Line> = functions
+            .iter()
+            .take(MAX_OVERLOADS)
+            .map(|function| signature_line(function, argument))
+            .collect();
+        let width = lines.iter().map(Line::width).max().unwrap_or(0) as u16 + 2;
+        let width = width.min(bounds.width);
+        let height = (lines.len() as u16 + 2).min(bounds.height);
+        let y = if y >= bounds.y + height {
+            y - height
+        } else {
+            (y + 1).min(bounds.bottom().saturating_sub(height))
+        };
+        let x = x.min(bounds.right().saturating_sub(width));
+        let area = Rect::new(x, y, width, height);
+        let title = match functions.len() {
+            n if n > MAX_OVERLOADS => format!("{MAX_OVERLOADS} of {n}"),
+            _ => String::new(),
+        };
+        f.render_widget(Clear, area);
+        f.render_widget(
+            Paragraph::new(lines).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(Color::DarkGray))
+                    .title(title),
+            ),
+            area,
+        );
+    }
+}
+
+/// `name(a integer, b text) → result`, with argument `argument` in bold.
+fn signature_line(function: &Function, argument: usize) -> Line<'static> {
+    let arguments = split_arguments(&function.arguments);
+    // Everything past the last argument goes to it if it is variadic.
+    let current = match arguments.last() {
+        Some(last) if last.starts_with("VARIADIC ") => argument.min(arguments.len() - 1),
+        _ => argument,
+    };
+    let mut spans = vec![Span::raw(format!("{}(", function.name))];
+    for (i, text) in arguments.iter().enumerate() {
+        if i > 0 {
+            spans.push(Span::raw(", "));
+        }
+        let style = if i == current {
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+        };
+        spans.push(Span::styled(text.to_string(), style));
+    }
+    spans.push(Span::raw(")"));
+    if let Some(result) = &function.result {
+        spans.push(Span::styled(
+            format!(" → {}", width::truncate(result, 40)),
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+    Line::from(spans)
+}
+
+/// The arguments of `pg_get_function_arguments()`, split on the commas
+/// outside parentheses and literals of their defaults.
+pub fn split_arguments(arguments: &str) -> Vec<&str> {
+    let bytes = arguments.as_bytes();
+    let mut parts = Vec::new();
+    let (mut start, mut depth, mut i) = (0, 0usize, 0);
+    while i < bytes.len() {
+        if let Some(end) = skip_quoted(arguments, i) {
+            i = end;
+            continue;
+        }
+        match bytes[i] {
+            b'(' => depth += 1,
+            b')' => depth = depth.saturating_sub(1),
+            b',' if depth == 0 => {
+                parts.push(arguments[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    let last = arguments[start..].trim();
+    if !last.is_empty() {
+        parts.push(last);
+    }
+    parts
+}
+
+/// The function whose call byte `at` of `text` is inside, the innermost if
+/// calls nest, and how many arguments come before the cursor in it.
+pub fn call_at(text: &str, at: usize) -> Option<(String, usize)> {
+    let bytes = text.as_bytes();
+    let at = at.min(bytes.len());
+    // The open parens before the cursor: the name before each, if it is a
+    // call, and the commas after it so far.
+    let mut open: Vec<(Option<&str>, usize)> = Vec::new();
+    let mut i = 0;
+    while i < at {
+        if let Some(end) = skip_quoted(text, i) {
+            // The cursor is in a literal or comment, or one left open.
+            if end > at || end == bytes.len() {
+                return None;
+            }
+            i = end;
+            continue;
+        }
+        match bytes[i] {
+            b'(' => open.push((name_before(text, i), 0)),
+            b')' => {
+                open.pop();
+            }
+            b',' => {
+                if let Some((_, commas)) = open.last_mut() {
+                    *commas += 1;
+                }
+            }
+            b';' => open.clear(),
+            _ => {}
+        }
+        i += 1;
+    }
+    let (name, commas) = open.pop()?;
+    Some((name?.to_string(), commas))
+}
+
+/// The function name just before the `(` at byte `paren`, qualified or not,
+/// unless it is a keyword.
+fn name_before(text: &str, paren: usize) -> Option<&str> {
+    let bytes = text.as_bytes();
+    let end = text[..paren].trim_end().len();
+    let start = (0..end)
+        .rev()
+        .find(|&i| !(is_ident(bytes[i]) || matches!(bytes[i], b'.' | b'"')))
+        .map_or(0, |i| i + 1);
+    let name = &text[start..end];
+    let plain = name.starts_with(|c: char| c == '"' || c.is_alphabetic() || c == '_');
+    (plain && !NOT_CALLS.contains(&name.to_lowercase().as_str())).then_some(name)
+}
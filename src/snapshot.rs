@@ -0,0 +1,213 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Result sets kept on disk with `:result save <name>`, to browse or export
+//! again later without running the query again.
+//!
+//! The format is compact rather than readable: a magic number and version,
+//! the query, the columns, then every row, with each string as its length
+//! in LEB128 followed by its UTF-8 bytes and each cell a byte telling
+//! `NULL` from a value.
+
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::config;
+use crate::results::{Column, ResultSet};
+
+const MAGIC: &[u8; 4] = b"DBVR";
+const VERSION: u8 = 1;
+const EXTENSION: &str = "dbvr";
+
+/// Where saved result sets live.
+pub fn results_dir() -> Option<PathBuf> {
+    config::state_dir().map(|dir| dir.join("results"))
+}
+
+/// A saved result set and the query it came from.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub query: Option<String>,
+    pub results: ResultSet,
+}
+
+/// A name is a single file name, nothing that leaves the directory.
+fn check_name(name: &str) -> Result<(), String> {
+    let ok = !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.'));
+    if !ok {
+        return Err(format!("Invalid result name: {name}"));
+    }
+    Ok(())
+}
+
+fn path(dir: &Path, name: &str) -> Result<PathBuf, String> {
+    check_name(name)?;
+    Ok(dir.join(format!("{name}.{EXTENSION}")))
+}
+
+fn write_len(out: &mut impl Write, mut len: usize) -> io::Result<()> {
+    loop {
+        let byte = (len & 0x7f) as u8;
+        len >>= 7;
+        if len == 0 {
+            return out.write_all(&[byte]);
+        }
+        out.write_all(&[byte | 0x80])?;
+    }
+}
+
+fn write_str(out: &mut impl Write, text: &str) -> io::Result<()> {
+    write_len(out, text.len())?;
+    out.write_all(text.as_bytes())
+}
+
+fn read_byte(input: &mut impl Read) -> io::Result<u8> {
+    let mut byte = [0];
+    input.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+fn read_len(input: &mut impl Read) -> io::Result<usize> {
+    let mut len = 0usize;
+    for shift in (0..usize::BITS).step_by(7) {
+        let byte = read_byte(input)?;
+        len |= usize::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(len);
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "length too long",
+    ))
+}
+
+fn read_str(input: &mut impl Read) -> io::Result<String> {
+    let len = read_len(input)?;
+    let mut bytes = Vec::new();
+    input.take(len as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    String::from_utf8(bytes).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+impl Snapshot {
+    pub fn write(&self, out: &mut impl Write) -> io::Result<()> {
+        out.write_all(MAGIC)?;
+        out.write_all(&[VERSION])?;
+        write_str(out, self.query.as_deref().unwrap_or_default())?;
+        write_len(out, self.results.columns.len())?;
+        for column in &self.results.columns {
+            write_str(out, &column.name)?;
+            write_str(out, &column.ty)?;
+        }
+        write_len(out, self.results.rows.len())?;
+        for row in &self.results.rows {
+            for cell in row {
+                match cell {
+                    Some(value) => {
+                        out.write_all(&[1])?;
+                        write_str(out, value)?;
+                    }
+                    None => out.write_all(&[0])?,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn read(input: &mut impl Read) -> io::Result<Self> {
+        let invalid = |what: &str| io::Error::new(io::ErrorKind::InvalidData, what.to_string());
+        let mut magic = [0; 4];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("not a saved result set"));
+        }
+        let version = read_byte(input)?;
+        if version != VERSION {
+            return Err(invalid(&format!("unknown version {version}")));
+        }
+        let query = Some(read_str(input)?).filter(|query| !query.is_empty());
+        let columns = (0..read_len(input)?)
+            .map(|_| Ok(Column::new(read_str(input)?, read_str(input)?)))
+            .collect::<io::Result<Vec<_>>>()?;
+        let mut results = ResultSet::new(columns);
+        for _ in 0..read_len(input)? {
+            let row = (0..results.columns.len())
+                .map(|_| match read_byte(input)? {
+                    0 => Ok(None),
+                    1 => Ok(Some(read_str(input)?)),
+                    _ => Err(invalid("bad cell")),
+                })
+                .collect::<io::Result<Vec<_>>>()?;
+            results.rows.push(row);
+        }
+        Ok(Self { query, results })
+    }
+
+    /// Saves the snapshot as `name` in `dir`, refusing to replace one
+    /// unless `force`.
+    pub fn save(&self, dir: &Path, name: &str, force: bool) -> Result<PathBuf, String> {
+        let path = path(dir, name)?;
+        if path.exists() && !force {
+            return Err(format!("Result {name} exists (add ! to replace it)"));
+        }
+        let fail = |err: io::Error| format!("Failed to save {}: {err}", path.display());
+        fs::create_dir_all(dir).map_err(fail)?;
+        let mut out = io::BufWriter::new(fs::File::create(&path).map_err(fail)?);
+        self.write(&mut out)
+            .and_then(|()| out.flush())
+            .map_err(fail)?;
+        Ok(path)
+    }
+
+    pub fn load(dir: &Path, name: &str) -> Result<Self, String> {
+        let path = path(dir, name)?;
+        let file = match fs::File::open(&path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                return Err(format!("No saved result {name}"));
+            }
+            Err(err) => return Err(format!("Failed to read {}: {err}", path.display())),
+        };
+        Self::read(&mut io::BufReader::new(file))
+            .map_err(|err| format!("Failed to read {}: {err}", path.display()))
+    }
+}
+
+/// The names of the saved result sets in `dir`, sorted.
+pub fn list(dir: &Path) -> io::Result<Vec<String>> {
+    let mut names = Vec::new();
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(names),
+        Err(err) => return Err(err),
+    };
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == EXTENSION)
+            && let Some(name) = path.file_stem()
+        {
+            names.push(name.to_string_lossy().into_owned());
+        }
+    }
+    names.sort();
+    Ok(names)
+}
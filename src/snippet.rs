@@ -0,0 +1,165 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Snippets from the config file, expanded with `<Tab>` in insert mode.
+//!
+//! Templates may contain tab stops `$1`, `$2`, ... (optionally with default
+//! text, `${1:default}`) and a final stop `$0`. `<Tab>` jumps from one stop
+//! to the next.
+
+use crate::editor::{Buffer, Cursor, byte_index};
+
+/// A template with its tab stops resolved.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Expansion {
+    pub text: String,
+    /// Char offsets into `text` and the length of the default text there, in
+    /// the order they are visited.
+    pub stops: Vec<(usize, usize)>,
+}
+
+/// Strips the tab stop markers out of `template`, remembering where they
+/// were. A `$` not followed by a digit or `{` is kept as is.
+pub fn expand(template: &str) -> Expansion {
+    let mut text = String::new();
+    let mut len = 0;
+    let mut stops: Vec<(usize, usize, usize)> = Vec::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '$' {
+            if chars.peek().is_some_and(char::is_ascii_digit) {
+                let number: String =
+                    std::iter::from_fn(|| chars.next_if(char::is_ascii_digit)).collect();
+                stops.push((number.parse().unwrap_or(0), len, 0));
+                continue;
+            }
+            if chars.peek() == Some(&'{') {
+                let rest: String = chars.clone().skip(1).take_while(|c| *c != '}').collect();
+                if let Some((number, default)) = rest.split_once(':')
+                    && let Ok(number) = number.parse()
+                {
+                    chars.nth(rest.chars().count() + 1);
+                    let default_len = default.chars().count();
+                    stops.push((number, len, default_len));
+                    text.push_str(default);
+                    len += default_len;
+                    continue;
+                }
+            }
+        }
+        text.push(c);
+        len += 1;
+    }
+
+    // `$0` is the last stop; without one the cursor ends after the text.
+    stops.sort_by_key(|(number, ..)| if *number == 0 { usize::MAX } else { *number });
+    if !stops.iter().any(|(number, ..)| *number == 0) {
+        stops.push((0, len, 0));
+    }
+    Expansion {
+        text,
+        stops: stops
+            .into_iter()
+            .map(|(_, offset, default)| (offset, default))
+            .collect(),
+    }
+}
+
+/// Where `offset` chars into `text` ends up when `text` is inserted at
+/// `start`.
+fn advance(start: Cursor, text: &str, offset: usize) -> Cursor {
+    let mut cursor = start;
+    for c in text.chars().take(offset) {
+        if c == '\n' {
+            cursor.row += 1;
+            cursor.col = 0;
+        } else {
+            cursor.col += 1;
+        }
+    }
+    cursor
+}
+
+/// The remaining tab stops of the snippet being filled in.
+#[derive(Debug, Clone, Default)]
+pub struct ActiveSnippet {
+    /// Each stop with the length of its default text.
+    stops: Vec<(Cursor, usize)>,
+    /// The stop the cursor was last sent to and the length of its line at
+    /// the time, to shift later stops on that line by what was typed.
+    current: Option<(Cursor, usize)>,
+    /// Length of the default text at the current stop, replaced by the
+    /// first char typed there.
+    placeholder: usize,
+}
+
+impl ActiveSnippet {
+    /// Inserts `expansion` at the cursor and moves to its first stop. Returns
+    /// `None` if that was the only stop.
+    pub fn insert(buffer: &mut Buffer, expansion: &Expansion) -> Option<Self> {
+        let start = buffer.cursor;
+        buffer.insert_str(&expansion.text);
+        let mut snippet = Self {
+            stops: expansion
+                .stops
+                .iter()
+                .map(|(offset, default)| (advance(start, &expansion.text, *offset), *default))
+                .collect(),
+            current: None,
+            placeholder: 0,
+        };
+        snippet.next(buffer).then_some(snippet)
+    }
+
+    /// Moves to the next stop. Returns false once there are none left.
+    pub fn next(&mut self, buffer: &mut Buffer) -> bool {
+        if let Some((stop, len)) = self.current.take() {
+            let typed = buffer.lines[stop.row].chars().count() as isize - len as isize;
+            for later in self
+                .stops
+                .iter_mut()
+                .map(|(later, _)| later)
+                .filter(|later| later.row == stop.row && later.col >= stop.col)
+            {
+                later.col = later.col.saturating_add_signed(typed);
+            }
+        }
+        if self.stops.is_empty() {
+            return false;
+        }
+        let (stop, placeholder) = self.stops.remove(0);
+        buffer.cursor = stop;
+        buffer.clamp_cursor(true);
+        self.current = Some((stop, buffer.lines[stop.row].chars().count()));
+        self.placeholder = placeholder;
+        !self.stops.is_empty()
+    }
+
+    /// Called before a char is typed: deletes the default text of the
+    /// current stop if the cursor is still at its start.
+    pub fn overwrite(&mut self, buffer: &mut Buffer) {
+        let placeholder = std::mem::take(&mut self.placeholder);
+        let Some((stop, _)) = self.current else {
+            return;
+        };
+        if placeholder == 0 || buffer.cursor != stop {
+            return;
+        }
+        let line = &mut buffer.lines[stop.row];
+        let start = byte_index(line, stop.col);
+        let end = byte_index(line, stop.col + placeholder);
+        line.replace_range(start..end, "");
+        buffer.modified = true;
+    }
+}
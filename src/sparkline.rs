@@ -0,0 +1,158 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Sparklines of numeric columns under their names in the grid, switched
+//! on a column at a time with `zs`: the shape of the loaded rows in block
+//! characters, whether they go up or down, and their range, to spot a
+//! spike in a monitoring query without scrolling through it.
+
+use crate::results::ResultSet;
+use crate::values;
+
+/// Narrower than this, a column with a sparkline is widened.
+pub const MIN_WIDTH: usize = 16;
+
+const BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trend {
+    Up,
+    Down,
+    Flat,
+}
+
+impl Trend {
+    fn arrow(self) -> char {
+        match self {
+            Trend::Up => '↗',
+            Trend::Down => '↘',
+            Trend::Flat => '→',
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Trend::Up => "trending up",
+            Trend::Down => "trending down",
+            Trend::Flat => "flat",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sparkline {
+    /// The numbers of the column in the order of the rows, `NULL`s and
+    /// anything else left out.
+    numbers: Vec<f64>,
+    pub min: f64,
+    pub max: f64,
+    pub trend: Trend,
+}
+
+impl Sparkline {
+    /// The sparkline of column `col`, `None` if it has no numbers.
+    pub fn of(results: &ResultSet, col: usize) -> Option<Self> {
+        let column = results.columns.get(col)?;
+        let numbers: Vec<f64> = results
+            .rows
+            .iter()
+            .filter_map(|row| values::number(column, row.get(col)?))
+            .filter(|n| n.is_finite())
+            .collect();
+        let min = numbers.iter().copied().reduce(f64::min)?;
+        let max = numbers.iter().copied().reduce(f64::max)?;
+        Some(Self {
+            trend: trend(&numbers, max - min),
+            numbers,
+            min,
+            max,
+        })
+    }
+
+    /// The sparkline in `width` characters: the blocks, the trend and,
+    /// room allowing, the range, `▁▂█▃ ↗ 3.7..99`.
+    pub fn render(&self, width: usize) -> String {
+        let range = format!(" {}..{}", compact(self.min), compact(self.max));
+        let blocks = match width.saturating_sub(2) {
+            room if room >= range.chars().count() + 4 => room - range.chars().count(),
+            room => room,
+        };
+        let mut line = self.blocks(blocks);
+        line.push(' ');
+        line.push(self.trend.arrow());
+        if blocks + 2 + range.chars().count() <= width {
+            line.push_str(&range);
+        }
+        line
+    }
+
+    /// The numbers in `width` blocks, each as high as the biggest number
+    /// it covers so that spikes show.
+    fn blocks(&self, width: usize) -> String {
+        let count = self.numbers.len();
+        let width = width.min(count);
+        let span = self.max - self.min;
+        (0..width)
+            .map(|i| {
+                let bucket = &self.numbers[i * count / width..(i + 1) * count / width];
+                let high = bucket.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+                let level = if span == 0.0 {
+                    0
+                } else {
+                    ((high - self.min) / span * 7.0).round() as usize
+                };
+                BLOCKS[level.min(7)]
+            })
+            .collect()
+    }
+
+    /// `min 3.7, max 99, trending up`, for the status line.
+    pub fn summary(&self) -> String {
+        format!(
+            "min {}, max {}, {}",
+            compact(self.min),
+            compact(self.max),
+            self.trend.name()
+        )
+    }
+}
+
+/// Up or down if the last third of the numbers is on average more than a
+/// twentieth of the range off the first third.
+fn trend(numbers: &[f64], span: f64) -> Trend {
+    let third = numbers.len() / 3;
+    if third == 0 || span == 0.0 {
+        return Trend::Flat;
+    }
+    let mean = |numbers: &[f64]| numbers.iter().sum::<f64>() / numbers.len() as f64;
+    let change = mean(&numbers[numbers.len() - third..]) - mean(&numbers[..third]);
+    match change / span {
+        change if change > 0.05 => Trend::Up,
+        change if change < -0.05 => Trend::Down,
+        _ => Trend::Flat,
+    }
+}
+
+/// A number in a few characters: `3.7`, `12.5k` or `1.2M`.
+fn compact(n: f64) -> String {
+    let (n, suffix) = match n.abs() {
+        a if a >= 1e9 => (n / 1e9, "G"),
+        a if a >= 1e6 => (n / 1e6, "M"),
+        a if a >= 1e4 => (n / 1e3, "k"),
+        _ => (n, ""),
+    };
+    let text = format!("{n:.2}");
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    format!("{text}{suffix}")
+}
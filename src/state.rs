@@ -0,0 +1,1092 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! What the UI is showing and the commands that change it.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::process::ExitStatus;
+use std::time::{Duration, Instant};
+
+use crossterm::event::KeyEvent;
+use ratatui::layout::Rect;
+
+use crate::action::Bus;
+use crate::browse::{Browser, PageTo};
+use crate::clipboard::Clipboard;
+use crate::compare::Compare;
+use crate::config::Environment;
+use crate::dashboard::{Activity, Dashboard, ServerStats};
+use crate::db::asof::{AsOf, Snapshot};
+use crate::db::capabilities::Capabilities;
+use crate::db::monitor::Report;
+use crate::db::schema::{Changes, Schema};
+use crate::db::session::{Session, Transaction};
+use crate::editor::{Buffer, Cursor, Register};
+use crate::edits::Edits;
+use crate::elements::Elements;
+use crate::erd::Erd;
+use crate::grid::Grid;
+use crate::jobs::{self, Jobs};
+use crate::keymap::{Keymap, Lookup, Mapping, Resolver};
+use crate::library::Library;
+use crate::popup::{Popup, Toast};
+use crate::queue::Queue;
+use crate::quickfix::{Entry, Go, Quickfix};
+use crate::record::Recorder;
+use crate::redact::Redactions;
+use crate::results::ResultSet;
+use crate::rowdiff::RowDiff;
+use crate::signature::Signatures;
+use crate::snippet::ActiveSnippet;
+use crate::statusline::StatusLine;
+use crate::tabs::Tabs;
+use crate::tutor::Tutor;
+use crate::{
+    audit, chart, completion, db, dialect, dump, generate, grid, hover, join, lsp, migrate, pivot,
+    plan, results, substitute, swap, transfer,
+};
+
+#[derive(Debug)]
+pub struct State {
+    pub(crate) is_running: bool,
+    pub(crate) mode: Mode,
+    pub(crate) status: String,
+    pub(crate) buffers: Vec<Buffer>,
+    /// Index into `buffers` of the one shown in the editor.
+    pub(crate) current: usize,
+    pub(crate) register: Register,
+    /// Where yanks are copied to, besides `register`.
+    pub(crate) clipboard: Clipboard,
+    /// First key of a two key normal mode command like `dd` or `gg`.
+    pub(crate) pending: Option<char>,
+    /// Operator and `i` or `a` of a text object being typed, the `d` and
+    /// `i` of `di(`.
+    pub(crate) text_object: Option<(char, char)>,
+    /// What each key typed in `Mode::Replace` went over, `None` past the
+    /// end of the line, for `<BS>` to put back.
+    pub(crate) replaced: Vec<Option<char>>,
+    /// Where the cursor was and what was highlighted before `/`, to go back
+    /// to should it be given up.
+    pub(crate) search_origin: Option<(Cursor, Option<regex::Regex>)>,
+    /// Pattern of the last `:s`, `/` or `*`, highlighted in the editor until `:noh`.
+    pub(crate) search: Option<regex::Regex>,
+    /// Close brackets and quotes as they are typed.
+    pub(crate) auto_pairs: bool,
+    /// Keywords to highlight and how to explain, for the server or as the
+    /// profile says.
+    pub(crate) dialect: dialect::Dialect,
+    /// Snippet templates by trigger word.
+    pub(crate) snippets: HashMap<String, String>,
+    /// The snippet whose tab stops `<Tab>` is moving through.
+    pub(crate) snippet: Option<ActiveSnippet>,
+    /// The function call being typed and the overloads of its function.
+    pub(crate) signatures: Signatures,
+    /// The completions `<C-n>` offers.
+    pub(crate) completion: Option<completion::Menu>,
+    /// The language server of the config, if there is one and it runs.
+    pub(crate) lsp: Option<lsp::Client>,
+    /// Key mappings of the config.
+    pub(crate) keymap: Keymap,
+    /// Keys typed so far of what may be a mapping.
+    pub(crate) resolver: Resolver,
+    /// How long the resolver waits for the rest of a mapping, `None` for
+    /// as long as it takes.
+    pub(crate) timeout_len: Option<Duration>,
+    /// Last value entered for each bind parameter, offered again next time.
+    pub(crate) binds: HashMap<String, String>,
+    /// The parameter labels of each statement `:prepare`d this session.
+    pub(crate) prepared: HashMap<String, Vec<String>>,
+    /// Variables from `:setvar`, substituted into queries.
+    pub(crate) vars: BTreeMap<String, String>,
+    /// `None` if there is nowhere to keep swap files.
+    pub(crate) swap: Option<swap::Swap>,
+    /// `None` unless the config turns it on.
+    pub(crate) audit: Option<audit::AuditLog>,
+    /// Where `:record` is writing the session's keys.
+    pub(crate) recorder: Option<Recorder>,
+    pub(crate) session: Session,
+    /// Database attached with `:connect`, which queries go to instead of
+    /// the session until `:disconnect`.
+    pub(crate) backend: Option<db::backend::Backend>,
+    /// Started with `--demo`: the backend is the demo and there is no
+    /// server behind the session.
+    pub(crate) demo: bool,
+    /// The lesson of `--tutor`.
+    pub(crate) tutor: Option<Tutor>,
+    #[cfg(feature = "plugins")]
+    pub(crate) plugins: crate::plugin::Plugins,
+    /// `None` without an `init.lua`.
+    #[cfg(feature = "lua")]
+    pub(crate) lua: Option<crate::lua::Lua>,
+    /// The ex command being typed in `Mode::Command`.
+    pub(crate) command_line: String,
+    /// The last ex command run from the command line, left below the
+    /// status line as vim does.
+    pub(crate) last_ex: Option<String>,
+    /// False while the connection is lost and being re-established.
+    pub(crate) connected: bool,
+    /// What the profile connected with says the database is.
+    pub(crate) environment: Option<Environment>,
+    /// Round trip time of the last health check.
+    pub(crate) latency: Option<Duration>,
+    /// How long the last statement took.
+    pub(crate) elapsed: Option<Duration>,
+    pub(crate) transaction: Transaction,
+    /// Whether a statement failing in a transaction rolls back only itself.
+    pub(crate) on_error_rollback: bool,
+    /// Whether queries are rolled back once run, to see what they would do.
+    pub(crate) dry_run: bool,
+    /// Rows an `UPDATE` or `DELETE` may change before asking whether to
+    /// commit, 0 for no limit.
+    pub(crate) row_guard: u64,
+    /// A query the planner expects to cost more than this asks first, 0
+    /// for no limit.
+    pub(crate) cost_guard: u64,
+    /// A query the planner expects to return or touch more rows than this
+    /// asks first, 0 for no limit.
+    pub(crate) est_row_guard: u64,
+    /// `LIMIT` added to reads without one, 0 for none.
+    pub(crate) auto_limit: u64,
+    /// MiB of rows a result keeps in memory before the older ones go to
+    /// disk, 0 for no limit.
+    pub(crate) result_memory: u64,
+    /// Whether the server makes transactions read-only, as far as `:set`
+    /// knows.
+    pub(crate) read_only: bool,
+    /// The rows the next query should change, from `:run expect=N`.
+    pub(crate) expected_rows: Option<u64>,
+    pub(crate) statusline: StatusLine,
+    /// Applied server side as `statement_timeout`, and client side in case
+    /// the server stops answering altogether.
+    pub(crate) statement_timeout: Option<Duration>,
+    /// Queues actions for the UI loop, and lets background tasks report
+    /// back to it.
+    pub(crate) messages: Bus,
+    /// Rows of the last query that returned any.
+    pub(crate) results: Option<ResultSet>,
+    /// The query `results` came from, as typed.
+    pub(crate) last_query: Option<String>,
+    /// The results of the other statements of the batch `results` came
+    /// from, if it returned more than one set of rows.
+    pub(crate) result_tabs: Option<Tabs>,
+    /// While `gm` shows only the marked rows: all the results, their grid
+    /// and which of their rows each shown one is.
+    pub(crate) all_rows: Option<(ResultSet, Grid, Vec<usize>)>,
+    /// The report in the results grid, refreshed every so often.
+    pub(crate) report: Option<Report>,
+    /// When the report was last asked for.
+    pub(crate) report_at: Instant,
+    /// The table `:browse` shows a page of in the results grid.
+    pub(crate) browser: Option<Browser>,
+    /// Cells changed in the results, not yet written back.
+    pub(crate) edits: Option<Edits>,
+    /// Rows to a page of `:browse`.
+    pub(crate) page_size: u64,
+    /// Whether queries the UI makes up to read with, like those of a
+    /// `:browse` filter, show in the SQL preview too, not only its writes.
+    pub(crate) preview: bool,
+    pub(crate) grid: Grid,
+    pub(crate) gutter: grid::Gutter,
+    /// Line numbers of the editor.
+    pub(crate) editor_gutter: grid::Gutter,
+    /// A block cursor in normal mode and a bar in insert and command mode,
+    /// `:set cursorshape`.
+    pub(crate) cursor_shape: bool,
+    /// Soft wrap long lines in the editor, `:set wrap`.
+    pub(crate) wrap: bool,
+    /// Underline what the linter finds in the editor, `:set lint`.
+    pub(crate) lint: bool,
+    /// Add a script to sort `:export html` tables by a column, `:set
+    /// htmlsort`.
+    pub(crate) html_sort: bool,
+    /// `:set shorten-ids`: show UUIDs and hashes by their first characters.
+    pub(crate) shorten_ids: bool,
+    /// `:set timezone`: the session's `TimeZone`, which the grid moves
+    /// timestamps fetched before it was set to as well.
+    pub(crate) timezone: Option<chrono_tz::Tz>,
+    /// Columns masked for screen sharing, from the profile and `:redact`.
+    pub(crate) redactions: Redactions,
+    /// `:set plain` or `--no-color`: no colors or box drawing, and changes
+    /// of mode and pane said on the status line, for screen readers.
+    pub(crate) plain: bool,
+    /// Count typed before a results grid motion, like the 5 of `5j`.
+    pub(crate) count: Option<usize>,
+    /// Plan of the buffer's query, shown instead of the results until Esc.
+    pub(crate) plan: Option<plan::PlanView>,
+    /// The last two plans of the query in each buffer, by buffer id: the
+    /// one before (if any) and the latest.
+    pub(crate) plans: HashMap<usize, (Option<plan::Plan>, plan::Plan)>,
+    /// `:plandiff` of those, shown instead of the results until Esc.
+    pub(crate) plan_diff: Option<plan::PlanDiff>,
+    /// `:erd`, shown instead of the results until Esc.
+    pub(crate) erd: Option<Erd>,
+    /// `:stats`, shown instead of the results until Esc.
+    pub(crate) dashboard: Option<Dashboard>,
+    /// What the statements run this session came to, for `:stats`.
+    pub(crate) activity: Activity,
+    /// The saved queries, shown instead of the results until Esc.
+    pub(crate) library: Option<Library>,
+    /// Where the project keeps its saved queries, from `.dbvi.toml`.
+    pub(crate) project_queries: Option<PathBuf>,
+    /// Where the project keeps its migrations, from `.dbvi.toml`.
+    pub(crate) migrations: Option<PathBuf>,
+    /// The elements of an array or composite cell, opened with Enter and
+    /// shown instead of the results until Esc.
+    pub(crate) elements: Option<Elements>,
+    /// Two rows compared with `:rowdiff`, shown instead of the results
+    /// until Esc.
+    pub(crate) row_diff: Option<RowDiff>,
+    /// The results of two connections side by side, from `:compare`,
+    /// shown instead of the results until Esc.
+    pub(crate) compare: Option<Compare>,
+    /// The snapshot `:asof` holds open.
+    pub(crate) snapshot: Option<Snapshot>,
+    /// What snapshot the results shown came from, if they came from one.
+    pub(crate) results_as_of: Option<String>,
+    /// Background operations, listed by `:jobs`.
+    pub(crate) jobs: Jobs,
+    /// Queries waiting their turn on the connection, listed by `:queued`.
+    pub(crate) queue: Queue,
+    /// Places to go through with `:cnext`, from `:grep-schema` or the
+    /// statements `:source` failed on.
+    pub(crate) quickfix: Quickfix,
+    /// The cached catalog of the database, see `:schema`.
+    pub(crate) schema: Schema,
+    /// The schemas unqualified names are looked up in, as the server last
+    /// said; empty until it has.
+    pub(crate) search_path: Vec<String>,
+    /// What the session's role may do on the server, as it last said;
+    /// nothing is known, and so nothing turned off, until it has.
+    pub(crate) capabilities: Capabilities,
+    /// Where `schema` is cached, if anywhere.
+    pub(crate) schema_path: Option<PathBuf>,
+    /// DDL ran since the schema was last refreshed.
+    pub(crate) schema_stale: bool,
+    /// When the server was last asked whether the schema changed.
+    pub(crate) schema_checked_at: Instant,
+    /// Changes to the schema the user was told about, so as to tell them
+    /// only once.
+    pub(crate) schema_notice: Option<Changes>,
+    /// Shown over everything until the next key.
+    pub(crate) popup: Option<Popup>,
+    /// The table the popup describes, for `D`, `E` and `V`.
+    pub(crate) described: Option<String>,
+    /// Shown in a corner for a while.
+    pub(crate) toast: Option<Toast>,
+    /// Pane that normal mode keys go to.
+    pub(crate) focus: Pane,
+    /// Height of the results pane, in percent of the space it shares with
+    /// the editor.
+    pub(crate) split: u16,
+    /// Dragging the border between the results and the editor.
+    pub(crate) resizing: bool,
+    /// Where the panes were last drawn, for the mouse.
+    pub(crate) results_area: Rect,
+    pub(crate) editor_area: Rect,
+    /// The terminal changed size since the last draw.
+    pub(crate) resized: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Pane {
+    Results,
+    Editor,
+}
+
+/// Sent from background tasks to the UI loop.
+#[derive(Debug)]
+pub enum Message {
+    Reconnecting {
+        attempt: u32,
+        delay: Duration,
+        error: String,
+    },
+    Reconnected,
+    /// Result of a periodic health check.
+    Ping(Result<Duration, String>),
+    /// `:source` finished statement `done` of `total`.
+    SourceProgress {
+        done: usize,
+        total: usize,
+    },
+    /// `:import` has sent `done` of `total` bytes.
+    ImportProgress {
+        done: u64,
+        total: u64,
+    },
+    /// `:copy` has written `bytes` so far.
+    CopyProgress {
+        bytes: u64,
+    },
+    /// `:stats` asked the server how it is doing.
+    ServerStats(Result<ServerStats, String>),
+    /// A report was run, for the first time or to refresh it.
+    ReportDone {
+        report: Report,
+        outcome: Result<ResultSet, String>,
+    },
+    /// A background task is done, with this to say about it.
+    Status(String),
+    /// A background task is done, with more to say than fits the status.
+    Popup(Popup),
+    /// `:source` is done; `report` has a line per statement run, and
+    /// `failures` where the ones that failed are.
+    SourceDone {
+        path: String,
+        report: String,
+        failures: Vec<Entry>,
+        status: String,
+        connection_lost: bool,
+    },
+    /// A queued query is done; `outcome` has the rows of each statement
+    /// that returned any and a status, or why it failed.
+    QueuedDone {
+        id: usize,
+        sql: String,
+        outcome: Result<(Vec<(String, ResultSet)>, String), String>,
+        connection_lost: bool,
+    },
+    /// `:pipe` is done; `outcome` is what the command printed and how it
+    /// exited.
+    PipeDone {
+        command: String,
+        outcome: Result<(String, ExitStatus), String>,
+    },
+    /// A line of output from `:dump` or `:restore`, for the log buffer with
+    /// this id.
+    Log {
+        buffer: usize,
+        line: String,
+    },
+    /// The signatures of the relations on the server, to compare with
+    /// the cached schema.
+    SchemaChecked(Vec<(String, String)>),
+    /// What the `search_path` came to after something may have changed it.
+    SearchPath(Vec<String>),
+    /// What the server said the session's role may do, once connected.
+    Capabilities(Capabilities),
+    /// What the language server found in a document.
+    LspDiagnostics {
+        uri: String,
+        diagnostics: Vec<lsp::Diagnostic>,
+    },
+    /// The language server is gone.
+    LspExited,
+    /// The overloads of a function a call is being typed to.
+    Signatures(String, Vec<db::catalog::Function>),
+    /// The cached schema was brought up to date.
+    SchemaRefreshed(Schema),
+    /// How far along job `id` is.
+    JobProgress {
+        id: usize,
+        progress: db::monitor::Progress,
+    },
+    /// Job `id` ran to the end, one way or the other.
+    JobDone {
+        id: usize,
+        elapsed: Duration,
+        outcome: Result<jobs::Done, String>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Normal,
+    Insert,
+    Command,
+    /// `R`: typing goes over the text rather than before it.
+    Replace,
+    /// `/`: typing a pattern into `command_line` to search the editor for,
+    /// found as it is typed.
+    Search,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    RunQuery(String),
+    /// `:run [expect=N]`: runs the buffer, asking before it commits should
+    /// the rows changed not be `expect`.
+    Run {
+        expect: Option<u64>,
+    },
+    ConnInfo,
+    /// Send queries to another database, by URL, until `:disconnect`.
+    Connect(String),
+    /// Reconnect the session to another database of the same server.
+    SwitchDatabase(String),
+    Disconnect,
+    /// Stop everything at once: cancel every job and the queue, end the
+    /// session's backend, which rolls back its transaction, and disconnect.
+    KillSwitch,
+    /// Connect the session again after the kill switch.
+    Reconnect,
+    /// List the tables of the database queries go to.
+    Tables,
+    /// Open the source of a view or function in a read-only buffer.
+    Definition(String),
+    /// Open the reconstructed `CREATE TABLE` of a table in a new buffer.
+    Ddl(String),
+    /// Open a statement skeleton listing every column of a table.
+    Generate(generate::Skeleton, String),
+    /// `:s/pattern/replacement/`, on some lines of the buffer.
+    Substitute(substitute::Substitute),
+    /// Stop highlighting the last pattern.
+    NoHighlight,
+    /// Yank the whole buffer, `:%y`.
+    YankBuffer,
+    /// Open a file in a new buffer, or switch to it if it is open already.
+    Edit(String),
+    /// Write the buffer to the given file or its own.
+    Write(Option<String>),
+    /// Run a script statement by statement, carrying on past errors if
+    /// forced.
+    Source {
+        path: String,
+        force: bool,
+    },
+    /// Stream the output of the buffer's query to a CSV file with `COPY`.
+    CopyOut(String),
+    /// `:copy-to <target> <table> [results]`: copy the buffer's query or
+    /// the results shown into a table on another connection, a profile or
+    /// a URL.
+    CopyTo {
+        target: String,
+        table: String,
+        source: transfer::Source,
+    },
+    /// `:dump table|schema|db <path>`: run `pg_dump` into a file.
+    Dump {
+        scope: dump::Scope,
+        path: String,
+    },
+    /// `:restore <path>`: run `pg_restore` on an archive from `:dump`.
+    Restore(String),
+    /// `:migrate [status|up|down]`, see `migrate`.
+    Migrate(migrate::Action),
+    /// Show a server report in the results grid.
+    Report(Report),
+    /// `:report <template> [output]`: render saved queries through a Tera
+    /// template into a file.
+    RenderReport {
+        template: String,
+        output: Option<String>,
+    },
+    /// Cancel the query of a backend, or end its session if `terminate`.
+    SignalBackend {
+        pid: i32,
+        terminate: bool,
+    },
+    /// `:setval[!] <sequence> <value>`: set a sequence, forced if it would
+    /// be behind the column it fills.
+    SetVal {
+        sequence: String,
+        value: i64,
+        force: bool,
+    },
+    /// `D` or `E` over a table `K` describes: disable or enable its user
+    /// triggers.
+    Triggers {
+        table: String,
+        enable: bool,
+    },
+    /// `V` over a table `K` describes: validate its `NOT VALID` constraints.
+    ValidateConstraints(String),
+    /// `VACUUM (ANALYZE)` a table in the background.
+    Vacuum(String),
+    /// `:refresh [concurrently] <matview>`: refresh a materialized view in
+    /// the background.
+    Refresh {
+        matview: String,
+        concurrently: bool,
+    },
+    /// Load a CSV file into a table, creating it if need be.
+    Import {
+        path: String,
+        table: Option<String>,
+    },
+    /// Open (or throw away) the swap files left by a crashed session.
+    Recover {
+        discard: bool,
+    },
+    /// Show the plan of the buffer's query, running it if `analyze`.
+    Explain {
+        analyze: bool,
+    },
+    /// Compare the buffer's last two plans.
+    PlanDiff,
+    /// Indexes for the sequential scans of the buffer's last analyzed plan.
+    SuggestIndex,
+    /// Draw the tables of a schema, or those around a table, with their
+    /// foreign keys.
+    Erd(Option<String>),
+    /// `:stats`, the session's activity and the server's.
+    Stats,
+    /// `:capabilities`, what the session's role may do on the server.
+    Capabilities,
+    /// Time the buffer's query over `runs` runs, after an untimed one if
+    /// `warm_up`.
+    Bench {
+        runs: usize,
+        warm_up: bool,
+    },
+    /// Open the debug log in a read-only buffer.
+    Log,
+    /// `:record <file>`: write the keys of the session to a file for
+    /// `--replay`, or stop without one.
+    Record(Option<String>),
+    /// Chart the distribution of a result column, the cursor's if none is
+    /// named.
+    Histogram(Option<String>),
+    /// Plot result columns against each other.
+    Chart(chart::ChartSpec),
+    /// Replace the results with a crosstab of them.
+    Pivot(pivot::PivotSpec),
+    /// List the plugins loaded and what they provide.
+    Plugins,
+    /// Run an ex command provided by a plugin.
+    Plugin {
+        name: String,
+        args: String,
+    },
+    /// Replace the results with what a plugin makes of them.
+    Transform(String),
+    /// Write the results to a file: `csv`, `table` or a plugin's exporter.
+    Export {
+        exporter: String,
+        path: String,
+    },
+    /// Run some Lua, `:lua`.
+    Lua(String),
+    /// Run an action defined in `init.lua`.
+    LuaAction {
+        name: String,
+        args: String,
+    },
+    /// Edit the buffer in `$VISUAL`/`$EDITOR`, like psql's `\e`.
+    EditExternal,
+    /// Run a shell command with the terminal handed over, `:!`.
+    Shell(String),
+    /// Feed the results as CSV to a shell command and show what it prints.
+    Pipe(String),
+    /// Show the results as a table in `$PAGER`.
+    Page,
+    /// Page through a table, in the order of a column, descending with
+    /// `true`, and of its key.
+    Browse {
+        table: String,
+        sort: Option<(String, bool)>,
+    },
+    /// Turn the page of the table being browsed.
+    TurnPage(PageTo),
+    /// Sort the table being browsed by a column, on the server.
+    SortBy(String),
+    /// Ask for a filter on a column of the table being browsed.
+    Filter(String),
+    /// Drop the filters of the table being browsed.
+    Unfilter,
+    /// Change the cell under the cursor, to be written back to its table.
+    EditCell,
+    /// `<C-n>` in insert mode: offer completions for the word before the
+    /// cursor.
+    Complete,
+    /// Look up the overloads of a function a call is being typed to.
+    LookUpSignatures(String),
+    /// `K` in the editor: describe the relation, column or function under
+    /// the cursor.
+    Hover(hover::Target),
+    /// Show the type of the column under the cursor and the table column
+    /// it was read from, if any.
+    ColumnInfo,
+    /// `:apply`: review and run the `UPDATE`s of the changed cells.
+    ApplyEdits,
+    /// `:discard`: put the changed cells back as they were.
+    DiscardEdits,
+    /// Show the cached schema, or bring it up to date.
+    Schema {
+        refresh: bool,
+    },
+    /// Save the buffer as a named query, replacing one of that name if
+    /// forced.
+    SaveQuery {
+        name: String,
+        tags: Vec<String>,
+        force: bool,
+    },
+    /// Browse the saved queries.
+    Queries,
+    /// `:prepare <name>`: make the last statement of the buffer a
+    /// server-side prepared statement, replacing one of that name.
+    Prepare(String),
+    /// `:execute <name> [(args…)]`: run a prepared statement, asking for
+    /// its arguments if none are given.
+    Execute {
+        name: String,
+        args: Option<String>,
+    },
+    /// Set a variable, or unset it without a value.
+    SetVar {
+        name: String,
+        value: Option<String>,
+    },
+    /// List the variables, `:vars`.
+    Vars,
+    /// `:redact [column]`: mask the columns matching a name or glob, or
+    /// list what is masked.
+    Redact(Option<String>),
+    /// `:unredact <column>`: stop masking them.
+    Unredact(String),
+    /// `:compare <a> <b>`: run the buffer against two profiles or URLs and
+    /// show the rows side by side.
+    Compare([String; 2]),
+    /// Take, attach to, query or let go of a snapshot, `:asof`.
+    AsOf(AsOf),
+    /// `:rowdiff [<row> <row>]`: compare two rows of the results column by
+    /// column, the two given or else the two marked or selected.
+    RowDiff(Option<(usize, usize)>),
+    /// Keep the results on disk, replacing a saved result set of that name
+    /// if forced.
+    SaveResult {
+        name: String,
+        force: bool,
+    },
+    /// Show a saved result set.
+    LoadResult(String),
+    /// Join two result sets, saved or shown, on the client.
+    Join(join::Spec),
+    /// List the saved result sets.
+    ListResults,
+    /// List the background jobs, `:jobs`.
+    Jobs,
+    /// `:queue`: run the buffer in the background once what is ahead of it
+    /// on the connection is done.
+    Queue,
+    /// List the queued queries, `:queued`.
+    Queued,
+    /// Stop a running background job.
+    CancelJob(usize),
+    /// Show the quickfix list, `:copen`.
+    OpenQuickfix,
+    /// `:diagnostics`: fill the quickfix list with what the linter finds in
+    /// the buffer.
+    Diagnostics,
+    CloseQuickfix,
+    /// Go to an entry of the quickfix list, like `:cnext` does.
+    Quickfix(Go),
+    NextBuffer,
+    PreviousBuffer,
+    /// Close the buffer, refusing to drop unsaved changes unless forced.
+    DeleteBuffer {
+        force: bool,
+    },
+    Set {
+        option: String,
+        value: Option<String>,
+    },
+    Chain(Vec<Command>),
+    None,
+    /// Quit, refusing to drop unsaved changes unless forced.
+    Quit {
+        force: bool,
+    },
+}
+
+impl State {
+    pub fn new(session: Session, messages: Bus) -> Self {
+        Self {
+            is_running: true,
+            mode: Mode::Normal,
+            replaced: Vec::new(),
+            search_origin: None,
+            status: "Welcome to dbvi! Press `q` to quit.".into(),
+            buffers: vec![Buffer::new("[query]")],
+            current: 0,
+            register: Register::default(),
+            clipboard: Clipboard::None,
+            pending: None,
+            text_object: None,
+            search: None,
+            auto_pairs: false,
+            dialect: dialect::Dialect::default(),
+            snippets: HashMap::new(),
+            snippet: None,
+            signatures: Signatures::default(),
+            completion: None,
+            lsp: None,
+            keymap: Keymap::builtin(),
+            resolver: Resolver::default(),
+            timeout_len: Some(Duration::from_secs(1)),
+            binds: HashMap::new(),
+            prepared: HashMap::new(),
+            vars: BTreeMap::new(),
+            swap: None,
+            audit: None,
+            recorder: None,
+            results: None,
+            result_tabs: None,
+            all_rows: None,
+            grid: Grid::default(),
+            gutter: grid::Gutter::default(),
+            editor_gutter: grid::Gutter::default(),
+            cursor_shape: true,
+            wrap: false,
+            lint: true,
+            html_sort: true,
+            shorten_ids: false,
+            timezone: None,
+            redactions: Redactions::default(),
+            plain: false,
+            count: None,
+            last_query: None,
+            report: None,
+            browser: None,
+            edits: None,
+            page_size: 200,
+            preview: false,
+            report_at: Instant::now(),
+            plan: None,
+            plans: HashMap::new(),
+            plan_diff: None,
+            erd: None,
+            dashboard: None,
+            activity: Activity::default(),
+            library: None,
+            project_queries: None,
+            migrations: None,
+            elements: None,
+            row_diff: None,
+            compare: None,
+            snapshot: None,
+            results_as_of: None,
+            jobs: Jobs::default(),
+            queue: Queue::default(),
+            quickfix: Quickfix::default(),
+            schema: Schema::default(),
+            search_path: Vec::new(),
+            capabilities: Capabilities::default(),
+            schema_path: None,
+            schema_stale: false,
+            schema_checked_at: Instant::now(),
+            schema_notice: None,
+            popup: None,
+            described: None,
+            toast: None,
+            focus: Pane::Editor,
+            split: 60,
+            resizing: false,
+            results_area: Rect::default(),
+            editor_area: Rect::default(),
+            resized: false,
+            session,
+            command_line: String::new(),
+            last_ex: None,
+            connected: true,
+            environment: None,
+            elapsed: None,
+            transaction: Transaction::Idle,
+            on_error_rollback: true,
+            dry_run: false,
+            row_guard: 1000,
+            cost_guard: 0,
+            est_row_guard: 0,
+            auto_limit: 0,
+            result_memory: (results::DEFAULT_MEMORY_LIMIT >> 20) as u64,
+            read_only: false,
+            expected_rows: None,
+            statusline: StatusLine::default(),
+            tutor: None,
+            latency: None,
+            backend: None,
+            demo: false,
+            #[cfg(feature = "plugins")]
+            plugins: Default::default(),
+            #[cfg(feature = "lua")]
+            lua: None,
+            statement_timeout: None,
+            messages,
+        }
+    }
+
+    /// The bus the UI loop takes actions from, to drive it from outside.
+    pub fn bus(&self) -> Bus {
+        self.messages.clone()
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.is_running
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// Maps `lhs`, written as in vim, to `rhs` in `mode`: keys like
+    /// `"ihello<Esc>"`, or a [`Mapping`] to an ex command or a template.
+    pub fn map(&mut self, mode: Mode, lhs: &str, rhs: impl Into<Mapping>) {
+        let rhs = rhs.into().rhs(&self.keymap);
+        self.keymap.map(mode, self.keymap.parse(lhs), rhs);
+    }
+
+    /// Masks the columns matching `pattern`, as `:redact` does; false if
+    /// they already were.
+    pub fn redact(&mut self, pattern: &str) -> bool {
+        self.redactions.add(pattern)
+    }
+
+    /// What `keys` are among the mappings of the current mode, those of
+    /// `init.lua` first.
+    pub(crate) fn lookup(&self, keys: &[KeyEvent]) -> Lookup {
+        let found = self.keymap.lookup(self.mode, keys);
+        #[cfg(feature = "lua")]
+        if let Some(lua) = &self.lua {
+            return lua.lookup(self.mode, keys).or(found);
+        }
+        found
+    }
+
+    /// The status line, or what is left of it after the last command.
+    pub fn status(&self) -> &str {
+        &self.status
+    }
+
+    /// Text of the buffer shown in the editor.
+    pub fn text(&self) -> String {
+        self.buffer().text()
+    }
+
+    pub(crate) fn buffer(&self) -> &Buffer {
+        &self.buffers[self.current]
+    }
+
+    pub(crate) fn buffer_mut(&mut self) -> &mut Buffer {
+        &mut self.buffers[self.current]
+    }
+
+    /// Opens `buffer` and makes it current.
+    pub(crate) fn open_buffer(&mut self, buffer: Buffer) {
+        self.buffers.push(buffer);
+        self.current = self.buffers.len() - 1;
+    }
+
+    /// Appends a statement the user ran to the audit log, if there is one,
+    /// and tells the `post_query` hooks about it.
+    pub(crate) fn audit(
+        &mut self,
+        statement: &str,
+        binds: &[Option<String>],
+        elapsed: Duration,
+        outcome: Result<u64, String>,
+    ) {
+        self.elapsed = Some(elapsed);
+        self.activity.record(elapsed, &outcome);
+        #[cfg(feature = "lua")]
+        if let Some(lua) = &self.lua {
+            lua.post_query(statement, elapsed.as_secs_f64() * 1000.0, &outcome);
+        }
+        let Some(audit) = &self.audit else {
+            return;
+        };
+        if let Err(err) = audit.record(statement, binds, elapsed, outcome) {
+            self.status = format!("Failed to write audit log: {err}");
+        }
+    }
+
+    /// Shows `results` in the grid, with the cursor on the first cell.
+    pub fn show_results(&mut self, results: Option<ResultSet>) {
+        self.grid = results.as_ref().map(Grid::new).unwrap_or_default();
+        self.results = results;
+        self.report = None;
+        self.browser = None;
+        self.edits = None;
+        self.plan = None;
+        self.plan_diff = None;
+        self.erd = None;
+        self.dashboard = None;
+        self.jobs.shown = false;
+        self.queue.shown = false;
+        self.quickfix.shown = false;
+        self.library = None;
+        self.elements = None;
+        self.row_diff = None;
+        self.compare = None;
+        self.results_as_of = None;
+        self.result_tabs = None;
+        self.all_rows = None;
+    }
+
+    /// Shows the results of each statement of a batch that returned rows,
+    /// the last one in front and the others a tab away.
+    pub fn show_batch(&mut self, mut results: Vec<(String, ResultSet)>) {
+        if results.len() < 2 {
+            self.show_results(results.pop().map(|(_, results)| results));
+        } else if let Some((tabs, shown)) = Tabs::new(results) {
+            self.show_results(Some(shown));
+            self.result_tabs = Some(tabs);
+        }
+    }
+
+    /// `]r` and `[r`: shows the results of the statement `by` tabs away.
+    pub(crate) fn switch_result(&mut self, by: isize) {
+        let shown = self.report.is_none() && self.browser.is_none() && self.plan.is_none();
+        if !shown || self.results.is_none() || self.result_tabs.is_none() {
+            self.status = "No other results to switch to".into();
+            return;
+        }
+        let (Some(mut tabs), Some(results)) = (self.result_tabs.take(), self.results.take()) else {
+            return;
+        };
+        let results = tabs.switch(results, by);
+        self.show_results(Some(results));
+        self.status = format!("Results {} of {}", tabs.current + 1, tabs.len());
+        self.result_tabs = Some(tabs);
+    }
+
+    /// Shows `results`, the page `browser` is on. Turning the page of the
+    /// same table keeps the columns as they were.
+    pub fn show_page(&mut self, browser: Browser, results: ResultSet) {
+        let same = self.browser.as_ref().map(|shown| &shown.table) == Some(&browser.table);
+        if same {
+            self.grid = self.grid.refreshed(&results);
+            self.grid.row = 0;
+            self.results = Some(results);
+            self.edits = None;
+        } else {
+            self.show_results(Some(results));
+        }
+        if let Some(results) = &self.results {
+            let sort = browser.sort.as_ref().and_then(|(column, descending)| {
+                Some((results.column_index(column)?, *descending))
+            });
+            self.grid.sorted_by(results, sort);
+        }
+        self.browser = Some(browser);
+    }
+
+    pub fn browser(&self) -> Option<&Browser> {
+        self.browser.as_ref()
+    }
+
+    pub fn edits(&self) -> Option<&Edits> {
+        self.edits.as_ref()
+    }
+
+    /// Marks the changed cells in the grid, where the rows are now.
+    /// `gm`: shows only the marked rows of the results, or all of them
+    /// again, with the marks made or taken away in between.
+    pub(crate) fn toggle_marked_rows(&mut self) {
+        if let Some((results, mut grid, rows)) = self.all_rows.take() {
+            for (shown, row) in rows.iter().enumerate() {
+                if self.grid.marked.contains(&shown) {
+                    grid.marked.insert(*row);
+                } else {
+                    grid.marked.remove(row);
+                }
+            }
+            self.results = Some(results);
+            self.grid = grid;
+            self.status = "All rows".into();
+            return;
+        }
+        if self.report.is_some() || self.browser.is_some() {
+            self.status = "Only the rows of a query can be shown marked".into();
+            return;
+        }
+        let Some(results) = self.results.take() else {
+            return;
+        };
+        let rows: Vec<usize> = self
+            .grid
+            .marked
+            .iter()
+            .copied()
+            .filter(|row| *row < results.rows.len())
+            .collect();
+        if rows.is_empty() {
+            self.status = "No rows marked, m marks one".into();
+            self.results = Some(results);
+            return;
+        }
+        let mut marked = ResultSet::new(results.columns.clone());
+        marked.rows = results.rows.select(rows.iter().copied());
+        let mut grid = Grid::new(&marked);
+        grid.marked = (0..rows.len()).collect();
+        grid.hidden = self.grid.hidden.clone();
+        grid.pinned = self.grid.pinned;
+        grid.col = self.grid.col;
+        self.status = match rows.len() {
+            1 => "1 marked row, gm for all".into(),
+            n => format!("{n} marked rows, gm for all"),
+        };
+        self.results = Some(marked);
+        let all = std::mem::replace(&mut self.grid, grid);
+        self.all_rows = Some((results, all, rows));
+    }
+
+    pub(crate) fn mark_edits(&mut self) {
+        self.grid.edited = match (&self.edits, &self.results) {
+            (Some(edits), Some(results)) => edits.cells(results),
+            _ => Default::default(),
+        };
+    }
+
+    /// Line numbers of the focused pane, which `:set number` changes as in
+    /// vim, where it is a window option.
+    pub(crate) fn gutter(&self) -> grid::Gutter {
+        match self.focus {
+            Pane::Results => self.gutter,
+            Pane::Editor => self.editor_gutter,
+        }
+    }
+
+    pub(crate) fn gutter_mut(&mut self) -> &mut grid::Gutter {
+        match self.focus {
+            Pane::Results => &mut self.gutter,
+            Pane::Editor => &mut self.editor_gutter,
+        }
+    }
+
+    /// Chars that fit on an editor line, past the line numbers.
+    pub(crate) fn editor_width(&self) -> usize {
+        let gutter = self.editor_gutter.width(self.buffer().lines.len());
+        self.editor_area.width.saturating_sub(gutter) as usize
+    }
+
+    /// Whether the current buffer may be changed, complaining if not.
+    pub(crate) fn editable(&mut self) -> bool {
+        if self.buffer().read_only {
+            self.status = "Buffer is read-only".into();
+        }
+        !self.buffer().read_only
+    }
+
+    /// Puts `register` in the register and copies it to the clipboard.
+    pub(crate) fn yank(&mut self, register: Register) {
+        if let Err(err) = self.clipboard.copy(&register.text) {
+            self.status = err;
+        }
+        self.register = register;
+    }
+
+    /// What `p` pastes: the clipboard if something else was copied there
+    /// since the last yank, otherwise the register.
+    pub(crate) fn pasted(&mut self) -> Register {
+        match self.clipboard.paste() {
+            Some(text) if text != self.register.text => Register {
+                linewise: text.ends_with('\n'),
+                text,
+            },
+            _ => self.register.clone(),
+        }
+    }
+}
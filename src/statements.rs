@@ -0,0 +1,243 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Lexing just deep enough to tell SQL text from literals and comments, for
+//! splitting scripts into statements and finding placeholders.
+
+pub fn is_ident(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || c == b'_' || c >= 0x80
+}
+
+/// `name` as a quoted identifier, safe to splice into SQL whatever it is.
+pub fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// `value` as a string literal, which the server takes for whatever type
+/// the context wants.
+pub fn quote_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// Index just past the `delimiter` closing a token that started before
+/// `from`, or the end of `sql` if it is never closed.
+fn skip_past(sql: &str, from: usize, delimiter: &str) -> usize {
+    sql[from..]
+        .find(delimiter)
+        .map_or(sql.len(), |index| from + index + delimiter.len())
+}
+
+/// If a string literal, quoted identifier, dollar quoted string or comment
+/// starts at byte `i` of `sql`, the index just past its end.
+pub fn skip_quoted(sql: &str, i: usize) -> Option<usize> {
+    let bytes = sql.as_bytes();
+    let previous = i.checked_sub(1).map(|i| bytes[i]);
+    match (bytes[i], bytes.get(i + 1).copied()) {
+        (b'-', Some(b'-')) => Some(skip_past(sql, i, "\n")),
+        (b'/', Some(b'*')) => Some(skip_past(sql, i + 2, "*/")),
+        (b'\'', _) => {
+            // E'...' strings may also escape quotes with a backslash.
+            let escapes = matches!(previous, Some(b'e' | b'E'))
+                && !i.checked_sub(2).is_some_and(|i| is_ident(bytes[i]));
+            let mut i = i + 1;
+            while i < bytes.len() {
+                match bytes[i] {
+                    b'\\' if escapes => i += 2,
+                    b'\'' if bytes.get(i + 1) == Some(&b'\'') => i += 2,
+                    b'\'' => break,
+                    _ => i += 1,
+                }
+            }
+            Some((i + 1).min(bytes.len()))
+        }
+        (b'"', _) => Some(skip_past(sql, i + 1, "\"")),
+        // `$$...$$` or `$tag$...$tag$`, but not `$1` or a `$` inside a name.
+        (b'$', next)
+            if !previous.is_some_and(is_ident) && !next.is_some_and(|c| c.is_ascii_digit()) =>
+        {
+            let tag_len = bytes[i + 1..]
+                .iter()
+                .position(|c| !is_ident(*c))
+                .unwrap_or(bytes.len() - i - 1);
+            if bytes.get(i + 1 + tag_len) != Some(&b'$') {
+                return None;
+            }
+            let tag = &sql[i..i + tag_len + 2];
+            Some(skip_past(sql, i + tag.len(), tag))
+        }
+        _ => None,
+    }
+}
+
+/// The names (qualified and quoted ones whole), literals and punctuation of
+/// `sql` with where each starts, comments left out.
+pub fn words(sql: &str) -> Vec<(usize, &str)> {
+    let bytes = sql.as_bytes();
+    let mut words = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let start = i;
+        if is_ident(bytes[i]) || bytes[i] == b'"' {
+            while i < bytes.len() && (is_ident(bytes[i]) || matches!(bytes[i], b'.' | b'"')) {
+                i = match bytes[i] {
+                    b'"' => skip_quoted(sql, i).unwrap_or(bytes.len()),
+                    _ => i + 1,
+                };
+            }
+            words.push((start, &sql[start..i]));
+        } else if let Some(end) = skip_quoted(sql, i) {
+            if !matches!(bytes[i], b'-' | b'/') {
+                words.push((start, &sql[start..end]));
+            }
+            i = end;
+        } else {
+            if !bytes[i].is_ascii_whitespace() {
+                words.push((start, &sql[i..i + 1]));
+            }
+            i += 1;
+        }
+    }
+    words
+}
+
+/// Whether a statement of `sql` is an `UPDATE`, `DELETE` or `MERGE`, which
+/// a mistake in the `WHERE` can turn on the whole table.
+pub fn is_bulk_write(sql: &str) -> bool {
+    split(sql).iter().any(|statement| {
+        let keyword = statement
+            .split(|c: char| !c.is_alphanumeric())
+            .next()
+            .unwrap_or_default();
+        ["update", "delete", "merge"]
+            .iter()
+            .any(|write| keyword.eq_ignore_ascii_case(write))
+    })
+}
+
+/// Statements that change nothing, or only the session.
+const READS: &[&str] = &[
+    "show",
+    "table",
+    "values",
+    "begin",
+    "start",
+    "commit",
+    "end",
+    "rollback",
+    "abort",
+    "savepoint",
+    "release",
+    "set",
+    "reset",
+    "discard",
+    "listen",
+    "unlisten",
+    "fetch",
+    "move",
+    "close",
+    "deallocate",
+];
+
+/// Statements that write if the one in them does.
+const WRAPPERS: &[&str] = &["select", "with", "explain", "declare", "prepare"];
+
+/// What makes one of [`WRAPPERS`] a write, `INTO` for `SELECT INTO`.
+const WRITES: &[&str] = &["insert", "update", "delete", "merge", "into"];
+
+/// Whether a statement of `sql` may change data or the schema. Anything not
+/// known to only read counts, a `CALL` or a `DO` as much as an `INSERT`.
+pub fn is_write(sql: &str) -> bool {
+    let any = |word: &str, of: &[&str]| of.iter().any(|w| word.eq_ignore_ascii_case(w));
+    split(sql).iter().any(|statement| {
+        let words = words(statement);
+        match words.first() {
+            Some((_, first)) if any(first, READS) => false,
+            Some((_, first)) if any(first, WRAPPERS) => {
+                words.iter().any(|(_, word)| any(word, WRITES))
+            }
+            Some(_) => true,
+            None => false,
+        }
+    })
+}
+
+/// `sql` with a `LIMIT` of `rows`, if it is a single query that reads and
+/// says nothing of how many rows itself: no `LIMIT`, `FETCH` or `FOR
+/// UPDATE` anywhere in it, subqueries included.
+pub fn limit(sql: &str, rows: u64) -> Option<String> {
+    let any = |word: &str, of: &[&str]| of.iter().any(|w| word.eq_ignore_ascii_case(w));
+    let [statement] = split(sql)[..] else {
+        return None;
+    };
+    let words = words(statement);
+    let (_, first) = words.first()?;
+    if !any(first, &["select", "with", "table", "values"])
+        || is_write(statement)
+        || words
+            .iter()
+            .any(|(_, word)| any(word, &["limit", "fetch", "for"]))
+    {
+        return None;
+    }
+    // After the last word, not a comment that would swallow the limit.
+    let (start, last) = words.last()?;
+    Some(format!("{} LIMIT {rows}", &statement[..start + last.len()]))
+}
+
+/// Whether `sql` is a single statement that `EXPLAIN` can plan without
+/// running it.
+pub fn is_explainable(sql: &str) -> bool {
+    let [statement] = split(sql)[..] else {
+        return false;
+    };
+    words(statement).first().is_some_and(|(_, first)| {
+        [
+            "select", "with", "table", "values", "insert", "update", "delete", "merge",
+        ]
+        .iter()
+        .any(|keyword| first.eq_ignore_ascii_case(keyword))
+    })
+}
+
+/// Splits a script on the `;`s between statements. Statements that are
+/// empty or only comments are dropped.
+pub fn split(sql: &str) -> Vec<&str> {
+    let bytes = sql.as_bytes();
+    let mut statements = Vec::new();
+    let mut start = 0;
+    let mut has_code = false;
+    let mut i = 0;
+    while i < bytes.len() {
+        if let Some(end) = skip_quoted(sql, i) {
+            // Comments alone don't make a statement, literals do.
+            has_code |= !matches!(&bytes[i..i + 2.min(bytes.len() - i)], b"--" | b"/*");
+            i = end;
+            continue;
+        }
+        if bytes[i] == b';' {
+            if has_code {
+                statements.push(sql[start..i].trim());
+            }
+            start = i + 1;
+            has_code = false;
+        } else if !bytes[i].is_ascii_whitespace() {
+            has_code = true;
+        }
+        i += 1;
+    }
+    if has_code {
+        statements.push(sql[start..].trim());
+    }
+    statements
+}
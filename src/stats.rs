@@ -0,0 +1,173 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Quick statistics over the rows of a result column that are loaded,
+//! without another trip to the server.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use crate::results::ResultSet;
+use crate::values;
+
+/// How many of the most frequent values are kept.
+const TOP: usize = 5;
+
+#[derive(Debug, Clone)]
+pub struct ColumnStats {
+    pub rows: usize,
+    pub nulls: usize,
+    pub distinct: usize,
+    pub min: Option<String>,
+    pub max: Option<String>,
+    /// Only for numeric columns.
+    pub mean: Option<f64>,
+    /// Most frequent values and how often they occur.
+    pub top: Vec<(String, usize)>,
+}
+
+/// Every non-`NULL` value of column `col` and how often it occurs, most
+/// frequent first.
+pub fn frequencies(results: &ResultSet, col: usize) -> Vec<(String, usize)> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for value in results.rows.iter().filter_map(|row| row.get(col)) {
+        *counts.entry(value).or_default() += 1;
+    }
+    let mut counts: Vec<(String, usize)> = counts
+        .into_iter()
+        .map(|(value, count)| (value.to_string(), count))
+        .collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts
+}
+
+/// The values of a numeric column `col`, `money` by its amount, skipping
+/// `NULL`s and anything that doesn't parse, like `NaN`.
+pub fn numbers(results: &ResultSet, col: usize) -> Vec<f64> {
+    let column = &results.columns[col];
+    results
+        .rows
+        .iter()
+        .filter_map(|row| values::number(column, row.get(col)?))
+        .filter(|n| n.is_finite())
+        .collect()
+}
+
+/// Bars for a histogram of column `col`, at most `max_bars` of them: how
+/// often each value occurs, or for a numeric column with more values than
+/// that, how many fall into each of `max_bars` equal ranges.
+pub fn histogram(results: &ResultSet, col: usize, max_bars: usize) -> Vec<(String, u64)> {
+    let mut counts = frequencies(results, col);
+    let numbers = numbers(results, col);
+    if !results.columns[col].is_numeric() || counts.len() <= max_bars || numbers.is_empty() {
+        if results.columns[col].is_numeric() {
+            counts.sort_by(|a, b| {
+                let (a, b) = (a.0.parse::<f64>(), b.0.parse::<f64>());
+                a.ok().partial_cmp(&b.ok()).unwrap_or(Ordering::Equal)
+            });
+        }
+        return counts
+            .into_iter()
+            .take(max_bars)
+            .map(|(value, count)| (value.replace('\n', "↵"), count as u64))
+            .collect();
+    }
+
+    let min = numbers.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = numbers.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let step = (max - min) / max_bars as f64;
+    // Enough decimals to tell neighbouring buckets apart.
+    let precision = (1.0 - step.log10().floor()).max(0.0) as usize;
+    let mut buckets = vec![0u64; max_bars];
+    for n in numbers {
+        let bucket = ((n - min) / step) as usize;
+        buckets[bucket.min(max_bars - 1)] += 1;
+    }
+    buckets
+        .into_iter()
+        .enumerate()
+        .map(|(index, count)| {
+            let low = min + step * index as f64;
+            (
+                format!("{low:.precision$} – {:.precision$}", low + step),
+                count,
+            )
+        })
+        .collect()
+}
+
+impl ColumnStats {
+    pub fn compute(results: &ResultSet, col: usize) -> Self {
+        let column = &results.columns[col];
+        let numeric = column.is_numeric();
+        let values: Vec<&str> = results.rows.iter().filter_map(|row| row.get(col)).collect();
+        // Numbers compare as numbers, intervals by how long they are, money
+        // by its amount and everything else as text, which is right for
+        // dates and timestamps in ISO format too.
+        let compare = |a: &&str, b: &&str| match (a.parse::<f64>(), b.parse::<f64>()) {
+            (Ok(a), Ok(b)) if numeric => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+            _ if matches!(column.ty.as_str(), "interval" | "money") => {
+                values::compare(column, a, b)
+            }
+            _ => a.cmp(b),
+        };
+        let mean = numeric.then(|| numbers(results, col)).and_then(|numbers| {
+            (!numbers.is_empty()).then(|| numbers.iter().sum::<f64>() / numbers.len() as f64)
+        });
+        let mut top = frequencies(results, col);
+        let distinct = top.len();
+        top.truncate(TOP);
+        Self {
+            rows: results.rows.len(),
+            nulls: results.rows.len() - values.len(),
+            distinct,
+            min: values
+                .iter()
+                .copied()
+                .min_by(compare)
+                .map(|v| v.to_string()),
+            max: values
+                .iter()
+                .copied()
+                .max_by(compare)
+                .map(|v| v.to_string()),
+            mean,
+            top,
+        }
+    }
+
+    /// The stats as `label: value` lines.
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = vec![
+            format!("rows:     {}", self.rows),
+            format!("nulls:    {}", self.nulls),
+            format!("distinct: {}", self.distinct),
+            format!("min:      {}", self.min.as_deref().unwrap_or("-")),
+            format!("max:      {}", self.max.as_deref().unwrap_or("-")),
+        ];
+        if let Some(mean) = self.mean {
+            lines.push(format!("mean:     {mean}"));
+        }
+        if !self.top.is_empty() {
+            lines.push(String::new());
+            lines.push("most frequent:".into());
+            let width = self.top.iter().map(|(_, n)| n.to_string().len()).max();
+            let width = width.unwrap_or_default();
+            for (value, count) in &self.top {
+                lines.push(format!("  {count:>width$}  {}", value.replace('\n', "↵")));
+            }
+        }
+        lines
+    }
+}
@@ -0,0 +1,256 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The line above the command line, made of segments picked in the config:
+//!
+//! ```toml
+//! [statusline]
+//! left = ["mode", "status"]
+//! right = ["keys", "transaction", "rows", "timing", "searchpath", "connection", "clock"]
+//!
+//! [statusline.colors]
+//! insert = "green"
+//! command = "#d7af5f"
+//! ```
+
+use std::str::FromStr;
+
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+};
+use serde::{Deserialize, Deserializer};
+
+use crate::db::session::Transaction;
+use crate::input;
+use crate::state::{Mode, State};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Segment {
+    /// `Mode: Normal`, in the mode's color, and `recording` during
+    /// `:record`.
+    Mode,
+    /// The last message.
+    Status,
+    /// `● shop postgres@localhost 1.2ms`, colored by connection health.
+    Connection,
+    /// `TX` while a transaction is open, red once it has failed.
+    Transaction,
+    /// Rows in the results grid.
+    Rows,
+    /// How long the last statement took.
+    Timing,
+    /// Local time.
+    Clock,
+    /// `search_path: sales, public`, the schemas unqualified names are
+    /// looked up in.
+    SearchPath,
+    /// Keys of a command still being typed, like the `5g` of `5gg`.
+    Keys,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StatusLine {
+    /// Segments from the left, separated by `|`.
+    pub left: Vec<Segment>,
+    /// Segments against the right edge.
+    pub right: Vec<Segment>,
+    pub colors: ModeColors,
+}
+
+impl Default for StatusLine {
+    fn default() -> Self {
+        Self {
+            left: vec![Segment::Mode, Segment::Status],
+            right: vec![Segment::Keys, Segment::SearchPath, Segment::Connection],
+            colors: ModeColors::default(),
+        }
+    }
+}
+
+/// Colors of the mode segment, by name (`"green"`) or as `"#rrggbb"`. Blue,
+/// green, yellow and red if not set.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ModeColors {
+    #[serde(deserialize_with = "color")]
+    pub normal: Option<Color>,
+    #[serde(deserialize_with = "color")]
+    pub insert: Option<Color>,
+    #[serde(deserialize_with = "color")]
+    pub command: Option<Color>,
+    #[serde(deserialize_with = "color")]
+    pub replace: Option<Color>,
+}
+
+pub(crate) fn color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Color>, D::Error> {
+    let name = String::deserialize(deserializer)?;
+    Color::from_str(&name)
+        .map(Some)
+        .map_err(|_| serde::de::Error::custom(format!("unknown color {name}")))
+}
+
+impl StatusLine {
+    /// Whether `segment` is on either side.
+    pub fn shows(&self, segment: Segment) -> bool {
+        self.left.contains(&segment) || self.right.contains(&segment)
+    }
+
+    /// The left and right side of the line.
+    pub fn render(&self, state: &State) -> (Line<'static>, Line<'static>) {
+        self.render_short(state, false)
+    }
+
+    /// The line as `render` makes it, cut down to `width` on a narrow
+    /// terminal: the mode without its `Mode: `, and the right side's
+    /// segments dropped, the least missed first, until both sides fit.
+    pub fn fit(&self, state: &State, width: u16) -> (Line<'static>, Line<'static>) {
+        let mut fitted = self.clone();
+        let mut drop = [
+            Segment::Clock,
+            Segment::SearchPath,
+            Segment::Timing,
+            Segment::Rows,
+            Segment::Connection,
+        ]
+        .into_iter();
+        loop {
+            let (left, right) = fitted.render_short(state, true);
+            if left.width() + right.width() < width as usize {
+                return (left, right);
+            }
+            match drop.next() {
+                Some(segment) => fitted.right.retain(|shown| *shown != segment),
+                None => return (left, right),
+            }
+        }
+    }
+
+    fn render_short(&self, state: &State, short: bool) -> (Line<'static>, Line<'static>) {
+        let mut left = Vec::new();
+        for spans in self
+            .left
+            .iter()
+            .filter_map(|segment| self.segment(*segment, state, short))
+        {
+            if !left.is_empty() {
+                left.push(Span::raw(" | "));
+            }
+            left.extend(spans);
+        }
+        let mut right = Vec::new();
+        for spans in self
+            .right
+            .iter()
+            .filter_map(|segment| self.segment(*segment, state, short))
+        {
+            right.extend(spans);
+            right.push(Span::raw(" "));
+        }
+        (Line::from(left), Line::from(right))
+    }
+
+    /// `None` if the segment has nothing to show right now.
+    fn segment(&self, segment: Segment, state: &State, short: bool) -> Option<Vec<Span<'static>>> {
+        let spans = match segment {
+            Segment::Mode => {
+                let color = match state.mode {
+                    Mode::Normal => self.colors.normal.unwrap_or(Color::Blue),
+                    Mode::Insert => self.colors.insert.unwrap_or(Color::Green),
+                    Mode::Command | Mode::Search => self.colors.command.unwrap_or(Color::Yellow),
+                    Mode::Replace => self.colors.replace.unwrap_or(Color::Red),
+                };
+                let style = Style::default().fg(color).add_modifier(Modifier::BOLD);
+                let mode = match short {
+                    true => format!("{:?}", state.mode),
+                    false => format!("Mode: {:?}", state.mode),
+                };
+                let mut spans = vec![Span::styled(mode, style)];
+                if state.recorder.is_some() {
+                    spans.push(Span::styled(
+                        " recording",
+                        Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                    ));
+                }
+                spans
+            }
+            Segment::Status if state.status.is_empty() => return None,
+            Segment::Status => vec![Span::raw(state.status.clone())],
+            Segment::Connection => connection(state),
+            Segment::Transaction => match state.transaction {
+                Transaction::Idle => return None,
+                Transaction::Open => vec![Span::styled("TX", Style::default().fg(Color::Yellow))],
+                Transaction::Failed => {
+                    vec![Span::styled("TX failed", Style::default().fg(Color::Red))]
+                }
+            },
+            Segment::Rows => match state.results.as_ref()?.rows.len() {
+                1 => vec![Span::raw("1 row")],
+                rows => vec![Span::raw(format!("{rows} rows"))],
+            },
+            Segment::Timing => vec![Span::raw(format!("{:.1?}", state.elapsed?))],
+            Segment::Clock => vec![Span::raw(chrono::Local::now().format("%H:%M").to_string())],
+            Segment::SearchPath if state.backend.is_some() || state.search_path.is_empty() => {
+                return None;
+            }
+            Segment::SearchPath => vec![Span::styled(
+                format!("search_path: {}", state.search_path.join(", ")),
+                Style::default().fg(Color::DarkGray),
+            )],
+            Segment::Keys => match input::pending_keys(state) {
+                keys if keys.is_empty() => return None,
+                keys => vec![Span::styled(
+                    keys,
+                    Style::default().add_modifier(Modifier::BOLD),
+                )],
+            },
+        };
+        Some(spans)
+    }
+}
+
+fn connection(state: &State) -> Vec<Span<'static>> {
+    if let Some(backend) = &state.backend {
+        return vec![
+            Span::styled("● ", Style::default().fg(Color::Cyan)),
+            Span::raw(backend.name()),
+        ];
+    }
+    let options = &state.session.options;
+    let (color, latency) = match (state.connected, state.latency) {
+        (false, _) => (Color::Red, "down".to_string()),
+        (true, Some(latency)) => (
+            Color::Green,
+            format!("{:.1}ms", latency.as_secs_f64() * 1000.0),
+        ),
+        (true, None) => (Color::Yellow, "?".to_string()),
+    };
+    let mut spans = Vec::new();
+    if let Some(env) = state.environment {
+        spans.push(Span::styled(
+            format!("{} ", env.env.name()),
+            Style::default().fg(env.color).add_modifier(Modifier::BOLD),
+        ));
+    }
+    spans.push(Span::styled("● ", Style::default().fg(color)));
+    spans.push(Span::raw(format!(
+        "{} {}@{} {latency}",
+        options.get_database().unwrap_or(options.get_username()),
+        options.get_username(),
+        options.get_host(),
+    )));
+    spans
+}
@@ -0,0 +1,234 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `:[range]s/pattern/replacement/[flags]`.
+//!
+//! Patterns are Rust regexes rather than vim's. In the replacement `&` and
+//! `\0` stand for the whole match and `\1` to `\9` for groups, as in vim.
+
+use std::ops::RangeInclusive;
+
+use regex::{Captures, Regex, RegexBuilder};
+
+/// One end of a line range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Address {
+    /// 1-based, as typed.
+    Line(usize),
+    /// `.`
+    Current,
+    /// `$`
+    Last,
+}
+
+impl Address {
+    fn resolve(self, current: usize, lines: usize) -> usize {
+        match self {
+            Address::Line(line) => line.saturating_sub(1),
+            Address::Current => current,
+            Address::Last => lines.saturating_sub(1),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Substitute {
+    /// `None` for `%`, the whole buffer.
+    pub range: Option<(Address, Address)>,
+    pub pattern: Regex,
+    /// In `regex`'s syntax, `${1}`.
+    pub replacement: String,
+    /// Every match on a line rather than only the first.
+    pub global: bool,
+    /// Ask before each replacement.
+    pub confirm: bool,
+}
+
+impl PartialEq for Substitute {
+    fn eq(&self, other: &Self) -> bool {
+        self.range == other.range
+            && self.pattern.as_str() == other.pattern.as_str()
+            && self.replacement == other.replacement
+            && self.global == other.global
+            && self.confirm == other.confirm
+    }
+}
+
+impl Eq for Substitute {}
+
+impl Substitute {
+    /// The lines to work on, as indexes, for a buffer of `lines` lines with
+    /// the cursor on `current`.
+    pub fn lines(&self, current: usize, lines: usize) -> Result<RangeInclusive<usize>, String> {
+        let (start, end) = match self.range {
+            None => (0, lines.saturating_sub(1)),
+            Some((start, end)) => (start.resolve(current, lines), end.resolve(current, lines)),
+        };
+        if start > end {
+            return Err("Backwards range given".into());
+        }
+        if end >= lines {
+            return Err("Invalid range".into());
+        }
+        Ok(start..=end)
+    }
+
+    /// `line` with the match (or every match, if global) replaced, and how
+    /// many there were.
+    pub fn apply(&self, line: &str) -> (String, usize) {
+        let matches = self.pattern.find_iter(line).count();
+        let (limit, count) = if self.global {
+            (0, matches)
+        } else {
+            (1, matches.min(1))
+        };
+        let replaced = self
+            .pattern
+            .replacen(line, limit, self.replacement.as_str())
+            .into_owned();
+        (replaced, count)
+    }
+
+    /// Replaces the one match `captures` in `line`, returning the new line
+    /// and the byte offset just past the replacement.
+    pub fn replace_one(&self, line: &str, captures: &Captures) -> (String, usize) {
+        let matched = captures.get(0).map_or(0..0, |m| m.range());
+        let mut replacement = String::new();
+        captures.expand(&self.replacement, &mut replacement);
+        let end = matched.start + replacement.len();
+        let mut line = line.to_string();
+        line.replace_range(matched, &replacement);
+        (line, end)
+    }
+}
+
+/// Parses `input` if it is a substitute command, like `%s/a/b/g` or
+/// `3,$s#a#b#`.
+pub fn parse(input: &str) -> Option<Result<Substitute, String>> {
+    let (range, rest) = parse_range(input)?;
+    let rest = rest
+        .strip_prefix("substitute")
+        .or_else(|| rest.strip_prefix('s'))?;
+    let delimiter = rest.chars().next()?;
+    if delimiter.is_alphanumeric() || delimiter.is_whitespace() || delimiter == '\\' {
+        return None;
+    }
+    Some(parse_parts(range, &rest[delimiter.len_utf8()..], delimiter))
+}
+
+fn parse_range(input: &str) -> Option<(Option<(Address, Address)>, &str)> {
+    if let Some(rest) = input.strip_prefix('%') {
+        return Some((None, rest));
+    }
+    let Some((start, rest)) = parse_address(input) else {
+        return Some((Some((Address::Current, Address::Current)), input));
+    };
+    match rest.strip_prefix(',') {
+        Some(rest) => {
+            let (end, rest) = parse_address(rest)?;
+            Some((Some((start, end)), rest))
+        }
+        None => Some((Some((start, start)), rest)),
+    }
+}
+
+fn parse_address(input: &str) -> Option<(Address, &str)> {
+    if let Some(rest) = input.strip_prefix('.') {
+        return Some((Address::Current, rest));
+    }
+    if let Some(rest) = input.strip_prefix('$') {
+        return Some((Address::Last, rest));
+    }
+    let digits = input.len() - input.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let line = input[..digits].parse().ok()?;
+    Some((Address::Line(line), &input[digits..]))
+}
+
+fn parse_parts(
+    range: Option<(Address, Address)>,
+    input: &str,
+    delimiter: char,
+) -> Result<Substitute, String> {
+    // `\/` is the delimiter itself, any other escape is left to the regex
+    // or the replacement.
+    let mut parts = Vec::new();
+    let mut part = String::new();
+    let mut chars = input.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(next) if next == delimiter && regex::escape(&next.to_string()).len() == 1 => {
+                    part.push(next);
+                }
+                Some(next) => {
+                    part.push('\\');
+                    part.push(next);
+                }
+                None => {}
+            },
+            c if c == delimiter && parts.len() < 2 => parts.push(std::mem::take(&mut part)),
+            c => part.push(c),
+        }
+    }
+    parts.push(part);
+    let pattern = &parts[0];
+    let replacement = parts.get(1).map_or("", String::as_str);
+    let flags = parts.get(2).map_or("", String::as_str);
+    if pattern.is_empty() {
+        return Err("Empty pattern".into());
+    }
+    let (mut global, mut confirm, mut ignore_case) = (false, false, false);
+    for flag in flags.trim().chars() {
+        match flag {
+            'g' => global = true,
+            'c' => confirm = true,
+            'i' => ignore_case = true,
+            'I' => ignore_case = false,
+            _ => return Err(format!("Unknown flag \"{flag}\", use g, c, i or I")),
+        }
+    }
+    let pattern = RegexBuilder::new(pattern)
+        .case_insensitive(ignore_case)
+        .build()
+        .map_err(|err| format!("Invalid pattern: {err}"))?;
+    Ok(Substitute {
+        range,
+        pattern,
+        replacement: replacement_template(replacement),
+        global,
+        confirm,
+    })
+}
+
+/// Turns vim's `&`, `\1` and `\t` into `regex`'s `${0}`, `${1}` and a tab,
+/// escaping the `$`s that were meant literally.
+fn replacement_template(replacement: &str) -> String {
+    let mut template = String::new();
+    let mut chars = replacement.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '&' => template.push_str("${0}"),
+            '$' => template.push_str("$$"),
+            '\\' => match chars.next() {
+                Some(digit @ '0'..='9') => template.push_str(&format!("${{{digit}}}")),
+                Some('t') => template.push('\t'),
+                Some('$') => template.push_str("$$"),
+                Some(other) => template.push(other),
+                None => template.push('\\'),
+            },
+            c => template.push(c),
+        }
+    }
+    template
+}
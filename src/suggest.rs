@@ -0,0 +1,221 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `:suggest-index`: indexes that might spare an analyzed plan the
+//! sequential scans that read many more rows than they kept.
+//!
+//! The candidates come from the conditions the scans filtered on, columns
+//! compared for equality first and those compared by range after, as an
+//! index wants them. Only the plan is looked at, not the indexes the table
+//! has, so they are for review rather than to be run as they are.
+
+use crate::plan::{Node, Plan};
+use crate::statements;
+
+/// Rows a scan has to filter out, over all its loops, to be worth an index.
+const MIN_REMOVED: f64 = 1000.0;
+
+/// Words of a condition that aren't columns.
+const KEYWORDS: &[&str] = &[
+    "and", "or", "not", "is", "null", "true", "false", "any", "all", "some", "in", "like", "ilike",
+    "between", "array", "distinct", "from", "case", "when", "then", "else", "end", "hashed",
+    "subplan",
+];
+
+/// How a condition compares a column, by `=` or by `<`, `>` or a pattern.
+enum Compared {
+    Equal,
+    Range,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suggestion {
+    pub table: String,
+    pub columns: Vec<String>,
+    /// What in the plan it comes from.
+    pub why: String,
+}
+
+impl Suggestion {
+    /// The `CREATE INDEX CONCURRENTLY` for it, with why as a comment.
+    pub fn statement(&self) -> String {
+        format!(
+            "-- {}\nCREATE INDEX CONCURRENTLY ON {} ({});",
+            self.why,
+            self.table,
+            self.columns.join(", ")
+        )
+    }
+}
+
+/// The indexes to suggest for `plan`, none unless it was analyzed.
+pub fn suggest(plan: &Plan) -> Vec<Suggestion> {
+    let mut suggestions: Vec<Suggestion> = Vec::new();
+    for (index, node) in plan.nodes.iter().enumerate() {
+        let found = match node.node_type.as_str() {
+            "Seq Scan" => filtered_scan(node).into_iter().collect(),
+            "Nested Loop" => join_filter(plan, index, node),
+            _ => Vec::new(),
+        };
+        for suggestion in found {
+            if !suggestions
+                .iter()
+                .any(|s| s.table == suggestion.table && s.columns == suggestion.columns)
+            {
+                suggestions.push(suggestion);
+            }
+        }
+    }
+    suggestions
+}
+
+/// Rows a node filtered out by `key` over all its loops.
+fn removed(node: &Node, key: &str) -> f64 {
+    let removed: f64 = node
+        .detail(key)
+        .and_then(|rows| rows.parse().ok())
+        .unwrap_or_default();
+    removed * node.loops
+}
+
+/// Whether `qualifier`, the part of a column before its `.` if it has
+/// one, names the table `node` scans.
+fn owns(node: &Node, qualifier: Option<&str>) -> bool {
+    match qualifier {
+        None => true,
+        Some(qualifier) => {
+            node.alias.as_deref() == Some(qualifier)
+                || node.relation.as_deref().is_some_and(|relation| {
+                    relation == qualifier || relation.ends_with(&format!(".{qualifier}"))
+                })
+        }
+    }
+}
+
+/// A scan that kept few of the rows it read, on the columns of its filter.
+fn filtered_scan(node: &Node) -> Option<Suggestion> {
+    let table = node.relation.clone()?;
+    let filter = node.detail("Filter")?;
+    let removed = removed(node, "Rows Removed by Filter");
+    let kept = node.actual_rows? * node.loops;
+    if removed < MIN_REMOVED || removed <= kept {
+        return None;
+    }
+    let columns = columns(filter, |qualifier| owns(node, qualifier));
+    (!columns.is_empty()).then(|| Suggestion {
+        why: format!(
+            "Seq Scan on {table} read {:.0} rows to keep {kept:.0}, Filter: {filter}",
+            removed + kept
+        ),
+        table,
+        columns,
+    })
+}
+
+/// A nested loop that threw away most of what it joined, on the columns
+/// of its join filter of the table it scanned sequentially over and over.
+fn join_filter(plan: &Plan, index: usize, node: &Node) -> Vec<Suggestion> {
+    let Some(filter) = node.detail("Join Filter") else {
+        return Vec::new();
+    };
+    let removed = removed(node, "Rows Removed by Join Filter");
+    if removed < MIN_REMOVED {
+        return Vec::new();
+    }
+    plan.nodes[index + 1..plan.subtree_end(index)]
+        .iter()
+        // The inner side, scanned again for each row of the outer one.
+        .filter(|scan| scan.node_type == "Seq Scan" && scan.loops > 1.0)
+        .filter_map(|scan| {
+            let table = scan.relation.clone()?;
+            // Unqualified, a column could be of either side.
+            let columns = columns(filter, |qualifier| {
+                qualifier.is_some() && owns(scan, qualifier)
+            });
+            (!columns.is_empty()).then(|| Suggestion {
+                why: format!(
+                    "Nested Loop removed {removed:.0} rows scanning {table} {:.0} times, \
+                     Join Filter: {filter}",
+                    scan.loops
+                ),
+                table,
+                columns,
+            })
+        })
+        .collect()
+}
+
+/// The columns `condition` compares that `owns` says are of the table,
+/// those compared for equality first. Columns inside a function call are
+/// left out, an index on them wouldn't serve it.
+pub fn columns(condition: &str, owns: impl Fn(Option<&str>) -> bool) -> Vec<String> {
+    let words: Vec<&str> = statements::words(condition)
+        .into_iter()
+        .map(|(_, word)| word)
+        .collect();
+    let at = |i: usize| words.get(i).copied().unwrap_or_default();
+    let before = |i: usize| i.checked_sub(1).map(at).unwrap_or_default();
+    let is_name = |word: &str| {
+        word.starts_with(|c: char| c.is_alphabetic() || c == '_' || c == '"')
+            && !KEYWORDS.iter().any(|k| word.eq_ignore_ascii_case(k))
+    };
+    let (mut equal, mut range) = (Vec::new(), Vec::new());
+    // Whether each open paren is that of a function call.
+    let mut parens: Vec<bool> = Vec::new();
+    for (i, word) in words.iter().enumerate() {
+        match *word {
+            "(" => parens.push(is_name(before(i))),
+            ")" => {
+                parens.pop();
+            }
+            _ if !is_name(word)
+                || before(i) == ":"
+                || at(i + 1) == "("
+                || parens.iter().any(|call| *call) =>
+            {
+                continue;
+            }
+            _ => {
+                let (qualifier, column) = match word.rsplit_once('.') {
+                    Some((qualifier, column)) => (Some(qualifier), column),
+                    None => (None, *word),
+                };
+                if !owns(qualifier) {
+                    continue;
+                }
+                let kind = match (at(i + 1), at(i + 2)) {
+                    ("=", _) => Some(Compared::Equal),
+                    ("<", ">") | ("!", _) => None,
+                    ("<" | ">" | "~", _) => Some(Compared::Range),
+                    _ => match (before(i), i.checked_sub(2).map(at).unwrap_or_default()) {
+                        ("=", "!") | (">", "<") => None,
+                        ("=", "<" | ">") => Some(Compared::Range),
+                        ("=", _) => Some(Compared::Equal),
+                        ("<" | ">" | "~", _) => Some(Compared::Range),
+                        _ => None,
+                    },
+                };
+                let column = column.to_string();
+                match kind {
+                    Some(Compared::Equal) if !equal.contains(&column) => equal.push(column),
+                    Some(Compared::Range) if !range.contains(&column) => range.push(column),
+                    _ => {}
+                }
+            }
+        }
+    }
+    range.retain(|column| !equal.contains(column));
+    equal.extend(range);
+    equal
+}
@@ -0,0 +1,182 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Swap files, so a crash doesn't take unsaved buffers along with it.
+//!
+//! Every few seconds each modified buffer is written to
+//! `$XDG_STATE_HOME/dbvi/swap/<pid>-<buffer id>.swp`: its name, its file (or
+//! an empty line) and its text. A clean exit removes them; whatever is left
+//! from a process that is no longer running can be recovered. Each process
+//! holds a lock on `<pid>.lock` next to them while it runs, which is how
+//! the others tell.
+
+use std::collections::HashMap;
+use std::fs::{self, File, TryLockError};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::config;
+use crate::editor::Buffer;
+
+const INTERVAL: Duration = Duration::from_secs(4);
+
+pub fn swap_dir() -> Option<PathBuf> {
+    config::state_dir().map(|dir| dir.join("swap"))
+}
+
+/// The swap files of this process.
+#[derive(Debug)]
+pub struct Swap {
+    dir: PathBuf,
+    /// What was last written for each buffer id.
+    written: HashMap<usize, String>,
+    saved_at: Instant,
+    /// Locked for as long as the process runs.
+    _lock: File,
+}
+
+impl Swap {
+    pub fn new(dir: PathBuf) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        remove_stale_locks(&dir);
+        let lock = File::create(lock_path(&dir, std::process::id()))?;
+        lock.try_lock().map_err(io::Error::from)?;
+        Ok(Self {
+            dir,
+            written: HashMap::new(),
+            saved_at: Instant::now(),
+            _lock: lock,
+        })
+    }
+
+    fn path(&self, id: usize) -> PathBuf {
+        self.dir.join(format!("{}-{id}.swp", std::process::id()))
+    }
+
+    /// Writes the buffers that changed since the last save if it's time
+    /// to, and drops the swap files of buffers saved or closed since.
+    pub fn autosave(&mut self, buffers: &[Buffer]) -> io::Result<()> {
+        if self.saved_at.elapsed() < INTERVAL {
+            return Ok(());
+        }
+        self.saved_at = Instant::now();
+
+        let mut written = HashMap::new();
+        for buffer in buffers.iter().filter(|b| b.modified && !b.read_only) {
+            let path = buffer
+                .path
+                .as_ref()
+                .map(|path| path.display().to_string())
+                .unwrap_or_default();
+            let contents = format!("{}\n{path}\n{}", buffer.name, buffer.text());
+            if self.written.get(&buffer.id) != Some(&contents) {
+                fs::write(self.path(buffer.id), &contents)?;
+            }
+            written.insert(buffer.id, contents);
+        }
+        for id in self.written.keys().filter(|id| !written.contains_key(id)) {
+            let _ = fs::remove_file(self.path(*id));
+        }
+        self.written = written;
+        Ok(())
+    }
+
+    /// When [`autosave`](Self::autosave) next has something to do, `None`
+    /// with no buffer to write nor swap file to drop.
+    pub fn due(&self, buffers: &[Buffer]) -> Option<Instant> {
+        let unsaved = buffers.iter().any(|b| b.modified && !b.read_only);
+        (unsaved || !self.written.is_empty()).then(|| self.saved_at + INTERVAL)
+    }
+
+    /// Removes every swap file of this process, on a clean exit.
+    pub fn remove_all(&mut self) {
+        for id in std::mem::take(&mut self.written).into_keys() {
+            let _ = fs::remove_file(self.path(id));
+        }
+        // Windows won't remove it while open; a later start clears it.
+        let _ = fs::remove_file(lock_path(&self.dir, std::process::id()));
+    }
+}
+
+fn lock_path(dir: &Path, pid: u32) -> PathBuf {
+    dir.join(format!("{pid}.lock"))
+}
+
+/// Whether process `pid` still holds its lock. Without a lock file it never
+/// had swap files or exited cleanly; when in doubt, it is running, as its
+/// swap files are then left alone.
+fn is_running(dir: &Path, pid: u32) -> bool {
+    match File::open(lock_path(dir, pid)) {
+        Ok(file) => matches!(file.try_lock_shared(), Err(TryLockError::WouldBlock)),
+        Err(err) => err.kind() != io::ErrorKind::NotFound,
+    }
+}
+
+/// Removes the lock files of processes that are gone and left no swap
+/// files behind.
+fn remove_stale_locks(dir: &Path) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let names: Vec<String> = entries
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .collect();
+    for name in &names {
+        let Some(pid) = name
+            .strip_suffix(".lock")
+            .and_then(|pid| pid.parse::<u32>().ok())
+        else {
+            continue;
+        };
+        let prefix = format!("{pid}-");
+        if pid != std::process::id()
+            && !names.iter().any(|name| name.starts_with(&prefix))
+            && !is_running(dir, pid)
+        {
+            let _ = fs::remove_file(dir.join(name));
+        }
+    }
+}
+
+/// Swap files left behind by processes that are no longer running.
+pub fn leftovers(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut leftovers: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            let pid = path
+                .file_name()
+                .and_then(|name| name.to_str()?.strip_suffix(".swp")?.split_once('-'))
+                .and_then(|(pid, _)| pid.parse::<u32>().ok());
+            pid.is_some_and(|pid| pid != std::process::id() && !is_running(dir, pid))
+        })
+        .collect();
+    leftovers.sort();
+    leftovers
+}
+
+/// Reads a swap file back into a (modified) buffer.
+pub fn recover(path: &Path) -> io::Result<Buffer> {
+    let contents = fs::read_to_string(path)?;
+    let mut parts = contents.splitn(3, '\n');
+    let name = parts.next().unwrap_or_default();
+    let file = parts.next().unwrap_or_default();
+    let mut buffer = Buffer::from_text(name, parts.next().unwrap_or_default());
+    buffer.path = (!file.is_empty()).then(|| PathBuf::from(file));
+    buffer.modified = true;
+    Ok(buffer)
+}
@@ -0,0 +1,78 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The results of a batch, one tab for each statement that returned rows,
+//! switched between with `]r` and `[r`.
+
+use crate::results::ResultSet;
+
+#[derive(Debug)]
+struct Tab {
+    sql: String,
+    /// `None` while the tab is the one shown, its results in the grid.
+    results: Option<ResultSet>,
+}
+
+#[derive(Debug)]
+pub struct Tabs {
+    tabs: Vec<Tab>,
+    pub current: usize,
+}
+
+impl Tabs {
+    /// Tabs for the `(statement, results)` of a batch, with the last one
+    /// shown: its results are returned to go in the grid. `None` with fewer
+    /// than two result sets.
+    pub fn new(mut results: Vec<(String, ResultSet)>) -> Option<(Self, ResultSet)> {
+        if results.len() < 2 {
+            return None;
+        }
+        let (sql, shown) = results.pop()?;
+        let mut tabs: Vec<Tab> = results
+            .into_iter()
+            .map(|(sql, results)| Tab {
+                sql,
+                results: Some(results),
+            })
+            .collect();
+        tabs.push(Tab { sql, results: None });
+        let current = tabs.len() - 1;
+        Some((Self { tabs, current }, shown))
+    }
+
+    pub fn len(&self) -> usize {
+        self.tabs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tabs.is_empty()
+    }
+
+    /// The statement the results shown came from.
+    pub fn sql(&self) -> &str {
+        &self.tabs[self.current].sql
+    }
+
+    /// Puts `shown` back in its tab and moves `by` tabs, wrapping around,
+    /// returning the results to show instead.
+    pub fn switch(&mut self, shown: ResultSet, by: isize) -> ResultSet {
+        self.tabs[self.current].results = Some(shown);
+        let len = self.tabs.len() as isize;
+        self.current = (self.current as isize + by).rem_euclid(len) as usize;
+        self.tabs[self.current]
+            .results
+            .take()
+            .expect("only the tab shown has no results")
+    }
+}
@@ -0,0 +1,189 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `:report <template>`: saved queries rendered through a Tera template
+//! into Markdown or HTML.
+//!
+//! The first line of the template names the queries it uses, as
+//! `{# queries: revenue = weekly/revenue, top_customers #}`, a Tera
+//! comment so it renders to nothing. Each is there under its alias, or
+//! under the last part of its name, as an object with `columns`, `rows`,
+//! `records` (one object per row, by column name), `count` and `table`, the
+//! whole result as a Markdown table or, for HTML, an HTML one to put in
+//! with `| safe`. `database` and `generated` say where and when.
+
+use serde_json::{Map, Value, json};
+use tera::{Context, Tera};
+
+use crate::export;
+use crate::results::ResultSet;
+
+const QUERIES: &str = "queries:";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    /// The alias each query is under in the template, then its name in the
+    /// library.
+    pub queries: Vec<(String, String)>,
+    body: String,
+}
+
+impl Template {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let first = source.lines().next().unwrap_or_default().trim();
+        let list = first
+            .strip_prefix("{#")
+            .and_then(|first| first.strip_suffix("#}"))
+            .and_then(|comment| comment.trim().strip_prefix(QUERIES))
+            .ok_or("The template's first line has to name its queries, as {# queries: a, b #}")?;
+        let mut queries = Vec::new();
+        for entry in list.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (alias, name) = match entry.split_once('=') {
+                Some((alias, name)) => (alias.trim().to_string(), name.trim().to_string()),
+                None => (alias_of(entry), entry.to_string()),
+            };
+            if !is_identifier(&alias) {
+                return Err(format!(
+                    "{alias} can't be a name in a template, give {name} one: alias = {name}"
+                ));
+            }
+            if queries.iter().any(|(taken, _)| *taken == alias) {
+                return Err(format!("Two queries are called {alias}"));
+            }
+            queries.push((alias, name));
+        }
+        if queries.is_empty() {
+            return Err("The template names no queries".into());
+        }
+        // The line naming the queries would leave an empty one behind.
+        let body = source.split_once('\n').map_or("", |(_, body)| body);
+        Ok(Self {
+            queries,
+            body: body.to_string(),
+        })
+    }
+
+    /// The template with `results`, one per query in order, HTML escaped
+    /// if `html`.
+    pub fn render(
+        &self,
+        results: &[ResultSet],
+        database: &str,
+        generated: &str,
+        html: bool,
+    ) -> Result<String, String> {
+        let mut context = Context::new();
+        context.insert("database", database);
+        context.insert("generated", generated);
+        for ((alias, _), results) in self.queries.iter().zip(results) {
+            context.insert(alias.as_str(), &value(results, html)?);
+        }
+        Tera::one_off(&self.body, &context, html).map_err(|err| describe(&err))
+    }
+}
+
+/// Where the report from `template` goes when not told: the template's
+/// path without `.tera`, so `weekly.md.tera` makes `weekly.md`.
+pub fn output_path(template: &str) -> Option<&str> {
+    template
+        .strip_suffix(".tera")
+        .filter(|output| !output.is_empty() && !output.ends_with('/'))
+}
+
+/// Whether the report at `path` is HTML rather than Markdown.
+pub fn is_html(path: &str) -> bool {
+    let path = path.to_ascii_lowercase();
+    path.ends_with(".html") || path.ends_with(".htm")
+}
+
+/// `weekly/top-customers` is `top_customers` in the template.
+fn alias_of(name: &str) -> String {
+    name.rsplit('/').next().unwrap_or(name).replace('-', "_")
+}
+
+fn is_identifier(name: &str) -> bool {
+    name.chars()
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Integers, floats and booleans stay so for the template's filters and
+/// tests; everything else is a string, `numeric` too so as to keep its
+/// digits, and `NULL` is `null`.
+fn cell(results: &ResultSet, col: usize, cell: Option<&str>) -> Value {
+    let Some(text) = cell else {
+        return Value::Null;
+    };
+    let parsed = match results.columns[col].ty.as_str() {
+        "bool" => Some(Value::Bool(text == "t")),
+        "int2" | "int4" | "int8" | "oid" => text.parse::<i64>().ok().map(Value::from),
+        "float4" | "float8" => text
+            .parse()
+            .ok()
+            .and_then(serde_json::Number::from_f64)
+            .map(Value::Number),
+        _ => None,
+    };
+    parsed.unwrap_or_else(|| Value::String(text.into()))
+}
+
+fn value(results: &ResultSet, html: bool) -> Result<Value, String> {
+    let rows: Vec<Vec<Value>> = results
+        .rows
+        .iter()
+        .map(|row| {
+            row.iter()
+                .enumerate()
+                .map(|(col, text)| cell(results, col, text))
+                .collect()
+        })
+        .collect();
+    let records: Vec<Value> = rows
+        .iter()
+        .map(|row| {
+            let record: Map<String, Value> = results
+                .columns
+                .iter()
+                .zip(row)
+                .map(|(column, value)| (column.name.clone(), value.clone()))
+                .collect();
+            Value::Object(record)
+        })
+        .collect();
+    let mut table = Vec::new();
+    match html {
+        true => export::write_html_table(results, false, &mut table),
+        false => export::write_markdown(results, &mut table),
+    }
+    .map_err(|err| err.to_string())?;
+    Ok(json!({
+        "columns": results.columns.iter().map(|column| &column.name).collect::<Vec<_>>(),
+        "rows": rows,
+        "records": records,
+        "count": results.rows.len(),
+        "table": String::from_utf8_lossy(&table),
+    }))
+}
+
+/// Tera's error and what caused it, which is where it says what is wrong.
+fn describe(err: &tera::Error) -> String {
+    let mut text = err.to_string();
+    let mut source = std::error::Error::source(err);
+    while let Some(cause) = source {
+        text += &format!(": {cause}");
+        source = cause.source();
+    }
+    text
+}
@@ -0,0 +1,186 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Text objects, the `(` of `ci(` and the `s` of `das`: a span of the
+//! buffer around the cursor, found in the buffer's text as a whole so they
+//! can span lines. Also the bracket matching, for `%`.
+//!
+//! | object        | inner (`i`)               | around (`a`)               |
+//! |---------------|---------------------------|----------------------------|
+//! | `'` `"`       | the literal's contents    | with its quotes            |
+//! | `(` `)` `b`   | between the parens        | with the parens            |
+//! | `[` `]`       | between the brackets      | with the brackets          |
+//! | `s`           | the statement, no `;`     | with its `;` and the space after |
+//! | `q`           | the subquery, trimmed     | with its parens            |
+
+use std::ops::Range;
+
+use crate::statements::{self, skip_quoted};
+
+/// Byte range of `object` around byte `at` of `text`, its inside if
+/// `inner`.
+pub fn find(text: &str, at: usize, object: char, inner: bool) -> Option<Range<usize>> {
+    match object {
+        '\'' | '"' => quoted(text, at, object as u8, inner),
+        '(' | ')' | 'b' => enclosing(text, at, b'(', b')', inner, |_| true),
+        '[' | ']' => enclosing(text, at, b'[', b']', inner, |_| true),
+        'q' => enclosing(text, at, b'(', b')', inner, is_query)
+            .map(|range| if inner { trim(text, range) } else { range }),
+        's' => statement(text, at, inner),
+        _ => None,
+    }
+}
+
+/// The literal or quoted identifier the cursor is in, or else the next one
+/// on its line, like vim.
+fn quoted(text: &str, at: usize, quote: u8, inner: bool) -> Option<Range<usize>> {
+    let line_end = text[at..].find('\n').map_or(text.len(), |index| at + index);
+    let mut i = 0;
+    while i < line_end {
+        let Some(end) = skip_quoted(text, i) else {
+            i += 1;
+            continue;
+        };
+        // Tokens come in order, so the first one past the cursor is either
+        // around it or the next one on the line.
+        if text.as_bytes()[i] == quote && end > at {
+            let closed = end - i >= 2 && text.as_bytes()[end - 1] == quote;
+            return Some(match (inner, closed) {
+                (true, true) => i + 1..end - 1,
+                (true, false) => i + 1..end,
+                (false, _) => i..end,
+            });
+        }
+        i = end;
+    }
+    None
+}
+
+/// Byte offsets of every matched `open` and `close` in `text`, outside of
+/// literals and comments, in the order they close.
+fn pairs(text: &str, open: u8, close: u8) -> Vec<(usize, usize)> {
+    let bytes = text.as_bytes();
+    let mut opened = Vec::new();
+    let mut pairs = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        if let Some(end) = skip_quoted(text, i) {
+            i = end;
+            continue;
+        }
+        if bytes[i] == open {
+            opened.push(i);
+        } else if bytes[i] == close
+            && let Some(start) = opened.pop()
+        {
+            pairs.push((start, i));
+        }
+        i += 1;
+    }
+    pairs
+}
+
+/// Where the bracket matching the one at byte `at` is, or the one matching
+/// the first bracket after `at` on its line, like `%`.
+pub fn matching(text: &str, at: usize) -> Option<usize> {
+    let line_end = text[at..].find('\n').map_or(text.len(), |index| at + index);
+    let bracket = text[at..line_end].find(['(', ')', '[', ']'])? + at;
+    let (open, close) = match text.as_bytes()[bracket] {
+        b'(' | b')' => (b'(', b')'),
+        _ => (b'[', b']'),
+    };
+    pairs(text, open, close)
+        .into_iter()
+        .find_map(|(start, end)| match bracket {
+            _ if bracket == start => Some(end),
+            _ if bracket == end => Some(start),
+            _ => None,
+        })
+}
+
+/// The innermost `open`...`close` pair around `at` whose contents pass
+/// `accept`.
+fn enclosing(
+    text: &str,
+    at: usize,
+    open: u8,
+    close: u8,
+    inner: bool,
+    accept: impl Fn(&str) -> bool,
+) -> Option<Range<usize>> {
+    pairs(text, open, close)
+        .into_iter()
+        .filter(|(start, end)| *start <= at && at <= *end && accept(&text[start + 1..*end]))
+        .max_by_key(|(start, _)| *start)
+        .map(|(start, end)| start..end + 1)
+        .map(|range| {
+            if inner {
+                range.start + 1..range.end - 1
+            } else {
+                range
+            }
+        })
+}
+
+fn is_query(text: &str) -> bool {
+    let keyword = text
+        .trim_start()
+        .split(|c: char| !c.is_alphanumeric())
+        .next()
+        .unwrap_or_default();
+    ["select", "with", "values", "table"]
+        .iter()
+        .any(|k| keyword.eq_ignore_ascii_case(k))
+}
+
+/// `range` of `text` without leading and trailing whitespace.
+fn trim(text: &str, range: Range<usize>) -> Range<usize> {
+    let slice = &text[range.clone()];
+    let start = range.start + (slice.len() - slice.trim_start().len());
+    let end = range.end - (slice.len() - slice.trim_end().len());
+    start..end.max(start)
+}
+
+/// The statement the cursor is in, or the one before it if the cursor is
+/// between statements.
+fn statement(text: &str, at: usize, inner: bool) -> Option<Range<usize>> {
+    let base = text.as_ptr() as usize;
+    let ranges: Vec<Range<usize>> = statements::split(text)
+        .into_iter()
+        .map(|statement| {
+            let start = statement.as_ptr() as usize - base;
+            start..start + statement.len()
+        })
+        .collect();
+    let range = ranges
+        .iter()
+        .rev()
+        .find(|range| range.start <= at)
+        .or_else(|| ranges.first())?
+        .clone();
+    if inner {
+        return Some(range);
+    }
+    let rest = &text[range.end..];
+    let after = rest.trim_start();
+    let mut end = range.end + (rest.len() - after.len());
+    if after.starts_with(';') {
+        end += 1;
+        let rest = &text[end..];
+        end += rest.len() - rest.trim_start().len();
+    } else {
+        end = range.end;
+    }
+    Some(range.start..end)
+}
@@ -0,0 +1,154 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `:copy-to <target> <table>`: rows from this connection into a table on
+//! another, with `COPY` at both ends. The buffer's query streams straight
+//! through without being held; the results shown go over in batches.
+
+use std::time::{Duration, Instant};
+
+use futures_util::StreamExt;
+use sqlx::PgPool;
+use sqlx::postgres::PgPoolCopyExt;
+
+use crate::config::Profile;
+use crate::results::{Column, ResultSet};
+use crate::statements::quote_ident;
+
+/// Rows of the results sent at a time.
+pub const BATCH_ROWS: usize = 1000;
+
+/// How often progress is reported while streaming a query.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Where `:copy-to` takes its rows from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    /// The buffer's query, streamed.
+    Query,
+    /// The results shown.
+    Results,
+}
+
+/// Why rows can't go into the profile `name`, if they can't: a read-only
+/// profile is never written to, prod or not.
+pub fn refusal(name: &str, profile: &Profile) -> Option<String> {
+    profile.read_only().then(|| {
+        format!("{name} is read-only, set read_only = false in its profile to copy into it")
+    })
+}
+
+/// The `COPY` into `columns` of `table` that the rows are sent to, in
+/// `COPY`'s text format.
+pub fn copy_in_statement(table: &str, columns: &[Column]) -> String {
+    let columns: Vec<String> = columns.iter().map(|c| quote_ident(&c.name)).collect();
+    format!("COPY {table} ({}) FROM STDIN", columns.join(", "))
+}
+
+/// The statement that creates `table` for `columns` when the target lacks
+/// it, typed as they were read.
+pub fn create_table(table: &str, columns: &[Column]) -> String {
+    let names: Vec<String> = columns.iter().map(|c| c.name.clone()).collect();
+    let types: Vec<String> = columns.iter().map(|c| c.ty.clone()).collect();
+    crate::import::create_table(table, &names, &types)
+}
+
+/// `rows` of `results` as `COPY` text: tab separated, `\N` for `NULL` and
+/// backslash escapes for what would break a line or a field.
+pub fn encode(results: &ResultSet, rows: std::ops::Range<usize>) -> Vec<u8> {
+    let mut out = String::new();
+    for index in rows {
+        let Some(row) = results.rows.get(index) else {
+            break;
+        };
+        for (col, cell) in row.iter().enumerate() {
+            if col > 0 {
+                out.push('\t');
+            }
+            match cell {
+                None => out.push_str("\\N"),
+                Some(value) => {
+                    for c in value.chars() {
+                        match c {
+                            '\\' => out.push_str("\\\\"),
+                            '\t' => out.push_str("\\t"),
+                            '\n' => out.push_str("\\n"),
+                            '\r' => out.push_str("\\r"),
+                            c => out.push(c),
+                        }
+                    }
+                }
+            }
+        }
+        out.push('\n');
+    }
+    out.into_bytes()
+}
+
+/// Sends `results` to `statement`, a [`copy_in_statement`], on `target` in
+/// batches of [`BATCH_ROWS`], calling `progress` with the rows sent after
+/// each. Returns the rows loaded.
+pub async fn send_results(
+    target: &PgPool,
+    statement: &str,
+    results: &ResultSet,
+    mut progress: impl FnMut(u64),
+) -> Result<u64, sqlx::Error> {
+    let mut copy = target.copy_in_raw(statement).await?;
+    let total = results.rows.len();
+    let mut sent = 0;
+    while sent < total {
+        let end = (sent + BATCH_ROWS).min(total);
+        copy.send(encode(results, sent..end)).await?;
+        sent = end;
+        progress(sent as u64);
+    }
+    copy.finish().await
+}
+
+/// Streams the output of `query` on `source` to `statement`, a
+/// [`copy_in_statement`], on `target`, calling `progress` with the rows
+/// sent so far every so often. Returns the rows loaded.
+pub async fn stream_query(
+    source: &PgPool,
+    query: &str,
+    target: &PgPool,
+    statement: &str,
+    mut progress: impl FnMut(u64),
+) -> Result<u64, sqlx::Error> {
+    let query = query.trim().trim_end_matches(';');
+    let mut rows = source
+        .copy_out_raw(&format!("COPY ({query}\n) TO STDOUT"))
+        .await?;
+    let mut copy = target.copy_in_raw(statement).await?;
+    let mut sent = 0;
+    let mut reported = Instant::now();
+    while let Some(chunk) = rows.next().await {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(err) => {
+                let _ = copy.abort(err.to_string()).await;
+                return Err(err);
+            }
+        };
+        // Line breaks in values are escaped, so each one ends a row.
+        sent += chunk.iter().filter(|&&byte| byte == b'\n').count() as u64;
+        copy.send(chunk).await?;
+        if reported.elapsed() >= PROGRESS_INTERVAL {
+            reported = Instant::now();
+            progress(sent);
+        }
+    }
+    copy.finish().await
+}
@@ -0,0 +1,155 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `dbvi --tutor`, lessons in the style of vimtutor on the demo shop. Each
+//! lesson says what to do in a box above the results and checks the state
+//! after every key to know when it has been done.
+
+use ratatui::{
+    Frame,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::Line,
+    widgets::{Block, Borders, Paragraph, Wrap},
+};
+
+use crate::state::{Mode, Pane, State};
+
+pub struct Lesson {
+    pub title: &'static str,
+    pub text: &'static [&'static str],
+    /// Whether the lesson has been done; the last one never is.
+    done: fn(&State) -> bool,
+}
+
+pub const LESSONS: &[Lesson] = &[
+    Lesson {
+        title: "Modes",
+        text: &[
+            "dbvi is modal, like vim: keys move and act in Normal mode, and type in Insert mode.",
+            "Press i for Insert mode, type  select * from customers  and press Esc to go back.",
+        ],
+        done: |state| {
+            let text = state.buffer().text().to_lowercase();
+            state.mode == Mode::Normal && text.contains("select") && text.contains("customers")
+        },
+    },
+    Lesson {
+        title: "Running queries",
+        text: &["In Normal mode, Enter runs the buffer. Press Enter."],
+        done: |state| state.results.is_some(),
+    },
+    Lesson {
+        title: "Moving around the results",
+        text: &[
+            "Ctrl-w k moves into the results, Ctrl-w j back to the editor.",
+            "In the results, h j k l move as in vim and a count repeats: go to the fifth row with 4j.",
+        ],
+        done: |state| state.focus == Pane::Results && state.grid.row == 4,
+    },
+    Lesson {
+        title: "Sorting",
+        text: &[
+            "Move to the name column with l and press s to sort by it. Again sorts the other way.",
+        ],
+        done: |state| state.grid.sort.is_some_and(|(col, _)| col == 1),
+    },
+    Lesson {
+        title: "Yanking",
+        text: &[
+            "y yanks the cell under the cursor, or everything selected after v.",
+            "Yank a name, go back with Ctrl-w j and press p to paste it after the query.",
+        ],
+        done: |state| {
+            let register = &state.register.text;
+            state.focus == Pane::Editor
+                && !register.is_empty()
+                && state.buffer().lines.len() == 1
+                && state.buffer().text().ends_with(register.as_str())
+        },
+    },
+    Lesson {
+        title: "Exporting",
+        text: &[
+            "Commands start with : in Normal mode. Write the results to a file with",
+            ":export csv /tmp/customers.csv  (or :export table for aligned text).",
+        ],
+        done: |state| state.status.starts_with("Exported"),
+    },
+    Lesson {
+        title: "Done",
+        text: &[
+            "That's the basics. Try :tables, or select * from orders, and :q to quit.",
+            "Everything here is made up, so nothing can break.",
+        ],
+        done: |_| false,
+    },
+];
+
+#[derive(Debug, Default)]
+pub struct Tutor {
+    lesson: usize,
+}
+
+impl Tutor {
+    pub fn lesson(&self) -> &'static Lesson {
+        &LESSONS[self.lesson]
+    }
+
+    /// Moves on if the lesson has been done, saying so.
+    pub fn check(&mut self, state: &mut State) {
+        if (self.lesson().done)(state) {
+            state.status = format!("Lesson {} done: {}", self.lesson + 1, self.lesson().title);
+            self.lesson += 1;
+        }
+    }
+
+    /// Lines the lesson box takes at `width`, with its borders.
+    pub fn height(&self, width: u16) -> u16 {
+        let width = width.saturating_sub(2).max(1) as usize;
+        let lines: usize = self
+            .lesson()
+            .text
+            .iter()
+            .map(|line| line.chars().count().div_ceil(width).max(1))
+            .sum();
+        lines as u16 + 2
+    }
+
+    pub fn render(&self, f: &mut Frame, area: Rect) {
+        let lesson = self.lesson();
+        let title = format!(
+            "Tutor {}/{}: {}",
+            self.lesson + 1,
+            LESSONS.len(),
+            lesson.title
+        );
+        let text: Vec<Line> = lesson.text.iter().map(|line| Line::from(*line)).collect();
+        let block = Block::default()
+            .title(Line::from(title).centered())
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Yellow))
+            .title_style(Style::default().add_modifier(Modifier::BOLD));
+        f.render_widget(
+            Paragraph::new(text).wrap(Wrap { trim: false }).block(block),
+            area,
+        );
+    }
+}
+
+impl std::fmt::Debug for Lesson {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.title)
+    }
+}
@@ -0,0 +1,569 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Drawing the state into a frame.
+
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crossterm::cursor::SetCursorStyle;
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Direction, Flex, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph},
+};
+
+use crate::editor::Cursor;
+use crate::grid::Look;
+use crate::state::{Mode, Pane, State};
+use crate::{editor, highlight, lint, textobject, width};
+
+/// Below this size the panes would not fit, and a notice is drawn instead.
+pub const MIN_WIDTH: u16 = 30;
+pub const MIN_HEIGHT: u16 = 10;
+/// Narrower than this, the margin around the panes goes and the status line
+/// keeps what fits of it.
+pub const NARROW_WIDTH: u16 = 60;
+/// Shorter than this, only the focused pane is drawn; `<C-w>` switches to
+/// the other one.
+pub const SHORT_HEIGHT: u16 = 16;
+
+/// `:set plain`, for the prompts that are drawn without the state.
+static PLAIN: AtomicBool = AtomicBool::new(false);
+
+pub fn draw_ui(f: &mut ratatui::Frame, state: &mut State) {
+    draw(f, state);
+    if state.plain {
+        flatten(f.buffer_mut());
+    }
+}
+
+/// The cursor shape of `mode`: a bar where typing puts text in, an
+/// underline where it goes over text and a block otherwise.
+pub(crate) fn cursor_style(mode: Mode) -> SetCursorStyle {
+    match mode {
+        Mode::Normal => SetCursorStyle::SteadyBlock,
+        Mode::Insert | Mode::Command | Mode::Search => SetCursorStyle::SteadyBar,
+        Mode::Replace => SetCursorStyle::SteadyUnderScore,
+    }
+}
+
+pub(crate) fn set_plain(on: bool) {
+    PLAIN.store(on, Ordering::Relaxed);
+}
+
+/// Flattens a prompt drawn into `f` as `draw_ui` does the rest, should
+/// `:set plain` be on.
+pub(crate) fn finish(f: &mut ratatui::Frame) {
+    if PLAIN.load(Ordering::Relaxed) {
+        flatten(f.buffer_mut());
+    }
+}
+
+/// Takes the colors and box drawing out of what was drawn, for `:set
+/// plain`. Text that stood out by its background is shown reversed, so
+/// the cursor and selections still show.
+fn flatten(buffer: &mut Buffer) {
+    for cell in &mut buffer.content {
+        let highlighted = cell.bg != Color::Reset || cell.modifier.contains(Modifier::REVERSED);
+        let mut style = Style::reset();
+        if highlighted {
+            style = style.add_modifier(Modifier::REVERSED);
+        }
+        cell.set_style(style);
+        let mut chars = cell.symbol().chars();
+        if let (Some(c), None) = (chars.next(), chars.next())
+            && let Some(plain) = ascii(c)
+        {
+            cell.set_char(plain);
+        }
+    }
+}
+
+/// The ASCII drawn for a line, block or bullet character.
+fn ascii(c: char) -> Option<char> {
+    match c {
+        '─' | '━' | '═' | '┄' | '┅' | '┈' | '┉' | '╌' | '╍' | '╴' | '╶' | '╸' | '╺' => {
+            Some('-')
+        }
+        '│' | '┃' | '║' | '┆' | '┇' | '┊' | '┋' | '╎' | '╏' | '╵' | '╷' | '╹' | '╻' => {
+            Some('|')
+        }
+        '\u{2500}'..='\u{257f}' => Some('+'),
+        '\u{2580}'..='\u{259f}' => Some('#'),
+        '\u{2801}'..='\u{28ff}' | '●' | '•' => Some('*'),
+        '\u{2800}' => Some(' '),
+        '…' => Some('~'),
+        '↗' => Some('/'),
+        '↘' => Some('\\'),
+        '↵' => Some('$'),
+        '·' => Some('.'),
+        '×' => Some('x'),
+        '▲' | '↑' => Some('^'),
+        '▼' | '▾' | '↓' => Some('v'),
+        '▶' | '▸' | '→' => Some('>'),
+        '◀' | '←' => Some('<'),
+        _ => None,
+    }
+}
+
+fn draw(f: &mut ratatui::Frame, state: &mut State) {
+    let area = f.area();
+    if area.width < MIN_WIDTH || area.height < MIN_HEIGHT {
+        let text = vec![
+            Line::from("Terminal too small"),
+            Line::from(format!(
+                "{}x{}, needs {MIN_WIDTH}x{MIN_HEIGHT}",
+                area.width, area.height
+            )),
+        ];
+        let [area] = Layout::vertical([Constraint::Length(2)])
+            .flex(Flex::Center)
+            .areas(area);
+        f.render_widget(
+            Paragraph::new(text)
+                .centered()
+                .style(Style::default().fg(Color::Yellow)),
+            area,
+        );
+        return;
+    }
+    let resized = std::mem::take(&mut state.resized);
+    if resized {
+        state.grid.resized();
+    }
+
+    let narrow = area.width < NARROW_WIDTH;
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(if narrow { 0 } else { 1 })
+        .constraints([
+            Constraint::Min(5),    // body
+            Constraint::Length(2), // footer command input
+        ])
+        .split(f.area());
+    let mut body = chunks[0];
+    if let Some(tutor) = &state.tutor {
+        let [lesson, rest] = Layout::vertical([
+            Constraint::Length(tutor.height(body.width)),
+            Constraint::Min(0),
+        ])
+        .areas(body);
+        tutor.render(f, lesson);
+        body = rest;
+    }
+    // Never out of sight while connected to production.
+    if let Some(env) = state.environment.filter(|env| env.is_prod())
+        && state.backend.is_none()
+    {
+        let [banner, rest] =
+            Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(body);
+        let options = &state.session.options;
+        let text = format!(
+            "PROD  {} {}@{}",
+            options.get_database().unwrap_or(options.get_username()),
+            options.get_username(),
+            options.get_host(),
+        );
+        f.render_widget(
+            Paragraph::new(text).centered().style(
+                Style::default()
+                    .fg(Color::Black)
+                    .bg(env.color)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            banner,
+        );
+        body = rest;
+    }
+    let [results_area, editor_area] = match state.focus {
+        _ if area.height >= SHORT_HEIGHT => Layout::vertical([
+            Constraint::Percentage(state.split),
+            Constraint::Percentage(100 - state.split),
+        ])
+        .areas(body),
+        Pane::Results => [
+            body,
+            Rect {
+                y: body.bottom(),
+                height: 0,
+                ..body
+            },
+        ],
+        Pane::Editor => [Rect { height: 0, ..body }, body],
+    };
+    state.results_area = results_area;
+    state.editor_area = editor_area;
+
+    let title = match (&state.plan, &state.results) {
+        _ if state.jobs.shown => format!("Jobs ({} running)", state.jobs.running()),
+        _ if state.queue.shown => format!("Queue ({} waiting)", state.queue.len()),
+        _ if state.quickfix.shown => match state.quickfix.title.as_str() {
+            "" => "Quickfix".into(),
+            title => format!("Quickfix: {title} ({} items)", state.quickfix.len()),
+        },
+        _ if state.library.is_some() => "Saved queries".into(),
+        _ if let Some(elements) = &state.elements => elements.title.clone(),
+        _ if let Some(diff) = &state.row_diff => diff.title.clone(),
+        _ if let Some(compare) = &state.compare => compare.title.clone(),
+        _ if state.plan_diff.is_some() => "Plan diff (old │ new)".into(),
+        _ if state.dashboard.is_some() => "Session statistics".into(),
+        _ if let Some(erd) = &state.erd => format!(
+            "ERD of {} ({} tables, {} keys, {}; - and + zoom)",
+            erd.scope,
+            erd.tables(),
+            erd.keys(),
+            erd.detail.name()
+        ),
+        (Some(_), _) => "Plan".into(),
+        (None, Some(results)) => {
+            let title = match (&state.browser, &state.result_tabs) {
+                _ if let Some((all, ..)) = &state.all_rows => {
+                    format!("Marked rows ({} of {})", results.rows.len(), all.rows.len())
+                }
+                (Some(browser), _) => browser.title(),
+                (None, Some(tabs)) => format!(
+                    "Results {} of {} ({} rows)",
+                    tabs.current + 1,
+                    tabs.len(),
+                    results.rows.len()
+                ),
+                (None, None) => format!("Results ({} rows)", results.rows.len()),
+            };
+            let title = match results.rows.on_disk() {
+                0 => title,
+                rows => format!("{title}, {rows} on disk"),
+            };
+            let title = match &state.results_as_of {
+                Some(as_of) => format!("{title} as of {as_of}"),
+                None => title,
+            };
+            let title = match state.grid.marked.len() {
+                _ if state.all_rows.is_some() => title,
+                0 => title,
+                rows => format!("{title}, {rows} marked"),
+            };
+            match state.edits.as_ref().map(|edits| edits.len()) {
+                None | Some(0) => title,
+                Some(1) => format!("{title}, 1 cell changed"),
+                Some(cells) => format!("{title}, {cells} cells changed"),
+            }
+        }
+        (None, None) => "Results".into(),
+    };
+    let block = Block::default()
+        .title(Line::from(title).centered())
+        .borders(Borders::TOP)
+        .border_style(pane_border(state.focus == Pane::Results));
+    let inner = block.inner(results_area);
+    f.render_widget(block, results_area);
+    let focused = state.focus == Pane::Results;
+    match &state.results {
+        _ if state.jobs.shown => state.jobs.render(f, inner, focused),
+        _ if state.queue.shown => state.queue.render(f, inner, focused),
+        _ if state.quickfix.shown => state.quickfix.render(f, inner, focused),
+        _ if state.library.is_some() => {
+            if let Some(library) = &mut state.library {
+                library.render(f, inner, focused);
+            }
+        }
+        _ if state.elements.is_some() => {
+            if let Some(elements) = &mut state.elements {
+                elements.render(f, inner, focused);
+            }
+        }
+        _ if state.compare.is_some() => {
+            if let Some(compare) = &mut state.compare {
+                compare.render(f, inner, focused);
+            }
+        }
+        _ if state.row_diff.is_some() => {
+            if let Some(diff) = &mut state.row_diff {
+                diff.render(f, inner, focused);
+            }
+        }
+        _ if state.plan_diff.is_some() => {
+            if let Some(diff) = &mut state.plan_diff {
+                diff.render(f, inner, focused);
+            }
+        }
+        _ if let Some(dashboard) = &state.dashboard => {
+            dashboard.render(f, inner, &state.activity);
+        }
+        _ if state.erd.is_some() => {
+            if let Some(erd) = &mut state.erd {
+                erd.render(f, inner);
+            }
+        }
+        _ if state.plan.is_some() => {
+            if let Some(plan) = &mut state.plan {
+                plan.render(f, inner, focused);
+            }
+        }
+        Some(results) => {
+            let mut inner = inner;
+            // Which statement of the batch the rows came from.
+            if let Some(tabs) = &state.result_tabs {
+                let [line, rest] =
+                    Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(inner);
+                let sql = tabs.sql().split_whitespace().collect::<Vec<_>>().join(" ");
+                f.render_widget(
+                    Paragraph::new(sql).style(Style::default().fg(Color::DarkGray)),
+                    line,
+                );
+                inner = rest;
+            }
+            state.grid.render(
+                f,
+                inner,
+                results,
+                focused,
+                state.gutter,
+                Look {
+                    shorten_ids: state.shorten_ids,
+                    timezone: state.timezone,
+                    redactions: &state.redactions,
+                },
+            )
+        }
+        None => f.render_widget(
+            Paragraph::new("Query results will go here...")
+                .style(Style::default().fg(Color::White)),
+            inner,
+        ),
+    }
+    // Of the spilled rows the grid read back in, keep only those on screen.
+    if let Some(results) = &mut state.results {
+        results.rows.page_out(state.grid.shown_rows());
+    }
+
+    draw_editor(f, state, editor_area, resized);
+
+    let footer_text = match state.mode {
+        Mode::Command => Line::from(format!(":{}", state.command_line)),
+        Mode::Search => Line::from(format!("/{}", state.command_line)),
+        Mode::Insert => Line::styled(
+            "-- INSERT --",
+            Style::default().add_modifier(Modifier::BOLD),
+        ),
+        Mode::Replace => Line::styled(
+            "-- REPLACE --",
+            Style::default().add_modifier(Modifier::BOLD),
+        ),
+        _ => match &state.last_ex {
+            Some(line) => Line::styled(format!(":{line}"), Style::default().fg(Color::DarkGray)),
+            None => Line::default(),
+        },
+    };
+    let (left, right) = match narrow {
+        true => state.statusline.fit(state, chunks[1].width),
+        false => state.statusline.render(state),
+    };
+    let footer = Paragraph::new(footer_text).block(
+        Block::default()
+            .title(left)
+            .title(right.right_aligned())
+            .borders(Borders::TOP),
+    );
+    if matches!(state.mode, Mode::Command | Mode::Search) {
+        let cursor_x = chunks[1].x + 1 + width::width(&state.command_line) as u16;
+        f.set_cursor_position((cursor_x, chunks[1].y + 1));
+    }
+    f.render_widget(footer, chunks[1]);
+
+    if let Some(toast) = &state.toast {
+        toast.render(f, body);
+    }
+    if let Some(popup) = &state.popup {
+        popup.render(f);
+    }
+}
+
+/// Borders of the focused pane stand out.
+fn pane_border(focused: bool) -> Style {
+    if focused {
+        Style::default().fg(Color::Cyan)
+    } else {
+        Style::default()
+    }
+}
+
+fn draw_editor(
+    f: &mut ratatui::Frame,
+    state: &mut State,
+    area: ratatui::layout::Rect,
+    resized: bool,
+) {
+    let block = Block::default()
+        .borders(Borders::TOP)
+        .border_style(pane_border(state.focus == Pane::Editor));
+    let inner = block.inner(area);
+    let mode = state.mode;
+    let focused = state.focus == Pane::Editor;
+    let search = state.search.clone();
+    let (gutter, wrap, dialect) = (state.editor_gutter, state.wrap, state.dialect);
+    // Not while typing, when everything is unfinished.
+    let mut diagnostics: Vec<(usize, Range<usize>)> = Vec::new();
+    if state.lint && mode == Mode::Normal {
+        let text = state.buffer().text();
+        diagnostics.extend(
+            lint::lint(&text, &state.schema, &state.search_path)
+                .iter()
+                .map(|diagnostic| diagnostic.locate(&text)),
+        );
+    }
+    if let Some(lsp) = &state.lsp
+        && mode == Mode::Normal
+    {
+        let buffer = state.buffer();
+        diagnostics.extend(lsp.diagnostics(buffer).iter().filter_map(|diagnostic| {
+            let line = buffer.lines.get(diagnostic.row)?;
+            let start = editor::byte_index(line, diagnostic.start);
+            let end = editor::byte_index(line, diagnostic.end.max(diagnostic.start + 1));
+            Some((diagnostic.row, start..end))
+        }));
+    }
+    let buffer = state.buffer_mut();
+    let gutter_width = gutter.width(buffer.lines.len()).min(inner.width);
+    let width = (inner.width - gutter_width) as usize;
+    if resized {
+        buffer.fill_window(inner.height as usize);
+    }
+    if wrap {
+        buffer.scroll_to_cursor_wrapped(inner.height as usize, width);
+    } else {
+        buffer.scroll_to_cursor(inner.height as usize);
+    }
+
+    let mut title = buffer.name.clone();
+    if buffer.read_only {
+        title.push_str(" [RO]");
+    }
+    if buffer.modified {
+        title.push_str(" [+]");
+    }
+    let cursor_row = buffer.cursor.row;
+    // The bracket under the cursor and its match.
+    let at = buffer.offset(buffer.cursor);
+    let text = buffer.text();
+    let brackets: Vec<Cursor> = match text.as_bytes().get(at) {
+        Some(b'(' | b')' | b'[' | b']') => textobject::matching(&text, at)
+            .map(|other| vec![buffer.cursor, buffer.cursor_at(other)])
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    };
+    let mut highlight = highlight::HighlightState::new(dialect);
+    let lines: Vec<Line> = buffer
+        .lines
+        .iter()
+        .enumerate()
+        .map(|(row, line)| {
+            let mut highlighted = highlight::highlight_line(line, &mut highlight);
+            if let Some(search) = &search {
+                let matches: Vec<_> = search.find_iter(line).map(|m| m.range()).collect();
+                highlighted = highlight::mark(highlighted, &matches, search_style());
+            }
+            let brackets: Vec<_> = brackets
+                .iter()
+                .filter(|cursor| cursor.row == row)
+                .map(|cursor| {
+                    let start = editor::byte_index(line, cursor.col);
+                    start..start + 1
+                })
+                .collect();
+            let highlighted = highlight::mark(highlighted, &brackets, bracket_style());
+            let problems: Vec<_> = diagnostics
+                .iter()
+                .filter(|(at, _)| *at == row)
+                .map(|(_, range)| range.clone())
+                .collect();
+            (
+                row,
+                highlight::mark(highlighted, &problems, diagnostic_style()),
+            )
+        })
+        .skip(buffer.scroll)
+        .take(inner.height as usize)
+        .flat_map(|(row, line)| {
+            let lines = if wrap {
+                highlight::wrap(line, width)
+            } else {
+                vec![line]
+            };
+            lines.into_iter().enumerate().map(move |(index, line)| {
+                if gutter_width == 0 {
+                    return line;
+                }
+                // Only the first screen line of a wrapped line is numbered.
+                let label = match index {
+                    0 => gutter.label(row, cursor_row),
+                    _ => String::new(),
+                };
+                let style = if row == cursor_row {
+                    Style::default().fg(Color::Yellow)
+                } else {
+                    Style::default().fg(Color::DarkGray)
+                };
+                let number =
+                    Span::styled(format!("{label:>0$} ", gutter_width as usize - 1), style);
+                Line::from(
+                    std::iter::once(number)
+                        .chain(line.spans)
+                        .collect::<Vec<_>>(),
+                )
+            })
+        })
+        .collect();
+    let editor = Paragraph::new(lines).block(block.title(Line::from(title).centered()));
+    f.render_widget(editor, area);
+
+    if !matches!(mode, Mode::Command | Mode::Search) && focused {
+        let row = buffer.cursor.row;
+        let (line, x) = buffer.screen_position(buffer.cursor, wrap.then_some(width));
+        let y = if wrap {
+            let above: usize = (buffer.scroll..row)
+                .map(|row| buffer.visual_rows(row, width))
+                .sum();
+            above + line
+        } else {
+            row - buffer.scroll
+        };
+        let (x, y) = (inner.x + gutter_width + x as u16, inner.y + y as u16);
+        f.set_cursor_position((x, y));
+        if mode == Mode::Insert {
+            match &state.completion {
+                Some(menu) => menu.render(f, area, x, y),
+                None => state.signatures.render(f, area, x, y),
+            }
+        }
+    }
+}
+
+fn search_style() -> Style {
+    Style::default().fg(Color::Black).bg(Color::Yellow)
+}
+
+fn diagnostic_style() -> Style {
+    Style::default()
+        .fg(Color::Red)
+        .add_modifier(Modifier::UNDERLINED)
+}
+
+fn bracket_style() -> Style {
+    Style::default()
+        .bg(Color::DarkGray)
+        .add_modifier(Modifier::BOLD)
+}
@@ -0,0 +1,381 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Intervals, money and ranges as people read them. The server's text for
+//! these is exact but hard on the eye, `2 days 03:04:00` or
+//! `["2024-01-01 00:00:00","2024-01-02 00:00:00")`, and sorts wrong as
+//! text. The grid shows them tidied up and sorts them by what they amount
+//! to; exports and yanks keep the server's text, which reads back in as the
+//! same value.
+
+use std::cmp::Ordering;
+
+use chrono::{NaiveDateTime, Offset, TimeZone};
+use chrono_tz::Tz;
+
+use crate::results::{self, Column};
+
+const MICROS_PER_DAY: i128 = 86_400_000_000;
+
+/// An interval as Postgres keeps it: months, days and microseconds, each
+/// with a sign of its own.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Interval {
+    pub months: i64,
+    pub days: i64,
+    pub micros: i64,
+}
+
+impl Interval {
+    /// An interval written in the `postgres` style, the default, or in the
+    /// `iso_8601` one.
+    pub fn parse(text: &str) -> Option<Self> {
+        match text.strip_prefix('P') {
+            Some(iso) => Self::parse_iso(iso),
+            None => Self::parse_postgres(text),
+        }
+    }
+
+    /// `1 year -2 mons +3 days -04:05:06.5`, any of the parts left out.
+    fn parse_postgres(text: &str) -> Option<Self> {
+        let mut interval = Self::default();
+        let mut words = text.split_whitespace().peekable();
+        words.peek()?;
+        while let Some(word) = words.next() {
+            if word.contains(':') {
+                interval.micros += time(word)?;
+                continue;
+            }
+            let n: i64 = word.parse().ok()?;
+            match words.next()?.trim_end_matches('s') {
+                "year" => interval.months += n * 12,
+                "mon" => interval.months += n,
+                "day" => interval.days += n,
+                _ => return None,
+            }
+        }
+        Some(interval)
+    }
+
+    /// `1Y-2M3DT-4H-5M-6.5S`, after the `P`.
+    fn parse_iso(text: &str) -> Option<Self> {
+        let (date, time) = text.split_once('T').unwrap_or((text, ""));
+        let mut interval = Self::default();
+        for (n, unit) in designators(date)? {
+            match unit {
+                'Y' => interval.months += n as i64 * 12,
+                'M' => interval.months += n as i64,
+                'W' => interval.days += n as i64 * 7,
+                'D' => interval.days += n as i64,
+                _ => return None,
+            }
+        }
+        for (n, unit) in designators(time)? {
+            let seconds = match unit {
+                'H' => n * 3600.0,
+                'M' => n * 60.0,
+                'S' => n,
+                _ => return None,
+            };
+            interval.micros += (seconds * 1e6).round() as i64;
+        }
+        Some(interval)
+    }
+
+    /// How long it is, taking a month to be 30 days as Postgres does when
+    /// it compares intervals.
+    pub fn micros(&self) -> i128 {
+        (self.months as i128 * 30 + self.days as i128) * MICROS_PER_DAY + self.micros as i128
+    }
+
+    /// `1 year 2 months 3 days 04:05`, seconds only if there are some.
+    pub fn render(&self) -> String {
+        let mut parts = Vec::new();
+        for (n, unit) in [
+            (self.months / 12, "year"),
+            (self.months % 12, "month"),
+            (self.days, "day"),
+        ] {
+            if n != 0 {
+                let plural = if n.abs() == 1 { "" } else { "s" };
+                parts.push(format!("{n} {unit}{plural}"));
+            }
+        }
+        if self.micros != 0 || parts.is_empty() {
+            let sign = if self.micros < 0 { "-" } else { "" };
+            let micros = self.micros.unsigned_abs();
+            let seconds = micros / 1_000_000;
+            let mut time = format!("{sign}{:02}:{:02}", seconds / 3600, seconds / 60 % 60);
+            let (seconds, fraction) = (seconds % 60, micros % 1_000_000);
+            if seconds != 0 || fraction != 0 {
+                time.push_str(&format!(":{seconds:02}"));
+            }
+            if fraction != 0 {
+                let fraction = format!("{fraction:06}");
+                time.push_str(&format!(".{}", fraction.trim_end_matches('0')));
+            }
+            parts.push(time);
+        }
+        parts.join(" ")
+    }
+}
+
+/// `-04:05:06.5` in microseconds.
+fn time(text: &str) -> Option<i64> {
+    let (sign, text) = match text.strip_prefix('-') {
+        Some(text) => (-1, text),
+        None => (1, text.strip_prefix('+').unwrap_or(text)),
+    };
+    let mut parts = text.split(':');
+    let hours: i64 = parts.next()?.parse().ok()?;
+    let minutes: i64 = parts.next()?.parse().ok()?;
+    let seconds = parts.next().unwrap_or("0");
+    if parts.next().is_some() {
+        return None;
+    }
+    let (whole, fraction) = seconds.split_once('.').unwrap_or((seconds, ""));
+    let whole: i64 = whole.parse().ok()?;
+    if fraction.len() > 6 || !fraction.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let fraction: i64 = format!("{fraction:0<6}").parse().ok()?;
+    Some(sign * (((hours * 60 + minutes) * 60 + whole) * 1_000_000 + fraction))
+}
+
+/// The numbers of an ISO 8601 duration and the letters after them.
+fn designators(text: &str) -> Option<Vec<(f64, char)>> {
+    let mut designators = Vec::new();
+    let mut number = String::new();
+    for c in text.chars() {
+        match c {
+            '0'..='9' | '.' | '-' | '+' => number.push(c),
+            unit => designators.push((std::mem::take(&mut number).parse().ok()?, unit)),
+        }
+    }
+    number.is_empty().then_some(designators)
+}
+
+/// The amount of a `money` value, whatever currency sign and separators the
+/// server's `lc_monetary` writes it with: `-$1,234.56` and `1.234,56 €`
+/// are both 1234.56, the first below zero.
+pub fn money(text: &str) -> Option<f64> {
+    let negative = text.contains('-') || text.starts_with('(');
+    let number: String = text
+        .chars()
+        .filter(|c| c.is_ascii_digit() || matches!(c, '.' | ','))
+        .collect();
+    if !number.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    // The last separator is the decimal one, unless it is the only kind
+    // there is and it marks off thousands.
+    let point = number.rfind(['.', ',']).filter(|&at| {
+        let separator = &number[at..at + 1];
+        let mixed = number.contains('.') && number.contains(',');
+        mixed || (number.matches(separator).count() == 1 && number.len() - at - 1 != 3)
+    });
+    let digits = |text: &str| text.replace(['.', ','], "");
+    let amount: f64 = match point {
+        Some(at) => format!("{}.{}", digits(&number[..at]), &number[at + 1..]),
+        None => digits(&number),
+    }
+    .parse()
+    .ok()?;
+    Some(if negative { -amount } else { amount })
+}
+
+/// A cell of `column` as a number: money by its amount, anything else if
+/// it is one.
+pub fn number(column: &Column, text: &str) -> Option<f64> {
+    match column.ty.as_str() {
+        "money" => money(text),
+        _ => text.parse().ok(),
+    }
+}
+
+/// A range with its bounds unquoted and the open ends shown as infinite,
+/// `[2024-01-01,∞)`.
+pub fn range(text: &str) -> Option<String> {
+    if text == "empty" {
+        return Some(text.into());
+    }
+    let open = text.chars().next().filter(|c| matches!(c, '[' | '('))?;
+    let close = text.chars().last().filter(|c| matches!(c, ']' | ')'))?;
+    let inner = text.get(1..text.len() - 1)?;
+    let mut bounds = Vec::new();
+    let mut bound = String::new();
+    let (mut quoted, mut was_quoted) = (false, false);
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.as_str().starts_with('"') => {
+                chars.next();
+                bound.push('"');
+            }
+            '"' => {
+                quoted = !quoted;
+                was_quoted = true;
+            }
+            '\\' => bound.push(chars.next()?),
+            ',' if !quoted => {
+                bounds.push((std::mem::take(&mut bound), was_quoted));
+                was_quoted = false;
+            }
+            c => bound.push(c),
+        }
+    }
+    bounds.push((bound, was_quoted));
+    let [(lower, lower_quoted), (upper, upper_quoted)] = <[_; 2]>::try_from(bounds).ok()?;
+    let lower = if lower.is_empty() && !lower_quoted {
+        "-∞".into()
+    } else {
+        lower
+    };
+    let upper = if upper.is_empty() && !upper_quoted {
+        "∞".into()
+    } else {
+        upper
+    };
+    Some(format!("{open}{lower},{upper}{close}"))
+}
+
+/// A multirange, `{[1,3),[5,7)}`, with each of its ranges as [`range`]
+/// shows them.
+fn multirange(text: &str) -> Option<String> {
+    let inner = text.strip_prefix('{')?.strip_suffix('}')?;
+    let mut ranges = Vec::new();
+    let mut rest = inner;
+    while !rest.is_empty() {
+        // A range ends at the first `]` or `)` outside quotes.
+        let mut quoted = false;
+        let mut escaped = false;
+        let end = rest.char_indices().find_map(|(at, c)| {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => quoted = !quoted,
+                ']' | ')' if !quoted => return Some(at + 1),
+                _ => {}
+            }
+            None
+        })?;
+        ranges.push(range(&rest[..end])?);
+        rest = rest[end..].trim_start_matches([',', ' ']);
+    }
+    Some(format!("{{{}}}", ranges.join(",")))
+}
+
+/// A `timestamptz` as the server writes it in the ISO `DateStyle`,
+/// `2024-03-10 08:30:00.5+00`, moved to `zone` and written the same way,
+/// for `:set timezone`. `None` for anything else, `infinity` or BC dates.
+pub fn in_zone(column: &Column, text: &str, zone: Tz) -> Option<String> {
+    if column.ty != "timestamptz" {
+        return None;
+    }
+    // Past the date, whose dashes are not a sign.
+    let at = text.get(10..)?.rfind(['+', '-'])? + 10;
+    let (local, offset) = text.split_at(at);
+    let (whole, fraction) = match local.split_once('.') {
+        Some((whole, fraction)) => (whole, Some(fraction)),
+        None => (local, None),
+    };
+    let local = NaiveDateTime::parse_from_str(whole, "%Y-%m-%d %H:%M:%S").ok()?;
+    let utc = local - chrono::Duration::seconds(parse_offset(offset)?.into());
+    let moved = zone.from_utc_datetime(&utc);
+    let mut shown = moved.format("%Y-%m-%d %H:%M:%S").to_string();
+    if let Some(fraction) = fraction {
+        shown.push('.');
+        shown.push_str(fraction);
+    }
+    shown.push_str(&write_offset(moved.offset().fix().local_minus_utc()));
+    Some(shown)
+}
+
+/// Seconds east of UTC from `+05`, `-03:30` or `+00:53:28`.
+fn parse_offset(text: &str) -> Option<i32> {
+    let (sign, rest) = match text.split_at_checked(1)? {
+        ("+", rest) => (1, rest),
+        ("-", rest) => (-1, rest),
+        _ => return None,
+    };
+    let mut seconds = 0;
+    for (part, unit) in rest.split(':').zip([3600, 60, 1]) {
+        if part.len() != 2 {
+            return None;
+        }
+        seconds += part.parse::<i32>().ok()? * unit;
+    }
+    Some(sign * seconds)
+}
+
+/// An offset as the server writes it, minutes and seconds only if any.
+fn write_offset(seconds: i32) -> String {
+    let sign = if seconds < 0 { '-' } else { '+' };
+    let seconds = seconds.abs();
+    let mut text = format!("{sign}{:02}", seconds / 3600);
+    if seconds % 3600 != 0 {
+        text.push_str(&format!(":{:02}", seconds / 60 % 60));
+    }
+    if seconds % 60 != 0 {
+        text.push_str(&format!(":{:02}", seconds % 60));
+    }
+    text
+}
+
+/// Characters kept of a shortened identifier.
+const SHORT_ID: usize = 8;
+
+/// A UUID, or what looks like a hash, a long run of hex digits as text or
+/// `bytea`, cut to its first few digits: `3f2a9c1e…`. `None` for anything
+/// else.
+pub fn short_id(column: &Column, text: &str) -> Option<String> {
+    let (prefix, digits) = match column.ty.as_str() {
+        "uuid" => ("", text),
+        "bytea" => ("\\x", text.strip_prefix("\\x")?),
+        _ if column.is_numeric() => return None,
+        _ => ("", text),
+    };
+    let hash = digits.len() >= 32 && digits.bytes().all(|b| b.is_ascii_hexdigit());
+    (column.ty == "uuid" || hash)
+        .then(|| format!("{prefix}{}…", &digits[..SHORT_ID.min(digits.len())]))
+}
+
+/// How the grid shows `text` of `column`, `None` for as it is.
+pub fn render(column: &Column, text: &str) -> Option<String> {
+    match column.ty.as_str() {
+        "interval" => Some(Interval::parse(text)?.render()),
+        ty if ty.ends_with("multirange") => multirange(text),
+        ty if ty.ends_with("range") => range(text),
+        _ => None,
+    }
+}
+
+/// Orders two values of `column`: intervals by how long they are and money
+/// by its amount, anything else as [`results::compare`] does.
+pub fn compare(column: &Column, a: &str, b: &str) -> Ordering {
+    match column.ty.as_str() {
+        "interval" => {
+            if let (Some(a), Some(b)) = (Interval::parse(a), Interval::parse(b)) {
+                return a.micros().cmp(&b.micros());
+            }
+        }
+        "money" => {
+            if let (Some(a), Some(b)) = (money(a), money(b)) {
+                return a.partial_cmp(&b).unwrap_or(Ordering::Equal);
+            }
+        }
+        _ => {}
+    }
+    results::compare(a, b)
+}
@@ -0,0 +1,99 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! psql-style variables, substituted into queries before they run:
+//!
+//! ```text
+//! :setvar env 'prod'
+//! select * from deploys where env = :'env'
+//! ```
+//!
+//! `:'name'` becomes the value as a string literal, `:"name"` as a quoted
+//! identifier and `:name` the value as it is. A `:name` that is not a
+//! variable is left for `params` to bind.
+
+use std::collections::BTreeMap;
+
+use crate::statements::{is_ident, quote_ident, quote_literal, skip_quoted};
+
+/// Whether `name` can be a variable, as in `:name`.
+pub fn is_name(name: &str) -> bool {
+    name.bytes()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == b'_')
+        && name.bytes().all(is_ident)
+}
+
+/// The value of `:setvar name <value>`: the text as it is, or what a
+/// single quoted literal says.
+pub fn parse_value(text: &str) -> Result<String, String> {
+    let text = text.trim();
+    let Some(quoted) = text.strip_prefix('\'') else {
+        return Ok(text.to_string());
+    };
+    match quoted.strip_suffix('\'') {
+        Some(inner) if !inner.replace("''", "").contains('\'') => Ok(inner.replace("''", "'")),
+        _ => Err(format!("Unterminated quoted value: {text}")),
+    }
+}
+
+/// `sql` with the variables of `vars` in it replaced by their values,
+/// skipping over literals, quoted identifiers, comments and `::` casts.
+pub fn substitute(sql: &str, vars: &BTreeMap<String, String>) -> String {
+    if vars.is_empty() {
+        return sql.to_string();
+    }
+    let bytes = sql.as_bytes();
+    let mut out = String::with_capacity(sql.len());
+    let (mut i, mut last) = (0, 0);
+    while i < bytes.len() {
+        let next = bytes.get(i + 1).copied();
+        let value = match (bytes[i], next) {
+            (b':', Some(b':')) => {
+                i += 2;
+                continue;
+            }
+            (b':', Some(quote @ (b'\'' | b'"'))) => {
+                let start = i + 2;
+                let end = sql[start..].find(quote as char).map(|end| start + end);
+                end.and_then(|end| {
+                    let value = vars.get(&sql[start..end])?;
+                    Some(match quote {
+                        b'\'' => (quote_literal(value), end + 1),
+                        _ => (quote_ident(value), end + 1),
+                    })
+                })
+            }
+            (b':', Some(next)) if next.is_ascii_alphabetic() || next == b'_' => {
+                let mut end = i + 1;
+                while end < bytes.len() && is_ident(bytes[end]) {
+                    end += 1;
+                }
+                vars.get(&sql[i + 1..end]).map(|value| (value.clone(), end))
+            }
+            _ => None,
+        };
+        if let Some((value, end)) = value {
+            out.push_str(&sql[last..i]);
+            out.push_str(&value);
+            (i, last) = (end, end);
+        } else if let Some(end) = skip_quoted(sql, i) {
+            i = end;
+        } else {
+            i += 1;
+        }
+    }
+    out.push_str(&sql[last..]);
+    out
+}
@@ -0,0 +1,142 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Display width of text, in terminal columns.
+//!
+//! Text is measured grapheme by grapheme, the way ratatui draws it: CJK
+//! characters and most emoji take two columns, combining marks none, and
+//! a base character with its marks is one thing the cursor steps over.
+//! Positions stay in chars, as in the editor.
+
+use std::borrow::Cow;
+
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::editor::byte_index;
+
+/// Columns taken by `text`.
+pub fn width(text: &str) -> usize {
+    text.graphemes(true).map(UnicodeWidthStr::width).sum()
+}
+
+/// Columns taken by the first `col` chars of `line`.
+pub fn columns(line: &str, col: usize) -> usize {
+    width(&line[..byte_index(line, col)])
+}
+
+/// `text` cut to at most `width` columns, ending in `…` if it had to be
+/// cut.
+pub fn truncate(text: &str, max: usize) -> Cow<'_, str> {
+    if width(text) <= max {
+        return Cow::Borrowed(text);
+    }
+    let mut cut = String::new();
+    let mut used = 0;
+    for grapheme in text.graphemes(true) {
+        let width = grapheme.width();
+        if used + width >= max {
+            break;
+        }
+        cut.push_str(grapheme);
+        used += width;
+    }
+    if max > 0 {
+        cut.push('…');
+    }
+    Cow::Owned(cut)
+}
+
+/// `text` padded with spaces to `width` columns, on the left if `right`.
+pub fn pad(text: &str, max: usize, right: bool) -> String {
+    let padding = " ".repeat(max.saturating_sub(width(text)));
+    if right {
+        padding + text
+    } else {
+        format!("{text}{padding}")
+    }
+}
+
+/// The chars where each screen line of `line` starts when wrapped at
+/// `width` columns; a wide character that would not fit goes to the next
+/// one. Never empty.
+pub fn wrap(line: &str, max: usize) -> Vec<usize> {
+    let max = max.max(1);
+    let mut starts = vec![0];
+    let (mut col, mut used) = (0, 0);
+    for grapheme in line.graphemes(true) {
+        let width = grapheme.width();
+        if used > 0 && used + width > max {
+            starts.push(col);
+            used = 0;
+        }
+        used += width;
+        col += grapheme.chars().count();
+    }
+    starts
+}
+
+/// The screen line and column of char `col` of `line` wrapped at `width`
+/// columns.
+pub fn locate(line: &str, col: usize, max: usize) -> (usize, usize) {
+    let starts = wrap(line, max);
+    let index = starts.iter().rposition(|&start| start <= col).unwrap_or(0);
+    let start = byte_index(line, starts[index]);
+    let end = byte_index(line, col);
+    (index, width(&line[start..end.max(start)]))
+}
+
+/// The char shown at screen column `x` of `line`, or the end of the line
+/// if it is shorter.
+pub fn char_at(line: &str, x: usize) -> usize {
+    let (mut col, mut used) = (0, 0);
+    for grapheme in line.graphemes(true) {
+        used += grapheme.width();
+        if used > x {
+            return col;
+        }
+        col += grapheme.chars().count();
+    }
+    col
+}
+
+/// The char starting the grapheme that char `col` of `line` is part of.
+pub fn grapheme_start(line: &str, col: usize) -> usize {
+    let mut start = 0;
+    for grapheme in line.graphemes(true) {
+        let end = start + grapheme.chars().count();
+        if end > col {
+            return start;
+        }
+        start = end;
+    }
+    col
+}
+
+/// The char after the grapheme at char `col` of `line`.
+pub fn next_grapheme(line: &str, col: usize) -> usize {
+    let mut start = 0;
+    for grapheme in line.graphemes(true) {
+        start += grapheme.chars().count();
+        if start > col {
+            return start;
+        }
+    }
+    col + 1
+}
+
+/// The char starting the grapheme before char `col` of `line`.
+pub fn previous_grapheme(line: &str, col: usize) -> usize {
+    grapheme_start(line, col.saturating_sub(1))
+}
@@ -0,0 +1,272 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! First-run setup. With no config file, and nothing on the command line or
+//! in the environment saying where to connect, a form asks for a connection
+//! and saves it as a profile.
+
+use std::{env, path::Path};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    Frame,
+    layout::{Constraint, Flex, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+};
+
+use crate::app::Args;
+
+/// Servers the session can talk to, which all speak the Postgres protocol.
+const SERVERS: &[&str] = &["postgres", "redshift", "greenplum"];
+const SSL_MODES: &[&str] = &["prefer", "disable", "require", "verify-ca", "verify-full"];
+
+/// Field indices.
+const NAME: usize = 0;
+const SERVER: usize = 1;
+const HOST: usize = 2;
+const PORT: usize = 3;
+const DATABASE: usize = 4;
+const USER: usize = 5;
+const PASSWORD: usize = 6;
+const SSL: usize = 7;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Input {
+    Text,
+    Digits,
+    /// Letters, digits, `-` and `_`, which make a bare TOML key.
+    Name,
+    Masked,
+    Choice(&'static [&'static str]),
+}
+
+#[derive(Debug, Clone)]
+struct Field {
+    label: &'static str,
+    input: Input,
+    value: String,
+}
+
+/// What a key did to the form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    Continue,
+    /// Enter on the last field.
+    Done,
+    /// Esc, to carry on without a profile.
+    Cancel,
+}
+
+#[derive(Debug, Clone)]
+pub struct Wizard {
+    fields: Vec<Field>,
+    focus: usize,
+}
+
+/// Whether to run the setup: there is nowhere to look for a config, or no
+/// config, and nothing else says where to connect.
+pub fn wanted(args: &Args, config: Option<&Path>) -> bool {
+    let told = args.conninfo.is_some()
+        || args.url.is_some()
+        || args.service.is_some()
+        || args.profile.is_some()
+        || ["DATABASE_URL", "PGHOST", "PGDATABASE", "PGSERVICE"]
+            .iter()
+            .any(|var| env::var_os(var).is_some());
+    !told && config.is_some_and(|path| !path.exists())
+}
+
+impl Default for Wizard {
+    fn default() -> Self {
+        let user = env::var("USER").unwrap_or_else(|_| "postgres".into());
+        let field = |label, input, value: &str| Field {
+            label,
+            input,
+            value: value.into(),
+        };
+        Self {
+            fields: vec![
+                field("Profile", Input::Name, "local"),
+                field("Server", Input::Choice(SERVERS), SERVERS[0]),
+                field("Host", Input::Text, "localhost"),
+                field("Port", Input::Digits, "5432"),
+                field("Database", Input::Text, &user),
+                field("User", Input::Text, &user),
+                field("Password", Input::Masked, ""),
+                field("SSL", Input::Choice(SSL_MODES), SSL_MODES[0]),
+            ],
+            focus: 0,
+        }
+    }
+}
+
+impl Wizard {
+    pub fn key(&mut self, key: KeyEvent) -> Step {
+        let last = self.fields.len() - 1;
+        match key.code {
+            KeyCode::Esc => return Step::Cancel,
+            KeyCode::Enter if self.focus == last => return Step::Done,
+            KeyCode::Enter | KeyCode::Tab | KeyCode::Down => {
+                self.focus = (self.focus + 1).min(last)
+            }
+            KeyCode::BackTab | KeyCode::Up => self.focus = self.focus.saturating_sub(1),
+            KeyCode::Left | KeyCode::Right => self.cycle(key.code == KeyCode::Right),
+            KeyCode::Backspace => {
+                self.fields[self.focus].value.pop();
+            }
+            KeyCode::Char('u')
+                if key.modifiers.contains(KeyModifiers::CONTROL)
+                    && !matches!(self.fields[self.focus].input, Input::Choice(_)) =>
+            {
+                self.fields[self.focus].value.clear()
+            }
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                let field = &mut self.fields[self.focus];
+                let fits = match field.input {
+                    Input::Text | Input::Masked => true,
+                    Input::Digits => c.is_ascii_digit(),
+                    Input::Name => c.is_ascii_alphanumeric() || c == '-' || c == '_',
+                    Input::Choice(_) => {
+                        self.cycle(true);
+                        false
+                    }
+                };
+                if fits {
+                    self.fields[self.focus].value.push(c);
+                }
+            }
+            _ => {}
+        }
+        Step::Continue
+    }
+
+    /// Moves a choice to the next or previous option, and the port along
+    /// with the server while it is the default one.
+    fn cycle(&mut self, forward: bool) {
+        let Input::Choice(choices) = self.fields[self.focus].input else {
+            return;
+        };
+        let default_port = self.default_port();
+        let field = &mut self.fields[self.focus];
+        let index = choices.iter().position(|c| *c == field.value).unwrap_or(0);
+        let index = if forward {
+            (index + 1) % choices.len()
+        } else {
+            (index + choices.len() - 1) % choices.len()
+        };
+        field.value = choices[index].into();
+        if self.focus == SERVER && self.fields[PORT].value == default_port {
+            self.fields[PORT].value = self.default_port();
+        }
+    }
+
+    fn default_port(&self) -> String {
+        match self.fields[SERVER].value.as_str() {
+            "redshift" => "5439",
+            _ => "5432",
+        }
+        .into()
+    }
+
+    pub fn name(&self) -> &str {
+        match self.fields[NAME].value.as_str() {
+            "" => "local",
+            name => name,
+        }
+    }
+
+    /// Empty if none was given, which leaves it to be asked for.
+    pub fn password(&self) -> &str {
+        &self.fields[PASSWORD].value
+    }
+
+    pub fn url(&self) -> String {
+        let value = |field: usize| self.fields[field].value.trim();
+        let mut url = url::Url::parse("postgres://localhost").expect("valid base url");
+        let _ = url.set_host(Some(match value(HOST) {
+            "" => "localhost",
+            host => host,
+        }));
+        let _ = url.set_port(value(PORT).parse().ok());
+        let _ = url.set_username(value(USER));
+        url.set_path(value(DATABASE));
+        if value(SSL) != "prefer" {
+            url.query_pairs_mut().append_pair("sslmode", value(SSL));
+        }
+        url.into()
+    }
+
+    /// The config file with the profile in it.
+    pub fn config(&self) -> String {
+        let mut config = format!(
+            "# Written by the dbvi setup, start with -p {name}.\n\
+             [profiles.{name}]\n\
+             url = {url}\n",
+            name = self.name(),
+            url = toml::Value::String(self.url()),
+        );
+        let server = &self.fields[SERVER].value;
+        if server != "postgres" {
+            config.push_str(&format!("dialect = \"{server}\"\n"));
+        }
+        config
+    }
+
+    pub fn render(&self, f: &mut Frame) {
+        let height = self.fields.len() as u16 + 6;
+        let [area] = Layout::vertical([Constraint::Length(height)])
+            .flex(Flex::Center)
+            .areas(f.area());
+        let [area] = Layout::horizontal([Constraint::Length(72)])
+            .flex(Flex::Center)
+            .areas(area);
+        let mut lines = vec![
+            Line::from("No config yet, where should dbvi connect?"),
+            Line::from(""),
+        ];
+        for (index, field) in self.fields.iter().enumerate() {
+            let focused = index == self.focus;
+            let value = match field.input {
+                Input::Masked => "*".repeat(field.value.chars().count()),
+                Input::Choice(_) if focused => format!("< {} >", field.value),
+                _ => field.value.clone(),
+            };
+            let style = if focused {
+                Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default()
+            };
+            lines.push(Line::from(vec![
+                Span::styled(format!("{:>10}: ", field.label), style),
+                Span::raw(value),
+            ]));
+        }
+        lines.push(Line::from(""));
+        lines.push(
+            Line::from("Tab next · ←/→ choose · C-u clears · Enter on SSL saves · Esc skips")
+                .style(Style::default().fg(Color::DarkGray)),
+        );
+        let form = Paragraph::new(lines).block(
+            Block::default()
+                .title(Line::from("Setup").centered())
+                .borders(Borders::ALL),
+        );
+        f.render_widget(Clear, area);
+        f.render_widget(form, area);
+    }
+}
@@ -0,0 +1,141 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Per-project settings, from a `.dbvi.toml` in the directory dbvi starts
+//! in:
+//!
+//! ```toml
+//! # Connect with this profile unless told otherwise on the command line.
+//! profile = "app-dev"
+//! # Saved queries of the project, relative to this file.
+//! queries = "sql/queries"
+//! # Numbered migrations for `:migrate`, relative to this file.
+//! migrations = "db/migrations"
+//!
+//! [options]
+//! statement_timeout = "10s"
+//! number = true
+//! ```
+//!
+//! A file checked into a repository is not necessarily one's own, so it is
+//! only loaded once trusted, and trusted again whenever it changes: the
+//! trusted files and a hash of what they said are kept in
+//! `$XDG_STATE_HOME/dbvi/trusted`.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::config;
+
+pub const FILE: &str = ".dbvi.toml";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Workspace {
+    pub profile: Option<String>,
+    pub queries: Option<PathBuf>,
+    pub migrations: Option<PathBuf>,
+    /// Values for `:set` options, over those of the config file.
+    pub options: BTreeMap<String, toml::Value>,
+    /// The file it was read from.
+    #[serde(skip)]
+    pub path: PathBuf,
+}
+
+impl Workspace {
+    /// Parses the workspace file at `path` with `contents`.
+    pub fn parse(path: &Path, contents: &str) -> Result<Self, String> {
+        let mut workspace: Self =
+            toml::from_str(contents).map_err(|err| format!("{}: {err}", path.display()))?;
+        workspace.path = path.to_path_buf();
+        Ok(workspace)
+    }
+
+    /// The directory of the project's saved queries.
+    pub fn queries_dir(&self) -> Option<PathBuf> {
+        let queries = self.queries.as_ref()?;
+        let dir = self.path.parent().unwrap_or(Path::new("."));
+        Some(dir.join(queries))
+    }
+
+    /// The directory of the project's migrations.
+    pub fn migrations_dir(&self) -> Option<PathBuf> {
+        let migrations = self.migrations.as_ref()?;
+        let dir = self.path.parent().unwrap_or(Path::new("."));
+        Some(dir.join(migrations))
+    }
+}
+
+/// The workspace file of `dir`, if it has one.
+pub fn find(dir: &Path) -> Option<PathBuf> {
+    let path = dir.join(FILE);
+    path.is_file().then_some(path)
+}
+
+/// The trusted workspace files, one `<sha256> <path>` line each.
+fn trust_path() -> Option<PathBuf> {
+    config::state_dir().map(|dir| dir.join("trusted"))
+}
+
+fn line(path: &Path, contents: &str) -> String {
+    let hash: String = Sha256::digest(contents.as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    format!("{hash} {}", path.display())
+}
+
+/// Whether the workspace file at `path` was trusted as it is now.
+pub fn is_trusted(trusted: &str, path: &Path, contents: &str) -> bool {
+    let line = line(path, contents);
+    trusted.lines().any(|trusted| trusted == line)
+}
+
+/// `trusted` with the workspace file at `path` trusted as it is now, in
+/// place of whatever it said when last trusted.
+pub fn trust(trusted: &str, path: &Path, contents: &str) -> String {
+    let suffix = format!(" {}", path.display());
+    let mut lines: Vec<String> = trusted
+        .lines()
+        .filter(|line| !line.ends_with(&suffix))
+        .map(str::to_string)
+        .collect();
+    lines.push(line(path, contents));
+    lines.join("\n") + "\n"
+}
+
+/// The list of trusted workspace files, empty if there is none yet.
+pub fn load_trusted() -> io::Result<String> {
+    let Some(path) = trust_path() else {
+        return Ok(String::new());
+    };
+    match fs::read_to_string(path) {
+        Ok(trusted) => Ok(trusted),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(String::new()),
+        Err(err) => Err(err),
+    }
+}
+
+pub fn save_trusted(trusted: &str) -> io::Result<()> {
+    let path = trust_path().ok_or_else(|| io::Error::other("no state directory"))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, trusted)
+}
@@ -0,0 +1,53 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use clap::Parser;
+use dbvi::{Args, db};
+
+#[test]
+fn connection() {
+    let args = Args::try_parse_from(["dbvi", "service=shop"]).unwrap();
+    assert_eq!(args.target(), Some("service=shop"));
+    assert!(Args::try_parse_from(["dbvi", "service=shop", "--url", "postgres://db"]).is_err());
+    assert!(Args::try_parse_from(["dbvi", "host=db", "--service", "shop"]).is_err());
+    assert!(Args::try_parse_from(["dbvi", "--url", "postgres://db", "--service", "shop"]).is_err());
+}
+
+#[test]
+fn url_is_the_positional_conninfo() {
+    let url = "postgres://app@db.internal:6543/shop";
+    let connect = |args: &[&str]| {
+        let args = Args::try_parse_from(args).unwrap();
+        let options = db::connect_options(args.target(), None).unwrap();
+        (
+            options.get_host().to_string(),
+            options.get_port(),
+            options.get_database().map(String::from),
+            options.get_username().to_string(),
+        )
+    };
+    let positional = connect(&["dbvi", url]);
+    assert_eq!(connect(&["dbvi", "--url", url]), positional);
+    assert_eq!(connect(&["dbvi", "-u", url]), positional);
+    assert_eq!(
+        positional,
+        (
+            "db.internal".into(),
+            6543,
+            Some("shop".into()),
+            "app".into()
+        )
+    );
+    assert!(Args::try_parse_from(["dbvi", url, "--url", url]).is_err());
+}
@@ -0,0 +1,41 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use dbvi::commands;
+use dbvi::db::asof::{AsOf, is_id};
+use dbvi::state::Command;
+
+#[test]
+fn knows_snapshot_ids() {
+    assert!(is_id("00000003-0000001B-1"));
+    assert!(is_id("00000004-00000002"));
+    assert!(!is_id("00000003"));
+    assert!(!is_id("00000003-'; DROP TABLE orders; --"));
+    assert!(!is_id("00000003--1"));
+}
+
+#[test]
+fn parses() {
+    assert_eq!(commands::parse("asof"), Ok(Command::AsOf(AsOf::Take)));
+    assert_eq!(commands::parse("asof run"), Ok(Command::AsOf(AsOf::Run)));
+    assert_eq!(
+        commands::parse("asof close"),
+        Ok(Command::AsOf(AsOf::Close))
+    );
+    assert_eq!(
+        commands::parse("asof 00000003-0000001B-1"),
+        Ok(Command::AsOf(AsOf::Import("00000003-0000001B-1".into())))
+    );
+    assert!(commands::parse("asof yesterday").is_err());
+}
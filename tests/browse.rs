@@ -0,0 +1,234 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod common;
+
+use common::Harness;
+use dbvi::Command;
+use dbvi::browse::{Browser, Filter, PageTo, Total};
+use dbvi::results::{Column, ResultSet};
+
+fn browser(rows: u64, exact: bool) -> Browser {
+    let mut browser = Browser::new(
+        "public.orders",
+        vec!["id".into()],
+        100,
+        Total { rows, exact },
+    );
+    browser.page = 2;
+    browser
+}
+
+fn page(rows: &[[Option<&str>; 2]]) -> ResultSet {
+    let mut results = ResultSet::new(vec![Column::new("id", "int4"), Column::new("day", "date")]);
+    for row in rows {
+        results.rows.push(*row);
+    }
+    results
+}
+
+#[test]
+fn pages() {
+    let counted = browser(1050, true);
+    assert_eq!(counted.pages(), 11);
+    assert_eq!(counted.title(), "public.orders, page 3 of 11");
+    assert_eq!(counted.target(PageTo::Next(3)), 5);
+    assert_eq!(counted.target(PageTo::Next(30)), 10);
+    assert_eq!(counted.target(PageTo::Previous(5)), 0);
+    assert_eq!(counted.target(PageTo::Number(7)), 6);
+    assert_eq!(counted.target(PageTo::Last), 10);
+    assert_eq!(browser(0, true).pages(), 1);
+
+    // An estimate may be short, so pages past it are worth a try.
+    let estimated = browser(1050, false);
+    assert_eq!(estimated.title(), "public.orders, page 3 of ~11");
+    assert_eq!(estimated.target(PageTo::Next(30)), 32);
+}
+
+#[test]
+fn offsets() {
+    let mut unkeyed = browser(1050, false);
+    unkeyed.key.clear();
+    assert_eq!(
+        unkeyed.query(PageTo::Next(1)),
+        (3, "SELECT * FROM public.orders LIMIT 100 OFFSET 300".into())
+    );
+    assert_eq!(
+        unkeyed.query(PageTo::Number(1)).1,
+        "SELECT * FROM public.orders LIMIT 100"
+    );
+}
+
+#[test]
+fn seeks() {
+    let mut browser = browser(1050, true);
+    assert_eq!(
+        browser.query(PageTo::Next(1)).1,
+        r#"SELECT * FROM public.orders ORDER BY "id" LIMIT 100 OFFSET 300"#
+    );
+    browser.remember(2, &page(&[[Some("201"), None], [Some("300"), None]]));
+    assert_eq!(
+        browser.query(PageTo::Next(1)).1,
+        r#"SELECT * FROM public.orders WHERE ("id") > ('300') ORDER BY "id" LIMIT 100"#
+    );
+    assert_eq!(
+        browser.query(PageTo::Next(3)).1,
+        r#"SELECT * FROM public.orders WHERE ("id") > ('300') ORDER BY "id" LIMIT 100 OFFSET 200"#
+    );
+    // Back from the page before, read backwards.
+    assert_eq!(
+        browser.query(PageTo::Previous(1)).1,
+        r#"SELECT * FROM (SELECT * FROM public.orders WHERE ("id") < ('201') ORDER BY "id" DESC LIMIT 100) AS page ORDER BY "id""#
+    );
+    // The first page is closer to the start.
+    assert_eq!(
+        browser.query(PageTo::Previous(2)).1,
+        r#"SELECT * FROM public.orders ORDER BY "id" LIMIT 100"#
+    );
+    // The last page of a counted table is as short as it would be.
+    assert_eq!(
+        browser.query(PageTo::Last),
+        (
+            10,
+            r#"SELECT * FROM (SELECT * FROM public.orders ORDER BY "id" DESC LIMIT 50) AS page ORDER BY "id""#
+                .into()
+        )
+    );
+}
+
+#[test]
+fn seeks_by_a_column() {
+    let mut browser = browser(1050, true);
+    browser.sort = Some(("day".into(), true));
+    browser.remember(
+        2,
+        &page(&[[Some("7"), None], [Some("9"), Some("2024-01-02")]]),
+    );
+    assert_eq!(
+        browser.query(PageTo::Next(1)).1,
+        r#"SELECT * FROM public.orders WHERE ("day" < '2024-01-02') OR ("day" = '2024-01-02' AND "id" > '9') ORDER BY "day" DESC, "id" LIMIT 100"#
+    );
+    // Nulls come first in descending order.
+    assert_eq!(
+        browser.query(PageTo::Previous(1)).1,
+        r#"SELECT * FROM (SELECT * FROM public.orders WHERE ("day" IS NULL AND "id" < '7') ORDER BY "day", "id" DESC LIMIT 100) AS page ORDER BY "day" DESC, "id""#
+    );
+}
+
+#[test]
+fn keys() {
+    let mut harness = Harness::new();
+    let results = page(&[[Some("201"), None], [Some("202"), None]]);
+    harness.state.show_page(browser(1050, true), results);
+    harness.keys("<C-w>k3<C-f><C-b>G2G:page 9<CR>f:nofilter<CR>");
+    assert_eq!(
+        harness.commands,
+        [
+            Command::TurnPage(PageTo::Next(3)),
+            Command::TurnPage(PageTo::Previous(1)),
+            Command::TurnPage(PageTo::Last),
+            Command::TurnPage(PageTo::Number(9)),
+            Command::Filter("id".into()),
+            Command::Unfilter,
+        ]
+    );
+    assert!(harness.render().contains("public.orders, page 3 of 11"));
+
+    // Any other results are not a page of it.
+    harness.state.show_results(None);
+    assert!(harness.state.browser().is_none());
+}
+
+#[test]
+fn filters() {
+    let parse = |text| Filter::parse("name", text).unwrap();
+    assert_eq!(parse("ada").sql(), r#""name" = 'ada'"#);
+    assert_eq!(parse(">=  O'Brien").sql(), r#""name" >= 'O''Brien'"#);
+    assert_eq!(parse("<> x").sql(), r#""name" <> 'x'"#);
+    assert_eq!(
+        parse("~ 50%_off").sql(),
+        r#""name"::text ILIKE '%50\%\_off%'"#
+    );
+    assert_eq!(parse("NULL").sql(), r#""name" IS NULL"#);
+    assert_eq!(parse("!null").sql(), r#""name" IS NOT NULL"#);
+    assert_eq!(parse("<>x").text(), "!= x");
+    assert!(Filter::parse("name", ">=").is_err());
+
+    let mut browser = browser(1050, true);
+    browser.filter("id", Some(Filter::parse("id", "> 5").unwrap()));
+    browser.filter("name", Some(parse("~ ad")));
+    browser.filter("id", Some(Filter::parse("id", "> 7").unwrap()));
+    assert_eq!(browser.page, 0);
+    assert_eq!(
+        browser.title(),
+        "public.orders [name ~ ad] [id > 7], page 1 of 11"
+    );
+    browser.remember(0, &page(&[[Some("8"), None], [Some("9"), None]]));
+    assert_eq!(
+        browser.query(PageTo::Next(1)).1,
+        r#"SELECT * FROM public.orders WHERE "name"::text ILIKE '%ad%' AND "id" > '7' AND (("id") > ('9')) ORDER BY "id" LIMIT 100"#
+    );
+    browser.filter("name", None);
+    assert_eq!(browser.filters.len(), 1);
+    browser.unfilter();
+    assert_eq!(browser.title(), "public.orders, page 1 of 11");
+}
+
+#[test]
+fn sorts() {
+    let mut browser = browser(1050, true);
+    browser.filter("day", Some(Filter::parse("day", "!null").unwrap()));
+    browser.sort_by("day");
+    assert_eq!(browser.sort, Some(("day".into(), false)));
+    assert_eq!(
+        browser.query(PageTo::Number(2)).1,
+        r#"SELECT * FROM public.orders WHERE "day" IS NOT NULL ORDER BY "day", "id" LIMIT 100 OFFSET 100"#
+    );
+    browser.sort_by("day");
+    assert_eq!(browser.sort, Some(("day".into(), true)));
+    browser.sort_by("id");
+    assert_eq!(browser.sort, Some(("id".into(), false)));
+
+    let mut harness = Harness::new();
+    let results = page(&[[Some("1"), Some("2024-01-02")]]);
+    harness.state.show_page(browser, results);
+    assert!(harness.render().contains("id ▲"));
+    harness.keys("<C-w>kls");
+    assert_eq!(harness.commands, [Command::SortBy("day".into())]);
+}
+
+#[test]
+fn bulk_actions() {
+    let mut harness = Harness::new();
+    let results = page(&[
+        [Some("1"), None],
+        [Some("2"), Some("2024-01-02")],
+        [Some("3"), None],
+    ]);
+    harness.state.show_page(browser(3, true), results);
+    harness.keys("<C-w>kVjYi");
+    assert_eq!(harness.state.status(), "2 rows copied");
+    harness.keys("<C-w>jp");
+    assert_eq!(
+        harness.state.text(),
+        "INSERT INTO public.orders (\"id\", \"day\") VALUES (1, NULL);\n\
+         INSERT INTO public.orders (\"id\", \"day\") VALUES (2, '2024-01-02');\n"
+    );
+
+    harness.keys("<C-w>kggjVjD");
+    assert_eq!(
+        harness.state.text(),
+        "DELETE FROM public.orders\nWHERE (\"id\") IN (\n    (2),\n    (3)\n);"
+    );
+}
@@ -0,0 +1,93 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use dbvi::commands;
+use dbvi::db::capabilities::{Capabilities, Need};
+use dbvi::db::server::{Flavor, Server};
+use dbvi::state::Command;
+
+#[test]
+fn unknown_turns_nothing_off() {
+    let unknown = Capabilities::default();
+    assert!(!unknown.lacks(Need::Catalogs));
+    assert!(!unknown.lacks(Need::Extension("pg_stat_statements")));
+    assert_eq!(unknown.notice(), None);
+}
+
+#[test]
+fn lacks() {
+    let capabilities = Capabilities {
+        catalogs: Some(false),
+        extensions: Some(vec!["plpgsql".into()]),
+        ..Default::default()
+    };
+    assert!(capabilities.lacks(Need::Catalogs));
+    assert!(!capabilities.lacks(Need::Extension("plpgsql")));
+    assert!(capabilities.lacks(Need::Extension("pg_stat_statements")));
+    assert!(capabilities.notice().unwrap().contains(":capabilities"));
+    assert_eq!(
+        Need::Extension("pg_stat_statements").refusal(),
+        "pg_stat_statements is not installed in this database, \
+         CREATE EXTENSION pg_stat_statements"
+    );
+}
+
+#[test]
+fn report() {
+    let capabilities = Capabilities {
+        catalogs: Some(true),
+        temp_tables: Some(false),
+        schema: Some("public".into()),
+        create: Some(true),
+        extensions: Some(vec!["pg_stat_statements".into(), "plpgsql".into()]),
+        ..Default::default()
+    };
+    let server = Server {
+        flavor: Flavor::Postgres,
+        version: 160004,
+    };
+    let rows = capabilities.report(server);
+    let answer = |asked: &str| {
+        rows.iter()
+            .find(|[row, ..]| row == asked)
+            .map(|[_, answer, _]| answer.as_str())
+    };
+    assert_eq!(answer("Server"), Some("Postgres 16.4"));
+    assert_eq!(answer("Read the system catalogs"), Some("yes"));
+    assert_eq!(answer("Create temporary tables"), Some("no"));
+    assert_eq!(answer("Create objects in public"), Some("yes"));
+    assert_eq!(answer("Superuser"), Some("unknown"));
+    assert_eq!(answer("pg_stat_statements"), Some("yes"));
+    assert_eq!(answer("Extensions"), Some("pg_stat_statements, plpgsql"));
+}
+
+#[test]
+fn release() {
+    let release = |version| {
+        Server {
+            flavor: Flavor::Postgres,
+            version,
+        }
+        .release()
+    };
+    assert_eq!(release(160004).as_deref(), Some("16.4"));
+    assert_eq!(release(90624).as_deref(), Some("9.6.24"));
+    assert_eq!(release(0), None);
+}
+
+#[test]
+fn parse() {
+    assert_eq!(commands::parse("capabilities"), Ok(Command::Capabilities));
+    assert_eq!(commands::parse("caps"), Ok(Command::Capabilities));
+}
@@ -0,0 +1,91 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod common;
+
+use common::Harness;
+use dbvi::clipboard;
+use dbvi::results::{Column, ResultSet};
+
+#[test]
+fn osc52() {
+    assert_eq!(clipboard::osc52("select 1"), "\x1b]52;c;c2VsZWN0IDE=\x07");
+}
+
+#[test]
+fn tmux_passthrough() {
+    assert_eq!(
+        clipboard::tmux_passthrough(&clipboard::osc52("x")),
+        "\x1bPtmux;\x1b\x1b]52;c;eA==\x07\x1b\\"
+    );
+}
+
+#[test]
+fn by_name() {
+    assert_eq!(
+        clipboard::Clipboard::from_name("osc52").unwrap().name(),
+        "osc52"
+    );
+    assert_eq!(
+        clipboard::Clipboard::from_name("none").unwrap().name(),
+        "none"
+    );
+    assert!(clipboard::Clipboard::from_name("primary").is_err());
+}
+
+#[test]
+fn yank_cells_then_paste() {
+    let mut harness = Harness::new();
+    let mut results = ResultSet::new(vec![Column::new("id", "int4"), Column::new("name", "text")]);
+    results.rows = vec![
+        vec![Some("1".into()), Some("Ada".into())],
+        vec![Some("2".into()), None],
+    ]
+    .into();
+    harness.state.show_results(Some(results));
+    harness.keys("<C-w>ky");
+    assert_eq!(harness.state.status(), "1 cell yanked");
+    harness.keys("vjly");
+    assert_eq!(harness.state.status(), "4 cells yanked");
+    harness.keys("<C-w>jp");
+    assert_eq!(harness.state.text(), "1\tAda\n2\t");
+}
+
+#[test]
+fn copy_as() {
+    let mut harness = Harness::new();
+    let mut results = ResultSet::new(vec![Column::new("id", "int4"), Column::new("day", "date")]);
+    results.rows = vec![
+        vec![Some("1".into()), Some("2024-01-01".into())],
+        vec![Some("2".into()), None],
+    ]
+    .into();
+    harness.state.show_results(Some(results));
+    harness.keys("<C-w>kVjY");
+    assert!(harness.render().contains("p  pandas.DataFrame(...)"));
+    harness.keys("v");
+    assert_eq!(harness.state.status(), "2 rows copied");
+    harness.keys("<C-w>jp");
+    assert_eq!(
+        harness.state.text(),
+        "VALUES\n    (1, '2024-01-01'::date),\n    (2, NULL)\n"
+    );
+}
+
+#[test]
+fn ctrl_v_in_insert_mode() {
+    let mut harness = Harness::new();
+    harness.keys("iselect 1<Esc>yyo<C-v>, 2");
+    assert_eq!(harness.state.text(), "select 1\nselect 1, 2");
+}
@@ -0,0 +1,177 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A terminal-free harness: keys in as vim would write them, the screen out
+//! as text, and no server behind the session.
+
+use crossterm::event::Event;
+use ratatui::{Terminal, backend::TestBackend, style::Style};
+use serde_json::json;
+use sqlx::postgres::PgConnectOptions;
+
+use dbvi::db::schema::Schema;
+use dbvi::db::session::Session;
+use dbvi::results::{Column, ResultSet};
+use dbvi::{Command, State, action, flush_keys, handle_input, keys, press, ui};
+
+pub struct Harness {
+    pub state: State,
+    terminal: Terminal<TestBackend>,
+    /// Commands the keys turned into, which nothing runs.
+    pub commands: Vec<Command>,
+}
+
+impl Harness {
+    #[allow(dead_code)] // Not every test binary drives the app.
+    pub fn new() -> Self {
+        Self::with_size(80, 20)
+    }
+
+    pub fn with_size(width: u16, height: u16) -> Self {
+        let options = PgConnectOptions::new()
+            .host("localhost")
+            .port(5432)
+            .username("dbvi")
+            .database("test");
+        let (bus, _) = action::channel();
+        Self {
+            state: State::new(Session::lazy(options), bus),
+            terminal: Terminal::new(TestBackend::new(width, height)).expect("test terminal"),
+            commands: Vec::new(),
+        }
+    }
+
+    /// Presses `keys`, written as in a vim mapping: `ihello<Esc>`, `<C-w>j`,
+    /// `:q<CR>`. `<lt>` is a `<`. They go through the mappings first.
+    #[allow(dead_code)] // Not every test binary drives the app.
+    pub fn keys(&mut self, keys: &str) -> &mut Self {
+        for key in keys::parse(keys) {
+            // Drawn between keys like the real loop, which some keys (the
+            // editor's scrolling, the mouse) depend on.
+            self.render();
+            let commands = press(&mut self.state, key);
+            self.commands.extend(commands);
+        }
+        self
+    }
+
+    /// Lets `timeoutlen` pass, for keys held as the start of a mapping.
+    #[allow(dead_code)] // Not every test binary maps keys.
+    pub fn timeout(&mut self) -> &mut Self {
+        let commands = flush_keys(&mut self.state);
+        self.commands.extend(commands);
+        self
+    }
+
+    /// Pastes `text` as a terminal with bracketed paste would.
+    #[allow(dead_code)] // Not every test binary pastes.
+    pub fn paste(&mut self, text: &str) -> &mut Self {
+        let command = handle_input(&mut self.state, Event::Paste(text.into()));
+        if !matches!(command, Command::None) {
+            self.commands.push(command);
+        }
+        self
+    }
+
+    /// Resizes the terminal, telling the app like the real one would.
+    #[allow(dead_code)] // Not every test binary resizes.
+    pub fn resize(&mut self, width: u16, height: u16) -> &mut Self {
+        self.terminal.backend_mut().resize(width, height);
+        let command = handle_input(&mut self.state, Event::Resize(width, height));
+        if !matches!(command, Command::None) {
+            self.commands.push(command);
+        }
+        self
+    }
+
+    /// The screen after drawing the state, one line per row with trailing
+    /// blanks trimmed.
+    pub fn render(&mut self) -> String {
+        self.terminal
+            .draw(|frame| ui::draw_ui(frame, &mut self.state))
+            .expect("draw");
+        let buffer = self.terminal.backend().buffer();
+        let width = buffer.area.width as usize;
+        buffer
+            .content
+            .chunks(width)
+            .map(|row| {
+                let line: String = row.iter().map(|cell| cell.symbol()).collect();
+                line.trim_end().to_string()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// The styles of the cells drawn by the last `render`.
+    #[allow(dead_code)] // Not every test binary looks at colors.
+    pub fn styles(&self) -> Vec<Style> {
+        let buffer = self.terminal.backend().buffer();
+        buffer.content.iter().map(|cell| cell.style()).collect()
+    }
+}
+
+/// A cached schema of tables, as a refresh would have saved it: for each,
+/// its schema, name and columns. A column is its name and type, then any of
+/// `not null`, `generated` and `primary key`, like `id integer not null
+/// primary key`. Every table is signed `1/2/`.
+#[allow(dead_code)] // Not every test binary needs a schema.
+pub fn schema_of(tables: &[(&str, &str, &[&str])]) -> Schema {
+    let relations: serde_json::Map<String, serde_json::Value> = tables
+        .iter()
+        .map(|(schema, name, columns)| {
+            let columns: Vec<_> = columns
+                .iter()
+                .map(|column| {
+                    let mut words = column.splitn(3, ' ');
+                    let name = words.next().unwrap_or_default();
+                    let ty = words.next().expect("a column type");
+                    let flags = words.next().unwrap_or_default();
+                    json!({
+                        "name": name, "ty": ty,
+                        "not_null": flags.contains("not null"),
+                        "generated": flags.contains("generated"),
+                        "primary_key": flags.contains("primary key"),
+                    })
+                })
+                .collect();
+            let relation = json!({
+                "schema": schema, "name": name, "kind": "table", "signature": "1/2/",
+                "columns": columns,
+            });
+            (format!("{schema}.{name}"), relation)
+        })
+        .collect();
+    serde_json::from_value(json!({ "relations": relations })).expect("schema")
+}
+
+/// Results of `columns`, each its name and type like `id int4`, holding
+/// `rows`, `None` for `NULL`.
+#[allow(dead_code)] // Not every test binary needs results.
+pub fn results_of<S, R>(columns: &[&str], rows: impl IntoIterator<Item = R>) -> ResultSet
+where
+    S: AsRef<str>,
+    R: IntoIterator<Item = Option<S>>,
+{
+    let columns = columns
+        .iter()
+        .map(|column| {
+            let (name, ty) = column.split_once(' ').expect("a column type");
+            Column::new(name, ty)
+        })
+        .collect();
+    let mut results = ResultSet::new(columns);
+    results.rows = rows.into_iter().collect();
+    results
+}
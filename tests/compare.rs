@@ -0,0 +1,91 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod common;
+
+use common::results_of;
+use dbvi::commands;
+use dbvi::compare::{Change, Compare};
+use dbvi::redact::Redactions;
+use dbvi::results::ResultSet;
+use dbvi::state::Command;
+use ratatui::{Terminal, backend::TestBackend};
+
+fn orders(rows: &[(&str, &str)]) -> ResultSet {
+    let rows = rows.iter().map(|(id, status)| [Some(*id), Some(*status)]);
+    results_of(&["id int4", "status text"], rows)
+}
+
+#[test]
+fn matches_rows_by_key() {
+    let prod = orders(&[("1", "paid"), ("2", "new"), ("3", "paid"), ("5", "new")]);
+    let staging = orders(&[("1", "paid"), ("3", "refunded"), ("4", "new"), ("5", "new")]);
+    let compare = Compare::new(["prod", "staging"], &prod, &staging, &Redactions::default());
+    assert_eq!(
+        compare.changes(),
+        [
+            Change::Same,
+            Change::Removed,
+            Change::Changed,
+            Change::Added,
+            Change::Same
+        ]
+    );
+    assert_eq!(compare.differing(), 3);
+    assert_eq!(
+        compare.title,
+        "prod │ staging: 2 same, 1 changed, 1 only in prod, 1 only in staging (d for only those)"
+    );
+}
+
+#[test]
+fn matches_rows_in_another_order() {
+    let prod = orders(&[("1", "paid"), ("2", "new")]);
+    let staging = orders(&[("2", "new"), ("1", "paid")]);
+    let mut compare = Compare::new(["prod", "staging"], &prod, &staging, &Redactions::default());
+    assert_eq!(compare.differing(), 0);
+    compare.toggle_only_differing();
+    assert!(compare.changes().is_empty());
+}
+
+#[test]
+fn renders_side_by_side() {
+    let prod = orders(&[("1", "paid"), ("2", "new")]);
+    let staging = orders(&[("1", "refunded"), ("3", "new")]);
+    let mut compare = Compare::new(["prod", "staging"], &prod, &staging, &Redactions::default());
+    let mut terminal = Terminal::new(TestBackend::new(40, 5)).unwrap();
+    terminal
+        .draw(|f| compare.render(f, f.area(), true))
+        .unwrap();
+    let screen: Vec<String> = terminal
+        .backend()
+        .buffer()
+        .content
+        .chunks(40)
+        .map(|row| row.iter().map(|cell| cell.symbol()).collect::<String>())
+        .collect();
+    assert_eq!(screen[0].trim_end(), "  id   status       │ id   status");
+    assert_eq!(screen[1].trim_end(), "~ 1    paid         │ 1    refunded");
+    assert_eq!(screen[2].trim_end(), "- 2    new          │");
+    assert_eq!(screen[3].trim_end(), "+                   │ 3    new");
+}
+
+#[test]
+fn parses() {
+    assert_eq!(
+        commands::parse("compare prod staging"),
+        Ok(Command::Compare(["prod".into(), "staging".into()]))
+    );
+    assert!(commands::parse("compare prod").is_err());
+}
@@ -0,0 +1,100 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod common;
+
+use common::schema_of;
+use dbvi::completion::{Menu, enum_labels, from_schema, word_start};
+use dbvi::db::schema::Schema;
+use dbvi::editor::{Buffer, Cursor};
+
+fn schema() -> Schema {
+    let mut schema = schema_of(&[(
+        "public",
+        "orders",
+        &[
+            "id integer not null generated primary key",
+            "ordered_at timestamp",
+            "status order_status not null",
+        ],
+    )]);
+    let status = &mut schema.relations.get_mut("public.orders").unwrap().columns[2];
+    status.labels = vec!["new".into(), "shipped".into(), "can't ship".into()];
+    schema
+}
+
+#[test]
+fn word_starts() {
+    assert_eq!(word_start("select ord", 10), 7);
+    assert_eq!(word_start("select o.id", 11), 9);
+    assert_eq!(word_start("ord", 3), 0);
+    assert_eq!(word_start("select ", 7), 7);
+}
+
+#[test]
+fn schema_names() {
+    let labels = |prefix| -> Vec<String> {
+        from_schema(&schema(), prefix)
+            .into_iter()
+            .map(|item| item.label)
+            .collect()
+    };
+    assert_eq!(labels("ORD"), ["ordered_at", "orders"]);
+    assert_eq!(labels("i"), ["id"]);
+    assert!(labels("x").is_empty());
+}
+
+#[test]
+fn accept_replaces_the_word() {
+    let mut buffer = Buffer::from_text("query", "select * from ord");
+    buffer.cursor = Cursor { row: 0, col: 17 };
+    let mut menu = Menu::new(from_schema(&schema(), "ord"), &buffer).unwrap();
+    menu.move_by(1);
+    menu.accept(&mut buffer);
+    assert_eq!(buffer.line(), "select * from orders");
+    assert!(Menu::new(Vec::new(), &buffer).is_none());
+}
+
+#[test]
+fn enum_labels_in_quotes() {
+    let schema = schema();
+    let labels = |line: &str| -> Option<(usize, Vec<String>)> {
+        let (start, items) = enum_labels(&schema, line, line.chars().count())?;
+        Some((start, items.into_iter().map(|item| item.insert).collect()))
+    };
+    assert_eq!(
+        labels("where status = '"),
+        Some((
+            16,
+            vec!["new'".into(), "shipped'".into(), "can''t ship'".into()]
+        ))
+    );
+    assert_eq!(
+        labels("where o.status<>'SH"),
+        Some((17, vec!["shipped'".into()]))
+    );
+    assert_eq!(
+        labels("where status = 'can''t"),
+        Some((16, vec!["can''t ship'".into()]))
+    );
+    assert_eq!(labels("where status = 'new' and"), None);
+    assert_eq!(labels("where status like '"), None);
+    assert_eq!(labels("where ordered_at = '"), Some((20, vec![])));
+
+    let mut buffer = Buffer::from_text("query", "where status = 'sh'");
+    buffer.cursor = Cursor { row: 0, col: 18 };
+    let (start, items) = enum_labels(&schema, buffer.line(), 18).unwrap();
+    Menu::at(items, start).unwrap().accept(&mut buffer);
+    assert_eq!(buffer.line(), "where status = 'shipped'");
+}
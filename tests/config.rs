@@ -0,0 +1,123 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use dbvi::config::{Config, Env};
+use dbvi::keymap::Mapping;
+use ratatui::style::Color;
+
+#[test]
+fn environments() {
+    let config: Config = toml::from_str(
+        r##"
+        [profiles.prod]
+        url = "postgres://app@db.internal/app"
+        env = "prod"
+        init = ["SET TIME ZONE 'UTC'"]
+
+        [profiles.hotfix]
+        env = "prod"
+        color = "#ff8800"
+        read_only = false
+
+        [profiles.staging]
+        env = "staging"
+
+        [profiles.local]
+        "##,
+    )
+    .unwrap();
+    let profile = |name: &str| &config.profiles[name];
+
+    let prod = profile("prod").environment().unwrap();
+    assert!(prod.is_prod());
+    assert_eq!((prod.env.name(), prod.color), ("prod", Color::Red));
+    assert!(profile("prod").read_only());
+    // Any connection to it comes up read-only, not only the session's.
+    let init = config.init_statements(Some(profile("prod"))).unwrap();
+    assert_eq!(
+        init[init.len() - 2..],
+        [
+            "SET TIME ZONE 'UTC'",
+            "SET default_transaction_read_only = on"
+        ]
+    );
+
+    // Writable, but still prod as far as confirming writes goes.
+    let hotfix = profile("hotfix");
+    assert!(!hotfix.read_only());
+    let init = config.init_statements(Some(hotfix)).unwrap();
+    assert!(!init.iter().any(|sql| sql.contains("read_only")));
+    assert_eq!(
+        hotfix.environment().unwrap().color,
+        Color::Rgb(0xff, 0x88, 0x00)
+    );
+
+    let staging = profile("staging");
+    assert_eq!(staging.environment().unwrap().env, Env::Staging);
+    assert!(!staging.read_only());
+    assert!(profile("local").environment().is_none());
+
+    assert!(toml::from_str::<Config>("[profiles.x]\nenv = \"qa\"").is_err());
+}
+
+#[test]
+fn profile_overrides() {
+    let parse = || {
+        toml::from_str::<Config>(
+            r#"
+        [options]
+        autolimit = 0
+        wrap = true
+
+        [map.normal]
+        "<leader>r" = ":run<CR>"
+        gq = ":queue<CR>"
+
+        [profiles.prod]
+        env = "prod"
+
+        [profiles.prod.options]
+        autolimit = 500
+
+        [profiles.prod.map.normal]
+        "<leader>r" = { command = "queue" }
+
+        [profiles.hotfix]
+        env = "prod"
+        read_only = false
+        "#,
+        )
+        .unwrap()
+    };
+    let mut config = parse();
+    config.apply_profile("prod");
+    assert_eq!(config.options["readonly"].as_bool(), Some(true));
+    assert_eq!(config.options["autolimit"].as_integer(), Some(500));
+    assert_eq!(config.options["wrap"].as_bool(), Some(true));
+    assert!(matches!(
+        &config.map.normal["<leader>r"],
+        Mapping::Command { command } if command == "queue"
+    ));
+    assert!(matches!(&config.map.normal["gq"], Mapping::Keys(keys) if keys == ":queue<CR>"));
+
+    // A writable profile leaves readonly to the options.
+    let mut hotfix = parse();
+    hotfix.apply_profile("hotfix");
+    assert!(!hotfix.options.contains_key("readonly"));
+    assert_eq!(hotfix.options["autolimit"].as_integer(), Some(0));
+    assert!(matches!(
+        &hotfix.map.normal["<leader>r"],
+        Mapping::Keys(keys) if keys == ":run<CR>"
+    ));
+}
@@ -0,0 +1,81 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::time::Duration;
+
+use dbvi::dashboard::{Activity, Dashboard, ServerStats, uptime};
+use ratatui::{Terminal, backend::TestBackend};
+
+#[test]
+fn activity() {
+    let mut activity = Activity::default();
+    assert_eq!(activity.average(), None);
+    activity.record(Duration::from_millis(30), &Ok(10));
+    activity.record(Duration::from_millis(10), &Err("syntax error".into()));
+    assert_eq!(
+        (activity.queries, activity.failed, activity.rows),
+        (2, 1, 10)
+    );
+    assert_eq!(activity.average(), Some(Duration::from_millis(20)));
+}
+
+#[test]
+fn ratios() {
+    let stats = ServerStats {
+        blocks_hit: 990,
+        blocks_read: 10,
+        commits: 0,
+        rollbacks: 0,
+        connections: 5,
+        max_connections: 100,
+    };
+    assert_eq!(stats.cache_hit_ratio(), Some(0.99));
+    assert_eq!(stats.commit_ratio(), None);
+    assert_eq!(stats.connection_ratio(), Some(0.05));
+    assert_eq!(uptime(Duration::from_secs(42)), "42s");
+    assert_eq!(uptime(Duration::from_secs(3733)), "1h 02m 13s");
+}
+
+#[test]
+fn render() {
+    let mut activity = Activity::default();
+    activity.record(Duration::from_millis(5), &Ok(3));
+    let dashboard = Dashboard {
+        server: Some(Ok(ServerStats {
+            blocks_hit: 99,
+            blocks_read: 1,
+            commits: 9,
+            rollbacks: 1,
+            connections: 5,
+            max_connections: 100,
+        })),
+        fetched_at: None,
+    };
+    let mut terminal = Terminal::new(TestBackend::new(80, 10)).unwrap();
+    terminal
+        .draw(|f| dashboard.render(f, f.area(), &activity))
+        .unwrap();
+    let screen: String = terminal
+        .backend()
+        .buffer()
+        .content
+        .iter()
+        .map(|cell| cell.symbol())
+        .collect();
+    assert!(screen.contains("Queries      1"), "{screen}");
+    assert!(screen.contains("3 returned or changed"), "{screen}");
+    assert!(screen.contains(" 99.0%"), "{screen}");
+    assert!(screen.contains("9 committed, 1 rolled back"), "{screen}");
+    assert!(screen.contains("5 of 100"), "{screen}");
+}
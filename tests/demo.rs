@@ -0,0 +1,70 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use dbvi::db::demo::Demo;
+
+fn query(sql: &str) -> Result<Vec<Vec<Option<String>>>, String> {
+    let (results, _) = Demo::new().query(sql)?;
+    Ok(results
+        .expect("rows")
+        .rows
+        .iter()
+        .map(|row| row.to_vec())
+        .collect())
+}
+
+#[test]
+fn select_columns() {
+    let rows = query("select name, id from products where id = 1").unwrap();
+    assert_eq!(
+        rows,
+        [vec![Some("Mechanical keyboard".into()), Some("1".into())]]
+    );
+}
+
+#[test]
+fn where_order_and_limit() {
+    let rows =
+        query("SELECT id FROM public.products WHERE price > 50 ORDER BY price DESC LIMIT 2;")
+            .unwrap();
+    assert_eq!(rows, [vec![Some("3".into())], vec![Some("6".into())]]);
+}
+
+#[test]
+fn quoted_values() {
+    let rows = query("select id from customers where name = 'Tony Hoare'").unwrap();
+    assert_eq!(rows, [vec![Some("10".into())]]);
+}
+
+#[test]
+fn last_statement_wins() {
+    let (results, count) = Demo::new()
+        .query("select * from orders; select * from customers")
+        .unwrap();
+    assert_eq!(results.unwrap().columns[1].name, "name");
+    assert_eq!(count, 12);
+}
+
+#[test]
+fn errors() {
+    assert_eq!(
+        query("select * from nope"),
+        Err("relation \"nope\" does not exist".into())
+    );
+    assert_eq!(
+        query("select nope from orders"),
+        Err("column \"nope\" does not exist".into())
+    );
+    assert!(query("delete from orders").is_err());
+}
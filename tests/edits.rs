@@ -0,0 +1,66 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod common;
+
+use common::results_of;
+use dbvi::edits::{Edits, Target};
+use dbvi::results::ResultSet;
+
+fn results() -> ResultSet {
+    results_of(
+        &["id int4", "name text", "total numeric"],
+        [
+            [Some("1"), Some("ann"), Some("10")],
+            [Some("2"), Some("bob"), None],
+        ],
+    )
+}
+
+fn edits() -> Edits {
+    Edits::new(Target {
+        table: "people".into(),
+        columns: vec![Some("id".into()), Some("name".into()), None],
+        key: vec![0],
+    })
+}
+
+#[test]
+fn changes() {
+    let mut results = results();
+    let mut edits = edits();
+    assert!(edits.change(&mut results, 0, 0, Some("3".into())).is_err());
+    assert!(edits.change(&mut results, 0, 2, Some("3".into())).is_err());
+    edits
+        .change(&mut results, 1, 1, Some("bo'b".into()))
+        .unwrap();
+    edits.change(&mut results, 0, 1, None).unwrap();
+    assert_eq!(edits.len(), 2);
+    assert_eq!(results.rows.cell(1, 1), Some("bo'b"));
+    assert_eq!(
+        edits.to_sql(&results),
+        "UPDATE people SET \"name\" = NULL WHERE \"id\" = 1;\n\
+         UPDATE people SET \"name\" = 'bo''b' WHERE \"id\" = 2;\n"
+    );
+
+    // The changes follow the rows about.
+    results.sort(0, true);
+    assert_eq!(
+        edits.cells(&results).into_iter().collect::<Vec<_>>(),
+        [(0, 1), (1, 1)]
+    );
+    edits.discard(&mut results);
+    assert_eq!(results.rows.cell(0, 1), Some("bob"));
+    assert_eq!(results.rows.cell(1, 1), Some("ann"));
+}
@@ -0,0 +1,113 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod common;
+
+use common::Harness;
+use dbvi::elements::{Node, parse};
+use dbvi::results::{Column, ResultSet};
+
+fn value(text: &str) -> Node {
+    Node::Value(Some(text.into()))
+}
+
+#[test]
+fn arrays() {
+    let ints = Column::new("a", "int4[]");
+    assert_eq!(
+        parse(&ints, "{1,NULL,3}"),
+        Some(Node::Array(vec![value("1"), Node::Value(None), value("3")]))
+    );
+    assert_eq!(parse(&ints, "{}"), Some(Node::Array(vec![])));
+    assert_eq!(parse(&ints, "[0:1]={5,6}").unwrap().render(), "{5, 6}");
+    let matrix = parse(&ints, "{{1,2},{3,4}}").unwrap();
+    assert_eq!(matrix.render(), "{{1, 2}, {3, 4}}");
+    assert_eq!(matrix.literal(), "{{1,2},{3,4}}");
+
+    let texts = parse(
+        &Column::new("t", "text[]"),
+        r#"{plain,"a b","say \"hi\"","NULL",NULL}"#,
+    )
+    .unwrap();
+    assert_eq!(
+        texts,
+        Node::Array(vec![
+            value("plain"),
+            value("a b"),
+            value("say \"hi\""),
+            value("NULL"),
+            Node::Value(None),
+        ])
+    );
+    assert_eq!(
+        texts.render(),
+        r#"{plain, "a b", "say \"hi\"", "NULL", NULL}"#
+    );
+    assert_eq!(texts.literal(), r#"{plain,"a b","say \"hi\"","NULL",NULL}"#);
+    assert_eq!(parse(&Column::new("t", "text"), "{1,2}"), None);
+}
+
+#[test]
+fn composites() {
+    let pair = parse(&Column::new("p", "?"), r#"(1,"y z")"#).unwrap();
+    assert_eq!(pair, Node::Record(vec![value("1"), value("y z")]));
+    assert_eq!(pair.render(), r#"(1, "y z")"#);
+    assert_eq!(pair.literal(), r#"(1,"y z")"#);
+    assert_eq!(
+        parse(&Column::new("r", "record"), "(1,)").unwrap().render(),
+        "(1, NULL)"
+    );
+
+    let pairs = parse(&Column::new("ps", "?"), r#"{"(1,q)","(2,\"a b\")"}"#).unwrap();
+    assert_eq!(
+        pairs,
+        Node::Array(vec![
+            Node::Record(vec![value("1"), value("q")]),
+            Node::Record(vec![value("2"), value("a b")]),
+        ])
+    );
+    assert_eq!(pairs.render(), r#"{(1, q), (2, "a b")}"#);
+    assert_eq!(pairs.literal(), r#"{"(1,q)","(2,\"a b\")"}"#);
+
+    let nested = parse(&Column::new("r", "record"), r#"(1,"{2,3}")"#).unwrap();
+    assert_eq!(
+        nested,
+        Node::Record(vec![value("1"), Node::Array(vec![value("2"), value("3")])])
+    );
+}
+
+#[test]
+fn viewer() {
+    let mut harness = Harness::new();
+    let mut results = ResultSet::new(vec![Column::new("id", "int4"), Column::new("m", "int4[]")]);
+    results.rows = vec![vec![Some("1".into()), Some("{{1,2},{3,4}}".into())]].into();
+    harness.state.show_results(Some(results));
+    let screen = harness.render();
+    assert!(screen.contains("{{1, 2}, {3, 4}}"), "{screen}");
+
+    harness.keys("<C-w>kl<CR>");
+    let screen = harness.render();
+    assert!(screen.contains("Elements of m, row 1"), "{screen}");
+    assert!(screen.contains("▸ [2]   {3, 4}"), "{screen}");
+
+    harness.keys("jl");
+    let screen = harness.render();
+    assert!(screen.contains("▾ [2]   array of 2"), "{screen}");
+    harness.keys("jjy");
+    assert_eq!(harness.state.status(), "Element yanked");
+    harness.keys("hy<Esc>");
+    assert!(harness.render().contains("Results (1 rows)"));
+    harness.keys("<C-w>jp");
+    assert_eq!(harness.state.text(), "{3,4}");
+}
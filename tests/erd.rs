@@ -0,0 +1,87 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod common;
+
+use common::schema_of;
+use dbvi::db::catalog::ForeignKey;
+use dbvi::db::schema::Schema;
+use dbvi::erd::Erd;
+
+fn schema() -> Schema {
+    schema_of(&[
+        (
+            "public",
+            "items",
+            &[
+                "id integer not null primary key",
+                "order_id integer not null",
+            ],
+        ),
+        ("public", "orders", &["id integer not null primary key"]),
+        ("public", "notes", &["id integer not null primary key"]),
+    ])
+}
+
+fn keys() -> Vec<ForeignKey> {
+    vec![ForeignKey {
+        name: "items_order_id_fkey".into(),
+        table: "public.items".into(),
+        columns: vec!["order_id".into()],
+        references: "public.orders".into(),
+        referenced: vec!["id".into()],
+    }]
+}
+
+#[test]
+fn diagram() {
+    let mut erd = Erd::new(&schema(), &keys(), Some("items"), &[]).unwrap();
+    assert_eq!((erd.tables(), erd.keys()), (2, 1));
+    // The referenced table comes first, the wire running up to its key.
+    assert_eq!(
+        erd.text(),
+        [
+            "┌─ orders ──────────────┐",
+            "│ id        integer  PK │◀─┐",
+            "└───────────────────────┘  │",
+            "                           │",
+            "┌─ items ───────────────┐  │",
+            "│ id        integer  PK │  │",
+            "│ order_id  integer  FK ├──┘",
+            "└───────────────────────┘",
+        ]
+        .join("\n")
+    );
+    erd.zoom(false);
+    erd.zoom(false);
+    assert_eq!(
+        erd.text(),
+        [
+            "┌─ orders ─┐◀─┐",
+            "└──────────┘  │",
+            "              │",
+            "┌─ items ──┐──┘",
+            "└──────────┘",
+        ]
+        .join("\n")
+    );
+
+    assert_eq!(
+        Erd::new(&schema(), &keys(), Some("public"), &[])
+            .unwrap()
+            .tables(),
+        3
+    );
+    assert!(Erd::new(&schema(), &keys(), Some("nowhere"), &[]).is_err());
+}
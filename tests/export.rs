@@ -0,0 +1,182 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use dbvi::export;
+use dbvi::results::{Column, ResultSet};
+
+#[test]
+fn table() {
+    let mut results = ResultSet::new(vec![Column::new("id", "int4"), Column::new("name", "text")]);
+    results.rows = vec![
+        vec![Some("7".into()), Some("Ada\nLovelace".into())],
+        vec![Some("12".into()), None],
+    ]
+    .into();
+    let mut out = Vec::new();
+    export::write_table(&results, &mut out).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        " id |     name\n\
+         ----+--------------\n  \
+           7 | Ada↵Lovelace\n \
+          12 | NULL\n\
+         (2 rows)\n"
+    );
+}
+
+#[test]
+fn markdown() {
+    let mut results = ResultSet::new(vec![Column::new("id", "int4"), Column::new("name", "text")]);
+    results.rows = vec![
+        vec![Some("7".into()), Some("Ada\nLove|lace".into())],
+        vec![Some("12".into()), None],
+    ]
+    .into();
+    let mut out = Vec::new();
+    export::write_markdown(&results, &mut out).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "| id | name |\n\
+         | ---: | --- |\n\
+         | 7 | Ada<br>Love\\|lace |\n\
+         | 12 |  |\n"
+    );
+}
+
+#[test]
+fn xlsx() {
+    use std::io::Read;
+
+    let mut results = ResultSet::new(vec![
+        Column::new("id", "int4"),
+        Column::new("paid", "bool"),
+        Column::new("day", "date"),
+        Column::new("at", "timestamptz"),
+        Column::new("note", "text"),
+    ]);
+    results.rows = vec![
+        vec![
+            Some("7".into()),
+            Some("t".into()),
+            Some("2024-01-01".into()),
+            Some("2024-01-01 18:00:00+02".into()),
+            Some("<b> & \"co\"".into()),
+        ],
+        vec![Some("12".into()), Some("f".into()), None, None, None],
+    ]
+    .into();
+    let mut out = Vec::new();
+    export::write_xlsx(&results, &mut out).unwrap();
+
+    let mut zip = zip::ZipArchive::new(std::io::Cursor::new(out)).unwrap();
+    let mut sheet = String::new();
+    zip.by_name("xl/worksheets/sheet1.xml")
+        .unwrap()
+        .read_to_string(&mut sheet)
+        .unwrap();
+    for cell in [
+        r#"<c r="A1" s="1" t="inlineStr"><is><t>id</t></is></c>"#,
+        r#"<c r="A2"><v>7</v></c>"#,
+        r#"<c r="B2" t="b"><v>1</v></c>"#,
+        r#"<c r="C2" s="2"><v>45292</v></c>"#,
+        r#"<c r="D2" s="3"><v>45292.75</v></c>"#,
+        r#"<t xml:space="preserve">&lt;b&gt; &amp; &quot;co&quot;</t>"#,
+        r#"<c r="B3" t="b"><v>0</v></c></row>"#,
+        r#"<col min="4" max="4" width="24" customWidth="1"/>"#,
+    ] {
+        assert!(sheet.contains(cell), "{cell} not in {sheet}");
+    }
+    assert!(zip.by_name("xl/styles.xml").is_ok());
+}
+
+#[test]
+fn html() {
+    let mut results = ResultSet::new(vec![Column::new("id", "int4"), Column::new("name", "text")]);
+    results.rows = vec![
+        vec![Some("7".into()), Some("<Ada> & co".into())],
+        vec![Some("12".into()), None],
+    ]
+    .into();
+    let mut out = Vec::new();
+    export::write_html(&results, true, &mut out).unwrap();
+    let html = String::from_utf8(out).unwrap();
+    assert!(html.contains(
+        "<tr><th class=\"sortable\" title=\"int4\">id</th><th class=\"sortable\" title=\"text\">name</th></tr>"
+    ));
+    assert!(html.contains("<tr><td class=\"number\">7</td><td>&lt;Ada&gt; &amp; co</td></tr>"));
+    assert!(html.contains("<td class=\"null\">NULL</td>"));
+    assert!(html.contains("<caption>2 rows</caption>"));
+    assert!(html.contains("<script>"));
+
+    let mut out = Vec::new();
+    export::write_html(&results, false, &mut out).unwrap();
+    let html = String::from_utf8(out).unwrap();
+    assert!(html.contains("<th title=\"int4\">id</th>"));
+    assert!(!html.contains("<script>"));
+}
+
+#[test]
+fn pandas_and_values() {
+    let mut results = ResultSet::new(vec![
+        Column::new("id", "int4"),
+        Column::new("name", "text"),
+        Column::new("ok", "bool"),
+        Column::new("price", "money"),
+    ]);
+    results.rows = vec![
+        vec![
+            Some("7".into()),
+            Some("O'Brien \"Ada\"\n".into()),
+            Some("t".into()),
+            Some("$1.50".into()),
+        ],
+        vec![Some("12".into()), None, Some("f".into()), None],
+    ]
+    .into();
+    assert_eq!(
+        export::to_pandas(&results),
+        "pandas.DataFrame({\n    \"id\": [7, 12],\n    \"name\": [\"O'Brien \\\"Ada\\\"\\n\", None],\n    \
+         \"ok\": [True, False],\n    \"price\": [\"$1.50\", None],\n})\n"
+    );
+    assert_eq!(
+        export::to_values(&results),
+        "VALUES\n    (7, 'O''Brien \"Ada\"\n', TRUE, '$1.50'::money),\n    (12, NULL, FALSE, NULL)\n"
+    );
+}
+
+#[test]
+fn statements() {
+    let mut results = ResultSet::new(vec![Column::new("id", "int4"), Column::new("name", "text")]);
+    results.rows = vec![
+        vec![Some("7".into()), Some("O'Brien".into())],
+        vec![Some("12".into()), None],
+    ]
+    .into();
+    assert_eq!(
+        export::to_inserts(&results, "people"),
+        "INSERT INTO people (\"id\", \"name\") VALUES (7, 'O''Brien');\n\
+         INSERT INTO people (\"id\", \"name\") VALUES (12, NULL);\n"
+    );
+    let key = ["id".to_string()];
+    assert_eq!(
+        export::to_delete(&results, "people", &key).unwrap(),
+        "DELETE FROM people\nWHERE (\"id\") IN (\n    (7),\n    (12)\n);\n"
+    );
+    assert_eq!(
+        export::to_update(&results, "people", &key, 1).unwrap(),
+        "UPDATE people\nSET \"name\" = 'O''Brien'\nWHERE (\"id\") IN (\n    (7),\n    (12)\n);\n"
+    );
+    assert!(export::to_delete(&results, "people", &[]).is_err());
+    assert!(export::to_delete(&results, "people", &["uid".into()]).is_err());
+}
@@ -0,0 +1,110 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod common;
+
+use common::Harness;
+use dbvi::geometry::{from_hex, parse};
+use dbvi::results::{Column, ResultSet};
+
+/// Little endian EWKB of type `kind`, with `srid` if given, then `counts`
+/// and `coords` as they come.
+fn ewkb(kind: u32, srid: Option<u32>, counts: &[u32], coords: &[f64]) -> String {
+    let mut bytes = vec![1];
+    let flag = if srid.is_some() { 0x2000_0000 } else { 0 };
+    bytes.extend((kind | flag).to_le_bytes());
+    bytes.extend(srid.iter().flat_map(|srid| srid.to_le_bytes()));
+    bytes.extend(counts.iter().flat_map(|count| count.to_le_bytes()));
+    bytes.extend(coords.iter().flat_map(|n| n.to_le_bytes()));
+    bytes.iter().map(|b| format!("{b:02X}")).collect()
+}
+
+fn square() -> String {
+    ewkb(3, None, &[1, 5], &[0., 0., 4., 0., 4., 4., 0., 4., 0., 0.])
+}
+
+#[test]
+fn wkt() {
+    let point = from_hex(&ewkb(1, Some(4326), &[], &[1., 2.5])).unwrap();
+    assert_eq!(point.wkt(), "SRID=4326;POINT(1 2.5)");
+    assert_eq!(point.kind(), "POINT");
+
+    let square = from_hex(&square()).unwrap();
+    assert_eq!(square.wkt(), "POLYGON((0 0,4 0,4 4,0 4,0 0))");
+    assert_eq!(square.summary(), "POLYGON((0 0,4 0,4 4,…))");
+    assert_eq!(square.bounds(), Some(((0., 0.), (4., 4.))));
+
+    // Big endian ISO WKB with a Z, in a multipoint.
+    let mut hex = String::from("00000003EC00000001");
+    hex += "00000003E9";
+    for n in [1f64, 2., 3.] {
+        hex += &n
+            .to_be_bytes()
+            .iter()
+            .map(|b| format!("{b:02X}"))
+            .collect::<String>();
+    }
+    assert_eq!(from_hex(&hex).unwrap().wkt(), "MULTIPOINT Z((1 2 3))");
+
+    let empty = from_hex(&ewkb(1, None, &[], &[f64::NAN, f64::NAN])).unwrap();
+    assert_eq!(empty.wkt(), "POINT EMPTY");
+    assert_eq!(empty.bounds(), None);
+}
+
+#[test]
+fn not_geometry() {
+    let unknown = Column::new("g", "?");
+    assert!(parse(&unknown, &square()).is_some());
+    assert!(parse(&Column::new("g", "text"), &square()).is_none());
+    assert!(parse(&unknown, "(1,2)").is_none());
+    assert!(parse(&unknown, "0101").is_none());
+    // One byte short, or one too many.
+    assert!(parse(&unknown, &square()[2..]).is_none());
+    assert!(parse(&unknown, &(square() + "00")).is_none());
+}
+
+#[test]
+fn plot() {
+    let square = from_hex(&square()).unwrap();
+    assert_eq!(
+        square.plot(9, 5),
+        [
+            "+-------+",
+            "|       |",
+            "|       |",
+            "|       |",
+            "+-------+"
+        ]
+    );
+    let line = from_hex(&ewkb(2, None, &[2], &[0., 0., 2., 2.])).unwrap();
+    assert_eq!(line.plot(5, 3), ["   /+", " //", "+"]);
+    let point = from_hex(&ewkb(1, None, &[], &[7., 7.])).unwrap();
+    assert_eq!(point.plot(5, 3), ["", "  *", ""]);
+}
+
+#[test]
+fn enter_on_a_geometry() {
+    let mut harness = Harness::new();
+    let mut results = ResultSet::new(vec![Column::new("g", "?")]);
+    results.rows = vec![vec![Some(square())]].into();
+    harness.state.show_results(Some(results));
+    let screen = harness.render();
+    assert!(screen.contains("POLYGON((0 0,4 0,4 4,…))"), "{screen}");
+    harness.keys("<C-w>k<CR>");
+    let screen = harness.render();
+    assert!(screen.contains("POLYGON of g, row 1"), "{screen}");
+    assert!(screen.contains("x 0 … 4, y 0 … 4, 5 points"), "{screen}");
+    harness.keys("q");
+    assert!(!harness.render().contains("POLYGON of g"));
+}
@@ -0,0 +1,180 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod common;
+
+use common::schema_of;
+use dbvi::db::schema::Schema;
+use dbvi::hover::{self, Target};
+use dbvi::popup::Popup;
+
+fn schema() -> Schema {
+    schema_of(&[
+        (
+            "public",
+            "orders",
+            &["id integer", "customer_id integer", "total integer"],
+        ),
+        ("public", "customers", &["id integer", "name integer"]),
+    ])
+}
+
+#[test]
+fn name_at() {
+    let line = "select o.total, sum(x) from public.orders o";
+    assert_eq!(hover::name_at(line, 7).as_deref(), Some("o"));
+    assert_eq!(hover::name_at(line, 10).as_deref(), Some("o.total"));
+    assert_eq!(hover::name_at(line, 17).as_deref(), Some("sum"));
+    assert_eq!(hover::name_at(line, 40).as_deref(), Some("public.orders"));
+    assert_eq!(hover::name_at(line, 14), None);
+}
+
+#[test]
+fn tables() {
+    let tables = |sql| {
+        hover::tables(sql)
+            .into_iter()
+            .map(|table| (table.name, table.alias))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        tables(
+            "select * from orders as o, customers \
+             join \"Items\" i on i.id = o.id where o.id in (select 1 from dual)"
+        ),
+        [
+            ("orders".to_string(), Some("o".to_string())),
+            ("customers".to_string(), None),
+            ("\"Items\"".to_string(), Some("i".to_string())),
+            ("dual".to_string(), None),
+        ]
+    );
+    assert_eq!(
+        tables("update orders set total = 0"),
+        [("orders".to_string(), None)]
+    );
+    assert!(tables("select * from generate_series(1, 3) n").is_empty());
+    assert_eq!(hover::tables("select 1 from  orders")[0].at, 15);
+}
+
+#[test]
+fn target() {
+    let schema = schema();
+    let sql =
+        "select o.total, name, count(*) from orders o join customers c on c.id = o.customer_id";
+    let target = |name| hover::target(sql, name, &schema, &[]);
+    let column = |table: &str, column: &str| Target::Column {
+        table: table.into(),
+        column: column.into(),
+    };
+    assert_eq!(target("o.total"), column("orders", "total"));
+    assert_eq!(target("name"), column("\"public\".\"customers\"", "name"));
+    assert_eq!(target("o"), Target::Relation("orders".into()));
+    assert_eq!(
+        target("customers"),
+        Target::Relation("\"public\".\"customers\"".into())
+    );
+    assert_eq!(target("count"), Target::Name("count".into()));
+    assert_eq!(
+        target("public.orders.id"),
+        column("\"public\".\"orders\"", "id")
+    );
+}
+
+#[test]
+fn relation_popup_marks_disabled_triggers_and_not_valid_constraints() {
+    use dbvi::db::catalog::{Constraint, Summary, Trigger};
+    let summary = Summary {
+        name: "orders".into(),
+        kind: "table".into(),
+        comment: None,
+        columns: Vec::new(),
+        triggers: vec![
+            Trigger {
+                name: "audit".into(),
+                enabled: false,
+            },
+            Trigger {
+                name: "touch".into(),
+                enabled: true,
+            },
+        ],
+        constraints: vec![
+            Constraint {
+                name: "orders_pkey".into(),
+                kind: "primary key".into(),
+                valid: true,
+            },
+            Constraint {
+                name: "positive".into(),
+                kind: "check".into(),
+                valid: false,
+            },
+        ],
+        partition_of: None,
+        partitions: 0,
+    };
+    let Popup::Text { title, lines } = hover::relation_popup(&summary) else {
+        panic!("not text");
+    };
+    assert_eq!(title, "table orders");
+    assert_eq!(
+        lines,
+        [
+            "triggers",
+            "  audit  DISABLED",
+            "  touch",
+            "",
+            "constraints",
+            "  orders_pkey  primary key",
+            "  positive     check  NOT VALID",
+            "",
+            "D disables the triggers, E enables them, V validates the constraints",
+        ]
+    );
+}
+
+#[test]
+fn relation_popup_says_what_it_partitions() {
+    use dbvi::db::catalog::Summary;
+    let table = |name: &str, partition_of: Option<&str>, partitions| Summary {
+        name: name.into(),
+        kind: "partitioned table".into(),
+        comment: None,
+        columns: Vec::new(),
+        triggers: Vec::new(),
+        constraints: Vec::new(),
+        partition_of: partition_of.map(String::from),
+        partitions,
+    };
+    let lines = |summary| match hover::relation_popup(&summary) {
+        Popup::Text { lines, .. } => lines,
+        _ => panic!("not text"),
+    };
+    assert_eq!(
+        lines(table("events", None, 2)),
+        ["2 partitions, :partitions events for the tree"]
+    );
+    assert_eq!(
+        lines(table(
+            "events_2025",
+            Some("events FOR VALUES FROM ('2025-01-01') TO ('2026-01-01')"),
+            1
+        )),
+        [
+            "partition of events FOR VALUES FROM ('2025-01-01') TO ('2026-01-01')",
+            "1 partition, :partitions events_2025 for the tree",
+        ]
+    );
+}
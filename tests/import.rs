@@ -0,0 +1,29 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use dbvi::import;
+
+#[test]
+fn statements() {
+    let columns = vec!["id".to_string(), "Full Name".to_string()];
+    let types = vec!["bigint".to_string(), "text".to_string()];
+    assert_eq!(
+        import::create_table("people", &columns, &types),
+        "CREATE TABLE people (\n    \"id\" bigint,\n    \"Full Name\" text\n)"
+    );
+    assert_eq!(
+        import::copy_statement("people", &columns),
+        "COPY people (\"id\", \"Full Name\") FROM STDIN (FORMAT csv, HEADER true)"
+    );
+}
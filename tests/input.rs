@@ -0,0 +1,376 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Driving the engine through the library, keys in and commands out, with no
+//! terminal or server involved.
+
+mod common;
+
+use common::Harness;
+use dbvi::db::monitor::Report;
+use dbvi::hover::Target;
+use dbvi::quickfix::Go;
+use dbvi::results::{Column, ResultSet};
+use dbvi::{Command, Mode};
+
+#[test]
+fn insert_then_run() {
+    let mut harness = Harness::new();
+    harness.keys("i");
+    assert_eq!(harness.state.mode(), Mode::Insert);
+    harness.keys("select 1<Esc>");
+    assert_eq!(harness.state.mode(), Mode::Normal);
+    assert_eq!(harness.state.text(), "select 1");
+    harness.keys("<CR>");
+    assert_eq!(harness.commands, [Command::RunQuery("select 1".into())]);
+}
+
+#[test]
+fn delete_line() {
+    let mut harness = Harness::new();
+    harness.keys("ione<CR>two<Esc>ggdd");
+    assert_eq!(harness.state.text(), "two");
+}
+
+#[test]
+fn replace() {
+    let mut harness = Harness::new();
+    harness.keys("iselect 1 from t<Esc>0rS");
+    assert_eq!(harness.state.text(), "Select 1 from t");
+    harness.keys("0lllllllR2, 3");
+    assert_eq!(harness.state.mode(), Mode::Replace);
+    assert_eq!(harness.state.text(), "Select 2, 3om t");
+    // Backspace puts back what was typed over.
+    harness.keys("<BS><BS>");
+    assert_eq!(harness.state.text(), "Select 2,from t");
+    harness.keys("<Esc>$Rable<Esc>");
+    assert_eq!(harness.state.text(), "Select 2,from able");
+    assert_eq!(harness.state.mode(), Mode::Normal);
+}
+
+#[test]
+fn search() {
+    let mut harness = Harness::new();
+    harness.keys("iselect a, b from t where a = b<Esc>0");
+    // Found as it is typed, and given up with <Esc>.
+    harness.keys("/fr");
+    assert_eq!(harness.state.mode(), Mode::Search);
+    assert!(harness.render().contains("/fr"));
+    harness.keys("<Esc>x");
+    assert_eq!(harness.state.text(), "elect a, b from t where a = b");
+
+    harness.keys("/a<CR>x");
+    assert_eq!(harness.state.text(), "elect , b from t where a = b");
+    harness.keys("nx");
+    assert_eq!(harness.state.text(), "elect , b from t where  = b");
+    harness.keys("/nothing<CR>");
+    assert_eq!(harness.state.status(), "Pattern not found: nothing");
+
+    // The word under the cursor, around the end and back with N.
+    harness.keys("0/b<CR>*x");
+    assert_eq!(harness.state.text(), "elect , b from t where  = ");
+    harness.keys("Nx");
+    assert_eq!(harness.state.text(), "elect ,  from t where  = ");
+    harness.keys("n");
+    assert_eq!(harness.state.status(), "Pattern not found: \\bb\\b");
+}
+
+#[test]
+fn command_line() {
+    let mut harness = Harness::new();
+    harness.keys(":q!");
+    assert_eq!(harness.state.mode(), Mode::Command);
+    harness.keys("<CR>");
+    assert_eq!(harness.commands, [Command::Quit { force: true }]);
+    assert_eq!(harness.state.mode(), Mode::Normal);
+}
+
+#[test]
+fn unknown_command() {
+    let mut harness = Harness::new();
+    harness.keys(":frobnicate<CR>");
+    assert!(harness.commands.is_empty());
+    assert!(!harness.state.status().is_empty());
+}
+
+#[test]
+fn savepoints() {
+    let mut harness = Harness::new();
+    harness.keys(":savepoint before<CR>:rollback to before<CR>:release before<CR>");
+    harness.keys(":rollback to savepoint before<CR>:savepoint a-b<CR>");
+    assert_eq!(
+        harness.commands,
+        [
+            Command::RunQuery("SAVEPOINT before".into()),
+            Command::RunQuery("ROLLBACK TO SAVEPOINT before".into()),
+            Command::RunQuery("RELEASE SAVEPOINT before".into()),
+            Command::RunQuery("ROLLBACK TO SAVEPOINT before".into()),
+        ]
+    );
+    assert!(harness.render().contains("Usage: savepoint <name>"));
+}
+
+#[test]
+fn set_search_path() {
+    let mut harness = Harness::new();
+    harness.keys(":schema sales<CR>:schema \"Sales\" , public<CR>:schema sales;drop<CR>");
+    assert_eq!(
+        harness.commands,
+        [
+            Command::RunQuery("SET search_path TO sales".into()),
+            Command::RunQuery("SET search_path TO \"Sales\", public".into()),
+        ]
+    );
+    assert!(
+        harness
+            .render()
+            .contains("Usage: schema [refresh | <name>, ...]")
+    );
+}
+
+#[test]
+fn hover() {
+    let mut harness = Harness::new();
+    harness.keys("iselect t.id from things t<Esc>0lllllllllK");
+    assert_eq!(
+        harness.commands,
+        [Command::Hover(Target::Column {
+            table: "things".into(),
+            column: "id".into()
+        })]
+    );
+}
+
+#[test]
+fn signature_lookups() {
+    let mut harness = Harness::new();
+    harness.keys("iselect round(1, round(2<Esc>");
+    assert_eq!(
+        harness.commands,
+        [Command::LookUpSignatures("round".into())]
+    );
+}
+
+#[test]
+fn complete() {
+    let mut harness = Harness::new();
+    harness.keys("iselect ord<C-n>");
+    assert_eq!(harness.commands, [Command::Complete]);
+}
+
+#[test]
+fn prepared_statements() {
+    let mut harness = Harness::new();
+    harness.keys(":prepare ByID<CR>:execute byid<CR>:execute byid(1, 'a)')<CR>:execute byid(1<CR>");
+    assert_eq!(
+        harness.commands,
+        [
+            Command::Prepare("byid".into()),
+            Command::Execute {
+                name: "byid".into(),
+                args: None,
+            },
+            Command::Execute {
+                name: "byid".into(),
+                args: Some("1, 'a)'".into()),
+            },
+        ]
+    );
+    assert!(harness.render().contains("Usage: execute <name> [(args…)]"));
+}
+
+#[test]
+fn result_tabs() {
+    let mut harness = Harness::new();
+    let results = |name| ResultSet::new(vec![Column::new(name, "int4")]);
+    harness.state.show_batch(vec![
+        ("select 1 as first".into(), results("first")),
+        ("select 2 as second".into(), results("second")),
+    ]);
+    assert!(harness.render().contains("Results 2 of 2"));
+    harness.keys("]r");
+    let screen = harness.render();
+    assert!(screen.contains("Results 1 of 2"));
+    assert!(screen.contains("select 1 as first"));
+    harness.keys("<C-w>k[r");
+    assert!(harness.render().contains("Results 2 of 2"));
+}
+
+#[test]
+fn queue_commands() {
+    let mut harness = Harness::new();
+    harness.keys(":queue<CR>:queued<CR>");
+    assert_eq!(harness.commands, [Command::Queue, Command::Queued]);
+}
+
+#[test]
+fn column_info() {
+    let mut harness = Harness::new();
+    let mut results = ResultSet::new(vec![Column::new("id", "int4"), Column::new("n", "int8")]);
+    results.rows = vec![vec![Some("1".into()), Some("2".into())]].into();
+    harness.state.show_results(Some(results));
+    harness.keys("<C-w>klK");
+    assert_eq!(harness.commands, [Command::ColumnInfo]);
+}
+
+#[test]
+fn run_expecting_rows() {
+    let mut harness = Harness::new();
+    harness.keys(":run<CR>:run expect=1<CR>:run expect=some<CR>");
+    assert_eq!(
+        harness.commands,
+        [
+            Command::Run { expect: None },
+            Command::Run { expect: Some(1) },
+        ]
+    );
+    assert!(harness.render().contains("Usage: run [expect=<rows>]"));
+}
+
+#[test]
+fn grep_schema() {
+    let mut harness = Harness::new();
+    harness.keys(":grep-schema ^order_<CR>:grep-schema<CR>");
+    assert_eq!(
+        harness.commands,
+        [Command::Report(Report::Grep("^order_".into()))]
+    );
+    assert!(harness.render().contains("Usage: grep-schema <pattern>"));
+}
+
+#[test]
+fn switch_database() {
+    let mut harness = Harness::new();
+    harness.keys(":db<CR>:db reporting<CR>");
+    assert_eq!(
+        harness.commands,
+        [
+            Command::Report(Report::Databases),
+            Command::SwitchDatabase("reporting".into()),
+        ]
+    );
+}
+
+#[test]
+fn extensions() {
+    let mut harness = Harness::new();
+    harness.keys(":extensions<CR>:ext<CR>");
+    assert_eq!(
+        harness.commands,
+        [
+            Command::Report(Report::Extensions),
+            Command::Report(Report::Extensions),
+        ]
+    );
+}
+
+#[test]
+fn sequences() {
+    let mut harness = Harness::new();
+    harness.keys(":sequences<CR>:setval orders_id_seq 501<CR>:setval! s 1<CR>:setval s x<CR>");
+    assert_eq!(
+        harness.commands,
+        [
+            Command::Report(Report::Sequences),
+            Command::SetVal {
+                sequence: "orders_id_seq".into(),
+                value: 501,
+                force: false,
+            },
+            Command::SetVal {
+                sequence: "s".into(),
+                value: 1,
+                force: true,
+            },
+        ]
+    );
+    assert!(harness.render().contains("x isn't a number"));
+    assert_eq!(
+        dbvi::db::monitor::setval_statement("public.\"Order\"_seq", 7),
+        "SELECT setval('public.\"Order\"_seq', 7)"
+    );
+}
+
+#[test]
+fn partitions() {
+    let mut harness = Harness::new();
+    harness.keys(":partitions events<CR>:partitions<CR>");
+    assert_eq!(
+        harness.commands,
+        [Command::Report(Report::Partitions("events".into()))]
+    );
+    assert!(harness.render().contains("Usage: partitions <table>"));
+}
+
+#[test]
+fn matviews() {
+    let mut harness = Harness::new();
+    harness.keys(":matviews<CR>:refresh totals<CR>:refresh concurrently totals<CR>:refresh<CR>");
+    assert_eq!(
+        harness.commands,
+        [
+            Command::Report(Report::MatViews),
+            Command::Refresh {
+                matview: "totals".into(),
+                concurrently: false,
+            },
+            Command::Refresh {
+                matview: "totals".into(),
+                concurrently: true,
+            },
+        ]
+    );
+    assert!(
+        harness
+            .render()
+            .contains("Usage: refresh [concurrently] <matview>")
+    );
+    assert_eq!(
+        dbvi::db::monitor::refresh_statement("totals", true),
+        "REFRESH MATERIALIZED VIEW CONCURRENTLY totals"
+    );
+}
+
+#[test]
+fn quickfix_commands() {
+    let mut harness = Harness::new();
+    harness.keys(":cn<CR>:cprev<CR>:cc 3<CR>:cc<CR>:copen<CR>:diagnostics<CR>:cc x<CR>");
+    assert_eq!(
+        harness.commands,
+        [
+            Command::Quickfix(Go::Next),
+            Command::Quickfix(Go::Prev),
+            Command::Quickfix(Go::Nth(3)),
+            Command::Quickfix(Go::Current),
+            Command::OpenQuickfix,
+            Command::Diagnostics,
+        ]
+    );
+    assert!(harness.render().contains("Usage: cc [<item>]"));
+}
+
+#[test]
+fn bracketed_paste() {
+    let mut harness = Harness::new();
+    harness.keys("i");
+    harness.paste("select 1;\r\n  select (2);\r");
+    assert!(harness.commands.is_empty());
+    assert_eq!(harness.state.mode(), Mode::Insert);
+    assert_eq!(harness.state.text(), "select 1;\n  select (2);\n");
+
+    harness.keys("<Esc>:echo ");
+    harness.paste("a\nb\n");
+    assert!(harness.render().contains(":echo a b"));
+    assert!(harness.commands.is_empty());
+}
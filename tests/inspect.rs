@@ -0,0 +1,99 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod common;
+
+use common::Harness;
+use dbvi::inspect::{lines, rust_type};
+use dbvi::results::{Column, ResultSet};
+
+#[test]
+fn numbers() {
+    assert_eq!(
+        lines(&Column::new("n", "numeric"), Some("0.10000000000000000001")),
+        [
+            "type      numeric → BigDecimal",
+            "raw       \"0.10000000000000000001\"",
+            "length    22 bytes",
+            "digits    20, 20 after the point",
+            "sci       1e-1",
+            "as f64    0.1, rounded",
+        ]
+    );
+    assert_eq!(
+        lines(&Column::new("n", "numeric"), Some("2.50"))[5],
+        "as f64    2.5"
+    );
+    assert_eq!(
+        lines(&Column::new("id", "int8"), Some("255"))[3..],
+        ["hex       0xff", "sci       2.55e2"]
+    );
+}
+
+#[test]
+fn timestamps() {
+    assert_eq!(
+        lines(
+            &Column::new("at", "timestamptz"),
+            Some("2024-03-01 09:30:00.25+09")
+        )[3..],
+        [
+            "iso 8601  2024-03-01T09:30:00.250+09:00",
+            "utc       2024-03-01T00:30:00.250+00:00",
+            "epoch     1709253000.250",
+        ]
+    );
+    assert_eq!(
+        lines(&Column::new("on", "date"), Some("1970-01-02"))[3],
+        "epoch     86400 (midnight UTC)"
+    );
+}
+
+#[test]
+fn text_bytes_and_null() {
+    let text = lines(&Column::new("s", "text"), Some("café\u{a0}"));
+    assert_eq!(
+        text[1..],
+        [
+            "raw       \"café\\u{a0}\"",
+            "length    7 bytes, 5 characters"
+        ]
+    );
+    assert_eq!(
+        lines(&Column::new("b", "bytea"), Some("\\x6869"))[3..],
+        ["decoded   2 bytes", "utf-8     \"hi\""]
+    );
+    assert_eq!(
+        lines(&Column::new("x", "int4"), None),
+        [
+            "type      int4 → i32",
+            "raw       NULL, no value on the wire"
+        ]
+    );
+    assert_eq!(rust_type("_timestamptz"), "Vec<DateTime<Utc>>");
+    assert_eq!(rust_type("int4[]"), "Vec<i32>");
+}
+
+#[test]
+fn gi_on_a_cell() {
+    let mut harness = Harness::new();
+    let mut results = ResultSet::new(vec![Column::new("id", "int4"), Column::new("ok", "bool")]);
+    results.rows = vec![vec![Some("1".into()), Some("t".into())]].into();
+    harness.state.show_results(Some(results));
+    harness.keys("<C-w>klgi");
+    let screen = harness.render();
+    assert!(screen.contains("Cell ok, row 1"), "{screen}");
+    assert!(screen.contains("bool → bool"), "{screen}");
+    assert!(screen.contains("decoded   true"), "{screen}");
+}
@@ -0,0 +1,175 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod common;
+
+use std::future;
+use std::time::Duration;
+
+use common::Harness;
+use dbvi::db::monitor::Progress;
+use dbvi::jobs::{Done, JobState, Jobs, Kind, Output};
+use dbvi::popup::Popup;
+use dbvi::state::Message;
+use dbvi::{Action, Command};
+use ratatui::{Terminal, backend::TestBackend};
+
+#[tokio::test]
+async fn finish() {
+    let (bus, mut actions) = dbvi::action::channel();
+    let mut jobs = Jobs::default();
+    let popup = Popup::Text {
+        title: "Benchmark".into(),
+        lines: vec!["min 1ms".into()],
+    };
+    let output = Some(Output::Popup(Box::new(popup)));
+    let id = jobs.spawn(&bus, Kind::Bench, "10 runs".into(), |_| async move {
+        Ok(Done {
+            summary: "Benchmarked 10 runs in 12ms".into(),
+            output,
+        })
+    });
+    assert!(matches!(jobs.get(id).unwrap().state, JobState::Running));
+    assert_eq!(jobs.running(), 1);
+
+    let Some(Action::Message(Message::JobDone {
+        id: done,
+        elapsed,
+        outcome,
+    })) = actions.recv().await
+    else {
+        panic!("expected the job to report back");
+    };
+    assert_eq!(done, id);
+    jobs.finish(id, elapsed, outcome);
+    assert_eq!(jobs.running(), 0);
+    let job = jobs.get(id).unwrap();
+    assert!(matches!(&job.state, JobState::Done(done) if done.summary.starts_with("Benchmarked")));
+    assert!(matches!(job.output(), Some(Output::Popup(_))));
+
+    let failed = jobs.spawn(&bus, Kind::Copy, "\"out.csv\"".into(), |_| async {
+        Err("Failed to copy to \"out.csv\": permission denied".to_string())
+    });
+    let Some(Action::Message(Message::JobDone {
+        elapsed, outcome, ..
+    })) = actions.recv().await
+    else {
+        panic!("expected the job to report back");
+    };
+    jobs.finish(failed, elapsed, outcome);
+    assert!(matches!(
+        &jobs.get(failed).unwrap().state,
+        JobState::Failed(_)
+    ));
+    // Newest first.
+    assert_eq!(jobs.selected().unwrap().id, failed);
+}
+
+#[tokio::test]
+async fn cancel() {
+    let (bus, _actions) = dbvi::action::channel();
+    let mut jobs = Jobs::default();
+    let id = jobs.spawn(&bus, Kind::Pipe, "sort".into(), |_| future::pending());
+    let job = jobs.cancel(id).unwrap();
+    assert!(matches!(job.state, JobState::Cancelled));
+    assert_eq!(
+        jobs.cancel(id).unwrap_err(),
+        format!("Job {id} isn't running")
+    );
+    assert_eq!(jobs.cancel(99).unwrap_err(), "No job 99");
+
+    // Finishing after all doesn't undo the cancel.
+    jobs.finish(id, Duration::ZERO, Ok("sorted".to_string().into()));
+    assert!(matches!(jobs.get(id).unwrap().state, JobState::Cancelled));
+}
+
+#[tokio::test]
+async fn render() {
+    let (bus, _actions) = dbvi::action::channel();
+    let mut jobs = Jobs::default();
+    let vacuum = jobs.spawn(&bus, Kind::Vacuum, "orders".into(), |_| future::pending());
+    jobs.progress(
+        vacuum,
+        Progress {
+            command: "VACUUM".into(),
+            phase: "scanning heap".into(),
+            done: 50,
+            total: Some(200),
+            unit: "blocks".into(),
+        },
+    );
+    let id = jobs.spawn(&bus, Kind::Import, "\"c.csv\" into c".into(), |_| {
+        future::pending()
+    });
+    jobs.finish(
+        id,
+        Duration::from_millis(5),
+        Ok("Imported 3 rows into c in 5ms".to_string().into()),
+    );
+
+    let mut terminal = Terminal::new(TestBackend::new(100, 4)).unwrap();
+    terminal.draw(|f| jobs.render(f, f.area(), true)).unwrap();
+    let screen: Vec<String> = terminal
+        .backend()
+        .buffer()
+        .content
+        .chunks(100)
+        .map(|row| row.iter().map(|cell| cell.symbol()).collect::<String>())
+        .collect();
+    assert!(screen[0].contains("id  kind    state"));
+    assert!(screen[1].contains("2  import  done          5.0ms"));
+    assert!(screen[1].contains("Imported 3 rows"));
+    assert!(screen[2].contains("1  vacuum  running"));
+    assert!(screen[2].contains("orders  [█████░░░░░░░░░░░░░░░]  25%  VACUUM: scanning heap"));
+}
+
+#[tokio::test]
+async fn progress() {
+    let (bus, mut actions) = dbvi::action::channel();
+    let mut jobs = Jobs::default();
+    let copy = Progress {
+        command: "COPY FROM".into(),
+        phase: String::new(),
+        done: 1200,
+        total: None,
+        unit: "rows".into(),
+    };
+    let reported = copy.clone();
+    let id = jobs.spawn(&bus, Kind::Import, "\"c.csv\" into c".into(), |reporter| {
+        reporter.progress(reported);
+        future::pending()
+    });
+    let Some(Action::Message(Message::JobProgress { id: of, progress })) = actions.recv().await
+    else {
+        panic!("expected the job to report its progress");
+    };
+    assert_eq!((of, &progress), (id, &copy));
+    jobs.progress(id, progress);
+    assert_eq!(jobs.get(id).unwrap().progress, Some(copy.clone()));
+    assert_eq!(copy.percent(), None);
+    assert_eq!(copy.describe(), "COPY FROM, 1200 rows");
+
+    // Not once it is over.
+    jobs.cancel(id).unwrap();
+    let done = Progress { done: 1300, ..copy };
+    jobs.progress(id, done);
+    assert_eq!(jobs.get(id).unwrap().progress.as_ref().unwrap().done, 1200);
+}
+
+#[test]
+fn command() {
+    let mut harness = Harness::new();
+    harness.keys(":jobs<CR>");
+    assert_eq!(harness.commands, vec![Command::Jobs]);
+}
@@ -0,0 +1,143 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod common;
+
+use common::results_of;
+use dbvi::commands;
+use dbvi::join::{self, Kind, Spec};
+use dbvi::results::ResultSet;
+use dbvi::state::Command;
+
+fn customers() -> ResultSet {
+    results_of(
+        &["id text", "name text"],
+        [
+            [Some("1"), Some("Ada")],
+            [Some("2"), Some("Grace")],
+            [None, Some("Nobody")],
+        ],
+    )
+}
+
+fn orders() -> ResultSet {
+    results_of(
+        &["id text", "name text", "total text"],
+        [
+            [Some("1"), Some("first"), Some("10")],
+            [Some("3"), Some("stray"), Some("5")],
+            [Some("1"), Some("second"), Some("20")],
+        ],
+    )
+}
+
+fn rows(results: &ResultSet) -> Vec<Vec<Option<String>>> {
+    results
+        .rows
+        .iter()
+        .map(|row| row.iter().map(|cell| cell.map(String::from)).collect())
+        .collect()
+}
+
+fn cells(rows: &[&[Option<&str>]]) -> Vec<Vec<Option<String>>> {
+    rows.iter()
+        .map(|row| row.iter().map(|cell| cell.map(String::from)).collect())
+        .collect()
+}
+
+#[test]
+fn inner_left_and_full() {
+    let mut spec = Spec::parse("customers orders on id").unwrap();
+    let joined = join::join(&spec, &customers(), &orders()).unwrap();
+    let names: Vec<&str> = joined.columns.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, ["id", "name", "orders.name", "total"]);
+    assert_eq!(
+        rows(&joined),
+        cells(&[
+            &[Some("1"), Some("Ada"), Some("first"), Some("10")],
+            &[Some("1"), Some("Ada"), Some("second"), Some("20")],
+        ])
+    );
+
+    spec.kind = Kind::Left;
+    let joined = join::join(&spec, &customers(), &orders()).unwrap();
+    assert_eq!(joined.rows.len(), 4);
+
+    spec.kind = Kind::Full;
+    let joined = join::join(&spec, &customers(), &orders()).unwrap();
+    assert_eq!(
+        rows(&joined),
+        cells(&[
+            &[Some("1"), Some("Ada"), Some("first"), Some("10")],
+            &[Some("1"), Some("Ada"), Some("second"), Some("20")],
+            &[Some("2"), Some("Grace"), None, None],
+            &[None, Some("Nobody"), None, None],
+            &[Some("3"), None, Some("stray"), Some("5")],
+        ])
+    );
+}
+
+#[test]
+fn different_key_names() {
+    let spec = Spec::parse("orders customers on id=id full").unwrap();
+    assert_eq!(spec.kind, Kind::Full);
+    let spec = Spec {
+        on: ("total".into(), "id".into()),
+        ..spec
+    };
+    let joined = join::join(&spec, &orders(), &customers()).unwrap();
+    let names: Vec<&str> = joined.columns.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(
+        names,
+        ["id", "name", "total", "customers.id", "customers.name"]
+    );
+    // Nothing matches, and the right key keeps to its own column.
+    assert_eq!(
+        rows(&joined)[3..],
+        cells(&[
+            &[None, None, None, Some("1"), Some("Ada")],
+            &[None, None, None, Some("2"), Some("Grace")],
+            &[None, None, None, None, Some("Nobody")],
+        ])
+    );
+    let spec = Spec::parse("% orders on nope").unwrap();
+    assert_eq!(
+        join::join(&spec, &customers(), &orders()).unwrap_err(),
+        "% has no column nope"
+    );
+}
+
+#[test]
+fn command() {
+    assert_eq!(
+        commands::parse("join % prod_orders on id left"),
+        Ok(Command::Join(Spec {
+            left: "%".into(),
+            right: "prod_orders".into(),
+            on: ("id".into(), "id".into()),
+            kind: Kind::Left,
+        }))
+    );
+    for bad in [
+        "join a b",
+        "join a b on",
+        "join a b on id inner",
+        "join a b on =id",
+    ] {
+        assert_eq!(
+            commands::parse(bad),
+            Err("Usage: join <result> <result> on <column>[=<column>] [left|full]".into())
+        );
+    }
+}
@@ -0,0 +1,209 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod common;
+
+use std::time::Duration;
+
+use common::Harness;
+use crossterm::event::{Event, KeyCode};
+use dbvi::action::{self, Action};
+use dbvi::keymap::{self, Keymap, Mapping, Mappings, Rhs};
+use dbvi::{Command, Mode, keys};
+use futures_util::{StreamExt, stream};
+
+#[test]
+fn sequences() {
+    let mut harness = Harness::new();
+    harness.state.map(Mode::Normal, "gb", "ihello<Esc>");
+    harness.keys("gb");
+    assert_eq!(harness.state.text(), "hello");
+    // `g` waits to see if `b` follows, then goes on to be vim's `gg`.
+    harness.keys("o<Esc>ggdd");
+    assert_eq!(harness.state.text(), "");
+    assert_eq!(harness.state.mode(), Mode::Normal);
+}
+
+#[test]
+fn insert_mode_and_timeout() {
+    let mut harness = Harness::new();
+    harness.state.map(Mode::Insert, "jk", "<Esc>");
+    harness.keys("ihijk");
+    assert_eq!(harness.state.text(), "hi");
+    assert_eq!(harness.state.mode(), Mode::Normal);
+    harness.keys("Aj<Esc>");
+    assert_eq!(harness.state.text(), "hij");
+    // A lone `j` is typed once the timeout passes.
+    harness.keys("Aj");
+    assert_eq!(harness.state.text(), "hij");
+    harness.timeout();
+    assert_eq!(harness.state.text(), "hijj");
+}
+
+#[test]
+fn ambiguous() {
+    let mut harness = Harness::new();
+    harness.state.map(Mode::Normal, "Q", ":q<CR>");
+    harness.state.map(Mode::Normal, "QQ", ":q!<CR>");
+    harness.keys("Q");
+    assert!(harness.commands.is_empty());
+    harness.timeout();
+    assert_eq!(harness.commands, [Command::Quit { force: false }]);
+    harness.commands.clear();
+    harness.keys("QQ");
+    assert_eq!(harness.commands, [Command::Quit { force: true }]);
+}
+
+#[test]
+fn config() {
+    let mappings: Mappings = toml::from_str(
+        r#"
+        [normal]
+        "<C-t>" = ":tables<CR>"
+        [insert]
+        jk = "<Esc>"
+        "#,
+    )
+    .unwrap();
+    let mut keymap = Keymap::default();
+    mappings.apply(&mut keymap);
+    let found = keymap.lookup(Mode::Normal, &keys::parse("<C-t>"));
+    assert!(matches!(found.exact, Some(Rhs::Keys(keys)) if keys.len() == 8));
+    assert!(keymap.lookup(Mode::Insert, &keys::parse("j")).longer);
+    assert!(
+        keymap
+            .lookup(Mode::Normal, &keys::parse("j"))
+            .exact
+            .is_none()
+    );
+}
+
+#[test]
+fn leader_and_commands() {
+    let mut harness = Harness::new();
+    harness.state.map(
+        Mode::Normal,
+        "<leader>q",
+        Mapping::Command {
+            command: ":q!".into(),
+        },
+    );
+    harness.keys("\\q");
+    assert_eq!(harness.commands, [Command::Quit { force: true }]);
+
+    let mappings: Mappings = toml::from_str(
+        r#"
+        leader = "<Space>"
+        [normal]
+        "<leader>e" = { command = "export csv /tmp/out.csv" }
+        "<Leader>t" = "<leader>e"
+        "#,
+    )
+    .unwrap();
+    let mut keymap = Keymap::default();
+    mappings.apply(&mut keymap);
+    let found = keymap.lookup(Mode::Normal, &keys::parse(" e"));
+    assert!(matches!(found.exact, Some(Rhs::Command(line)) if line == "export csv /tmp/out.csv"));
+    let found = keymap.lookup(Mode::Normal, &keys::parse(" t"));
+    assert!(matches!(found.exact, Some(Rhs::Keys(keys)) if keys == keymap.parse("<Space>e")));
+}
+
+#[test]
+fn templates() {
+    let mut harness = Harness::new();
+    harness.state.map(
+        Mode::Normal,
+        "<leader>s",
+        Mapping::Insert {
+            insert: "SELECT * FROM ${1:t} WHERE $0;".into(),
+        },
+    );
+    harness.state.map(
+        Mode::Command,
+        "<C-q>",
+        Mapping::Insert {
+            insert: "q${1:!}".into(),
+        },
+    );
+    harness.keys("\\susers<Tab>id = 1");
+    assert_eq!(harness.state.text(), "SELECT * FROM users WHERE id = 1;");
+    assert_eq!(harness.state.mode(), Mode::Insert);
+    // On the command line, the template goes in without its tab stops.
+    harness.keys("<Esc>:<C-q><CR>");
+    assert_eq!(harness.commands, [Command::Quit { force: true }]);
+}
+
+#[test]
+fn kill_switch() {
+    let mut harness = Harness::new();
+    harness.keys("ZQ");
+    assert_eq!(harness.commands, [Command::KillSwitch]);
+    harness.commands.clear();
+    harness.keys("iZQ<Esc>");
+    assert_eq!(harness.state.text(), "ZQ");
+    assert!(harness.commands.is_empty());
+
+    let mut keymap = Keymap::builtin();
+    keymap.set_kill_switch(Some("<C-k><C-k>"));
+    assert!(
+        keymap
+            .lookup(Mode::Normal, &keys::parse("Z"))
+            .exact
+            .is_none()
+    );
+    assert!(!keymap.lookup(Mode::Normal, &keys::parse("Z")).longer);
+    let found = keymap.lookup(Mode::Normal, &keys::parse("<C-k><C-k>"));
+    assert!(matches!(found.exact, Some(Rhs::Command(line)) if line == "killswitch"));
+    keymap.set_kill_switch(Some(""));
+    assert!(!keymap.lookup(Mode::Normal, &keys::parse("<C-k>")).longer);
+}
+
+#[tokio::test]
+async fn kill_switch_while_a_query_runs() {
+    let keymap = Keymap::builtin();
+    let typed = |keys: &str| {
+        let events = keys::parse(keys).into_iter().map(|key| Ok(Event::Key(key)));
+        stream::iter(events.collect::<Vec<_>>()).chain(stream::pending())
+    };
+    let (bus, mut actions) = action::channel();
+    // The query never ends, but the switch still gets through.
+    let killed = keymap::unless_killed(
+        std::future::pending::<()>(),
+        typed("jZZQ"),
+        &keymap,
+        Mode::Normal,
+        &bus,
+    );
+    assert_eq!(killed.await, None);
+    let mut passed = Vec::new();
+    while let Ok(Action::Event(Event::Key(key))) = actions.try_recv() {
+        passed.push(key.code);
+    }
+    assert_eq!(passed, [KeyCode::Char('j'), KeyCode::Char('Z')]);
+
+    // Whatever else is typed waits its turn, the half typed switch too.
+    let done = keymap::unless_killed(
+        tokio::time::sleep(Duration::from_millis(50)),
+        typed("kZ"),
+        &keymap,
+        Mode::Normal,
+        &bus,
+    );
+    assert_eq!(done.await, Some(()));
+    let mut passed = Vec::new();
+    while let Ok(Action::Event(Event::Key(key))) = actions.try_recv() {
+        passed.push(key.code);
+    }
+    assert_eq!(passed, [KeyCode::Char('k'), KeyCode::Char('Z')]);
+}
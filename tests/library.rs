@@ -0,0 +1,100 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod common;
+
+use common::Harness;
+use dbvi::Command;
+use dbvi::library::{self, Library, fuzzy};
+
+#[test]
+fn fuzzy_ranking() {
+    assert!(fuzzy("rev", "weekly/revenue").is_some());
+    assert!(fuzzy("WR", "weekly/revenue").is_some());
+    assert_eq!(fuzzy("xyz", "weekly/revenue"), None);
+    assert_eq!(fuzzy("", "anything"), Some(0));
+    // A run at the start of a word beats scattered letters.
+    assert!(fuzzy("rev", "weekly/revenue") > fuzzy("rev", "recent_events"));
+}
+
+#[test]
+fn save_and_browse() {
+    let dir = std::env::temp_dir().join(format!("dbvi-library-{}", std::process::id()));
+    let text = "-- tags: old\nselect status, sum(total)\nfrom orders\ngroup by 1";
+    let tags = ["finance".to_string(), "weekly".to_string()];
+    let path = library::save(&dir, "weekly/revenue", &tags, text, false).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "-- tags: finance, weekly\nselect status, sum(total)\nfrom orders\ngroup by 1\n"
+    );
+    library::save(&dir, "customers", &[], "select * from customers", false).unwrap();
+    assert_eq!(
+        library::save(&dir, "customers", &[], "select 1", false).unwrap_err(),
+        "Query customers exists (add ! to replace it)"
+    );
+    library::save(
+        &dir,
+        "customers",
+        &[],
+        "-- everyone\nselect * from customers",
+        true,
+    )
+    .unwrap();
+    for name in ["../escape", "/abs", "a//b", ""] {
+        assert!(library::save(&dir, name, &[], "select 1", false).is_err());
+    }
+
+    let mut library = Library::new(Library::load(&dir).unwrap());
+    let names: Vec<&str> = library.matches().map(|entry| entry.name.as_str()).collect();
+    assert_eq!(names, ["customers", "weekly/revenue"]);
+    let customers = library.selected().unwrap();
+    assert_eq!(customers.summary, "select * from customers");
+    assert!(customers.tags.is_empty());
+
+    for c in "#fin".chars() {
+        library.push(c);
+    }
+    let revenue = library.selected().unwrap();
+    assert_eq!(revenue.name, "weekly/revenue");
+    assert_eq!(revenue.tags, ["finance", "weekly"]);
+    assert_eq!(library.matches().count(), 1);
+    library.pop();
+    library.pop();
+    library.pop();
+    library.pop();
+    assert_eq!(library.matches().count(), 2);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn commands() {
+    let mut harness = Harness::new();
+    harness.keys(":save-query! weekly/revenue #finance<CR>:queries<CR>:save-query<CR>");
+    assert_eq!(
+        harness.commands,
+        vec![
+            Command::SaveQuery {
+                name: "weekly/revenue".into(),
+                tags: vec!["finance".into()],
+                force: true,
+            },
+            Command::Queries,
+        ]
+    );
+    assert!(
+        harness
+            .render()
+            .contains("Usage: save-query <name> [#tag...]")
+    );
+}
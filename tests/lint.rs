@@ -0,0 +1,81 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod common;
+
+use common::schema_of;
+use dbvi::db::schema::Schema;
+use dbvi::lint::lint;
+
+fn schema() -> Schema {
+    schema_of(&[(
+        "public",
+        "orders",
+        &["id integer not null generated primary key", "total numeric"],
+    )])
+}
+
+/// The messages found in `text`, with the text they are about.
+fn problems<'a>(text: &'a str, schema: &Schema) -> Vec<(&'a str, String)> {
+    lint(text, schema, &[])
+        .into_iter()
+        .map(|diagnostic| (&text[diagnostic.range], diagnostic.message))
+        .collect()
+}
+
+#[test]
+fn syntax() {
+    let none = Schema::default();
+    assert_eq!(
+        problems("select (1 + 2;\nselect 1)", &none),
+        [
+            ("(", "Unclosed (".to_string()),
+            (")", "Unmatched )".to_string())
+        ]
+    );
+    assert_eq!(
+        problems("select 'it''s", &none),
+        [("'", "Unterminated string".to_string())]
+    );
+    assert_eq!(
+        problems("select a, b, from t", &none),
+        [(",", "Trailing comma".to_string())]
+    );
+    assert_eq!(
+        problems("delete from t;\nupdate t set a = 1 where b", &none),
+        [(
+            "delete",
+            "DELETE without WHERE deletes every row".to_string()
+        )]
+    );
+    assert_eq!(problems("select 'it''s', $$ ( $$ -- (\n", &none), []);
+}
+
+#[test]
+fn names() {
+    let schema = schema();
+    assert_eq!(
+        problems(
+            "select o.totl, o.id, x.y from orders o join ordrs x on true",
+            &schema
+        ),
+        [
+            ("totl", "No column totl in orders".to_string()),
+            ("ordrs", "No table ordrs in the cached schema".to_string()),
+        ]
+    );
+    let created = "create table items (id int);\n\
+                   with recent as (select 1) select * from recent, items, pg_class";
+    assert!(problems(created, &schema).is_empty());
+}
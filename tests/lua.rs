@@ -0,0 +1,120 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![cfg(feature = "lua")]
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use tokio::sync::mpsc::UnboundedReceiver;
+
+use dbvi::keymap::Rhs;
+use dbvi::lua::Lua;
+use dbvi::{Action, Command, Message, Mode, action, keys};
+
+fn lua(script: &str) -> (Lua, UnboundedReceiver<Action>) {
+    let (bus, actions) = action::channel();
+    let lua = Lua::new(bus).unwrap();
+    lua.eval(script).unwrap();
+    (lua, actions)
+}
+
+#[test]
+fn eval() {
+    let (lua, _) = lua("x = 20");
+    assert_eq!(lua.eval("x + 1, 'y'"), Ok("21  y".into()));
+    assert!(lua.eval("error('boom')").unwrap_err().contains("boom"));
+}
+
+#[test]
+fn map_to_keys() {
+    let (lua, _) = lua(r#"dbvi.map("n", "Q", ":q<CR>") dbvi.map("n", "gq", "ZZ")"#);
+    // Terminals report Q with shift.
+    let q = KeyEvent::new(KeyCode::Char('Q'), KeyModifiers::SHIFT);
+    match lua.lookup(Mode::Normal, &[q]).exact {
+        Some(Rhs::Keys(pressed)) => assert_eq!(pressed, keys::parse(":q<CR>")),
+        other => panic!("{other:?}"),
+    }
+    assert!(lua.lookup(Mode::Insert, &[q]).exact.is_none());
+    let g = lua.lookup(Mode::Normal, &keys::parse("g"));
+    assert!(g.exact.is_none() && g.longer);
+}
+
+#[test]
+fn map_to_command() {
+    let (lua, _) = lua(r#"dbvi.map("n", "<leader>t", { command = "tables" })"#);
+    match lua.lookup(Mode::Normal, &keys::parse("\\t")).exact {
+        Some(Rhs::Command(line)) => assert_eq!(line, "tables"),
+        other => panic!("{other:?}"),
+    }
+}
+
+#[test]
+fn map_to_function() {
+    let (lua, mut actions) =
+        lua(r#"dbvi.map("n", "<C-t>", function() dbvi.command("tables") end)"#);
+    let Some(Rhs::Lua(function)) = lua.lookup(Mode::Normal, &keys::parse("<C-t>")).exact else {
+        panic!("not mapped to a function");
+    };
+    lua.call(&function);
+    assert!(matches!(
+        actions.try_recv(),
+        Ok(Action::Command(Command::Tables))
+    ));
+}
+
+#[test]
+fn actions() {
+    let (lua, mut actions) =
+        lua(r#"dbvi.action("hi", function(args) dbvi.status("hi " .. args) end)"#);
+    assert_eq!(
+        lua.parse("hi  there "),
+        Some(Command::LuaAction {
+            name: "hi".into(),
+            args: "there".into()
+        })
+    );
+    assert_eq!(lua.parse("bye"), None);
+    lua.action("hi", "there");
+    assert!(matches!(
+        actions.try_recv(),
+        Ok(Action::Message(Message::Status(status))) if status == "hi there"
+    ));
+}
+
+#[test]
+fn pre_query() {
+    let (lua, _) = lua(r#"
+        dbvi.on("pre_query", function(sql) return (sql:gsub("everything", "*")) end)
+        dbvi.on("pre_query", function(sql) if sql:match("^delete") then return false end end)
+        "#);
+    assert_eq!(
+        lua.pre_query("select everything from t".into()),
+        Ok("select * from t".into())
+    );
+    assert!(lua.pre_query("delete from t".into()).is_err());
+}
+
+#[test]
+fn post_query() {
+    let (lua, mut actions) = lua(
+        r#"dbvi.on("post_query", function(sql, r) dbvi.status(sql .. " " .. (r.rows or r.error)) end)"#,
+    );
+    lua.post_query("select 1", 1.5, &Ok(1));
+    lua.post_query("select x", 1.5, &Err("no x".into()));
+    let statuses: Vec<String> = std::iter::from_fn(|| match actions.try_recv() {
+        Ok(Action::Message(Message::Status(status))) => Some(status),
+        _ => None,
+    })
+    .collect();
+    assert_eq!(statuses, ["select 1 1", "select x no x"]);
+}
@@ -0,0 +1,69 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod common;
+
+use common::{Harness, results_of};
+
+fn harness() -> Harness {
+    let mut harness = Harness::new();
+    let results = results_of(&["id int4"], ["3", "1", "4", "2"].map(|id| [Some(id)]));
+    harness.state.show_results(Some(results));
+    harness.keys("<C-w>k");
+    harness
+}
+
+/// Yanks from the results and reads it back from the editor, which is
+/// emptied again after.
+fn yanked(harness: &mut Harness) -> String {
+    harness.keys("y<C-w>jp");
+    let text = harness.state.text();
+    harness.keys("dddddddd<C-w>k");
+    text
+}
+
+#[test]
+fn yanks_only_the_marked_rows() {
+    let mut harness = harness();
+    harness.keys("mjjm");
+    assert_eq!(harness.state.status(), "2 rows marked");
+    assert!(harness.render().contains("Results (4 rows), 2 marked"));
+    assert_eq!(yanked(&mut harness), "3\n4");
+
+    // Sorting takes the marks along.
+    harness.keys("s");
+    assert_eq!(yanked(&mut harness), "3\n4");
+
+    harness.keys("M");
+    assert_eq!(harness.state.status(), "Marks cleared");
+    harness.keys("gg");
+    assert_eq!(yanked(&mut harness), "1");
+}
+
+#[test]
+fn shows_the_marked_rows_on_their_own() {
+    let mut harness = harness();
+    harness.keys("gm");
+    assert_eq!(harness.state.status(), "No rows marked, m marks one");
+    harness.keys("Vjjmgm");
+    assert_eq!(harness.state.status(), "3 marked rows, gm for all");
+    let screen = harness.render();
+    assert!(screen.contains("Marked rows (3 of 4)"), "{screen}");
+    assert!(!screen.contains(" 2 "), "{screen}");
+
+    // Marks taken away here stay away.
+    harness.keys("ggmgm");
+    assert!(harness.render().contains("Results (4 rows), 2 marked"));
+    assert_eq!(yanked(&mut harness), "1\n4");
+}
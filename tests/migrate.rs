@@ -0,0 +1,105 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use dbvi::commands;
+use dbvi::migrate::{self, Action, Applied, Migration};
+use dbvi::state::Command;
+
+#[test]
+fn command() {
+    assert_eq!(
+        commands::parse("migrate"),
+        Ok(Command::Migrate(Action::Status))
+    );
+    assert_eq!(
+        commands::parse("migrate up"),
+        Ok(Command::Migrate(Action::Up))
+    );
+    assert_eq!(
+        commands::parse("migrate down"),
+        Ok(Command::Migrate(Action::Down))
+    );
+    assert!(commands::parse("migrate sideways").is_err());
+}
+
+#[test]
+fn splits_off_the_down_section() {
+    let text = "ALTER TABLE orders ADD COLUMN status text;\n\n-- migrate:down\nALTER TABLE orders DROP COLUMN status;\n";
+    let migration = Migration::parse("0003_add-status.sql", text).unwrap();
+    assert_eq!(migration.version, 3);
+    assert_eq!(migration.name, "add-status");
+    assert_eq!(migration.up, "ALTER TABLE orders ADD COLUMN status text;");
+    assert_eq!(
+        migration.down.as_deref(),
+        Some("ALTER TABLE orders DROP COLUMN status;")
+    );
+
+    let migration = Migration::parse("12-seed.sql", "INSERT INTO t VALUES (1);").unwrap();
+    assert_eq!((migration.version, migration.down), (12, None));
+    assert!(Migration::parse("seed.sql", "").is_err());
+}
+
+#[test]
+fn loads_in_order_and_refuses_duplicates() {
+    let dir = std::env::temp_dir().join(format!("dbvi-migrate-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("10_b.sql"), "select 10").unwrap();
+    std::fs::write(dir.join("2_a.sql"), "select 2").unwrap();
+    std::fs::write(dir.join("README.md"), "not a migration").unwrap();
+    let versions: Vec<i64> = migrate::load(&dir)
+        .unwrap()
+        .iter()
+        .map(|m| m.version)
+        .collect();
+    assert_eq!(versions, [2, 10]);
+
+    std::fs::write(dir.join("002_again.sql"), "select 2").unwrap();
+    let err = migrate::load(&dir).unwrap_err();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert!(err.contains("version 2"), "{err}");
+}
+
+#[test]
+fn status() {
+    let migrations = [
+        Migration::parse("1_init.sql", "create table t (id int)").unwrap(),
+        Migration::parse("2_edited.sql", "alter table t add x int").unwrap(),
+        Migration::parse("4_next.sql", "alter table t add y int").unwrap(),
+    ];
+    let applied = |version, name: &str, checksum: &str| Applied {
+        version,
+        name: name.into(),
+        checksum: checksum.into(),
+        applied_at: "2026-10-01 09:00:00".into(),
+    };
+    let applied = [
+        applied(1, "init", &migrations[0].checksum),
+        applied(2, "edited", "stale"),
+        applied(3, "gone", "whatever"),
+    ];
+    let pending: Vec<i64> = migrate::pending(&migrations, &applied)
+        .iter()
+        .map(|m| m.version)
+        .collect();
+    assert_eq!(pending, [4]);
+
+    let results = migrate::status(&migrations, &applied);
+    let states: Vec<String> = (0..results.rows.len())
+        .map(|i| {
+            let row = results.rows.get(i).unwrap();
+            format!("{} {}", row.get(0).unwrap(), row.get(2).unwrap())
+        })
+        .collect();
+    assert_eq!(states, ["1 applied", "2 changed", "3 missing", "4 pending"]);
+}
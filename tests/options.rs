@@ -0,0 +1,122 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod common;
+
+use common::Harness;
+use dbvi::options::{self, Request};
+use dbvi::{Command, State};
+
+/// Runs `:set option[=value]` the way the app does, minus the server.
+fn set(state: &mut State, option: &str, value: Option<&str>) -> Result<String, String> {
+    match options::request(state, option, value)? {
+        Request::All => Ok("all".into()),
+        Request::Show(opt) => Ok(opt.show(state)),
+        Request::Set(opt, value) => {
+            opt.set(state, value)?;
+            Ok(opt.show(state))
+        }
+    }
+}
+
+#[test]
+fn booleans() {
+    let mut harness = Harness::new();
+    let state = &mut harness.state;
+    assert_eq!(set(state, "wrap?", None).unwrap(), "nowrap");
+    assert_eq!(set(state, "wrap", None).unwrap(), "wrap");
+    assert_eq!(set(state, "wrap!", None).unwrap(), "nowrap");
+    assert_eq!(set(state, "invwrap", None).unwrap(), "wrap");
+    assert_eq!(set(state, "nowrap", None).unwrap(), "nowrap");
+    assert_eq!(set(state, "nu", Some("on")).unwrap(), "number");
+    assert_eq!(set(state, "dryrun?", None).unwrap(), "nodryrun");
+    assert_eq!(set(state, "dryrun", None).unwrap(), "dryrun");
+    assert_eq!(set(state, "oer?", None).unwrap(), "onerrorrollback");
+    assert_eq!(
+        set(state, "ap", Some("maybe")).unwrap_err(),
+        "Expected true or false, not maybe"
+    );
+}
+
+#[test]
+fn numbers_and_choices() {
+    let mut harness = Harness::new();
+    let state = &mut harness.state;
+    assert_eq!(set(state, "split", None).unwrap(), "split=60");
+    assert_eq!(set(state, "split", Some("40")).unwrap(), "split=40");
+    assert_eq!(
+        set(state, "split", Some("95")).unwrap_err(),
+        "Expected a number from 10 to 90, not 95"
+    );
+    assert_eq!(
+        set(state, "nosplit", None).unwrap_err(),
+        "split is not a boolean option"
+    );
+    assert_eq!(
+        set(state, "dialect", Some("redshift")).unwrap(),
+        "dialect=redshift"
+    );
+    assert_eq!(
+        set(state, "cb", Some("primary")).unwrap_err(),
+        "Expected one of auto, system, osc52, none, not primary"
+    );
+    assert_eq!(
+        set(state, "statement_timeout?", None).unwrap(),
+        "statement_timeout=0 (disabled)"
+    );
+    assert_eq!(
+        set(state, "frobnicate", None).unwrap_err(),
+        "Unknown option: frobnicate"
+    );
+    assert_eq!(set(state, "", None).unwrap(), "all");
+}
+
+#[test]
+fn server_settings() {
+    let find = |name| options::find(name).unwrap();
+    let readonly = find("ro");
+    assert!(readonly.on_server() && find("statement_timeout").on_server());
+    assert!(!find("autolimit").on_server());
+    assert_eq!(
+        readonly.sql(&options::Value::Bool(false)).unwrap(),
+        "SET default_transaction_read_only = off"
+    );
+
+    let mut harness = Harness::new();
+    let state = &mut harness.state;
+    assert_eq!(set(state, "readonly?", None).unwrap(), "noreadonly");
+    assert_eq!(
+        set(state, "autolimit", Some("500")).unwrap(),
+        "autolimit=500"
+    );
+}
+
+#[test]
+fn command_line() {
+    let mut harness = Harness::new();
+    harness.keys(":set split=40<CR>:set<CR>");
+    assert_eq!(
+        harness.commands,
+        [
+            Command::Set {
+                option: "split".into(),
+                value: Some("40".into())
+            },
+            Command::Set {
+                option: String::new(),
+                value: None
+            }
+        ]
+    );
+}
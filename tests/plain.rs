@@ -0,0 +1,73 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod common;
+
+use common::{Harness, results_of};
+use dbvi::options::{self, Value};
+use dbvi::results::ResultSet;
+use ratatui::style::{Color, Modifier};
+
+fn plain(harness: &mut Harness) {
+    let opt = options::find("plain").unwrap();
+    opt.set(&mut harness.state, Value::Bool(true)).unwrap();
+}
+
+fn orders() -> ResultSet {
+    results_of(
+        &["id int4", "status text"],
+        [[Some("1"), Some("paid")], [Some("2"), None]],
+    )
+}
+
+#[test]
+fn draws_without_colors_or_box_drawing() {
+    let mut harness = Harness::new();
+    harness.state.show_results(Some(orders()));
+    let screen = harness.render();
+    assert!(screen.contains('│') || screen.contains('─'));
+
+    plain(&mut harness);
+    harness.keys("iselect 1");
+    let screen = harness.render();
+    assert!(screen.contains("paid"), "{screen}");
+    assert!(screen.contains("select 1"), "{screen}");
+    assert!(screen.is_ascii(), "{screen}");
+    for style in harness.styles() {
+        assert!(matches!(style.fg, None | Some(Color::Reset)), "{style:?}");
+        assert!(matches!(style.bg, None | Some(Color::Reset)), "{style:?}");
+        assert!(
+            (style.add_modifier - Modifier::REVERSED).is_empty(),
+            "{style:?}"
+        );
+    }
+}
+
+#[test]
+fn says_changes_of_mode_and_pane() {
+    let mut harness = Harness::new();
+    harness.state.show_results(Some(orders()));
+    harness.keys("i");
+    assert_ne!(harness.state.status(), "Insert mode");
+
+    plain(&mut harness);
+    harness.keys("<Esc>");
+    assert_eq!(harness.state.status(), "Normal mode");
+    harness.keys("i");
+    assert_eq!(harness.state.status(), "Insert mode");
+    harness.keys("<Esc><C-w>k");
+    assert_eq!(harness.state.status(), "Results pane");
+    harness.keys("<C-w>j");
+    assert_eq!(harness.state.status(), "Editor pane");
+}
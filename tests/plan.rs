@@ -0,0 +1,44 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use dbvi::plan::{Estimate, Plan};
+use serde_json::json;
+
+#[test]
+fn estimate_against_the_guards() {
+    let plan = Plan::parse(&json!([{
+        "Plan": {
+            "Node Type": "Nested Loop",
+            "Total Cost": 250000.5,
+            "Plan Rows": 4000000,
+            "Plans": [
+                {"Node Type": "Seq Scan", "Relation Name": "a", "Total Cost": 20.0, "Plan Rows": 2000},
+                {"Node Type": "Seq Scan", "Relation Name": "b", "Total Cost": 20.0, "Plan Rows": 2000}
+            ]
+        }
+    }]))
+    .unwrap();
+    let estimate = Estimate::of(&plan).unwrap();
+    assert_eq!(estimate.rows, 4000000.0);
+    assert_eq!(estimate.exceeds(0, 0), None);
+    assert_eq!(estimate.exceeds(1_000_000, 0), None);
+    assert_eq!(
+        estimate.exceeds(100_000, 0).as_deref(),
+        Some("Estimated cost 250000 > costguard=100000")
+    );
+    assert_eq!(
+        estimate.exceeds(100_000, 1_000_000).as_deref(),
+        Some("Estimated cost 250000 > costguard=100000, 4000000 rows > estrowguard=1000000")
+    );
+}
@@ -0,0 +1,74 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![cfg(feature = "plugins")]
+
+use std::path::Path;
+
+use dbvi::plugin::{Kind, Plugins};
+use dbvi::results::{Column, ResultSet};
+
+fn plugins() -> Plugins {
+    let (plugins, errors) =
+        Plugins::load(&Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/plugins"));
+    assert_eq!(errors, Vec::<String>::new());
+    plugins
+}
+
+#[test]
+fn manifest() {
+    let plugins = plugins();
+    assert_eq!(
+        plugins.describe(),
+        "echo: command echo, pane echo-pane, transform identity\nspin: command spin"
+    );
+    assert_eq!(plugins.command("echo-pane"), Some(Kind::Pane));
+    assert_eq!(plugins.command("identity"), None);
+}
+
+#[test]
+fn command() {
+    let output = plugins().run(Kind::Command, "echo", "hi there", None);
+    assert_eq!(output, Ok("command echo hi there\n".into()));
+}
+
+#[test]
+fn transform() {
+    let mut results = ResultSet::new(vec![Column::new("id", "int4"), Column::new("name", "text")]);
+    results.rows = vec![
+        vec![Some("1".into()), Some("a, b".into())],
+        vec![Some("2".into()), None],
+    ]
+    .into();
+    let transformed = plugins().transform("identity", &results).unwrap();
+    assert_eq!(transformed.columns[0].ty, "int4");
+    assert_eq!(transformed.rows, results.rows);
+}
+
+#[test]
+fn runs_out_of_fuel() {
+    let mut plugins = plugins();
+    plugins.fuel = 100_000;
+    let err = plugins.run(Kind::Command, "spin", "", None).unwrap_err();
+    assert!(err.starts_with("spin: "), "{err}");
+}
+
+#[test]
+fn unknown() {
+    assert!(
+        plugins()
+            .run(Kind::Export, "parquet", "out.parquet", None)
+            .is_err()
+    );
+}
@@ -0,0 +1,33 @@
+;; Echoes requests back: `command echo` and `pane echo-pane` whole, and
+;; `transform identity` without its first line, so the results unchanged.
+(module
+  (memory (export "memory") 1)
+  (global $next (mut i32) (i32.const 1024))
+  (data (i32.const 0) "command echo\npane echo-pane\ntransform identity\n")
+
+  (func (export "dbvi_alloc") (param $len i32) (result i32)
+    (local $ptr i32)
+    (local.set $ptr (global.get $next))
+    (global.set $next (i32.add (global.get $next) (local.get $len)))
+    (local.get $ptr))
+
+  (func (export "dbvi_manifest") (result i64)
+    (i64.const 47))
+
+  (func (export "dbvi_run") (param $ptr i32) (param $len i32) (result i64)
+    (local $skip i32)
+    ;; Past the first newline, for a transform.
+    (if (i32.eq (i32.load8_u (local.get $ptr)) (i32.const 116))
+      (then
+        (block $done
+          (loop $scan
+            (br_if $done (i32.ge_u (local.get $skip) (local.get $len)))
+            (local.set $skip (i32.add (local.get $skip) (i32.const 1)))
+            (br_if $done
+              (i32.eq
+                (i32.load8_u (i32.add (local.get $ptr) (i32.sub (local.get $skip) (i32.const 1))))
+                (i32.const 10)))
+            (br $scan)))))
+    (i64.or
+      (i64.shl (i64.extend_i32_u (i32.add (local.get $ptr) (local.get $skip))) (i64.const 32))
+      (i64.extend_i32_u (i32.sub (local.get $len) (local.get $skip))))))
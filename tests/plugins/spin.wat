@@ -0,0 +1,14 @@
+;; Never returns from `command spin`, for the fuel limit to stop.
+(module
+  (memory (export "memory") 1)
+  (data (i32.const 0) "command spin\n")
+
+  (func (export "dbvi_alloc") (param $len i32) (result i32)
+    (i32.const 1024))
+
+  (func (export "dbvi_manifest") (result i64)
+    (i64.const 13))
+
+  (func (export "dbvi_run") (param $ptr i32) (param $len i32) (result i64)
+    (loop $forever (br $forever))
+    (i64.const 0)))
@@ -0,0 +1,41 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use dbvi::queue::Queue;
+
+fn ids(queue: &Queue) -> Vec<usize> {
+    queue.iter().map(|queued| queued.id).collect()
+}
+
+#[test]
+fn reorder_and_drop() {
+    let mut queue = Queue::default();
+    assert_eq!(queue.push("-- vacuum\nVACUUM orders".into()), (1, 0));
+    assert_eq!(queue.push("ANALYZE orders".into()), (2, 1));
+    assert_eq!(queue.push("REINDEX TABLE orders".into()), (3, 2));
+    assert_eq!(queue.iter().next().unwrap().summary(), "VACUUM orders");
+
+    queue.move_selected(1);
+    assert_eq!((ids(&queue), queue.cursor), (vec![2, 1, 3], 1));
+    queue.move_selected(5);
+    assert_eq!((ids(&queue), queue.cursor), (vec![2, 3, 1], 2));
+    queue.move_selected(-9);
+    assert_eq!((ids(&queue), queue.cursor), (vec![1, 2, 3], 0));
+
+    queue.bottom();
+    assert_eq!(queue.remove_selected().map(|queued| queued.id), Some(3));
+    assert_eq!(queue.cursor, 1);
+    assert_eq!(queue.pop().map(|queued| queued.id), Some(1));
+    assert_eq!(ids(&queue), [2]);
+}
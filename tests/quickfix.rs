@@ -0,0 +1,48 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use dbvi::quickfix::{Entry, Go, Quickfix, Target};
+
+fn entry(line: usize) -> Entry {
+    Entry {
+        target: Target::Line {
+            path: "migrate.sql".into(),
+            line,
+        },
+        text: format!("failed on {line}"),
+    }
+}
+
+#[test]
+fn walking() {
+    let mut quickfix = Quickfix::default();
+    assert!(quickfix.go(Go::Next).is_err());
+    quickfix.set("source migrate.sql", vec![entry(0), entry(4), entry(9)]);
+    assert_eq!(quickfix.entries()[1].location(), "migrate.sql:5");
+
+    // The first :cnext goes to the second, as in Vim, since the list
+    // starts on the first.
+    assert_eq!(quickfix.go(Go::Next).map(|(item, _)| item), Ok(2));
+    assert_eq!(quickfix.go(Go::Next).map(|(item, _)| item), Ok(3));
+    assert!(quickfix.go(Go::Next).is_err());
+    assert_eq!(quickfix.go(Go::First).map(|(item, _)| item), Ok(1));
+    assert!(quickfix.go(Go::Prev).is_err());
+    assert_eq!(quickfix.go(Go::Nth(7)).map(|(item, _)| item), Ok(3));
+    assert!(quickfix.go(Go::Nth(0)).is_err());
+
+    quickfix.top();
+    quickfix.move_by(1);
+    let (item, entry) = quickfix.go(Go::Selected).unwrap();
+    assert_eq!((item, entry.text.as_str()), (2, "failed on 4"));
+}
@@ -0,0 +1,108 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::time::Duration;
+
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers, MouseEventKind};
+use dbvi::keys;
+use dbvi::record::{self, Recorder};
+
+#[test]
+fn keys_round_trip() {
+    let written = [
+        "x",
+        "<C-w>",
+        "<lt>",
+        "<Space>",
+        "<CR>",
+        "<S-Tab>",
+        "<F5>",
+        "<M-x>",
+        "<C-M-Del>",
+    ];
+    for notation in written {
+        let parsed = keys::parse(notation);
+        assert_eq!(parsed.len(), 1, "{notation}");
+        assert_eq!(keys::notation(&parsed[0]), notation);
+    }
+    assert_eq!(
+        keys::parse("<C-Space>"),
+        [KeyEvent::new(KeyCode::Char(' '), KeyModifiers::CONTROL)]
+    );
+    // Not a key, so taken literally.
+    assert_eq!(keys::parse("<Fx>").len(), 4);
+}
+
+#[test]
+fn lines() {
+    let paste = Event::Paste("SELECT 'a\"b'\n".into());
+    assert_eq!(
+        record::line(&paste).as_deref(),
+        Some(r#"paste "SELECT 'a\"b'\n""#)
+    );
+    let text = format!(
+        "# a comment\n0 key i\n15 key ab<Esc>\n40 {}\n70 click 3 4\n90 resize 80 24\n",
+        record::line(&paste).unwrap()
+    );
+    let events = record::parse(&text).unwrap();
+    assert_eq!(events.len(), 7);
+    assert_eq!(events[2].0, Duration::from_millis(15));
+    assert_eq!(events[3].1, Event::Key(KeyEvent::from(KeyCode::Esc)));
+    assert_eq!(events[4].1, paste);
+    assert!(matches!(
+        events[5].1,
+        Event::Mouse(mouse) if matches!(mouse.kind, MouseEventKind::Down(_))
+            && (mouse.column, mouse.row) == (3, 4)
+    ));
+    assert_eq!(events[6].1, Event::Resize(80, 24));
+    assert_eq!(
+        record::parse("0 key i\nsoon key j\n"),
+        Err("Line 2: soon key j".into())
+    );
+}
+
+#[test]
+fn recorder_writes_as_it_goes() {
+    let path = std::env::temp_dir().join(format!("dbvi-record-{}", std::process::id()));
+    let mut recorder = Recorder::create(&path).unwrap();
+    recorder
+        .record(&Event::Key(KeyEvent::new(
+            KeyCode::Char('w'),
+            KeyModifiers::CONTROL,
+        )))
+        .unwrap();
+    recorder.record(&Event::FocusGained).unwrap();
+    recorder
+        .record(&Event::Key(KeyEvent::from(KeyCode::Char('<'))))
+        .unwrap();
+    assert_eq!(recorder.events(), 2);
+    for key in keys::parse(":record<CR>") {
+        recorder.record(&Event::Key(key)).unwrap();
+    }
+    assert_eq!(recorder.finish().unwrap(), (path.clone(), 2));
+    let text = std::fs::read_to_string(&path).unwrap();
+    let events: Vec<Event> = record::parse(&text)
+        .unwrap()
+        .into_iter()
+        .map(|(_, event)| event)
+        .collect();
+    assert_eq!(
+        events,
+        [
+            Event::Key(KeyEvent::new(KeyCode::Char('w'), KeyModifiers::CONTROL)),
+            Event::Key(KeyEvent::from(KeyCode::Char('<'))),
+        ]
+    );
+    std::fs::remove_file(path).unwrap();
+}
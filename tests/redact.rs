@@ -0,0 +1,117 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod common;
+
+use common::{Harness, results_of};
+use dbvi::Command;
+use dbvi::export;
+use dbvi::redact::{MASK, Redactions};
+use dbvi::results::ResultSet;
+
+fn people() -> ResultSet {
+    results_of(
+        &["name text", "SSN text", "user_password text"],
+        [
+            [Some("ada"), Some("078-05-1120"), Some("hunter2")],
+            [Some("bob"), None, Some("swordfish")],
+        ],
+    )
+}
+
+#[test]
+fn globs_on_column_names() {
+    let redactions = Redactions::new(["*password*".to_string(), "ssn".to_string()]);
+    assert!(redactions.covers("password"));
+    assert!(redactions.covers("user_PASSWORD_hash"));
+    assert!(redactions.covers("SSN"));
+    assert!(!redactions.covers("ssn_last4"));
+    assert!(!redactions.covers("name"));
+    assert!(Redactions::new(["e?ail".to_string()]).covers("email"));
+}
+
+#[test]
+fn adding_and_removing() {
+    let mut redactions = Redactions::default();
+    assert!(redactions.add("*ssn*"));
+    assert!(!redactions.add("*SSN*"));
+    assert!(redactions.covers("ssn"));
+    assert!(!redactions.remove("ssn"));
+    assert!(redactions.remove("*Ssn*"));
+    assert!(redactions.is_empty());
+}
+
+#[test]
+fn masked_in_exports() {
+    let results = people();
+    let redactions = Redactions::new(["*password*".to_string(), "ssn".to_string()]);
+    let mut csv = Vec::new();
+    export::write_csv(&redactions.apply(&results), &mut csv).unwrap();
+    assert_eq!(
+        String::from_utf8(csv).unwrap(),
+        format!("name,SSN,user_password\nada,{MASK},{MASK}\nbob,,{MASK}\n")
+    );
+    // Nothing to mask leaves the results as they are.
+    let none = Redactions::new(["email".to_string()]);
+    assert!(matches!(
+        none.apply(&results),
+        std::borrow::Cow::Borrowed(_)
+    ));
+}
+
+#[test]
+fn copy_is_masked_by_the_server() {
+    let redactions = Redactions::new(["*password*".to_string()]);
+    let names = ["id".to_string(), "password".to_string()];
+    assert_eq!(
+        redactions.wrap("SELECT * FROM users;", &names).unwrap(),
+        format!(
+            "SELECT c1 AS \"id\", CASE WHEN c2 IS NULL THEN NULL ELSE '{MASK}' END AS \"password\" \
+             FROM (SELECT * FROM users) AS t(c1, c2)"
+        )
+    );
+    assert_eq!(redactions.wrap("SELECT 1", &["id".to_string()]), None);
+}
+
+#[test]
+fn masked_in_the_grid_and_yanks() {
+    let mut harness = Harness::new();
+    harness.state.show_results(Some(people()));
+    assert!(harness.render().contains("hunter2"));
+    assert!(harness.state.redact("*password*"));
+    assert!(harness.state.redact("ssn"));
+    let screen = harness.render();
+    assert!(!screen.contains("hunter2"), "{screen}");
+    assert!(!screen.contains("078-05-1120"), "{screen}");
+    assert!(screen.contains(MASK), "{screen}");
+    assert!(screen.contains("ada"), "{screen}");
+    harness.keys("<C-w>kllgi");
+    assert_eq!(harness.state.status(), "user_password is redacted");
+    harness.keys("Vy<C-w>jp");
+    assert_eq!(harness.state.text(), format!("ada\t{MASK}\t{MASK}"));
+}
+
+#[test]
+fn commands() {
+    let mut harness = Harness::new();
+    harness.keys(":redact *ssn*<CR>:unredact *ssn*<CR>:redact<CR>");
+    assert!(matches!(
+        harness.commands.as_slice(),
+        [
+            Command::Redact(Some(a)),
+            Command::Unredact(b),
+            Command::Redact(None)
+        ] if a == "*ssn*" && b == "*ssn*"
+    ));
+}
@@ -0,0 +1,125 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use dbvi::results::{Column, ResultSet, Rows};
+
+fn cells(rows: &Rows) -> Vec<Vec<Option<String>>> {
+    rows.iter().map(|row| row.to_vec()).collect()
+}
+
+#[test]
+fn push_and_get() {
+    let mut rows = Rows::default();
+    rows.push([Some("1"), None, Some("")]);
+    rows.push([Some("2"), Some("two")]);
+    rows.push([Some("3"), Some("three"), Some("x"), Some("extra")]);
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows.cell(0, 1), None);
+    assert_eq!(rows.cell(0, 2), Some(""));
+    assert_eq!(rows.cell(1, 2), None);
+    assert_eq!(rows.cell(2, 1), Some("three"));
+    assert_eq!(rows.get(2).unwrap().len(), 3);
+    assert!(rows.get(3).is_none());
+}
+
+#[test]
+fn many_rows() {
+    let mut rows = Rows::default();
+    for i in 0..1000 {
+        rows.push([Some(i.to_string()), (i % 3 != 0).then(|| "x".repeat(i % 5))]);
+    }
+    for i in [0, 63, 64, 65, 999] {
+        let row = rows.get(i).unwrap();
+        assert_eq!(row.get(0), Some(i.to_string().as_str()));
+        assert_eq!(row.get(1).map(str::len), (i % 3 != 0).then_some(i % 5));
+    }
+    assert_eq!(rows.iter().nth(500).unwrap().get(0), Some("500"));
+}
+
+#[test]
+fn sort_select_and_retain() {
+    let mut results = ResultSet::new(vec![Column::new("n", "int4"), Column::new("s", "text")]);
+    results.rows = vec![
+        vec![Some("10".into()), Some("b".into())],
+        vec![None, Some("c".into())],
+        vec![Some("9".into()), Some("a".into())],
+    ]
+    .into();
+    results.sort(0, false);
+    assert_eq!(
+        cells(&results.rows),
+        [
+            vec![Some("9".into()), Some("a".into())],
+            vec![Some("10".into()), Some("b".into())],
+            vec![None, Some("c".into())],
+        ]
+    );
+    assert_eq!(
+        cells(&results.rows.select([2, 0]).project(&[1])),
+        [vec![Some("c".into())], vec![Some("a".into())]]
+    );
+    results.rows.retain(|row| row.get(0).is_some());
+    results.rows.truncate(1);
+    assert_eq!(
+        cells(&results.rows),
+        [vec![Some("9".into()), Some("a".into())]]
+    );
+}
+
+#[test]
+fn spill_to_disk() {
+    let mut rows = Rows::default().spill_over(4096);
+    for i in 0..2000 {
+        rows.push([
+            Some(i.to_string()),
+            (i % 7 != 0).then(|| "y".repeat(i % 11)),
+        ]);
+    }
+    assert_eq!(rows.len(), 2000);
+    assert!(rows.on_disk() > 0);
+    assert!(rows.on_disk() < rows.len());
+    for i in [0, 1, 63, 64, 999, 1999] {
+        assert_eq!(rows.cell(i, 0), Some(i.to_string().as_str()));
+        assert_eq!(
+            rows.cell(i, 1).map(str::len),
+            (i % 7 != 0).then_some(i % 11)
+        );
+    }
+
+    rows.page_out(1990..2000);
+    assert_eq!(rows.cell(5, 0), Some("5"));
+    let on_disk = rows.on_disk();
+    rows.set(5, 1, Some("changed"));
+    rows.set(6, 0, None);
+    assert_eq!(rows.on_disk(), on_disk);
+    assert_eq!(rows.cell(5, 1), Some("changed"));
+    assert_eq!(rows.cell(6, 1), Some("yyyyyy"));
+    // What's set outlives the chunk being let go of.
+    rows.page_out(1990..2000);
+    assert_eq!(rows.cell(5, 1), Some("changed"));
+    assert_eq!(rows.cell(6, 0), None);
+    assert_eq!(
+        cells(&rows.select([5])),
+        [vec![Some("5".into()), Some("changed".into())]]
+    );
+
+    let picked = rows.select([1999, 3]).project(&[0]);
+    assert_eq!(
+        cells(&picked),
+        [vec![Some("1999".into())], vec![Some("3".into())]]
+    );
+    rows.truncate(10);
+    assert_eq!(rows.len(), 10);
+    assert_eq!(rows.cell(9, 0), Some("9"));
+}
@@ -0,0 +1,85 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod common;
+
+use common::results_of;
+use dbvi::commands;
+use dbvi::grid::Grid;
+use dbvi::redact::Redactions;
+use dbvi::results::ResultSet;
+use dbvi::rowdiff::{RowDiff, pick};
+use dbvi::state::Command;
+
+fn orders() -> ResultSet {
+    results_of(
+        &["id int4", "status text", "card text", "total numeric"],
+        [
+            [Some("1"), Some("paid"), Some("4111"), Some("3.70")],
+            [Some("2"), Some("failed"), None, Some("3.70")],
+            [Some("3"), Some("paid"), Some("5500"), Some("3.70")],
+        ],
+    )
+}
+
+#[test]
+fn picks_the_marked_or_selected_rows() {
+    let results = orders();
+    let mut grid = Grid::new(&results);
+    assert!(pick(&grid, None).is_err());
+    assert_eq!(pick(&grid, Some((1, 3))), Ok((0, 2)));
+    grid.marked.extend([0, 2]);
+    assert_eq!(pick(&grid, None), Ok((0, 2)));
+    grid.marked.insert(1);
+    assert!(pick(&grid, None).is_err());
+
+    grid.marked.clear();
+    grid.toggle_selection(true);
+    grid.row = 1;
+    assert_eq!(pick(&grid, None), Ok((0, 1)));
+}
+
+#[test]
+fn counts_the_columns_that_differ() {
+    let results = orders();
+    let diff = RowDiff::new(&results, (0, 1), &Redactions::default()).unwrap();
+    assert_eq!(diff.differing(), 3);
+    assert_eq!(
+        diff.title,
+        "Row 1 │ row 2: 3 of 4 columns differ (d for only those)"
+    );
+    // Masked, but still found to differ.
+    let redactions = Redactions::new(["card".to_string()]);
+    let diff = RowDiff::new(&results, (0, 2), &redactions).unwrap();
+    assert_eq!(diff.differing(), 2);
+
+    let err = RowDiff::new(&results, (1, 1), &redactions).unwrap_err();
+    assert_eq!(err, "Pick two different rows of the 3");
+    assert!(RowDiff::new(&results, (0, 7), &redactions).is_err());
+}
+
+#[test]
+fn parses() {
+    assert_eq!(commands::parse("rowdiff"), Ok(Command::RowDiff(None)));
+    assert_eq!(
+        commands::parse("rowdiff 3 7"),
+        Ok(Command::RowDiff(Some((3, 7))))
+    );
+    for line in ["rowdiff 3", "rowdiff 0 1", "rowdiff a b"] {
+        assert_eq!(
+            commands::parse(line),
+            Err("Usage: rowdiff [<row> <row>]".into())
+        );
+    }
+}
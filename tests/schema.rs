@@ -0,0 +1,154 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod common;
+
+use common::{Harness, schema_of};
+use dbvi::Command;
+use dbvi::db::schema::{Schema, ddl_targets};
+
+fn schema() -> Schema {
+    schema_of(&[
+        (
+            "public",
+            "orders",
+            &["id integer not null generated primary key"],
+        ),
+        (
+            "audit",
+            "orders",
+            &["id integer not null generated primary key"],
+        ),
+        (
+            "audit",
+            "Events",
+            &["id integer not null generated primary key"],
+        ),
+    ])
+}
+
+#[test]
+fn find() {
+    let schema = schema();
+    assert_eq!(schema.find("orders").unwrap().schema, "public");
+    assert_eq!(schema.find("audit.orders").unwrap().schema, "audit");
+    assert_eq!(schema.find("AUDIT.Orders").unwrap().schema, "audit");
+    assert!(schema.find("audit.Events").is_none());
+    let events = schema.find("audit.\"Events\"").unwrap();
+    assert_eq!(events.qualified(), "audit.\"Events\"");
+    assert_eq!(schema.find("orders").unwrap().qualified(), "orders");
+    assert!(schema.find("missing").is_none());
+}
+
+#[test]
+fn resolve() {
+    let schema = schema();
+    let path = ["audit".to_string(), "public".to_string()];
+    assert_eq!(schema.resolve("orders", &path).unwrap().schema, "audit");
+    assert_eq!(
+        schema.resolve("public.orders", &path).unwrap().schema,
+        "public"
+    );
+    let path = ["sales".to_string(), "public".to_string()];
+    assert_eq!(schema.resolve("orders", &path).unwrap().schema, "public");
+    assert_eq!(schema.resolve("orders", &[]).unwrap().schema, "public");
+}
+
+#[test]
+fn invalidate() {
+    let mut schema = schema();
+    let dropped = schema.invalidate(&["orders".into(), "audit.orders".into(), "nope".into()]);
+    assert_eq!(dropped, 2);
+    assert_eq!(schema.relations.len(), 1);
+}
+
+#[test]
+fn save_and_load() {
+    let dir = std::env::temp_dir().join(format!("dbvi-schema-{}", std::process::id()));
+    let path = dir.join("schema").join("cache.json");
+    assert_eq!(Schema::load(&path).unwrap(), Schema::default());
+    let schema = schema();
+    schema.save(&path).unwrap();
+    assert_eq!(Schema::load(&path).unwrap(), schema);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn changes() {
+    let schema = schema();
+    let signatures = |list: &[(&str, &str)]| -> Vec<(String, String)> {
+        list.iter()
+            .map(|(key, signature)| (key.to_string(), signature.to_string()))
+            .collect()
+    };
+    let same = signatures(&[
+        ("public.orders", "1/2/"),
+        ("audit.orders", "1/2/"),
+        ("audit.Events", "1/2/"),
+    ]);
+    assert!(schema.changes(&same).is_empty());
+    assert_eq!(schema.changes(&same).to_string(), "no changes");
+
+    let migrated = signatures(&[
+        ("public.orders", "1/7/"),
+        ("audit.orders", "1/2/"),
+        ("public.invoices", "9/9/"),
+    ]);
+    let changes = schema.changes(&migrated);
+    assert_eq!((changes.added, changes.changed, changes.dropped), (1, 1, 1));
+    assert_eq!(changes.to_string(), "1 new, 1 changed, 1 dropped");
+
+    // Servers without xmin can't tell.
+    let unsigned = signatures(&[
+        ("public.orders", ""),
+        ("audit.orders", ""),
+        ("audit.Events", ""),
+    ]);
+    assert!(schema.changes(&unsigned).is_empty());
+}
+
+#[test]
+fn ddl() {
+    assert_eq!(ddl_targets("select 1; update t set x = 1"), None);
+    assert_eq!(
+        ddl_targets("create table if not exists audit.log(id int)"),
+        Some(vec!["audit.log".into()])
+    );
+    assert_eq!(
+        ddl_targets("-- widen it\nALTER TABLE ONLY orders ALTER COLUMN total TYPE numeric;"),
+        Some(vec!["orders".into()])
+    );
+    assert_eq!(
+        ddl_targets("drop table if exists a, \"B\" cascade; create or replace view v as select 1"),
+        Some(vec!["a".into(), "\"B\"".into(), "v".into()])
+    );
+    assert_eq!(ddl_targets("drop schema s cascade"), Some(vec![]));
+    assert_eq!(
+        ddl_targets("create materialized view mv as select 1"),
+        Some(vec!["mv".into()])
+    );
+}
+
+#[test]
+fn command() {
+    let mut harness = Harness::new();
+    harness.keys(":schema<CR>:schema refresh<CR>");
+    assert_eq!(
+        harness.commands,
+        vec![
+            Command::Schema { refresh: false },
+            Command::Schema { refresh: true }
+        ]
+    );
+}
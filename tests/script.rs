@@ -0,0 +1,103 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use dbvi::Command;
+use dbvi::script::{Error, Failure, Format, parse};
+
+#[test]
+fn commands_by_line() {
+    let script = parse(
+        "\" nightly extract\n\
+         # also a comment\n\
+         connect warehouse\n\
+         \n\
+         :edit ~/orders.sql\n\
+         run expect=3\n\
+         export csv /tmp/orders.csv\n\
+         quit\n",
+    )
+    .unwrap();
+    let lines: Vec<usize> = script.iter().map(|(line, _)| *line).collect();
+    assert_eq!(lines, [3, 5, 6, 7, 8]);
+    assert_eq!(script[0].1, Command::Connect("warehouse".into()));
+    assert_eq!(script[1].1, Command::Edit("~/orders.sql".into()));
+    assert_eq!(script[2].1, Command::Run { expect: Some(3) });
+    assert_eq!(
+        script[3].1,
+        Command::Export {
+            exporter: "csv".into(),
+            path: "/tmp/orders.csv".into(),
+        }
+    );
+}
+
+#[test]
+fn refused_before_anything_runs() {
+    assert_eq!(
+        parse("connect warehouse\nbrowse orders\n"),
+        Err((2, ":browse can't run in a script".into()))
+    );
+    assert_eq!(
+        parse("run expect=many\n"),
+        Err((1, "Usage: run [expect=<rows>]".into()))
+    );
+}
+
+#[test]
+fn failures_exit_apart() {
+    let lost = Error::from_sqlx("Failed to run query", &sqlx::Error::PoolTimedOut);
+    assert_eq!(lost.failure, Failure::Connection);
+    assert_eq!(lost.failure.exit_code(), 3);
+    let odd = Error::from_sqlx("Failed to run query", &sqlx::Error::Protocol("odd".into()));
+    assert_eq!(odd.failure, Failure::Command);
+    let codes: Vec<u8> = [
+        Failure::Command,
+        Failure::Script,
+        Failure::Connection,
+        Failure::Sql,
+        Failure::Timeout,
+    ]
+    .map(Failure::exit_code)
+    .into();
+    assert_eq!(codes, [1, 2, 3, 4, 5]);
+}
+
+#[test]
+fn reports() {
+    let err = Error::from(String::from("No results"));
+    assert_eq!(
+        Format::Text.report("x.dbvi", Some(3), Err(&err)),
+        "x.dbvi:3: No results"
+    );
+    assert_eq!(
+        Format::Text.report("x.dbvi", Some(1), Ok("Connected")),
+        "x.dbvi:1: Connected"
+    );
+    let json: serde_json::Value =
+        serde_json::from_str(&Format::Json.report("x.dbvi", Some(3), Err(&err))).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!({
+            "file": "x.dbvi",
+            "line": 3,
+            "error": "command",
+            "message": "No results",
+            "sqlstate": null,
+            "exit": 1,
+        })
+    );
+    let json: serde_json::Value =
+        serde_json::from_str(&Format::Json.report("x.dbvi", None, Ok("Connected"))).unwrap();
+    assert_eq!(json["line"], serde_json::Value::Null);
+}
@@ -0,0 +1,51 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use dbvi::db::session::SessionSettings;
+
+#[test]
+fn replays_settings() {
+    let mut settings = SessionSettings::default();
+    settings.record("SET search_path TO sales");
+    settings.record("set local work_mem = '1GB'");
+    settings.record("SET search_path = public;");
+    settings.record("LISTEN jobs");
+    assert_eq!(
+        settings.statements(),
+        ["SET search_path = public", "LISTEN jobs"]
+    );
+    settings.record("unlisten *");
+    settings.record("RESET search_path");
+    assert!(settings.statements().is_empty());
+}
+
+#[test]
+fn replays_prepared_statements() {
+    let mut settings = SessionSettings::default();
+    settings.record("PREPARE byid AS SELECT $1");
+    settings.record("PREPARE other(int) AS SELECT $1");
+    settings.record("PREPARE byid AS SELECT $1 + 1");
+    assert_eq!(
+        settings.statements(),
+        [
+            "PREPARE other(int) AS SELECT $1",
+            "PREPARE byid AS SELECT $1 + 1"
+        ]
+    );
+    settings.record("DEALLOCATE other");
+    assert_eq!(settings.statements(), ["PREPARE byid AS SELECT $1 + 1"]);
+    settings.record("SET search_path TO sales");
+    settings.record("DEALLOCATE PREPARE ALL");
+    assert_eq!(settings.statements(), ["SET search_path TO sales"]);
+}
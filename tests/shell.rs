@@ -0,0 +1,51 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod common;
+
+use common::Harness;
+use dbvi::{Command, shell};
+
+#[test]
+fn bang_and_pipe() {
+    let mut harness = Harness::new();
+    harness.keys(":!ls -l<CR>:pipe wc -l<CR>");
+    assert_eq!(
+        harness.commands,
+        [
+            Command::Shell("ls -l".into()),
+            Command::Pipe("wc -l".into())
+        ]
+    );
+    harness.keys(":!<CR>");
+    assert_eq!(harness.state.status(), "Usage: !<command>");
+}
+
+#[tokio::test]
+async fn pipe_feeds_stdin() {
+    let (output, status) = shell::pipe("tr a-z A-Z", b"id,name\n1,ada\n".to_vec())
+        .await
+        .unwrap();
+    assert!(status.success());
+    assert_eq!(output, "ID,NAME\n1,ADA\n");
+}
+
+#[tokio::test]
+async fn pipe_reports_failure() {
+    let (output, status) = shell::pipe("head -c 2; echo oops >&2; exit 3", vec![b'x'; 1 << 20])
+        .await
+        .unwrap();
+    assert_eq!(status.code(), Some(3));
+    assert_eq!(output, "xxoops\n");
+}
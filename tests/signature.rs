@@ -0,0 +1,44 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use dbvi::signature::{call_at, split_arguments};
+
+#[test]
+fn calls() {
+    let at = |text: &str| call_at(text, text.len());
+    assert_eq!(at("select upper("), Some(("upper".into(), 0)));
+    assert_eq!(at("select pg.round(x, "), Some(("pg.round".into(), 1)));
+    assert_eq!(
+        at("select coalesce(lower(a), 'x,y', b"),
+        Some(("coalesce".into(), 2))
+    );
+    assert_eq!(at("select coalesce(lower(a"), Some(("lower".into(), 0)));
+    assert_eq!(at("select upper(a) "), None);
+    assert_eq!(at("select 1 where x in (1, "), None);
+    assert_eq!(at("select upper('a, ("), None);
+    assert_eq!(at("select upper(a); select "), None);
+}
+
+#[test]
+fn arguments() {
+    assert_eq!(
+        split_arguments("a integer, b text DEFAULT 'x, y'::text, c numeric DEFAULT round(1, 2)"),
+        [
+            "a integer",
+            "b text DEFAULT 'x, y'::text",
+            "c numeric DEFAULT round(1, 2)"
+        ]
+    );
+    assert!(split_arguments("").is_empty());
+}
@@ -0,0 +1,86 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod common;
+
+use common::Harness;
+use dbvi::Command;
+use dbvi::results::{Column, ResultSet};
+use dbvi::snapshot::{self, Snapshot};
+
+#[test]
+fn save_and_load() {
+    let dir = std::env::temp_dir().join(format!("dbvi-snapshot-{}", std::process::id()));
+    let mut results = ResultSet::new(vec![Column::new("id", "int4"), Column::new("note", "text")]);
+    let long = "x".repeat(300);
+    results.rows = vec![
+        vec![Some("1".into()), Some("héllo\nworld".into())],
+        vec![Some("2".into()), None],
+        vec![Some("3".into()), Some(long.clone())],
+    ]
+    .into();
+    let snapshot = Snapshot {
+        query: Some("select * from notes".into()),
+        results,
+    };
+    snapshot.save(&dir, "notes", false).unwrap();
+    assert_eq!(
+        snapshot.save(&dir, "notes", false).unwrap_err(),
+        "Result notes exists (add ! to replace it)"
+    );
+    snapshot.save(&dir, "notes", true).unwrap();
+    assert!(snapshot.save(&dir, "../notes", false).is_err());
+
+    let loaded = Snapshot::load(&dir, "notes").unwrap();
+    assert_eq!(loaded.query.as_deref(), Some("select * from notes"));
+    let columns: Vec<(&str, &str)> = loaded
+        .results
+        .columns
+        .iter()
+        .map(|column| (column.name.as_str(), column.ty.as_str()))
+        .collect();
+    assert_eq!(columns, [("id", "int4"), ("note", "text")]);
+    assert_eq!(loaded.results.rows, snapshot.results.rows);
+    assert_eq!(snapshot::list(&dir).unwrap(), ["notes"]);
+    assert_eq!(
+        Snapshot::load(&dir, "missing").unwrap_err(),
+        "No saved result missing"
+    );
+
+    std::fs::write(dir.join("broken.dbvr"), b"DBVR\x01\x05sel").unwrap();
+    assert!(Snapshot::load(&dir, "broken").is_err());
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn commands() {
+    let mut harness = Harness::new();
+    harness.keys(":result save! big<CR>:result load big<CR>:result<CR>:result drop big<CR>");
+    assert_eq!(
+        harness.commands,
+        vec![
+            Command::SaveResult {
+                name: "big".into(),
+                force: true,
+            },
+            Command::LoadResult("big".into()),
+            Command::ListResults,
+        ]
+    );
+    assert!(
+        harness
+            .render()
+            .contains("Usage: result save[!]|load <name>")
+    );
+}
@@ -0,0 +1,82 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod common;
+
+use common::{Harness, results_of};
+use dbvi::results::ResultSet;
+use dbvi::sparkline::{Sparkline, Trend};
+
+fn results(values: &[&str]) -> ResultSet {
+    let rows = values
+        .iter()
+        .map(|value| [(*value != "NULL").then_some(*value), Some("db1")]);
+    results_of(&["latency float8", "host text"], rows)
+}
+
+#[test]
+fn spans_the_numbers_and_skips_nulls() {
+    let sparkline = Sparkline::of(&results(&["1", "NULL", "5", "3", "8"]), 0).unwrap();
+    assert_eq!((sparkline.min, sparkline.max), (1.0, 8.0));
+    assert_eq!(sparkline.render(20), "▁▅▃█ ↗ 1..8");
+    assert_eq!(sparkline.summary(), "min 1, max 8, trending up");
+}
+
+#[test]
+fn finds_the_trend() {
+    let down = Sparkline::of(&results(&["9", "8", "7", "3", "2", "1"]), 0).unwrap();
+    assert_eq!(down.trend, Trend::Down);
+    let flat = Sparkline::of(&results(&["4", "9", "1", "9", "4"]), 0).unwrap();
+    assert_eq!(flat.trend, Trend::Flat);
+    let same = Sparkline::of(&results(&["2", "2", "2"]), 0).unwrap();
+    assert_eq!(same.render(10), "▁▁▁ →");
+}
+
+#[test]
+fn keeps_spikes_when_squeezed() {
+    let mut values = vec!["1"; 40];
+    values[17] = "100";
+    let sparkline = Sparkline::of(&results(&values), 0).unwrap();
+    let line = sparkline.render(10);
+    assert_eq!(line.chars().count(), 10);
+    assert!(line.contains('█'), "{line}");
+    assert!(!line.contains(".."), "no room for the range: {line}");
+}
+
+#[test]
+fn has_nothing_for_text() {
+    assert_eq!(Sparkline::of(&results(&["1"]), 1), None);
+    assert_eq!(Sparkline::of(&results(&["NULL"]), 0), None);
+}
+
+#[test]
+fn toggles_under_the_column_name() {
+    let mut harness = Harness::new();
+    harness
+        .state
+        .show_results(Some(results(&["1", "2", "4", "16000"])));
+    harness.keys("<C-w>kzs");
+    assert_eq!(
+        harness.state.status(),
+        "Sparkline of latency: min 1, max 16k, trending up"
+    );
+    let screen = harness.render();
+    assert!(screen.contains("▁▁▁█ ↗ 1..16k"), "{screen}");
+
+    harness.keys("zs");
+    assert!(!harness.render().contains('↗'));
+
+    harness.keys("lzs");
+    assert_eq!(harness.state.status(), "host isn't numeric");
+}
@@ -0,0 +1,91 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use dbvi::statements::{is_bulk_write, is_explainable, is_write, limit, split};
+
+#[test]
+fn splits_on_semicolons() {
+    assert_eq!(split("select 1; select 2;"), ["select 1", "select 2"]);
+}
+
+#[test]
+fn ignores_semicolons_in_literals_and_comments() {
+    assert_eq!(
+        split("select ';'; -- a; b\nselect $$;$$"),
+        ["select ';'", "-- a; b\nselect $$;$$"]
+    );
+}
+
+#[test]
+fn drops_empty_statements() {
+    assert_eq!(split(";; /* only a comment */;\nselect 1"), ["select 1"]);
+}
+
+#[test]
+fn bulk_writes() {
+    assert!(is_bulk_write("select 1; UPDATE t SET x = 1"));
+    assert!(is_bulk_write("delete from t"));
+    assert!(!is_bulk_write("insert into t values (1)"));
+    assert!(!is_bulk_write("select 'delete'"));
+}
+
+#[test]
+fn explainable() {
+    assert!(is_explainable("-- all of them\nSELECT * FROM a, b;"));
+    assert!(is_explainable("with x as (select 1) delete from t"));
+    assert!(!is_explainable("select 1; select 2"));
+    assert!(!is_explainable("create index on t (x)"));
+    assert!(!is_explainable("explain select 1"));
+}
+
+#[test]
+fn writes() {
+    assert!(is_write("insert into t values (1)"));
+    assert!(is_write("select 1; create table t (x int)"));
+    assert!(is_write(
+        "with gone as (delete from t returning *) select * from gone"
+    ));
+    assert!(is_write("select * into copy from t"));
+    assert!(is_write("call refresh()"));
+    assert!(!is_write("select 'delete', \"update\" from t -- insert"));
+    assert!(!is_write(
+        "begin; set default_transaction_read_only = off; commit"
+    ));
+    assert!(!is_write("explain select * from t"));
+    assert!(!is_write("-- only a comment"));
+}
+
+#[test]
+fn limits() {
+    assert_eq!(
+        limit("select * from t; -- all of it", 100).unwrap(),
+        "select * from t LIMIT 100"
+    );
+    assert_eq!(
+        limit("with x as (select 1) table x", 5).unwrap(),
+        "with x as (select 1) table x LIMIT 5"
+    );
+    assert_eq!(limit("select * from t limit 10", 100), None);
+    assert_eq!(
+        limit(
+            "select * from (select * from t fetch first 1 row only) s",
+            100
+        ),
+        None
+    );
+    assert_eq!(limit("select * from t for update", 100), None);
+    assert_eq!(limit("select 1; select 2", 100), None);
+    assert_eq!(limit("insert into t select * from u", 100), None);
+    assert_eq!(limit("show search_path", 100), None);
+}
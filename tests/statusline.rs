@@ -0,0 +1,124 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod common;
+
+use common::Harness;
+use dbvi::db::session::Transaction;
+use dbvi::results::{Column, ResultSet};
+use dbvi::statusline::StatusLine;
+use ratatui::style::Color;
+
+#[test]
+fn segments() {
+    let statusline: StatusLine = toml::from_str(
+        r##"
+        left = ["mode", "transaction", "status"]
+        right = ["rows", "timing", "connection"]
+        colors = { insert = "green", command = "#d7af5f" }
+        "##,
+    )
+    .unwrap();
+    assert_eq!(statusline.colors.insert, Some(Color::Green));
+    assert_eq!(
+        statusline.colors.command,
+        Some(Color::Rgb(0xd7, 0xaf, 0x5f))
+    );
+
+    let mut harness = Harness::new();
+    let mut results = ResultSet::new(vec![Column::new("id", "int4")]);
+    results.rows = vec![vec![Some("1".into())], vec![Some("2".into())]].into();
+    harness.state.show_results(Some(results));
+    let (left, right) = statusline.render(&harness.state);
+    assert_eq!(
+        left.to_string(),
+        "Mode: Normal | Welcome to dbvi! Press `q` to quit."
+    );
+    // Not set in the config, so the default.
+    assert_eq!(left.spans[0].style.fg, Some(Color::Blue));
+    assert_eq!(right.to_string(), "2 rows ● test dbvi@localhost ? ");
+
+    harness.keys("i");
+    let (left, _) = statusline.render(&harness.state);
+    assert_eq!(left.spans[0].content, "Mode: Insert");
+    assert_eq!(left.spans[0].style.fg, Some(Color::Green));
+}
+
+#[test]
+fn unknown_color() {
+    let err = toml::from_str::<StatusLine>("colors = { normal = \"mauve\" }").unwrap_err();
+    assert!(err.to_string().contains("unknown color mauve"));
+}
+
+#[test]
+fn transactions() {
+    let idle = Transaction::Idle;
+    assert_eq!(idle.after("select 1", true), Transaction::Idle);
+    assert_eq!(idle.after("select 1", false), Transaction::Idle);
+    let open = idle.after("BEGIN;\nupdate t set x = 1", true);
+    assert_eq!(open, Transaction::Open);
+    let failed = open.after("insert into t values (1)", false);
+    assert_eq!(failed, Transaction::Failed);
+    assert_eq!(
+        failed.after("rollback to savepoint a", true),
+        Transaction::Open
+    );
+    assert_eq!(failed.after("rollback", true), Transaction::Idle);
+    assert_eq!(open.after("commit; select 1", true), Transaction::Idle);
+    assert_eq!(
+        idle.after("start transaction read only", true),
+        Transaction::Open
+    );
+
+    assert!(!Transaction::is_control("update t set x = 1; select 1"));
+    assert!(!Transaction::is_control("prepare q as select 1"));
+    assert!(Transaction::is_control("select 1; COMMIT"));
+    assert!(Transaction::is_control("savepoint a"));
+    assert!(Transaction::is_control("release a"));
+    assert!(Transaction::is_control("prepare transaction 'x'"));
+}
+
+#[test]
+fn keys_being_typed() {
+    let statusline: StatusLine = toml::from_str(r#"right = ["keys"]"#).unwrap();
+    let mut harness = Harness::new();
+    let keys = |harness: &Harness| statusline.render(&harness.state).1.to_string();
+    harness.keys("d");
+    assert_eq!(keys(&harness), "d ");
+    harness.keys("i");
+    assert_eq!(keys(&harness), "di ");
+    harness.keys("w");
+    assert_eq!(keys(&harness), "");
+
+    let mut results = ResultSet::new(vec![Column::new("id", "int4")]);
+    results.rows = vec![vec![Some("1".into())], vec![Some("2".into())]].into();
+    harness.state.show_results(Some(results));
+    harness.keys("<C-w>");
+    assert_eq!(keys(&harness), "<C-w> ");
+    harness.keys("k12g");
+    assert_eq!(keys(&harness), "12g ");
+    harness.keys("g");
+    assert_eq!(keys(&harness), "");
+}
+
+#[test]
+fn last_ex_command() {
+    let mut harness = Harness::new();
+    harness.keys(":noh<CR>");
+    let screen = harness.render();
+    assert_eq!(screen.lines().last().map(str::trim), Some(":noh"));
+    harness.keys(":set wr<Esc>");
+    let screen = harness.render();
+    assert_eq!(screen.lines().last().map(str::trim), Some(":noh"));
+}
@@ -0,0 +1,91 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use dbvi::commands;
+use dbvi::plan::Plan;
+use dbvi::state::Command;
+use dbvi::suggest::{self, Suggestion};
+use serde_json::json;
+
+#[test]
+fn command() {
+    assert_eq!(commands::parse("suggest-index"), Ok(Command::SuggestIndex));
+}
+
+#[test]
+fn columns_compared_for_equality_come_first() {
+    let all = |_: Option<&str>| true;
+    assert_eq!(
+        suggest::columns(
+            "((created_at >= '2026-01-01'::date) AND (status = 'paid'::text) AND (lower(email) = 'x'::text))",
+            all
+        ),
+        ["status", "created_at"]
+    );
+    assert_eq!(
+        suggest::columns(
+            "((note ~~ 'a%'::text) AND (kind <> 'x'::text) AND (NOT deleted))",
+            all
+        ),
+        ["note"]
+    );
+    assert_eq!(
+        suggest::columns("(o.customer_id = c.id)", |qualifier| qualifier == Some("o")),
+        ["customer_id"]
+    );
+}
+
+#[test]
+fn from_an_analyzed_plan() {
+    let plan = Plan::parse(&json!([{
+        "Plan": {
+            "Node Type": "Nested Loop",
+            "Total Cost": 9000.0, "Plan Rows": 10,
+            "Actual Total Time": 80.0, "Actual Rows": 10, "Actual Loops": 1,
+            "Join Filter": "(o.customer_id = c.id)",
+            "Rows Removed by Join Filter": 49990,
+            "Plans": [
+                {
+                    "Node Type": "Seq Scan", "Relation Name": "customers", "Alias": "c",
+                    "Total Cost": 20.0, "Plan Rows": 100,
+                    "Actual Total Time": 1.0, "Actual Rows": 100, "Actual Loops": 1
+                },
+                {
+                    "Node Type": "Seq Scan", "Relation Name": "orders", "Alias": "o",
+                    "Total Cost": 8000.0, "Plan Rows": 500,
+                    "Actual Total Time": 0.5, "Actual Rows": 500, "Actual Loops": 100,
+                    "Filter": "(status = 'paid'::text)",
+                    "Rows Removed by Filter": 20
+                }
+            ]
+        },
+        "Planning Time": 0.1, "Execution Time": 80.0
+    }]))
+    .unwrap();
+    // The scan of orders kept most of what it read, its filter is no
+    // reason for an index.
+    assert_eq!(
+        suggest::suggest(&plan),
+        [Suggestion {
+            table: "orders".into(),
+            columns: vec!["customer_id".into()],
+            why: "Nested Loop removed 49990 rows scanning orders 100 times, Join Filter: (o.customer_id = c.id)".into(),
+        }]
+    );
+    assert_eq!(
+        suggest::suggest(&plan)[0].statement(),
+        "-- Nested Loop removed 49990 rows scanning orders 100 times, Join Filter: (o.customer_id = c.id)\n\
+         CREATE INDEX CONCURRENTLY ON orders (customer_id);"
+    );
+}
@@ -0,0 +1,58 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fs::{self, File};
+
+use dbvi::editor::Buffer;
+use dbvi::swap::{self, Swap};
+
+#[test]
+fn leftovers_of_live_processes_are_left_alone() {
+    let dir = std::env::temp_dir().join(format!("dbvi-swap-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let _swap = Swap::new(dir.clone()).unwrap();
+
+    // Crashed: its lock file is there but no longer locked.
+    fs::write(dir.join("4000001.lock"), "").unwrap();
+    fs::write(dir.join("4000001-1.swp"), "crashed\n\nselect 1").unwrap();
+    // From before lock files, or whose lock file is gone.
+    fs::write(dir.join("4000002-1.swp"), "old\n\nselect 2").unwrap();
+    // Still running elsewhere.
+    let live = File::create(dir.join("4000003.lock")).unwrap();
+    live.try_lock().unwrap();
+    fs::write(dir.join("4000003-1.swp"), "live\n\nselect 3").unwrap();
+
+    let names: Vec<_> = swap::leftovers(&dir)
+        .iter()
+        .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+        .collect();
+    assert_eq!(names, ["4000001-1.swp", "4000002-1.swp"]);
+
+    drop(live);
+    assert_eq!(swap::leftovers(&dir).len(), 3);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn due_only_with_something_to_write() {
+    let dir = std::env::temp_dir().join(format!("dbvi-swap-due-{}", std::process::id()));
+    let mut swap = Swap::new(dir.clone()).unwrap();
+    let mut buffer = Buffer::from_text("query", "select 1");
+    assert_eq!(swap.due(std::slice::from_ref(&buffer)), None);
+
+    buffer.modified = true;
+    assert!(swap.due(std::slice::from_ref(&buffer)).is_some());
+    swap.remove_all();
+    let _ = fs::remove_dir_all(&dir);
+}
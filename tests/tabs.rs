@@ -0,0 +1,46 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod common;
+
+use common::results_of;
+use dbvi::results::ResultSet;
+use dbvi::tabs::Tabs;
+
+fn results(name: &str) -> ResultSet {
+    results_of(&[&format!("{name} int4")], [[Some("1")]])
+}
+
+#[test]
+fn switches_between_statements() {
+    assert!(Tabs::new(vec![("select 1".into(), results("a"))]).is_none());
+
+    let (mut tabs, shown) = Tabs::new(vec![
+        ("select 1 as a".into(), results("a")),
+        ("select 2 as b".into(), results("b")),
+        ("select 3 as c".into(), results("c")),
+    ])
+    .unwrap();
+    assert_eq!((tabs.len(), tabs.current), (3, 2));
+    assert_eq!(shown.columns[0].name, "c");
+    assert_eq!(tabs.sql(), "select 3 as c");
+
+    let shown = tabs.switch(shown, 1);
+    assert_eq!((tabs.current, tabs.sql()), (0, "select 1 as a"));
+    assert_eq!(shown.columns[0].name, "a");
+    let shown = tabs.switch(shown, -1);
+    assert_eq!(shown.columns[0].name, "c");
+    let shown = tabs.switch(shown, -1);
+    assert_eq!((tabs.current, shown.columns[0].name.as_str()), (1, "b"));
+}
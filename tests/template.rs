@@ -0,0 +1,127 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod common;
+
+use common::results_of;
+use dbvi::commands;
+use dbvi::results::ResultSet;
+use dbvi::state::Command;
+use dbvi::template::{self, Template};
+
+fn revenue() -> ResultSet {
+    results_of(
+        &["week text", "total numeric", "orders int8", "late bool"],
+        [
+            [Some("W40"), Some("1200.50"), Some("31"), Some("f")],
+            [Some("W41"), Some("<b>9</b>"), Some("4"), None],
+        ],
+    )
+}
+
+#[test]
+fn names_its_queries_on_the_first_line() {
+    let template =
+        Template::parse("{# queries: money = weekly/revenue, weekly/top-customers #}\n").unwrap();
+    assert_eq!(
+        template.queries,
+        [
+            ("money".to_string(), "weekly/revenue".to_string()),
+            (
+                "top_customers".to_string(),
+                "weekly/top-customers".to_string()
+            ),
+        ]
+    );
+    assert!(Template::parse("# Weekly\n").is_err());
+    assert!(Template::parse("{# queries: #}").is_err());
+    assert_eq!(
+        Template::parse("{# queries: 2024 #}").unwrap_err(),
+        "2024 can't be a name in a template, give 2024 one: alias = 2024"
+    );
+    assert_eq!(
+        Template::parse("{# queries: a/x, b/x #}").unwrap_err(),
+        "Two queries are called x"
+    );
+}
+
+#[test]
+fn renders_markdown() {
+    let template = Template::parse(
+        "{# queries: weekly/revenue #}\n\
+         # {{ database }} on {{ generated }}\n\
+         {% for r in revenue.records %}{{ r.week }}: {{ r.total }}{% if r.orders > 10 %} busy{% endif %}{% if r.late %} late{% endif %}\n{% endfor %}\
+         {{ revenue.count }} weeks\n\
+         {{ revenue.table }}",
+    )
+    .unwrap();
+    let text = template
+        .render(&[revenue()], "shop", "2026-10-15 09:00", false)
+        .unwrap();
+    assert_eq!(
+        text,
+        "# shop on 2026-10-15 09:00\n\
+         W40: 1200.50 busy\n\
+         W41: <b>9</b>\n\
+         2 weeks\n\
+         | week | total | orders | late |\n\
+         | --- | ---: | ---: | --- |\n\
+         | W40 | 1200.50 | 31 | f |\n\
+         | W41 | <b>9</b> | 4 |  |\n"
+    );
+}
+
+#[test]
+fn escapes_html() {
+    let template = Template::parse(
+        "{# queries: revenue #}\n{{ revenue.rows.1.1 }}\n{{ revenue.table | safe }}",
+    )
+    .unwrap();
+    let text = template.render(&[revenue()], "shop", "now", true).unwrap();
+    assert!(
+        text.starts_with("&lt;b&gt;9&lt;&#x2F;b&gt;\n<table>\n"),
+        "{text}"
+    );
+    assert!(
+        text.contains("<td class=\"number\">&lt;b&gt;9&lt;/b&gt;</td>"),
+        "{text}"
+    );
+    assert!(template.render(&[revenue()], "shop", "now", false).is_ok());
+    let broken = Template::parse("{# queries: revenue #}\n{{ revenue.nope.deeper }}").unwrap();
+    assert!(broken.render(&[revenue()], "shop", "now", false).is_err());
+}
+
+#[test]
+fn command() {
+    assert_eq!(
+        commands::parse("report weekly.md.tera"),
+        Ok(Command::RenderReport {
+            template: "weekly.md.tera".into(),
+            output: None,
+        })
+    );
+    assert_eq!(
+        commands::parse("report weekly.tera /tmp/weekly.html"),
+        Ok(Command::RenderReport {
+            template: "weekly.tera".into(),
+            output: Some("/tmp/weekly.html".into()),
+        })
+    );
+    assert_eq!(
+        template::output_path("reports/weekly.md.tera"),
+        Some("reports/weekly.md")
+    );
+    assert_eq!(template::output_path("weekly.md"), None);
+    assert!(template::is_html("Weekly.HTML"));
+}
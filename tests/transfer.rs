@@ -0,0 +1,107 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod common;
+
+use common::results_of;
+use dbvi::commands;
+use dbvi::config::Config;
+use dbvi::results::ResultSet;
+use dbvi::state::Command;
+use dbvi::transfer::{self, Source};
+
+fn orders() -> ResultSet {
+    results_of(
+        &["id int4", "Note text"],
+        [
+            [Some("1"), Some("tab\there\nnext \\ line\r")],
+            [Some("2"), None],
+            [Some("3"), Some("")],
+        ],
+    )
+}
+
+#[test]
+fn encodes_copy_text() {
+    assert_eq!(
+        String::from_utf8(transfer::encode(&orders(), 0..3)).unwrap(),
+        "1\ttab\\there\\nnext \\\\ line\\r\n2\t\\N\n3\t\n"
+    );
+    assert_eq!(transfer::encode(&orders(), 2..10), b"3\t\n");
+}
+
+#[test]
+fn statements() {
+    let columns = &orders().columns;
+    assert_eq!(
+        transfer::copy_in_statement("archive.orders", columns),
+        "COPY archive.orders (\"id\", \"Note\") FROM STDIN"
+    );
+    assert_eq!(
+        transfer::create_table("archive.orders", columns),
+        "CREATE TABLE archive.orders (\n    \"id\" int4,\n    \"Note\" text\n)"
+    );
+}
+
+#[test]
+fn command() {
+    assert_eq!(
+        commands::parse("copy-to staging orders"),
+        Ok(Command::CopyTo {
+            target: "staging".into(),
+            table: "orders".into(),
+            source: Source::Query,
+        })
+    );
+    assert_eq!(
+        commands::parse("copy-to postgres://localhost/shop orders results"),
+        Ok(Command::CopyTo {
+            target: "postgres://localhost/shop".into(),
+            table: "orders".into(),
+            source: Source::Results,
+        })
+    );
+    assert!(commands::parse("copy-to staging").is_err());
+}
+
+#[test]
+fn refuses_read_only_targets() {
+    let config: Config = toml::from_str(
+        r#"
+        [profiles.prod]
+        env = "prod"
+
+        [profiles.reports]
+        read_only = true
+
+        [profiles.hotfix]
+        env = "prod"
+        read_only = false
+
+        [profiles.staging]
+        env = "staging"
+        "#,
+    )
+    .unwrap();
+    let refusal = |name: &str| transfer::refusal(name, &config.profiles[name]);
+    assert_eq!(
+        refusal("prod").as_deref(),
+        Some("prod is read-only, set read_only = false in its profile to copy into it")
+    );
+    assert!(refusal("reports").is_some());
+    // Let through, to be confirmed as a write to prod.
+    assert_eq!(refusal("hotfix"), None);
+    assert!(config.profiles["hotfix"].environment().unwrap().is_prod());
+    assert_eq!(refusal("staging"), None);
+}
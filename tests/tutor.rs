@@ -0,0 +1,46 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod common;
+
+use common::Harness;
+use dbvi::results::{Column, ResultSet};
+use dbvi::tutor::Tutor;
+
+#[test]
+fn lessons() {
+    let mut harness = Harness::new();
+    let mut tutor = Tutor::default();
+    let mut step = |harness: &mut Harness, keys: &str| {
+        harness.keys(keys);
+        tutor.check(&mut harness.state);
+        tutor.lesson().title
+    };
+    assert_eq!(step(&mut harness, "iselect * from cust"), "Modes");
+    assert_eq!(step(&mut harness, "omers<Esc>"), "Running queries");
+
+    // Nothing runs queries here, so the results come from the test.
+    let mut results = ResultSet::new(vec![Column::new("id", "int4"), Column::new("name", "text")]);
+    results.rows = (1..=6)
+        .map(|id| vec![Some(id.to_string()), Some(format!("customer {id}"))])
+        .collect();
+    harness.state.show_results(Some(results));
+    assert_eq!(step(&mut harness, ""), "Moving around the results");
+    assert_eq!(step(&mut harness, "<C-w>k3j"), "Moving around the results");
+    assert_eq!(step(&mut harness, "j"), "Sorting");
+    assert_eq!(step(&mut harness, "s"), "Sorting");
+    assert_eq!(step(&mut harness, "ls"), "Yanking");
+    assert_eq!(step(&mut harness, "y<C-w>jp"), "Exporting");
+    assert_eq!(harness.state.text(), "select * from customerscustomer 5");
+}
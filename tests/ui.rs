@@ -0,0 +1,154 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Snapshots of the screen after some keys. `cargo insta review` (or
+//! `INSTA_UPDATE=always`) to accept changes.
+
+mod common;
+
+use common::Harness;
+use dbvi::results::{Column, ResultSet};
+use unicode_segmentation::UnicodeSegmentation;
+
+#[test]
+fn startup() {
+    insta::assert_snapshot!(Harness::new().render());
+}
+
+#[test]
+fn insert_mode() {
+    let mut harness = Harness::new();
+    harness.keys("iselect id, name<CR>from customers");
+    insta::assert_snapshot!(harness.render());
+}
+
+#[test]
+fn command_line() {
+    let mut harness = Harness::new();
+    harness.keys(":set number");
+    insta::assert_snapshot!(harness.render());
+}
+
+#[test]
+fn results_grid() {
+    let mut harness = Harness::new();
+    let mut results = ResultSet::new(vec![Column::new("id", "int4"), Column::new("name", "text")]);
+    results.rows = vec![
+        vec![Some("1".into()), Some("Ada".into())],
+        vec![Some("2".into()), None],
+        vec![Some("10".into()), Some("Grace Hopper".into())],
+    ]
+    .into();
+    harness.state.show_results(Some(results));
+    harness.keys("<C-w>kls");
+    insta::assert_snapshot!(harness.render());
+}
+
+#[test]
+fn wide_results() {
+    let mut harness = Harness::new();
+    let columns = (0..500).map(|n| Column::new(format!("c{n}"), "int4"));
+    let mut results = ResultSet::new(columns.collect());
+    results.rows = vec![(0..500).map(|n| Some((n * 1000).to_string())).collect()].into();
+    harness.state.show_results(Some(results));
+    let header = |harness: &mut Harness| harness.render().lines().nth(2).unwrap().to_string();
+
+    harness.keys("<C-w>k$");
+    let last = header(&mut harness);
+    assert!(last.trim_end().ends_with("c499 │"), "{last}");
+    assert!(!last.contains(" c0 "), "{last}");
+    harness.keys("0");
+    assert!(header(&mut harness).starts_with("   c0 │"));
+    // Scrolled just far enough to show the cursor column whole.
+    harness.keys("20l");
+    let middle = header(&mut harness);
+    assert!(middle.starts_with("   c12 │"), "{middle}");
+    assert!(middle.contains("c20 │"), "{middle}");
+}
+
+#[test]
+fn too_small() {
+    let mut harness = Harness::new();
+    harness.resize(20, 6);
+    let screen = harness.render();
+    assert!(screen.contains("Terminal too small"), "{screen}");
+    assert!(screen.contains("20x6, needs 30x10"), "{screen}");
+    harness.resize(80, 20);
+    assert!(harness.render().contains("Mode: Normal"));
+}
+
+#[test]
+fn resize_fills_the_grid() {
+    let mut harness = Harness::new();
+    let mut results = ResultSet::new(vec![Column::new("n", "int4")]);
+    results.rows = (0..30).map(|n| [Some(n.to_string())]).collect();
+    harness.state.show_results(Some(results));
+    harness.keys("<C-w>kG");
+    let first_row = |harness: &mut Harness| harness.render().lines().nth(3).unwrap().to_string();
+    assert_ne!(first_row(&mut harness).trim(), "0 │");
+    // Tall enough for every row: the top ones come back into view.
+    harness.resize(80, 80);
+    assert_eq!(first_row(&mut harness).trim(), "0 │");
+}
+
+#[test]
+fn wide_characters() {
+    let mut harness = Harness::new();
+    let mut results = ResultSet::new(vec![Column::new("name", "text"), Column::new("n", "int4")]);
+    results.rows = vec![
+        vec![Some("東京".into()), Some("1".into())],
+        vec![Some("Zu\u{308}rich".into()), Some("2".into())],
+        vec![Some("plain".into()), Some("3".into())],
+    ]
+    .into();
+    harness.state.show_results(Some(results));
+    let screen = harness.render();
+    // Wide chars fill two cells and combining marks none, so the
+    // separators of every row line up.
+    let separators: Vec<usize> = screen
+        .lines()
+        .skip(2)
+        .take(4)
+        .map(|line| line.graphemes(true).position(|cell| cell == "│").unwrap())
+        .collect();
+    assert!(
+        separators.windows(2).all(|pair| pair[0] == pair[1]),
+        "{screen}"
+    );
+}
+
+#[test]
+fn narrow_and_short() {
+    let mut harness = Harness::with_size(44, 20);
+    let screen = harness.render();
+    // No margin, and the status line keeps only what fits.
+    assert!(screen.lines().next().unwrap().starts_with("─"), "{screen}");
+    assert!(screen.contains("Normal"), "{screen}");
+    assert!(!screen.contains("Mode: Normal"), "{screen}");
+    assert!(!screen.contains("dbvi@localhost"), "{screen}");
+
+    // Only the focused pane, until there is room for both.
+    harness.resize(80, 12);
+    let screen = harness.render();
+    assert!(screen.contains("[query]"), "{screen}");
+    assert!(!screen.contains("Results"), "{screen}");
+    harness.keys("<C-w>k");
+    let screen = harness.render();
+    assert!(screen.contains("Results"), "{screen}");
+    assert!(!screen.contains("[query]"), "{screen}");
+    harness.resize(80, 20);
+    let screen = harness.render();
+    assert!(screen.contains("Results"), "{screen}");
+    assert!(screen.contains("Mode: Normal"), "{screen}");
+}
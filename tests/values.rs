@@ -0,0 +1,199 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod common;
+
+use common::Harness;
+use dbvi::results::{Column, ResultSet};
+use dbvi::values::{Interval, in_zone, money, range, render, short_id};
+use dbvi::{State, export, options};
+
+fn interval(text: &str) -> String {
+    render(&Column::new("i", "interval"), text).unwrap_or_else(|| format!("unread: {text}"))
+}
+
+#[test]
+fn intervals() {
+    assert_eq!(interval("2 days 03:04:00"), "2 days 03:04");
+    assert_eq!(
+        interval("1 year 2 mons -1 days +00:00:01.5"),
+        "1 year 2 months -1 day 00:00:01.5"
+    );
+    assert_eq!(interval("-00:30:00"), "-00:30");
+    assert_eq!(interval("00:00:00"), "00:00");
+    assert_eq!(interval("100:00:00"), "100:00");
+    assert_eq!(
+        interval("P1Y2M3DT4H5M6.25S"),
+        "1 year 2 months 3 days 04:05:06.25"
+    );
+    assert_eq!(interval("PT0S"), "00:00");
+    assert_eq!(interval("@ 1 day ago"), "unread: @ 1 day ago");
+
+    let month = Interval::parse("1 mon").unwrap();
+    assert_eq!(month.micros(), Interval::parse("30 days").unwrap().micros());
+    assert_eq!(
+        Interval::parse("-1 days +02:00:00"),
+        Some(Interval {
+            months: 0,
+            days: -1,
+            micros: 7_200_000_000
+        })
+    );
+}
+
+#[test]
+fn amounts() {
+    assert_eq!(money("$1,234.56"), Some(1234.56));
+    assert_eq!(money("-$0.50"), Some(-0.5));
+    assert_eq!(money("1.234,56 €"), Some(1234.56));
+    assert_eq!(money("¥1,235"), Some(1235.0));
+    assert_eq!(money("$"), None);
+}
+
+#[test]
+fn ranges() {
+    assert_eq!(range("[1,10)").as_deref(), Some("[1,10)"));
+    assert_eq!(range("(,5]").as_deref(), Some("(-∞,5]"));
+    assert_eq!(range("empty").as_deref(), Some("empty"));
+    assert_eq!(
+        range(r#"["2024-01-01 00:00:00","2024-01-02 00:00:00")"#).as_deref(),
+        Some("[2024-01-01 00:00:00,2024-01-02 00:00:00)")
+    );
+    assert_eq!(range(r#"["a ""b""",)"#).as_deref(), Some(r#"[a "b",∞)"#));
+    assert_eq!(
+        render(&Column::new("m", "int4multirange"), "{[1,3), [5,)}").as_deref(),
+        Some("{[1,3),[5,∞)}")
+    );
+    assert_eq!(render(&Column::new("t", "text"), "[1,10)"), None);
+}
+
+#[test]
+fn sorted_by_amount_and_exported_as_is() {
+    let mut results = ResultSet::new(vec![
+        Column::new("took", "interval"),
+        Column::new("cost", "money"),
+    ]);
+    results.rows = vec![
+        vec![Some("1 day".into()), Some("$900.00".into())],
+        vec![Some("23:00:00".into()), Some("$1,000.00".into())],
+        vec![Some("1 mon".into()), Some("-$5.00".into())],
+    ]
+    .into();
+    results.sort(0, false);
+    let column = |results: &ResultSet, col| -> Vec<String> {
+        results
+            .rows
+            .iter()
+            .map(|row| row.get(col).unwrap_or_default().to_string())
+            .collect()
+    };
+    assert_eq!(column(&results, 0), ["23:00:00", "1 day", "1 mon"]);
+    results.sort(1, true);
+    assert_eq!(column(&results, 1), ["$1,000.00", "$900.00", "-$5.00"]);
+    assert_eq!(
+        export::to_values(&results),
+        "VALUES\n    ('23:00:00'::interval, '$1,000.00'::money),\n    \
+         ('1 day', '$900.00'),\n    ('1 mon', '-$5.00')\n"
+    );
+}
+
+#[test]
+fn short_ids() {
+    let uuid = "3f2a9c1e-8b7d-4c6a-9e5f-1a2b3c4d5e6f";
+    assert_eq!(
+        short_id(&Column::new("id", "uuid"), uuid).as_deref(),
+        Some("3f2a9c1e…")
+    );
+    let md5 = "d41d8cd98f00b204e9800998ecf8427e";
+    assert_eq!(
+        short_id(&Column::new("h", "text"), md5).as_deref(),
+        Some("d41d8cd9…")
+    );
+    assert_eq!(
+        short_id(&Column::new("h", "bytea"), &format!("\\x{md5}")).as_deref(),
+        Some("\\xd41d8cd9…")
+    );
+    assert_eq!(short_id(&Column::new("h", "text"), "d41d8cd9"), None);
+    assert_eq!(
+        short_id(&Column::new("n", "numeric"), &"1".repeat(40)),
+        None
+    );
+}
+
+#[test]
+fn shortened_in_the_grid_only() {
+    let mut harness = Harness::new();
+    let uuid = "3f2a9c1e-8b7d-4c6a-9e5f-1a2b3c4d5e6f";
+    let mut results = ResultSet::new(vec![Column::new("id", "uuid")]);
+    results.rows = vec![vec![Some(uuid.into())]].into();
+    harness.state.show_results(Some(results));
+    assert!(harness.render().contains(uuid));
+    let shorten = |state: &mut State, on| {
+        let opt = options::find("shorten-ids").unwrap();
+        opt.set(state, options::Value::Bool(on)).unwrap();
+    };
+    shorten(&mut harness.state, true);
+    let screen = harness.render();
+    assert!(screen.contains("3f2a9c1e… │"), "{screen}");
+    assert!(!screen.contains(uuid), "{screen}");
+    harness.keys("<C-w>ky<C-w>jp");
+    assert_eq!(harness.state.text(), uuid);
+    shorten(&mut harness.state, false);
+    assert!(harness.render().contains(uuid));
+}
+
+#[test]
+fn moved_to_a_time_zone() {
+    let at = Column::new("at", "timestamptz");
+    let chicago = "America/Chicago".parse().unwrap();
+    assert_eq!(
+        in_zone(&at, "2024-03-10 07:30:00.5+00", chicago).as_deref(),
+        Some("2024-03-10 01:30:00.5-06")
+    );
+    // An hour later the clocks have gone forward.
+    assert_eq!(
+        in_zone(&at, "2024-03-10 14:00:00+05:30", chicago).as_deref(),
+        Some("2024-03-10 03:30:00-05")
+    );
+    let kolkata = "Asia/Kolkata".parse().unwrap();
+    assert_eq!(
+        in_zone(&at, "2024-01-01 00:00:00-08", kolkata).as_deref(),
+        Some("2024-01-01 13:30:00+05:30")
+    );
+    assert_eq!(in_zone(&at, "infinity", chicago), None);
+    let naive = Column::new("at", "timestamp");
+    assert_eq!(in_zone(&naive, "2024-01-01 00:00:00+00", chicago), None);
+}
+
+#[test]
+fn time_zone_in_the_grid() {
+    let mut harness = Harness::new();
+    let mut results = ResultSet::new(vec![Column::new("created_at", "timestamptz")]);
+    results.rows = vec![vec![Some("2024-06-01 12:00:00+00".into())]].into();
+    harness.state.show_results(Some(results));
+    let opt = options::find("timezone").unwrap();
+    let zone = opt.kind.parse("Europe/Berlin").unwrap();
+    opt.set(&mut harness.state, zone).unwrap();
+    let screen = harness.render();
+    assert!(screen.contains("created_at (Europe/Berlin)"), "{screen}");
+    assert!(screen.contains("2024-06-01 14:00:00+02"), "{screen}");
+    assert_eq!(
+        opt.kind.parse("Mars/Olympus").unwrap_err(),
+        "Unknown time zone Mars/Olympus"
+    );
+    assert_eq!(
+        opt.sql(&opt.kind.parse("default").unwrap()).as_deref(),
+        Some("RESET TimeZone")
+    );
+}
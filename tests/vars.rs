@@ -0,0 +1,69 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod common;
+
+use std::collections::BTreeMap;
+
+use common::Harness;
+use dbvi::Command;
+use dbvi::vars::{parse_value, substitute};
+
+#[test]
+fn substitution() {
+    let vars = BTreeMap::from([
+        ("env".to_string(), "it's prod".to_string()),
+        ("tbl".to_string(), "Deploys".to_string()),
+        ("n".to_string(), "10".to_string()),
+    ]);
+    assert_eq!(
+        substitute("select * from :\"tbl\" where env = :'env' limit :n", &vars),
+        "select * from \"Deploys\" where env = 'it''s prod' limit 10"
+    );
+    // Not in literals, comments or casts, and unknown names are left for
+    // binding.
+    assert_eq!(
+        substitute("select ':n', 1::n -- :n\n, :other, :'other'", &vars),
+        "select ':n', 1::n -- :n\n, :other, :'other'"
+    );
+}
+
+#[test]
+fn values() {
+    assert_eq!(parse_value("'prod'").unwrap(), "prod");
+    assert_eq!(parse_value(" 'it''s' ").unwrap(), "it's");
+    assert_eq!(parse_value("plain words").unwrap(), "plain words");
+    assert!(parse_value("'open").is_err());
+}
+
+#[test]
+fn commands() {
+    let mut harness = Harness::new();
+    harness.keys(":setvar env 'prod'<CR>:unsetvar env<CR>:vars<CR>:setvar 1x<CR>");
+    assert_eq!(
+        harness.commands,
+        vec![
+            Command::SetVar {
+                name: "env".into(),
+                value: Some("prod".into()),
+            },
+            Command::SetVar {
+                name: "env".into(),
+                value: None,
+            },
+            Command::Vars,
+        ]
+    );
+    assert!(harness.render().contains("Usage: setvar <name> [value]"));
+}
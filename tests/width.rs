@@ -0,0 +1,73 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use dbvi::editor::{Buffer, Cursor};
+use dbvi::width;
+
+#[test]
+fn measure() {
+    assert_eq!(width::width("abc"), 3);
+    assert_eq!(width::width("日本語"), 6);
+    assert_eq!(width::width("e\u{301}te\u{301}"), 3);
+    assert_eq!(width::width("👩‍💻"), 2);
+    assert_eq!(width::columns("日本語", 2), 4);
+}
+
+#[test]
+fn truncate_and_pad() {
+    assert_eq!(width::truncate("日本語", 6), "日本語");
+    assert_eq!(width::truncate("日本語", 5), "日本…");
+    assert_eq!(width::truncate("日本語", 4), "日…");
+    assert_eq!(width::pad("日本", 6, false), "日本  ");
+    assert_eq!(width::pad("日本", 6, true), "  日本");
+}
+
+#[test]
+fn wrap_and_locate() {
+    // The third wide char does not fit after `a` and the first two.
+    assert_eq!(width::wrap("a日本語b", 5), [0, 3]);
+    assert_eq!(width::locate("a日本語b", 4, 5), (1, 2));
+    assert_eq!(width::char_at("a日本語b", 2), 1);
+    assert_eq!(width::char_at("a日本語b", 3), 2);
+    assert_eq!(width::char_at("ab", 9), 2);
+}
+
+#[test]
+fn cursor_steps_over_graphemes() {
+    let mut buffer = Buffer::new("test");
+    buffer.lines = vec!["ae\u{301}b".into()];
+    buffer.move_right(false);
+    assert_eq!(buffer.cursor.col, 1);
+    buffer.move_right(false);
+    assert_eq!(buffer.cursor.col, 3);
+    buffer.move_left();
+    assert_eq!(buffer.cursor.col, 1);
+    buffer.cursor.col = 3;
+    buffer.backspace();
+    assert_eq!(buffer.lines, ["ab"]);
+    assert_eq!(buffer.cursor, Cursor { row: 0, col: 1 });
+}
+
+#[test]
+fn cursor_on_screen() {
+    let mut buffer = Buffer::new("test");
+    buffer.lines = vec!["日本語 sql".into()];
+    let cursor = Cursor { row: 0, col: 4 };
+    assert_eq!(buffer.screen_position(cursor, None), (0, 7));
+    assert_eq!(buffer.screen_position(cursor, Some(4)), (1, 3));
+    assert_eq!(buffer.position_at(0, 3, None), Cursor { row: 0, col: 1 });
+    buffer.cursor = Cursor { row: 0, col: 1 };
+    buffer.move_visual(true, 4);
+    assert_eq!(buffer.cursor.col, 3);
+}
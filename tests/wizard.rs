@@ -0,0 +1,55 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use dbvi::keys;
+use dbvi::wizard::{Step, Wizard};
+
+fn type_keys(wizard: &mut Wizard, keys: &str) -> Step {
+    keys::parse(keys)
+        .into_iter()
+        .map(|key| wizard.key(key))
+        .last()
+        .unwrap_or(Step::Continue)
+}
+
+#[test]
+fn redshift_profile() {
+    let mut wizard = Wizard::default();
+    // `!` is no good in a profile name, and the port follows the server.
+    let step = type_keys(
+        &mut wizard,
+        "<C-u>warehouse!<Tab><Right><Tab><C-u>db.example.com<Tab><Tab>\
+         <C-u>dev<Tab><C-u>an alyst<Tab>secret<Tab><Right><Right><CR>",
+    );
+    assert_eq!(step, Step::Done);
+    assert_eq!(wizard.name(), "warehouse");
+    assert_eq!(wizard.password(), "secret");
+    assert_eq!(
+        wizard.url(),
+        "postgres://an%20alyst@db.example.com:5439/dev?sslmode=require"
+    );
+    assert_eq!(
+        wizard.config(),
+        "# Written by the dbvi setup, start with -p warehouse.\n\
+         [profiles.warehouse]\n\
+         url = \"postgres://an%20alyst@db.example.com:5439/dev?sslmode=require\"\n\
+         dialect = \"redshift\"\n"
+    );
+}
+
+#[test]
+fn escape_skips() {
+    let mut wizard = Wizard::default();
+    assert_eq!(type_keys(&mut wizard, "<Tab><Esc>"), Step::Cancel);
+}
@@ -0,0 +1,60 @@
+// Copyright 2025 cowboy
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::Path;
+
+use dbvi::workspace::{self, Workspace};
+
+#[test]
+fn parse_and_queries_dir() {
+    let path = Path::new("/work/shop/.dbvi.toml");
+    let workspace = Workspace::parse(
+        path,
+        "profile = \"app-dev\"\nqueries = \"sql/queries\"\n\n[options]\nnumber = true\n",
+    )
+    .unwrap();
+    assert_eq!(workspace.profile.as_deref(), Some("app-dev"));
+    assert_eq!(
+        workspace.queries_dir().unwrap(),
+        Path::new("/work/shop/sql/queries")
+    );
+    assert_eq!(workspace.options["number"], toml::Value::Boolean(true));
+
+    let empty = Workspace::parse(path, "").unwrap();
+    assert_eq!(empty.queries_dir(), None);
+    let err = Workspace::parse(path, "profiles = \"typo\"").unwrap_err();
+    assert!(err.starts_with("/work/shop/.dbvi.toml: "), "{err}");
+}
+
+#[test]
+fn trusted_until_changed() {
+    let shop = Path::new("/work/shop/.dbvi.toml");
+    let blog = Path::new("/work/blog/.dbvi.toml");
+    let before = "profile = \"app-dev\"\n";
+    let after = "profile = \"prod\"\n";
+    assert!(!workspace::is_trusted("", shop, before));
+
+    let trusted = workspace::trust("", shop, before);
+    let trusted = workspace::trust(&trusted, blog, before);
+    assert!(workspace::is_trusted(&trusted, shop, before));
+    assert!(workspace::is_trusted(&trusted, blog, before));
+    assert!(!workspace::is_trusted(&trusted, shop, after));
+
+    // Trusting the new contents forgets the old ones.
+    let trusted = workspace::trust(&trusted, shop, after);
+    assert!(workspace::is_trusted(&trusted, shop, after));
+    assert!(!workspace::is_trusted(&trusted, shop, before));
+    assert!(workspace::is_trusted(&trusted, blog, before));
+    assert_eq!(trusted.lines().count(), 2);
+}